@@ -158,6 +158,7 @@ export class SolanaIndexer {
                 totalBidVolume: market.totalBidVolume.toNumber(),
                 totalAskVolume: market.totalAskVolume.toNumber(),
                 isPaused: market.isPaused,
+                feeRecipient: market.feeRecipient.toBase58(),
             };
             this.markets.set(pubkey, decoded);
             return;
@@ -180,6 +181,7 @@ export class SolanaIndexer {
                 timestamp: order.timestamp.toNumber(),
                 isLocked: order.isLocked,
                 expiresAt: order.expiresAt.toNumber(),
+                updateCount: order.updateCount.toNumber(),
             };
             this.orders.set(pubkey, decoded);
             this.rebuildOrderBook(decoded.market);
@@ -221,26 +223,58 @@ export class SolanaIndexer {
         try {
             const events = [...this.eventParser.parseLogs(logs)];
             for (const event of events) {
-                if (event.name !== 'TradeExecutedEvent') continue;
-                const d = event.data as any;
-                const trade: Trade = {
-                    bidOrderId: d.bidOrderId.toNumber(),
-                    askOrderId: d.askOrderId.toNumber(),
-                    market: d.market.toBase58(),
-                    buyer: d.buyer.toBase58(),
-                    seller: d.seller.toBase58(),
-                    fillPrice: d.fillPrice.toNumber(),
-                    fillQuantity: d.fillQuantity.toNumber(),
-                    feeAmount: d.feeAmount.toNumber(),
-                    timestamp: d.timestamp.toNumber(),
-                    signature,
-                };
-                this.trades.push(trade);
-                if (this.trades.length > 1000) this.trades.shift();
-                this.tradeListeners.forEach(cb => cb(trade));
+                for (const trade of decodeTrades(event, signature)) {
+                    this.trades.push(trade);
+                    if (this.trades.length > 1000) this.trades.shift();
+                    this.tradeListeners.forEach(cb => cb(trade));
+                }
             }
         } catch {
             // Ignore non-SolaMatch logs
         }
     }
 }
+
+// ── Trade decoding ────────────────────────────────────────────────────────────
+// Fills arrive either one per TradeExecutedEvent or coalesced into a
+// TradeBatchEvent (one taker against up to 8 makers); both decode to Trades.
+export function decodeTrades(event: { name: string; data: any }, signature: string): Trade[] {
+    const d = event.data;
+    if (event.name === 'TradeExecutedEvent') {
+        return [{
+            tradeSeq: d.tradeSeq.toNumber(),
+            bidOrderId: d.bidOrderId.toNumber(),
+            askOrderId: d.askOrderId.toNumber(),
+            market: d.market.toBase58(),
+            buyer: d.buyer.toBase58(),
+            seller: d.seller.toBase58(),
+            fillPrice: d.fillPrice.toNumber(),
+            fillQuantity: d.fillQuantity.toNumber(),
+            feeAmount: d.feeAmount.toNumber(),
+            dustAmount: d.dustAmount.toNumber(),
+            timestamp: d.timestamp.toNumber(),
+            signature,
+        }];
+    }
+    if (event.name === 'TradeBatchEvent') {
+        const takerIsBid = 'buy' in d.takerSide;
+        const takerId = d.takerOrderId.toNumber();
+        const taker = d.taker.toBase58();
+        const first = d.firstTradeSeq.toNumber();
+        return d.fills.slice(0, d.fillCount).map((f: any, i: number): Trade => ({
+            tradeSeq: first + i,
+            bidOrderId: takerIsBid ? takerId : f.makerOrderId.toNumber(),
+            askOrderId: takerIsBid ? f.makerOrderId.toNumber() : takerId,
+            market: d.market.toBase58(),
+            buyer: takerIsBid ? taker : null,
+            seller: takerIsBid ? null : taker,
+            fillPrice: f.price.toNumber(),
+            fillQuantity: f.quantity.toNumber(),
+            feeAmount: null,
+            dustAmount: null,
+            timestamp: d.timestamp.toNumber(),
+            signature,
+        }));
+    }
+    return [];
+}
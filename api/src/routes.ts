@@ -23,6 +23,7 @@ const OrderSchema = {
         timestamp: { type: 'number', description: 'Unix timestamp (seconds)' },
         isLocked: { type: 'boolean' },
         expiresAt: { type: 'number', description: 'Expiry unix timestamp (0 = none)' },
+        updateCount: { type: 'number', description: 'Bumped on every fill/cancel; pass as expected_update_count' },
     },
 };
 
@@ -36,6 +37,7 @@ const MarketSchema = {
         totalBidVolume: { type: 'number' },
         totalAskVolume: { type: 'number' },
         isPaused: { type: 'boolean', description: 'True if emergency pause is active' },
+        feeRecipient: { type: 'string', description: 'Fee destination (defaults to the market fee vault)' },
     },
 };
 
@@ -52,14 +54,16 @@ const OrderBookSchema = {
 const TradeSchema = {
     type: 'object',
     properties: {
+        tradeSeq: { type: 'number', description: 'Per-market fill sequence number' },
         bidOrderId: { type: 'number' },
         askOrderId: { type: 'number' },
         market: { type: 'string' },
-        buyer: { type: 'string' },
-        seller: { type: 'string' },
+        buyer: { type: ['string', 'null'], description: 'null for a maker in a batched fill' },
+        seller: { type: ['string', 'null'], description: 'null for a maker in a batched fill' },
         fillPrice: { type: 'number' },
         fillQuantity: { type: 'number' },
-        feeAmount: { type: 'number', description: 'Protocol fee deducted from trade (lamports)' },
+        feeAmount: { type: ['number', 'null'], description: 'Protocol fee deducted from trade (lamports); null for batched fills' },
+        dustAmount: { type: ['number', 'null'], description: 'Rounding dust kept back from the seller (lamports); null for batched fills' },
         timestamp: { type: 'number' },
         signature: { type: 'string', description: 'Solana transaction signature' },
     },
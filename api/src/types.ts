@@ -19,6 +19,7 @@ export interface DecodedOrder {
     timestamp: number;      // unix seconds
     isLocked: boolean;
     expiresAt: number;      // 0 = no expiry
+    updateCount: number;    // version for cancel's expected_update_count
 }
 
 export interface DecodedMarket {
@@ -29,13 +30,14 @@ export interface DecodedMarket {
     totalBidVolume: number;
     totalAskVolume: number;
     isPaused: boolean;
+    feeRecipient: string;
 }
 
 export interface DecodedFeeConfig {
     market: string;
-    treasury: string;
     feeBps: number;
     accumulatedFees: number;
+    protocolAccumulatedFees: number; // protocol share of accumulatedFees
 }
 
 export interface OrderBook {
@@ -45,15 +47,19 @@ export interface OrderBook {
     timestamp: number;      // last update unix ms
 }
 
+// Fills decoded from a TradeBatchEvent carry only the taker's owner and no
+// per-fill fee breakdown; those fields are null.
 export interface Trade {
+    tradeSeq: number;       // Market.trade_seq of this fill
     bidOrderId: number;
     askOrderId: number;
     market: string;
-    buyer: string;
-    seller: string;
+    buyer: string | null;
+    seller: string | null;
     fillPrice: number;
     fillQuantity: number;
-    feeAmount: number;
+    feeAmount: number | null;
+    dustAmount: number | null; // rounding dust kept back from the seller
     timestamp: number;
     signature: string;
 }
@@ -0,0 +1,73 @@
+/**
+ * Order Matching Engine — Cancel Preview
+ *
+ * preview_cancel returns, via return data, exactly what cancelling an
+ * order would pay out: the escrow cancel_order releases now and the rent
+ * close_order reclaims afterwards. previewCancel runs it as a view;
+ * decodeCancelPreview reads the raw return data (e.g. from a simulated
+ * transaction's `returnData`) for callers that build their own.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+
+export interface CancelPreview {
+    canCancel: boolean;
+    /** Program error cancel_order would fail with (0 = none). */
+    errorCode: number;
+    remainingQuantity: number;
+    /** Lamports cancel_order releases (BUY escrow still held). */
+    escrowRefund: number;
+    /** The refund goes into the owner's TradingBalance, not a wallet. */
+    refundToBalance: boolean;
+    /** Funder or owner; receives the refund unless refundToBalance. */
+    refundWallet: PublicKey;
+    /** Lamports close_order reclaims afterwards from the order account. */
+    rentRefund: number;
+    /** Subsidized rent returns to the market's RentSubsidyVault, not the owner. */
+    rentToSubsidyVault: boolean;
+    /** escrowRefund is quote tokens paid from the market's quote vault. */
+    refundInQuoteTokens: boolean;
+    /** Base tokens an ask gets back from the market's base vault. */
+    baseRefund: number;
+    /** Lamports close_order reclaims from the escrow vault, always to the owner. */
+    vaultRentRefund: number;
+}
+
+function fromRaw(raw: any): CancelPreview {
+    return {
+        canCancel: raw.canCancel,
+        errorCode: raw.errorCode,
+        remainingQuantity: raw.remainingQuantity.toNumber(),
+        escrowRefund: raw.escrowRefund.toNumber(),
+        refundToBalance: raw.refundToBalance,
+        refundWallet: raw.refundWallet,
+        rentRefund: raw.rentRefund.toNumber(),
+        rentToSubsidyVault: raw.rentToSubsidyVault,
+        refundInQuoteTokens: raw.refundInQuoteTokens,
+        baseRefund: raw.baseRefund.toNumber(),
+        vaultRentRefund: raw.vaultRentRefund.toNumber(),
+    };
+}
+
+export async function previewCancel(
+    program: anchor.Program,
+    market: PublicKey,
+    orderId: number
+): Promise<CancelPreview> {
+    const [order] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), market.toBuffer(), new anchor.BN(orderId).toArrayLike(Buffer, "le", 8)],
+        program.programId
+    );
+    const raw = await program.methods
+        .previewCancel(new anchor.BN(orderId))
+        .accounts({ market, order })
+        .view();
+    return fromRaw(raw);
+}
+
+/** Decode preview_cancel's return data (base64 as RPCs report it, or raw bytes). */
+export function decodeCancelPreview(program: anchor.Program, data: string | Uint8Array): CancelPreview {
+    const bytes = typeof data === "string" ? Buffer.from(data, "base64") : Buffer.from(data);
+    return fromRaw(program.coder.types.decode("CancelPreview", bytes));
+}
@@ -14,6 +14,8 @@
  *   get-market        Show market info
  *   get-order         Show a specific order
  *   list-orders       List all orders for a market
+ *   matcher-stats     Show a matcher's activity on a market
+ *   verify-hash       Replay a market's events and check its state hash
  */
 
 import * as anchor from "@coral-xyz/anchor";
@@ -28,6 +30,7 @@ import { Command } from "commander";
 import * as fs from "fs";
 import * as os from "os";
 import * as path from "path";
+import { fetchMarketEvents, verifyStateHashChain } from "./stateHash";
 
 // ── IDL (paste your generated IDL here after `anchor build`) ─────────────────
 // For demo purposes this is a minimal inline IDL matching our program.
@@ -81,6 +84,14 @@ function orderPda(market: PublicKey, orderId: number, programId: PublicKey): Pub
     return pda;
 }
 
+function matcherStatsPda(market: PublicKey, matcher: PublicKey, programId: PublicKey): PublicKey {
+    const [pda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("matcher"), market.toBuffer(), matcher.toBuffer()],
+        programId
+    );
+    return pda;
+}
+
 function explorerUrl(sig: string): string {
     return `https://explorer.solana.com/tx/${sig}?cluster=devnet`;
 }
@@ -112,6 +123,12 @@ cli
     .command("init-market")
     .description("Initialize a new order book market")
     .requiredOption("-n, --name <name>", "Market name (e.g. SOL/MOCK)")
+    .option("--taker-only-secs <n>", "Taker-only window after open/resume, in seconds", "0")
+    .option("--tick-size <n>", "Prices must be a multiple of this", "1")
+    .option("--lot-size <n>", "Quantities must be a multiple of this", "1")
+    .option("--min-qty <n>", "Smallest order quantity", "1")
+    .option("--matcher-fee-bps <bps>", "Matcher's cut of each fill's seller payment", "0")
+    .option("--permissioned", "Only traders granted a seat (add_trader) may place orders", false)
     .action(async (opts) => {
         const parent = cli.opts();
         const wallet = loadWallet(parent.keypair);
@@ -125,7 +142,15 @@ cli
         console.log(`  Market PDA : ${mktPda.toBase58()}`);
 
         const tx = await program.methods
-            .initializeMarket(opts.name)
+            .initializeMarket(
+                opts.name,
+                new anchor.BN(parseInt(opts.takerOnlySecs)),
+                new anchor.BN(opts.tickSize),
+                new anchor.BN(opts.lotSize),
+                new anchor.BN(opts.minQty),
+                parseInt(opts.matcherFeeBps),
+                Boolean(opts.permissioned)
+            )
             .accounts({
                 authority: wallet.publicKey,
                 market: mktPda,
@@ -260,7 +285,7 @@ cli
         }
 
         const tx = await program.methods
-            .cancelOrder(new anchor.BN(ordId))
+            .cancelOrder(new anchor.BN(ordId), order.updateCount) // fail if it filled since the estimate
             .accounts({
                 owner: wallet.publicKey,
                 market: mktPda,
@@ -294,6 +319,8 @@ cli
         console.log(`  Next Order ID : ${market.nextOrderId.toString()}`);
         console.log(`  Bid Volume    : ${market.totalBidVolume.toString()} units`);
         console.log(`  Ask Volume    : ${market.totalAskVolume.toString()} units`);
+        console.log(`  Event Seq     : ${market.eventSeq.toString()}`);
+        console.log(`  State Hash    : ${Buffer.from(market.stateHash).toString("hex")}`);
     });
 
 // ── get-order ─────────────────────────────────────────────────────────────────
@@ -336,7 +363,7 @@ cli
     .command("list-orders")
     .description("List all orders for a market")
     .requiredOption("-m, --market <pda>", "Market PDA address")
-    .option("--status <s>", "Filter by status: open|filled|cancelled|partiallyFilled")
+    .option("--status <s>", "Filter by status: open|filled|cancelled|partiallyFilled|pendingTrigger")
     .action(async (opts) => {
         const parent = cli.opts();
         const wallet = loadWallet(parent.keypair);
@@ -374,4 +401,65 @@ cli
         console.log("─".repeat(70));
     });
 
+// ── matcher-stats ─────────────────────────────────────────────────────────────
+cli
+    .command("matcher-stats")
+    .description("Show a matcher's activity on a market")
+    .requiredOption("-m, --market <pda>", "Market PDA address")
+    .option("--matcher <pubkey>", "Matcher (defaults to the wallet)")
+    .action(async (opts) => {
+        const parent = cli.opts();
+        const wallet = loadWallet(parent.keypair);
+        const provider = getProvider(wallet, parent.url);
+        const idl = loadIdl();
+        const program = getProgram(provider, idl);
+
+        const mktPda = new PublicKey(opts.market);
+        const matcher = opts.matcher ? new PublicKey(opts.matcher) : wallet.publicKey;
+        const statsPda = matcherStatsPda(mktPda, matcher, PROGRAM_ID);
+        const stats = await program.account.matcherStats.fetchNullable(statsPda);
+        if (!stats) {
+            console.error(`  ❌ No stats for ${matcher.toBase58()} (run create_matcher_stats first)`);
+            process.exit(1);
+        }
+
+        console.log(`\n🤖 Matcher ${matcher.toBase58()}`);
+        console.log("─".repeat(40));
+        console.log(`  Matches      : ${stats.matchesExecuted.toString()}`);
+        console.log(`  Volume       : ${stats.volumeMatched.toString()} units`);
+        console.log(`  Notional     : ${formatLamports(stats.notionalMatched.toNumber())}`);
+        console.log(`  Fees         : ${formatLamports(stats.feesGenerated.toNumber())}`);
+        console.log(`  Rewards      : ${formatLamports(stats.rewardsEarned.toNumber())}`);
+        console.log(`  Last active  : slot ${stats.lastActiveSlot.toString()}`);
+    });
+
+// ── verify-hash ───────────────────────────────────────────────────────────────
+cli
+    .command("verify-hash")
+    .description("Replay a market's events and check them against its state hash")
+    .requiredOption("-m, --market <pda>", "Market PDA address")
+    .action(async (opts) => {
+        const parent = cli.opts();
+        const wallet = loadWallet(parent.keypair);
+        const provider = getProvider(wallet, parent.url);
+        const idl = loadIdl();
+        const program = getProgram(provider, idl);
+
+        const mktPda = new PublicKey(opts.market);
+        const market = await program.account.market.fetch(mktPda);
+        const events = await fetchMarketEvents(provider.connection, program, mktPda);
+
+        console.log(`\n🔗 Replaying ${events.length} events for market ${opts.market.slice(0, 8)}...`);
+        const result = verifyStateHashChain(program.coder as anchor.BorshCoder, events, market as any);
+
+        console.log(`  Market    : seq=${market.eventSeq.toString()} hash=${Buffer.from(market.stateHash).toString("hex")}`);
+        console.log(`  Replayed  : seq=${result.eventSeq} hash=${result.stateHash.toString("hex")}`);
+        if (!result.ok) {
+            const at = result.failedAt !== undefined ? ` at event #${result.failedAt}` : "";
+            console.error(`  ❌ Verification failed${at}: ${result.reason}`);
+            process.exit(1);
+        }
+        console.log("  ✅ State hash chain verified");
+    });
+
 cli.parse(process.argv);
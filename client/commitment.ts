@@ -0,0 +1,41 @@
+/**
+ * Order Matching Engine — Commit–Reveal Helper
+ *
+ * commit_order stores
+ *
+ *   hash = sha256(side_u8 || price_le || quantity_le || salt || owner)
+ *
+ * (side 0 = buy, 1 = sell); reveal_order recomputes it from the preimage.
+ * Keep the salt secret and random until the reveal.
+ */
+
+import { PublicKey } from "@solana/web3.js";
+import { createHash, randomBytes } from "crypto";
+
+export type CommitSide = "buy" | "sell";
+
+function u64Le(n: number | bigint): Buffer {
+    const buf = Buffer.alloc(8);
+    buf.writeBigUInt64LE(BigInt(n));
+    return buf;
+}
+
+export function newSalt(): Buffer {
+    return randomBytes(32);
+}
+
+export function commitmentHash(
+    side: CommitSide,
+    price: number | bigint,
+    quantity: number | bigint,
+    salt: Uint8Array,
+    owner: PublicKey
+): Buffer {
+    return createHash("sha256")
+        .update(Buffer.from([side === "buy" ? 0 : 1]))
+        .update(u64Le(price))
+        .update(u64Le(quantity))
+        .update(Buffer.from(salt))
+        .update(owner.toBuffer())
+        .digest();
+}
@@ -0,0 +1,47 @@
+/**
+ * Order Matching Engine — Self-CPI Events
+ *
+ * Order placement, matching, cancels and closes emit their events twice:
+ * to the program log (`emit!`) and as a self-CPI (`emit_cpi!`) whose
+ * instruction data is the event. RPC nodes truncate long logs, dropping
+ * log events, but inner instructions are always kept — so an indexer
+ * that reads events from here doesn't lose fills in packed crank
+ * transactions.
+ *
+ * Inner instruction data is EVENT_IX_TAG (8 bytes, little-endian) followed
+ * by the event exactly as it would appear in the log.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import { PublicKey, VersionedTransactionResponse } from "@solana/web3.js";
+
+/** anchor_lang::event::EVENT_IX_TAG_LE */
+export const EVENT_IX_TAG = Buffer.from("e445a52e51cb9a1d", "hex");
+
+export interface CpiEvent {
+    name: string;
+    data: any;
+}
+
+/** Decode the self-CPI events of `programId` in a fetched transaction, in order. */
+export function parseCpiEvents(
+    coder: anchor.BorshCoder,
+    programId: PublicKey,
+    tx: VersionedTransactionResponse | null
+): CpiEvent[] {
+    if (!tx?.meta?.innerInstructions) return [];
+    const keys = tx.transaction.message.getAccountKeys({
+        accountKeysFromLookups: tx.meta.loadedAddresses,
+    });
+    const events: CpiEvent[] = [];
+    for (const { instructions } of tx.meta.innerInstructions) {
+        for (const ix of instructions) {
+            if (!keys.get(ix.programIdIndex)?.equals(programId)) continue;
+            const data = Buffer.from(anchor.utils.bytes.bs58.decode(ix.data));
+            if (!data.subarray(0, 8).equals(EVENT_IX_TAG)) continue;
+            const event = coder.events.decode(data.subarray(8).toString("base64"));
+            if (event) events.push(event);
+        }
+    }
+    return events;
+}
@@ -0,0 +1,254 @@
+/**
+ * Order Matching Engine — Cancel-on-Disconnect Heartbeat
+ *
+ * A dead-man's switch for market makers. The quoting strategy calls
+ * ping() on every cycle; if no ping arrives for `timeoutMs`, the service
+ * cancels every active order it tracks on the configured markets.
+ *
+ * The cancels are batched cancel_order instructions, `ordersPerTx` to a
+ * transaction, rather than cancel_all_orders, which skips orders it can't
+ * refund with the accounts given and would fail silently here. They are
+ * built ahead of time and rebuilt on refresh() (new orders, fresh
+ * blockhash) so firing the switch costs as few RPC round trips as
+ * possible. Every transaction is retried up to `maxRetries` times, rebuilt
+ * from the orders still active, and each step is reported through onAlert.
+ *
+ * Signing goes through `signTransaction`, so the owner key can live in a
+ * remote signer (HSM, signing service) rather than this process. The
+ * program has no off-chain signed-cancel message yet; until it does, the
+ * remote signer signs the prepared transactions themselves.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram, Transaction } from "@solana/web3.js";
+
+export type SignTransaction = (tx: Transaction) => Promise<Transaction>;
+
+export type HeartbeatAlert =
+    | { kind: "missed"; silentMs: number; orders: number }
+    | { kind: "submitted"; signature: string; orders: PublicKey[] }
+    | { kind: "retry"; attempt: number; orders: PublicKey[]; error: string }
+    | { kind: "failed"; orders: PublicKey[]; error: string };
+
+export interface HeartbeatOptions {
+    /** The order owner; must sign the cancels. */
+    owner: PublicKey;
+    /** Only orders on these markets are tracked and cancelled. */
+    markets: PublicKey[];
+    /** No ping for this long fires the switch. */
+    timeoutMs: number;
+    /** How often the watchdog looks (default timeoutMs / 4). */
+    checkIntervalMs?: number;
+    /** How often prepared transactions are rebuilt (default 30s; blockhashes last ~60s). */
+    refreshIntervalMs?: number;
+    /** Send attempts per transaction after the first (default 3). */
+    maxRetries?: number;
+    retryDelayMs?: number;
+    /** cancel_order instructions per transaction (default 5). */
+    ordersPerTx?: number;
+    /** Defaults to the program provider's wallet. */
+    signTransaction?: SignTransaction;
+    onAlert?: (alert: HeartbeatAlert) => void;
+}
+
+const ORDER_OWNER_OFFSET = 8; // after the account discriminator
+const ORDER_MARKET_OFFSET = ORDER_OWNER_OFFSET + 32;
+
+interface PreparedCancel {
+    orders: PublicKey[];
+    tx: Transaction;
+}
+
+export class HeartbeatService {
+    private readonly tracked = new Map<string, PublicKey>();
+    private prepared: PreparedCancel[] = [];
+    private lastPing = Date.now();
+    private watchdog: ReturnType<typeof setInterval> | null = null;
+    private refresher: ReturnType<typeof setInterval> | null = null;
+    private firing: Promise<string[]> | null = null;
+
+    /** Set once the switch has fired; ping() re-arms it. */
+    tripped = false;
+
+    constructor(private readonly program: anchor.Program, private readonly opts: HeartbeatOptions) {}
+
+    /** Start watching. Prepares the cancels first. */
+    async start(): Promise<void> {
+        await this.refresh();
+        this.lastPing = Date.now();
+        const check = this.opts.checkIntervalMs ?? Math.max(1, Math.floor(this.opts.timeoutMs / 4));
+        this.watchdog = setInterval(() => void this.check(), check);
+        this.refresher = setInterval(() => void this.refresh().catch(() => {}), this.opts.refreshIntervalMs ?? 30_000);
+    }
+
+    stop(): void {
+        if (this.watchdog) clearInterval(this.watchdog);
+        if (this.refresher) clearInterval(this.refresher);
+        this.watchdog = this.refresher = null;
+    }
+
+    /** The strategy is alive. Re-arms a tripped switch. */
+    ping(): void {
+        this.lastPing = Date.now();
+        this.tripped = false;
+    }
+
+    /** Track an order (its PDA) for cancellation. Call refresh() to prepare it. */
+    track(order: PublicKey): void {
+        this.tracked.set(order.toBase58(), order);
+    }
+
+    untrack(order: PublicKey): void {
+        this.tracked.delete(order.toBase58());
+    }
+
+    trackedOrders(): PublicKey[] {
+        return [...this.tracked.values()];
+    }
+
+    /** Track every active order the owner has on the configured markets. */
+    async discover(): Promise<PublicKey[]> {
+        const { connection } = this.program.provider;
+        const found: PublicKey[] = [];
+        for (const market of this.opts.markets) {
+            const accounts = await connection.getProgramAccounts(this.program.programId, {
+                dataSlice: { offset: 0, length: 0 },
+                filters: [
+                    { memcmp: this.program.coder.accounts.memcmp("Order") },
+                    { memcmp: { offset: ORDER_OWNER_OFFSET, bytes: this.opts.owner.toBase58() } },
+                    { memcmp: { offset: ORDER_MARKET_OFFSET, bytes: market.toBase58() } },
+                ],
+            });
+            for (const { pubkey } of accounts) {
+                this.track(pubkey);
+                found.push(pubkey);
+            }
+        }
+        await this.refresh();
+        return found;
+    }
+
+    /**
+     * Rebuild the prepared cancels from the tracked orders that are still
+     * active, under a fresh blockhash. Inactive orders stop being tracked.
+     */
+    async refresh(): Promise<void> {
+        this.prepared = await this.build(this.trackedOrders());
+    }
+
+    /** Fire the switch now: send every prepared cancel. Returns the signatures. */
+    cancelAll(): Promise<string[]> {
+        if (!this.firing) {
+            this.tripped = true;
+            this.firing = this.sendAll().finally(() => (this.firing = null));
+        }
+        return this.firing;
+    }
+
+    private async check(): Promise<void> {
+        const silentMs = Date.now() - this.lastPing;
+        if (this.tripped || silentMs < this.opts.timeoutMs) return;
+        this.alert({ kind: "missed", silentMs, orders: this.tracked.size });
+        await this.cancelAll();
+    }
+
+    private async sendAll(): Promise<string[]> {
+        const signatures: string[] = [];
+        for (const batch of this.prepared) {
+            const signature = await this.sendWithRetry(batch);
+            if (signature) signatures.push(signature);
+        }
+        this.prepared = [];
+        return signatures;
+    }
+
+    private async sendWithRetry(batch: PreparedCancel): Promise<string | null> {
+        const { connection } = this.program.provider;
+        const maxRetries = this.opts.maxRetries ?? 3;
+        let current = batch;
+        for (let attempt = 0; ; attempt++) {
+            try {
+                const signed = await this.sign(current.tx);
+                const signature = await connection.sendRawTransaction(signed.serialize());
+                const { value } = await connection.confirmTransaction(signature, "confirmed");
+                if (value.err) throw new Error(`cancel failed: ${JSON.stringify(value.err)}`);
+                for (const order of current.orders) this.untrack(order);
+                this.alert({ kind: "submitted", signature, orders: current.orders });
+                return signature;
+            } catch (err: any) {
+                const error = String(err?.message ?? err);
+                if (attempt >= maxRetries) {
+                    this.alert({ kind: "failed", orders: current.orders, error });
+                    return null;
+                }
+                this.alert({ kind: "retry", attempt: attempt + 1, orders: current.orders, error });
+                await new Promise((resolve) => setTimeout(resolve, this.opts.retryDelayMs ?? 500));
+                // Rebuild: fresh blockhash, and drop orders that filled or
+                // were cancelled in the meantime.
+                let rebuilt: PreparedCancel[];
+                try {
+                    rebuilt = await this.build(current.orders);
+                } catch {
+                    continue; // RPC trouble — resend the previous build
+                }
+                if (rebuilt.length === 0) return null; // nothing left to cancel
+                current = rebuilt[0];
+            }
+        }
+    }
+
+    private sign(tx: Transaction): Promise<Transaction> {
+        const sign = this.opts.signTransaction ?? ((t: Transaction) => this.program.provider.wallet!.signTransaction(t));
+        return sign(tx);
+    }
+
+    private async build(orders: PublicKey[]): Promise<PreparedCancel[]> {
+        const infos = await this.program.account.order.fetchMultiple(orders);
+        const markets = new Set(this.opts.markets.map((m) => m.toBase58()));
+        const active: [PublicKey, any][] = [];
+        orders.forEach((address, i) => {
+            const order: any = infos[i];
+            const live = order && ("open" in order.status || "partiallyFilled" in order.status);
+            if (live && order.owner.equals(this.opts.owner) && markets.has(order.market.toBase58())) {
+                active.push([address, order]);
+            } else {
+                this.untrack(address);
+            }
+        });
+
+        const { blockhash } = await this.program.provider.connection.getLatestBlockhash();
+        const perTx = this.opts.ordersPerTx ?? 5;
+        const prepared: PreparedCancel[] = [];
+        for (let i = 0; i < active.length; i += perTx) {
+            const batch = active.slice(i, i + perTx);
+            const tx = new Transaction({ feePayer: this.opts.owner, recentBlockhash: blockhash });
+            for (const [address, order] of batch) tx.add(await this.cancelIx(address, order));
+            prepared.push({ orders: batch.map(([address]) => address), tx });
+        }
+        return prepared;
+    }
+
+    private cancelIx(address: PublicKey, order: any) {
+        const pda = (seed: string) =>
+            PublicKey.findProgramAddressSync(
+                [Buffer.from(seed), order.market.toBuffer(), order.owner.toBuffer()],
+                this.program.programId
+            )[0];
+        return this.program.methods
+            .cancelOrder(order.orderId, new anchor.BN(0))
+            .accounts({
+                owner: order.owner,
+                market: order.market,
+                order: address,
+                tradingBalance: order.fundedFromBalance ? pda("balance") : null,
+                userStats: order.countedInStats ? pda("user_stats") : null,
+                funder: order.funder.equals(order.owner) ? null : order.funder,
+                systemProgram: SystemProgram.programId,
+            })
+            .instruction();
+    }
+
+    private alert(alert: HeartbeatAlert): void {
+        this.opts.onAlert?.(alert);
+    }
+}
@@ -0,0 +1,147 @@
+/**
+ * Order Matching Engine — Market Discovery
+ *
+ * listMarkets pages through every market the program knows about,
+ * loadAllMarkets hydrates Market accounts in batched getMultipleAccounts
+ * calls, and aggregateMarkets rolls them up for a landing page.
+ *
+ * Discovery walks the on-chain MarketRegistry: listings are numbered
+ * 0..count in creation order, so a cursor is a listing index and every
+ * page is one getMultipleAccounts call — no getProgramAccounts scan.
+ * Delisted markets are skipped, so a page may hold fewer than pageSize
+ * entries. Markets created without the registry aren't listed.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+
+/** getMultipleAccounts accepts at most 100 keys per call. */
+export const MAX_ACCOUNTS_PER_CALL = 100;
+
+export interface MarketEntry {
+    address: PublicKey;
+    registryIndex: number;
+    name: string;
+    creator: PublicKey;
+}
+
+export interface MarketPage {
+    entries: MarketEntry[];
+    /** Pass back as `cursor` for the next page; null on the last page. */
+    nextCursor: number | null;
+}
+
+export interface LoadedMarket {
+    address: PublicKey;
+    /** Decoded Market, or null when the account was closed (not yet pruned). */
+    account: any | null;
+}
+
+export interface MarketSummary {
+    address: PublicKey;
+    name: string;
+    openInterest: number;   // open bid + ask units
+    fills: number;          // lifetime fills (Market.trade_seq)
+    lastTradePrice: number;
+}
+
+export interface MarketAggregate {
+    totalMarkets: number;   // live markets
+    closedMarkets: number;  // listed but no longer on-chain
+    totalOpenInterest: number;
+    topMarkets: MarketSummary[];
+}
+
+export function registryPda(programId: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync([Buffer.from("registry")], programId)[0];
+}
+
+export function listingPda(programId: PublicKey, registryIndex: number): PublicKey {
+    const index = Buffer.alloc(8);
+    index.writeBigUInt64LE(BigInt(registryIndex));
+    return PublicKey.findProgramAddressSync([Buffer.from("listing"), index], programId)[0];
+}
+
+export async function listMarkets(
+    program: anchor.Program,
+    cursor = 0,
+    pageSize = MAX_ACCOUNTS_PER_CALL
+): Promise<MarketPage> {
+    const connection = program.provider.connection;
+    const registry = await connection.getAccountInfo(registryPda(program.programId));
+    if (!registry) return { entries: [], nextCursor: null };
+    const count = program.coder.accounts.decode("MarketRegistry", registry.data).count.toNumber();
+
+    const end = Math.min(cursor + Math.min(pageSize, MAX_ACCOUNTS_PER_CALL), count);
+    const indices = Array.from({ length: Math.max(end - cursor, 0) }, (_, i) => cursor + i);
+    const infos = indices.length
+        ? await connection.getMultipleAccountsInfo(indices.map((i) => listingPda(program.programId, i)))
+        : [];
+    const entries: MarketEntry[] = [];
+    infos.forEach((info, i) => {
+        if (!info) return;
+        const listing = program.coder.accounts.decode("MarketListing", info.data);
+        if (!listing.active) return;
+        entries.push({
+            address: listing.market,
+            registryIndex: indices[i],
+            name: listing.marketName,
+            creator: listing.creator,
+        });
+    });
+    return { entries, nextCursor: end < count ? end : null };
+}
+
+export async function loadAllMarkets(program: anchor.Program, addresses: PublicKey[]): Promise<LoadedMarket[]> {
+    const loaded: LoadedMarket[] = [];
+    for (let i = 0; i < addresses.length; i += MAX_ACCOUNTS_PER_CALL) {
+        const batch = addresses.slice(i, i + MAX_ACCOUNTS_PER_CALL);
+        const infos = await program.provider.connection.getMultipleAccountsInfo(batch);
+        batch.forEach((address, j) => {
+            const info = infos[j];
+            let account: any | null = null;
+            if (info && info.owner.equals(program.programId)) {
+                try {
+                    account = program.coder.accounts.decode("Market", info.data);
+                } catch {
+                    account = null; // closed and reused, or not a Market
+                }
+            }
+            loaded.push({ address, account });
+        });
+    }
+    return loaded;
+}
+
+/** Walk every page of listMarkets and hydrate the result. */
+export async function fetchAllMarkets(program: anchor.Program): Promise<LoadedMarket[]> {
+    const addresses: PublicKey[] = [];
+    let cursor: number | null = 0;
+    while (cursor !== null) {
+        const page: MarketPage = await listMarkets(program, cursor);
+        addresses.push(...page.entries.map((e) => e.address));
+        cursor = page.nextCursor;
+    }
+    return loadAllMarkets(program, addresses);
+}
+
+/**
+ * Landing-page roll-up. Markets are ranked by lifetime fills until the
+ * Market account tracks rolling volume.
+ */
+export function aggregateMarkets(markets: LoadedMarket[], top = 5): MarketAggregate {
+    const live = markets.filter((m) => m.account !== null);
+    const summaries: MarketSummary[] = live.map(({ address, account }) => ({
+        address,
+        name: account.marketName,
+        openInterest: account.totalBidVolume.toNumber() + account.totalAskVolume.toNumber(),
+        fills: account.tradeSeq.toNumber(),
+        lastTradePrice: account.lastTradePrice.toNumber(),
+    }));
+    return {
+        totalMarkets: live.length,
+        closedMarkets: markets.length - live.length,
+        totalOpenInterest: summaries.reduce((sum, m) => sum + m.openInterest, 0),
+        topMarkets: [...summaries].sort((a, b) => b.fills - a.fills).slice(0, top),
+    };
+}
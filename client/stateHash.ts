@@ -0,0 +1,191 @@
+/**
+ * Order Matching Engine — State Hash Verifier
+ *
+ * Every state-changing instruction advances the market's hash chain:
+ *
+ *   state_hash = sha256(prev_state_hash || event_seq_le || borsh(event))
+ *
+ * where borsh(event) is the emitted event encoded with its own state_hash
+ * zeroed. Replaying the event stream from the zero hash must reproduce
+ * Market.state_hash, so a light client can check a market's history without
+ * trusting an indexer.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import { Connection, PublicKey } from "@solana/web3.js";
+import { createHash } from "crypto";
+import { parseCpiEvents } from "./cpiEvents";
+
+/**
+ * Events that advance the chain (all carry `eventSeq` and `stateHash`). Every
+ * market-scoped event is one; only protocol-wide events are left out.
+ */
+export const CHAINED_EVENTS = new Set([
+    "MarketInitializedEvent",
+    "FeeConfigUpdatedEvent",
+    "OrderPlacedEvent",
+    "TradeExecutedEvent",
+    "OrderCancelledEvent",
+    "MarketPausedEvent",
+    "MarketParamsStagedEvent",
+    "MarketParamsAppliedEvent",
+    "MarketParamsDiscardedEvent",
+    "MakersRestrictedSetEvent",
+    "AuthorityRenouncedEvent",
+    "MarketExpiryConfiguredEvent",
+    "MarketSettledEvent",
+    "FeeRecipientUpdatedEvent",
+    "MakerShareLimitSetEvent",
+    "MarketArchivingEvent",
+    "ProbationLimitsSetEvent",
+    "TradeBatchEvent",
+    "TradeEventModeSetEvent",
+    "CommitRevealSetEvent",
+    "OrderSplitEvent",
+    "OrdersMergedEvent",
+    "MarketSidePausedEvent",
+    "CrankRewardSetEvent",
+    "FixedPointPricesSetEvent",
+    "OrderModifiedEvent",
+    "SessionStatsResetEvent",
+    "StopOrderTriggeredEvent",
+    "OrderReducedEvent",
+    "AuctionModeSetEvent",
+    "AuctionSettledEvent",
+    "MarketUpgradedEvent",
+    "MatcherRestrictedSetEvent",
+    "MaxOrdersPerUserSetEvent",
+    "ReferralShareSetEvent",
+    "SweepDelaySetEvent",
+    "BeneficiarySetEvent",
+    "OrderExpiryUpdatedEvent",
+    "OrderCommittedEvent",
+    "OrderRevealedEvent",
+    "CommitmentRefundedEvent",
+    "OrderClosedEvent",
+    "OrderSweptEvent",
+    "RoleGrantedEvent",
+    "RoleRevokedEvent",
+    "TraderSeatGrantedEvent",
+    "TraderSeatRevokedEvent",
+    "MatcherRegisteredEvent",
+    "MatcherRevokedEvent",
+    "FeeExemptionSetEvent",
+    "BalanceDepositedEvent",
+    "ArchiveStepEvent",
+    "MarketClosedEvent",
+    "MarketListedEvent",
+    "MarketDelistedEvent",
+    "FeesWithdrawnEvent",
+    "ProtocolFeesWithdrawnEvent",
+    "BalanceWithdrawnEvent",
+    "FundsClaimedEvent",
+    "RentSubsidyFundedEvent",
+    "EmergencyCancelEvent",
+    "OrderForceCancelledEvent",
+    "OrderUpgradedEvent",
+    "BookSidesCreatedEvent",
+    "MarketSnapshotEvent",
+]);
+
+export const ZERO_HASH = Buffer.alloc(32);
+
+export interface ChainEvent {
+    name: string;
+    data: any;
+}
+
+export interface VerifyResult {
+    ok: boolean;
+    eventSeq: number;
+    stateHash: Buffer;
+    /** Index into the event list of the first event that broke the chain. */
+    failedAt?: number;
+    reason?: string;
+}
+
+function u64Le(n: number | anchor.BN): Buffer {
+    return new anchor.BN(n.toString()).toArrayLike(Buffer, "le", 8);
+}
+
+/** Hash one chain step: the event as emitted, with its stamp replaced. */
+export function chainStep(coder: anchor.BorshCoder, prevHash: Buffer, event: ChainEvent): Buffer {
+    const unstamped = { ...event.data, stateHash: Array.from(ZERO_HASH) };
+    const encoded = coder.types.encode(event.name, unstamped);
+    return createHash("sha256")
+        .update(prevHash)
+        .update(u64Le(event.data.eventSeq))
+        .update(encoded)
+        .digest();
+}
+
+/**
+ * Recompute the chain over `events` (oldest first, starting at seq 1) and
+ * compare the result against the live market account.
+ */
+export function verifyStateHashChain(
+    coder: anchor.BorshCoder,
+    events: ChainEvent[],
+    market: { eventSeq: anchor.BN; stateHash: number[] }
+): VerifyResult {
+    let hash = ZERO_HASH;
+    let seq = 0;
+
+    for (let i = 0; i < events.length; i++) {
+        const event = events[i];
+        seq += 1;
+        if (new anchor.BN(event.data.eventSeq.toString()).toNumber() !== seq) {
+            return { ok: false, eventSeq: seq, stateHash: hash, failedAt: i, reason: "sequence gap" };
+        }
+        hash = chainStep(coder, hash, event);
+        if (!hash.equals(Buffer.from(event.data.stateHash))) {
+            return { ok: false, eventSeq: seq, stateHash: hash, failedAt: i, reason: "hash mismatch" };
+        }
+    }
+
+    if (market.eventSeq.toNumber() !== seq) {
+        return { ok: false, eventSeq: seq, stateHash: hash, reason: "stream shorter than market" };
+    }
+    if (!hash.equals(Buffer.from(market.stateHash))) {
+        return { ok: false, eventSeq: seq, stateHash: hash, reason: "head mismatch" };
+    }
+    return { ok: true, eventSeq: seq, stateHash: hash };
+}
+
+/**
+ * Collect the chained events of `market` from transaction logs and self-CPI
+ * events, oldest first; an event the log lost to truncation is picked up
+ * from its CPI copy. Only covers transactions still served by the RPC
+ * node's history.
+ */
+export async function fetchMarketEvents(
+    connection: Connection,
+    program: anchor.Program,
+    market: PublicKey
+): Promise<ChainEvent[]> {
+    const parser = new anchor.EventParser(program.programId, program.coder as anchor.BorshCoder);
+    const sigs = await connection.getSignaturesForAddress(market, undefined, "confirmed");
+    // Keyed by event_seq: the log and CPI copies of an event are identical
+    const events = new Map<string, ChainEvent>();
+
+    for (const { signature, err } of sigs.reverse()) {
+        if (err) continue;
+        const tx = await connection.getTransaction(signature, {
+            commitment: "confirmed",
+            maxSupportedTransactionVersion: 0,
+        });
+        const logged = Array.from(parser.parseLogs(tx?.meta?.logMessages ?? []));
+        const viaCpi = parseCpiEvents(program.coder as anchor.BorshCoder, program.programId, tx);
+        for (const event of [...logged, ...viaCpi]) {
+            // eventSeq 0: emitted after the market was closed, outside its chain
+            if (
+                CHAINED_EVENTS.has(event.name) &&
+                event.data.market?.equals(market) &&
+                !event.data.eventSeq.isZero()
+            ) {
+                events.set(event.data.eventSeq.toString(), event);
+            }
+        }
+    }
+    return Array.from(events.values()).sort((a, b) => a.data.eventSeq.cmp(b.data.eventSeq));
+}
@@ -0,0 +1,266 @@
+//! In-memory book of one market's resting orders, and the crossing pairs in
+//! it. Pairs are checked with the program's own `compute_settlement`, so the
+//! crank only submits matches the program would accept given what it has
+//! seen on chain.
+
+use anchor_lang::prelude::Pubkey;
+use order_matching_engine::errors::MatchingEngineError;
+use order_matching_engine::matching::{compute_settlement, MatchContext, MatchSettlement};
+use order_matching_engine::state::{Market, Order, Side};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// A bid/ask pair that fills as `settlement` says.
+pub struct Cross {
+    pub bid: Pubkey,
+    pub ask: Pubkey,
+    pub settlement: MatchSettlement,
+}
+
+/// Result of a scan: the pairs to match, best first, and the pair that
+/// stopped the scan when the best remaining cross can't fill.
+pub struct Scan {
+    pub crosses: Vec<Cross>,
+    pub blocked: Option<(Pubkey, Pubkey, MatchingEngineError)>,
+}
+
+pub struct Book {
+    pub market: Market,
+    orders: HashMap<Pubkey, Order>,
+}
+
+impl Book {
+    pub fn new(market: Market) -> Self {
+        Self {
+            market,
+            orders: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn get(&self, key: &Pubkey) -> Option<&Order> {
+        self.orders.get(key)
+    }
+
+    /// Insert or refresh an order; one that can no longer fill drops out.
+    pub fn upsert(&mut self, key: Pubkey, order: Order) {
+        if order.is_active() {
+            self.orders.insert(key, order);
+        } else {
+            self.orders.remove(&key);
+        }
+    }
+
+    pub fn remove(&mut self, key: &Pubkey) {
+        self.orders.remove(key);
+    }
+
+    /// Replace the whole book with a fresh scan of the market's orders.
+    pub fn reset(&mut self, orders: impl IntoIterator<Item = (Pubkey, Order)>) {
+        self.orders.clear();
+        for (key, order) in orders {
+            self.upsert(key, order);
+        }
+    }
+
+    /// Crossing pairs in price-time priority: the best bid against the best
+    /// ask, each fill applied to working copies so that one order can fill
+    /// against several in the same scan.
+    pub fn scan(&self, now: i64, max_slippage_bps: u16) -> Scan {
+        let mut bids = self.side(Side::Buy, now);
+        let mut asks = self.side(Side::Sell, now);
+        bids.sort_by_key(|(_, o)| (Reverse(o.price_key()), o.timestamp, o.order_id));
+        asks.sort_by_key(|(_, o)| (o.price_key(), o.timestamp, o.order_id));
+
+        let market = &self.market;
+        // Fees change how a fill is paid out, never whether or how much of
+        // it fills, so the scan leaves them out.
+        let ctx = MatchContext {
+            is_paused: market.is_paused,
+            is_expired: market.is_expired(now),
+            is_archiving: market.is_archiving,
+            matcher_fee_bps: market.matcher_fee_bps,
+            max_slippage_bps,
+            now,
+            last_trade_price: market.last_trade_price,
+            price_band_bps: market.price_band_bps,
+            min_residual_quantity: market.min_order_quantity,
+            is_auction: market.auction_mode,
+            ..MatchContext::default()
+        };
+
+        let mut scan = Scan {
+            crosses: Vec::new(),
+            blocked: None,
+        };
+        let (mut b, mut a) = (0, 0);
+        while b < bids.len() && a < asks.len() {
+            let (bid_key, bid) = &bids[b];
+            let (ask_key, ask) = &asks[a];
+            if bid.price_key() < ask.price_key() {
+                break;
+            }
+            let settlement = match compute_settlement(bid, ask, &ctx) {
+                Ok(settlement) => settlement,
+                Err(err) => {
+                    scan.blocked = Some((*bid_key, *ask_key, err));
+                    break;
+                }
+            };
+            let (bid_key, ask_key) = (*bid_key, *ask_key);
+
+            let bid = &mut bids[b].1;
+            bid.filled_quantity = settlement.bid_filled_after;
+            bid.status = settlement.bid_status_after.clone();
+            bid.escrow_lamports = bid.escrow_lamports.saturating_sub(settlement.total_debit);
+            bid.consume_display(settlement.fill_quantity);
+            if !bid.is_active() {
+                b += 1;
+            }
+            let ask = &mut asks[a].1;
+            ask.filled_quantity = settlement.ask_filled_after;
+            ask.status = settlement.ask_status_after.clone();
+            ask.consume_display(settlement.fill_quantity);
+            if !ask.is_active() {
+                a += 1;
+            }
+
+            scan.crosses.push(Cross {
+                bid: bid_key,
+                ask: ask_key,
+                settlement,
+            });
+        }
+        scan
+    }
+
+    /// One side's orders that can fill at all, as working copies. Orders
+    /// settling in SPL tokens need token accounts the crank doesn't pass.
+    fn side(&self, side: Side, now: i64) -> Vec<(Pubkey, Order)> {
+        self.orders
+            .iter()
+            .filter(|(_, o)| {
+                o.side == side
+                    && !o.is_locked
+                    && !o.is_expired(now)
+                    && !o.escrow_in_vault
+                    && o.base_escrow == 0
+            })
+            .map(|(key, o)| (*key, o.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::AccountDeserialize;
+    use order_matching_engine::state::OrderStatus;
+
+    /// An all-zero account: the accounts have no Default.
+    fn zeroed<T: AccountDeserialize>(len: usize) -> T {
+        T::try_deserialize_unchecked(&mut &vec![0; len][..]).unwrap()
+    }
+
+    fn market() -> Market {
+        Market {
+            tick_size: 1,
+            lot_size: 1,
+            min_order_quantity: 1,
+            version: Market::VERSION,
+            ..zeroed(Market::LEN)
+        }
+    }
+
+    fn order(order_id: u64, side: Side, price: u64, quantity: u64) -> (Pubkey, Order) {
+        let escrow_lamports = if side == Side::Buy { price * quantity } else { 0 };
+        let order = Order {
+            order_id,
+            side,
+            price,
+            quantity,
+            escrow_lamports,
+            timestamp: order_id as i64,
+            ..zeroed(Order::LEN)
+        };
+        (Pubkey::new_unique(), order)
+    }
+
+    fn book(orders: Vec<(Pubkey, Order)>) -> Book {
+        let mut book = Book::new(market());
+        book.reset(orders);
+        book
+    }
+
+    #[test]
+    fn no_cross_when_the_spread_is_open() {
+        let scan = book(vec![order(0, Side::Buy, 990, 5), order(1, Side::Sell, 1_000, 5)]).scan(0, 0);
+        assert!(scan.crosses.is_empty());
+        assert!(scan.blocked.is_none());
+    }
+
+    #[test]
+    fn matches_best_prices_first() {
+        let (low_bid, high_bid, ask) = (
+            order(0, Side::Buy, 1_000, 5),
+            order(1, Side::Buy, 1_100, 5),
+            order(2, Side::Sell, 1_000, 5),
+        );
+        let (high_key, ask_key) = (high_bid.0, ask.0);
+        let scan = book(vec![low_bid, high_bid, ask]).scan(0, 0);
+        assert_eq!(scan.crosses.len(), 1);
+        assert_eq!(scan.crosses[0].bid, high_key);
+        assert_eq!(scan.crosses[0].ask, ask_key);
+        assert_eq!(scan.crosses[0].settlement.fill_quantity, 5);
+    }
+
+    #[test]
+    fn one_bid_sweeps_several_asks() {
+        let bid = order(0, Side::Buy, 1_000, 10);
+        let bid_key = bid.0;
+        let scan = book(vec![
+            bid,
+            order(1, Side::Sell, 990, 4),
+            order(2, Side::Sell, 1_000, 4),
+            order(3, Side::Sell, 1_000, 4),
+        ])
+        .scan(0, 0);
+        let fills: Vec<u64> = scan.crosses.iter().map(|c| c.settlement.fill_quantity).collect();
+        assert_eq!(fills, [4, 4, 2]);
+        assert!(scan.crosses.iter().all(|c| c.bid == bid_key));
+        assert_eq!(scan.crosses[2].settlement.bid_status_after, OrderStatus::Filled);
+    }
+
+    #[test]
+    fn skips_orders_that_cant_fill() {
+        let (_, mut locked) = order(0, Side::Buy, 1_200, 5);
+        locked.is_locked = true;
+        let (_, mut expired) = order(1, Side::Buy, 1_100, 5);
+        expired.expires_at = 10;
+        let (_, mut filled) = order(2, Side::Sell, 900, 5);
+        filled.status = OrderStatus::Filled;
+        let live_bid = order(3, Side::Buy, 1_000, 5);
+        let live_key = live_bid.0;
+
+        let mut book = book(vec![live_bid, order(4, Side::Sell, 1_000, 5)]);
+        for o in [locked, expired, filled] {
+            book.upsert(Pubkey::new_unique(), o);
+        }
+        assert_eq!(book.len(), 4);
+        let scan = book.scan(20, 0);
+        assert_eq!(scan.crosses.len(), 1);
+        assert_eq!(scan.crosses[0].bid, live_key);
+    }
+
+    #[test]
+    fn stops_at_a_cross_the_program_would_reject() {
+        let mut book = book(vec![order(0, Side::Buy, 1_000, 5), order(1, Side::Sell, 1_000, 5)]);
+        book.market.auction_mode = true;
+        let scan = book.scan(0, 0);
+        assert!(scan.crosses.is_empty());
+        assert!(matches!(scan.blocked, Some((_, _, MatchingEngineError::AuctionInProgress))));
+    }
+}
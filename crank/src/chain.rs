@@ -0,0 +1,324 @@
+//! Everything that talks to the cluster: account scans and fetches, the
+//! program's event logs, and match_orders transactions.
+//!
+//! The RPC clients speak solana-sdk 1.18 while the program's account and
+//! instruction types come from anchor-lang; keys and account metas cross
+//! between the two as raw bytes (`sdk_key` / `program_key`).
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use futures_util::StreamExt;
+use order_matching_engine::client::{self, MatchExtras};
+use order_matching_engine::events::OrderPlacedEvent;
+use order_matching_engine::state::{Market, Order};
+use solana_account_decoder::UiAccountEncoding;
+use solana_pubsub_client::nonblocking::pubsub_client::PubsubClient;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::config::{
+    RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionLogsConfig,
+    RpcTransactionLogsFilter,
+};
+use solana_rpc_client_api::filter::{Memcmp, RpcFilterType};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::config::Config;
+
+/// Offset of `Order.market`: after the discriminator and `owner`.
+const ORDER_MARKET_OFFSET: usize = 8 + 32;
+
+pub fn sdk_key(key: &Pubkey) -> solana_sdk::pubkey::Pubkey {
+    solana_sdk::pubkey::Pubkey::new_from_array(key.to_bytes())
+}
+
+pub fn program_key(key: &solana_sdk::pubkey::Pubkey) -> Pubkey {
+    Pubkey::new_from_array(key.to_bytes())
+}
+
+/// The PDA an `OrderPlacedEvent` describes, by the seeds its order was
+/// created under.
+pub fn order_address(event: &OrderPlacedEvent) -> Pubkey {
+    match event.client_nonce {
+        Some(nonce) => client::find_owner_order_address(&event.market, &event.owner, nonce).0,
+        None => client::find_order_address(&event.market, event.order_id).0,
+    }
+}
+
+pub struct Chain {
+    rpc: RpcClient,
+    payer: Keypair,
+    priority_fee: u64,
+    compute_unit_limit: u32,
+    max_retries: u32,
+    max_slippage_bps: u16,
+    dry_run: bool,
+}
+
+impl Chain {
+    pub fn new(config: &Config) -> Result<Self> {
+        let payer = solana_sdk::signature::read_keypair_file(&config.keypair)
+            .map_err(|err| anyhow::anyhow!("reading {}: {err}", config.keypair.display()))?;
+        Ok(Self {
+            rpc: RpcClient::new_with_commitment(config.rpc_url.clone(), CommitmentConfig::confirmed()),
+            payer,
+            priority_fee: config.priority_fee,
+            compute_unit_limit: config.compute_unit_limit,
+            max_retries: config.max_retries,
+            max_slippage_bps: config.max_slippage_bps,
+            dry_run: config.dry_run,
+        })
+    }
+
+    pub fn matcher(&self) -> Pubkey {
+        program_key(&self.payer.pubkey())
+    }
+
+    pub async fn fetch_market(&self, key: &Pubkey) -> Result<Market> {
+        let account = self
+            .rpc
+            .get_account(&sdk_key(key))
+            .await
+            .with_context(|| format!("fetching market {key}"))?;
+        Market::try_from_bytes(&account.data)
+            .with_context(|| format!("{key} is not a current-version Market"))
+    }
+
+    /// Every order of `market`, found by `Order` discriminator and market.
+    pub async fn scan_orders(&self, market: &Pubkey) -> Result<Vec<(Pubkey, Order)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, Order::DISCRIMINATOR)),
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    ORDER_MARKET_OFFSET,
+                    market.as_ref(),
+                )),
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        let accounts = self
+            .rpc
+            .get_program_accounts_with_config(&sdk_key(&order_matching_engine::ID), config)
+            .await
+            .with_context(|| format!("scanning the orders of {market}"))?;
+        // Orders written at an older layout don't deserialize; they can't
+        // be matched before they're migrated either
+        Ok(accounts
+            .into_iter()
+            .filter_map(|(key, account)| {
+                let order = Order::try_from_bytes(&account.data).ok()?;
+                Some((program_key(&key), order))
+            })
+            .collect())
+    }
+
+    /// The current state of each of `keys`; None for closed accounts.
+    pub async fn fetch_orders(&self, keys: &[Pubkey]) -> Result<Vec<Option<Order>>> {
+        let sdk_keys: Vec<_> = keys.iter().map(sdk_key).collect();
+        let accounts = self.rpc.get_multiple_accounts(&sdk_keys).await?;
+        Ok(accounts
+            .into_iter()
+            .map(|account| Order::try_from_bytes(&account?.data).ok())
+            .collect())
+    }
+
+    /// Which of `keys` exist.
+    async fn exist(&self, keys: &[Pubkey]) -> Result<Vec<bool>> {
+        let sdk_keys: Vec<_> = keys.iter().map(sdk_key).collect();
+        let accounts = self.rpc.get_multiple_accounts(&sdk_keys).await?;
+        Ok(accounts.iter().map(Option::is_some).collect())
+    }
+
+    /// match_orders for one pair, with the optional accounts that exist.
+    async fn match_instruction(
+        &self,
+        market: (&Pubkey, &Market),
+        bid: (&Pubkey, &Order),
+        ask: (&Pubkey, &Order),
+    ) -> Result<Instruction> {
+        let (market_key, matcher) = (market.0, self.matcher());
+        let exist = self
+            .exist(&[
+                client::find_market_pda(b"fee_config", market_key).0,
+                client::find_owner_pda(b"matcher", market_key, &matcher).0,
+                client::find_owner_pda(b"matcher_seat", market_key, &matcher).0,
+                client::find_owner_pda(b"seat", market_key, &bid.1.owner).0,
+                client::find_owner_pda(b"seat", market_key, &ask.1.owner).0,
+                client::find_market_pda(b"trades", market_key).0,
+            ])
+            .await?;
+        let extras = MatchExtras {
+            fee_config: exist[0],
+            matcher_stats: exist[1],
+            matcher_seat: exist[2],
+            bid_seat: exist[3],
+            ask_seat: exist[4],
+            trade_history: exist[5],
+        };
+        let args = order_matching_engine::instruction::MatchOrders {
+            max_slippage_bps: self.max_slippage_bps,
+            min_expected_bid_remaining: 0,
+            min_expected_ask_remaining: 0,
+        };
+        let ix = client::match_orders_ix(&matcher, market, bid, ask, args, extras);
+        Ok(Instruction {
+            program_id: sdk_key(&ix.program_id),
+            accounts: ix
+                .accounts
+                .into_iter()
+                .map(|meta| AccountMeta {
+                    pubkey: sdk_key(&meta.pubkey),
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: ix.data,
+        })
+    }
+
+    /// Match one pair. Transactions that fail to land are resent with a
+    /// fresh blockhash up to `max_retries` times; a program error isn't
+    /// retried. In dry-run mode the match is only simulated, and no
+    /// signature is returned.
+    pub async fn submit_match(
+        &self,
+        market_key: &Pubkey,
+        market: &Market,
+        bid: (&Pubkey, &Order),
+        ask: (&Pubkey, &Order),
+    ) -> Result<Option<Signature>> {
+        let mut instructions = Vec::new();
+        if self.compute_unit_limit > 0 {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(
+                self.compute_unit_limit,
+            ));
+        }
+        if self.priority_fee > 0 {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+                self.priority_fee,
+            ));
+        }
+        instructions.push(self.match_instruction((market_key, market), bid, ask).await?);
+
+        let mut attempt = 0;
+        loop {
+            let blockhash = self.rpc.get_latest_blockhash().await?;
+            let tx = Transaction::new_signed_with_payer(
+                &instructions,
+                Some(&self.payer.pubkey()),
+                &[&self.payer],
+                blockhash,
+            );
+            if self.dry_run {
+                let simulation = self.rpc.simulate_transaction(&tx).await?.value;
+                if let Some(err) = simulation.err {
+                    bail!("simulation failed: {err}: {:?}", simulation.logs.unwrap_or_default());
+                }
+                return Ok(None);
+            }
+            match self.rpc.send_and_confirm_transaction(&tx).await {
+                Ok(signature) => return Ok(Some(signature)),
+                Err(err) => {
+                    let program_error = matches!(
+                        err.get_transaction_error(),
+                        Some(TransactionError::InstructionError(..))
+                    );
+                    if program_error || attempt >= self.max_retries {
+                        return Err(err.into());
+                    }
+                    attempt += 1;
+                    eprintln!("[crank] match not landed ({err}); retry {attempt}/{}", self.max_retries);
+                    tokio::time::sleep(Duration::from_millis(250 << attempt.min(4))).await;
+                }
+            }
+        }
+    }
+}
+
+/// Anchor events of type `E` that this program logged, in order. Lines of
+/// other programs in the same transaction are told apart by tracking the
+/// invoke stack.
+pub fn parse_events<E: AnchorDeserialize + Discriminator>(logs: &[String]) -> Vec<E> {
+    let program = order_matching_engine::ID.to_string();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+    for line in logs {
+        let Some(rest) = line.strip_prefix("Program ") else {
+            continue;
+        };
+        if let Some(data) = rest.strip_prefix("data: ") {
+            if stack.last() != Some(&program.as_str()) {
+                continue;
+            }
+            let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(data) else {
+                continue;
+            };
+            if let Some(body) = bytes.strip_prefix(E::DISCRIMINATOR) {
+                if let Ok(event) = E::deserialize(&mut &body[..]) {
+                    events.push(event);
+                }
+            }
+            continue;
+        }
+        let mut words = rest.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some(id), Some("invoke")) => stack.push(id),
+            (Some(_), Some(verb)) if verb == "success" || verb.starts_with("failed") => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+/// Forward every `OrderPlacedEvent` the program logs to `sender`,
+/// resubscribing whenever the websocket drops. Returns once the receiver
+/// is gone.
+pub async fn watch_order_placed(ws_url: String, sender: UnboundedSender<OrderPlacedEvent>) {
+    loop {
+        if let Err(err) = subscribe(&ws_url, &sender).await {
+            eprintln!("[crank] log subscription: {err:#}");
+        }
+        if sender.is_closed() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+async fn subscribe(ws_url: &str, sender: &UnboundedSender<OrderPlacedEvent>) -> Result<()> {
+    let client = PubsubClient::new(ws_url)
+        .await
+        .with_context(|| format!("connecting to {ws_url}"))?;
+    let (mut stream, _unsubscribe) = client
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![order_matching_engine::ID.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .await?;
+    while let Some(response) = stream.next().await {
+        if response.value.err.is_some() {
+            continue;
+        }
+        for event in parse_events::<OrderPlacedEvent>(&response.value.logs) {
+            if sender.send(event).is_err() {
+                return Ok(());
+            }
+        }
+    }
+    bail!("subscription closed")
+}
@@ -0,0 +1,170 @@
+//! Crank settings: a TOML file (`--config`) with any CLI flag overriding it.
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(name = "solamatch-crank", about = "Fills crossing orders on Solamatch markets")]
+pub struct Args {
+    /// TOML file with any of the settings below
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// JSON-RPC endpoint
+    #[arg(long)]
+    pub rpc_url: Option<String>,
+
+    /// Websocket endpoint (default: derived from the RPC URL)
+    #[arg(long)]
+    pub ws_url: Option<String>,
+
+    /// Keypair that signs and pays for match transactions
+    #[arg(long)]
+    pub keypair: Option<PathBuf>,
+
+    /// Market to serve (repeatable)
+    #[arg(long = "market")]
+    pub markets: Vec<String>,
+
+    /// Full rescan interval, in milliseconds
+    #[arg(long)]
+    pub poll_interval_ms: Option<u64>,
+
+    /// Priority fee, in micro-lamports per compute unit (0 = none)
+    #[arg(long)]
+    pub priority_fee: Option<u64>,
+
+    /// Compute unit limit for each match transaction (0 = runtime default)
+    #[arg(long)]
+    pub compute_unit_limit: Option<u32>,
+
+    /// Resubmissions of a match whose transaction failed to land
+    #[arg(long)]
+    pub max_retries: Option<u32>,
+
+    /// max_slippage_bps passed to match_orders (0 = no limit)
+    #[arg(long)]
+    pub max_slippage_bps: Option<u16>,
+
+    /// Simulate matches instead of sending them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// The same settings as read from a TOML file; every key is optional.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    rpc_url: Option<String>,
+    ws_url: Option<String>,
+    keypair: Option<PathBuf>,
+    #[serde(default)]
+    markets: Vec<String>,
+    poll_interval_ms: Option<u64>,
+    priority_fee: Option<u64>,
+    compute_unit_limit: Option<u32>,
+    max_retries: Option<u32>,
+    max_slippage_bps: Option<u16>,
+    dry_run: Option<bool>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub rpc_url: String,
+    pub ws_url: String,
+    pub keypair: PathBuf,
+    pub markets: Vec<String>,
+    pub poll_interval_ms: u64,
+    pub priority_fee: u64,
+    pub compute_unit_limit: u32,
+    pub max_retries: u32,
+    pub max_slippage_bps: u16,
+    pub dry_run: bool,
+}
+
+impl Config {
+    pub fn load(args: Args) -> Result<Self> {
+        let file = match &args.config {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("reading {}", path.display()))?;
+                toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?
+            }
+            None => FileConfig::default(),
+        };
+
+        let rpc_url = args
+            .rpc_url
+            .or(file.rpc_url)
+            .unwrap_or_else(|| "http://127.0.0.1:8899".to_string());
+        let ws_url = match args.ws_url.or(file.ws_url) {
+            Some(url) => url,
+            None => ws_url_for(&rpc_url)?,
+        };
+        let keypair = expand_home(
+            args.keypair
+                .or(file.keypair)
+                .unwrap_or_else(|| PathBuf::from("~/.config/solana/id.json")),
+        )?;
+        let markets = if args.markets.is_empty() {
+            file.markets
+        } else {
+            args.markets
+        };
+        if markets.is_empty() {
+            bail!("no markets to serve: pass --market or list `markets` in the config file");
+        }
+
+        Ok(Self {
+            rpc_url,
+            ws_url,
+            keypair,
+            markets,
+            poll_interval_ms: args.poll_interval_ms.or(file.poll_interval_ms).unwrap_or(2_000),
+            priority_fee: args.priority_fee.or(file.priority_fee).unwrap_or(0),
+            compute_unit_limit: args
+                .compute_unit_limit
+                .or(file.compute_unit_limit)
+                .unwrap_or(0),
+            max_retries: args.max_retries.or(file.max_retries).unwrap_or(3),
+            max_slippage_bps: args.max_slippage_bps.or(file.max_slippage_bps).unwrap_or(0),
+            dry_run: args.dry_run || file.dry_run.unwrap_or(false),
+        })
+    }
+}
+
+/// The websocket endpoint a validator serves next to `rpc_url`: same host,
+/// ws(s) scheme, and the port after the RPC port when one is given.
+fn ws_url_for(rpc_url: &str) -> Result<String> {
+    let (scheme, rest) = if let Some(rest) = rpc_url.strip_prefix("https://") {
+        ("wss://", rest)
+    } else if let Some(rest) = rpc_url.strip_prefix("http://") {
+        ("ws://", rest)
+    } else {
+        bail!("can't derive a websocket URL from {rpc_url}; pass --ws-url");
+    };
+    let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let authority = match authority.rsplit_once(':') {
+        Some((host, port)) => {
+            let port: u16 = port
+                .parse()
+                .with_context(|| format!("bad port in {rpc_url}"))?;
+            format!("{host}:{}", port + 1)
+        }
+        None => authority.to_string(),
+    };
+    Ok(format!("{scheme}{authority}{path}"))
+}
+
+/// `path` with a leading `~` standing for the home directory.
+fn expand_home(path: PathBuf) -> Result<PathBuf> {
+    match path.strip_prefix("~") {
+        Ok(rest) => {
+            let home = std::env::var_os("HOME").context("HOME is not set; give an absolute keypair path")?;
+            Ok(PathBuf::from(home).join(rest))
+        }
+        Err(_) => Ok(path),
+    }
+}
@@ -0,0 +1,141 @@
+//! Solamatch matching crank.
+//!
+//! Keeps an in-memory book per served market — seeded by a full scan of
+//! its orders, updated from `OrderPlacedEvent` logs and rescanned every
+//! poll interval to catch cancels, expiries and anything the websocket
+//! missed — and submits `match_orders` for every crossing pair.
+//!
+//!     solamatch-crank --market <MARKET> [--config crank.toml] [--dry-run]
+
+mod book;
+mod chain;
+mod config;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{Context, Result};
+use book::Book;
+use chain::Chain;
+use clap::Parser;
+use config::{Args, Config};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let config = Config::load(Args::parse())?;
+    let chain = Chain::new(&config)?;
+
+    let mut books = HashMap::new();
+    for market in &config.markets {
+        let key = Pubkey::from_str(market).with_context(|| format!("bad market address {market}"))?;
+        let mut book = Book::new(chain.fetch_market(&key).await?);
+        book.reset(chain.scan_orders(&key).await?);
+        println!(
+            "[crank] serving {} ({key}): {} resting orders",
+            book.market.market_name,
+            book.len()
+        );
+        books.insert(key, book);
+    }
+    println!(
+        "[crank] matcher {}{}",
+        chain.matcher(),
+        if config.dry_run { " (dry run)" } else { "" }
+    );
+
+    let (sender, mut placed) = mpsc::unbounded_channel();
+    tokio::spawn(chain::watch_order_placed(config.ws_url.clone(), sender));
+    let mut poll = tokio::time::interval(Duration::from_millis(config.poll_interval_ms));
+
+    loop {
+        tokio::select! {
+            Some(event) = placed.recv() => {
+                let Some(book) = books.get_mut(&event.market) else {
+                    continue;
+                };
+                let key = chain::order_address(&event);
+                match chain.fetch_orders(&[key]).await {
+                    Ok(mut orders) => {
+                        if let Some(order) = orders.pop().flatten() {
+                            book.upsert(key, order);
+                        }
+                    }
+                    Err(err) => eprintln!("[crank] {err:#}"),
+                }
+                crank(&chain, &event.market, book, config.max_slippage_bps).await;
+            }
+            _ = poll.tick() => {
+                for (key, book) in books.iter_mut() {
+                    match chain.scan_orders(key).await {
+                        Ok(orders) => book.reset(orders),
+                        Err(err) => {
+                            eprintln!("[crank] {err:#}");
+                            continue;
+                        }
+                    }
+                    crank(&chain, key, book, config.max_slippage_bps).await;
+                }
+            }
+        }
+    }
+}
+
+/// Match every crossing pair in `book`, best first, refreshing both orders
+/// after each attempt. Stops at the first failure: the pairs after it were
+/// worked out assuming it would fill.
+async fn crank(chain: &Chain, market_key: &Pubkey, book: &mut Book, max_slippage_bps: u16) {
+    match chain.fetch_market(market_key).await {
+        Ok(market) => book.market = market,
+        Err(err) => {
+            eprintln!("[crank] {err:#}");
+            return;
+        }
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64);
+    let scan = book.scan(now, max_slippage_bps);
+    if let Some((bid, ask, err)) = scan.blocked {
+        eprintln!("[crank] {market_key}: bid {bid} × ask {ask} crosses but can't fill: {err}");
+    }
+
+    for cross in scan.crosses {
+        let (Some(bid), Some(ask)) = (book.get(&cross.bid).cloned(), book.get(&cross.ask).cloned())
+        else {
+            break;
+        };
+        let result = chain
+            .submit_match(market_key, &book.market, (&cross.bid, &bid), (&cross.ask, &ask))
+            .await;
+        match &result {
+            Ok(Some(signature)) => println!(
+                "[crank] matched #{} × #{}: {} @ {} ({signature})",
+                bid.order_id, ask.order_id, cross.settlement.fill_quantity, cross.settlement.fill_price
+            ),
+            Ok(None) => println!(
+                "[crank] would match #{} × #{}: {} @ {}",
+                bid.order_id, ask.order_id, cross.settlement.fill_quantity, cross.settlement.fill_price
+            ),
+            Err(err) => eprintln!("[crank] match #{} × #{} failed: {err:#}", bid.order_id, ask.order_id),
+        }
+
+        match chain.fetch_orders(&[cross.bid, cross.ask]).await {
+            Ok(orders) => {
+                for (key, order) in [cross.bid, cross.ask].into_iter().zip(orders) {
+                    match order {
+                        Some(order) => book.upsert(key, order),
+                        None => book.remove(&key),
+                    }
+                }
+            }
+            Err(err) => eprintln!("[crank] {err:#}"),
+        }
+        // A dry run changes nothing, so later pairs would repeat this one's
+        // starting state; only the first is meaningful
+        if result.is_err() || matches!(result, Ok(None)) {
+            break;
+        }
+    }
+}
@@ -0,0 +1,1045 @@
+//! Solamatch core — the pure matching math.
+//!
+//! Crossing, fill size, fees, dust, refunds, price bands and crank rewards for one bid/ask match,
+//! plus the time-weighted average of fill prices,
+//! for integer prices and for Q64.64 fixed-point prices,
+//! with no Anchor or Solana dependency so the on-chain program, the client
+//! simulator and the matcher's planner all run the exact same code.
+//!
+//! Rounding / dust policy:
+//!   - Buyer escrow is debited exactly (fill_price * fill_quantity + price
+//!     improvement refund); nothing is ever rounded against the escrow.
+//!   - The fee is gross * fee_bps / 10_000 rounded down.
+//!   - The seller payment is rounded down: when the exact fee has a fractional
+//!     part, the seller gives up the one lamport that covers it. That lamport
+//!     is dust, so escrow_in == payouts_out + fees + dust holds exactly.
+//!   - A market's matcher fee is gross * matcher_fee_bps / 10_000 rounded
+//!     down, also out of the seller payment: it only ever moves lamports the
+//!     escrow already pays out, and is 0 (changing nothing) at 0 bps.
+//!
+//! Fixed-point prices (Q64.64 quote atoms per base unit) extend that policy:
+//!   - A BUY escrows its notional rounded up, and always keeps at least the
+//!     rounded-up notional of its remaining size.
+//!   - The seller's gross payment for a fill is its notional rounded down.
+//!   - A fill releases the escrow above what the bid's remainder needs (all
+//!     of it on the final fill); whatever the seller isn't paid goes back to
+//!     the buyer as a refund. Escrow is therefore conserved exactly across
+//!     any sequence of partial fills.
+#![no_std]
+
+/// Basis-point denominator (10_000 bps = 100%).
+pub const BPS_DENOMINATOR: u128 = 10_000;
+
+/// Errors the pure math can produce.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CoreError {
+    MathOverflow,
+    /// A fill would take an order past its quantity.
+    FillExceedsQuantity,
+    /// A fill's amounts don't add up: the escrow debit isn't the seller's
+    /// gross plus the buyer refund, or the gross isn't fully split.
+    UnbalancedSettlement,
+}
+
+/// Price and size of one side of a match.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct OrderTerms {
+    pub price: u64,
+    pub quantity: u64,
+    pub filled_quantity: u64,
+}
+
+impl OrderTerms {
+    pub fn remaining(&self) -> u64 {
+        self.quantity.saturating_sub(self.filled_quantity)
+    }
+}
+
+// ─── Crossing ─────────────────────────────────────────────────────────────────
+
+/// Whether a bid and an ask cross, within the matcher's slippage bound.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CrossCheck {
+    /// Prices cross; `spread` = bid_price - ask_price (the buyer's price improvement).
+    Crossed { spread: u64 },
+    /// bid_price < ask_price.
+    NotCrossed,
+    /// Prices cross, but (bid - ask) / bid exceeds `max_slippage_bps`.
+    SlippageExceeded { slippage_bps: u64 },
+}
+
+/// Check a bid/ask pair. `max_slippage_bps` of 0 disables the slippage bound.
+pub fn check_cross(bid_price: u64, ask_price: u64, max_slippage_bps: u16) -> CrossCheck {
+    if bid_price < ask_price {
+        return CrossCheck::NotCrossed;
+    }
+    let spread = bid_price - ask_price;
+    if max_slippage_bps > 0 && bid_price > 0 {
+        let slippage_bps = ((spread as u128) * BPS_DENOMINATOR / bid_price as u128) as u64;
+        if slippage_bps > max_slippage_bps as u64 {
+            return CrossCheck::SlippageExceeded { slippage_bps };
+        }
+    }
+    CrossCheck::Crossed { spread }
+}
+
+// ─── Fees ─────────────────────────────────────────────────────────────────────
+
+/// How a gross payment splits between fee, rounding dust and the payee.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct FeeBreakdown {
+    pub gross: u64,
+    pub fee: u64,  // rounded down
+    pub dust: u64, // 1 when the exact fee is fractional, else 0
+    pub net: u64,  // gross - fee - dust - matcher_fee
+    pub matcher_fee: u64, // rounded down; 0 unless `pay_matcher` is applied
+}
+
+/// Fee for a payment at `fee_bps` (rounded down).
+pub fn calc_fee(payment: u64, fee_bps: u16) -> u64 {
+    ((payment as u128) * (fee_bps as u128) / BPS_DENOMINATOR) as u64
+}
+
+/// Split `gross` into fee, dust and net at `fee_bps`.
+pub fn fee_breakdown(gross: u64, fee_bps: u16) -> Result<FeeBreakdown, CoreError> {
+    let exact = (gross as u128) * (fee_bps as u128);
+    let fee = calc_fee(gross, fee_bps);
+    let dust = if exact.is_multiple_of(BPS_DENOMINATOR) { 0 } else { 1 };
+    let net = gross
+        .checked_sub(fee)
+        .and_then(|net| net.checked_sub(dust))
+        .ok_or(CoreError::MathOverflow)?;
+    Ok(FeeBreakdown { gross, fee, dust, net, matcher_fee: 0 })
+}
+
+impl FeeBreakdown {
+    /// Take the matcher's cut at `matcher_fee_bps` (rounded down) out of
+    /// the payee's net. The gross, and so the escrow debit, is unchanged.
+    pub fn pay_matcher(self, matcher_fee_bps: u16) -> Result<FeeBreakdown, CoreError> {
+        let matcher_fee = calc_fee(self.gross, matcher_fee_bps);
+        let net = self
+            .net
+            .checked_sub(matcher_fee)
+            .ok_or(CoreError::MathOverflow)?;
+        Ok(FeeBreakdown { net, matcher_fee, ..self })
+    }
+}
+
+// ─── Fills ────────────────────────────────────────────────────────────────────
+
+/// Every amount of one bid/ask fill at the maker (ask) price.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct FillOutcome {
+    pub fill_quantity: u64,
+    pub fill_price: u64,      // whole quote atoms (rounded down for fixed-point prices)
+    pub fill_price_q64: u128, // exact, Q64.64
+    pub fee: FeeBreakdown, // on the gross seller payment
+    pub buyer_refund: u64, // price improvement returned to the buyer
+    pub total_debit: u64,  // lamports leaving the bid escrow
+    pub bid_filled_after: u64,
+    pub ask_filled_after: u64,
+    pub bid_complete: bool,
+    pub ask_complete: bool,
+}
+
+impl FillOutcome {
+    /// Post-trade invariants, checked on every fill before it settles:
+    /// neither order filled past its quantity, every lamport debited from
+    /// the bid paid out as gross or refund, and every lamport of the gross
+    /// paid out as net, fees or dust.
+    pub fn check(&self, bid_quantity: u64, ask_quantity: u64) -> Result<(), CoreError> {
+        if self.bid_filled_after > bid_quantity || self.ask_filled_after > ask_quantity {
+            return Err(CoreError::FillExceedsQuantity);
+        }
+        let fee = &self.fee;
+        let paid_out = [fee.fee, fee.dust, fee.matcher_fee]
+            .into_iter()
+            .try_fold(fee.net, u64::checked_add);
+        if self.fee.gross.checked_add(self.buyer_refund) != Some(self.total_debit)
+            || paid_out != Some(fee.gross)
+        {
+            return Err(CoreError::UnbalancedSettlement);
+        }
+        Ok(())
+    }
+}
+
+/// Fill a crossing pair as far as both remainders allow. Crossing must be
+/// checked first (see `check_cross`); a non-crossing pair is a MathOverflow.
+pub fn compute_fill(bid: &OrderTerms, ask: &OrderTerms, fee_bps: u16) -> Result<FillOutcome, CoreError> {
+    compute_fill_up_to(bid, ask, fee_bps, u64::MAX)
+}
+
+/// `compute_fill` of at most `max_quantity` units. A cap below a remainder
+/// leaves that order incomplete.
+pub fn compute_fill_up_to(
+    bid: &OrderTerms,
+    ask: &OrderTerms,
+    fee_bps: u16,
+    max_quantity: u64,
+) -> Result<FillOutcome, CoreError> {
+    let fill_quantity = bid.remaining().min(ask.remaining()).min(max_quantity);
+    let fill_price = ask.price;
+
+    let gross = fill_price
+        .checked_mul(fill_quantity)
+        .ok_or(CoreError::MathOverflow)?;
+    let fee = fee_breakdown(gross, fee_bps)?;
+
+    let buyer_refund = bid
+        .price
+        .checked_sub(ask.price)
+        .and_then(|improvement| improvement.checked_mul(fill_quantity))
+        .ok_or(CoreError::MathOverflow)?;
+    let total_debit = gross
+        .checked_add(buyer_refund)
+        .ok_or(CoreError::MathOverflow)?;
+
+    let bid_filled_after = bid
+        .filled_quantity
+        .checked_add(fill_quantity)
+        .ok_or(CoreError::MathOverflow)?;
+    let ask_filled_after = ask
+        .filled_quantity
+        .checked_add(fill_quantity)
+        .ok_or(CoreError::MathOverflow)?;
+
+    Ok(FillOutcome {
+        fill_quantity,
+        fill_price,
+        fill_price_q64: to_q64(fill_price),
+        fee,
+        buyer_refund,
+        total_debit,
+        bid_filled_after,
+        ask_filled_after,
+        bid_complete: bid_filled_after >= bid.quantity,
+        ask_complete: ask_filled_after >= ask.quantity,
+    })
+}
+
+// ─── Fixed-point prices ───────────────────────────────────────────────────────
+
+/// A price of one quote atom per base unit, in Q64.64.
+pub const Q64_ONE: u128 = 1 << 64;
+
+const LOW_64: u128 = (1 << 64) - 1;
+
+/// A whole-atom price in Q64.64.
+pub fn to_q64(price: u64) -> u128 {
+    (price as u128) << 64
+}
+
+/// Price and size of one side of a match, priced in Q64.64.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct OrderTermsQ64 {
+    pub price_q64: u128,
+    pub quantity: u64,
+    pub filled_quantity: u64,
+}
+
+impl OrderTermsQ64 {
+    pub fn remaining(&self) -> u64 {
+        self.quantity.saturating_sub(self.filled_quantity)
+    }
+}
+
+/// `price_q64 * quantity` in whole quote atoms, rounded down or up. The
+/// 192-bit product is formed exactly from the price's 64-bit halves; only
+/// a result above u64::MAX is an error.
+pub fn notional_q64(price_q64: u128, quantity: u64, round_up: bool) -> Result<u64, CoreError> {
+    let quantity = quantity as u128;
+    let whole = (price_q64 >> 64) * quantity; // < 2^128
+    let frac = (price_q64 & LOW_64) * quantity; // < 2^128
+    let mut atoms = whole
+        .checked_add(frac >> 64)
+        .ok_or(CoreError::MathOverflow)?;
+    if round_up && frac & LOW_64 != 0 {
+        atoms = atoms.checked_add(1).ok_or(CoreError::MathOverflow)?;
+    }
+    u64::try_from(atoms).map_err(|_| CoreError::MathOverflow)
+}
+
+/// Escrow a fixed-point BUY holds for `remaining` units: the notional
+/// rounded up.
+pub fn escrow_q64(price_q64: u128, remaining: u64) -> Result<u64, CoreError> {
+    notional_q64(price_q64, remaining, true)
+}
+
+/// `check_cross` for Q64.64 prices. `spread` is reported in whole quote
+/// atoms, rounded down. Above 2^114 the slippage ratio is computed on
+/// shifted operands and may overstate by a basis point.
+pub fn check_cross_q64(bid_price_q64: u128, ask_price_q64: u128, max_slippage_bps: u16) -> CrossCheck {
+    if bid_price_q64 < ask_price_q64 {
+        return CrossCheck::NotCrossed;
+    }
+    let spread = bid_price_q64 - ask_price_q64;
+    if max_slippage_bps > 0 && bid_price_q64 > 0 {
+        let slippage_bps = match spread.checked_mul(BPS_DENOMINATOR) {
+            Some(scaled) => scaled / bid_price_q64,
+            // spread <= bid, so bid >= 2^114 here and bid / 10_000 > 0
+            None => spread / (bid_price_q64 / BPS_DENOMINATOR),
+        } as u64;
+        if slippage_bps > max_slippage_bps as u64 {
+            return CrossCheck::SlippageExceeded { slippage_bps };
+        }
+    }
+    CrossCheck::Crossed {
+        spread: (spread >> 64) as u64,
+    }
+}
+
+/// `compute_fill` for Q64.64 prices. `bid_escrow` is the escrow the bid
+/// still holds; the fill releases all of it above `escrow_q64` of the
+/// bid's remainder after the fill. A bid holding less than that, or a
+/// non-crossing pair, is a MathOverflow.
+pub fn compute_fill_q64(
+    bid: &OrderTermsQ64,
+    bid_escrow: u64,
+    ask: &OrderTermsQ64,
+    fee_bps: u16,
+) -> Result<FillOutcome, CoreError> {
+    compute_fill_q64_up_to(bid, bid_escrow, ask, fee_bps, u64::MAX)
+}
+
+/// `compute_fill_q64` of at most `max_quantity` units.
+pub fn compute_fill_q64_up_to(
+    bid: &OrderTermsQ64,
+    bid_escrow: u64,
+    ask: &OrderTermsQ64,
+    fee_bps: u16,
+    max_quantity: u64,
+) -> Result<FillOutcome, CoreError> {
+    if bid.price_q64 < ask.price_q64 {
+        return Err(CoreError::MathOverflow);
+    }
+    let fill_quantity = bid.remaining().min(ask.remaining()).min(max_quantity);
+
+    let gross = notional_q64(ask.price_q64, fill_quantity, false)?;
+    let fee = fee_breakdown(gross, fee_bps)?;
+
+    let bid_filled_after = bid
+        .filled_quantity
+        .checked_add(fill_quantity)
+        .ok_or(CoreError::MathOverflow)?;
+    let ask_filled_after = ask
+        .filled_quantity
+        .checked_add(fill_quantity)
+        .ok_or(CoreError::MathOverflow)?;
+    let bid_complete = bid_filled_after >= bid.quantity;
+
+    let still_needed = if bid_complete {
+        0
+    } else {
+        escrow_q64(bid.price_q64, bid.quantity - bid_filled_after)?
+    };
+    let total_debit = bid_escrow
+        .checked_sub(still_needed)
+        .ok_or(CoreError::MathOverflow)?;
+    let buyer_refund = total_debit
+        .checked_sub(gross)
+        .ok_or(CoreError::MathOverflow)?;
+
+    Ok(FillOutcome {
+        fill_quantity,
+        fill_price: (ask.price_q64 >> 64) as u64,
+        fill_price_q64: ask.price_q64,
+        fee,
+        buyer_refund,
+        total_debit,
+        bid_filled_after,
+        ask_filled_after,
+        bid_complete,
+        ask_complete: ask_filled_after >= ask.quantity,
+    })
+}
+
+// ─── Crank rewards ────────────────────────────────────────────────────────────
+
+/// Matcher reward for clearing a cross that has stood `age_slots`:
+/// `base + per_slot * age_slots`, capped at `max` (0 = no cap). With
+/// `per_slot` 0 this is a flat `base`.
+pub fn crank_reward(base: u64, per_slot: u64, max: u64, age_slots: u64) -> u64 {
+    let reward = base.saturating_add(per_slot.saturating_mul(age_slots));
+    if max > 0 {
+        reward.min(max)
+    } else {
+        reward
+    }
+}
+
+// ─── Price band ───────────────────────────────────────────────────────────────
+
+/// True when `price` is within `band_bps` of `reference`, the boundary
+/// included. A zero band or a zero reference (no trade yet) accepts any price.
+pub fn within_band(price: u64, reference: u64, band_bps: u64) -> bool {
+    if band_bps == 0 || reference == 0 {
+        return true;
+    }
+    let deviation = price.abs_diff(reference) as u128;
+    deviation * BPS_DENOMINATOR <= band_bps as u128 * reference as u128
+}
+
+// ─── TWAP ─────────────────────────────────────────────────────────────────────
+
+/// Advance a Uniswap-style price accumulator to `now`: add `price` weighted
+/// by the seconds it has stood since `since`. Nothing accrues before the
+/// first fill (`since` 0) or over a non-positive interval; a long gap just
+/// weighs the standing price for longer.
+pub fn accumulate_price(cumulative: u128, price: u64, since: i64, now: i64) -> Result<u128, CoreError> {
+    if since == 0 || now <= since {
+        return Ok(cumulative);
+    }
+    let elapsed = (now - since) as u128;
+    cumulative
+        .checked_add(price as u128 * elapsed)
+        .ok_or(CoreError::MathOverflow)
+}
+
+/// Time-weighted average price between two accumulator snapshots, each a
+/// `(cumulative, timestamp)` pair: the accumulator delta over the elapsed
+/// seconds, rounded down. None for an empty or reversed window.
+pub fn time_weighted_average(start: (u128, i64), end: (u128, i64)) -> Option<u64> {
+    if end.1 <= start.1 || end.0 < start.0 {
+        return None;
+    }
+    let average = (end.0 - start.0) / (end.1 - start.1) as u128;
+    u64::try_from(average).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(price: u64, quantity: u64, filled_quantity: u64) -> OrderTerms {
+        OrderTerms { price, quantity, filled_quantity }
+    }
+
+    #[test]
+    fn cross_check() {
+        assert_eq!(check_cross(99, 100, 0), CrossCheck::NotCrossed);
+        assert_eq!(check_cross(100, 100, 0), CrossCheck::Crossed { spread: 0 });
+        assert_eq!(check_cross(110, 100, 0), CrossCheck::Crossed { spread: 10 });
+        // 10 / 110 = 909 bps
+        assert_eq!(check_cross(110, 100, 909), CrossCheck::Crossed { spread: 10 });
+        assert_eq!(
+            check_cross(110, 100, 908),
+            CrossCheck::SlippageExceeded { slippage_bps: 909 }
+        );
+    }
+
+    #[test]
+    fn fee_rounds_down_with_one_lamport_of_dust() {
+        assert_eq!(
+            fee_breakdown(100_000, 100),
+            Ok(FeeBreakdown { gross: 100_000, fee: 1_000, dust: 0, net: 99_000, matcher_fee: 0 })
+        );
+        // 9_999 * 100 / 10_000 = 99.99
+        assert_eq!(
+            fee_breakdown(9_999, 100),
+            Ok(FeeBreakdown { gross: 9_999, fee: 99, dust: 1, net: 9_899, matcher_fee: 0 })
+        );
+        assert_eq!(
+            fee_breakdown(1, 1),
+            Ok(FeeBreakdown { gross: 1, fee: 0, dust: 1, net: 0, matcher_fee: 0 })
+        );
+        assert_eq!(fee_breakdown(0, 100).map(|f| f.net), Ok(0));
+    }
+
+    #[test]
+    fn fee_never_overflows() {
+        let fee = fee_breakdown(u64::MAX, 10_000).unwrap();
+        assert_eq!(fee.fee, u64::MAX);
+        assert_eq!(fee.net, 0);
+    }
+
+    #[test]
+    fn matcher_fee_comes_out_of_the_seller_payment() {
+        // 9_999 * 25 / 10_000 = 24.9975: the seller keeps the fraction
+        let fee = fee_breakdown(9_999, 100).unwrap().pay_matcher(25).unwrap();
+        assert_eq!(fee, FeeBreakdown { gross: 9_999, fee: 99, dust: 1, net: 9_875, matcher_fee: 24 });
+        assert_eq!(fee.gross, fee.net + fee.fee + fee.dust + fee.matcher_fee);
+        // 0 bps changes nothing
+        assert_eq!(fee_breakdown(9_999, 100).unwrap().pay_matcher(0), fee_breakdown(9_999, 100));
+        // Fee and matcher fee together can't take more than the gross
+        assert_eq!(fee_breakdown(1, 10_000).unwrap().pay_matcher(10_000), Err(CoreError::MathOverflow));
+    }
+
+    #[test]
+    fn partial_fill_at_maker_price_refunds_improvement() {
+        let fill = compute_fill(&terms(12_000, 10, 0), &terms(10_000, 4, 0), 100).unwrap();
+        assert_eq!(fill.fill_quantity, 4);
+        assert_eq!(fill.fill_price, 10_000);
+        assert_eq!(fill.fee.gross, 40_000);
+        assert_eq!(fill.fee.fee, 400);
+        assert_eq!(fill.buyer_refund, 8_000);
+        assert_eq!(fill.total_debit, 48_000);
+        assert_eq!((fill.bid_filled_after, fill.ask_filled_after), (4, 4));
+        assert!(!fill.bid_complete);
+        assert!(fill.ask_complete);
+    }
+
+    #[test]
+    fn sweep_refunds_accumulate_across_ask_prices() {
+        // One bid of 10 @ 12_000 swept across four asks, as match_orders_multi does
+        let mut bid = terms(12_000, 10, 0);
+        let asks = [terms(10_000, 2, 0), terms(11_000, 3, 0), terms(12_000, 4, 0), terms(9_000, 5, 0)];
+        let (mut refunds, mut debits, mut net, mut fees) = (0, 0, 0, 0);
+        for ask in &asks {
+            if bid.remaining() == 0 {
+                break;
+            }
+            let fill = compute_fill(&bid, ask, 25).unwrap();
+            bid.filled_quantity = fill.bid_filled_after;
+            refunds += fill.buyer_refund;
+            debits += fill.total_debit;
+            net += fill.fee.net;
+            fees += fill.fee.fee + fill.fee.dust;
+        }
+        assert_eq!(bid.filled_quantity, 10);
+        // 2 * 2_000 + 3 * 1_000 + 4 * 0 + 1 * 3_000
+        assert_eq!(refunds, 10_000);
+        // The whole escrow leaves the bid, and nothing more
+        assert_eq!(debits, 12_000 * 10);
+        assert_eq!(debits, net + fees + refunds);
+    }
+
+    #[test]
+    fn escrow_is_conserved() {
+        for (bid_price, ask_price, qty, fee_bps) in [
+            (10_000, 10_000, 7, 0),
+            (10_001, 9_999, 3, 30),
+            (12_345, 6_789, 11, 1_000),
+            (1, 1, 1, 1),
+        ] {
+            let fill = compute_fill(&terms(bid_price, qty, 0), &terms(ask_price, qty, 0), fee_bps).unwrap();
+            assert_eq!(
+                fill.total_debit,
+                fill.fee.net + fill.fee.fee + fill.fee.dust + fill.buyer_refund
+            );
+            assert_eq!(fill.total_debit, bid_price * qty);
+            assert!(fill.bid_complete && fill.ask_complete);
+        }
+    }
+
+    #[test]
+    fn capped_fills_add_up_to_one_full_fill() {
+        let (bid, ask) = (terms(12_000, 100, 0), terms(10_000, 100, 0));
+        let whole = compute_fill(&bid, &ask, 30).unwrap();
+
+        let first = compute_fill_up_to(&bid, &ask, 30, 60).unwrap();
+        assert_eq!(first.fill_quantity, 60);
+        assert!(!first.bid_complete && !first.ask_complete);
+        let bid = terms(12_000, 100, first.bid_filled_after);
+        let ask = terms(10_000, 100, first.ask_filled_after);
+        // A cap above both remainders changes nothing
+        let second = compute_fill_up_to(&bid, &ask, 30, 1_000).unwrap();
+        assert_eq!(second.fill_quantity, 40);
+        assert!(second.bid_complete && second.ask_complete);
+
+        assert_eq!(first.total_debit + second.total_debit, whole.total_debit);
+        assert_eq!(first.buyer_refund + second.buyer_refund, whole.buyer_refund);
+        assert_eq!(first.fee.gross + second.fee.gross, whole.fee.gross);
+    }
+
+    #[test]
+    fn overflow_is_reported() {
+        assert_eq!(
+            compute_fill(&terms(u64::MAX, 2, 0), &terms(u64::MAX, 2, 0), 0),
+            Err(CoreError::MathOverflow)
+        );
+        // A non-crossing pair can't be filled
+        assert_eq!(
+            compute_fill(&terms(1, 1, 0), &terms(2, 1, 0), 0),
+            Err(CoreError::MathOverflow)
+        );
+    }
+
+    #[test]
+    fn fills_at_the_u64_boundary() {
+        // The whole u64 range of units at one lamport each
+        let fill = compute_fill(&terms(1, u64::MAX, 0), &terms(1, u64::MAX, 0), 10_000).unwrap();
+        assert_eq!(fill.fill_quantity, u64::MAX);
+        assert_eq!(fill.total_debit, u64::MAX);
+        assert_eq!((fill.fee.fee, fill.fee.net), (u64::MAX, 0));
+        assert!(fill.bid_complete && fill.ask_complete);
+        assert_eq!(fill.check(u64::MAX, u64::MAX), Ok(()));
+
+        // The last unit of an order already filled to u64::MAX - 1
+        let fill = compute_fill(&terms(2, u64::MAX, u64::MAX - 1), &terms(1, 5, 0), 0).unwrap();
+        assert_eq!(fill.fill_quantity, 1);
+        assert_eq!(fill.bid_filled_after, u64::MAX);
+        assert_eq!((fill.fee.gross, fill.buyer_refund), (1, 1));
+
+        // A debit of exactly u64::MAX fits; one more unit doesn't
+        assert_eq!(
+            compute_fill(&terms(u64::MAX, 1, 0), &terms(u64::MAX / 2 + 1, 1, 0), 0).map(|f| f.total_debit),
+            Ok(u64::MAX)
+        );
+        assert_eq!(
+            compute_fill(&terms(u64::MAX, 2, 0), &terms(u64::MAX / 2 + 1, 2, 0), 0),
+            Err(CoreError::MathOverflow)
+        );
+        // Prices at u64::MAX in Q64.64 (the top of the u128 range)
+        let max_q64 = to_q64(u64::MAX);
+        let fill = compute_fill_q64(&terms_q64(max_q64, 1, 0), u64::MAX, &terms_q64(max_q64, 1, 0), 0).unwrap();
+        assert_eq!((fill.fee.gross, fill.total_debit), (u64::MAX, u64::MAX));
+        assert_eq!(fill.check(1, 1), Ok(()));
+    }
+
+    #[test]
+    fn every_fill_passes_its_invariants() {
+        for bid_price in [1, 7, 9_999, 10_000, u64::MAX / 1_000] {
+            for ask_price in [1, 3, 9_999] {
+                for (bid_qty, ask_qty, filled, cap) in [(1, 1, 0, u64::MAX), (10, 3, 4, 2), (1_000, 999, 0, 500)] {
+                    for (fee_bps, matcher_bps) in [(0, 0), (1, 0), (30, 5), (10_000, 0)] {
+                        if ask_price > bid_price {
+                            continue;
+                        }
+                        let fill = compute_fill_up_to(
+                            &terms(bid_price, bid_qty, filled),
+                            &terms(ask_price, ask_qty, 0),
+                            fee_bps,
+                            cap,
+                        )
+                        .unwrap();
+                        let fill = FillOutcome { fee: fill.fee.pay_matcher(matcher_bps).unwrap(), ..fill };
+                        assert_eq!(fill.check(bid_qty, ask_qty), Ok(()));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn broken_fills_fail_their_invariants() {
+        let fill = compute_fill(&terms(12_000, 10, 0), &terms(10_000, 4, 0), 100).unwrap();
+        assert_eq!(fill.check(10, 4), Ok(()));
+        // Past either order's quantity
+        assert_eq!(fill.check(3, 4), Err(CoreError::FillExceedsQuantity));
+        assert_eq!(fill.check(10, 3), Err(CoreError::FillExceedsQuantity));
+        // A lamport debited but never paid out
+        let leak = FillOutcome { total_debit: fill.total_debit + 1, ..fill };
+        assert_eq!(leak.check(10, 4), Err(CoreError::UnbalancedSettlement));
+        // A lamport of the gross paid out twice
+        let fee = FeeBreakdown { net: fill.fee.net + 1, ..fill.fee };
+        assert_eq!(FillOutcome { fee, ..fill }.check(10, 4), Err(CoreError::UnbalancedSettlement));
+        // Sums that overflow are unbalanced, not a panic
+        let fee = FeeBreakdown { net: u64::MAX, fee: 1, ..fill.fee };
+        assert_eq!(FillOutcome { fee, ..fill }.check(10, 4), Err(CoreError::UnbalancedSettlement));
+    }
+
+    fn terms_q64(price_q64: u128, quantity: u64, filled_quantity: u64) -> OrderTermsQ64 {
+        OrderTermsQ64 { price_q64, quantity, filled_quantity }
+    }
+
+    #[test]
+    fn q64_notional_at_the_smallest_prices() {
+        // 2^-64 atoms per unit: even u64::MAX units are worth less than an atom
+        assert_eq!(notional_q64(1, u64::MAX, false), Ok(0));
+        assert_eq!(notional_q64(1, u64::MAX, true), Ok(1));
+        assert_eq!(notional_q64(Q64_ONE - 1, 1, false), Ok(0));
+        assert_eq!(notional_q64(Q64_ONE - 1, 1, true), Ok(1));
+        // 2^-32 atoms per unit × 2^32 units = exactly one atom
+        assert_eq!(notional_q64(1 << 32, 1 << 32, false), Ok(1));
+        assert_eq!(notional_q64(1 << 32, 1 << 32, true), Ok(1));
+        // 0.25 atoms × 10 = 2.5
+        assert_eq!(notional_q64(Q64_ONE / 4, 10, false), Ok(2));
+        assert_eq!(notional_q64(Q64_ONE / 4, 10, true), Ok(3));
+        assert_eq!(notional_q64(Q64_ONE / 4, 0, true), Ok(0));
+    }
+
+    #[test]
+    fn q64_notional_at_the_largest_prices() {
+        // Just under u64::MAX + 1 atoms per unit
+        assert_eq!(notional_q64(u128::MAX, 1, false), Ok(u64::MAX));
+        assert_eq!(notional_q64(u128::MAX, 1, true), Err(CoreError::MathOverflow));
+        assert_eq!(notional_q64(u128::MAX, 2, false), Err(CoreError::MathOverflow));
+        assert_eq!(notional_q64(to_q64(u64::MAX), 1, true), Ok(u64::MAX));
+        // The widest intermediate: both halves of the price times u64::MAX
+        assert_eq!(notional_q64(u128::MAX, u64::MAX, false), Err(CoreError::MathOverflow));
+        assert_eq!(notional_q64(Q64_ONE + 1, u64::MAX, false), Ok(u64::MAX));
+        assert_eq!(notional_q64(Q64_ONE + 1, u64::MAX, true), Err(CoreError::MathOverflow));
+        assert_eq!(notional_q64(Q64_ONE + 1, u64::MAX - 1, false), Ok(u64::MAX - 1));
+        assert_eq!(notional_q64(Q64_ONE + 1, u64::MAX - 1, true), Ok(u64::MAX));
+    }
+
+    #[test]
+    fn q64_whole_prices_match_integer_math() {
+        for (price, qty) in [(1u64, 1u64), (10_000, 7), (12_345, 11), (u32::MAX as u64, u32::MAX as u64)] {
+            assert_eq!(notional_q64(to_q64(price), qty, false), Ok(price * qty));
+            assert_eq!(notional_q64(to_q64(price), qty, true), Ok(price * qty));
+        }
+        for (bid_price, ask_price, bid_qty, ask_qty, filled, fee_bps) in [
+            (12_000u64, 10_000u64, 10u64, 4u64, 0u64, 100u16),
+            (10_001, 9_999, 3, 3, 0, 30),
+            (12_345, 6_789, 11, 20, 5, 1_000),
+            (1, 1, 1, 1, 0, 1),
+        ] {
+            let integer = compute_fill(
+                &terms(bid_price, bid_qty, filled),
+                &terms(ask_price, ask_qty, 0),
+                fee_bps,
+            )
+            .unwrap();
+            let fixed = compute_fill_q64(
+                &terms_q64(to_q64(bid_price), bid_qty, filled),
+                bid_price * (bid_qty - filled),
+                &terms_q64(to_q64(ask_price), ask_qty, 0),
+                fee_bps,
+            )
+            .unwrap();
+            assert_eq!(fixed, integer);
+            assert_eq!(check_cross_q64(to_q64(bid_price), to_q64(ask_price), 500), check_cross(bid_price, ask_price, 500));
+        }
+    }
+
+    #[test]
+    fn q64_cross_check() {
+        // Sub-atom prices cross and slip like any others
+        assert_eq!(check_cross_q64(Q64_ONE / 3, Q64_ONE / 2, 0), CrossCheck::NotCrossed);
+        assert_eq!(check_cross_q64(Q64_ONE / 2, Q64_ONE / 3, 0), CrossCheck::Crossed { spread: 0 });
+        // (1/2 - 1/3) / (1/2) = 3333 bps
+        assert_eq!(
+            check_cross_q64(Q64_ONE / 2, Q64_ONE / 3, 3_000),
+            CrossCheck::SlippageExceeded { slippage_bps: 3_333 }
+        );
+        assert_eq!(
+            check_cross_q64(u128::MAX, u128::MAX / 2, 4_000),
+            CrossCheck::SlippageExceeded { slippage_bps: 5_000 }
+        );
+        assert_eq!(
+            check_cross_q64(u128::MAX, u128::MAX / 2, 5_000),
+            CrossCheck::Crossed { spread: 1 << 63 }
+        );
+        assert_eq!(check_cross_q64(1, 1, 1), CrossCheck::Crossed { spread: 0 });
+    }
+
+    #[test]
+    fn q64_fill_rounds_seller_down_and_refunds_the_buyer() {
+        // 0.3 atoms per unit: 7 units escrow ceil(2.1) = 3
+        let price = Q64_ONE * 3 / 10;
+        let escrow = escrow_q64(price, 7).unwrap();
+        assert_eq!(escrow, 3);
+
+        // 3 units: seller gets floor(0.9) = 0; 4 left still need ceil(1.2) = 2
+        let fill = compute_fill_q64(&terms_q64(price, 7, 0), escrow, &terms_q64(price, 3, 0), 0).unwrap();
+        assert_eq!(fill.fill_quantity, 3);
+        assert_eq!(fill.fee.gross, 0);
+        assert_eq!(fill.total_debit, 1);
+        assert_eq!(fill.buyer_refund, 1);
+        assert_eq!(fill.fill_price, 0);
+        assert_eq!(fill.fill_price_q64, price);
+
+        // The last 4 release everything that's left
+        let fill = compute_fill_q64(&terms_q64(price, 7, 3), 2, &terms_q64(price, 4, 0), 0).unwrap();
+        assert!(fill.bid_complete);
+        assert_eq!(fill.fee.gross, 1);
+        assert_eq!(fill.total_debit, 2);
+        assert_eq!(fill.buyer_refund, 1);
+
+        // An escrow short of the remainder's needs is rejected
+        assert_eq!(
+            compute_fill_q64(&terms_q64(price, 7, 0), 1, &terms_q64(price, 3, 0), 0),
+            Err(CoreError::MathOverflow)
+        );
+        // As is a pair that doesn't cross
+        assert_eq!(
+            compute_fill_q64(&terms_q64(price, 7, 0), escrow, &terms_q64(price + 1, 7, 0), 0),
+            Err(CoreError::MathOverflow)
+        );
+    }
+
+    #[test]
+    fn q64_escrow_is_conserved_over_many_partial_fills() {
+        // Tiny deterministic LCG so the crate stays dependency-free
+        let mut seed: u64 = 0x5eed;
+        let mut next = |bound: u64| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            seed.rotate_right(17) % bound
+        };
+
+        for (bid_price, quantity) in [
+            (Q64_ONE * 3 / 10 + 7, 1_000_003u64), // 0.3 atoms
+            (Q64_ONE / 1_000_000_007, 9_999_999_999), // ~1e-9 atoms
+            (1, u64::MAX),                        // the smallest price
+            (to_q64(4_000) + Q64_ONE / 3, 250_000), // 4000.33 atoms
+        ] {
+            let initial_escrow = escrow_q64(bid_price, quantity).unwrap();
+            let mut bid = terms_q64(bid_price, quantity, 0);
+            let mut escrow = initial_escrow;
+            let (mut paid_out, mut fills) = (0u64, 0);
+
+            while bid.remaining() > 0 {
+                // Makers at or below the bid, alternating slivers with up to
+                // half the remainder
+                let ask_price = bid_price - (bid_price / 1_000) * (next(1_000) as u128);
+                let size = if fills % 2 == 0 {
+                    1 + next(1_000)
+                } else {
+                    1 + next(bid.remaining() / 2 + 1)
+                };
+                let ask = terms_q64(ask_price, size.min(bid.remaining()), 0);
+                let fee_bps = next(200) as u16;
+
+                let fill = compute_fill_q64(&bid, escrow, &ask, fee_bps).unwrap();
+                assert_eq!(
+                    fill.total_debit,
+                    fill.fee.net + fill.fee.fee + fill.fee.dust + fill.buyer_refund
+                );
+                assert_eq!(fill.fee.gross, notional_q64(ask_price, fill.fill_quantity, false).unwrap());
+
+                escrow -= fill.total_debit;
+                paid_out += fill.total_debit;
+                bid.filled_quantity = fill.bid_filled_after;
+                assert!(escrow >= escrow_q64(bid_price, bid.remaining()).unwrap());
+                fills += 1;
+            }
+            assert!(fills > 1);
+            assert_eq!(escrow, 0);
+            assert_eq!(paid_out, initial_escrow);
+        }
+    }
+
+    #[test]
+    fn crank_reward_grows_with_age_up_to_the_cap() {
+        assert_eq!(crank_reward(1_000, 10, 5_000, 0), 1_000);
+        assert_eq!(crank_reward(1_000, 10, 5_000, 200), 3_000);
+        assert_eq!(crank_reward(1_000, 10, 5_000, 1_000), 5_000);
+        assert_eq!(crank_reward(1_000, 10, 5_000, u64::MAX), 5_000);
+        // Flat when per_slot is 0; nothing when unconfigured
+        assert_eq!(crank_reward(1_000, 0, 0, 1_000), 1_000);
+        assert_eq!(crank_reward(0, 0, 0, 1_000), 0);
+    }
+
+    #[test]
+    fn price_band_includes_its_boundary() {
+        // 5% of 10_000 is 500 either way
+        assert!(within_band(10_500, 10_000, 500));
+        assert!(within_band(9_500, 10_000, 500));
+        assert!(!within_band(10_501, 10_000, 500));
+        assert!(!within_band(9_499, 10_000, 500));
+        // Off without a band or before the first trade
+        assert!(within_band(u64::MAX, 10_000, 0));
+        assert!(within_band(u64::MAX, 0, 500));
+        // No overflow at the extremes
+        assert!(within_band(u64::MAX, u64::MAX, 1));
+        assert!(!within_band(0, u64::MAX, 9_999));
+    }
+
+    #[test]
+    fn twap_accumulator_weights_each_price_by_how_long_it_stood() {
+        // (timestamp, price) of each fill; the accumulator advances with the
+        // previous fill's price before the new one is written
+        let fills = [(1_000, 100), (1_010, 200), (1_040, 150), (1_040, 160), (5_040, 90)];
+        let (mut cumulative, mut last_price, mut last_ts) = (0u128, 0u64, 0i64);
+        let mut snapshots = [(0u128, 0i64); 5];
+        for (i, &(ts, price)) in fills.iter().enumerate() {
+            cumulative = accumulate_price(cumulative, last_price, last_ts, ts).unwrap();
+            last_price = price;
+            last_ts = ts;
+            snapshots[i] = (cumulative, ts);
+        }
+
+        // The first fill has no prior timestamp; the two fills at 1_040 add
+        // nothing between them; the 4_000s gap weighs 160 throughout
+        let expected = 100 * 10 + 200 * 30 + 160 * 4_000;
+        assert_eq!(snapshots[0].0, 0);
+        assert_eq!(snapshots[2].0, snapshots[3].0);
+        assert_eq!(cumulative, expected);
+        assert_eq!(snapshots[4].0 - snapshots[1].0, 200 * 30 + 160 * 4_000);
+
+        assert_eq!(time_weighted_average(snapshots[0], snapshots[4]), Some((expected / 4_040) as u64));
+        assert_eq!(time_weighted_average(snapshots[1], snapshots[2]), Some(200));
+        // An empty window has no average
+        assert_eq!(time_weighted_average(snapshots[2], snapshots[3]), None);
+        assert_eq!(time_weighted_average(snapshots[4], snapshots[0]), None);
+    }
+
+    #[test]
+    fn twap_accumulator_overflow_is_an_error() {
+        assert_eq!(accumulate_price(u128::MAX, 1, 1, 2), Err(CoreError::MathOverflow));
+        // The largest single step still fits
+        assert!(accumulate_price(0, u64::MAX, 1, i64::MAX).is_ok());
+    }
+}
+
+/// Property tests: the settlement invariants over random inputs, up to
+/// u64::MAX, rather than the hand-picked cases above.
+#[cfg(test)]
+mod properties {
+    extern crate std;
+
+    use super::*;
+    use proptest::prelude::*;
+
+    /// A bid and an ask that cross, with room left on both.
+    fn crossing_pair() -> impl Strategy<Value = (OrderTerms, OrderTerms)> {
+        (1..=u64::MAX, 1..=u64::MAX, 1..=u64::MAX)
+            .prop_flat_map(|(ask_price, bid_quantity, ask_quantity)| {
+                (
+                    ask_price..=u64::MAX,
+                    Just(ask_price),
+                    Just(bid_quantity),
+                    0..bid_quantity,
+                    Just(ask_quantity),
+                    0..ask_quantity,
+                )
+            })
+            .prop_map(|(bid_price, ask_price, bid_quantity, bid_filled, ask_quantity, ask_filled)| {
+                (
+                    OrderTerms { price: bid_price, quantity: bid_quantity, filled_quantity: bid_filled },
+                    OrderTerms { price: ask_price, quantity: ask_quantity, filled_quantity: ask_filled },
+                )
+            })
+    }
+
+    /// The same, scaled down so that whole fills don't overflow.
+    fn small_crossing_pair() -> impl Strategy<Value = (OrderTerms, OrderTerms)> {
+        (1..=1_000_000u64, 0..=1_000_000u64, 1..=1_000_000u64, 1..=1_000_000u64).prop_map(
+            |(ask_price, improvement, bid_quantity, ask_quantity)| {
+                (
+                    OrderTerms { price: ask_price + improvement, quantity: bid_quantity, filled_quantity: 0 },
+                    OrderTerms { price: ask_price, quantity: ask_quantity, filled_quantity: 0 },
+                )
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn any_inputs_settle_or_fail_without_panicking(
+            bid_price in any::<u64>(),
+            bid_quantity in any::<u64>(),
+            bid_filled in any::<u64>(),
+            ask_price in any::<u64>(),
+            ask_quantity in any::<u64>(),
+            ask_filled in any::<u64>(),
+            fee_bps in any::<u16>(),
+            matcher_fee_bps in any::<u16>(),
+            max_slippage_bps in any::<u16>(),
+            cap in any::<u64>(),
+        ) {
+            let bid = OrderTerms { price: bid_price, quantity: bid_quantity, filled_quantity: bid_filled };
+            let ask = OrderTerms { price: ask_price, quantity: ask_quantity, filled_quantity: ask_filled };
+            let _ = check_cross(bid_price, ask_price, max_slippage_bps);
+            if let Ok(fill) = compute_fill_up_to(&bid, &ask, fee_bps, cap) {
+                let _ = fill.fee.pay_matcher(matcher_fee_bps);
+                prop_assert!(fill.check(bid_quantity.max(bid_filled), ask_quantity.max(ask_filled)).is_ok());
+            }
+        }
+
+        #[test]
+        fn a_fill_conserves_the_escrow_debit(
+            (bid, ask) in crossing_pair(),
+            fee_bps in 0..=10_000u16,
+            matcher_fee_bps in 0..=10_000u16,
+            cap in 1..=u64::MAX,
+        ) {
+            // Overflow of the notional is the only way a crossing pair fails
+            let Ok(mut fill) = compute_fill_up_to(&bid, &ask, fee_bps, cap) else {
+                return Ok(());
+            };
+            if let Ok(with_matcher) = fill.fee.pay_matcher(matcher_fee_bps) {
+                fill.fee = with_matcher;
+            }
+            prop_assert_eq!(fill.check(bid.quantity, ask.quantity), Ok(()));
+
+            let fee = fill.fee;
+            prop_assert_eq!(fill.total_debit as u128, fee.gross as u128 + fill.buyer_refund as u128);
+            prop_assert_eq!(
+                fee.gross as u128,
+                fee.net as u128 + fee.fee as u128 + fee.dust as u128 + fee.matcher_fee as u128
+            );
+            prop_assert_eq!(fee.gross as u128, ask.price as u128 * fill.fill_quantity as u128);
+            prop_assert_eq!(
+                fill.buyer_refund as u128,
+                (bid.price - ask.price) as u128 * fill.fill_quantity as u128
+            );
+        }
+
+        #[test]
+        fn a_fill_never_exceeds_either_remainder_or_the_cap(
+            (bid, ask) in crossing_pair(),
+            cap in 1..=u64::MAX,
+        ) {
+            let Ok(fill) = compute_fill_up_to(&bid, &ask, 0, cap) else {
+                return Ok(());
+            };
+            prop_assert!(fill.fill_quantity > 0);
+            prop_assert!(fill.fill_quantity <= bid.remaining().min(ask.remaining()).min(cap));
+            prop_assert!(fill.bid_filled_after <= bid.quantity);
+            prop_assert!(fill.ask_filled_after <= ask.quantity);
+            // One side always completes unless the cap stopped the fill
+            prop_assert!(fill.bid_complete || fill.ask_complete || fill.fill_quantity == cap);
+        }
+
+        #[test]
+        fn repeated_fills_are_monotone_and_conserve_the_escrow(
+            (mut bid, mut ask) in small_crossing_pair(),
+            caps in proptest::collection::vec(1..=2_000_000u64, 1..16),
+            fee_bps in 0..=10_000u16,
+        ) {
+            let escrow = bid.price * bid.quantity;
+            let (mut debited, mut filled) = (0u64, 0u64);
+            for cap in caps {
+                if bid.remaining() == 0 || ask.remaining() == 0 {
+                    break;
+                }
+                let fill = compute_fill_up_to(&bid, &ask, fee_bps, cap).unwrap();
+                prop_assert_eq!(fill.check(bid.quantity, ask.quantity), Ok(()));
+                // Filled quantities only grow
+                prop_assert!(fill.bid_filled_after > bid.filled_quantity);
+                prop_assert!(fill.ask_filled_after > ask.filled_quantity);
+                prop_assert_eq!(fill.bid_complete, fill.bid_filled_after == bid.quantity);
+                prop_assert_eq!(fill.ask_complete, fill.ask_filled_after == ask.quantity);
+                bid.filled_quantity = fill.bid_filled_after;
+                ask.filled_quantity = fill.ask_filled_after;
+                debited += fill.total_debit;
+                filled += fill.fill_quantity;
+            }
+            // A completed order stays complete: filling it again moves nothing
+            let again = compute_fill_up_to(&bid, &ask, fee_bps, u64::MAX).unwrap();
+            if bid.remaining() == 0 || ask.remaining() == 0 {
+                prop_assert_eq!(again.fill_quantity, 0);
+                prop_assert_eq!(again.total_debit, 0);
+                prop_assert!(bid.remaining() > 0 || again.bid_complete);
+                prop_assert!(ask.remaining() > 0 || again.ask_complete);
+            }
+            // The bid is debited exactly its price for every unit filled,
+            // and never more than it escrowed
+            prop_assert_eq!(filled, bid.filled_quantity);
+            prop_assert_eq!(debited, bid.price * filled);
+            prop_assert!(debited <= escrow);
+        }
+
+        #[test]
+        fn q64_fills_keep_the_escrow_the_remainder_needs(
+            ask_price_q64 in Q64_ONE / 1_000..Q64_ONE * 1_000,
+            improvement in 0..Q64_ONE * 10,
+            quantity in 1..=100_000u64,
+            caps in proptest::collection::vec(1..=20_000u64, 1..32),
+        ) {
+            let bid_price_q64 = ask_price_q64 + improvement;
+            let mut bid = OrderTermsQ64 { price_q64: bid_price_q64, quantity, filled_quantity: 0 };
+            let ask = OrderTermsQ64 { price_q64: ask_price_q64, quantity: u64::MAX, filled_quantity: 0 };
+            let initial = escrow_q64(bid_price_q64, quantity).unwrap();
+            let (mut escrow, mut paid_out) = (initial, 0u64);
+            for cap in caps {
+                if bid.remaining() == 0 {
+                    break;
+                }
+                let fill = compute_fill_q64_up_to(&bid, escrow, &ask, 0, cap).unwrap();
+                prop_assert_eq!(fill.check(bid.quantity, ask.quantity), Ok(()));
+                escrow -= fill.total_debit;
+                paid_out += fill.total_debit;
+                bid.filled_quantity = fill.bid_filled_after;
+                prop_assert!(escrow >= escrow_q64(bid_price_q64, bid.remaining()).unwrap());
+            }
+            prop_assert_eq!(escrow + paid_out, initial);
+            if bid.remaining() == 0 {
+                prop_assert_eq!(escrow, 0);
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Prints the instructions the `client` module builds for a fixed set of
+//! keys and orders, as JSON. tests/client-instructions.ts rebuilds each one
+//! with the TypeScript client and checks they agree byte for byte.
+//!
+//!     cargo run -q -p order-matching-engine --features client --example client_instructions
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use order_matching_engine::client::{self, MatchExtras};
+use order_matching_engine::instruction;
+use order_matching_engine::matching::MatchOrdersParams;
+use order_matching_engine::state::{Market, Order, Side};
+
+const MARKET_NAME: &str = "CLIENT/MOCK";
+const REGISTRY_INDEX: u64 = 9;
+
+fn key(byte: u8) -> Pubkey {
+    Pubkey::new_from_array([byte; 32])
+}
+
+fn zeroed<T: AccountDeserialize>(len: usize) -> T {
+    T::try_deserialize_unchecked(&mut &vec![0; len][..]).expect("zeroed account")
+}
+
+fn order(owner: Pubkey, market: Pubkey, order_id: u64, side: Side) -> Order {
+    let mut order: Order = zeroed(Order::LEN);
+    order.owner = owner;
+    order.market = market;
+    order.order_id = order_id;
+    order.side = side;
+    order
+}
+
+fn to_json(name: &str, ix: &Instruction) -> String {
+    let accounts: Vec<String> = ix
+        .accounts
+        .iter()
+        .map(|meta| {
+            format!(
+                r#"{{"pubkey":"{}","isSigner":{},"isWritable":{}}}"#,
+                meta.pubkey, meta.is_signer, meta.is_writable
+            )
+        })
+        .collect();
+    let data: String = ix.data.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!(
+        r#"{{"name":"{name}","programId":"{}","data":"{data}","accounts":[{}]}}"#,
+        ix.program_id,
+        accounts.join(",")
+    )
+}
+
+fn main() {
+    let (authority, buyer, seller, matcher) = (key(1), key(2), key(3), key(4));
+    let market_key = client::find_market_address(&authority, MARKET_NAME).0;
+    let mut market: Market = zeroed(Market::LEN);
+    market.fee_recipient = key(5);
+    market.bid_book = key(9);
+    market.ask_book = key(10);
+
+    // A bid funded from the buyer's trading balance, paid for by a third
+    // party and indexed in the book; an ask with its own beneficiary and
+    // referrer, tracked in the seller's OpenOrders
+    let mut bid = order(buyer, market_key, 7, Side::Buy);
+    bid.funded_from_balance = true;
+    bid.counted_in_stats = true;
+    bid.in_book = true;
+    bid.funder = key(6);
+    let mut ask = order(seller, market_key, 8, Side::Sell);
+    ask.tracked_in_open_orders = true;
+    ask.beneficiary = key(7);
+    ask.referrer = key(8);
+    ask.status = order_matching_engine::state::OrderStatus::Filled;
+    ask.subsidized = true;
+
+    let bid_key = client::find_order_address(&market_key, bid.order_id).0;
+    let ask_key = client::find_order_address(&market_key, ask.order_id).0;
+    let extras = MatchExtras {
+        fee_config: true,
+        matcher_seat: true,
+        bid_seat: true,
+        trade_history: true,
+        ..MatchExtras::default()
+    };
+    let instructions = [
+        (
+            "initializeMarket",
+            client::initialize_market_ix(
+                &authority,
+                REGISTRY_INDEX,
+                instruction::InitializeMarket {
+                    market_name: MARKET_NAME.to_string(),
+                    taker_only_window_secs: 30,
+                    tick_size: 10,
+                    lot_size: 2,
+                    min_order_quantity: 4,
+                    matcher_fee_bps: 25,
+                    permissioned: false,
+                },
+            ),
+        ),
+        (
+            "placeOrder",
+            client::place_order_ix(
+                &buyer,
+                (&market_key, &market),
+                instruction::PlaceOrder {
+                    side: Side::Buy,
+                    price: 1_000,
+                    quantity: 10,
+                    order_id: bid.order_id,
+                    expires_at: 0,
+                },
+            ),
+        ),
+        (
+            "matchOrders",
+            client::match_orders_ix(
+                &matcher,
+                (&market_key, &market),
+                (&bid_key, &bid),
+                (&ask_key, &ask),
+                instruction::MatchOrders {
+                    max_slippage_bps: 50,
+                    min_expected_bid_remaining: 10,
+                    min_expected_ask_remaining: 10,
+                },
+                extras,
+            ),
+        ),
+        (
+            "matchOrdersV2",
+            client::match_orders_v2_ix(
+                &matcher,
+                (&market_key, &market),
+                (&bid_key, &bid),
+                (&ask_key, &ask),
+                instruction::MatchOrdersV2 {
+                    params: MatchOrdersParams {
+                        max_slippage_bps: 50,
+                        min_expected_bid_remaining: 10,
+                        min_expected_ask_remaining: 10,
+                        max_fill_quantity: Some(6),
+                        min_fill_quantity: 1,
+                    },
+                },
+                extras,
+            ),
+        ),
+        ("cancelOrder", client::cancel_order_ix((&market_key, &market), &bid, 3)),
+        ("closeOrder", client::close_order_ix(&market_key, &ask)),
+    ];
+
+    let lines: Vec<String> = instructions
+        .iter()
+        .map(|(name, ix)| to_json(name, ix))
+        .collect();
+    println!("[{}]", lines.join(",\n"));
+}
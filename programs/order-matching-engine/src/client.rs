@@ -0,0 +1,317 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use crate::state::{Market, Order};
+use crate::{accounts, instruction};
+
+// ─── Off-chain Client ─────────────────────────────────────────────────────────
+//
+// PDA derivations and instruction builders for Rust integrators, so a bot
+// doesn't hand-roll discriminators and seeds. Built with the `client`
+// feature, which implies `no-entrypoint`.
+//
+// Builders take the Anchor-generated argument structs (`instruction::*`)
+// and fill in every account: required ones from the arguments, optional
+// ones from the state of the order they act on. Optional accounts that
+// depend only on what exists on chain are left out unless asked for; for
+// anything else build `accounts::*` directly.
+
+pub fn find_market_address(authority: &Pubkey, name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"market", authority.as_ref(), name.as_bytes()], &crate::ID)
+}
+
+pub fn find_order_address(market: &Pubkey, order_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"order", market.as_ref(), &order_id.to_le_bytes()], &crate::ID)
+}
+
+/// The escrow vault holding order `order_id`'s lamport escrow, whatever
+/// seeds the order itself was created under.
+pub fn find_escrow_address(market: &Pubkey, order_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[Order::ESCROW_SEED, market.as_ref(), &order_id.to_le_bytes()], &crate::ID)
+}
+
+/// An order placed with `place_order_v2`, seeded by its owner's nonce.
+pub fn find_owner_order_address(market: &Pubkey, owner: &Pubkey, client_nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"order", market.as_ref(), owner.as_ref(), &client_nonce.to_le_bytes()],
+        &crate::ID,
+    )
+}
+
+pub fn find_config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config"], &crate::ID)
+}
+
+pub fn find_registry_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"registry"], &crate::ID)
+}
+
+/// The registry's listing number `registry_index`.
+pub fn find_listing_address(registry_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"listing", &registry_index.to_le_bytes()], &crate::ID)
+}
+
+pub fn find_event_authority_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"__event_authority"], &crate::ID)
+}
+
+/// A per-market PDA: `fee_config`, `fee_vault`, `rent_subsidy`.
+pub fn find_market_pda(seed: &[u8], market: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[seed, market.as_ref()], &crate::ID)
+}
+
+/// A per-owner PDA of a market: `balance`, `user_stats`, `open_orders`,
+/// `seat`, `matcher`, `matcher_seat`.
+pub fn find_owner_pda(seed: &[u8], market: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[seed, market.as_ref(), owner.as_ref()], &crate::ID)
+}
+
+impl Market {
+    /// Decode fetched account data, discriminator included.
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self> {
+        Self::try_deserialize(&mut &data[..])
+    }
+}
+
+impl Order {
+    /// Decode fetched account data, discriminator included.
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self> {
+        Self::try_deserialize(&mut &data[..])
+    }
+}
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// A lamport-quoted market at `find_market_address(authority, market_name)`,
+/// listed at `registry_index` (the registry's current `count`).
+pub fn initialize_market_ix(
+    authority: &Pubkey,
+    registry_index: u64,
+    args: instruction::InitializeMarket,
+) -> Instruction {
+    let market = find_market_address(authority, &args.market_name).0;
+    build(
+        accounts::InitializeMarket {
+            authority: *authority,
+            market,
+            fee_vault: find_market_pda(b"fee_vault", &market).0,
+            quote_mint: None,
+            quote_vault: None,
+            base_mint: None,
+            base_vault: None,
+            token_program: None,
+            config: find_config_address().0,
+            registry: find_registry_address().0,
+            listing: find_listing_address(registry_index).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// A wallet-funded order at `find_order_address(market, args.order_id)`,
+/// indexed in its side's book when the market has book sides.
+pub fn place_order_ix(
+    owner: &Pubkey,
+    (market_key, market): (&Pubkey, &Market),
+    args: instruction::PlaceOrder,
+) -> Instruction {
+    build(
+        accounts::PlaceOrder {
+            owner: *owner,
+            market: *market_key,
+            order: find_order_address(market_key, args.order_id).0,
+            escrow_vault: find_escrow_address(market_key, args.order_id).0,
+            trading_balance: None,
+            trader_seat: None,
+            user_stats: None,
+            open_orders: None,
+            book_side: market
+                .has_book_sides()
+                .then(|| market.book_side(&args.side)),
+            maker_book_side: None,
+            config: find_config_address().0,
+            beneficiary: None,
+            referrer: None,
+            funder: None,
+            rent_subsidy_vault: None,
+            quote_vault: None,
+            owner_quote_account: None,
+            base_vault: None,
+            owner_base_account: None,
+            token_program: None,
+            fee_config: None,
+            treasury: None,
+            fee_vault: None,
+            user_balance: None,
+            system_program: system_program::ID,
+            event_authority: find_event_authority_address().0,
+            program: crate::ID,
+        },
+        args,
+    )
+}
+
+/// Optional match_orders accounts that depend on what exists on chain
+/// rather than on the two orders: pass each one that does.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MatchExtras {
+    pub fee_config: bool,
+    pub matcher_stats: bool,
+    pub matcher_seat: bool,
+    pub bid_seat: bool,
+    pub ask_seat: bool,
+    pub trade_history: bool,
+}
+
+/// Match `bid` against `ask`. The accounts each order needs (trading
+/// balance, stats, open orders, book side, beneficiary, funder, user
+/// balance) follow from its flags; the market's fee vault is always
+/// passed. On a matcher_restricted market set `extras.matcher_seat`.
+/// Lamport settlement only.
+pub fn match_orders_ix(
+    matcher: &Pubkey,
+    market: (&Pubkey, &Market),
+    bid: (&Pubkey, &Order),
+    ask: (&Pubkey, &Order),
+    args: instruction::MatchOrders,
+    extras: MatchExtras,
+) -> Instruction {
+    build(match_accounts(matcher, market, bid, ask, extras), args)
+}
+
+/// match_orders_ix for match_orders_v2, whose MatchOrdersParams add fill
+/// size guards.
+pub fn match_orders_v2_ix(
+    matcher: &Pubkey,
+    market: (&Pubkey, &Market),
+    bid: (&Pubkey, &Order),
+    ask: (&Pubkey, &Order),
+    args: instruction::MatchOrdersV2,
+    extras: MatchExtras,
+) -> Instruction {
+    build(match_accounts(matcher, market, bid, ask, extras), args)
+}
+
+fn match_accounts(
+    matcher: &Pubkey,
+    (market_key, market): (&Pubkey, &Market),
+    (bid_key, bid): (&Pubkey, &Order),
+    (ask_key, ask): (&Pubkey, &Order),
+    extras: MatchExtras,
+) -> accounts::MatchOrders {
+    let per_owner = |seed: &[u8], owner: &Pubkey| find_owner_pda(seed, market_key, owner).0;
+    accounts::MatchOrders {
+        matcher: *matcher,
+        market: *market_key,
+        bid_order: *bid_key,
+        ask_order: *ask_key,
+        bid_escrow: find_escrow_address(market_key, bid.order_id).0,
+        bid_owner: bid.owner,
+        ask_owner: ask.owner,
+        fee_config: extras
+            .fee_config
+            .then(|| find_market_pda(b"fee_config", market_key).0),
+        treasury: market.fee_recipient,
+        fee_vault: Some(find_market_pda(b"fee_vault", market_key).0),
+        bid_trading_balance: bid.funded_from_balance.then(|| per_owner(b"balance", &bid.owner)),
+        bid_user_stats: bid.counted_in_stats.then(|| per_owner(b"user_stats", &bid.owner)),
+        ask_user_stats: ask.counted_in_stats.then(|| per_owner(b"user_stats", &ask.owner)),
+        bid_open_orders: bid
+            .tracked_in_open_orders
+            .then(|| per_owner(b"open_orders", &bid.owner)),
+        ask_open_orders: ask
+            .tracked_in_open_orders
+            .then(|| per_owner(b"open_orders", &ask.owner)),
+        bids: bid.in_book.then_some(market.bid_book),
+        asks: ask.in_book.then_some(market.ask_book),
+        config: find_config_address().0,
+        matcher_stats: extras.matcher_stats.then(|| per_owner(b"matcher", matcher)),
+        matcher_seat: extras.matcher_seat.then(|| per_owner(b"matcher_seat", matcher)),
+        bid_seat: extras.bid_seat.then(|| per_owner(b"seat", &bid.owner)),
+        ask_seat: extras.ask_seat.then(|| per_owner(b"seat", &ask.owner)),
+        ask_beneficiary: (ask.proceeds_recipient() != ask.owner).then(|| ask.proceeds_recipient()),
+        bid_funder: (bid.refund_recipient() != bid.owner).then(|| bid.refund_recipient()),
+        ask_referrer: (ask.referrer != Pubkey::default()).then_some(ask.referrer),
+        quote_vault: None,
+        token_program: None,
+        seller_quote_account: None,
+        buyer_quote_account: None,
+        base_vault: None,
+        buyer_base_account: None,
+        fill_receipt: None,
+        trade_history: extras
+            .trade_history
+            .then(|| find_market_pda(b"trades", market_key).0),
+        bid_user_balance: bid.settles_to_balance.then(|| per_owner(b"user_balance", &bid.owner)),
+        ask_user_balance: ask.settles_to_balance.then(|| per_owner(b"user_balance", &ask.owner)),
+        system_program: system_program::ID,
+        event_authority: find_event_authority_address().0,
+        program: crate::ID,
+    }
+}
+
+/// Cancel `order` (placed with an order id) with a full refund to its
+/// owner, or to its funder when a third party paid the escrow. Lamport
+/// escrow only.
+pub fn cancel_order_ix(
+    (market_key, market): (&Pubkey, &Market),
+    order: &Order,
+    expected_update_count: u64,
+) -> Instruction {
+    let per_owner = |seed: &[u8]| find_owner_pda(seed, market_key, &order.owner).0;
+    build(
+        accounts::CancelOrder {
+            owner: order.owner,
+            market: *market_key,
+            order: find_order_address(market_key, order.order_id).0,
+            escrow_vault: find_escrow_address(market_key, order.order_id).0,
+            trading_balance: order.funded_from_balance.then(|| per_owner(b"balance")),
+            user_stats: order.counted_in_stats.then(|| per_owner(b"user_stats")),
+            open_orders: order.tracked_in_open_orders.then(|| per_owner(b"open_orders")),
+            book_side: order.in_book.then(|| market.book_side(&order.side)),
+            funder: (order.refund_recipient() != order.owner).then(|| order.refund_recipient()),
+            quote_vault: None,
+            token_program: None,
+            owner_quote_account: None,
+            base_vault: None,
+            owner_base_account: None,
+            system_program: system_program::ID,
+            event_authority: find_event_authority_address().0,
+            program: crate::ID,
+        },
+        instruction::CancelOrder {
+            _order_id: order.order_id,
+            expected_update_count,
+        },
+    )
+}
+
+/// Close a filled or cancelled `order` and its escrow vault, returning
+/// their rent to the owner (the order's to the market's rent subsidy vault
+/// when that paid it).
+pub fn close_order_ix(market: &Pubkey, order: &Order) -> Instruction {
+    build(
+        accounts::CloseOrder {
+            owner: order.owner,
+            market: *market,
+            order: find_order_address(market, order.order_id).0,
+            escrow_vault: find_escrow_address(market, order.order_id).0,
+            rent_subsidy_vault: order
+                .subsidized
+                .then(|| find_market_pda(b"rent_subsidy", market).0),
+            system_program: system_program::ID,
+            event_authority: find_event_authority_address().0,
+            program: crate::ID,
+        },
+        instruction::CloseOrder {
+            _order_id: order.order_id,
+        },
+    )
+}
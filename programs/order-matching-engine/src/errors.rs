@@ -43,6 +43,8 @@ pub enum MatchingEngineError {
     // ── Emergency Pause (Kill Switch) ─────────────────────────────────────────
     #[msg("Market is paused — all new orders and matches are halted")]
     MarketPaused,
+    #[msg("New orders on this side are paused — resting orders still match and cancel")]
+    SidePaused,
 
     // ── Robustness ────────────────────────────────────────────────────────────
     #[msg("Order is currently locked in an active match — retry after confirmation")]
@@ -57,4 +59,371 @@ pub enum MatchingEngineError {
     FeeBpsTooHigh,
     #[msg("Treasury account does not match fee config")]
     TreasuryMismatch,
+
+    // ── Parameter Timelock ────────────────────────────────────────────────────
+    #[msg("Market parameters are timelocked — stage the change instead")]
+    ParamsTimelocked,
+    #[msg("Timelock must be zero or a positive number of seconds")]
+    InvalidTimelock,
+    #[msg("Staged effective time is earlier than the market timelock allows")]
+    TimelockTooShort,
+    #[msg("Staged parameters are not yet effective")]
+    TimelockNotElapsed,
+    #[msg("Staged parameters are already effective and can no longer be discarded")]
+    StagedParamsEffective,
+
+    // ── Trading Balance ───────────────────────────────────────────────────────
+    #[msg("Amount must be greater than zero")]
+    InvalidAmount,
+    #[msg("Trading balance is too low for this withdrawal")]
+    InsufficientBalance,
+    #[msg("Order was funded from a trading balance — pass the owner's balance account")]
+    TradingBalanceRequired,
+
+    // ── Trader Seats ──────────────────────────────────────────────────────────
+    #[msg("Market only accepts resting orders from seat holders — pass the owner's seat")]
+    MakerSeatRequired,
+
+    // ── Taker-Only Window ─────────────────────────────────────────────────────
+    #[msg("Market is in its taker-only window — resting orders are not accepted yet")]
+    TakerOnlyWindow,
+
+    // ── Renounced Authority ───────────────────────────────────────────────────
+    #[msg("Market authority has been renounced — the market is immutable")]
+    AuthorityRenounced,
+    #[msg("Discard or apply the staged parameters before renouncing")]
+    StagedParamsPending,
+
+    // ── Expiry Settlement ─────────────────────────────────────────────────────
+    #[msg("Expiry must be in the future, with a non-negative oracle staleness bound")]
+    InvalidExpiry,
+    #[msg("Market has expired — trading is closed")]
+    MarketExpired,
+    #[msg("Market has not reached its expiry yet")]
+    MarketNotExpired,
+    #[msg("Settlement price is already recorded and immutable")]
+    SettlementRecorded,
+    #[msg("Market has not been settled yet")]
+    MarketNotSettled,
+    #[msg("Oracle account is not the market's settlement oracle")]
+    OracleMismatch,
+    #[msg("Oracle price is stale")]
+    OracleStale,
+    #[msg("Oracle price must be greater than zero")]
+    InvalidOraclePrice,
+
+    // ── Fee Recipient ─────────────────────────────────────────────────────────
+    #[msg("Fee recipient can't be paid directly — pass the market's fee vault")]
+    FeeVaultRequired,
+
+    // ── Optimistic Concurrency ────────────────────────────────────────────────
+    #[msg("Order changed since it was read (update_count mismatch) — refetch and retry")]
+    StaleOrderState,
+    #[msg("Order shrank below the matcher's expected remaining quantity — rebuild the match")]
+    StaleMakerState,
+
+    // ── Maker Concentration ───────────────────────────────────────────────────
+    #[msg("Order would put the owner above the market's maximum share of this side")]
+    MakerConcentrationExceeded,
+    #[msg("Pass the owner's user stats account (concentration cap or counted order)")]
+    UserStatsRequired,
+    #[msg("Maker share must be at most 10000 bps")]
+    InvalidMakerShare,
+
+    // ── Protocol Fees ─────────────────────────────────────────────────────────
+    #[msg("Protocol fee share must be at most 10000 bps")]
+    InvalidProtocolFeeShare,
+    #[msg("The protocol is paused: no new orders or matching on any market")]
+    ProtocolPaused,
+    #[msg("The protocol is not paused")]
+    ProtocolNotPaused,
+    #[msg("Fee vault does not hold enough withdrawable fees")]
+    InsufficientFees,
+
+    // ── Market Archival ───────────────────────────────────────────────────────
+    #[msg("Market is being archived — placement and matching are closed")]
+    MarketArchiving,
+    #[msg("Market is not being archived")]
+    MarketNotArchiving,
+    #[msg("Market still has open orders")]
+    OpenOrdersRemain,
+    #[msg("Withdraw the protocol's fees from the vault before closing the market")]
+    ProtocolFeesPending,
+    #[msg("archive_step expects [order, owner, trading_balance, user_stats, open_orders] per order")]
+    InvalidArchiveAccounts,
+
+    // ── Snapshots ─────────────────────────────────────────────────────────────
+    #[msg("Market was poked too recently — wait POKE_INTERVAL_SLOTS")]
+    PokeTooSoon,
+
+    // ── Probation ─────────────────────────────────────────────────────────────
+    #[msg("Order notional exceeds the probation cap for new accounts")]
+    ProbationOrderTooLarge,
+    #[msg("Open-order limit for accounts on probation reached")]
+    ProbationOpenOrderLimit,
+
+    // ── Roles ─────────────────────────────────────────────────────────────────
+    #[msg("Role holder cannot be the default pubkey — use revoke_role")]
+    InvalidRoleHolder,
+
+    // ── Emergency Cancel ──────────────────────────────────────────────────────
+    #[msg("Market is healthy — use cancel_order, or get protocol-admin approval")]
+    EmergencyCancelNotAllowed,
+
+    // ── Split / Merge ─────────────────────────────────────────────────────────
+    #[msg("Split quantity must be positive and below the order's unfilled remainder")]
+    InvalidSplitQuantity,
+    #[msg("Orders must be two different orders with the same side, price and funding")]
+    OrdersNotMergeable,
+
+    // ── Commit–Reveal ─────────────────────────────────────────────────────────
+    #[msg("Commit–reveal placement is not enabled on this market")]
+    CommitRevealDisabled,
+    #[msg("Reveal window must be positive")]
+    InvalidRevealWindow,
+    #[msg("Commitment must escrow a positive max notional")]
+    InvalidEscrowBound,
+    #[msg("Revealed order does not match the commitment hash")]
+    CommitmentMismatch,
+    #[msg("Reveal window has closed — refund the commitment instead")]
+    RevealWindowClosed,
+    #[msg("Revealed order's notional exceeds the committed escrow")]
+    EscrowBoundExceeded,
+    #[msg("Commitment can still be revealed")]
+    CommitmentNotExpired,
+
+    // ── Beneficiary ───────────────────────────────────────────────────────────
+    #[msg("Only sell orders can name a beneficiary")]
+    BeneficiaryOnlyForSells,
+    #[msg("Beneficiary account does not match the ask order's beneficiary")]
+    BeneficiaryMismatch,
+
+    // ── Third-party Funding ───────────────────────────────────────────────────
+    #[msg("Only buy orders can be funded by a third party")]
+    FunderOnlyForBuys,
+    #[msg("Refund account does not match the order's funder")]
+    FunderMismatch,
+
+    // ── Rent Subsidy ──────────────────────────────────────────────────────────
+    #[msg("Order rent was subsidized — pass the market's rent subsidy vault")]
+    RentSubsidyVaultRequired,
+
+    // ── Crank Rewards ─────────────────────────────────────────────────────────
+    #[msg("Invalid crank reward: a per-slot rate needs a cap of at least the base")]
+    InvalidCrankReward,
+
+    // ── Fixed-Point Prices ────────────────────────────────────────────────────
+    #[msg("Wrong price format: fixed-point markets take place_order_q64, integer markets place_order")]
+    PriceFormatMismatch,
+
+    #[msg("The price format can only change before the market's first order")]
+    PriceFormatLocked,
+
+    // ── Token Quotes ──────────────────────────────────────────────────────────
+    #[msg("Quote mint must be an initialized SPL token mint")]
+    InvalidQuoteMint,
+    #[msg("Token-quoted market: pass the quote vault, token program and quote token account")]
+    QuoteAccountsRequired,
+    #[msg("Quote vault does not match the market's")]
+    QuoteVaultMismatch,
+    #[msg("Token account has the wrong mint or owner")]
+    QuoteAccountMismatch,
+    #[msg("Not a valid SPL token account")]
+    InvalidTokenAccount,
+    #[msg("Token program account is not the SPL Token program")]
+    InvalidTokenProgram,
+    #[msg("Not supported with a market token vault")]
+    TokenQuoteUnsupported,
+    #[msg("Withdraw the market's quote-token fees first")]
+    QuoteFeesPending,
+
+    // ── Base Escrow ───────────────────────────────────────────────────────────
+    #[msg("Base mint must be an initialized SPL token mint")]
+    InvalidBaseMint,
+    #[msg("Base-escrowed market: pass the base vault, token program and base token account")]
+    BaseAccountsRequired,
+    #[msg("Base vault does not match the market's")]
+    BaseVaultMismatch,
+    #[msg("Order still has base tokens locked")]
+    BaseEscrowLocked,
+
+    // ── Escrow Vaults ─────────────────────────────────────────────────────────
+    #[msg("Escrow debit would leave the escrow vault below its rent-exempt minimum")]
+    EscrowBelowRent,
+    #[msg("Escrow vault does not match the order's")]
+    EscrowVaultMismatch,
+
+    // ── Order Expiry ──────────────────────────────────────────────────────────
+    #[msg("Order has not expired — it has no expiry or its expires_at is still ahead")]
+    OrderNotExpired,
+
+    // ── Time In Force ─────────────────────────────────────────────────────────
+    #[msg("Fill-or-kill order could not be filled in full")]
+    FillOrKillNotFilled,
+    #[msg("Maker accounts must come in (order, wallet, seat, escrow vault) groups, at most 8")]
+    InvalidMakerAccounts,
+    #[msg("Maker order can't be filled at placement: it needs a trading balance, token vault or stats account")]
+    MakerNotFillable,
+
+    // ── Post Only ─────────────────────────────────────────────────────────────
+    #[msg("Post-only order would cross the best price on the other side of the book")]
+    PostOnlyWouldCross,
+
+    // ── Order Modification ────────────────────────────────────────────────────
+    #[msg("A third party funded this order's escrow — it can only shrink")]
+    SponsoredEscrowIncrease,
+
+    // ── Order Size ────────────────────────────────────────────────────────────
+    #[msg("Price is not a multiple of the market's tick size (or the tick size is zero)")]
+    InvalidTickSize,
+    #[msg("Quantity is not a multiple of the market's lot size (or the lot size is zero)")]
+    InvalidLotSize,
+    #[msg("Order quantity is below the market's minimum")]
+    OrderTooSmall,
+
+    // ── Market Pause ──────────────────────────────────────────────────────────
+    #[msg("Market is not paused")]
+    MarketNotPaused,
+
+    // ── Open Orders ───────────────────────────────────────────────────────────
+    #[msg("Owner has too many open orders (OpenOrders full or max_orders_per_user reached) — cancel or fill one first")]
+    TooManyOpenOrders,
+    #[msg("Order is listed in its owner's OpenOrders — pass that account")]
+    OpenOrdersRequired,
+
+    // ── Permissioned Markets ──────────────────────────────────────────────────
+    #[msg("Market is permissioned — the owner needs a trader seat; pass it")]
+    TraderNotWhitelisted,
+
+    // ── Stop Orders ───────────────────────────────────────────────────────────
+    #[msg("Trigger price must be greater than zero")]
+    InvalidTriggerPrice,
+    #[msg("Stop order has not been triggered yet and cannot match")]
+    StopNotTriggered,
+    #[msg("Order is not a stop order waiting for its trigger")]
+    OrderNotPendingTrigger,
+    #[msg("No trade since the stop was placed has reached its trigger price")]
+    TriggerNotReached,
+
+    // ── Price Band ────────────────────────────────────────────────────────────
+    #[msg("Price is further from the last trade than the market's price band allows")]
+    PriceOutOfBand,
+
+    // ── Match Guards ──────────────────────────────────────────────────────────
+    #[msg("Fill is below the matcher's minimum (or empty) — another crank got there first")]
+    FillBelowMinimum,
+
+    // ── Batch Cancel ──────────────────────────────────────────────────────────
+    #[msg("Cancel accounts must come in (order, escrow vault) pairs")]
+    InvalidCancelAccounts,
+
+    // ── Force Cancel ──────────────────────────────────────────────────────────
+    #[msg("owner account does not match the order's owner field")]
+    OrderOwnerMismatch,
+
+    // ── Protocol Config ───────────────────────────────────────────────────────
+    #[msg("Destination is not the protocol treasury (or owned by it)")]
+    ProtocolTreasuryMismatch,
+    #[msg("Protocol admin and treasury must be set")]
+    InvalidProtocolConfig,
+
+    // ── Iceberg Orders ────────────────────────────────────────────────────────
+    #[msg("Display quantity must be positive and no more than the order quantity")]
+    InvalidDisplayQuantity,
+
+    // ── Call Auction ──────────────────────────────────────────────────────────
+    #[msg("Market is collecting a call auction; only settle_auction fills orders")]
+    AuctionInProgress,
+    #[msg("Market is not in auction mode")]
+    AuctionNotActive,
+    #[msg("The auction is still collecting orders")]
+    AuctionNotEnded,
+    #[msg("Auction end must be in the future")]
+    InvalidAuctionEnd,
+    #[msg("Clearing price must lie between the ask and bid limits of every pair")]
+    InvalidClearingPrice,
+
+    // ── Dual Orders ───────────────────────────────────────────────────────────
+    #[msg("A two-sided quote's bid must be priced below its ask")]
+    CrossedQuote,
+
+    // ── Order Book Index ──────────────────────────────────────────────────────
+    #[msg("The book side is full; wait for orders to leave")]
+    BookFull,
+    #[msg("The market indexes orders in its book sides; pass the order's side's book")]
+    BookSideRequired,
+    #[msg("Book side belongs to another market or the other side")]
+    BookSideMismatch,
+
+    // ── Settlement Invariants ─────────────────────────────────────────────────
+    #[msg("Fill would take an order past its quantity")]
+    FillExceedsQuantity,
+    #[msg("Fill amounts don't balance: escrow debit, payouts and fees disagree")]
+    UnbalancedSettlement,
+
+    // ── Versioning ────────────────────────────────────────────────────────────
+    #[msg("Market is already at the current layout version")]
+    MarketUpToDate,
+
+    // ── Matcher Seats ─────────────────────────────────────────────────────────
+    #[msg("Matching on this market is restricted to registered matchers")]
+    MatcherNotAuthorized,
+
+    // ── Referrals ─────────────────────────────────────────────────────────────
+    #[msg("Referral share must be at most 10000 bps")]
+    InvalidReferralShare,
+    #[msg("Referrer account doesn't match the order's referrer")]
+    ReferrerMismatch,
+
+    // ── Order Sweeping ────────────────────────────────────────────────────────
+    #[msg("Sweep delay can't be negative")]
+    InvalidSweepDelay,
+    #[msg("Sweeping closed orders is disabled on this market")]
+    SweepDisabled,
+    #[msg("Order hasn't been closed for the market's sweep delay yet")]
+    SweepTooEarly,
+
+    // ── Trade History ─────────────────────────────────────────────────────────
+    #[msg("Trade history belongs to another market")]
+    TradeHistoryMismatch,
+
+    // ── Market Registry ───────────────────────────────────────────────────────
+    // No longer raised: initialize_market requires both accounts. Kept so
+    // the codes after it don't shift.
+    #[msg("The registry and the market's listing must be passed together")]
+    RegistryAccountsRequired,
+    #[msg("Market is already delisted")]
+    MarketNotListed,
+
+    // ── Deferred Settlement ───────────────────────────────────────────────────
+    #[msg("Order settles to its owner's UserBalance, which must be passed")]
+    UserBalanceRequired,
+    #[msg("Deferred settlement can't be combined with this order or instruction")]
+    DeferredSettlementUnsupported,
+    #[msg("No pending lamports to claim")]
+    NothingToClaim,
+    #[msg("Funds can't be claimed into the UserBalance itself")]
+    InvalidClaimDestination,
+
+    // ── TWAP ──────────────────────────────────────────────────────────────────
+    #[msg("TWAP window must start before now and at or below the current accumulator")]
+    InvalidTwapWindow,
+
+    // ── Book Side Allocation ──────────────────────────────────────────────────
+    #[msg("The market already has its book sides")]
+    BookSidesExist,
+
+    // ── Order Upgrades ────────────────────────────────────────────────────────
+    #[msg("Order is already at the current layout version")]
+    OrderUpToDate,
+}
+
+impl From<solamatch_core::CoreError> for MatchingEngineError {
+    fn from(err: solamatch_core::CoreError) -> Self {
+        match err {
+            solamatch_core::CoreError::MathOverflow => MatchingEngineError::MathOverflow,
+            solamatch_core::CoreError::FillExceedsQuantity => MatchingEngineError::FillExceedsQuantity,
+            solamatch_core::CoreError::UnbalancedSettlement => MatchingEngineError::UnbalancedSettlement,
+        }
+    }
 }
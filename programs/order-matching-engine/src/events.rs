@@ -1,5 +1,163 @@
 use anchor_lang::prelude::*;
-use crate::state::Side;
+use crate::state::{MarketParams, OrderStatus, Role, Side, TimeInForce, TriggerDirection};
+
+// ─── State Hash Chain ─────────────────────────────────────────────────────────
+//
+// Every event that records a change to a Market carries the market's
+// event_seq and the resulting state_hash:
+//
+//   state_hash = sha256(prev_state_hash || event_seq_le || borsh(event))
+//
+// where borsh(event) is the event encoded with event_seq set and state_hash
+// zeroed. Replaying the event stream from the zero hash must reproduce
+// Market.state_hash.
+//
+// Protocol-wide events (config, pause, oracle prices) belong to no market and
+// carry no event_seq. An event about a market that has since been closed is
+// emitted with event_seq 0, outside any chain.
+
+/// Event that advances a market's state hash chain.
+pub trait ChainedEvent: AnchorSerialize {
+    fn stamp(&mut self, event_seq: u64, state_hash: [u8; 32]);
+}
+
+macro_rules! chained_events {
+    ($($event:ident),* $(,)?) => {
+        $(impl ChainedEvent for $event {
+            fn stamp(&mut self, event_seq: u64, state_hash: [u8; 32]) {
+                self.event_seq = event_seq;
+                self.state_hash = state_hash;
+            }
+        })*
+    };
+}
+
+chained_events!(
+    MarketInitializedEvent,
+    FeeConfigUpdatedEvent,
+    OrderPlacedEvent,
+    TradeExecutedEvent,
+    OrderCancelledEvent,
+    MarketPausedEvent,
+    MarketParamsStagedEvent,
+    MarketParamsAppliedEvent,
+    MarketParamsDiscardedEvent,
+    MakersRestrictedSetEvent,
+    AuthorityRenouncedEvent,
+    MarketExpiryConfiguredEvent,
+    MarketSettledEvent,
+    FeeRecipientUpdatedEvent,
+    MakerShareLimitSetEvent,
+    MarketArchivingEvent,
+    ProbationLimitsSetEvent,
+    TradeBatchEvent,
+    TradeEventModeSetEvent,
+    CommitRevealSetEvent,
+    OrderSplitEvent,
+    OrdersMergedEvent,
+    MarketSidePausedEvent,
+    CrankRewardSetEvent,
+    FixedPointPricesSetEvent,
+    OrderModifiedEvent,
+    OrderReducedEvent,
+    SessionStatsResetEvent,
+    StopOrderTriggeredEvent,
+    AuctionModeSetEvent,
+    AuctionSettledEvent,
+    MarketUpgradedEvent,
+    MatcherRestrictedSetEvent,
+    MaxOrdersPerUserSetEvent,
+    ReferralShareSetEvent,
+    SweepDelaySetEvent,
+    BeneficiarySetEvent,
+    OrderExpiryUpdatedEvent,
+    OrderCommittedEvent,
+    OrderRevealedEvent,
+    CommitmentRefundedEvent,
+    OrderClosedEvent,
+    OrderSweptEvent,
+    RoleGrantedEvent,
+    RoleRevokedEvent,
+    TraderSeatGrantedEvent,
+    TraderSeatRevokedEvent,
+    MatcherRegisteredEvent,
+    MatcherRevokedEvent,
+    FeeExemptionSetEvent,
+    BalanceDepositedEvent,
+    ArchiveStepEvent,
+    MarketClosedEvent,
+    MarketListedEvent,
+    MarketDelistedEvent,
+    FeesWithdrawnEvent,
+    ProtocolFeesWithdrawnEvent,
+    BalanceWithdrawnEvent,
+    FundsClaimedEvent,
+    RentSubsidyFundedEvent,
+    EmergencyCancelEvent,
+    OrderForceCancelledEvent,
+    OrderUpgradedEvent,
+    BookSidesCreatedEvent,
+    MarketSnapshotEvent,
+);
+
+#[event]
+pub struct MarketInitializedEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub market_name: String,
+    pub taker_only_until_ts: i64,
+    pub quote_mint: Pubkey, // default = quoted in lamports
+    pub tick_size: u64,
+    pub lot_size: u64,
+    pub min_order_quantity: u64,
+    pub matcher_fee_bps: u16,
+    pub permissioned: bool,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct FeeConfigUpdatedEvent {
+    pub market: Pubkey,
+    pub fee_bps: u16,       // Maker rate
+    pub taker_fee_bps: u16, // Taker rate (equal to fee_bps unless split by set_taker_fee)
+    pub treasury: Pubkey,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct ReferralShareSetEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub referral_share_bps: u16,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct SweepDelaySetEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub sweep_delay_secs: i64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct FeeRecipientUpdatedEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub previous_recipient: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
 
 #[event]
 pub struct OrderPlacedEvent {
@@ -8,8 +166,163 @@ pub struct OrderPlacedEvent {
     pub market: Pubkey,
     pub side: Side,
     pub price: u64,
+    pub price_q64: u128,       // Exact Q64.64 price on fixed-point markets (0 = integer `price`)
     pub quantity: u64,
+    pub fee_bps: u16,          // Fee snapshot, charged when this order sells
+    pub time_in_force: TimeInForce, // Ioc / Fok orders fill at placement and never rest
+    pub post_only: bool,
+    pub trigger_price: u64,    // Stop orders start PendingTrigger (0 = plain limit order)
+    pub trigger_direction: TriggerDirection,
+    pub client_nonce: Option<u64>, // PDA nonce of a place_order_v2 order (None = PDA from order_id)
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+/// A stop order's trigger was reached and it joined the book.
+#[event]
+pub struct StopOrderTriggeredEvent {
+    pub market: Pubkey,
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub triggered_by: Pubkey,
+    pub trigger_price: u64,
+    pub last_trade_price: u64,  // The trade that satisfied the trigger
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct OrderSplitEvent {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub order_id: u64,
+    pub new_order_id: u64,
+    pub split_quantity: u64,
+    pub escrow_moved: u64,
+    pub new_expires_at: i64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct OrdersMergedEvent {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub survivor_order_id: u64,
+    pub absorbed_order_id: u64,
+    pub quantity: u64,         // survivor's quantity after the merge
+    pub filled_quantity: u64,  // survivor's filled quantity after the merge
+    pub escrow_moved: u64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct BeneficiarySetEvent {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub order_id: u64,
+    pub beneficiary: Pubkey,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct OrderModifiedEvent {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub order_id: u64,
+    pub side: Side,
+    pub old_price: u64,
+    pub new_price: u64,
+    pub old_quantity: u64,    // Totals, filled quantity included
+    pub new_quantity: u64,
+    pub escrow_added: u64,    // BUY escrow topped up
+    pub escrow_refunded: u64, // BUY escrow returned
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+/// reduce_order shrank an order; `closed` when it removed the whole
+/// remainder (an OrderCancelledEvent precedes it).
+#[event]
+pub struct OrderReducedEvent {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub order_id: u64,
+    pub side: Side,
+    pub old_quantity: u64,    // Totals, filled quantity included
+    pub new_quantity: u64,
+    pub escrow_refunded: u64, // BUY escrow returned
+    pub closed: bool,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct OrderExpiryUpdatedEvent {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub order_id: u64,
+    pub old_expires_at: i64,  // 0 = had no expiry
+    pub new_expires_at: i64,  // 0 = no expiry
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct OrderCommittedEvent {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub commitment_id: u64,
+    pub hash: [u8; 32],
+    pub escrow_lamports: u64,
+    pub reveal_deadline: i64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct OrderRevealedEvent {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub commitment_id: u64,
+    pub order_id: u64,
+    pub refund_lamports: u64, // escrow beyond the order's own, returned with the rent
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct CommitmentRefundedEvent {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub commitment_id: u64,
+    pub refund_lamports: u64,
     pub timestamp: i64,
+    pub event_seq: u64, // 0 = the market is gone; not in its chain
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct CommitRevealSetEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub commit_reveal: bool,
+    pub reveal_window_secs: i64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
 }
 
 #[event]
@@ -19,10 +332,73 @@ pub struct TradeExecutedEvent {
     pub market: Pubkey,
     pub buyer: Pubkey,
     pub seller: Pubkey,
+    pub proceeds_to: Pubkey,   // Ask's beneficiary (the seller unless one was named)
     pub fill_price: u64,
+    pub fill_price_q64: u128,  // Exact Q64.64 fill price (whole lamports << 64 on integer markets)
     pub fill_quantity: u64,
+    pub fee_bps: u16,          // Rate applied: the ask's placement snapshot (0 when waived)
+    pub ask_is_taker: bool,    // fee_bps is the ask's taker rate (else its maker rate)
     pub fee_amount: u64,       // Protocol fee deducted from seller payment
+    pub dust_amount: u64,      // Rounding dust kept back from the seller (→ fee recipient)
+    pub fee_paid_to: Pubkey,   // Fee recipient, or the fee vault on fallback
+    pub protocol_fee_amount: u64, // Protocol share of fee_amount (→ fee vault)
+    pub referrer: Pubkey,      // Ask's referrer (default = none)
+    pub referral_amount: u64,  // Part of fee_amount paid to the referrer (0 when it wasn't passed)
+    pub trade_seq: u64,        // Market.trade_seq of this fill
+    pub maker_fee_exempt: bool, // Fee waived: the resting order's owner holds a fee-exempt seat
+    pub taker_fee_exempt: bool, // Fee waived: the incoming order's owner holds a fee-exempt seat
+    pub crank_reward: u64,     // Paid to the matcher from the fee vault
+    pub matcher_fee: u64,      // Paid to the matcher out of the seller payment
+    pub bid_residual: u64,     // Bid dust remainder closed with this fill (not traded)
+    pub ask_residual: u64,     // Ask dust remainder closed with this fill (not traded)
+    pub residual_refund: u64,  // Bid escrow refunded for bid_residual (part of the buyer refund)
+    pub bid_remaining: u64,    // Bid quantity still on the book after the fill (0 once Filled)
+    pub ask_remaining: u64,    // Ask quantity still on the book after the fill (0 once Filled)
+    pub bid_status: OrderStatus, // Bid status after the fill
+    pub ask_status: OrderStatus, // Ask status after the fill
+    pub matcher: Pubkey,       // Crank that matched, or the taker for fills at placement
     pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+/// Max maker fills one TradeBatchEvent carries.
+pub const TRADE_BATCH_CAPACITY: usize = 8;
+
+/// One maker fill inside a TradeBatchEvent.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TradeBatchFill {
+    pub maker_order_id: u64,
+    pub quantity: u64,
+    pub price: u64,
+}
+
+/// Compact record of one taker matched against several makers, emitted
+/// once per multi-maker match. Fills `first_trade_seq..=last_trade_seq` are
+/// `fills[..fill_count]` in order; the rest of the array is zeroed.
+#[event]
+pub struct TradeBatchEvent {
+    pub market: Pubkey,
+    pub taker_order_id: u64,
+    pub taker_side: Side,
+    pub taker: Pubkey,
+    pub first_trade_seq: u64,
+    pub last_trade_seq: u64,
+    pub total_quantity: u64,
+    pub total_notional: u64,
+    pub total_fee: u64,
+    pub fill_count: u8,
+    pub fills: [TradeBatchFill; 8], // TRADE_BATCH_CAPACITY
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+impl TradeBatchEvent {
+    /// The populated fills.
+    pub fn fills(&self) -> &[TradeBatchFill] {
+        &self.fills[..(self.fill_count as usize).min(TRADE_BATCH_CAPACITY)]
+    }
 }
 
 #[event]
@@ -31,6 +407,43 @@ pub struct OrderCancelledEvent {
     pub owner: Pubkey,
     pub market: Pubkey,
     pub refund_lamports: u64,
+    pub base_refund: u64, // base tokens returned from the base vault (asks)
+    pub remaining_quantity: u64, // unfilled quantity taken off the book
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+/// A Filled or Cancelled order's PDA was closed. Not chained: closing
+/// leaves the market untouched.
+#[event]
+pub struct OrderClosedEvent {
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub market: Pubkey,
+    pub rent_reclaimed: u64,       // the order account's rent
+    pub reclaimed_to: Pubkey,      // the owner, or the RentSubsidyVault for subsidized rent
+    pub vault_rent_reclaimed: u64, // the escrow vault's rent, always to the owner
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+/// Someone other than the owner closed a long-finished order with
+/// sweep_order. Not chained, like OrderClosedEvent.
+#[event]
+pub struct OrderSweptEvent {
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub market: Pubkey,
+    pub swept_by: Pubkey,
+    pub tip: u64,             // paid to swept_by out of the rent
+    pub rent_reclaimed: u64,       // the rest of the order account's rent
+    pub reclaimed_to: Pubkey,      // the owner, or the RentSubsidyVault for subsidized rent
+    pub vault_rent_reclaimed: u64, // the escrow vault's rent, always to the owner
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
 }
 
 #[event]
@@ -38,5 +451,528 @@ pub struct MarketPausedEvent {
     pub market: Pubkey,
     pub authority: Pubkey,
     pub is_paused: bool,
+    pub taker_only_until_ts: i64,   // set on resume; unchanged on pause
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MarketSidePausedEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub side: Side,
+    pub is_paused: bool,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct CrankRewardSetEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub base: u64,
+    pub per_slot: u64,
+    pub max: u64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct FixedPointPricesSetEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub fixed_point_prices: bool,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MarketParamsStagedEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub params: MarketParams,
+    pub effective_ts: i64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MarketParamsAppliedEvent {
+    pub market: Pubkey,
+    pub params: MarketParams,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MarketParamsDiscardedEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub effective_ts: i64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MakersRestrictedSetEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub makers_restricted: bool,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct TradeEventModeSetEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub batch_trade_events: bool,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+/// start_auction (auction_mode = true) or end_auction.
+#[event]
+pub struct AuctionModeSetEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub auction_mode: bool,
+    pub auction_end_ts: i64,   // 0 once the market is back to continuous matching
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+/// One settle_auction call; each pair's fill also has its TradeExecutedEvent
+/// unless the market batches trade events.
+#[event]
+pub struct AuctionSettledEvent {
+    pub market: Pubkey,
+    pub settler: Pubkey,
+    pub clearing_price: u64,
+    pub fill_count: u32,
+    pub total_quantity: u64,
+    pub total_notional: u64,   // clearing_price × total_quantity
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MakerShareLimitSetEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub max_maker_share_bps: u16,
+    pub min_side_volume: u64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+/// Irreversible: the market has no authority from this event on.
+#[event]
+pub struct AuthorityRenouncedEvent {
+    pub market: Pubkey,
+    pub previous_authority: Pubkey,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MarketExpiryConfiguredEvent {
+    pub market: Pubkey,
+    pub expiry_ts: i64,
+    pub settlement_oracle: Pubkey,
+    pub oracle_max_staleness_secs: i64,
+    pub permissionless_settlement: bool,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MarketSettledEvent {
+    pub market: Pubkey,
+    pub settler: Pubkey,
+    pub settlement_price: u64,
+    pub oracle_publish_ts: i64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct OraclePricePublishedEvent {
+    pub feed: Pubkey,
+    pub publisher: Pubkey,
+    pub price: u64,
+    pub publish_ts: i64,
+}
+
+#[event]
+pub struct RoleGrantedEvent {
+    pub market: Pubkey,
+    pub role: Role,
+    pub holder: Pubkey,
+    pub previous_holder: Pubkey,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct RoleRevokedEvent {
+    pub market: Pubkey,
+    pub role: Role,
+    pub previous_holder: Pubkey,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct TraderSeatGrantedEvent {
+    pub market: Pubkey,
+    pub trader: Pubkey,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct TraderSeatRevokedEvent {
+    pub market: Pubkey,
+    pub trader: Pubkey,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MatcherRestrictedSetEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub matcher_restricted: bool,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MatcherRegisteredEvent {
+    pub market: Pubkey,
+    pub matcher: Pubkey,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MatcherRevokedEvent {
+    pub market: Pubkey,
+    pub matcher: Pubkey,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct FeeExemptionSetEvent {
+    pub market: Pubkey,
+    pub trader: Pubkey,
+    pub fee_exempt: bool,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct BalanceDepositedEvent {
+    pub owner: Pubkey,
+    pub market: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MaxOrdersPerUserSetEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub max_orders_per_user: u16,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct ProbationLimitsSetEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub probation_fills: u64,
+    pub probation_volume: u64,
+    pub max_order_notional: u64,
+    pub max_open_orders: u64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MarketArchivingEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub open_orders: u64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct ArchiveStepEvent {
+    pub market: Pubkey,
+    pub caller: Pubkey,
+    pub cancelled: u64,
+    pub open_orders_remaining: u64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MarketClosedEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+/// The authority started a new ticker session. Carries the range of the
+/// session it closed.
+#[event]
+pub struct SessionStatsResetEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub previous_high: u64,
+    pub previous_low: u64,
+    pub previous_start_ts: i64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+/// Periodic heartbeat from poke_market. Its event_seq / state_hash are the
+/// ones it takes, so a resyncing indexer can pick the chain up from it.
+#[event]
+pub struct MarketSnapshotEvent {
+    pub market: Pubkey,
+    pub slot: u64,
+    pub next_order_id: u64,
+    pub open_order_count: u64,
+    pub total_bid_volume: u64,
+    pub total_ask_volume: u64,
+    pub last_trade_price: u64,
+    pub last_trade_ts: i64,
+    pub cumulative_base_volume: u64,
+    pub cumulative_quote_volume: u128,
+    pub session_high: u64,
+    pub session_low: u64,
+    pub session_start_ts: i64,
+    pub fee_bps: u16,
+    pub fee_recipient: Pubkey,
+    pub dust_lamports: u64,
+    pub is_paused: bool,
+    pub is_archiving: bool,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProtocolFeeShareSetEvent {
+    pub admin: Pubkey,
+    pub protocol_fee_share_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProtocolPausedEvent {
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProtocolResumedEvent {
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProtocolConfigUpdatedEvent {
+    pub previous_admin: Pubkey,
+    pub admin: Pubkey,
+    pub protocol_fee_share_bps: u16,
+    pub treasury: Pubkey,
+    pub timestamp: i64,
+}
+
+/// Not chained: the registry is protocol-wide, and a delisted market may
+/// already be closed.
+#[event]
+pub struct MarketListedEvent {
+    pub market: Pubkey,
+    pub registry_index: u64,
+    pub creator: Pubkey,
+    pub market_name: String,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct MarketDelistedEvent {
+    pub market: Pubkey,
+    pub registry_index: u64,
+    pub delisted_by: Pubkey,
+    pub timestamp: i64,
+    pub event_seq: u64, // 0 = the market is gone; not in its chain
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct FeesWithdrawnEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct ProtocolFeesWithdrawnEvent {
+    pub market: Pubkey,
+    pub admin: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub remaining: u64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct BalanceWithdrawnEvent {
+    pub owner: Pubkey,
+    pub market: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct FundsClaimedEvent {
+    pub owner: Pubkey,
+    pub market: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+#[event]
+pub struct RentSubsidyFundedEvent {
+    pub market: Pubkey,
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+/// Not chained: the market may no longer exist to carry the hash. Its open
+/// volumes still include `remaining_quantity` until reconciled.
+#[event]
+pub struct EmergencyCancelEvent {
+    pub market: Pubkey,
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub side: Side,
+    pub remaining_quantity: u64,
+    pub refund_lamports: u64,
+    pub market_missing: bool,
+    pub timestamp: i64,
+    pub event_seq: u64, // 0 = the market is gone; not in its chain
+    pub state_hash: [u8; 32],
+}
+
+/// Follows the OrderCancelledEvent of a force_cancel_order, so the owner
+/// can tell the cancel wasn't theirs.
+#[event]
+pub struct OrderForceCancelledEvent {
+    pub market: Pubkey,
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub cancelled_by: Pubkey,
+    pub refund_lamports: u64,  // always paid to the owner (or its funder / trading balance)
+    pub market_settled: bool,  // false = the authority (or RiskManager) acting on a live market
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+/// upgrade_market migrated a market to the current account layout.
+#[event]
+pub struct MarketUpgradedEvent {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+    pub from_len: u64,         // account size before; equal to to_len if it didn't grow
+    pub to_len: u64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+/// upgrade_order migrated an order to the current account layout.
+#[event]
+pub struct OrderUpgradedEvent {
+    pub market: Pubkey,
+    pub order: Pubkey,
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+    pub from_len: u64,         // account size before; equal to to_len if it didn't grow
+    pub to_len: u64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
+}
+
+/// create_book_sides set up the market's BookSide accounts.
+#[event]
+pub struct BookSidesCreatedEvent {
+    pub market: Pubkey,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub authority: Pubkey,
     pub timestamp: i64,
+    pub event_seq: u64,
+    pub state_hash: [u8; 32],
 }
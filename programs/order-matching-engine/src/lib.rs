@@ -1,14 +1,23 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
+use solana_sha256_hasher::hashv;
 
 declare_id!("77aLU4dN1NTAWVGhNcNgWFwQ5K9XwkFnEWMLjGWWZBDD");
 
+#[cfg(feature = "client")]
+pub mod client;
 pub mod errors;
 pub mod events;
+pub mod matching;
 pub mod state;
+pub mod token;
+
+/// The pure matching math, re-exported for off-chain users of this crate.
+pub use solamatch_core;
 
 use errors::MatchingEngineError;
 use events::*;
+use matching::{CancelPreview, MakerOutcome, MatchContext, MatchOrdersParams, MatchSettlement, SimulatedMatch};
 use state::*;
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -23,69 +32,599 @@ pub mod order_matching_engine {
     // ═══════════════════════════════════════════════════════════════════════
 
     /// Create a new order book market.
-    /// Seeds: ["market", authority, market_name]
+    /// - taker_only_window_secs: after open (and after each resume) no new
+    ///   resting orders are accepted for this long (0 = no window).
+    /// - quote_mint (optional account): quotes the market in that SPL token.
+    ///   BUY escrow and fees are then held in the market's quote vault,
+    ///   created here. None or the native mint keeps lamport quotes.
+    /// - base_mint (optional account): asks lock their base tokens in the
+    ///   market's base vault, created here, and fills deliver them to the
+    ///   buyer. None keeps asks unescrowed.
+    /// - tick_size / lot_size: integer prices must be a multiple of
+    ///   tick_size and quantities of lot_size (both at least 1).
+    /// - min_order_quantity: smallest quantity an order may have.
+    ///   1 / 1 / 1 accepts any order, as before.
+    /// - matcher_fee_bps: match_orders pays its matcher this share of each
+    ///   fill's gross, out of the seller payment (capped like the trading
+    ///   fee; 0 = none). Fixed for the market's lifetime, so every resting
+    ///   ask knows its cut. Lamport-quoted markets only.
+    /// - permissioned: every placement needs the owner's TraderSeat
+    ///   (add_trader). Fixed for the market's lifetime.
+    /// Seeds: ["market", creator, market_name]
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize_market(
         ctx: Context<InitializeMarket>,
         market_name: String,
+        taker_only_window_secs: i64,
+        tick_size: u64,
+        lot_size: u64,
+        min_order_quantity: u64,
+        matcher_fee_bps: u16,
+        permissioned: bool,
     ) -> Result<()> {
         require!(
             market_name.len() <= Market::MAX_NAME_LEN,
             MatchingEngineError::MarketNameTooLong
         );
+        require!(
+            taker_only_window_secs >= 0,
+            MatchingEngineError::InvalidTimelock
+        );
+        require!(tick_size > 0, MatchingEngineError::InvalidTickSize);
+        require!(lot_size > 0, MatchingEngineError::InvalidLotSize);
+        require!(
+            matcher_fee_bps <= FeeConfig::MAX_FEE_BPS,
+            MatchingEngineError::FeeBpsTooHigh
+        );
+        require!(
+            !ctx.accounts.config.paused,
+            MatchingEngineError::ProtocolPaused
+        );
+        let now = Clock::get()?.unix_timestamp;
         let market = &mut ctx.accounts.market;
         market.authority = ctx.accounts.authority.key();
+        market.creator = ctx.accounts.authority.key();
+        market.expiry_ts = 0;
+        market.settlement_oracle = Pubkey::default();
+        market.oracle_max_staleness_secs = 0;
+        market.permissionless_settlement = false;
+        market.settlement_price = 0;
+        market.settled_at = 0;
+        market.fee_recipient = ctx.accounts.fee_vault.key();
+        market.max_maker_share_bps = 0;
+        market.maker_share_min_side_volume = 0;
+        market.open_order_count = 0;
+        market.is_archiving = false;
+        market.probation_fills = 0;
+        market.probation_volume = 0;
+        market.probation_max_order_notional = 0;
+        market.probation_max_open_orders = 0;
+        market.max_orders_per_user = 0;
+        market.referral_share_bps = 0;
+        market.sweep_delay_secs = Market::DEFAULT_SWEEP_DELAY_SECS;
+        market.last_trade_price = 0;
+        market.last_trade_ts = 0;
+        market.cumulative_base_volume = 0;
+        market.cumulative_quote_volume = 0;
+        market.price_cumulative = 0;
+        market.last_twap_update_ts = 0;
+        market.reset_session(now);
+        market.price_band_bps = 0;
+        market.last_poke_slot = 0;
+        market.trade_seq = 0;
+        market.batch_trade_events = false;
+        market.commit_reveal = false;
+        market.reveal_window_secs = 0;
+        market.fee_bps = 0;
+        market.taker_fee_bps = 0;
+        market.buys_paused = false;
+        market.sells_paused = false;
+        market.crank_reward_base = 0;
+        market.crank_reward_per_slot = 0;
+        market.crank_reward_max = 0;
+        market.fixed_point_prices = false;
+        market.quote_mint = Pubkey::default();
+        market.quote_vault = Pubkey::default();
+        market.quote_vault_bump = 0;
+        market.quote_fees = 0;
+        market.quote_protocol_fees = 0;
+        market.base_mint = Pubkey::default();
+        market.base_vault = Pubkey::default();
+        market.base_vault_bump = 0;
+
+        // ── Token vaults ─────────────────────────────────────────────────
+        let market_key = market.key();
+        let quote_mint = ctx
+            .accounts
+            .quote_mint
+            .as_ref()
+            .filter(|mint| mint.key() != token::NATIVE_MINT);
+        if let Some(mint) = quote_mint {
+            require!(token::is_mint(mint)?, MatchingEngineError::InvalidQuoteMint);
+            require!(matcher_fee_bps == 0, MatchingEngineError::TokenQuoteUnsupported);
+            let (Some(vault), Some(token_program)) =
+                (&ctx.accounts.quote_vault, &ctx.accounts.token_program)
+            else {
+                return err!(MatchingEngineError::QuoteAccountsRequired);
+            };
+            let (vault_key, vault_bump) = create_market_vault(
+                &ctx.accounts.authority,
+                market_key,
+                Market::QUOTE_VAULT_SEED,
+                mint,
+                vault,
+                token_program,
+                &ctx.accounts.system_program,
+            )?;
+            market.quote_mint = mint.key();
+            market.quote_vault = vault_key;
+            market.quote_vault_bump = vault_bump;
+        }
+        if let Some(mint) = &ctx.accounts.base_mint {
+            require!(token::is_mint(mint)?, MatchingEngineError::InvalidBaseMint);
+            let (Some(vault), Some(token_program)) =
+                (&ctx.accounts.base_vault, &ctx.accounts.token_program)
+            else {
+                return err!(MatchingEngineError::BaseAccountsRequired);
+            };
+            let (vault_key, vault_bump) = create_market_vault(
+                &ctx.accounts.authority,
+                market_key,
+                Market::BASE_VAULT_SEED,
+                mint,
+                vault,
+                token_program,
+                &ctx.accounts.system_program,
+            )?;
+            market.base_mint = mint.key();
+            market.base_vault = vault_key;
+            market.base_vault_bump = vault_bump;
+        }
+
+        let fee_vault = &mut ctx.accounts.fee_vault;
+        fee_vault.market = market.key();
+        fee_vault.bump = ctx.bumps.fee_vault;
+        fee_vault.protocol_fees = 0;
         market.market_name = market_name.clone();
         market.next_order_id = 0;
         market.total_bid_volume = 0;
         market.total_ask_volume = 0;
+        market.tick_size = tick_size;
+        market.lot_size = lot_size;
+        market.version = Market::VERSION;
+        market.min_order_quantity = min_order_quantity;
+        market.matcher_fee_bps = matcher_fee_bps;
         market.bump = ctx.bumps.market;
         market.is_paused = false;
+        market.params_timelock_secs = 0;
+        market.event_seq = 0;
+        market.state_hash = [0; 32];
+        market.makers_restricted = false;
+        market.matcher_restricted = false;
+        market.permissioned = permissioned;
+        market.taker_only_window_secs = taker_only_window_secs;
+        market.taker_only_until_ts = now
+            .checked_add(taker_only_window_secs)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        market.dust_lamports = 0;
+
+        let event = MarketInitializedEvent {
+            market: market.key(),
+            authority: market.authority,
+            market_name: market_name.clone(),
+            taker_only_until_ts: market.taker_only_until_ts,
+            quote_mint: market.quote_mint,
+            tick_size,
+            lot_size,
+            min_order_quantity,
+            matcher_fee_bps,
+            permissioned,
+            timestamp: now,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+
+        // ── Registry listing ─────────────────────────────────────────────
+        let registry = &mut ctx.accounts.registry;
+        let listing = &mut ctx.accounts.listing;
+        listing.registry_index = registry.count;
+        listing.market = market.key();
+        listing.creator = market.creator;
+        listing.market_name = market_name.clone();
+        listing.active = true;
+        listing.listed_at = now;
+        listing.bump = ctx.bumps.listing;
+        registry.count = registry
+            .count
+            .checked_add(1)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        let event = MarketListedEvent {
+            market: listing.market,
+            registry_index: listing.registry_index,
+            creator: listing.creator,
+            market_name: market_name.clone(),
+            timestamp: now,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
 
         msg!("Market '{}' initialized.", market_name);
         Ok(())
     }
 
     /// ⚡ KILL SWITCH: Pause all new orders and matching for this market.
-    /// Only the market authority can call this.
+    /// Authority or Pauser.
     /// cancel_order remains unaffected — users can always reclaim funds.
     pub fn pause_market(ctx: Context<AuthorityAction>) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::Pauser,
+        )?;
         let market = &mut ctx.accounts.market;
         require!(!market.is_paused, MatchingEngineError::MarketPaused);
         market.is_paused = true;
+        let event = MarketPausedEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            is_paused: true,
+            taker_only_until_ts: market.taker_only_until_ts,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
         msg!("Market '{}' PAUSED by authority.", market.market_name);
         Ok(())
     }
 
-    /// Resume a paused market. Only the market authority can call this.
+    /// Resume a paused market. Authority or Pauser.
+    /// Restarts the taker-only window.
     pub fn resume_market(ctx: Context<AuthorityAction>) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::Pauser,
+        )?;
+        let now = Clock::get()?.unix_timestamp;
         let market = &mut ctx.accounts.market;
+        // A stray resume would otherwise restart the taker-only window
+        require!(market.is_paused, MatchingEngineError::MarketNotPaused);
         market.is_paused = false;
+        market.taker_only_until_ts = now
+            .checked_add(market.taker_only_window_secs)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        let event = MarketPausedEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            is_paused: false,
+            taker_only_until_ts: market.taker_only_until_ts,
+            timestamp: now,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
         msg!("Market '{}' RESUMED by authority.", market.market_name);
         Ok(())
     }
 
+    /// Stop new orders on one side only. Resting orders on that side keep
+    /// matching and can still be cancelled; the other side is untouched.
+    /// Authority or Pauser.
+    pub fn pause_side(ctx: Context<AuthorityAction>, side: Side) -> Result<()> {
+        set_side_paused(ctx, side, true)
+    }
+
+    /// Accept new orders on a side stopped by pause_side. Authority or Pauser.
+    pub fn resume_side(ctx: Context<AuthorityAction>, side: Side) -> Result<()> {
+        set_side_paused(ctx, side, false)
+    }
+
+    /// Permanently give up the market authority.
+    /// Afterwards every authority-gated instruction fails with AuthorityRenounced;
+    /// placing, matching, cancelling and closing orders keep working.
+    /// The market must be live (not paused) with no staged parameters pending.
+    pub fn renounce_authority(ctx: Context<RenounceAuthority>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(!market.is_paused, MatchingEngineError::MarketPaused);
+        require!(
+            ctx.accounts.staged_params.data_is_empty(),
+            MatchingEngineError::StagedParamsPending
+        );
+
+        let previous_authority = market.authority;
+        market.authority = Market::RENOUNCED_AUTHORITY;
+
+        let event = AuthorityRenouncedEvent {
+            market: market.key(),
+            previous_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "⚠️ Market '{}' authority RENOUNCED by {} — market is now immutable.",
+            market.market_name,
+            previous_authority
+        );
+        Ok(())
+    }
+
+    /// Migrate a market created under an older account layout: grow it to
+    /// Market::LEN (the authority pays the added rent), give the fields it
+    /// predates their defaults and stamp Market::VERSION. The market is read
+    /// unchecked, so one too short to load anywhere else can still be
+    /// upgraded. Authority only; fails with MarketUpToDate once current.
+    pub fn upgrade_market(ctx: Context<UpgradeMarket>) -> Result<()> {
+        let authority = &ctx.accounts.authority;
+        let info = ctx.accounts.market.to_account_info();
+        {
+            let data = info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 + 32 && data[..8] == *Market::DISCRIMINATOR,
+                ErrorCode::AccountDiscriminatorMismatch
+            );
+            // The authority is the first field at every version
+            require_keys_eq!(
+                Pubkey::try_from(&data[8..8 + 32]).unwrap(),
+                authority.key(),
+                MatchingEngineError::Unauthorized
+            );
+        }
+
+        let from_len = info.data_len();
+        if from_len < Market::LEN {
+            let top_up = Rent::get()?
+                .minimum_balance(Market::LEN)
+                .saturating_sub(info.lamports());
+            if top_up > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: authority.to_account_info(),
+                            to: info.clone(),
+                        },
+                    ),
+                    top_up,
+                )?;
+            }
+            // New bytes are zeroed
+            info.resize(Market::LEN)?;
+        }
+
+        let mut market = Market::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        require!(
+            market.version < Market::VERSION,
+            MatchingEngineError::MarketUpToDate
+        );
+        let from_version = market.version;
+        market.upgrade(&info.key());
+        let event = MarketUpgradedEvent {
+            market: info.key(),
+            authority: authority.key(),
+            from_version,
+            to_version: market.version,
+            from_len: from_len as u64,
+            to_len: info.data_len() as u64,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut market, event)?;
+        market.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+        msg!(
+            "Market '{}' upgraded: version {} → {}, {} → {} bytes",
+            market.market_name,
+            from_version,
+            market.version,
+            from_len,
+            info.data_len()
+        );
+        Ok(())
+    }
+
+    /// Migrate an order written under an older account layout, as
+    /// upgrade_market does for markets: grow it to Order::LEN (the owner pays
+    /// the added rent), give the fields it predates their defaults and stamp
+    /// Order::VERSION. Owner only; fails with OrderUpToDate once current.
+    pub fn upgrade_order(ctx: Context<UpgradeOrder>) -> Result<()> {
+        let owner = &ctx.accounts.owner;
+        let market = &mut ctx.accounts.market;
+        let info = ctx.accounts.order.to_account_info();
+        {
+            let data = info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 + 32 + 32 && data[..8] == *Order::DISCRIMINATOR,
+                ErrorCode::AccountDiscriminatorMismatch
+            );
+            // The owner and market lead every version
+            require_keys_eq!(
+                Pubkey::try_from(&data[8..8 + 32]).unwrap(),
+                owner.key(),
+                MatchingEngineError::Unauthorized
+            );
+            require_keys_eq!(
+                Pubkey::try_from(&data[8 + 32..8 + 64]).unwrap(),
+                market.key(),
+                MatchingEngineError::MarketMismatch
+            );
+        }
+
+        let from_len = info.data_len();
+        if from_len < Order::LEN {
+            let top_up = Rent::get()?
+                .minimum_balance(Order::LEN)
+                .saturating_sub(info.lamports());
+            if top_up > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: owner.to_account_info(),
+                            to: info.clone(),
+                        },
+                    ),
+                    top_up,
+                )?;
+            }
+            // New bytes are zeroed
+            info.resize(Order::LEN)?;
+        }
+
+        let mut order = Order::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        require!(
+            order.version < Order::VERSION,
+            MatchingEngineError::OrderUpToDate
+        );
+        let from_version = order.version;
+        let now = Clock::get()?.unix_timestamp;
+        order.upgrade(now);
+        order.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+        let event = OrderUpgradedEvent {
+            market: market.key(),
+            order: info.key(),
+            order_id: order.order_id,
+            owner: owner.key(),
+            from_version,
+            to_version: order.version,
+            from_len: from_len as u64,
+            to_len: info.data_len() as u64,
+            timestamp: now,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Order #{} upgraded: version {} → {}, {} → {} bytes",
+            order.order_id,
+            from_version,
+            order.version,
+            from_len,
+            info.data_len()
+        );
+        Ok(())
+    }
+
+    /// Choose how multi-maker matches report fills: `true` emits only one
+    /// TradeBatchEvent per match, `false` also emits a TradeExecutedEvent
+    /// per maker. Single matches always emit TradeExecutedEvent.
+    /// Authority or ParamManager.
+    pub fn set_batch_trade_events(
+        ctx: Context<AuthorityAction>,
+        batch_trade_events: bool,
+    ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        let market = &mut ctx.accounts.market;
+        market.batch_trade_events = batch_trade_events;
+        let event = TradeEventModeSetEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            batch_trade_events,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Market '{}' batch_trade_events = {}",
+            market.market_name,
+            batch_trade_events
+        );
+        Ok(())
+    }
+
+    /// Switch the market to call auction mode: orders are placed as usual
+    /// but rest unmatched — match_orders and every other fill path fail
+    /// with AuctionInProgress — until settle_auction fills them at one
+    /// clearing price from `auction_end_ts`. Calling it again during an
+    /// auction moves the end. Authority or ParamManager.
+    pub fn start_auction(ctx: Context<AuthorityAction>, auction_end_ts: i64) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        let now = Clock::get()?.unix_timestamp;
+        require!(auction_end_ts > now, MatchingEngineError::InvalidAuctionEnd);
+        set_auction_mode(ctx, true, auction_end_ts, now)
+    }
+
+    /// Return an auction market to continuous matching, normally once
+    /// settle_auction has filled the crossing pairs. Orders left on the
+    /// book stay there and match as usual. Authority or ParamManager.
+    pub fn end_auction(ctx: Context<AuthorityAction>) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        require!(
+            ctx.accounts.market.auction_mode,
+            MatchingEngineError::AuctionNotActive
+        );
+        let now = Clock::get()?.unix_timestamp;
+        set_auction_mode(ctx, false, 0, now)
+    }
+
     // ═══════════════════════════════════════════════════════════════════════
     // Fee Configuration
     // ═══════════════════════════════════════════════════════════════════════
 
-    /// Initialize a fee config PDA for this market.
+    /// Initialize a fee config PDA for this market. `treasury` becomes the
+    /// market's fee recipient. `fee_bps` is both the maker and the taker
+    /// rate until set_taker_fee splits them.
     /// Seeds: ["fee_config", market]
-    /// Only callable by market authority.
+    /// Authority or FeeManager.
     pub fn initialize_fee_config(
         ctx: Context<InitializeFeeConfig>,
         fee_bps: u16,
         treasury: Pubkey,
     ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::FeeManager,
+        )?;
         require!(
             fee_bps <= FeeConfig::MAX_FEE_BPS,
             MatchingEngineError::FeeBpsTooHigh
         );
+        ctx.accounts.market.fee_recipient = treasury;
+        ctx.accounts.market.fee_bps = fee_bps;
+        ctx.accounts.market.taker_fee_bps = fee_bps;
         let fee_config = &mut ctx.accounts.fee_config;
         fee_config.market = ctx.accounts.market.key();
-        fee_config.treasury = treasury;
         fee_config.fee_bps = fee_bps;
+        fee_config.taker_fee_bps = fee_bps;
         fee_config.accumulated_fees = 0;
+        fee_config.protocol_accumulated_fees = 0;
         fee_config.bump = ctx.bumps.fee_config;
+
+        let event = FeeConfigUpdatedEvent {
+            market: fee_config.market,
+            fee_bps,
+            taker_fee_bps: fee_bps,
+            treasury,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
         msg!(
             "FeeConfig initialized: {}bps → treasury {}",
             fee_bps,
@@ -94,19 +633,46 @@ pub mod order_matching_engine {
         Ok(())
     }
 
-    /// Update fee_bps or treasury. Only callable by market authority.
+    /// Update fee_bps or the fee recipient (treasury). Authority or FeeManager.
+    /// A new fee_bps applies to orders placed afterwards; resting orders keep
+    /// the rate they were placed under. Sets the maker and taker rates alike.
+    /// Rejected once the market has a params timelock — use stage_market_params.
     pub fn update_fee_config(
         ctx: Context<UpdateFeeConfig>,
         new_fee_bps: u16,
         new_treasury: Pubkey,
     ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::FeeManager,
+        )?;
+        require!(
+            ctx.accounts.market.params_timelock_secs == 0,
+            MatchingEngineError::ParamsTimelocked
+        );
         require!(
             new_fee_bps <= FeeConfig::MAX_FEE_BPS,
             MatchingEngineError::FeeBpsTooHigh
         );
+        ctx.accounts.market.fee_recipient = new_treasury;
+        ctx.accounts.market.fee_bps = new_fee_bps;
+        ctx.accounts.market.taker_fee_bps = new_fee_bps;
         let fee_config = &mut ctx.accounts.fee_config;
         fee_config.fee_bps = new_fee_bps;
-        fee_config.treasury = new_treasury;
+        fee_config.taker_fee_bps = new_fee_bps;
+
+        let event = FeeConfigUpdatedEvent {
+            market: fee_config.market,
+            fee_bps: new_fee_bps,
+            taker_fee_bps: new_fee_bps,
+            treasury: new_treasury,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
         msg!(
             "FeeConfig updated: {}bps → treasury {}",
             new_fee_bps,
@@ -115,623 +681,9753 @@ pub mod order_matching_engine {
         Ok(())
     }
 
-    // ═══════════════════════════════════════════════════════════════════════
-    // Order Lifecycle
-    // ═══════════════════════════════════════════════════════════════════════
-
-    /// Place a buy or sell order.
-    /// - BUY: escrows (price * quantity) lamports in the Order PDA.
-    /// - SELL: no lamport escrow; records the intent on-chain.
-    /// - expires_at: Unix timestamp after which the order is invalid (0 = no expiry).
-    /// Seeds: ["order", market, order_id_le]
-    pub fn place_order(
-        ctx: Context<PlaceOrder>,
-        side: Side,
-        price: u64,
-        quantity: u64,
-        order_id: u64,
-        expires_at: i64,
-    ) -> Result<()> {
-        // ── Pause guard ─────────────────────────────────────────────────────
+    /// Charge sells that arrive as the taker `taker_fee_bps` instead of the
+    /// maker rate (fee_bps). Authority or FeeManager. Like update_fee_config
+    /// it applies to orders placed afterwards, and is rejected once the
+    /// market has a params timelock.
+    pub fn set_taker_fee(ctx: Context<UpdateFeeConfig>, taker_fee_bps: u16) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::FeeManager,
+        )?;
         require!(
-            !ctx.accounts.market.is_paused,
-            MatchingEngineError::MarketPaused
+            ctx.accounts.market.params_timelock_secs == 0,
+            MatchingEngineError::ParamsTimelocked
         );
-        // ── Input validation ────────────────────────────────────────────────
-        require!(price > 0, MatchingEngineError::InvalidPrice);
-        require!(quantity > 0, MatchingEngineError::InvalidQuantity);
         require!(
-            order_id == ctx.accounts.market.next_order_id,
-            MatchingEngineError::InvalidOrderId
+            taker_fee_bps <= FeeConfig::MAX_FEE_BPS,
+            MatchingEngineError::FeeBpsTooHigh
         );
+        ctx.accounts.market.taker_fee_bps = taker_fee_bps;
+        let fee_config = &mut ctx.accounts.fee_config;
+        fee_config.taker_fee_bps = taker_fee_bps;
 
-        let clock = Clock::get()?;
-
-        // Validate TTL if set
-        if expires_at > 0 {
-            require!(
-                expires_at > clock.unix_timestamp,
-                MatchingEngineError::OrderExpired
-            );
-        }
+        let event = FeeConfigUpdatedEvent {
+            market: fee_config.market,
+            fee_bps: fee_config.fee_bps,
+            taker_fee_bps,
+            treasury: ctx.accounts.market.fee_recipient,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Taker fee set: {}bps", taker_fee_bps);
+        Ok(())
+    }
 
-        // ── Pre-capture keys before any mutable borrow ──────────────────────
-        let owner_key = ctx.accounts.owner.key();
-        let market_key = ctx.accounts.market.key();
-        let order_bump = ctx.bumps.order;
+    /// Point fees at a new recipient (e.g. a treasury multisig).
+    /// Plain system accounts are paid directly by match_orders; anything that
+    /// can't be falls back to the market's fee vault.
+    /// Rejected once the market has a params timelock — use stage_market_params.
+    pub fn set_fee_recipient(ctx: Context<AuthorityAction>, fee_recipient: Pubkey) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::FeeManager,
+        )?;
+        let market = &mut ctx.accounts.market;
+        require!(
+            market.params_timelock_secs == 0,
+            MatchingEngineError::ParamsTimelocked
+        );
+        let previous_recipient = market.fee_recipient;
+        market.fee_recipient = fee_recipient;
 
-        // ── Escrow CPI BEFORE mutable borrow of `order` ─────────────────────
-        if side == Side::Buy {
-            let escrow_lamports = price
-                .checked_mul(quantity)
-                .ok_or(MatchingEngineError::MathOverflow)?;
-            system_program::transfer(
-                CpiContext::new(
-                    ctx.accounts.system_program.to_account_info(),
-                    system_program::Transfer {
-                        from: ctx.accounts.owner.to_account_info(),
-                        to: ctx.accounts.order.to_account_info(),
-                    },
-                ),
-                escrow_lamports,
-            )?;
-        }
+        let event = FeeRecipientUpdatedEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            previous_recipient,
+            fee_recipient,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!("Fee recipient: {} → {}", previous_recipient, fee_recipient);
+        Ok(())
+    }
 
-        // ── Populate Order account fields ────────────────────────────────────
-        let order = &mut ctx.accounts.order;
-        order.owner = owner_key;
-        order.market = market_key;
-        order.order_id = order_id;
-        order.side = side.clone();
-        order.price = price;
-        order.quantity = quantity;
-        order.filled_quantity = 0;
-        order.status = OrderStatus::Open;
-        order.timestamp = clock.unix_timestamp;
-        order.bump = order_bump;
-        order.is_locked = false;
-        order.expires_at = expires_at;
+    /// Pay an ask's referrer `referral_share_bps` of the market's part of
+    /// each match_orders fee (what's left after the protocol's share).
+    /// Authority or FeeManager. Only splits what the market already
+    /// collects, so traders pay the same and no timelock applies.
+    pub fn set_referral_share(
+        ctx: Context<AuthorityAction>,
+        referral_share_bps: u16,
+    ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::FeeManager,
+        )?;
+        require!(
+            referral_share_bps <= 10_000,
+            MatchingEngineError::InvalidReferralShare
+        );
+        let market = &mut ctx.accounts.market;
+        market.referral_share_bps = referral_share_bps;
+        let event = ReferralShareSetEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            referral_share_bps,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!("Market '{}' referral share = {}bps", market.market_name, referral_share_bps);
+        Ok(())
+    }
 
-        // ── Update market volumes ────────────────────────────────────────────
-        if side == Side::Buy {
-            ctx.accounts.market.total_bid_volume = ctx
-                .accounts
-                .market
-                .total_bid_volume
-                .checked_add(quantity)
-                .ok_or(MatchingEngineError::MathOverflow)?;
-        } else {
-            ctx.accounts.market.total_ask_volume = ctx
-                .accounts
-                .market
-                .total_ask_volume
-                .checked_add(quantity)
-                .ok_or(MatchingEngineError::MathOverflow)?;
-        }
+    // ═══════════════════════════════════════════════════════════════════════
+    // Protocol Fees
+    // ═══════════════════════════════════════════════════════════════════════
 
-        ctx.accounts.market.next_order_id = ctx
-            .accounts
-            .market
-            .next_order_id
-            .checked_add(1)
-            .ok_or(MatchingEngineError::MathOverflow)?;
+    /// Create the protocol config; the signer becomes the protocol admin and
+    /// treasury (update_config changes either).
+    /// Seeds: ["config"]
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        protocol_fee_share_bps: u16,
+    ) -> Result<()> {
+        require!(
+            protocol_fee_share_bps <= GlobalConfig::MAX_SHARE_BPS,
+            MatchingEngineError::InvalidProtocolFeeShare
+        );
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.protocol_fee_share_bps = protocol_fee_share_bps;
+        config.bump = ctx.bumps.config;
+        config.paused = false;
+        config.treasury = config.admin;
 
-        emit!(OrderPlacedEvent {
-            order_id,
-            owner: order.owner,
-            market: order.market,
-            side,
-            price,
-            quantity,
-            timestamp: clock.unix_timestamp,
+        emit!(ProtocolFeeShareSetEvent {
+            admin: config.admin,
+            protocol_fee_share_bps,
+            timestamp: Clock::get()?.unix_timestamp,
         });
-
-        msg!(
-            "Order #{} placed | side={:?} price={} qty={} expires_at={}",
-            order_id,
-            order.side,
-            price,
-            quantity,
-            expires_at,
-        );
+        msg!("Protocol config: admin={} share={} bps", config.admin, protocol_fee_share_bps);
         Ok(())
     }
 
-    /// Match a compatible bid (buy) and ask (sell) order.
-    ///
-    /// - Validates price crossing: bid.price >= ask.price
-    /// - Optional slippage guard: max_slippage_bps (0 = no limit)
-    /// - Deducts protocol fee from seller payment → treasury
-    /// - Transfers lamports from bid escrow: seller_net + fee + buyer_refund
-    /// - is_locked guard prevents re-entrancy on same order
-    /// - Anyone can call this (decentralized crank model)
-    pub fn match_orders(
-        ctx: Context<MatchOrders>,
-        max_slippage_bps: u16,
+    /// Change the protocol's share of every market fee. Protocol admin only.
+    pub fn set_protocol_fee_share(
+        ctx: Context<SetProtocolConfig>,
+        protocol_fee_share_bps: u16,
     ) -> Result<()> {
-        let clock = Clock::get()?;
-
-        // ── Pause guard ─────────────────────────────────────────────────────
         require!(
-            !ctx.accounts.market.is_paused,
-            MatchingEngineError::MarketPaused
+            protocol_fee_share_bps <= GlobalConfig::MAX_SHARE_BPS,
+            MatchingEngineError::InvalidProtocolFeeShare
         );
+        let config = &mut ctx.accounts.config;
+        config.protocol_fee_share_bps = protocol_fee_share_bps;
 
-        // ── Validate sides ───────────────────────────────────────────────────
-        require!(
-            ctx.accounts.bid_order.side == Side::Buy,
-            MatchingEngineError::InvalidOrderSide
-        );
-        require!(
-            ctx.accounts.ask_order.side == Side::Sell,
-            MatchingEngineError::InvalidOrderSide
-        );
+        emit!(ProtocolFeeShareSetEvent {
+            admin: config.admin,
+            protocol_fee_share_bps,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        msg!("Protocol fee share = {} bps", protocol_fee_share_bps);
+        Ok(())
+    }
 
-        // ── Validate both orders are active ──────────────────────────────────
+    /// Replace the protocol admin, fee share and treasury in one go. Protocol
+    /// admin only; a new admin takes effect immediately.
+    pub fn update_config(
+        ctx: Context<SetProtocolConfig>,
+        admin: Pubkey,
+        protocol_fee_share_bps: u16,
+        treasury: Pubkey,
+    ) -> Result<()> {
         require!(
-            ctx.accounts.bid_order.is_active(),
-            MatchingEngineError::OrderNotActive
+            protocol_fee_share_bps <= GlobalConfig::MAX_SHARE_BPS,
+            MatchingEngineError::InvalidProtocolFeeShare
         );
         require!(
-            ctx.accounts.ask_order.is_active(),
-            MatchingEngineError::OrderNotActive
+            admin != Pubkey::default() && treasury != Pubkey::default(),
+            MatchingEngineError::InvalidProtocolConfig
         );
+        let config = &mut ctx.accounts.config;
+        let previous_admin = config.admin;
+        config.admin = admin;
+        config.protocol_fee_share_bps = protocol_fee_share_bps;
+        config.treasury = treasury;
 
-        // ── Re-entrancy locks ────────────────────────────────────────────────
-        require!(
-            !ctx.accounts.bid_order.is_locked,
-            MatchingEngineError::OrderLocked
-        );
-        require!(
-            !ctx.accounts.ask_order.is_locked,
-            MatchingEngineError::OrderLocked
+        emit!(ProtocolConfigUpdatedEvent {
+            previous_admin,
+            admin,
+            protocol_fee_share_bps,
+            treasury,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        msg!(
+            "Protocol config: admin={} share={} bps treasury={}",
+            admin,
+            protocol_fee_share_bps,
+            treasury
         );
+        Ok(())
+    }
 
-        // ── TTL / Expiry check ────────────────────────────────────────────────
-        require!(
-            !ctx.accounts.bid_order.is_expired(clock.unix_timestamp),
-            MatchingEngineError::OrderExpired
-        );
-        require!(
-            !ctx.accounts.ask_order.is_expired(clock.unix_timestamp),
-            MatchingEngineError::OrderExpired
-        );
+    /// ⚡ GLOBAL KILL SWITCH: halt placement and matching on every market.
+    /// Protocol admin only. Cancels, closes and withdrawals stay open so
+    /// users can always exit.
+    pub fn pause_protocol(ctx: Context<SetProtocolConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(!config.paused, MatchingEngineError::ProtocolPaused);
+        config.paused = true;
+
+        emit!(ProtocolPausedEvent {
+            admin: config.admin,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        msg!("Protocol PAUSED by admin.");
+        Ok(())
+    }
+
+    /// Lift the global pause. Protocol admin only.
+    pub fn resume_protocol(ctx: Context<SetProtocolConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(config.paused, MatchingEngineError::ProtocolNotPaused);
+        config.paused = false;
+
+        emit!(ProtocolResumedEvent {
+            admin: config.admin,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        msg!("Protocol resumed.");
+        Ok(())
+    }
+
+    /// Create the MarketRegistry that initialize_market lists markets in.
+    /// Protocol admin only.
+    /// Seeds: ["registry"]
+    pub fn initialize_registry(ctx: Context<InitializeRegistry>) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        registry.count = 0;
+        registry.bump = ctx.bumps.registry;
+        msg!("Market registry initialized.");
+        Ok(())
+    }
 
-        // ── Same market ───────────────────────────────────────────────────────
+    /// Mark a market's listing inactive. The listing account stays, so
+    /// registry indices remain contiguous for clients walking 0..count.
+    /// Market authority or protocol admin; only the admin once the market
+    /// is closed or its authority renounced.
+    pub fn delist_market(ctx: Context<DelistMarket>, _registry_index: u64) -> Result<()> {
+        let signer = ctx.accounts.authority.key();
+        let info = ctx.accounts.market.to_account_info();
+        let market_authority = if market_is_healthy(&info) {
+            let market = Market::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+            (!market.is_renounced()).then_some(market.authority)
+        } else {
+            None
+        };
         require!(
-            ctx.accounts.bid_order.market == ctx.accounts.ask_order.market,
-            MatchingEngineError::MarketMismatch
+            signer == ctx.accounts.config.admin || market_authority == Some(signer),
+            MatchingEngineError::Unauthorized
         );
+        let listing = &mut ctx.accounts.listing;
+        require!(listing.active, MatchingEngineError::MarketNotListed);
+        listing.active = false;
+
+        let event = MarketDelistedEvent {
+            market: listing.market,
+            registry_index: listing.registry_index,
+            delisted_by: signer,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event_unchecked(&ctx.accounts.market, event)?;
+        msg!("Market {} delisted (listing {}).", listing.market, listing.registry_index);
+        Ok(())
+    }
 
-        // ── Price crossing check ──────────────────────────────────────────────
+    /// Withdraw the market's fees held in its fee vault: anything above rent
+    /// and the protocol's pending share. On a token-quoted market, withdraws
+    /// `amount` quote tokens of the market's fees from the quote vault to a
+    /// quote token account instead. Authority or FeeManager.
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::FeeManager,
+        )?;
+        require!(amount > 0, MatchingEngineError::InvalidAmount);
+        if ctx.accounts.market.is_token_quoted() {
+            let market = &mut ctx.accounts.market;
+            require!(market.quote_fees >= amount, MatchingEngineError::InsufficientFees);
+            let quote = VaultAccounts {
+                vault: ctx.accounts.quote_vault.as_deref(),
+                token_program: ctx.accounts.token_program.as_deref(),
+                user: None,
+            };
+            TokenVault::quote(market.key(), market, &quote)?.pay(
+                &ctx.accounts.destination,
+                None,
+                amount,
+            )?;
+            market.quote_fees -= amount;
+            let event = FeesWithdrawnEvent {
+                market: market.key(),
+                authority: ctx.accounts.authority.key(),
+                destination: ctx.accounts.destination.key(),
+                amount,
+                event_seq: 0,
+                state_hash: [0; 32], // stamped by record_event
+            };
+            record_event(market, event)?;
+            msg!("Withdrew {} quote tokens of market fees", amount);
+            return Ok(());
+        }
+        let vault_info = ctx.accounts.fee_vault.to_account_info();
         require!(
-            ctx.accounts.bid_order.price >= ctx.accounts.ask_order.price,
-            MatchingEngineError::PriceMismatch
+            market_fees_available(&ctx.accounts.fee_vault)? >= amount,
+            MatchingEngineError::InsufficientFees
         );
 
-        // ── Optional slippage guard ───────────────────────────────────────────
-        // Slippage = (bid_price - ask_price) / bid_price
-        // Revert if it exceeds max_slippage_bps
-        if max_slippage_bps > 0 && ctx.accounts.bid_order.price > 0 {
-            let spread = ctx.accounts.bid_order.price
-                .saturating_sub(ctx.accounts.ask_order.price);
-            let slippage_bps = (spread as u128)
-                .checked_mul(10_000)
-                .unwrap_or(u128::MAX)
-                .checked_div(ctx.accounts.bid_order.price as u128)
-                .unwrap_or(u128::MAX) as u64;
+        move_lamports(&vault_info, &ctx.accounts.destination.to_account_info(), amount)?;
+
+        let event = FeesWithdrawnEvent {
+            market: ctx.accounts.market.key(),
+            authority: ctx.accounts.authority.key(),
+            destination: ctx.accounts.destination.key(),
+            amount,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Withdrew {} lamports of market fees", amount);
+        Ok(())
+    }
+
+    /// Withdraw the protocol's share held in a market's fee vault — or, on a
+    /// token-quoted market, in its quote vault — to the protocol treasury.
+    /// Protocol admin only.
+    pub fn withdraw_protocol_fees(ctx: Context<WithdrawProtocolFees>, amount: u64) -> Result<()> {
+        require!(amount > 0, MatchingEngineError::InvalidAmount);
+        if ctx.accounts.market.is_token_quoted() {
+            let market = &mut ctx.accounts.market;
             require!(
-                slippage_bps <= max_slippage_bps as u64,
-                MatchingEngineError::SlippageExceeded
+                market.quote_protocol_fees >= amount,
+                MatchingEngineError::InsufficientFees
             );
+            let quote = VaultAccounts {
+                vault: ctx.accounts.quote_vault.as_deref(),
+                token_program: ctx.accounts.token_program.as_deref(),
+                user: None,
+            };
+            TokenVault::quote(market.key(), market, &quote)?.pay(
+                &ctx.accounts.destination,
+                Some(ctx.accounts.config.treasury),
+                amount,
+            )?;
+            market.quote_protocol_fees -= amount;
+            let event = ProtocolFeesWithdrawnEvent {
+                market: market.key(),
+                admin: ctx.accounts.admin.key(),
+                destination: ctx.accounts.destination.key(),
+                amount,
+                remaining: market.quote_protocol_fees,
+                event_seq: 0,
+                state_hash: [0; 32], // stamped by record_event
+            };
+            record_event(market, event)?;
+            msg!("Withdrew {} quote tokens of protocol fees", amount);
+            return Ok(());
         }
-
-        // ── Verify owner accounts ─────────────────────────────────────────────
-        require!(
-            ctx.accounts.bid_owner.key() == ctx.accounts.bid_order.owner,
-            MatchingEngineError::BidOwnerMismatch
-        );
+        let vault = &mut ctx.accounts.fee_vault;
         require!(
-            ctx.accounts.ask_owner.key() == ctx.accounts.ask_order.owner,
-            MatchingEngineError::AskOwnerMismatch
+            vault.protocol_fees >= amount,
+            MatchingEngineError::InsufficientFees
         );
 
-        // ── Set re-entrancy locks ─────────────────────────────────────────────
-        ctx.accounts.bid_order.is_locked = true;
-        ctx.accounts.ask_order.is_locked = true;
+        move_lamports(
+            &vault.to_account_info(),
+            &ctx.accounts.destination.to_account_info(),
+            amount,
+        )?;
+        vault.protocol_fees -= amount;
 
-        // ── Compute fill amounts ──────────────────────────────────────────────
-        let fill_qty = ctx
-            .accounts
-            .bid_order
-            .remaining_quantity()
-            .min(ctx.accounts.ask_order.remaining_quantity());
+        let event = ProtocolFeesWithdrawnEvent {
+            market: ctx.accounts.market.key(),
+            admin: ctx.accounts.admin.key(),
+            destination: ctx.accounts.destination.key(),
+            amount,
+            remaining: vault.protocol_fees,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Withdrew {} lamports of protocol fees", amount);
+        Ok(())
+    }
 
-        let fill_price = ctx.accounts.ask_order.price; // maker price
+    // ═══════════════════════════════════════════════════════════════════════
+    // Market Parameters (Timelock)
+    // ═══════════════════════════════════════════════════════════════════════
 
-        let gross_seller_payment = fill_price
-            .checked_mul(fill_qty)
-            .ok_or(MatchingEngineError::MathOverflow)?;
+    /// Apply a parameter set immediately.
+    /// Only allowed while the market has no timelock (params_timelock_secs == 0).
+    pub fn update_market_params(
+        ctx: Context<UpdateMarketParams>,
+        params: MarketParams,
+    ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        require!(
+            ctx.accounts.market.params_timelock_secs == 0,
+            MatchingEngineError::ParamsTimelocked
+        );
+        validate_market_params(&params)?;
+        write_market_params(&mut ctx.accounts.market, &mut ctx.accounts.fee_config, &params);
 
-        // ── Fee deduction ─────────────────────────────────────────────────────
-        let fee_amount = if let Some(fee_config) = &ctx.accounts.fee_config {
-            let fee = fee_config.calc_fee(gross_seller_payment);
-            // Verify treasury account matches fee_config
-            require!(
-                ctx.accounts.treasury.key() == fee_config.treasury,
-                MatchingEngineError::TreasuryMismatch
-            );
-            fee
-        } else {
-            0u64
+        let event = MarketParamsAppliedEvent {
+            market: ctx.accounts.market.key(),
+            params,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
         };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Market '{}' params updated.", ctx.accounts.market.market_name);
+        Ok(())
+    }
 
-        let net_seller_payment = gross_seller_payment
-            .checked_sub(fee_amount)
-            .ok_or(MatchingEngineError::MathOverflow)?;
+    /// Stage a parameter set that takes effect at `effective_ts`.
+    /// effective_ts must be at least params_timelock_secs in the future.
+    /// Seeds: ["staged_params", market]
+    pub fn stage_market_params(
+        ctx: Context<StageMarketParams>,
+        params: MarketParams,
+        effective_ts: i64,
+    ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        validate_market_params(&params)?;
 
-        // Price improvement refund to buyer
-        let price_improvement = ctx
-            .accounts
-            .bid_order
-            .price
-            .checked_sub(ctx.accounts.ask_order.price)
-            .ok_or(MatchingEngineError::MathOverflow)?;
-        let buyer_refund = price_improvement
-            .checked_mul(fill_qty)
+        let clock = Clock::get()?;
+        let earliest = clock
+            .unix_timestamp
+            .checked_add(ctx.accounts.market.params_timelock_secs)
             .ok_or(MatchingEngineError::MathOverflow)?;
+        require!(effective_ts >= earliest, MatchingEngineError::TimelockTooShort);
 
-        let total_debit = gross_seller_payment
-            .checked_add(buyer_refund)
-            .ok_or(MatchingEngineError::MathOverflow)?;
+        let staged = &mut ctx.accounts.staged_params;
+        staged.market = ctx.accounts.market.key();
+        staged.params = params.clone();
+        staged.effective_ts = effective_ts;
+        staged.staged_at = clock.unix_timestamp;
+        staged.bump = ctx.bumps.staged_params;
 
-        // ── Transfer lamports from bid PDA ────────────────────────────────────
-        // Debit bid_order escrow
-        **ctx
-            .accounts
-            .bid_order
-            .to_account_info()
-            .try_borrow_mut_lamports()? -= total_debit;
+        let event = MarketParamsStagedEvent {
+            market: staged.market,
+            authority: ctx.accounts.authority.key(),
+            params,
+            effective_ts,
+            timestamp: clock.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!(
+            "Market '{}' params staged, effective at {}",
+            ctx.accounts.market.market_name,
+            effective_ts
+        );
+        Ok(())
+    }
 
-        // Pay seller (net of fee)
-        **ctx
-            .accounts
-            .ask_owner
-            .to_account_info()
-            .try_borrow_mut_lamports()? += net_seller_payment;
+    /// Write a staged parameter set once its effective time has passed.
+    /// Permissionless — anyone can apply. Staging rent returns to the authority.
+    pub fn apply_staged_params(ctx: Context<ApplyStagedParams>) -> Result<()> {
+        let clock = Clock::get()?;
+        let staged = &ctx.accounts.staged_params;
+        require!(
+            clock.unix_timestamp >= staged.effective_ts,
+            MatchingEngineError::TimelockNotElapsed
+        );
 
-        // Refund buyer overpay (price improvement)
-        **ctx
-            .accounts
-            .bid_owner
-            .to_account_info()
-            .try_borrow_mut_lamports()? += buyer_refund;
+        let params = staged.params.clone();
+        write_market_params(&mut ctx.accounts.market, &mut ctx.accounts.fee_config, &params);
 
-        // Send fee to treasury
-        if fee_amount > 0 {
-            **ctx
-                .accounts
-                .treasury
-                .to_account_info()
-                .try_borrow_mut_lamports()? += fee_amount;
+        let event = MarketParamsAppliedEvent {
+            market: ctx.accounts.market.key(),
+            params,
+            timestamp: clock.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Market '{}' staged params applied.", ctx.accounts.market.market_name);
+        Ok(())
+    }
 
-            // Update accumulated_fees in FeeConfig
-            if let Some(fee_config) = &mut ctx.accounts.fee_config {
-                fee_config.accumulated_fees = fee_config
-                    .accumulated_fees
-                    .saturating_add(fee_amount);
-            }
-        }
+    /// Drop a staged parameter set before it becomes effective. Authority or ParamManager.
+    pub fn discard_staged_params(ctx: Context<DiscardStagedParams>) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        let clock = Clock::get()?;
+        let staged = &ctx.accounts.staged_params;
+        require!(
+            clock.unix_timestamp < staged.effective_ts,
+            MatchingEngineError::StagedParamsEffective
+        );
 
-        // ── Update fill state ─────────────────────────────────────────────────
-        ctx.accounts.bid_order.filled_quantity += fill_qty;
-        ctx.accounts.ask_order.filled_quantity += fill_qty;
+        let event = MarketParamsDiscardedEvent {
+            market: ctx.accounts.market.key(),
+            authority: ctx.accounts.authority.key(),
+            effective_ts: staged.effective_ts,
+            timestamp: clock.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Market '{}' staged params discarded.", ctx.accounts.market.market_name);
+        Ok(())
+    }
 
-        ctx.accounts.bid_order.status = if ctx.accounts.bid_order.filled_quantity
-            >= ctx.accounts.bid_order.quantity
-        {
-            OrderStatus::Filled
-        } else {
-            OrderStatus::PartiallyFilled
+    // ═══════════════════════════════════════════════════════════════════════
+    // Roles
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Create the market's roles account with every role unset.
+    /// Seeds: ["roles", market]
+    pub fn initialize_roles(ctx: Context<InitializeRoles>) -> Result<()> {
+        let roles = &mut ctx.accounts.roles;
+        roles.market = ctx.accounts.market.key();
+        roles.pauser = Pubkey::default();
+        roles.fee_manager = Pubkey::default();
+        roles.param_manager = Pubkey::default();
+        roles.risk_manager = Pubkey::default();
+        roles.bump = ctx.bumps.roles;
+        msg!("Roles opened for market '{}'", ctx.accounts.market.market_name);
+        Ok(())
+    }
+
+    /// Assign `role` to `holder`, replacing any previous holder. Authority only.
+    pub fn grant_role(ctx: Context<ManageRoles>, role: Role, holder: Pubkey) -> Result<()> {
+        require!(holder != Pubkey::default(), MatchingEngineError::InvalidRoleHolder);
+        let roles = &mut ctx.accounts.roles;
+        let previous_holder = roles.holder(role);
+        roles.set_holder(role, holder);
+        let event = RoleGrantedEvent {
+            market: roles.market,
+            role,
+            holder,
+            previous_holder,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
         };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Role {:?} granted to {}", role, holder);
+        Ok(())
+    }
 
-        ctx.accounts.ask_order.status = if ctx.accounts.ask_order.filled_quantity
-            >= ctx.accounts.ask_order.quantity
-        {
-            OrderStatus::Filled
-        } else {
-            OrderStatus::PartiallyFilled
+    /// Unset `role`; its former holder can no longer sign for it. Authority only.
+    pub fn revoke_role(ctx: Context<ManageRoles>, role: Role) -> Result<()> {
+        let roles = &mut ctx.accounts.roles;
+        let previous_holder = roles.holder(role);
+        roles.set_holder(role, Pubkey::default());
+        let event = RoleRevokedEvent {
+            market: roles.market,
+            role,
+            previous_holder,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
         };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Role {:?} revoked from {}", role, previous_holder);
+        Ok(())
+    }
 
-        // ── Release re-entrancy locks ─────────────────────────────────────────
-        ctx.accounts.bid_order.is_locked = false;
-        ctx.accounts.ask_order.is_locked = false;
+    // ═══════════════════════════════════════════════════════════════════════
+    // Trader Seats
+    // ═══════════════════════════════════════════════════════════════════════
 
-        emit!(TradeExecutedEvent {
-            bid_order_id: ctx.accounts.bid_order.order_id,
-            ask_order_id: ctx.accounts.ask_order.order_id,
-            market: ctx.accounts.bid_order.market,
-            buyer: ctx.accounts.bid_order.owner,
-            seller: ctx.accounts.ask_order.owner,
-            fill_price,
-            fill_quantity: fill_qty,
-            fee_amount,
+    /// Turn maker gating on or off. While on, place_order only accepts
+    /// resting orders from seat holders; matching stays open to everyone.
+    pub fn set_makers_restricted(
+        ctx: Context<AuthorityAction>,
+        makers_restricted: bool,
+    ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::RiskManager,
+        )?;
+        let market = &mut ctx.accounts.market;
+        market.makers_restricted = makers_restricted;
+        let event = MakersRestrictedSetEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            makers_restricted,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Market '{}' makers_restricted = {}",
+            market.market_name,
+            makers_restricted
+        );
+        Ok(())
+    }
+
+    /// Grant `trader` a seat on this market. Authority or RiskManager.
+    /// Seeds: ["seat", market, trader]
+    pub fn add_trader(ctx: Context<AddTrader>, trader: Pubkey) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::RiskManager,
+        )?;
+        let clock = Clock::get()?;
+        let seat = &mut ctx.accounts.trader_seat;
+        seat.market = ctx.accounts.market.key();
+        seat.trader = trader;
+        seat.granted_at = clock.unix_timestamp;
+        seat.bump = ctx.bumps.trader_seat;
+        seat.fee_exempt = false;
+
+        let event = TraderSeatGrantedEvent {
+            market: seat.market,
+            trader,
             timestamp: clock.unix_timestamp,
-        });
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Seat granted to {}", trader);
+        Ok(())
+    }
+
+    /// Revoke `trader`'s seat, returning its rent to the signer.
+    /// Orders the trader already has resting stay valid.
+    pub fn remove_trader(ctx: Context<RemoveTrader>, trader: Pubkey) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::RiskManager,
+        )?;
+        let event = TraderSeatRevokedEvent {
+            market: ctx.accounts.market.key(),
+            trader,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Seat revoked from {}", trader);
+        Ok(())
+    }
+
+    /// Waive (or reinstate) fees on every trade `trader` is party to, under
+    /// a market-maker agreement, wherever their seat is passed with the
+    /// fill. Authority or FeeManager. Read at match time, so a change
+    /// applies from the next trade.
+    pub fn set_fee_exempt(
+        ctx: Context<SetFeeExempt>,
+        trader: Pubkey,
+        fee_exempt: bool,
+    ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::FeeManager,
+        )?;
+        ctx.accounts.trader_seat.fee_exempt = fee_exempt;
+
+        let event = FeeExemptionSetEvent {
+            market: ctx.accounts.market.key(),
+            trader,
+            fee_exempt,
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Seat of {} fee_exempt = {}", trader, fee_exempt);
+        Ok(())
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // Matcher Seats
+    // ═══════════════════════════════════════════════════════════════════════
 
+    /// Turn matcher gating on or off. While on, match_orders and
+    /// match_orders_multi only accept a signer holding a MatcherSeat;
+    /// placement stays open. Authority only.
+    pub fn set_matcher_restricted(
+        ctx: Context<SetMatcherRestricted>,
+        matcher_restricted: bool,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        market.matcher_restricted = matcher_restricted;
+        let event = MatcherRestrictedSetEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            matcher_restricted,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
         msg!(
-            "Trade: {} units @ {} lamports | bid#{} x ask#{} | fee={} lamports",
-            fill_qty,
-            fill_price,
-            ctx.accounts.bid_order.order_id,
-            ctx.accounts.ask_order.order_id,
-            fee_amount,
+            "Market '{}' matcher_restricted = {}",
+            market.market_name,
+            matcher_restricted
         );
         Ok(())
     }
 
-    /// Cancel an open or partially filled order.
-    /// Refunds escrowed lamports to the buyer.
-    /// NOTE: cancel_order is NOT affected by the market pause — users can always reclaim funds.
-    pub fn cancel_order(ctx: Context<CancelOrder>, _order_id: u64) -> Result<()> {
-        let order = &mut ctx.accounts.order;
-        require!(order.is_active(), MatchingEngineError::OrderNotActive);
-        require!(!order.is_locked, MatchingEngineError::OrderLocked);
+    /// Let `matcher` run matches on this market. Authority only.
+    /// Seeds: ["matcher_seat", market, matcher]
+    pub fn register_matcher(ctx: Context<RegisterMatcher>, matcher: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
+        let seat = &mut ctx.accounts.matcher_seat;
+        seat.market = ctx.accounts.market.key();
+        seat.matcher = matcher;
+        seat.granted_at = clock.unix_timestamp;
+        seat.bump = ctx.bumps.matcher_seat;
 
-        let mut refund_lamports: u64 = 0;
-        if order.side == Side::Buy {
-            refund_lamports = order
-                .price
-                .checked_mul(order.remaining_quantity())
-                .ok_or(MatchingEngineError::MathOverflow)?;
-            if refund_lamports > 0 {
-                **order.to_account_info().try_borrow_mut_lamports()? -= refund_lamports;
-                **ctx
-                    .accounts
-                    .owner
-                    .to_account_info()
-                    .try_borrow_mut_lamports()? += refund_lamports;
-            }
-        }
+        let event = MatcherRegisteredEvent {
+            market: seat.market,
+            matcher,
+            timestamp: clock.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Matcher seat granted to {}", matcher);
+        Ok(())
+    }
 
-        // Update market volumes
-        let remaining = order.remaining_quantity();
-        if order.side == Side::Buy {
-            ctx.accounts.market.total_bid_volume =
-                ctx.accounts.market.total_bid_volume.saturating_sub(remaining);
-        } else {
-            ctx.accounts.market.total_ask_volume =
-                ctx.accounts.market.total_ask_volume.saturating_sub(remaining);
-        }
+    /// Revoke `matcher`'s seat, returning its rent to the authority. Its
+    /// match transactions still in flight then fail. Authority only.
+    pub fn revoke_matcher(ctx: Context<RevokeMatcher>, matcher: Pubkey) -> Result<()> {
+        let event = MatcherRevokedEvent {
+            market: ctx.accounts.market.key(),
+            matcher,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Matcher seat revoked from {}", matcher);
+        Ok(())
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // Maker Concentration
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Cap any single owner's share of a side's open volume.
+    /// `max_maker_share_bps` = 0 disables the cap; while a side (including
+    /// the new order) totals less than `min_side_volume` the cap is skipped
+    /// so small markets can still bootstrap.
+    pub fn set_maker_share_limit(
+        ctx: Context<AuthorityAction>,
+        max_maker_share_bps: u16,
+        min_side_volume: u64,
+    ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        require!(
+            max_maker_share_bps <= 10_000,
+            MatchingEngineError::InvalidMakerShare
+        );
+        let market = &mut ctx.accounts.market;
+        market.max_maker_share_bps = max_maker_share_bps;
+        market.maker_share_min_side_volume = min_side_volume;
+        let event = MakerShareLimitSetEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            max_maker_share_bps,
+            min_side_volume,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Market '{}' max maker share = {} bps (min side volume {})",
+            market.market_name,
+            max_maker_share_bps,
+            min_side_volume
+        );
+        Ok(())
+    }
 
-        let order_id = order.order_id;
-        let owner = order.owner;
-        let market_key = order.market;
-        order.status = OrderStatus::Cancelled;
+    /// Open per-owner open-volume tracking for (market, owner).
+    /// Seeds: ["user_stats", market, owner]
+    pub fn initialize_user_stats(ctx: Context<InitializeUserStats>) -> Result<()> {
+        let stats = &mut ctx.accounts.user_stats;
+        stats.market = ctx.accounts.market.key();
+        stats.owner = ctx.accounts.owner.key();
+        stats.open_bid_volume = 0;
+        stats.open_ask_volume = 0;
+        stats.bump = ctx.bumps.user_stats;
+        stats.open_orders = 0;
+        stats.fill_count = 0;
+        stats.filled_notional = 0;
+        msg!("UserStats opened for {}", stats.owner);
+        Ok(())
+    }
 
-        emit!(OrderCancelledEvent {
-            order_id,
-            owner,
+    /// Open the owner's list of open order ids on a market. Orders placed
+    /// with it passed are listed until filled or cancelled (at most
+    /// OpenOrders::CAPACITY at a time).
+    /// Seeds: ["open_orders", market, owner]
+    pub fn create_open_orders(ctx: Context<CreateOpenOrders>) -> Result<()> {
+        let open_orders = &mut ctx.accounts.open_orders;
+        open_orders.market = ctx.accounts.market.key();
+        open_orders.owner = ctx.accounts.owner.key();
+        open_orders.bump = ctx.bumps.open_orders;
+        open_orders.count = 0;
+        open_orders.order_ids = [0; OpenOrders::CAPACITY];
+        msg!("OpenOrders opened for {}", open_orders.owner);
+        Ok(())
+    }
+
+    /// Set up the market's two BookSide price ladders in accounts the
+    /// client has allocated (BookSide::LEN bytes each, owned by this
+    /// program) and record them in the market. From then on every placement
+    /// must pass its side's book, and GTC orders are indexed in it, best
+    /// price first, until filled or cancelled (at most BookSide::CAPACITY
+    /// per side). Once per market. Authority or ParamManager.
+    pub fn create_book_sides(ctx: Context<CreateBookSides>) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        let market_key = ctx.accounts.market.key();
+        for (loader, side) in [
+            (&ctx.accounts.bids, BookSide::BIDS),
+            (&ctx.accounts.asks, BookSide::ASKS),
+        ] {
+            let mut book = loader.load_init()?;
+            book.market = market_key;
+            book.side = side;
+        }
+        let market = &mut ctx.accounts.market;
+        market.bid_book = ctx.accounts.bids.key();
+        market.ask_book = ctx.accounts.asks.key();
+        let event = BookSidesCreatedEvent {
             market: market_key,
-            refund_lamports,
-        });
+            bids: market.bid_book,
+            asks: market.ask_book,
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!("BookSides created for market {}", market_key);
+        Ok(())
+    }
 
-        msg!("Order #{} cancelled. Refund: {} lamports", order_id, refund_lamports);
+    /// Create the market's TradeHistory: the last TradeHistory::CAPACITY
+    /// match_orders fills, readable in one fetch. match_orders appends to
+    /// it when passed.
+    /// Seeds: ["trades", market]
+    /// Authority or ParamManager.
+    pub fn create_trade_history(ctx: Context<CreateTradeHistory>) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        let market = ctx.accounts.market.key();
+        let mut history = ctx.accounts.trade_history.load_init()?;
+        history.market = market;
+        history.bump = ctx.bumps.trade_history;
+        msg!("TradeHistory created for market {}", market);
         Ok(())
     }
 
-    /// Close a Filled or Cancelled order PDA, returning rent to the owner.
-    pub fn close_order(ctx: Context<CloseOrder>, _order_id: u64) -> Result<()> {
-        let order = &ctx.accounts.order;
-        require!(
-            order.status == OrderStatus::Filled || order.status == OrderStatus::Cancelled,
-            MatchingEngineError::OrderNotClosed
+    // ═══════════════════════════════════════════════════════════════════════
+    // Probation
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Configure limits for new accounts. An owner is on probation until
+    /// their counted orders reach `probation_fills` fills or
+    /// `probation_volume` lamports of filled notional (0 = that bar unused;
+    /// both 0 = feature off). On probation each order's notional is capped
+    /// at `max_order_notional` and open orders at `max_open_orders`
+    /// (0 = no cap). Orders must be placed with the owner's UserStats.
+    pub fn set_probation_limits(
+        ctx: Context<AuthorityAction>,
+        probation_fills: u64,
+        probation_volume: u64,
+        max_order_notional: u64,
+        max_open_orders: u64,
+    ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        let market = &mut ctx.accounts.market;
+        market.probation_fills = probation_fills;
+        market.probation_volume = probation_volume;
+        market.probation_max_order_notional = max_order_notional;
+        market.probation_max_open_orders = max_open_orders;
+        let event = ProbationLimitsSetEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            probation_fills,
+            probation_volume,
+            max_order_notional,
+            max_open_orders,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Market '{}' probation: fills={} volume={} max_notional={} max_open={}",
+            market.market_name,
+            probation_fills,
+            probation_volume,
+            max_order_notional,
+            max_open_orders
         );
+        Ok(())
+    }
+
+    /// Cap every owner's open orders on this market at `max_orders_per_user`
+    /// (0 = unlimited). Orders must then be placed with the owner's
+    /// UserStats, whose open-order count fills and cancels bring back down.
+    /// Authority or ParamManager.
+    pub fn set_max_orders_per_user(
+        ctx: Context<AuthorityAction>,
+        max_orders_per_user: u16,
+    ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        let market = &mut ctx.accounts.market;
+        market.max_orders_per_user = max_orders_per_user;
+        let event = MaxOrdersPerUserSetEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            max_orders_per_user,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
         msg!(
-            "Order #{} closed. Rent reclaimed to {}",
-            order.order_id,
-            ctx.accounts.owner.key()
+            "Market '{}' max_orders_per_user = {}",
+            market.market_name,
+            max_orders_per_user
         );
         Ok(())
     }
-}
 
-// ─────────────────────────────────────────────────────────────────────────────
-// Account Validation Contexts
-// ─────────────────────────────────────────────────────────────────────────────
+    /// How long a Filled or Cancelled order must sit before anyone may
+    /// sweep_order it (0 = never). Authority or ParamManager.
+    pub fn set_sweep_delay(ctx: Context<AuthorityAction>, sweep_delay_secs: i64) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        require!(sweep_delay_secs >= 0, MatchingEngineError::InvalidSweepDelay);
+        let market = &mut ctx.accounts.market;
+        market.sweep_delay_secs = sweep_delay_secs;
+        let event = SweepDelaySetEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            sweep_delay_secs,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Market '{}' sweep_delay_secs = {}",
+            market.market_name,
+            sweep_delay_secs
+        );
+        Ok(())
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // Matcher Stats
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Start tracking the signer's matching activity on this market.
+    /// Permissionless. Seeds: ["matcher", market, matcher]
+    pub fn create_matcher_stats(ctx: Context<CreateMatcherStats>) -> Result<()> {
+        let stats = &mut ctx.accounts.matcher_stats;
+        stats.market = ctx.accounts.market.key();
+        stats.matcher = ctx.accounts.matcher.key();
+        stats.matches_executed = 0;
+        stats.volume_matched = 0;
+        stats.notional_matched = 0;
+        stats.fees_generated = 0;
+        stats.rewards_earned = 0;
+        stats.last_active_slot = 0;
+        stats.bump = ctx.bumps.matcher_stats;
+        msg!("MatcherStats opened for {}", stats.matcher);
+        Ok(())
+    }
+
+    /// Set the matcher reward paid from the fee vault on each match_orders:
+    /// `base + per_slot * age`, capped at `max`, where age is how many slots
+    /// the taker (newer) order has rested. per_slot 0 pays a flat `base`;
+    /// all zeros pays nothing. A per-slot rate requires a cap.
+    /// Authority or ParamManager.
+    pub fn set_crank_reward(
+        ctx: Context<AuthorityAction>,
+        base: u64,
+        per_slot: u64,
+        max: u64,
+    ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        require!(per_slot == 0 || max > 0, MatchingEngineError::InvalidCrankReward);
+        require!(max == 0 || max >= base, MatchingEngineError::InvalidCrankReward);
+
+        let market = &mut ctx.accounts.market;
+        market.crank_reward_base = base;
+        market.crank_reward_per_slot = per_slot;
+        market.crank_reward_max = max;
+        let event = CrankRewardSetEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            base,
+            per_slot,
+            max,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Market '{}' crank reward = {} + {}/slot (max {})",
+            market.market_name,
+            base,
+            per_slot,
+            max
+        );
+        Ok(())
+    }
+
+    /// Choose the market's price format: `true` prices orders in Q64.64
+    /// lamports per unit (place_order_q64), for assets whose fair price is
+    /// below a lamport; `false` in whole lamports (place_order). Fixed only
+    /// before the first order. Authority or ParamManager.
+    pub fn set_fixed_point_prices(
+        ctx: Context<AuthorityAction>,
+        fixed_point_prices: bool,
+    ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        let market = &mut ctx.accounts.market;
+        require!(market.next_order_id == 0, MatchingEngineError::PriceFormatLocked);
+        market.fixed_point_prices = fixed_point_prices;
+        let event = FixedPointPricesSetEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            fixed_point_prices,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Market '{}' fixed_point_prices = {}",
+            market.market_name,
+            fixed_point_prices
+        );
+        Ok(())
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // Order Lifecycle
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Place a buy or sell order.
+    /// - BUY: escrows (price * quantity) lamports in the Order PDA — or, on a
+    ///   token-quoted market, quote tokens from the owner's quote token
+    ///   account into the quote vault.
+    /// - SELL: no lamport escrow; on a base-escrowed market locks `quantity`
+    ///   base tokens from the owner's base token account in the base vault.
+    /// - expires_at: Unix timestamp after which the order is invalid (0 = no expiry).
+    /// Seeds: ["order", market, order_id_le] — order_id must be the market's
+    /// next_order_id, so concurrent placements race; prefer place_order_v2.
+    pub fn place_order(
+        mut ctx: Context<PlaceOrder>,
+        side: Side,
+        price: u64,
+        quantity: u64,
+        order_id: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        let request = OrderRequest {
+            side,
+            price,
+            price_q64: None,
+            quantity,
+            order_id,
+            expires_at,
+            beneficiary: ctx.accounts.beneficiary.as_ref().map(|b| b.key()),
+            referrer: ctx.accounts.referrer.as_ref().map(|r| r.key()),
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+            trigger: None,
+            display_quantity: 0,
+        };
+        place(&mut ctx, request)
+    }
+
+    /// Place a buy or sell order whose PDA is chosen by its owner rather than
+    /// by the market's order counter, so owners placing in the same slot
+    /// never collide. The program still assigns the next sequential
+    /// order_id, which events and fills report.
+    /// - BUY: escrows (price * quantity) lamports, from the owner's trading
+    ///   balance when passed and sufficient, else from the owner's wallet.
+    /// - Lamport markets only; no funder, beneficiary or rent subsidy.
+    /// - match_orders takes these orders like any other; cancel and close
+    ///   them with cancel_order_v2 / close_order_v2.
+    /// Seeds: ["order", market, owner, client_nonce_le]
+    pub fn place_order_v2(
+        ctx: Context<PlaceOrderV2>,
+        side: Side,
+        price: u64,
+        quantity: u64,
+        client_nonce: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let order_bump = ctx.bumps.order;
+        let escrow_bump = ctx.bumps.escrow_vault;
+        let event_authority_bump = ctx.bumps.event_authority;
+        let accounts = &mut *ctx.accounts;
+        require!(
+            !accounts.market.is_token_quoted() && !accounts.market.is_base_escrowed(),
+            MatchingEngineError::TokenQuoteUnsupported
+        );
+        let request = OrderRequest {
+            side,
+            price,
+            price_q64: None,
+            quantity,
+            order_id: accounts.market.next_order_id,
+            expires_at,
+            beneficiary: None,
+            referrer: accounts.referrer.as_ref().map(|r| r.key()),
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+            trigger: None,
+            display_quantity: 0,
+        };
+        let mut placement = check_placement(
+            &accounts.config,
+            &accounts.market,
+            accounts.trader_seat.is_some(),
+            accounts.user_stats.as_mut(),
+            accounts.open_orders.as_mut(),
+            &request,
+            clock.unix_timestamp,
+        )?;
+        placement.client_nonce = Some(client_nonce);
+        placement.escrow_bump = escrow_bump;
+        if request.side == Side::Buy {
+            placement.escrow_lamports = request.escrow()?;
+            placement.funded_from_balance = fund_escrow(
+                &accounts.escrow_vault.to_account_info(),
+                placement.escrow_lamports,
+                accounts.trading_balance.as_mut(),
+                accounts.owner.to_account_info(),
+                accounts.owner.to_account_info(),
+                &accounts.system_program,
+            )?;
+        }
+        open_order(
+            &mut accounts.market,
+            &mut accounts.order,
+            accounts.owner.key(),
+            order_bump,
+            &request,
+            &placement,
+            accounts.book_side.as_ref(),
+            clock.unix_timestamp,
+            Some(EventCpi::new(&accounts.event_authority, event_authority_bump)),
+        )
+    }
+
+    /// Quote both sides in one instruction: a BUY of `bid_quantity` at
+    /// `bid_price` and a SELL of `ask_quantity` at `ask_price`, created
+    /// together or not at all. The bid takes the market's next_order_id and
+    /// the ask the one after it; each order runs every placement check and
+    /// emits its own OrderPlacedEvent, and the bid escrows as in
+    /// place_order_v2. Fails with CrossedQuote unless the bid is priced
+    /// below the ask.
+    /// - Lamport markets only, without expiry, funder, beneficiary or rent
+    ///   subsidy.
+    /// Seeds: ["order", market, bid_order_id_le] and
+    /// ["order", market, ask_order_id_le]
+    pub fn place_dual_order(
+        ctx: Context<PlaceDualOrder>,
+        bid_price: u64,
+        bid_quantity: u64,
+        ask_price: u64,
+        ask_quantity: u64,
+        bid_order_id: u64,
+        ask_order_id: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let bumps = ctx.bumps;
+        let accounts = &mut *ctx.accounts;
+        require!(
+            !accounts.market.is_token_quoted() && !accounts.market.is_base_escrowed(),
+            MatchingEngineError::TokenQuoteUnsupported
+        );
+        require!(bid_price < ask_price, MatchingEngineError::CrossedQuote);
+
+        let quote = |side, price, quantity, order_id| OrderRequest {
+            side,
+            price,
+            price_q64: None,
+            quantity,
+            order_id,
+            expires_at: 0,
+            beneficiary: None,
+            referrer: None,
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+            trigger: None,
+            display_quantity: 0,
+        };
+
+        // ── Bid: the market's next_order_id ──────────────────────────────────
+        let bid = quote(Side::Buy, bid_price, bid_quantity, bid_order_id);
+        let mut placement = check_placement(
+            &accounts.config,
+            &accounts.market,
+            accounts.trader_seat.is_some(),
+            accounts.user_stats.as_mut(),
+            accounts.open_orders.as_mut(),
+            &bid,
+            clock.unix_timestamp,
+        )?;
+        placement.escrow_lamports = bid.escrow()?;
+        placement.escrow_bump = bumps.bid_escrow;
+        placement.funded_from_balance = fund_escrow(
+            &accounts.bid_escrow.to_account_info(),
+            placement.escrow_lamports,
+            accounts.trading_balance.as_mut(),
+            accounts.owner.to_account_info(),
+            accounts.owner.to_account_info(),
+            &accounts.system_program,
+        )?;
+        open_order(
+            &mut accounts.market,
+            &mut accounts.bid_order,
+            accounts.owner.key(),
+            bumps.bid_order,
+            &bid,
+            &placement,
+            accounts.bids.as_ref(),
+            clock.unix_timestamp,
+            Some(EventCpi::new(&accounts.event_authority, bumps.event_authority)),
+        )?;
+
+        // ── Ask: the id after it ─────────────────────────────────────────────
+        let ask = quote(Side::Sell, ask_price, ask_quantity, ask_order_id);
+        let mut placement = check_placement(
+            &accounts.config,
+            &accounts.market,
+            accounts.trader_seat.is_some(),
+            accounts.user_stats.as_mut(),
+            accounts.open_orders.as_mut(),
+            &ask,
+            clock.unix_timestamp,
+        )?;
+        placement.escrow_bump = bumps.ask_escrow;
+        open_order(
+            &mut accounts.market,
+            &mut accounts.ask_order,
+            accounts.owner.key(),
+            bumps.ask_order,
+            &ask,
+            &placement,
+            accounts.asks.as_ref(),
+            clock.unix_timestamp,
+            Some(EventCpi::new(&accounts.event_authority, bumps.event_authority)),
+        )
+    }
+
+    /// Re-quote in one instruction: cancel the owner's `old_order_id` as
+    /// cancel_order would, and place a GTC order on the same side at
+    /// `new_price` for `new_quantity` under the market's next_order_id. The
+    /// new order keeps the old one's expiry, post-only flag, beneficiary,
+    /// referrer, iceberg tranche size and deferred settlement. A BUY's escrow
+    /// is netted: the old escrow vault pays straight into the new order's and
+    /// only the difference is drawn from, or returned to, where it came from
+    /// (trading balance or wallet). A third-party-funded BUY is refunded to
+    /// its funder in full instead. Emits OrderCancelledEvent and
+    /// OrderPlacedEvent; if either half fails, both revert. The cancelled
+    /// order is left for close_order.
+    /// - Lamport markets with integer prices only.
+    /// Seeds: ["order", market, new_order_id_le]
+    pub fn cancel_and_replace(
+        ctx: Context<CancelAndReplace>,
+        _old_order_id: u64,
+        new_price: u64,
+        new_quantity: u64,
+        new_order_id: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let bumps = ctx.bumps;
+        let accounts = &mut *ctx.accounts;
+        require!(
+            !accounts.market.is_token_quoted() && !accounts.market.is_base_escrowed(),
+            MatchingEngineError::TokenQuoteUnsupported
+        );
+        let old = &mut accounts.old_order;
+        let side = old.side.clone();
+        let request = OrderRequest {
+            side: side.clone(),
+            price: new_price,
+            price_q64: None,
+            quantity: new_quantity,
+            order_id: new_order_id,
+            expires_at: old.expires_at,
+            beneficiary: (old.proceeds_recipient() != old.owner).then_some(old.proceeds_recipient()),
+            referrer: (old.referrer != Pubkey::default()).then_some(old.referrer),
+            time_in_force: TimeInForce::Gtc,
+            post_only: old.post_only,
+            trigger: None,
+            display_quantity: old.display_quantity,
+        };
+
+        // ── Carry the owner's own BUY escrow over, so the cancel refunds none
+        let carried = if side == Side::Buy && old.refund_recipient() == old.owner {
+            old.escrow_lamports
+        } else {
+            0
+        };
+        let carried_from_balance = old.funded_from_balance;
+        let settles_to_balance = old.settles_to_balance;
+        let system_program = accounts.system_program.to_account_info();
+        let old_escrow = EscrowAccounts {
+            vault: &accounts.old_escrow.to_account_info(),
+            system_program: &system_program,
+        };
+        let new_escrow = accounts.new_escrow.to_account_info();
+        if carried > 0 {
+            fund_vault_rent(&new_escrow, accounts.owner.to_account_info(), &system_program)?;
+            EscrowVault::of(old, &old_escrow)?.pay(&new_escrow, carried)?;
+            old.escrow_lamports = 0;
+        }
+
+        // ── Cancel ───────────────────────────────────────────────────────────
+        let funder = accounts.funder.as_ref().map(|f| f.to_account_info());
+        cancel_and_refund(
+            &mut accounts.market,
+            &mut accounts.old_order,
+            &accounts.owner.to_account_info(),
+            funder.as_ref(),
+            accounts.trading_balance.as_mut(),
+            accounts.user_stats.as_mut(),
+            accounts.open_orders.as_mut(),
+            accounts.book_side.as_ref(),
+            VaultAccounts {
+                vault: None,
+                token_program: None,
+                user: None,
+            },
+            old_escrow,
+            Some(EventCpi::new(&accounts.event_authority, bumps.event_authority)),
+        )?;
+
+        // ── Replace ──────────────────────────────────────────────────────────
+        let mut placement = check_placement(
+            &accounts.config,
+            &accounts.market,
+            accounts.trader_seat.is_some(),
+            accounts.user_stats.as_mut(),
+            accounts.open_orders.as_mut(),
+            &request,
+            clock.unix_timestamp,
+        )?;
+        placement.settles_to_balance = settles_to_balance;
+        placement.escrow_bump = bumps.new_escrow;
+        if side == Side::Buy {
+            let needed = request.escrow()?;
+            let new_vault = EscrowVault::new(
+                accounts.market.key(),
+                new_order_id,
+                bumps.new_escrow,
+                &EscrowAccounts {
+                    vault: &new_escrow,
+                    system_program: &system_program,
+                },
+            );
+            placement.escrow_lamports = needed;
+            placement.funded_from_balance = if carried == 0 {
+                fund_escrow(
+                    &new_escrow,
+                    needed,
+                    accounts.trading_balance.as_mut(),
+                    accounts.owner.to_account_info(),
+                    accounts.owner.to_account_info(),
+                    &accounts.system_program,
+                )?
+            } else if carried_from_balance {
+                // Only the difference moves, to or from the balance
+                let balance = accounts
+                    .trading_balance
+                    .as_mut()
+                    .ok_or(MatchingEngineError::TradingBalanceRequired)?;
+                if needed > carried {
+                    let delta = needed - carried;
+                    require!(
+                        balance.lamports >= delta,
+                        MatchingEngineError::InsufficientBalance
+                    );
+                    move_lamports(&balance.to_account_info(), &new_escrow, delta)?;
+                    balance.lamports -= delta;
+                } else {
+                    let surplus = carried - needed;
+                    new_vault.pay(&balance.to_account_info(), surplus)?;
+                    balance.lamports = balance
+                        .lamports
+                        .checked_add(surplus)
+                        .ok_or(MatchingEngineError::MathOverflow)?;
+                }
+                true
+            } else {
+                // ... or to or from the owner's wallet
+                if needed > carried {
+                    system_program::transfer(
+                        CpiContext::new(
+                            accounts.system_program.to_account_info(),
+                            system_program::Transfer {
+                                from: accounts.owner.to_account_info(),
+                                to: new_escrow.clone(),
+                            },
+                        ),
+                        needed - carried,
+                    )?;
+                } else {
+                    new_vault.pay(&accounts.owner.to_account_info(), carried - needed)?;
+                }
+                false
+            };
+        }
+        open_order(
+            &mut accounts.market,
+            &mut accounts.new_order,
+            accounts.owner.key(),
+            bumps.new_order,
+            &request,
+            &placement,
+            accounts.book_side.as_ref(),
+            clock.unix_timestamp,
+            Some(EventCpi::new(&accounts.event_authority, bumps.event_authority)),
+        )
+    }
+
+    /// place_order for fixed-point markets: the price is the Q64.64 value
+    /// `price + price_frac / 2^64` lamports per unit. A BUY escrows its
+    /// notional rounded up.
+    pub fn place_order_q64(
+        mut ctx: Context<PlaceOrder>,
+        side: Side,
+        price: u64,
+        quantity: u64,
+        order_id: u64,
+        expires_at: i64,
+        price_frac: u64,
+    ) -> Result<()> {
+        let request = OrderRequest {
+            side,
+            price,
+            price_q64: Some(((price as u128) << 64) | price_frac as u128),
+            quantity,
+            order_id,
+            expires_at,
+            beneficiary: ctx.accounts.beneficiary.as_ref().map(|b| b.key()),
+            referrer: ctx.accounts.referrer.as_ref().map(|r| r.key()),
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+            trigger: None,
+            display_quantity: 0,
+        };
+        place(&mut ctx, request)
+    }
+
+    /// place_order for market makers: fails with PostOnlyWouldCross instead
+    /// of placing an order that would cross the market's tracked best price
+    /// on the other side (a buy at or above the best ask, a sell at or below
+    /// the best bid), so the quote can only ever rest.
+    pub fn place_order_post_only(
+        mut ctx: Context<PlaceOrder>,
+        side: Side,
+        price: u64,
+        quantity: u64,
+        order_id: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        let request = OrderRequest {
+            side,
+            price,
+            price_q64: None,
+            quantity,
+            order_id,
+            expires_at,
+            beneficiary: ctx.accounts.beneficiary.as_ref().map(|b| b.key()),
+            referrer: ctx.accounts.referrer.as_ref().map(|r| r.key()),
+            time_in_force: TimeInForce::Gtc,
+            post_only: true,
+            trigger: None,
+            display_quantity: 0,
+        };
+        place(&mut ctx, request)
+    }
+
+    /// Place a stop order: a GTC limit order that rests as PendingTrigger —
+    /// escrowed like place_order but off the book and unmatchable — until
+    /// trigger_order sees a trade at or below (AtOrBelow) or at or above
+    /// (AtOrAbove) `trigger_price`. A BUY escrows in full here, since the
+    /// permissionless trigger can't draw on the owner's wallet; cancel_order
+    /// refunds it unchanged while the stop is pending. Integer-priced
+    /// markets only.
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_stop_order(
+        mut ctx: Context<PlaceOrder>,
+        side: Side,
+        price: u64,
+        quantity: u64,
+        order_id: u64,
+        expires_at: i64,
+        trigger_price: u64,
+        trigger_direction: TriggerDirection,
+    ) -> Result<()> {
+        let request = OrderRequest {
+            side,
+            price,
+            price_q64: None,
+            quantity,
+            order_id,
+            expires_at,
+            beneficiary: ctx.accounts.beneficiary.as_ref().map(|b| b.key()),
+            referrer: ctx.accounts.referrer.as_ref().map(|r| r.key()),
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+            trigger: Some((trigger_price, trigger_direction)),
+            display_quantity: 0,
+        };
+        place(&mut ctx, request)
+    }
+
+    /// Place an iceberg order: `quantity` is the true total, escrowed in
+    /// full for a BUY, but matching only ever fills the current
+    /// `display_quantity` tranche. Once a tranche is used up the order
+    /// reveals the next one and stays PartiallyFilled until the total is
+    /// exhausted. Order events report the displayed tranche, not the total;
+    /// cancel_order refunds the true remainder. Integer-priced markets only.
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_iceberg_order(
+        mut ctx: Context<PlaceOrder>,
+        side: Side,
+        price: u64,
+        quantity: u64,
+        order_id: u64,
+        expires_at: i64,
+        display_quantity: u64,
+    ) -> Result<()> {
+        require!(display_quantity > 0, MatchingEngineError::InvalidDisplayQuantity);
+        let request = OrderRequest {
+            side,
+            price,
+            price_q64: None,
+            quantity,
+            order_id,
+            expires_at,
+            beneficiary: ctx.accounts.beneficiary.as_ref().map(|b| b.key()),
+            referrer: ctx.accounts.referrer.as_ref().map(|r| r.key()),
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+            trigger: None,
+            display_quantity,
+        };
+        place(&mut ctx, request)
+    }
+
+    /// Open a pending stop order once the market's last trade — one executed
+    /// after the stop was placed — has reached its trigger price. The order
+    /// joins the book volumes and matches like any limit order from here.
+    /// Permissionless.
+    pub fn trigger_order(ctx: Context<TriggerOrder>, _order_id: u64) -> Result<()> {
+        let clock = Clock::get()?;
+        let market = &mut ctx.accounts.market;
+        let order = &mut ctx.accounts.order;
+        require!(
+            order.status == OrderStatus::PendingTrigger,
+            MatchingEngineError::OrderNotPendingTrigger
+        );
+        require!(!market.is_paused, MatchingEngineError::MarketPaused);
+        require!(!market.is_archiving, MatchingEngineError::MarketArchiving);
+        require!(
+            !order.is_expired(clock.unix_timestamp),
+            MatchingEngineError::OrderExpired
+        );
+        require!(
+            market.last_trade_ts >= order.timestamp
+                && order.trigger_reached(market.last_trade_price),
+            MatchingEngineError::TriggerNotReached
+        );
+
+        order.status = OrderStatus::Open;
+        order.placed_slot = clock.slot;
+        order.bump_update_count();
+        add_to_book(market, order)?;
+        sync_book(market, ctx.accounts.book_side.as_ref(), order)?;
+
+        let event = StopOrderTriggeredEvent {
+            market: market.key(),
+            order_id: order.order_id,
+            owner: order.owner,
+            triggered_by: ctx.accounts.caller.key(),
+            trigger_price: order.trigger_price,
+            last_trade_price: market.last_trade_price,
+            timestamp: clock.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Stop order #{} triggered at last price {}",
+            order.order_id,
+            market.last_trade_price
+        );
+        Ok(())
+    }
+
+    /// place_order with a time in force, taking resting liquidity at
+    /// placement. The resting counter-orders come in `remaining_accounts`
+    /// as (maker order, maker wallet, maker seat, maker escrow vault)
+    /// groups, at most TRADE_BATCH_CAPACITY, the wallet being the ask's
+    /// proceeds recipient or the bid's refund recipient, the seat the maker
+    /// owner's (or the program id for none), the vault a bid's escrow vault
+    /// (any account for an ask). Each fills like match_orders (at the ask's
+    /// price, fee on the seller, waived for an exempt seat) until this order
+    /// is filled; a maker that can't match fails the instruction.
+    /// - Gtc: the unfilled rest stays on the book.
+    /// - Ioc: the unfilled rest is cancelled and its escrow refunded here.
+    /// - Fok: fails with FillOrKillNotFilled unless the whole quantity fills.
+    /// IOC / FOK orders never rest, so they skip maker gating and the
+    /// taker-only window. Indexed makers need `maker_book_side`.
+    /// Lamport-quoted markets only; makers funded from a trading balance,
+    /// counted in user stats or listed in OpenOrders are left to
+    /// match_orders.
+    pub fn place_order_tif<'info>(
+        mut ctx: Context<'_, '_, 'info, 'info, PlaceOrder<'info>>,
+        side: Side,
+        price: u64,
+        quantity: u64,
+        order_id: u64,
+        expires_at: i64,
+        time_in_force: TimeInForce,
+    ) -> Result<()> {
+        require!(
+            !ctx.accounts.market.is_token_quoted() && !ctx.accounts.market.is_base_escrowed(),
+            MatchingEngineError::TokenQuoteUnsupported
+        );
+        let request = OrderRequest {
+            side,
+            price,
+            price_q64: None,
+            quantity,
+            order_id,
+            expires_at,
+            beneficiary: ctx.accounts.beneficiary.as_ref().map(|b| b.key()),
+            referrer: ctx.accounts.referrer.as_ref().map(|r| r.key()),
+            time_in_force,
+            post_only: false,
+            trigger: None,
+            display_quantity: 0,
+        };
+        place(&mut ctx, request)?;
+        fill_at_placement(&mut ctx)?;
+
+        let accounts = &mut *ctx.accounts;
+        match time_in_force {
+            TimeInForce::Gtc => sync_book(
+                &mut accounts.market,
+                accounts.book_side.as_ref(),
+                &accounts.order,
+            )?,
+            TimeInForce::Ioc => {
+                if accounts.order.is_active() {
+                    let funder = accounts.funder.as_ref().map(|f| f.to_account_info());
+                    cancel_and_refund(
+                        &mut accounts.market,
+                        &mut accounts.order,
+                        &accounts.owner.to_account_info(),
+                        funder.as_ref(),
+                        accounts.trading_balance.as_mut(),
+                        accounts.user_stats.as_mut(),
+                        accounts.open_orders.as_mut(),
+                        accounts.book_side.as_ref(),
+                        VaultAccounts::default(),
+                        EscrowAccounts {
+                            vault: &accounts.escrow_vault.to_account_info(),
+                            system_program: &accounts.system_program.to_account_info(),
+                        },
+                        None,
+                    )?;
+                }
+            }
+            TimeInForce::Fok => require!(
+                accounts.order.status == OrderStatus::Filled,
+                MatchingEngineError::FillOrKillNotFilled
+            ),
+        }
+        Ok(())
+    }
+
+    /// Market order for a taker: fill against the resting orders passed in
+    /// `remaining_accounts` as (maker order, maker wallet, maker seat, maker
+    /// escrow vault) groups, at most TRADE_BATCH_CAPACITY, in the order
+    /// given, until `max_quantity` has filled. Each fill is at the maker's
+    /// price, bounded by `limit_price` (no higher for a buy, no lower for a
+    /// sell); the wallet is the ask's proceeds recipient or the bid's refund
+    /// recipient, the seat the maker owner's (or the program id for none),
+    /// its fee exemption applying as does the taker's `trader_seat`, and
+    /// the vault a bid's escrow vault (any account for an ask). No Order is
+    /// created for the taker: a buy pays sellers and fees straight from the
+    /// taker's wallet, a sell is paid out of the bid's escrow. A maker that
+    /// can't fill fails the instruction, as does filling nothing
+    /// (FillBelowMinimum). Makers update and record TradeExecutedEvents as
+    /// in a match_orders fill, with Order::TAKER_ORDER_ID as the taker's
+    /// order id. Indexed makers need `book_side`. Integer-priced lamport
+    /// markets outside call auctions only; makers as for place_order_tif.
+    pub fn take_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, TakeOrder<'info>>,
+        side: Side,
+        max_quantity: u64,
+        limit_price: u64,
+    ) -> Result<()> {
+        let makers = ctx.remaining_accounts;
+        let market = &ctx.accounts.market;
+        require!(
+            !ctx.accounts.config.paused,
+            MatchingEngineError::ProtocolPaused
+        );
+        require!(
+            !market.is_token_quoted() && !market.is_base_escrowed(),
+            MatchingEngineError::TokenQuoteUnsupported
+        );
+        require!(
+            !market.fixed_point_prices,
+            MatchingEngineError::PriceFormatMismatch
+        );
+        require!(!market.auction_mode, MatchingEngineError::AuctionInProgress);
+        require!(!market.side_paused(&side), MatchingEngineError::SidePaused);
+        require!(
+            !market.permissioned || ctx.accounts.trader_seat.is_some(),
+            MatchingEngineError::TraderNotWhitelisted
+        );
+        require!(limit_price > 0, MatchingEngineError::InvalidPrice);
+        require!(max_quantity > 0, MatchingEngineError::InvalidQuantity);
+        require!(
+            makers.len().is_multiple_of(Market::MAKER_ACCOUNTS)
+                && makers.len() / Market::MAKER_ACCOUNTS <= TRADE_BATCH_CAPACITY,
+            MatchingEngineError::InvalidMakerAccounts
+        );
+
+        let clock = Clock::get()?;
+        let accounts = &mut *ctx.accounts;
+        let market_key = accounts.market.key();
+        let taker = accounts.taker.to_account_info();
+        let taker_exempt = accounts.trader_seat.as_ref().is_some_and(|seat| seat.fee_exempt);
+        let system_program = accounts.system_program.to_account_info();
+        let treasury = accounts.treasury.as_ref().map(|t| t.to_account_info());
+        let mut fees = FillFees {
+            config: &accounts.config,
+            fee_config: accounts.fee_config.as_mut(),
+            treasury: treasury.as_ref(),
+            fee_vault: accounts.fee_vault.as_mut(),
+            matcher: None,
+        };
+        // Stands in for the taker's side of each fill; never written
+        let mut stand_in = Order {
+            owner: taker.key(),
+            market: market_key,
+            order_id: Order::TAKER_ORDER_ID,
+            side: side.clone(),
+            price: limit_price,
+            quantity: max_quantity,
+            status: OrderStatus::Open,
+            timestamp: clock.unix_timestamp,
+            fee_bps: accounts.market.fee_bps,
+            taker_fee_bps: accounts.market.taker_fee_bps,
+            version: Order::VERSION,
+            ..Order::default()
+        };
+        let mut batch = TakerFills::default();
+
+        for group in makers.chunks(Market::MAKER_ACCOUNTS) {
+            if !stand_in.is_active() {
+                break;
+            }
+            let mut maker = load_maker(&group[0], market_key)?;
+            let wallet = &group[1];
+            let maker_exempt = maker_fee_exempt(&group[2], &maker)?;
+            let settlement = match side {
+                Side::Buy => settle_fill(
+                    &mut accounts.market,
+                    &mut stand_in,
+                    &mut maker,
+                    wallet,
+                    BidFunds::Taker {
+                        wallet: &taker,
+                        system_program: &system_program,
+                    },
+                    None,
+                    &mut fees,
+                    (taker_exempt, maker_exempt),
+                    &clock,
+                    None,
+                )?,
+                // Selling into a bid fills at the bid's price
+                Side::Sell => {
+                    let maker_price = maker.price;
+                    let escrow = EscrowAccounts {
+                        vault: &group[3],
+                        system_program: &system_program,
+                    };
+                    let bid_funds = BidFunds::Escrow {
+                        vault: EscrowVault::of(&maker, &escrow)?,
+                        refund: RefundTo::Wallet(wallet),
+                    };
+                    settle_fill(
+                        &mut accounts.market,
+                        &mut maker,
+                        &mut stand_in,
+                        &taker,
+                        bid_funds,
+                        None,
+                        &mut fees,
+                        (maker_exempt, taker_exempt),
+                        &clock,
+                        Some(maker_price),
+                    )?
+                }
+            };
+            sync_book(&mut accounts.market, accounts.book_side.as_ref(), &maker)?;
+            maker.exit(&crate::ID)?;
+            batch.push(maker.order_id, &settlement, accounts.market.trade_seq);
+        }
+        require!(batch.count > 0, MatchingEngineError::FillBelowMinimum);
+        msg!(
+            "Taker {} took {} units from {} maker(s)",
+            taker.key(),
+            batch.total_quantity,
+            batch.count
+        );
+        batch.record(&mut accounts.market, &stand_in, clock.unix_timestamp)
+    }
+
+    /// Match a compatible bid (buy) and ask (sell) order.
+    ///
+    /// - Validates price crossing: bid.price >= ask.price
+    /// - Optional slippage guard: max_slippage_bps (0 = no limit)
+    /// - Optional staleness guards: min_expected_{bid,ask}_remaining (0 = off) fail
+    ///   with StaleMakerState if either order shrank since the crank read it
+    /// - A fill of nothing fails with FillBelowMinimum; match_orders_v2 adds
+    ///   fill size guards
+    /// - Deducts protocol fee from seller payment → treasury
+    /// - Rounds the seller payment down; the rounding dust also goes to the
+    ///   treasury and is counted in market.dust_lamports
+    /// - Transfers lamports from bid escrow: seller_net + fee + buyer_refund
+    /// - Closes a remainder below the market's min_order_quantity with the
+    ///   fill: the order is Filled, and a bid's leftover escrow is added to
+    ///   the buyer refund
+    /// - On token-quoted markets pays the seller and buyer in quote tokens
+    ///   from the quote vault instead; the fee and dust stay in the vault
+    /// - On base-escrowed markets delivers the filled base tokens from the
+    ///   base vault to the buyer in the same instruction
+    /// - With fill_receipt passed, records the fill in a FillReceipt PDA at
+    ///   ["fill", market, trade_seq], rent paid by the matcher
+    /// - With trade_history passed, appends the fill to the market's ring
+    ///   buffer of recent trades
+    /// - Pays the matcher the market's crank reward from the fee vault, when
+    ///   passed (capped at the market fees it holds)
+    /// - is_locked guard prevents re-entrancy on same order
+    /// - Anyone can call this (decentralized crank model)
+    pub fn match_orders(
+        ctx: Context<MatchOrders>,
+        max_slippage_bps: u16,
+        min_expected_bid_remaining: u64,
+        min_expected_ask_remaining: u64,
+    ) -> Result<()> {
+        match_orders_v2(
+            ctx,
+            MatchOrdersParams {
+                max_slippage_bps,
+                min_expected_bid_remaining,
+                min_expected_ask_remaining,
+                ..MatchOrdersParams::default()
+            },
+        )
+    }
+
+    /// match_orders with its guards in one MatchOrdersParams, adding:
+    /// - Optional max_fill_quantity caps the fill below min(bid, ask remaining)
+    ///   — the capped order stays PartiallyFilled; Some(0) is InvalidQuantity
+    /// - min_fill_quantity (0 = off) fails with FillBelowMinimum rather than
+    ///   settle a smaller fill
+    pub fn match_orders_v2(ctx: Context<MatchOrders>, params: MatchOrdersParams) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(
+            !ctx.accounts.config.paused,
+            MatchingEngineError::ProtocolPaused
+        );
+        require_matcher_seat(&ctx.accounts.market, &ctx.accounts.matcher_seat)?;
+
+        // ── Validate the pair and compute settlement (shared with simulate_match)
+        let (maker_fee_exempt, taker_fee_exempt) = fee_exemptions(
+            &ctx.accounts.bid_order,
+            &ctx.accounts.ask_order,
+            &ctx.accounts.bid_seat,
+            &ctx.accounts.ask_seat,
+        );
+        let match_ctx = MatchContext {
+            is_paused: ctx.accounts.market.is_paused,
+            is_expired: ctx.accounts.market.is_expired(clock.unix_timestamp),
+            is_archiving: ctx.accounts.market.is_archiving,
+            fee_bps: trade_fee_bps(
+                &ctx.accounts.fee_config,
+                &ctx.accounts.bid_order,
+                &ctx.accounts.ask_order,
+                maker_fee_exempt || taker_fee_exempt,
+            ),
+            matcher_fee_bps: ctx.accounts.market.matcher_fee_bps,
+            max_slippage_bps: params.max_slippage_bps,
+            now: clock.unix_timestamp,
+            min_bid_remaining: params.min_expected_bid_remaining,
+            min_ask_remaining: params.min_expected_ask_remaining,
+            last_trade_price: ctx.accounts.market.last_trade_price,
+            price_band_bps: ctx.accounts.market.price_band_bps,
+            min_residual_quantity: ctx.accounts.market.min_order_quantity,
+            max_fill_quantity: params.max_fill_quantity,
+            min_fill_quantity: params.min_fill_quantity,
+            is_auction: ctx.accounts.market.auction_mode,
+            clearing_price: None,
+        };
+        let settlement = matching::compute_settlement(
+            &ctx.accounts.bid_order,
+            &ctx.accounts.ask_order,
+            &match_ctx,
+        )?;
+
+        // ── Verify owner accounts ─────────────────────────────────────────────
+        require!(
+            ctx.accounts.bid_owner.key() == ctx.accounts.bid_order.owner,
+            MatchingEngineError::BidOwnerMismatch
+        );
+        require!(
+            ctx.accounts.ask_owner.key() == ctx.accounts.ask_order.owner,
+            MatchingEngineError::AskOwnerMismatch
+        );
+
+        // Verify treasury account is the market's fee recipient
+        if ctx.accounts.fee_config.is_some() {
+            require!(
+                ctx.accounts.treasury.key() == ctx.accounts.market.fee_recipient,
+                MatchingEngineError::TreasuryMismatch
+            );
+        }
+
+        // Proceeds go to the ask's beneficiary, which must be passed when it
+        // isn't the seller
+        let proceeds_to = ctx.accounts.ask_order.proceeds_recipient();
+        if let Some(beneficiary) = &ctx.accounts.ask_beneficiary {
+            require!(
+                beneficiary.key() == proceeds_to,
+                MatchingEngineError::BeneficiaryMismatch
+            );
+        } else {
+            require!(
+                proceeds_to == ctx.accounts.ask_order.owner,
+                MatchingEngineError::BeneficiaryMismatch
+            );
+        }
+
+        // A referrer passed must be the ask's; leaving it out only forgoes
+        // the referral share
+        if let Some(referrer) = &ctx.accounts.ask_referrer {
+            require!(
+                ctx.accounts.ask_order.referrer != Pubkey::default()
+                    && referrer.key() == ctx.accounts.ask_order.referrer,
+                MatchingEngineError::ReferrerMismatch
+            );
+        }
+
+        // Counted orders must release their volume from the owner's stats
+        if ctx.accounts.bid_order.counted_in_stats {
+            require!(
+                ctx.accounts.bid_user_stats.is_some(),
+                MatchingEngineError::UserStatsRequired
+            );
+        }
+        if ctx.accounts.ask_order.counted_in_stats {
+            require!(
+                ctx.accounts.ask_user_stats.is_some(),
+                MatchingEngineError::UserStatsRequired
+            );
+        }
+        // As must listed orders their owner's OpenOrders
+        if ctx.accounts.bid_order.tracked_in_open_orders {
+            require!(
+                ctx.accounts.bid_open_orders.is_some(),
+                MatchingEngineError::OpenOrdersRequired
+            );
+        }
+        if ctx.accounts.ask_order.tracked_in_open_orders {
+            require!(
+                ctx.accounts.ask_open_orders.is_some(),
+                MatchingEngineError::OpenOrdersRequired
+            );
+        }
+        // And deferred-settlement orders their owner's UserBalance
+        if ctx.accounts.bid_order.settles_to_balance {
+            require!(
+                ctx.accounts.bid_user_balance.is_some(),
+                MatchingEngineError::UserBalanceRequired
+            );
+        }
+        if ctx.accounts.ask_order.settles_to_balance {
+            require!(
+                ctx.accounts.ask_user_balance.is_some(),
+                MatchingEngineError::UserBalanceRequired
+            );
+        }
+
+        // ── Set re-entrancy locks ─────────────────────────────────────────────
+        ctx.accounts.bid_order.is_locked = true;
+        ctx.accounts.ask_order.is_locked = true;
+
+        let MatchSettlement {
+            fill_quantity: fill_qty,
+            fill_price,
+            fill_price_q64,
+            fee_amount,
+            dust_amount,
+            matcher_fee_amount,
+            net_seller_payment,
+            buyer_refund,
+            total_debit,
+            ..
+        } = settlement;
+
+        let protocol_fee_amount = ctx.accounts.config.protocol_share(fee_amount);
+        let market_fee = fee_amount - protocol_fee_amount;
+        // The referrer's cut comes out of the market's share, in lamports
+        // only; one that can't take it leaves it with the market
+        let referral_amount = match &ctx.accounts.ask_referrer {
+            Some(referrer) if !ctx.accounts.bid_order.escrow_in_vault => {
+                let amount = ctx.accounts.market.referral_share(market_fee);
+                if amount > 0 && can_receive_fees(referrer, amount)? {
+                    amount
+                } else {
+                    0
+                }
+            }
+            _ => 0,
+        };
+        let treasury_amount = (market_fee - referral_amount)
+            .checked_add(dust_amount)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        let mut fee_paid_to = ctx.accounts.treasury.key();
+        if ctx.accounts.bid_order.escrow_in_vault {
+            // ── Settle from the quote vault ───────────────────────────────────
+            // The seller and buyer are paid in quote tokens; the fee and dust
+            // stay in the vault, split between the market and the protocol.
+            let quote = VaultAccounts {
+                vault: ctx.accounts.quote_vault.as_deref(),
+                token_program: ctx.accounts.token_program.as_deref(),
+                user: None,
+            };
+            let vault = TokenVault::quote(ctx.accounts.market.key(), &ctx.accounts.market, &quote)?;
+            let seller_account = ctx
+                .accounts
+                .seller_quote_account
+                .as_deref()
+                .ok_or(MatchingEngineError::QuoteAccountsRequired)?;
+            vault.pay(seller_account, Some(proceeds_to), net_seller_payment)?;
+            if buyer_refund > 0 {
+                let buyer_account = ctx
+                    .accounts
+                    .buyer_quote_account
+                    .as_deref()
+                    .ok_or(MatchingEngineError::QuoteAccountsRequired)?;
+                vault.pay(
+                    buyer_account,
+                    Some(ctx.accounts.bid_order.refund_recipient()),
+                    buyer_refund,
+                )?;
+            }
+            fee_paid_to = vault.vault.key();
+
+            let market = &mut ctx.accounts.market;
+            market.quote_fees = market
+                .quote_fees
+                .checked_add(treasury_amount)
+                .ok_or(MatchingEngineError::MathOverflow)?;
+            market.quote_protocol_fees = market
+                .quote_protocol_fees
+                .checked_add(protocol_fee_amount)
+                .ok_or(MatchingEngineError::MathOverflow)?;
+            if let Some(fee_config) = &mut ctx.accounts.fee_config {
+                fee_config.protocol_accumulated_fees = fee_config
+                    .protocol_accumulated_fees
+                    .saturating_add(protocol_fee_amount);
+            }
+        } else {
+            // ── Transfer lamports out of the bid's escrow vault ───────────────────
+            // The vault holds its rent plus the tracked escrow; the debit may
+            // only ever come out of the latter.
+            let bid_escrow = ctx.accounts.bid_escrow.to_account_info();
+            let system_program = ctx.accounts.system_program.to_account_info();
+            let escrow = EscrowVault::new(
+                ctx.accounts.market.key(),
+                ctx.accounts.bid_order.order_id,
+                ctx.accounts.bid_order.escrow_bump,
+                &EscrowAccounts {
+                    vault: &bid_escrow,
+                    system_program: &system_program,
+                },
+            );
+            escrow.require_escrow(total_debit)?;
+
+            // Pay seller (net of fee) — into its UserBalance when the ask
+            // settles there
+            let payee = match (&ctx.accounts.ask_user_balance, &ctx.accounts.ask_beneficiary) {
+                (Some(balance), _) if ctx.accounts.ask_order.settles_to_balance => {
+                    balance.to_account_info()
+                }
+                (_, Some(beneficiary)) => beneficiary.to_account_info(),
+                _ => ctx.accounts.ask_owner.to_account_info(),
+            };
+            escrow.pay(&payee, net_seller_payment)?;
+
+            // Pay the matcher its cut of the seller payment
+            escrow.pay(&ctx.accounts.matcher.to_account_info(), matcher_fee_amount)?;
+
+            // Refund buyer overpay (price improvement, plus a dust remainder's
+            // escrow) — back to the trading balance when the bid was funded
+            // from one, into the UserBalance when it settles there, else to
+            // whoever paid
+            let mut buyer_credit = 0;
+            if ctx.accounts.bid_order.funded_from_balance {
+                let balance = ctx
+                    .accounts
+                    .bid_trading_balance
+                    .as_mut()
+                    .ok_or(MatchingEngineError::TradingBalanceRequired)?;
+                escrow.pay(&balance.to_account_info(), buyer_refund)?;
+                balance.lamports = balance
+                    .lamports
+                    .checked_add(buyer_refund)
+                    .ok_or(MatchingEngineError::MathOverflow)?;
+            } else if ctx.accounts.bid_order.settles_to_balance {
+                let balance = ctx
+                    .accounts
+                    .bid_user_balance
+                    .as_ref()
+                    .ok_or(MatchingEngineError::UserBalanceRequired)?;
+                escrow.pay(&balance.to_account_info(), buyer_refund)?;
+                buyer_credit = buyer_refund;
+            } else {
+                let bid_owner = ctx.accounts.bid_owner.to_account_info();
+                let bid_funder = ctx.accounts.bid_funder.as_ref().map(|f| f.to_account_info());
+                let wallet = refund_wallet(&ctx.accounts.bid_order, &bid_owner, bid_funder.as_ref())?;
+                escrow.pay(wallet, buyer_refund)?;
+            }
+
+            // Record the credits as pending. A self-trade passes the same
+            // UserBalance twice; credit both copies so whichever is
+            // serialized last is still correct.
+            let seller_credit = if ctx.accounts.ask_order.settles_to_balance {
+                net_seller_payment
+            } else {
+                0
+            };
+            let (buyer, seller) = (ctx.accounts.bid_order.owner, ctx.accounts.ask_order.owner);
+            for balance in [
+                ctx.accounts.bid_user_balance.as_mut(),
+                ctx.accounts.ask_user_balance.as_mut(),
+            ]
+            .into_iter()
+            .flatten()
+            {
+                if balance.owner == buyer {
+                    balance.credit(buyer_credit)?;
+                }
+                if balance.owner == seller {
+                    balance.credit(seller_credit)?;
+                }
+            }
+
+            // Split off the protocol's share of the fee into the fee vault
+            if protocol_fee_amount > 0 {
+                let vault = ctx
+                    .accounts
+                    .fee_vault
+                    .as_mut()
+                    .ok_or(MatchingEngineError::FeeVaultRequired)?;
+                escrow.pay(&vault.to_account_info(), protocol_fee_amount)?;
+                vault.protocol_fees = vault
+                    .protocol_fees
+                    .checked_add(protocol_fee_amount)
+                    .ok_or(MatchingEngineError::MathOverflow)?;
+                if let Some(fee_config) = &mut ctx.accounts.fee_config {
+                    fee_config.protocol_accumulated_fees = fee_config
+                        .protocol_accumulated_fees
+                        .saturating_add(protocol_fee_amount);
+                }
+            }
+
+            if referral_amount > 0 {
+                let referrer = ctx
+                    .accounts
+                    .ask_referrer
+                    .as_ref()
+                    .ok_or(MatchingEngineError::ReferrerMismatch)?;
+                escrow.pay(&referrer.to_account_info(), referral_amount)?;
+            }
+
+            // Send the market's fee (and rounding dust) to the fee recipient —
+            // directly when it can take the lamports, otherwise into the fee vault
+            if treasury_amount > 0 {
+                let treasury = ctx.accounts.treasury.to_account_info();
+                let recipient = if can_receive_fees(&treasury, treasury_amount)? {
+                    treasury
+                } else {
+                    ctx.accounts
+                        .fee_vault
+                        .as_ref()
+                        .ok_or(MatchingEngineError::FeeVaultRequired)?
+                        .to_account_info()
+                };
+                fee_paid_to = recipient.key();
+                escrow.pay(&recipient, treasury_amount)?;
+
+            }
+        }
+
+        // Update accumulated_fees in FeeConfig
+        if let Some(fee_config) = &mut ctx.accounts.fee_config {
+            fee_config.accumulated_fees = fee_config
+                .accumulated_fees
+                .saturating_add(fee_amount);
+        }
+
+        ctx.accounts.market.dust_lamports = ctx
+            .accounts
+            .market
+            .dust_lamports
+            .checked_add(dust_amount)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+
+        ctx.accounts.bid_order.escrow_lamports = ctx
+            .accounts
+            .bid_order
+            .escrow_lamports
+            .checked_sub(total_debit)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+
+        // ── Deliver the base asset ────────────────────────────────────────────
+        // An ask with base tokens locked hands the filled quantity to the buyer.
+        if ctx.accounts.ask_order.base_escrow > 0 {
+            let base = VaultAccounts {
+                vault: ctx.accounts.base_vault.as_deref(),
+                token_program: ctx.accounts.token_program.as_deref(),
+                user: ctx.accounts.buyer_base_account.as_deref(),
+            };
+            let vault = TokenVault::base(ctx.accounts.market.key(), &ctx.accounts.market, &base)?;
+            vault.pay(vault.user()?, Some(ctx.accounts.bid_order.owner), fill_qty)?;
+            ctx.accounts.ask_order.base_escrow = ctx
+                .accounts
+                .ask_order
+                .base_escrow
+                .checked_sub(fill_qty)
+                .ok_or(MatchingEngineError::MathOverflow)?;
+        }
+
+        // ── Update fill state ─────────────────────────────────────────────────
+        ctx.accounts.bid_order.filled_quantity = settlement.bid_filled_after;
+        ctx.accounts.ask_order.filled_quantity = settlement.ask_filled_after;
+        ctx.accounts.bid_order.set_status(settlement.bid_status_after, clock.unix_timestamp);
+        ctx.accounts.ask_order.set_status(settlement.ask_status_after, clock.unix_timestamp);
+        ctx.accounts.bid_order.consume_display(fill_qty);
+        ctx.accounts.ask_order.consume_display(fill_qty);
+        ctx.accounts.bid_order.bump_update_count();
+        ctx.accounts.ask_order.bump_update_count();
+        check_filled_pair(&ctx.accounts.bid_order, &ctx.accounts.ask_order)?;
+
+        // ── Release open volume ───────────────────────────────────────────────
+        // Dust remainders closed with the fill leave the book along with it
+        let bid_released = fill_qty + settlement.bid_residual;
+        let ask_released = fill_qty + settlement.ask_residual;
+        let market = &mut ctx.accounts.market;
+        market.total_bid_volume = market.total_bid_volume.saturating_sub(bid_released);
+        market.total_ask_volume = market.total_ask_volume.saturating_sub(ask_released);
+        market.remove_from_top_of_book(&ctx.accounts.bid_order, bid_released);
+        market.remove_from_top_of_book(&ctx.accounts.ask_order, ask_released);
+        market.record_trade(
+            fill_price,
+            fill_qty,
+            settlement.gross_seller_payment,
+            clock.unix_timestamp,
+        )?;
+        let trade_seq = market.trade_seq;
+        for status in [&ctx.accounts.bid_order.status, &ctx.accounts.ask_order.status] {
+            if *status == OrderStatus::Filled {
+                market.open_order_count = market.open_order_count.saturating_sub(1);
+            }
+        }
+        // A self-trade passes the same stats PDA twice; update both copies so
+        // whichever is serialized last is still correct.
+        let bid_counted = ctx.accounts.bid_order.counted_in_stats;
+        let ask_counted = ctx.accounts.ask_order.counted_in_stats;
+        let bid_closed = ctx.accounts.bid_order.status == OrderStatus::Filled;
+        let ask_closed = ctx.accounts.ask_order.status == OrderStatus::Filled;
+        let notional = settlement.gross_seller_payment;
+        let (buyer, seller) = (ctx.accounts.bid_order.owner, ctx.accounts.ask_order.owner);
+        for stats in [
+            ctx.accounts.bid_user_stats.as_mut(),
+            ctx.accounts.ask_user_stats.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if bid_counted && stats.owner == buyer {
+                stats.record_fill(&Side::Buy, fill_qty, notional, bid_closed);
+                stats.release(&Side::Buy, settlement.bid_residual);
+            }
+            if ask_counted && stats.owner == seller {
+                stats.record_fill(&Side::Sell, fill_qty, notional, ask_closed);
+                stats.release(&Side::Sell, settlement.ask_residual);
+            }
+        }
+        // Filled orders leave their owners' OpenOrders (both copies again)
+        let closed_ids = [
+            (ctx.accounts.bid_order.tracked_in_open_orders && bid_closed)
+                .then_some((buyer, ctx.accounts.bid_order.order_id)),
+            (ctx.accounts.ask_order.tracked_in_open_orders && ask_closed)
+                .then_some((seller, ctx.accounts.ask_order.order_id)),
+        ];
+        for open_orders in [
+            ctx.accounts.bid_open_orders.as_mut(),
+            ctx.accounts.ask_open_orders.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            for (owner, order_id) in closed_ids.into_iter().flatten() {
+                if open_orders.owner == owner {
+                    open_orders.remove(order_id);
+                }
+            }
+        }
+        // Indexed orders move to their new remainder (or leave) in their BookSide
+        let accounts = &mut *ctx.accounts;
+        sync_book(&mut accounts.market, accounts.bids.as_ref(), &accounts.bid_order)?;
+        sync_book(&mut accounts.market, accounts.asks.as_ref(), &accounts.ask_order)?;
+
+        // ── Crank reward ──────────────────────────────────────────────────────
+        // Scales with how long the cross has stood — approximated by the
+        // taker (newer) order's resting age — and is paid from the market
+        // fees in the fee vault, as far as they go. No vault, no reward.
+        let taker_slot = if ctx.accounts.bid_order.order_id < ctx.accounts.ask_order.order_id {
+            ctx.accounts.ask_order.placed_slot
+        } else {
+            ctx.accounts.bid_order.placed_slot
+        };
+        let age_slots = if taker_slot == 0 {
+            0
+        } else {
+            clock.slot.saturating_sub(taker_slot)
+        };
+        let market = &ctx.accounts.market;
+        let mut crank_reward = solamatch_core::crank_reward(
+            market.crank_reward_base,
+            market.crank_reward_per_slot,
+            market.crank_reward_max,
+            age_slots,
+        );
+        match &ctx.accounts.fee_vault {
+            Some(vault) if crank_reward > 0 => {
+                crank_reward = crank_reward.min(market_fees_available(vault)?);
+                move_lamports(
+                    &vault.to_account_info(),
+                    &ctx.accounts.matcher.to_account_info(),
+                    crank_reward,
+                )?;
+            }
+            _ => crank_reward = 0,
+        }
+
+        if let Some(matcher_stats) = &mut ctx.accounts.matcher_stats {
+            matcher_stats.record_match(fill_qty, notional, fee_amount, clock.slot);
+            matcher_stats.record_reward(crank_reward.saturating_add(matcher_fee_amount));
+        }
+
+        // ── Fill receipt ──────────────────────────────────────────────────────
+        if let (Some(receipt), Some(bump)) =
+            (&mut ctx.accounts.fill_receipt, ctx.bumps.fill_receipt)
+        {
+            receipt.market = ctx.accounts.market.key();
+            receipt.fill_seq = trade_seq;
+            receipt.bid_order_id = ctx.accounts.bid_order.order_id;
+            receipt.ask_order_id = ctx.accounts.ask_order.order_id;
+            receipt.buyer = buyer;
+            receipt.seller = seller;
+            receipt.fill_price = fill_price;
+            receipt.fill_quantity = fill_qty;
+            receipt.matcher = ctx.accounts.matcher.key();
+            receipt.timestamp = clock.unix_timestamp;
+            receipt.bump = bump;
+        }
+        if let Some(history) = &ctx.accounts.trade_history {
+            history.load_mut()?.push(TradeRecord {
+                bid_order_id: ctx.accounts.bid_order.order_id,
+                ask_order_id: ctx.accounts.ask_order.order_id,
+                price: fill_price,
+                quantity: fill_qty,
+                timestamp: clock.unix_timestamp,
+            });
+        }
+
+        // ── Release re-entrancy locks ─────────────────────────────────────────
+        ctx.accounts.bid_order.is_locked = false;
+        ctx.accounts.ask_order.is_locked = false;
+
+        let event = TradeExecutedEvent {
+            bid_order_id: ctx.accounts.bid_order.order_id,
+            ask_order_id: ctx.accounts.ask_order.order_id,
+            market: ctx.accounts.bid_order.market,
+            buyer: ctx.accounts.bid_order.owner,
+            seller: ctx.accounts.ask_order.owner,
+            proceeds_to,
+            fill_price,
+            fill_price_q64,
+            fill_quantity: fill_qty,
+            fee_bps: match_ctx.fee_bps,
+            ask_is_taker: ctx.accounts.ask_order.order_id > ctx.accounts.bid_order.order_id,
+            fee_amount,
+            dust_amount,
+            fee_paid_to,
+            protocol_fee_amount,
+            referrer: ctx.accounts.ask_order.referrer,
+            referral_amount,
+            trade_seq,
+            maker_fee_exempt,
+            taker_fee_exempt,
+            crank_reward,
+            matcher_fee: matcher_fee_amount,
+            bid_residual: settlement.bid_residual,
+            ask_residual: settlement.ask_residual,
+            residual_refund: settlement.residual_refund,
+            bid_remaining: book_remaining(&ctx.accounts.bid_order),
+            ask_remaining: book_remaining(&ctx.accounts.ask_order),
+            bid_status: ctx.accounts.bid_order.status.clone(),
+            ask_status: ctx.accounts.ask_order.status.clone(),
+            matcher: ctx.accounts.matcher.key(),
+            timestamp: clock.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event_cpi(
+            &mut ctx.accounts.market,
+            event,
+            Some(EventCpi::new(&ctx.accounts.event_authority, ctx.bumps.event_authority)),
+        )?;
+
+        msg!(
+            "Trade: {} units @ {} lamports | bid#{} x ask#{} | fee={} lamports",
+            fill_qty,
+            fill_price,
+            ctx.accounts.bid_order.order_id,
+            ctx.accounts.ask_order.order_id,
+            fee_amount,
+        );
+        Ok(())
+    }
+
+    /// Fill one bid against several asks in one instruction. The asks come
+    /// in `remaining_accounts` as (ask order, ask wallet, ask seat, unused)
+    /// groups, at most TRADE_BATCH_CAPACITY, the wallet being the ask's
+    /// proceeds recipient and the seat the ask owner's TraderSeat (or the
+    /// program id for none); the fourth slot, a bid's escrow vault
+    /// elsewhere, is ignored.
+    /// Each fills at its own price up to the bid's remainder, exactly as
+    /// match_orders would settle the pair (fee on the seller, improvement
+    /// refunded to the buyer, matcher fee to the matcher); the rest are
+    /// ignored once the bid is filled.
+    /// An ask that can't fill — closed, no longer active, no longer
+    /// crossing, or not a genuine order of this market — fails the whole
+    /// instruction, so a crank never pays for a partial sweep it didn't
+    /// build; with `lenient` it is skipped instead, untouched, and the sweep
+    /// goes on. Returns a MakerOutcome per ask reached: its fill, or the
+    /// error it was skipped for.
+    /// Seat fee exemptions apply as in match_orders, from `bid_seat` and
+    /// the asks' seat slots. Indexed asks need `asks`.
+    /// Lamport-quoted markets only, without crank reward or matcher stats;
+    /// asks funded from a trading balance, counted in user stats or listed
+    /// in OpenOrders are left to match_orders.
+    pub fn match_orders_multi<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MatchOrdersMulti<'info>>,
+        lenient: bool,
+    ) -> Result<Vec<MakerOutcome>> {
+        let asks = ctx.remaining_accounts;
+        require!(
+            !ctx.accounts.config.paused,
+            MatchingEngineError::ProtocolPaused
+        );
+        require_matcher_seat(&ctx.accounts.market, &ctx.accounts.matcher_seat)?;
+        require!(
+            !ctx.accounts.market.is_token_quoted() && !ctx.accounts.market.is_base_escrowed(),
+            MatchingEngineError::TokenQuoteUnsupported
+        );
+        require!(
+            !asks.is_empty()
+                && asks.len().is_multiple_of(Market::MAKER_ACCOUNTS)
+                && asks.len() / Market::MAKER_ACCOUNTS <= TRADE_BATCH_CAPACITY,
+            MatchingEngineError::InvalidMakerAccounts
+        );
+        require_keys_eq!(
+            ctx.accounts.bid_owner.key(),
+            ctx.accounts.bid_order.owner,
+            MatchingEngineError::BidOwnerMismatch
+        );
+
+        let clock = Clock::get()?;
+        let accounts = &mut *ctx.accounts;
+        let market_key = accounts.market.key();
+        let bid_owner = accounts.bid_owner.to_account_info();
+        let bid_funder = accounts.bid_funder.as_ref().map(|f| f.to_account_info());
+        let treasury = accounts.treasury.as_ref().map(|t| t.to_account_info());
+        let matcher = accounts.matcher.to_account_info();
+        let bid_escrow = accounts.bid_escrow.to_account_info();
+        let system_program = accounts.system_program.to_account_info();
+        let mut fees = FillFees {
+            config: &accounts.config,
+            fee_config: accounts.fee_config.as_mut(),
+            treasury: treasury.as_ref(),
+            fee_vault: accounts.fee_vault.as_mut(),
+            matcher: Some(&matcher),
+        };
+        let bid_exempt = accounts.bid_seat.as_ref().is_some_and(|seat| seat.fee_exempt);
+        let mut batch = TakerFills::default();
+        let mut outcomes = Vec::with_capacity(asks.len() / Market::MAKER_ACCOUNTS);
+
+        for group in asks.chunks(Market::MAKER_ACCOUNTS) {
+            if !accounts.bid_order.is_active() {
+                break;
+            }
+            // Everything a fill could reject is checked before anything moves,
+            // so a skipped ask leaves no trace
+            let checked = load_maker(&group[0], market_key).and_then(|ask| {
+                let exempt = (bid_exempt, maker_fee_exempt(&group[2], &ask)?);
+                if ask.in_book {
+                    order_book(&accounts.market, accounts.asks.as_ref(), &ask)?;
+                }
+                if lenient {
+                    let match_ctx =
+                        fill_context(&accounts.market, &accounts.bid_order, &ask, &fees, exempt, &clock, None)?;
+                    matching::compute_settlement(&accounts.bid_order, &ask, &match_ctx)?;
+                }
+                Ok((ask, exempt))
+            });
+            let (mut ask, exempt) = match checked {
+                Ok(checked) => checked,
+                Err(err) if lenient => {
+                    outcomes.push(MakerOutcome {
+                        maker: group[0].key(),
+                        filled_quantity: 0,
+                        skip_code: error_code(&err),
+                    });
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            let refund = if accounts.bid_order.funded_from_balance {
+                RefundTo::Balance(
+                    accounts
+                        .bid_trading_balance
+                        .as_mut()
+                        .ok_or(MatchingEngineError::TradingBalanceRequired)?,
+                )
+            } else {
+                RefundTo::Wallet(refund_wallet(
+                    &accounts.bid_order,
+                    &bid_owner,
+                    bid_funder.as_ref(),
+                )?)
+            };
+            let escrow = EscrowAccounts {
+                vault: &bid_escrow,
+                system_program: &system_program,
+            };
+            let bid_funds = BidFunds::Escrow {
+                vault: EscrowVault::of(&accounts.bid_order, &escrow)?,
+                refund,
+            };
+            let settlement = settle_fill(
+                &mut accounts.market,
+                &mut accounts.bid_order,
+                &mut ask,
+                &group[1],
+                bid_funds,
+                accounts.bid_user_stats.as_mut(),
+                &mut fees,
+                exempt,
+                &clock,
+                None,
+            )?;
+            sync_book(&mut accounts.market, accounts.asks.as_ref(), &ask)?;
+            ask.exit(&crate::ID)?;
+            batch.push(ask.order_id, &settlement, accounts.market.trade_seq);
+            outcomes.push(MakerOutcome {
+                maker: group[0].key(),
+                filled_quantity: settlement.fill_quantity,
+                skip_code: 0,
+            });
+        }
+        if accounts.bid_order.status == OrderStatus::Filled {
+            untrack_order(&accounts.bid_order, accounts.bid_open_orders.as_mut())?;
+        }
+        sync_book(&mut accounts.market, accounts.bids.as_ref(), &accounts.bid_order)?;
+        msg!(
+            "Bid #{} swept {} ask(s) for {} units",
+            accounts.bid_order.order_id,
+            batch.count,
+            batch.total_quantity
+        );
+        batch.record(&mut accounts.market, &accounts.bid_order, clock.unix_timestamp)?;
+        Ok(outcomes)
+    }
+
+    /// Settle a call auction at one `clearing_price`, from its end time.
+    /// The crossing pairs come in `remaining_accounts` as (bid order, bid
+    /// refund wallet, bid seat, ask order, ask proceeds wallet, ask seat,
+    /// bid escrow vault) groups, the wallets being the bid's funder-or-owner
+    /// and the ask's beneficiary, the seats the owners' TraderSeats (or the
+    /// program id for none), whose fee exemptions apply as in match_orders. Every
+    /// bid's limit must be at or above the clearing price and every ask's
+    /// at or below it, else the whole call fails with InvalidClearingPrice.
+    /// Each pair fills as much as both remainders allow, at the clearing
+    /// price: the ask is paid it (less fees), and a bid priced above it has
+    /// the difference refunded from its escrow. An order may appear in
+    /// several pairs; later pairs see its earlier fills.
+    /// Only pairs are checked, not that the price clears the whole book;
+    /// large auctions settle over several calls. Anyone may call it, and
+    /// is paid the matcher fee. Indexed orders need their side's `bids` or
+    /// `asks`.
+    /// Integer-priced lamport markets only; orders funded from a trading
+    /// balance, counted in user stats or listed in OpenOrders can't settle
+    /// here.
+    pub fn settle_auction<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleAuction<'info>>,
+        clearing_price: u64,
+    ) -> Result<()> {
+        let pairs = ctx.remaining_accounts;
+        let clock = Clock::get()?;
+        require!(
+            !ctx.accounts.config.paused,
+            MatchingEngineError::ProtocolPaused
+        );
+        let market = &ctx.accounts.market;
+        require!(
+            !market.is_token_quoted() && !market.is_base_escrowed(),
+            MatchingEngineError::TokenQuoteUnsupported
+        );
+        require!(
+            !market.fixed_point_prices,
+            MatchingEngineError::PriceFormatMismatch
+        );
+        require!(market.auction_mode, MatchingEngineError::AuctionNotActive);
+        require!(
+            clock.unix_timestamp >= market.auction_end_ts,
+            MatchingEngineError::AuctionNotEnded
+        );
+        require!(clearing_price > 0, MatchingEngineError::InvalidClearingPrice);
+        require!(
+            !pairs.is_empty() && pairs.len().is_multiple_of(Market::AUCTION_ACCOUNTS_PER_PAIR),
+            MatchingEngineError::InvalidMakerAccounts
+        );
+
+        let accounts = &mut *ctx.accounts;
+        let market_key = accounts.market.key();
+        let treasury = accounts.treasury.as_ref().map(|t| t.to_account_info());
+        let settler = accounts.settler.to_account_info();
+        let system_program = accounts.system_program.to_account_info();
+        let mut fees = FillFees {
+            config: &accounts.config,
+            fee_config: accounts.fee_config.as_mut(),
+            treasury: treasury.as_ref(),
+            fee_vault: accounts.fee_vault.as_mut(),
+            matcher: Some(&settler),
+        };
+
+        let mut fill_count: u32 = 0;
+        let mut total_quantity: u64 = 0;
+        for pair in pairs.chunks(Market::AUCTION_ACCOUNTS_PER_PAIR) {
+            let mut bid = load_maker(&pair[0], market_key)?;
+            let mut ask = load_maker(&pair[3], market_key)?;
+            let exempt = (maker_fee_exempt(&pair[2], &bid)?, maker_fee_exempt(&pair[5], &ask)?);
+            let escrow = EscrowAccounts {
+                vault: &pair[6],
+                system_program: &system_program,
+            };
+            let bid_funds = BidFunds::Escrow {
+                vault: EscrowVault::of(&bid, &escrow)?,
+                refund: RefundTo::Wallet(&pair[1]),
+            };
+            let settlement = settle_fill(
+                &mut accounts.market,
+                &mut bid,
+                &mut ask,
+                &pair[4],
+                bid_funds,
+                None,
+                &mut fees,
+                exempt,
+                &clock,
+                Some(clearing_price),
+            )?;
+            sync_book(&mut accounts.market, accounts.bids.as_ref(), &bid)?;
+            sync_book(&mut accounts.market, accounts.asks.as_ref(), &ask)?;
+            bid.exit(&crate::ID)?;
+            ask.exit(&crate::ID)?;
+            fill_count += 1;
+            total_quantity = total_quantity
+                .checked_add(settlement.fill_quantity)
+                .ok_or(MatchingEngineError::MathOverflow)?;
+        }
+
+        let market = &mut accounts.market;
+        let event = AuctionSettledEvent {
+            market: market_key,
+            settler: settler.key(),
+            clearing_price,
+            fill_count,
+            total_quantity,
+            total_notional: clearing_price
+                .checked_mul(total_quantity)
+                .ok_or(MatchingEngineError::MathOverflow)?,
+            timestamp: clock.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Auction settled: {} pair(s), {} units @ {} lamports",
+            fill_count,
+            total_quantity,
+            clearing_price
+        );
+        Ok(())
+    }
+
+    /// Close a FillReceipt once it has been archived off-chain, returning its
+    /// rent to the matcher that paid it. Matcher only; works after the
+    /// market itself is gone.
+    pub fn close_fill_receipt(ctx: Context<CloseFillReceipt>) -> Result<()> {
+        let receipt = &ctx.accounts.fill_receipt;
+        msg!(
+            "Fill receipt #{} of market {} closed",
+            receipt.fill_seq,
+            receipt.market
+        );
+        Ok(())
+    }
+
+    /// Preview a bid/ask match without mutating anything.
+    /// Runs the exact validation and math of match_orders and returns the
+    /// settlement breakdown (or the error code it would fail with).
+    pub fn simulate_match(
+        ctx: Context<SimulateMatch>,
+        max_slippage_bps: u16,
+    ) -> Result<SimulatedMatch> {
+        let now = Clock::get()?.unix_timestamp;
+        let (maker_fee_exempt, taker_fee_exempt) = fee_exemptions(
+            &ctx.accounts.bid_order,
+            &ctx.accounts.ask_order,
+            &ctx.accounts.bid_seat,
+            &ctx.accounts.ask_seat,
+        );
+        let match_ctx = MatchContext {
+            is_paused: ctx.accounts.market.is_paused,
+            is_expired: ctx.accounts.market.is_expired(now),
+            is_archiving: ctx.accounts.market.is_archiving,
+            fee_bps: trade_fee_bps(
+                &ctx.accounts.fee_config,
+                &ctx.accounts.bid_order,
+                &ctx.accounts.ask_order,
+                maker_fee_exempt || taker_fee_exempt,
+            ),
+            matcher_fee_bps: ctx.accounts.market.matcher_fee_bps,
+            max_slippage_bps,
+            now,
+            min_bid_remaining: 0,
+            min_ask_remaining: 0,
+            last_trade_price: ctx.accounts.market.last_trade_price,
+            price_band_bps: ctx.accounts.market.price_band_bps,
+            min_residual_quantity: ctx.accounts.market.min_order_quantity,
+            max_fill_quantity: None,
+            min_fill_quantity: 0,
+            is_auction: ctx.accounts.market.auction_mode,
+            clearing_price: None,
+        };
+        Ok(matching::simulate(
+            &ctx.accounts.bid_order,
+            &ctx.accounts.ask_order,
+            &match_ctx,
+        ))
+    }
+
+    /// Preview cancelling an order without mutating anything: the exact
+    /// escrow cancel_order would refund now, where it goes, and the rent
+    /// close_order would reclaim afterwards from the order and, separately,
+    /// its escrow vault (or the error code the cancel would fail with).
+    pub fn preview_cancel(ctx: Context<PreviewCancel>, _order_id: u64) -> Result<CancelPreview> {
+        let order = &ctx.accounts.order;
+        Ok(matching::preview_cancel(
+            order,
+            order.to_account_info().lamports(),
+            ctx.accounts.escrow_vault.lamports(),
+        ))
+    }
+
+    /// Time-weighted average fill price from `window_start` — a snapshot
+    /// returned by an earlier call, or price_cumulative and
+    /// last_twap_update_ts read off the market — to now. Also returns the
+    /// current snapshot for the caller to keep as its next window's start.
+    /// Seconds before the market's first fill count at price 0, so start
+    /// windows after it. Read-only, so other programs can CPI into it.
+    pub fn get_twap(ctx: Context<GetTwap>, window_start: TwapSnapshot) -> Result<TwapQuote> {
+        let now = Clock::get()?.unix_timestamp;
+        let current = ctx.accounts.market.twap_snapshot(now)?;
+        let twap = solamatch_core::time_weighted_average(
+            (window_start.price_cumulative, window_start.timestamp),
+            (current.price_cumulative, current.timestamp),
+        )
+        .ok_or(MatchingEngineError::InvalidTwapWindow)?;
+        Ok(TwapQuote {
+            twap,
+            window_secs: now - window_start.timestamp,
+            current,
+        })
+    }
+
+    /// Cancel an open or partially filled order.
+    /// Refunds escrowed lamports (or quote tokens, from the quote vault) to the buyer.
+    /// NOTE: cancel_order is NOT affected by the market pause — users can always reclaim funds.
+    /// `expected_update_count` (0 = skip) fails the cancel with StaleOrderState
+    /// if the order changed since the client read it, e.g. a fill landed first.
+    pub fn cancel_order(
+        ctx: Context<CancelOrder>,
+        _order_id: u64,
+        expected_update_count: u64,
+    ) -> Result<()> {
+        ctx.accounts.order.check_update_count(expected_update_count)?;
+        let event_authority_bump = ctx.bumps.event_authority;
+        let accounts = &mut *ctx.accounts;
+        let funder = accounts.funder.as_ref().map(|f| f.to_account_info());
+        let vault = match accounts.order.side {
+            Side::Buy => VaultAccounts {
+                vault: accounts.quote_vault.as_deref(),
+                token_program: accounts.token_program.as_deref(),
+                user: accounts.owner_quote_account.as_deref(),
+            },
+            Side::Sell => VaultAccounts {
+                vault: accounts.base_vault.as_deref(),
+                token_program: accounts.token_program.as_deref(),
+                user: accounts.owner_base_account.as_deref(),
+            },
+        };
+        cancel_and_refund(
+            &mut accounts.market,
+            &mut accounts.order,
+            &accounts.owner.to_account_info(),
+            funder.as_ref(),
+            accounts.trading_balance.as_mut(),
+            accounts.user_stats.as_mut(),
+            accounts.open_orders.as_mut(),
+            accounts.book_side.as_ref(),
+            vault,
+            EscrowAccounts {
+                vault: &accounts.escrow_vault.to_account_info(),
+                system_program: &accounts.system_program.to_account_info(),
+            },
+            Some(EventCpi::new(&accounts.event_authority, event_authority_bump)),
+        )?;
+        Ok(())
+    }
+
+    /// cancel_order for orders placed with place_order_v2, addressed by
+    /// their owner and client nonce.
+    pub fn cancel_order_v2(
+        ctx: Context<CancelOrderV2>,
+        _client_nonce: u64,
+        expected_update_count: u64,
+    ) -> Result<()> {
+        ctx.accounts.order.check_update_count(expected_update_count)?;
+        let event_authority_bump = ctx.bumps.event_authority;
+        let accounts = &mut *ctx.accounts;
+        cancel_and_refund(
+            &mut accounts.market,
+            &mut accounts.order,
+            &accounts.owner.to_account_info(),
+            None,
+            accounts.trading_balance.as_mut(),
+            accounts.user_stats.as_mut(),
+            accounts.open_orders.as_mut(),
+            accounts.book_side.as_ref(),
+            VaultAccounts {
+                vault: None,
+                token_program: None,
+                user: None,
+            },
+            EscrowAccounts {
+                vault: &accounts.escrow_vault.to_account_info(),
+                system_program: &accounts.system_program.to_account_info(),
+            },
+            Some(EventCpi::new(&accounts.event_authority, event_authority_bump)),
+        )?;
+        Ok(())
+    }
+
+    /// Cancel any number of the signer's orders on this market in one
+    /// instruction. The orders come in `remaining_accounts` as (order,
+    /// escrow vault) pairs; each is cancelled exactly as cancel_order would,
+    /// with its own OrderCancelledEvent. An account that isn't an active
+    /// order of the signer on this market — or whose refund needs an account
+    /// not passed here (trading balance, user stats, OpenOrders, token
+    /// vaults, its escrow vault, a third-party funder) — is skipped rather
+    /// than failing the batch, so a stale order list still cancels what it
+    /// can.
+    pub fn cancel_all_orders<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelAllOrders<'info>>,
+    ) -> Result<()> {
+        let event_authority_bump = ctx.bumps.event_authority;
+        let accounts = &mut *ctx.accounts;
+        let owner = accounts.owner.to_account_info();
+        let system_program = accounts.system_program.to_account_info();
+        require!(
+            ctx.remaining_accounts
+                .len()
+                .is_multiple_of(Market::CANCEL_ACCOUNTS_PER_ORDER),
+            MatchingEngineError::InvalidCancelAccounts
+        );
+        let mut cancelled: u64 = 0;
+        let mut refunded: u64 = 0;
+        for group in ctx.remaining_accounts.chunks(Market::CANCEL_ACCOUNTS_PER_ORDER) {
+            let (info, escrow_vault) = (&group[0], &group[1]);
+            let Ok(mut order) = Account::<Order>::try_from(info) else {
+                continue;
+            };
+            if !accounts.can_cancel(&order, info.key, escrow_vault.key) {
+                continue;
+            }
+            let vault = match order.side {
+                Side::Buy => VaultAccounts {
+                    vault: accounts.quote_vault.as_deref(),
+                    token_program: accounts.token_program.as_deref(),
+                    user: accounts.owner_quote_account.as_deref(),
+                },
+                Side::Sell => VaultAccounts {
+                    vault: accounts.base_vault.as_deref(),
+                    token_program: accounts.token_program.as_deref(),
+                    user: accounts.owner_base_account.as_deref(),
+                },
+            };
+            let book = match order.side {
+                Side::Buy => accounts.bids.as_ref(),
+                Side::Sell => accounts.asks.as_ref(),
+            };
+            let refund = cancel_and_refund(
+                &mut accounts.market,
+                &mut order,
+                &owner,
+                None,
+                accounts.trading_balance.as_mut(),
+                accounts.user_stats.as_mut(),
+                accounts.open_orders.as_mut(),
+                book,
+                vault,
+                EscrowAccounts {
+                    vault: escrow_vault,
+                    system_program: &system_program,
+                },
+                Some(EventCpi::new(&accounts.event_authority, event_authority_bump)),
+            )?;
+            // Persist now: the same order may be listed twice
+            order.exit(&crate::ID)?;
+            refunded = refunded.saturating_add(refund);
+            cancelled += 1;
+        }
+        msg!(
+            "Cancelled {} of {} order(s) for {}. Refund: {} lamports",
+            cancelled,
+            ctx.remaining_accounts.len() / Market::CANCEL_ACCOUNTS_PER_ORDER,
+            owner.key(),
+            refunded
+        );
+        Ok(())
+    }
+
+    /// Escape hatch for orders whose market account is gone or can't be
+    /// decoded (or, with a healthy market, when the protocol admin co-signs).
+    /// Validates the order from its own PDA, refunds any escrow straight to
+    /// the owner's (or funder's) wallet and marks it Cancelled. Market volumes, counts,
+    /// the owner's stats and OpenOrders are deliberately left untouched —
+    /// reconcile them afterwards.
+    pub fn emergency_cancel(ctx: Context<EmergencyCancel>, _order_id: u64) -> Result<()> {
+        let market_missing = !market_is_healthy(&ctx.accounts.market);
+        require!(
+            market_missing || ctx.accounts.admin.is_some(),
+            MatchingEngineError::EmergencyCancelNotAllowed
+        );
+
+        let order = &mut ctx.accounts.order;
+        require!(order.is_cancellable(), MatchingEngineError::OrderNotActive);
+        require!(!order.is_locked, MatchingEngineError::OrderLocked);
+        // Escrow in a market vault can't be released without its market
+        require!(
+            !order.escrow_in_vault && order.base_escrow == 0,
+            MatchingEngineError::TokenQuoteUnsupported
+        );
+
+        // Even balance-funded escrow goes to the wallet: the balance may be
+        // unreachable without the market.
+        let refund_lamports = order.escrow_lamports;
+        if refund_lamports > 0 {
+            let owner = ctx.accounts.owner.to_account_info();
+            let funder = ctx.accounts.funder.as_ref().map(|f| f.to_account_info());
+            let wallet = refund_wallet(order, &owner, funder.as_ref())?;
+            let escrow = EscrowAccounts {
+                vault: &ctx.accounts.escrow_vault.to_account_info(),
+                system_program: &ctx.accounts.system_program.to_account_info(),
+            };
+            EscrowVault::of(order, &escrow)?.pay(wallet, refund_lamports)?;
+        }
+        order.escrow_lamports = 0;
+        order.set_status(OrderStatus::Cancelled, Clock::get()?.unix_timestamp);
+        order.bump_update_count();
+
+        let event = EmergencyCancelEvent {
+            market: order.market,
+            order_id: order.order_id,
+            owner: order.owner,
+            side: order.side.clone(),
+            remaining_quantity: order.remaining_quantity(),
+            refund_lamports,
+            market_missing,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event_unchecked(&ctx.accounts.market, event)?;
+        msg!(
+            "Order #{} emergency-cancelled. Refund: {} lamports (market missing: {})",
+            order.order_id,
+            refund_lamports,
+            market_missing
+        );
+        Ok(())
+    }
+
+    /// Close a Filled or Cancelled order PDA and its escrow vault, returning
+    /// rent to the owner. Subsidized rent goes back to the market's
+    /// RentSubsidyVault instead; the vault's always goes to the owner.
+    pub fn close_order(ctx: Context<CloseOrder>, _order_id: u64) -> Result<()> {
+        let order = &ctx.accounts.order;
+        require!(
+            order.status == OrderStatus::Filled || order.status == OrderStatus::Cancelled,
+            MatchingEngineError::OrderNotClosed
+        );
+        require!(order.base_escrow == 0, MatchingEngineError::BaseEscrowLocked);
+        // Everything left on a closed order and its vault is rent
+        let vault_rent = close_escrow_vault(
+            order,
+            &ctx.accounts.escrow_vault,
+            &ctx.accounts.owner.to_account_info(),
+            &ctx.accounts.system_program,
+        )?;
+        let rent_reclaimed = order.to_account_info().lamports();
+        let reclaimed_to = if order.subsidized {
+            return_subsidized_rent(order, ctx.accounts.rent_subsidy_vault.as_mut())?
+        } else {
+            ctx.accounts.owner.key()
+        };
+        let event = OrderClosedEvent {
+            order_id: order.order_id,
+            owner: order.owner,
+            market: order.market,
+            rent_reclaimed,
+            reclaimed_to,
+            vault_rent_reclaimed: vault_rent,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event_cpi(
+            &mut ctx.accounts.market,
+            event,
+            Some(EventCpi::new(&ctx.accounts.event_authority, ctx.bumps.event_authority)),
+        )?;
+        msg!(
+            "Order #{} closed. Rent reclaimed to {}",
+            order.order_id,
+            reclaimed_to
+        );
+        Ok(())
+    }
+
+    /// close_order for orders placed with place_order_v2, addressed by their
+    /// owner and client nonce. The rent always returns to the owner.
+    pub fn close_order_v2(ctx: Context<CloseOrderV2>, _client_nonce: u64) -> Result<()> {
+        let order = &ctx.accounts.order;
+        require!(
+            order.status == OrderStatus::Filled || order.status == OrderStatus::Cancelled,
+            MatchingEngineError::OrderNotClosed
+        );
+        let vault_rent = close_escrow_vault(
+            order,
+            &ctx.accounts.escrow_vault,
+            &ctx.accounts.owner.to_account_info(),
+            &ctx.accounts.system_program,
+        )?;
+        let event = OrderClosedEvent {
+            order_id: order.order_id,
+            owner: order.owner,
+            market: order.market,
+            rent_reclaimed: order.to_account_info().lamports(),
+            reclaimed_to: order.owner,
+            vault_rent_reclaimed: vault_rent,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event_cpi(
+            &mut ctx.accounts.market,
+            event,
+            Some(EventCpi::new(&ctx.accounts.event_authority, ctx.bumps.event_authority)),
+        )?;
+        msg!(
+            "Order #{} closed. Rent reclaimed to {}",
+            order.order_id,
+            order.owner
+        );
+        Ok(())
+    }
+
+    /// close_order for anyone, once the order has been Filled or Cancelled
+    /// for the market's `sweep_delay_secs`. The caller keeps
+    /// SWEEP_TIP_BPS of the order's rent; the rest, and the escrow vault's,
+    /// returns to the owner (or the RentSubsidyVault for subsidized rent),
+    /// so abandoned orders don't pile up forever.
+    pub fn sweep_order(ctx: Context<SweepOrder>, _order_id: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let order = &ctx.accounts.order;
+        require!(order.is_closed(), MatchingEngineError::OrderNotClosed);
+        require!(order.base_escrow == 0, MatchingEngineError::BaseEscrowLocked);
+        ctx.accounts.market.check_sweepable(order.closed_at, now)?;
+
+        let order_info = order.to_account_info();
+        let rent = order_info.lamports();
+        let tip = (rent as u128 * Market::SWEEP_TIP_BPS as u128 / 10_000) as u64;
+        move_lamports(&order_info, &ctx.accounts.sweeper.to_account_info(), tip)?;
+        let vault_rent = close_escrow_vault(
+            order,
+            &ctx.accounts.escrow_vault,
+            &ctx.accounts.owner.to_account_info(),
+            &ctx.accounts.system_program,
+        )?;
+        // `close = owner` hands the owner whatever is still on the order
+        let reclaimed_to = if order.subsidized {
+            return_subsidized_rent(order, ctx.accounts.rent_subsidy_vault.as_mut())?
+        } else {
+            order.owner
+        };
+        let event = OrderSweptEvent {
+            order_id: order.order_id,
+            owner: order.owner,
+            market: order.market,
+            swept_by: ctx.accounts.sweeper.key(),
+            tip,
+            rent_reclaimed: rent - tip,
+            reclaimed_to,
+            vault_rent_reclaimed: vault_rent,
+            timestamp: now,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event_cpi(
+            &mut ctx.accounts.market,
+            event,
+            Some(EventCpi::new(&ctx.accounts.event_authority, ctx.bumps.event_authority)),
+        )?;
+        msg!(
+            "Order #{} swept by {}. Tip: {}, rent reclaimed to {}",
+            order.order_id,
+            ctx.accounts.sweeper.key(),
+            tip,
+            reclaimed_to
+        );
+        Ok(())
+    }
+
+    /// Carve `split_quantity` of an order's unfilled remainder into a new
+    /// order (id `new_order_id`, same side, price and fee snapshot) with its
+    /// own `expires_at` (0 = none). BUY escrow moves proportionally. The new
+    /// order inherits the original's timestamp, so it keeps its place in
+    /// time priority rather than queuing anew. An iceberg's slice keeps its
+    /// tranche size, and deferred settlement carries over. Market volumes
+    /// are unchanged.
+    /// Seeds: ["order", market, new_order_id_le]
+    pub fn split_order(
+        ctx: Context<SplitOrder>,
+        _order_id: u64,
+        new_order_id: u64,
+        split_quantity: u64,
+        new_expires_at: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let market = &ctx.accounts.market;
+        let order = &ctx.accounts.order;
+        require!(!market.is_archiving, MatchingEngineError::MarketArchiving);
+        require!(order.is_active(), MatchingEngineError::OrderNotActive);
+        require!(!order.is_locked, MatchingEngineError::OrderLocked);
+        require!(!order.is_expired(now), MatchingEngineError::OrderExpired);
+        require!(
+            split_quantity > 0 && split_quantity < order.remaining_quantity(),
+            MatchingEngineError::InvalidSplitQuantity
+        );
+        // Both pieces must be orders the market would accept
+        market.check_quantity(split_quantity)?;
+        market.check_quantity(order.remaining_quantity() - split_quantity)?;
+        require!(
+            new_order_id == market.next_order_id,
+            MatchingEngineError::InvalidOrderId
+        );
+        if new_expires_at > 0 {
+            require!(new_expires_at > now, MatchingEngineError::OrderExpired);
+        }
+
+        // The slice is one more open order for the owner
+        if order.counted_in_stats {
+            let stats = ctx
+                .accounts
+                .user_stats
+                .as_mut()
+                .ok_or(MatchingEngineError::UserStatsRequired)?;
+            market.check_open_order_cap(stats)?;
+            if market.on_probation(stats) {
+                require!(
+                    market.probation_max_open_orders == 0
+                        || stats.open_orders < market.probation_max_open_orders,
+                    MatchingEngineError::ProbationOpenOrderLimit
+                );
+            }
+            stats.open_orders = stats
+                .open_orders
+                .checked_add(1)
+                .ok_or(MatchingEngineError::MathOverflow)?;
+        }
+        // ... and listed next to the original
+        if order.tracked_in_open_orders {
+            ctx.accounts
+                .open_orders
+                .as_mut()
+                .ok_or(MatchingEngineError::OpenOrdersRequired)?
+                .insert(new_order_id)?;
+        }
+
+        // ── Move the slice's escrow ──────────────────────────────────────────
+        // Fixed-point escrow rounds up per order, so the two halves can need
+        // a lamport more than the original holds; the owner tops it up.
+        // Escrow in the quote vault stays put; only the books move.
+        let (escrow_moved, top_up) = if order.side == Side::Buy {
+            let moved = order.escrow_for(split_quantity)?;
+            let kept = order.escrow_for(order.remaining_quantity() - split_quantity)?;
+            let needed = moved
+                .checked_add(kept)
+                .ok_or(MatchingEngineError::MathOverflow)?;
+            (moved, needed.saturating_sub(order.escrow_lamports))
+        } else {
+            (0, 0)
+        };
+        require!(
+            top_up == 0 || !order.escrow_in_vault,
+            MatchingEngineError::TokenQuoteUnsupported
+        );
+        let in_vault = order.escrow_in_vault;
+        let system_program = ctx.accounts.system_program.to_account_info();
+        let escrow_vault = ctx.accounts.escrow_vault.to_account_info();
+        if top_up > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.clone(),
+                    system_program::Transfer {
+                        from: ctx.accounts.owner.to_account_info(),
+                        to: escrow_vault.clone(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+        if !in_vault && escrow_moved > 0 {
+            let new_escrow_vault = ctx.accounts.new_escrow_vault.to_account_info();
+            fund_vault_rent(&new_escrow_vault, ctx.accounts.owner.to_account_info(), &system_program)?;
+            let escrow = EscrowAccounts {
+                vault: &escrow_vault,
+                system_program: &system_program,
+            };
+            EscrowVault::of(order, &escrow)?.pay(&new_escrow_vault, escrow_moved)?;
+        }
+
+        let order = &mut ctx.accounts.order;
+        order.quantity -= split_quantity;
+        order.displayed_remaining = order.displayed_remaining.min(order.remaining_quantity());
+        // An ask's locked base tokens follow the quantity
+        let base_moved = if order.base_escrow > 0 { split_quantity } else { 0 };
+        order.base_escrow -= base_moved;
+        order.escrow_lamports = order
+            .escrow_lamports
+            .checked_add(top_up)
+            .and_then(|escrow| escrow.checked_sub(escrow_moved))
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        order.bump_update_count();
+
+        let new_order = &mut ctx.accounts.new_order;
+        new_order.owner = order.owner;
+        new_order.market = order.market;
+        new_order.order_id = new_order_id;
+        new_order.side = order.side.clone();
+        new_order.price = order.price;
+        new_order.quantity = split_quantity;
+        new_order.filled_quantity = 0;
+        new_order.status = OrderStatus::Open;
+        new_order.timestamp = order.timestamp;
+        new_order.bump = ctx.bumps.new_order;
+        new_order.version = Order::VERSION;
+        new_order.is_locked = false;
+        new_order.expires_at = new_expires_at;
+        new_order.escrow_lamports = escrow_moved;
+        new_order.funded_from_balance = order.funded_from_balance;
+        new_order.update_count = 1;
+        new_order.counted_in_stats = order.counted_in_stats;
+        new_order.tracked_in_open_orders = order.tracked_in_open_orders;
+        new_order.fee_bps = order.fee_bps;
+        new_order.taker_fee_bps = order.taker_fee_bps;
+        new_order.beneficiary = order.beneficiary;
+        new_order.referrer = order.referrer;
+        new_order.funder = order.funder;
+        new_order.placed_slot = order.placed_slot;
+        new_order.price_q64 = order.price_q64;
+        new_order.escrow_in_vault = order.escrow_in_vault;
+        new_order.base_escrow = base_moved;
+        new_order.escrow_bump = ctx.bumps.new_escrow_vault;
+        new_order.in_book = order.in_book;
+        // An iceberg's slice shows one tranche of its own, not its whole size
+        new_order.display_quantity = order.display_quantity;
+        new_order.displayed_remaining = order.display_quantity.min(split_quantity);
+        new_order.settles_to_balance = order.settles_to_balance;
+
+        let market = &mut ctx.accounts.market;
+        sync_book(market, ctx.accounts.book_side.as_ref(), order)?;
+        sync_book(market, ctx.accounts.book_side.as_ref(), new_order)?;
+        market.next_order_id = market
+            .next_order_id
+            .checked_add(1)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        market.open_order_count = market
+            .open_order_count
+            .checked_add(1)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+
+        let event = OrderSplitEvent {
+            market: market.key(),
+            owner: order.owner,
+            order_id: order.order_id,
+            new_order_id,
+            split_quantity,
+            escrow_moved,
+            new_expires_at,
+            timestamp: now,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Order #{} split: {} units → order #{}",
+            order.order_id,
+            split_quantity,
+            new_order_id
+        );
+        Ok(())
+    }
+
+    /// Fold an order into another of the owner's with the same side, price,
+    /// funding, iceberg tranche size and settlement: quantities, fills and
+    /// BUY escrow move to the survivor and the absorbed order and its escrow
+    /// vault close, returning only their rent. To stop merges jumping the
+    /// queue, the survivor takes the later timestamp (and slot), the earlier
+    /// expiry and the higher fee snapshot of the two.
+    pub fn merge_orders(
+        ctx: Context<MergeOrders>,
+        _survivor_order_id: u64,
+        _absorbed_order_id: u64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let survivor = &ctx.accounts.survivor;
+        let absorbed = &ctx.accounts.absorbed;
+        require!(
+            survivor.key() != absorbed.key()
+                && survivor.side == absorbed.side
+                && survivor.price == absorbed.price
+                && survivor.price_q64 == absorbed.price_q64
+                && survivor.funded_from_balance == absorbed.funded_from_balance
+                && survivor.counted_in_stats == absorbed.counted_in_stats
+                && survivor.tracked_in_open_orders == absorbed.tracked_in_open_orders
+                && survivor.in_book == absorbed.in_book
+                && survivor.display_quantity == absorbed.display_quantity
+                && survivor.settles_to_balance == absorbed.settles_to_balance
+                && survivor.proceeds_recipient() == absorbed.proceeds_recipient()
+                && survivor.refund_recipient() == absorbed.refund_recipient()
+                && survivor.referrer == absorbed.referrer,
+            MatchingEngineError::OrdersNotMergeable
+        );
+        for order in [survivor, absorbed] {
+            require!(order.is_active(), MatchingEngineError::OrderNotActive);
+            require!(!order.is_locked, MatchingEngineError::OrderLocked);
+            require!(!order.is_expired(now), MatchingEngineError::OrderExpired);
+        }
+
+        // Two open orders become one
+        if survivor.counted_in_stats {
+            let stats = ctx
+                .accounts
+                .user_stats
+                .as_mut()
+                .ok_or(MatchingEngineError::UserStatsRequired)?;
+            stats.open_orders = stats.open_orders.saturating_sub(1);
+        }
+        untrack_order(absorbed, ctx.accounts.open_orders.as_mut())?;
+
+        // ── Move the absorbed escrow; its rent closes back to the owner ──────
+        // (or to the rent subsidy vault, if it paid it)
+        let escrow_moved = absorbed.escrow_lamports;
+        let base_moved = absorbed.base_escrow;
+        let system_program = ctx.accounts.system_program.to_account_info();
+        let absorbed_escrow = EscrowAccounts {
+            vault: &ctx.accounts.absorbed_escrow.to_account_info(),
+            system_program: &system_program,
+        };
+        let absorbed_escrow = EscrowVault::of(absorbed, &absorbed_escrow)?;
+        if !absorbed.escrow_in_vault && escrow_moved > 0 {
+            let survivor_escrow = ctx.accounts.survivor_escrow.to_account_info();
+            fund_vault_rent(&survivor_escrow, ctx.accounts.owner.to_account_info(), &system_program)?;
+            absorbed_escrow.pay(&survivor_escrow, escrow_moved)?;
+        }
+        absorbed_escrow.close(&ctx.accounts.owner.to_account_info())?;
+        if absorbed.subsidized {
+            return_subsidized_rent(absorbed, ctx.accounts.rent_subsidy_vault.as_mut())?;
+        }
+        let (absorbed_id, absorbed_quantity, absorbed_filled) =
+            (absorbed.order_id, absorbed.quantity, absorbed.filled_quantity);
+        let (absorbed_ts, absorbed_expiry, absorbed_fee_bps, absorbed_taker_fee_bps, absorbed_slot) = (
+            absorbed.timestamp,
+            absorbed.expires_at,
+            absorbed.fee_bps,
+            absorbed.taker_fee_bps,
+            absorbed.placed_slot,
+        );
+
+        let survivor = &mut ctx.accounts.survivor;
+        survivor.quantity = survivor
+            .quantity
+            .checked_add(absorbed_quantity)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        survivor.filled_quantity = survivor
+            .filled_quantity
+            .checked_add(absorbed_filled)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        survivor.escrow_lamports = survivor
+            .escrow_lamports
+            .checked_add(escrow_moved)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        survivor.base_escrow = survivor
+            .base_escrow
+            .checked_add(base_moved)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        survivor.status = if survivor.filled_quantity > 0 {
+            OrderStatus::PartiallyFilled
+        } else {
+            OrderStatus::Open
+        };
+        survivor.timestamp = survivor.timestamp.max(absorbed_ts);
+        survivor.placed_slot = survivor.placed_slot.max(absorbed_slot);
+        survivor.expires_at = match (survivor.expires_at, absorbed_expiry) {
+            (0, other) | (other, 0) => other,
+            (a, b) => a.min(b),
+        };
+        survivor.fee_bps = survivor.fee_bps.max(absorbed_fee_bps);
+        survivor.taker_fee_bps = survivor.taker_fee_bps.max(absorbed_taker_fee_bps);
+        survivor.bump_update_count();
+
+        let market = &mut ctx.accounts.market;
+        market.open_order_count = market.open_order_count.saturating_sub(1);
+        // The absorbed order's entry goes; the survivor's grows
+        if survivor.in_book {
+            ctx.accounts
+                .book_side
+                .as_ref()
+                .ok_or(MatchingEngineError::BookSideRequired)?
+                .load_mut()?
+                .remove(absorbed_id);
+        }
+        sync_book(market, ctx.accounts.book_side.as_ref(), survivor)?;
+
+        let event = OrdersMergedEvent {
+            market: market.key(),
+            owner: survivor.owner,
+            survivor_order_id: survivor.order_id,
+            absorbed_order_id: absorbed_id,
+            quantity: survivor.quantity,
+            filled_quantity: survivor.filled_quantity,
+            escrow_moved,
+            timestamp: now,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Order #{} merged into order #{}",
+            absorbed_id,
+            survivor.order_id
+        );
+        Ok(())
+    }
+
+    /// Redirect a resting sell's future proceeds. Owner only; the owner
+    /// keeps cancel refunds and rent whatever the beneficiary. Not for
+    /// orders that settle to a UserBalance.
+    pub fn set_beneficiary(
+        ctx: Context<OwnerOrderAction>,
+        _order_id: u64,
+        beneficiary: Pubkey,
+    ) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        require!(
+            order.side == Side::Sell,
+            MatchingEngineError::BeneficiaryOnlyForSells
+        );
+        // Its proceeds go to the owner's UserBalance, never a beneficiary
+        require!(
+            !order.settles_to_balance,
+            MatchingEngineError::DeferredSettlementUnsupported
+        );
+        require!(order.is_active(), MatchingEngineError::OrderNotActive);
+        require!(!order.is_locked, MatchingEngineError::OrderLocked);
+        order.beneficiary = beneficiary;
+        order.bump_update_count();
+
+        let event = BeneficiarySetEvent {
+            market: order.market,
+            owner: order.owner,
+            order_id: order.order_id,
+            beneficiary,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Order #{} beneficiary = {}", order.order_id, beneficiary);
+        Ok(())
+    }
+
+    /// Amend an active order's price and / or quantity in place, keeping its
+    /// id. Owner only. `new_quantity` is the total, filled part included, and
+    /// must leave something unfilled. A BUY's escrow follows its new
+    /// notional: the increase comes from the owner's trading balance (when
+    /// the order was funded from it) or wallet, the decrease goes back the
+    /// way cancel_order would refund it. A third-party-funded BUY can only
+    /// shrink. A new price or a larger quantity queues the order anew (its
+    /// timestamp is reset); fixed-point orders can only change quantity.
+    /// Lamport escrow only.
+    pub fn modify_order(
+        ctx: Context<ModifyOrder>,
+        _order_id: u64,
+        new_price: Option<u64>,
+        new_quantity: Option<u64>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let accounts = &mut *ctx.accounts;
+        let market = &mut accounts.market;
+        let order = &mut accounts.order;
+        require!(!market.is_paused, MatchingEngineError::MarketPaused);
+        require!(!market.is_archiving, MatchingEngineError::MarketArchiving);
+        require!(order.is_active(), MatchingEngineError::OrderNotActive);
+        require!(!order.is_locked, MatchingEngineError::OrderLocked);
+        require!(!order.is_expired(now), MatchingEngineError::OrderExpired);
+        // Escrow in a market vault would need token transfers
+        require!(
+            !order.escrow_in_vault && order.base_escrow == 0,
+            MatchingEngineError::TokenQuoteUnsupported
+        );
+        require!(
+            new_price.is_none() || !order.is_fixed_point(),
+            MatchingEngineError::PriceFormatMismatch
+        );
+        let price = new_price.unwrap_or(order.price);
+        let quantity = new_quantity.unwrap_or(order.quantity);
+        require!(price > 0, MatchingEngineError::InvalidPrice);
+        require!(
+            quantity > order.filled_quantity,
+            MatchingEngineError::InvalidQuantity
+        );
+        market.check_tick(price)?;
+        market.check_quantity(quantity)?;
+        require!(
+            new_price.is_none() || market.price_in_band(price),
+            MatchingEngineError::PriceOutOfBand
+        );
+
+        let (old_price, old_quantity) = (order.price, order.quantity);
+        let old_remaining = order.remaining_quantity();
+        let new_remaining = quantity - order.filled_quantity;
+
+        // ── Book totals ──────────────────────────────────────────────────────
+        market.remove_from_top_of_book(order, old_remaining);
+        order.price = price;
+        order.quantity = quantity;
+        if order.post_only {
+            require!(
+                !market.crosses_book(&order.side, order.price_key()),
+                MatchingEngineError::PostOnlyWouldCross
+            );
+        }
+        market.add_to_top_of_book(order, new_remaining);
+        let side_total = match order.side {
+            Side::Buy => &mut market.total_bid_volume,
+            Side::Sell => &mut market.total_ask_volume,
+        };
+        *side_total = side_total
+            .saturating_sub(old_remaining)
+            .checked_add(new_remaining)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        let side_after = *side_total;
+        sync_book(market, accounts.book_side.as_ref(), order)?;
+
+        // ── Owner limits, as at placement ────────────────────────────────────
+        if order.counted_in_stats {
+            let stats = accounts
+                .user_stats
+                .as_mut()
+                .ok_or(MatchingEngineError::UserStatsRequired)?;
+            stats.release(&order.side, old_remaining);
+            let owner_open = match order.side {
+                Side::Buy => &mut stats.open_bid_volume,
+                Side::Sell => &mut stats.open_ask_volume,
+            };
+            *owner_open = owner_open
+                .checked_add(new_remaining)
+                .ok_or(MatchingEngineError::MathOverflow)?;
+            let owner_after = *owner_open;
+            if new_remaining > old_remaining {
+                require!(
+                    !market.maker_share_exceeded(owner_after, side_after),
+                    MatchingEngineError::MakerConcentrationExceeded
+                );
+            }
+            if market.on_probation(stats) && market.probation_max_order_notional > 0 {
+                require!(
+                    order.escrow_for(quantity)? <= market.probation_max_order_notional,
+                    MatchingEngineError::ProbationOrderTooLarge
+                );
+            }
+        }
+
+        // ── BUY escrow follows the new notional ──────────────────────────────
+        let (mut escrow_added, mut escrow_refunded) = (0, 0);
+        if order.side == Side::Buy {
+            let needed = order.escrow_for(new_remaining)?;
+            if needed > order.escrow_lamports {
+                escrow_added = needed - order.escrow_lamports;
+                require!(
+                    order.funder == order.owner,
+                    MatchingEngineError::SponsoredEscrowIncrease
+                );
+                if order.funded_from_balance {
+                    let balance = accounts
+                        .trading_balance
+                        .as_mut()
+                        .ok_or(MatchingEngineError::TradingBalanceRequired)?;
+                    require!(
+                        balance.lamports >= escrow_added,
+                        MatchingEngineError::InsufficientBalance
+                    );
+                    move_lamports(
+                        &balance.to_account_info(),
+                        &accounts.escrow_vault.to_account_info(),
+                        escrow_added,
+                    )?;
+                    balance.lamports -= escrow_added;
+                } else {
+                    system_program::transfer(
+                        CpiContext::new(
+                            accounts.system_program.to_account_info(),
+                            system_program::Transfer {
+                                from: accounts.owner.to_account_info(),
+                                to: accounts.escrow_vault.to_account_info(),
+                            },
+                        ),
+                        escrow_added,
+                    )?;
+                }
+            } else {
+                escrow_refunded = order.escrow_lamports - needed;
+                let funder = accounts.funder.as_ref().map(|f| f.to_account_info());
+                return_escrow(
+                    order,
+                    escrow_refunded,
+                    accounts.trading_balance.as_mut(),
+                    &accounts.owner.to_account_info(),
+                    funder.as_ref(),
+                    EscrowAccounts {
+                        vault: &accounts.escrow_vault.to_account_info(),
+                        system_program: &accounts.system_program.to_account_info(),
+                    },
+                )?;
+            }
+            order.escrow_lamports = needed;
+        }
+
+        if price != old_price || quantity > old_quantity {
+            order.timestamp = now;
+        }
+        order.bump_update_count();
+
+        let event = OrderModifiedEvent {
+            market: order.market,
+            owner: order.owner,
+            order_id: order.order_id,
+            side: order.side.clone(),
+            old_price,
+            new_price: price,
+            old_quantity,
+            new_quantity: quantity,
+            escrow_added,
+            escrow_refunded,
+            timestamp: now,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Order #{} modified | price {} -> {} qty {} -> {}",
+            order.order_id,
+            old_price,
+            price,
+            old_quantity,
+            quantity
+        );
+        Ok(())
+    }
+
+    /// Shrink an active order by `reduce_by` unfilled units, keeping its id
+    /// and queue position. Owner only. A BUY gets back the escrow of the
+    /// units removed — computed from what remains unfilled, so a partial
+    /// fill's price improvement isn't counted twice — the way cancel_order
+    /// would refund it. Reducing by the whole remainder takes the order off
+    /// the book at once, leaving it closeable: Cancelled if nothing had
+    /// filled, otherwise Filled at its filled quantity. Lamport escrow only.
+    pub fn reduce_order(ctx: Context<ModifyOrder>, _order_id: u64, reduce_by: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let accounts = &mut *ctx.accounts;
+        let market = &mut accounts.market;
+        let order = &mut accounts.order;
+        require!(order.is_active(), MatchingEngineError::OrderNotActive);
+        require!(!order.is_locked, MatchingEngineError::OrderLocked);
+        // Escrow in a market vault would need token transfers
+        require!(
+            !order.escrow_in_vault && order.base_escrow == 0,
+            MatchingEngineError::TokenQuoteUnsupported
+        );
+        let old_remaining = order.remaining_quantity();
+        require!(
+            reduce_by > 0 && reduce_by <= old_remaining,
+            MatchingEngineError::InvalidQuantity
+        );
+        let old_quantity = order.quantity;
+        let owner = accounts.owner.to_account_info();
+        let funder = accounts.funder.as_ref().map(|f| f.to_account_info());
+        let escrow_vault = accounts.escrow_vault.to_account_info();
+        let system_program = accounts.system_program.to_account_info();
+        let escrow = EscrowAccounts {
+            vault: &escrow_vault,
+            system_program: &system_program,
+        };
+
+        let escrow_refunded = if reduce_by == old_remaining {
+            let refunded = cancel_and_refund(
+                market,
+                order,
+                &owner,
+                funder.as_ref(),
+                accounts.trading_balance.as_mut(),
+                accounts.user_stats.as_mut(),
+                accounts.open_orders.as_mut(),
+                accounts.book_side.as_ref(),
+                VaultAccounts {
+                    vault: None,
+                    token_program: None,
+                    user: None,
+                },
+                escrow,
+                None,
+            )?;
+            // What was filled is now the whole order
+            order.quantity = order.filled_quantity;
+            if order.filled_quantity > 0 {
+                order.set_status(OrderStatus::Filled, now);
+            }
+            refunded
+        } else {
+            market.remove_from_top_of_book(order, reduce_by);
+            let side_total = match order.side {
+                Side::Buy => &mut market.total_bid_volume,
+                Side::Sell => &mut market.total_ask_volume,
+            };
+            *side_total = side_total.saturating_sub(reduce_by);
+            if order.counted_in_stats {
+                accounts
+                    .user_stats
+                    .as_mut()
+                    .ok_or(MatchingEngineError::UserStatsRequired)?
+                    .release(&order.side, reduce_by);
+            }
+            order.quantity -= reduce_by;
+            sync_book(market, accounts.book_side.as_ref(), order)?;
+            let mut refunded = 0;
+            if order.side == Side::Buy {
+                let needed = order.escrow_for(order.remaining_quantity())?;
+                refunded = order.escrow_lamports.saturating_sub(needed);
+                return_escrow(
+                    order,
+                    refunded,
+                    accounts.trading_balance.as_mut(),
+                    &owner,
+                    funder.as_ref(),
+                    escrow,
+                )?;
+                order.escrow_lamports -= refunded;
+            }
+            order.bump_update_count();
+            refunded
+        };
+
+        let event = OrderReducedEvent {
+            market: order.market,
+            owner: order.owner,
+            order_id: order.order_id,
+            side: order.side.clone(),
+            old_quantity,
+            new_quantity: order.quantity,
+            escrow_refunded,
+            closed: !order.is_active(),
+            timestamp: now,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Order #{} reduced by {} | qty {} -> {}",
+            order.order_id,
+            reduce_by,
+            old_quantity,
+            order.quantity
+        );
+        Ok(())
+    }
+
+    /// Move a resting order's deadline in either direction without losing
+    /// queue position. `new_expires_at` must be in the future (0 = no
+    /// expiry); shortening to a time already past is rejected rather than
+    /// expiring the order — cancel it instead.
+    pub fn update_order_expiry(
+        ctx: Context<OwnerOrderAction>,
+        _order_id: u64,
+        new_expires_at: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let order = &mut ctx.accounts.order;
+        require!(order.is_active(), MatchingEngineError::OrderNotActive);
+        require!(!order.is_locked, MatchingEngineError::OrderLocked);
+        require!(!order.is_expired(now), MatchingEngineError::OrderExpired);
+        if new_expires_at > 0 {
+            require!(new_expires_at > now, MatchingEngineError::OrderExpired);
+        }
+        let old_expires_at = order.expires_at;
+        order.expires_at = new_expires_at;
+        order.bump_update_count();
+
+        let event = OrderExpiryUpdatedEvent {
+            market: order.market,
+            owner: order.owner,
+            order_id: order.order_id,
+            old_expires_at,
+            new_expires_at,
+            timestamp: now,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!(
+            "Order #{} expires_at {} -> {}",
+            order.order_id,
+            old_expires_at,
+            new_expires_at
+        );
+        Ok(())
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // Commit–Reveal
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Opt the market in to (or out of) sealed placement. Authority or
+    /// ParamManager. `reveal_window_secs` applies to new commitments.
+    pub fn set_commit_reveal(
+        ctx: Context<AuthorityAction>,
+        commit_reveal: bool,
+        reveal_window_secs: i64,
+    ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        if commit_reveal {
+            require!(
+                reveal_window_secs > 0,
+                MatchingEngineError::InvalidRevealWindow
+            );
+            // Commitments escrow lamports and lock no base tokens
+            let market = &ctx.accounts.market;
+            require!(
+                !market.is_token_quoted() && !market.is_base_escrowed(),
+                MatchingEngineError::TokenQuoteUnsupported
+            );
+        }
+        let market = &mut ctx.accounts.market;
+        market.commit_reveal = commit_reveal;
+        market.reveal_window_secs = reveal_window_secs;
+        let event = CommitRevealSetEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            commit_reveal,
+            reveal_window_secs,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Market '{}' commit_reveal = {} (window {}s)",
+            market.market_name,
+            commit_reveal,
+            reveal_window_secs
+        );
+        Ok(())
+    }
+
+    /// Seal an order: store `hash` (see Commitment::hash) and escrow
+    /// `max_notional` lamports, whatever the side.
+    /// Seeds: ["commitment", market, owner, commitment_id_le]
+    pub fn commit_order(
+        ctx: Context<CommitOrder>,
+        commitment_id: u64,
+        hash: [u8; 32],
+        max_notional: u64,
+    ) -> Result<()> {
+        require!(
+            !ctx.accounts.config.paused,
+            MatchingEngineError::ProtocolPaused
+        );
+        let market = &ctx.accounts.market;
+        require!(market.commit_reveal, MatchingEngineError::CommitRevealDisabled);
+        require!(!market.is_paused, MatchingEngineError::MarketPaused);
+        require!(!market.is_archiving, MatchingEngineError::MarketArchiving);
+        require!(max_notional > 0, MatchingEngineError::InvalidEscrowBound);
+
+        let clock = Clock::get()?;
+        let reveal_deadline = clock
+            .unix_timestamp
+            .checked_add(market.reveal_window_secs)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.owner.to_account_info(),
+                    to: ctx.accounts.commitment.to_account_info(),
+                },
+            ),
+            max_notional,
+        )?;
+
+        let commitment = &mut ctx.accounts.commitment;
+        commitment.market = market.key();
+        commitment.owner = ctx.accounts.owner.key();
+        commitment.commitment_id = commitment_id;
+        commitment.hash = hash;
+        commitment.escrow_lamports = max_notional;
+        commitment.committed_at = clock.unix_timestamp;
+        commitment.reveal_deadline = reveal_deadline;
+        commitment.bump = ctx.bumps.commitment;
+
+        let event = OrderCommittedEvent {
+            market: commitment.market,
+            owner: commitment.owner,
+            commitment_id,
+            hash,
+            escrow_lamports: max_notional,
+            reveal_deadline,
+            timestamp: clock.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!(
+            "Commitment #{} by {} | escrow={} reveal by {}",
+            commitment_id,
+            commitment.owner,
+            max_notional,
+            reveal_deadline
+        );
+        Ok(())
+    }
+
+    /// Open the sealed order. The preimage must hash to the commitment, the
+    /// reveal must land by `reveal_deadline`, and the order passes every
+    /// place_order check. A BUY takes its escrow from the commitment; the
+    /// rest (all of it for a SELL) returns to the owner with the rent.
+    #[allow(clippy::too_many_arguments)]
+    pub fn reveal_order(
+        ctx: Context<RevealOrder>,
+        _commitment_id: u64,
+        side: Side,
+        price: u64,
+        quantity: u64,
+        salt: [u8; 32],
+        order_id: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let owner = ctx.accounts.owner.key();
+        let commitment = &ctx.accounts.commitment;
+        require!(
+            ctx.accounts.market.commit_reveal,
+            MatchingEngineError::CommitRevealDisabled
+        );
+        require!(
+            clock.unix_timestamp <= commitment.reveal_deadline,
+            MatchingEngineError::RevealWindowClosed
+        );
+        require!(
+            Commitment::hash(&side, price, quantity, &salt, &owner) == commitment.hash,
+            MatchingEngineError::CommitmentMismatch
+        );
+
+        let request = OrderRequest {
+            side,
+            price,
+            price_q64: None,
+            quantity,
+            order_id,
+            expires_at,
+            beneficiary: None,
+            referrer: None,
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+            trigger: None,
+            display_quantity: 0,
+        };
+        let mut placement = check_placement(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            ctx.accounts.trader_seat.is_some(),
+            ctx.accounts.user_stats.as_mut(),
+            ctx.accounts.open_orders.as_mut(),
+            &request,
+            clock.unix_timestamp,
+        )?;
+
+        // ── Escrow from the commitment ───────────────────────────────────────
+        placement.escrow_bump = ctx.bumps.escrow_vault;
+        if request.side == Side::Buy {
+            placement.escrow_lamports = price
+                .checked_mul(quantity)
+                .ok_or(MatchingEngineError::MathOverflow)?;
+            require!(
+                placement.escrow_lamports <= commitment.escrow_lamports,
+                MatchingEngineError::EscrowBoundExceeded
+            );
+            let escrow_vault = ctx.accounts.escrow_vault.to_account_info();
+            fund_vault_rent(
+                &escrow_vault,
+                ctx.accounts.owner.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+            move_lamports(
+                &commitment.to_account_info(),
+                &escrow_vault,
+                placement.escrow_lamports,
+            )?;
+        }
+        let refund_lamports = commitment.escrow_lamports - placement.escrow_lamports;
+        let commitment_id = commitment.commitment_id;
+
+        open_order(
+            &mut ctx.accounts.market,
+            &mut ctx.accounts.order,
+            owner,
+            ctx.bumps.order,
+            &request,
+            &placement,
+            ctx.accounts.book_side.as_ref(),
+            clock.unix_timestamp,
+            None,
+        )?;
+
+        // The commitment closes to the owner, carrying the refund with the rent
+        let event = OrderRevealedEvent {
+            market: ctx.accounts.market.key(),
+            owner,
+            commitment_id,
+            order_id,
+            refund_lamports,
+            timestamp: clock.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Commitment #{} revealed as order #{}", commitment_id, order_id);
+        Ok(())
+    }
+
+    /// Return an unrevealed commitment's escrow and rent once its reveal
+    /// window has passed. Works on paused and archiving markets.
+    pub fn refund_commitment(ctx: Context<RefundCommitment>, _commitment_id: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let commitment = &ctx.accounts.commitment;
+        require!(
+            now > commitment.reveal_deadline,
+            MatchingEngineError::CommitmentNotExpired
+        );
+
+        let event = CommitmentRefundedEvent {
+            market: commitment.market,
+            owner: commitment.owner,
+            commitment_id: commitment.commitment_id,
+            refund_lamports: commitment.escrow_lamports,
+            timestamp: now,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event_unchecked(&ctx.accounts.market, event)?;
+        msg!(
+            "Commitment #{} refunded {} lamports",
+            commitment.commitment_id,
+            commitment.escrow_lamports
+        );
+        Ok(())
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // Trading Balance
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Open a pre-funded trading balance for (market, owner).
+    /// Seeds: ["balance", market, owner]
+    pub fn initialize_trading_balance(ctx: Context<InitializeTradingBalance>) -> Result<()> {
+        let balance = &mut ctx.accounts.trading_balance;
+        balance.owner = ctx.accounts.owner.key();
+        balance.market = ctx.accounts.market.key();
+        balance.lamports = 0;
+        balance.bump = ctx.bumps.trading_balance;
+        msg!("TradingBalance opened for {}", balance.owner);
+        Ok(())
+    }
+
+    /// Move lamports from the owner's wallet into their trading balance.
+    pub fn deposit_balance(ctx: Context<DepositBalance>, amount: u64) -> Result<()> {
+        require!(amount > 0, MatchingEngineError::InvalidAmount);
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.owner.to_account_info(),
+                    to: ctx.accounts.trading_balance.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let balance = &mut ctx.accounts.trading_balance;
+        balance.lamports = balance
+            .lamports
+            .checked_add(amount)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+
+        let event = BalanceDepositedEvent {
+            owner: balance.owner,
+            market: balance.market,
+            amount,
+            balance: balance.lamports,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Deposited {} lamports. Balance: {}", amount, balance.lamports);
+        Ok(())
+    }
+
+    /// Return lamports from the trading balance to the owner's wallet.
+    /// Not affected by the market pause — users can always reclaim funds.
+    pub fn withdraw_balance(ctx: Context<WithdrawBalance>, amount: u64) -> Result<()> {
+        require!(amount > 0, MatchingEngineError::InvalidAmount);
+        let balance = &mut ctx.accounts.trading_balance;
+        require!(
+            balance.lamports >= amount,
+            MatchingEngineError::InsufficientBalance
+        );
+
+        move_lamports(
+            &balance.to_account_info(),
+            &ctx.accounts.owner.to_account_info(),
+            amount,
+        )?;
+        balance.lamports -= amount;
+
+        let event = BalanceWithdrawnEvent {
+            owner: balance.owner,
+            market: balance.market,
+            amount,
+            balance: balance.lamports,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Withdrew {} lamports. Balance: {}", amount, balance.lamports);
+        Ok(())
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // Deferred Settlement
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Open the (market, owner) UserBalance that orders placed with it are
+    /// settled into.
+    /// Seeds: ["user_balance", market, owner]
+    pub fn initialize_user_balance(ctx: Context<InitializeUserBalance>) -> Result<()> {
+        let balance = &mut ctx.accounts.user_balance;
+        balance.owner = ctx.accounts.owner.key();
+        balance.market = ctx.accounts.market.key();
+        balance.pending_lamports = 0;
+        balance.bump = ctx.bumps.user_balance;
+        msg!("UserBalance opened for {}", balance.owner);
+        Ok(())
+    }
+
+    /// Sweep everything pending in the owner's UserBalance to `destination`.
+    /// Fails with NothingToClaim when there is nothing pending, so a second
+    /// claim of the same funds can't succeed. Not affected by any pause.
+    pub fn claim_funds(ctx: Context<ClaimFunds>) -> Result<()> {
+        let balance = &mut ctx.accounts.user_balance;
+        let amount = balance.pending_lamports;
+        require!(amount > 0, MatchingEngineError::NothingToClaim);
+
+        move_lamports(
+            &balance.to_account_info(),
+            &ctx.accounts.destination.to_account_info(),
+            amount,
+        )?;
+        balance.pending_lamports = 0;
+
+        let event = FundsClaimedEvent {
+            owner: balance.owner,
+            market: balance.market,
+            destination: ctx.accounts.destination.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Claimed {} lamports", amount);
+        Ok(())
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // Rent Subsidy
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Create the market's rent subsidy vault. Orders placed with it while
+    /// it holds at least one Order's rent cost their owner no rent.
+    /// Seeds: ["rent_subsidy", market]
+    /// Authority or FeeManager.
+    pub fn initialize_rent_subsidy_vault(ctx: Context<InitializeRentSubsidyVault>) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::FeeManager,
+        )?;
+        let vault = &mut ctx.accounts.rent_subsidy_vault;
+        vault.market = ctx.accounts.market.key();
+        vault.lamports = 0;
+        vault.orders_subsidized = 0;
+        vault.bump = ctx.bumps.rent_subsidy_vault;
+        msg!("Rent subsidy vault opened for '{}'", ctx.accounts.market.market_name);
+        Ok(())
+    }
+
+    /// Top up the rent subsidy vault. Anyone.
+    pub fn fund_rent_subsidy(ctx: Context<FundRentSubsidy>, amount: u64) -> Result<()> {
+        require!(amount > 0, MatchingEngineError::InvalidAmount);
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.funder.to_account_info(),
+                    to: ctx.accounts.rent_subsidy_vault.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let vault = &mut ctx.accounts.rent_subsidy_vault;
+        vault.lamports = vault
+            .lamports
+            .checked_add(amount)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+
+        let event = RentSubsidyFundedEvent {
+            market: vault.market,
+            funder: ctx.accounts.funder.key(),
+            amount,
+            balance: vault.lamports,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!("Rent subsidy funded with {} lamports. Balance: {}", amount, vault.lamports);
+        Ok(())
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // Oracle Feeds
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Create a price feed published by the signer.
+    /// Seeds: ["oracle_feed", publisher]
+    pub fn initialize_oracle_feed(ctx: Context<InitializeOracleFeed>) -> Result<()> {
+        let feed = &mut ctx.accounts.oracle_feed;
+        feed.publisher = ctx.accounts.publisher.key();
+        feed.price = 0;
+        feed.publish_ts = 0;
+        feed.bump = ctx.bumps.oracle_feed;
+        msg!("OracleFeed opened for {}", feed.publisher);
+        Ok(())
+    }
+
+    /// Publish a new price. Publisher only.
+    pub fn publish_oracle_price(ctx: Context<PublishOraclePrice>, price: u64) -> Result<()> {
+        require!(price > 0, MatchingEngineError::InvalidOraclePrice);
+        let feed = &mut ctx.accounts.oracle_feed;
+        feed.price = price;
+        feed.publish_ts = Clock::get()?.unix_timestamp;
+
+        emit!(OraclePricePublishedEvent {
+            feed: feed.key(),
+            publisher: feed.publisher,
+            price,
+            publish_ts: feed.publish_ts,
+        });
+        msg!("Oracle price published: {}", price);
+        Ok(())
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // Expiry Settlement
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Make this a dated market (expiry_ts = 0 makes it perpetual again).
+    /// Trading stops at expiry_ts; settle_at_expiry then records the
+    /// settlement price from `settlement_oracle`, accepting a publish no
+    /// older than oracle_max_staleness_secs. Authority only, before expiry.
+    pub fn configure_expiry(
+        ctx: Context<AuthorityAction>,
+        expiry_ts: i64,
+        settlement_oracle: Pubkey,
+        oracle_max_staleness_secs: i64,
+        permissionless_settlement: bool,
+    ) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        let now = Clock::get()?.unix_timestamp;
+        let market = &mut ctx.accounts.market;
+        require!(!market.is_expired(now), MatchingEngineError::MarketExpired);
+        require!(
+            (expiry_ts == 0 || expiry_ts > now) && oracle_max_staleness_secs >= 0,
+            MatchingEngineError::InvalidExpiry
+        );
+
+        market.expiry_ts = expiry_ts;
+        market.settlement_oracle = settlement_oracle;
+        market.oracle_max_staleness_secs = oracle_max_staleness_secs;
+        market.permissionless_settlement = permissionless_settlement;
+
+        let event = MarketExpiryConfiguredEvent {
+            market: market.key(),
+            expiry_ts,
+            settlement_oracle,
+            oracle_max_staleness_secs,
+            permissionless_settlement,
+            timestamp: now,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!("Market '{}' expires at {}", market.market_name, expiry_ts);
+        Ok(())
+    }
+
+    /// Record the settlement price of an expired market from its oracle.
+    /// Callable by the authority, or by anyone when permissionless_settlement
+    /// is set (or the authority was renounced). The price is immutable once set.
+    pub fn settle_at_expiry(ctx: Context<SettleAtExpiry>) -> Result<()> {
+        let clock = Clock::get()?;
+        let market = &mut ctx.accounts.market;
+        let settler = ctx.accounts.settler.key();
+        require!(
+            market.permissionless_settlement
+                || market.is_renounced()
+                || settler == market.authority,
+            MatchingEngineError::Unauthorized
+        );
+        require!(market.expiry_ts > 0, MatchingEngineError::MarketNotExpired);
+        require!(
+            market.is_expired(clock.unix_timestamp),
+            MatchingEngineError::MarketNotExpired
+        );
+        require!(!market.is_settled(), MatchingEngineError::SettlementRecorded);
+
+        let feed = &ctx.accounts.oracle_feed;
+        require!(feed.price > 0, MatchingEngineError::InvalidOraclePrice);
+        let oldest = clock
+            .unix_timestamp
+            .checked_sub(market.oracle_max_staleness_secs)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        require!(feed.publish_ts >= oldest, MatchingEngineError::OracleStale);
+
+        market.settlement_price = feed.price;
+        market.settled_at = clock.unix_timestamp;
+
+        let event = MarketSettledEvent {
+            market: market.key(),
+            settler,
+            settlement_price: feed.price,
+            oracle_publish_ts: feed.publish_ts,
+            timestamp: clock.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Market '{}' settled at {} lamports",
+            market.market_name,
+            feed.price
+        );
+        Ok(())
+    }
+
+    /// Cancel an active order with a full escrow refund to its owner, never
+    /// to the caller. Permissionless once the market is settled; before
+    /// that, Authority or RiskManager — to clear a compromised quoter's
+    /// orders, say, without archiving the market. Emits
+    /// OrderForceCancelledEvent after the OrderCancelledEvent.
+    pub fn force_cancel_order(ctx: Context<ForceCancelOrder>, _order_id: u64) -> Result<()> {
+        let market_settled = ctx.accounts.market.is_settled();
+        if !market_settled {
+            require_admin(
+                &ctx.accounts.caller,
+                &ctx.accounts.market,
+                &ctx.accounts.roles,
+                Role::RiskManager,
+            )?;
+        }
+        let accounts = &mut *ctx.accounts;
+        let funder = accounts.funder.as_ref().map(|f| f.to_account_info());
+        let vault = match accounts.order.side {
+            Side::Buy => VaultAccounts {
+                vault: accounts.quote_vault.as_deref(),
+                token_program: accounts.token_program.as_deref(),
+                user: accounts.owner_quote_account.as_deref(),
+            },
+            Side::Sell => VaultAccounts {
+                vault: accounts.base_vault.as_deref(),
+                token_program: accounts.token_program.as_deref(),
+                user: accounts.owner_base_account.as_deref(),
+            },
+        };
+        let refund_lamports = cancel_and_refund(
+            &mut accounts.market,
+            &mut accounts.order,
+            &accounts.owner.to_account_info(),
+            funder.as_ref(),
+            accounts.trading_balance.as_mut(),
+            accounts.user_stats.as_mut(),
+            accounts.open_orders.as_mut(),
+            accounts.book_side.as_ref(),
+            vault,
+            EscrowAccounts {
+                vault: &accounts.escrow_vault.to_account_info(),
+                system_program: &accounts.system_program.to_account_info(),
+            },
+            None,
+        )?;
+        let event = OrderForceCancelledEvent {
+            market: accounts.market.key(),
+            order_id: accounts.order.order_id,
+            owner: accounts.order.owner,
+            cancelled_by: accounts.caller.key(),
+            refund_lamports,
+            market_settled,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut accounts.market, event)?;
+        Ok(())
+    }
+
+    /// Cancel an order whose `expires_at` has passed, exactly as its owner's
+    /// cancel_order would: escrow back to the owner (or funder / trading
+    /// balance), volumes released. Permissionless, so stale orders don't
+    /// lock escrow forever. Good-till-cancelled orders (0) never expire.
+    pub fn expire_order(ctx: Context<ForceCancelOrder>, _order_id: u64) -> Result<()> {
+        require!(
+            ctx.accounts.order.is_expired(Clock::get()?.unix_timestamp),
+            MatchingEngineError::OrderNotExpired
+        );
+        let accounts = &mut *ctx.accounts;
+        let funder = accounts.funder.as_ref().map(|f| f.to_account_info());
+        let vault = match accounts.order.side {
+            Side::Buy => VaultAccounts {
+                vault: accounts.quote_vault.as_deref(),
+                token_program: accounts.token_program.as_deref(),
+                user: accounts.owner_quote_account.as_deref(),
+            },
+            Side::Sell => VaultAccounts {
+                vault: accounts.base_vault.as_deref(),
+                token_program: accounts.token_program.as_deref(),
+                user: accounts.owner_base_account.as_deref(),
+            },
+        };
+        cancel_and_refund(
+            &mut accounts.market,
+            &mut accounts.order,
+            &accounts.owner.to_account_info(),
+            funder.as_ref(),
+            accounts.trading_balance.as_mut(),
+            accounts.user_stats.as_mut(),
+            accounts.open_orders.as_mut(),
+            accounts.book_side.as_ref(),
+            vault,
+            EscrowAccounts {
+                vault: &accounts.escrow_vault.to_account_info(),
+                system_program: &accounts.system_program.to_account_info(),
+            },
+            None,
+        )?;
+        Ok(())
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // Market Archival
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Start winding the market down: placement and matching stop for good,
+    /// and archive_step can drain the remaining orders. Authority or RiskManager.
+    pub fn begin_archive(ctx: Context<AuthorityAction>) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::RiskManager,
+        )?;
+        let market = &mut ctx.accounts.market;
+        require!(!market.is_archiving, MatchingEngineError::MarketArchiving);
+        market.is_archiving = true;
+        let event = MarketArchivingEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            open_orders: market.open_order_count,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!(
+            "Market '{}' archiving — {} open orders to drain",
+            market.market_name,
+            market.open_order_count
+        );
+        Ok(())
+    }
+
+    /// Cancel up to `count` active orders of an archiving market with full
+    /// refunds to their owners. Permissionless.
+    /// remaining_accounts holds [order, refund wallet, trading_balance,
+    /// user_stats, open_orders, escrow vault] per order — the refund
+    /// wallet is the order's funder, which is the owner unless a third
+    /// party paid the escrow, or the owner's token account when the escrow
+    /// is in the quote vault (buys) or the base vault (asks). Pass the
+    /// program id for an unused optional slot.
+    /// Orders that are no longer active are skipped.
+    pub fn archive_step<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ArchiveStep<'info>>,
+        count: u8,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.market.is_archiving,
+            MatchingEngineError::MarketNotArchiving
+        );
+        let remaining = ctx.remaining_accounts;
+        require!(
+            remaining.len().is_multiple_of(Market::ARCHIVE_ACCOUNTS_PER_ORDER),
+            MatchingEngineError::InvalidArchiveAccounts
+        );
+
+        let market_key = ctx.accounts.market.key();
+        let system_program = ctx.accounts.system_program.to_account_info();
+        let mut cancelled: u64 = 0;
+        for slots in remaining
+            .chunks(Market::ARCHIVE_ACCOUNTS_PER_ORDER)
+            .take(count as usize)
+        {
+            let mut order = Account::<Order>::try_from(&slots[0])?;
+            require!(order.market == market_key, MatchingEngineError::MarketMismatch);
+            // A token account is checked against the owner when paid
+            require!(
+                order.escrow_in_vault
+                    || order.base_escrow > 0
+                    || slots[1].key() == order.refund_recipient(),
+                MatchingEngineError::Unauthorized
+            );
+            if !order.is_cancellable() {
+                continue;
+            }
+
+            let mut trading_balance = optional_account::<TradingBalance>(&slots[2])?;
+            if let Some(balance) = &trading_balance {
+                require!(
+                    balance.market == market_key && balance.owner == order.owner,
+                    MatchingEngineError::InvalidArchiveAccounts
+                );
+            }
+            let mut user_stats = optional_account::<UserStats>(&slots[3])?;
+            if let Some(stats) = &user_stats {
+                require!(
+                    stats.market == market_key && stats.owner == order.owner,
+                    MatchingEngineError::InvalidArchiveAccounts
+                );
+            }
+
+            let mut open_orders = optional_account::<OpenOrders>(&slots[4])?;
+            if let Some(open_orders) = &open_orders {
+                require!(
+                    open_orders.market == market_key && open_orders.owner == order.owner,
+                    MatchingEngineError::InvalidArchiveAccounts
+                );
+            }
+
+            let vault = VaultAccounts {
+                vault: match order.side {
+                    Side::Buy => ctx.accounts.quote_vault.as_deref(),
+                    Side::Sell => ctx.accounts.base_vault.as_deref(),
+                },
+                token_program: ctx.accounts.token_program.as_deref(),
+                user: Some(&slots[1]),
+            };
+            let book = match order.side {
+                Side::Buy => ctx.accounts.bids.as_ref(),
+                Side::Sell => ctx.accounts.asks.as_ref(),
+            };
+            cancel_and_refund(
+                &mut ctx.accounts.market,
+                &mut order,
+                &slots[1],
+                None,
+                trading_balance.as_mut(),
+                user_stats.as_mut(),
+                open_orders.as_mut(),
+                book,
+                vault,
+                EscrowAccounts {
+                    vault: &slots[5],
+                    system_program: &system_program,
+                },
+                None,
+            )?;
+
+            // Persist now: a later slot may load the same balance / stats.
+            order.exit(&crate::ID)?;
+            if let Some(balance) = &trading_balance {
+                balance.exit(&crate::ID)?;
+            }
+            if let Some(stats) = &user_stats {
+                stats.exit(&crate::ID)?;
+            }
+            if let Some(open_orders) = &open_orders {
+                open_orders.exit(&crate::ID)?;
+            }
+            cancelled += 1;
+        }
+
+        let open_orders_remaining = ctx.accounts.market.open_order_count;
+        let event = ArchiveStepEvent {
+            market: market_key,
+            caller: ctx.accounts.caller.key(),
+            cancelled,
+            open_orders_remaining,
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(&mut ctx.accounts.market, event)?;
+        msg!(
+            "Archive step: {} cancelled, {} open orders remaining",
+            cancelled,
+            open_orders_remaining
+        );
+        Ok(())
+    }
+
+    /// Close a market once no order is open, returning the rent of the
+    /// market and its fee vault (plus any market fees left in the vault) to
+    /// the authority. Orders still open must be filled or cancelled first —
+    /// by their owners, or by begin_archive + archive_step. A token-quoted
+    /// market's quote-token fees must be withdrawn first; its quote vault
+    /// stays open. Authority only.
+    pub fn close_market(ctx: Context<CloseMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(
+            market.open_order_count == 0,
+            MatchingEngineError::OpenOrdersRemain
+        );
+        require!(
+            ctx.accounts.fee_vault.protocol_fees == 0 && market.quote_protocol_fees == 0,
+            MatchingEngineError::ProtocolFeesPending
+        );
+        // Quote-token fees live outside the closing accounts
+        require!(market.quote_fees == 0, MatchingEngineError::QuoteFeesPending);
+
+        let event = MarketClosedEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+        msg!("Market '{}' closed", market.market_name);
+        Ok(())
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // Snapshots
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Emit a MarketSnapshotEvent so event-only indexers can resync during
+    /// quiet periods. Permissionless, at most once per POKE_INTERVAL_SLOTS
+    /// per market; only last_poke_slot and the event chain are written.
+    pub fn poke_market(ctx: Context<PokeMarket>) -> Result<()> {
+        let clock = Clock::get()?;
+        let market = &mut ctx.accounts.market;
+        require!(
+            market.last_poke_slot == 0
+                || clock.slot >= market.last_poke_slot.saturating_add(Market::POKE_INTERVAL_SLOTS),
+            MatchingEngineError::PokeTooSoon
+        );
+        market.last_poke_slot = clock.slot;
+
+        let event = MarketSnapshotEvent {
+            market: market.key(),
+            slot: clock.slot,
+            next_order_id: market.next_order_id,
+            open_order_count: market.open_order_count,
+            total_bid_volume: market.total_bid_volume,
+            total_ask_volume: market.total_ask_volume,
+            last_trade_price: market.last_trade_price,
+            last_trade_ts: market.last_trade_ts,
+            cumulative_base_volume: market.cumulative_base_volume,
+            cumulative_quote_volume: market.cumulative_quote_volume,
+            session_high: market.session_high,
+            session_low: market.session_low,
+            session_start_ts: market.session_start_ts,
+            fee_bps: ctx
+                .accounts
+                .fee_config
+                .as_ref()
+                .map_or(0, |fee_config| fee_config.fee_bps),
+            fee_recipient: market.fee_recipient,
+            dust_lamports: market.dust_lamports,
+            is_paused: market.is_paused,
+            is_archiving: market.is_archiving,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+            timestamp: clock.unix_timestamp,
+        };
+        record_event(market, event)?;
+        msg!("Market '{}' snapshot at slot {}", market.market_name, clock.slot);
+        Ok(())
+    }
+
+    /// Start a new ticker session: session_high / session_low clear until
+    /// the next fill. Lifetime volumes and the last price are kept. A fill
+    /// more than Market::SESSION_SECS after the session began does the
+    /// same on its own. Authority or ParamManager.
+    pub fn reset_session_stats(ctx: Context<AuthorityAction>) -> Result<()> {
+        require_admin(
+            &ctx.accounts.authority,
+            &ctx.accounts.market,
+            &ctx.accounts.roles,
+            Role::ParamManager,
+        )?;
+        let now = Clock::get()?.unix_timestamp;
+        let market = &mut ctx.accounts.market;
+        let event = SessionStatsResetEvent {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            previous_high: market.session_high,
+            previous_low: market.session_low,
+            previous_start_ts: market.session_start_ts,
+            timestamp: now,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        market.reset_session(now);
+        record_event(market, event)?;
+        msg!("Market '{}' session stats reset", market.market_name);
+        Ok(())
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Helpers
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Advance the market's state hash chain with `event`, stamp it, and emit it.
+/// state_hash = sha256(prev_state_hash || event_seq_le || borsh(event with zeroed hash))
+fn record_event<E: ChainedEvent + anchor_lang::Event>(market: &mut Market, event: E) -> Result<()> {
+    record_event_cpi(market, event, None)
+}
+
+/// record_event for a market passed unchecked because it may have been
+/// closed: chains `event` into it while it's a live Market, and otherwise
+/// emits it with event_seq 0.
+fn record_event_unchecked<E: ChainedEvent + anchor_lang::Event>(
+    market: &AccountInfo,
+    event: E,
+) -> Result<()> {
+    if !market_is_healthy(market) {
+        emit!(event);
+        return Ok(());
+    }
+    let mut state = Market::try_deserialize(&mut &market.try_borrow_data()?[..])?;
+    record_event(&mut state, event)?;
+    state.try_serialize(&mut &mut market.try_borrow_mut_data()?[..])
+}
+
+/// record_event that also emits the stamped event through `events`, when
+/// passed, as a self-CPI that log truncation can't drop.
+fn record_event_cpi<E: ChainedEvent + anchor_lang::Event>(
+    market: &mut Market,
+    mut event: E,
+    events: Option<EventCpi>,
+) -> Result<()> {
+    let event_seq = market
+        .event_seq
+        .checked_add(1)
+        .ok_or(MatchingEngineError::MathOverflow)?;
+    event.stamp(event_seq, [0; 32]);
+    let encoded = anchor_lang::prelude::borsh::to_vec(&event)?;
+    let state_hash = hashv(&[&market.state_hash, &event_seq.to_le_bytes(), &encoded]).to_bytes();
+    event.stamp(event_seq, state_hash);
+
+    market.event_seq = event_seq;
+    market.state_hash = state_hash;
+    emit!(event);
+    if let Some(events) = events {
+        events.emit(&event)?;
+    }
+    Ok(())
+}
+
+/// Empty a closing order's escrow vault into `owner`, who paid its rent.
+/// Returns the lamports reclaimed.
+fn close_escrow_vault<'info>(
+    order: &Order,
+    vault: &SystemAccount<'info>,
+    owner: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<u64> {
+    let vault_rent = vault.lamports();
+    let escrow = EscrowAccounts {
+        vault: &vault.to_account_info(),
+        system_program: &system_program.to_account_info(),
+    };
+    EscrowVault::of(order, &escrow)?.close(owner)?;
+    Ok(vault_rent)
+}
+
+/// The event authority an #[event_cpi] context carries, so helpers that
+/// don't see the Context can emit through it.
+#[derive(Clone, Copy)]
+struct EventCpi<'a, 'info> {
+    authority: &'a AccountInfo<'info>,
+    bump: u8,
+}
+
+impl<'a, 'info> EventCpi<'a, 'info> {
+    fn new(authority: &'a AccountInfo<'info>, bump: u8) -> Self {
+        Self { authority, bump }
+    }
+
+    /// What emit_cpi! does: invoke this program with the event as
+    /// instruction data, signed by the event authority.
+    fn emit<E: anchor_lang::Event>(&self, event: &E) -> Result<()> {
+        let data = anchor_lang::event::EVENT_IX_TAG_LE
+            .iter()
+            .copied()
+            .chain(event.data())
+            .collect::<Vec<u8>>();
+        let ix = anchor_lang::solana_program::instruction::Instruction::new_with_bytes(
+            crate::ID,
+            &data,
+            vec![AccountMeta::new_readonly(self.authority.key(), true)],
+        );
+        anchor_lang::solana_program::program::invoke_signed(
+            &ix,
+            std::slice::from_ref(self.authority),
+            &[&[b"__event_authority", &[self.bump]]],
+        )
+        .map_err(Into::into)
+    }
+}
+
+/// Move lamports between two accounts the program may debit directly.
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let mut from_lamports = from.try_borrow_mut_lamports()?;
+    **from_lamports = from_lamports
+        .checked_sub(amount)
+        .ok_or(MatchingEngineError::MathOverflow)?;
+    let mut to_lamports = to.try_borrow_mut_lamports()?;
+    **to_lamports = to_lamports
+        .checked_add(amount)
+        .ok_or(MatchingEngineError::MathOverflow)?;
+    Ok(())
+}
+
+/// Cancel an active order: release its remaining escrow (to the trading
+/// balance when it was funded from one, to the owner's token account when
+/// it's in one of the market's vaults, otherwise to `owner`), drop its
+/// remaining size from the market volumes and record OrderCancelledEvent.
+/// `vault` holds the accounts of the vault the order escrows in — the
+/// quote vault for a buy, the base vault for an ask.
+/// OrderCancelledEvent also goes through `events` when passed.
+/// Returns the refunded quote escrow.
+#[allow(clippy::too_many_arguments)]
+fn cancel_and_refund<'info>(
+    market: &mut Market,
+    order: &mut Account<'info, Order>,
+    owner: &AccountInfo<'info>,
+    funder: Option<&AccountInfo<'info>>,
+    trading_balance: Option<&mut Account<'info, TradingBalance>>,
+    user_stats: Option<&mut Account<'info, UserStats>>,
+    open_orders: Option<&mut Account<'info, OpenOrders>>,
+    book: Option<&AccountLoader<'info, BookSide>>,
+    vault: VaultAccounts<'_, 'info>,
+    escrow: EscrowAccounts<'_, 'info>,
+    events: Option<EventCpi>,
+) -> Result<u64> {
+    require!(order.is_cancellable(), MatchingEngineError::OrderNotActive);
+    require!(!order.is_locked, MatchingEngineError::OrderLocked);
+
+    let mut refund_lamports: u64 = 0;
+    if order.side == Side::Buy {
+        // Release exactly the escrow still held — never a recomputed amount.
+        refund_lamports = order.escrow_lamports;
+        if refund_lamports > 0 {
+            if order.escrow_in_vault {
+                let vault = TokenVault::quote(order.market, market, &vault)?;
+                vault.pay(vault.user()?, Some(order.refund_recipient()), refund_lamports)?;
+            } else if order.funded_from_balance {
+                let balance =
+                    trading_balance.ok_or(MatchingEngineError::TradingBalanceRequired)?;
+                EscrowVault::of(order, &escrow)?.pay(&balance.to_account_info(), refund_lamports)?;
+                balance.lamports = balance
+                    .lamports
+                    .checked_add(refund_lamports)
+                    .ok_or(MatchingEngineError::MathOverflow)?;
+            } else {
+                let wallet = refund_wallet(order, owner, funder)?;
+                EscrowVault::of(order, &escrow)?.pay(wallet, refund_lamports)?;
+            }
+        }
+        order.escrow_lamports = 0;
+    }
+    // An ask's locked base tokens go back to the owner
+    let base_refund = order.base_escrow;
+    if base_refund > 0 {
+        let vault = TokenVault::base(order.market, market, &vault)?;
+        vault.pay(vault.user()?, Some(order.owner), base_refund)?;
+        order.base_escrow = 0;
+    }
+
+    // Update market volumes
+    let remaining = order.remaining_quantity();
+    if order.counted_in_stats {
+        let stats = user_stats.ok_or(MatchingEngineError::UserStatsRequired)?;
+        stats.release(&order.side, remaining);
+        stats.open_orders = stats.open_orders.saturating_sub(1);
+    }
+    untrack_order(order, open_orders)?;
+    // A stop still waiting for its trigger never joined the book volumes
+    if order.status != OrderStatus::PendingTrigger {
+        if order.side == Side::Buy {
+            market.total_bid_volume = market.total_bid_volume.saturating_sub(remaining);
+        } else {
+            market.total_ask_volume = market.total_ask_volume.saturating_sub(remaining);
+        }
+        market.remove_from_top_of_book(order, remaining);
+    }
+    let now = Clock::get()?.unix_timestamp;
+    order.set_status(OrderStatus::Cancelled, now);
+    market.open_order_count = market.open_order_count.saturating_sub(1);
+    order.bump_update_count();
+    sync_book(market, book, order)?;
+
+    let event = OrderCancelledEvent {
+        order_id: order.order_id,
+        owner: order.owner,
+        market: order.market,
+        refund_lamports,
+        base_refund,
+        remaining_quantity: remaining,
+        timestamp: now,
+        event_seq: 0,
+        state_hash: [0; 32], // stamped by record_event
+    };
+    record_event_cpi(market, event, events)?;
+
+    msg!(
+        "Order #{} cancelled. Refund: {} lamports, {} base",
+        order.order_id,
+        refund_lamports,
+        base_refund
+    );
+    Ok(refund_lamports)
+}
+
+/// Unlist an order that just left the book from its owner's OpenOrders,
+/// which must be passed when the order is listed there.
+fn untrack_order(order: &Order, open_orders: Option<&mut Account<OpenOrders>>) -> Result<()> {
+    if order.tracked_in_open_orders {
+        open_orders
+            .ok_or(MatchingEngineError::OpenOrdersRequired)?
+            .remove(order.order_id);
+    }
+    Ok(())
+}
+
+/// Send `amount` of a BUY's lamport escrow out of its escrow vault back the
+/// way it came: into the owner's trading balance when the order was funded
+/// from one, otherwise to the refund wallet. The caller adjusts
+/// order.escrow_lamports.
+fn return_escrow<'info>(
+    order: &Account<'info, Order>,
+    amount: u64,
+    trading_balance: Option<&mut Account<'info, TradingBalance>>,
+    owner: &AccountInfo<'info>,
+    funder: Option<&AccountInfo<'info>>,
+    escrow: EscrowAccounts<'_, 'info>,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let escrow = EscrowVault::of(order, &escrow)?;
+    if order.funded_from_balance {
+        let balance = trading_balance.ok_or(MatchingEngineError::TradingBalanceRequired)?;
+        escrow.pay(&balance.to_account_info(), amount)?;
+        balance.lamports = balance
+            .lamports
+            .checked_add(amount)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+    } else {
+        let wallet = refund_wallet(order, owner, funder)?;
+        escrow.pay(wallet, amount)?;
+    }
+    Ok(())
+}
+
+/// The wallet a BUY's escrow goes back to: the order's funder, which must
+/// then be passed, or else the owner.
+fn refund_wallet<'a, 'info>(
+    order: &Order,
+    owner: &'a AccountInfo<'info>,
+    funder: Option<&'a AccountInfo<'info>>,
+) -> Result<&'a AccountInfo<'info>> {
+    let wallet = funder.unwrap_or(owner);
+    require!(
+        wallet.key() == order.refund_recipient(),
+        MatchingEngineError::FunderMismatch
+    );
+    Ok(wallet)
+}
+
+/// Create a new market's token vault for `mint` at the PDA [seed, market];
+/// `vault` must be that address. Returns its key and bump.
+fn create_market_vault<'info>(
+    authority: &Signer<'info>,
+    market_key: Pubkey,
+    seed: &[u8],
+    mint: &AccountInfo<'info>,
+    vault: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<(Pubkey, u8)> {
+    token::check_program(token_program)?;
+    let (vault_key, vault_bump) =
+        Pubkey::find_program_address(&[seed, market_key.as_ref()], &crate::ID);
+    let mismatch = if seed == Market::BASE_VAULT_SEED {
+        MatchingEngineError::BaseVaultMismatch
+    } else {
+        MatchingEngineError::QuoteVaultMismatch
+    };
+    if vault.key() != vault_key {
+        return Err(mismatch.into());
+    }
+    token::create_vault(
+        &authority.to_account_info(),
+        vault,
+        mint,
+        token_program,
+        &system_program.to_account_info(),
+        &[seed, market_key.as_ref(), &[vault_bump]],
+    )?;
+    Ok((vault_key, vault_bump))
+}
+
+/// Token accounts an instruction may be passed for one of a market's
+/// vaults: the vault, the token program and the user's token account of
+/// the vault's mint.
+#[derive(Clone, Copy, Default)]
+struct VaultAccounts<'a, 'info> {
+    vault: Option<&'a AccountInfo<'info>>,
+    token_program: Option<&'a AccountInfo<'info>>,
+    user: Option<&'a AccountInfo<'info>>,
+}
+
+/// One of a market's token vaults (quote or base), checked against the
+/// market.
+struct TokenVault<'a, 'info> {
+    seed: &'static [u8],
+    market: Pubkey,
+    mint: Pubkey,
+    bump: u8,
+    vault: &'a AccountInfo<'info>,
+    token_program: &'a AccountInfo<'info>,
+    user: Option<&'a AccountInfo<'info>>,
+    missing: MatchingEngineError,
+}
+
+impl<'a, 'info> TokenVault<'a, 'info> {
+    /// The quote vault of a token-quoted `market` (whose key is
+    /// `market_key`); the vault and the token program are required.
+    fn quote(market_key: Pubkey, market: &Market, accounts: &VaultAccounts<'a, 'info>) -> Result<Self> {
+        let missing = MatchingEngineError::QuoteAccountsRequired;
+        let (Some(vault), Some(token_program)) = (accounts.vault, accounts.token_program) else {
+            return Err(missing.into());
+        };
+        require!(
+            market.is_token_quoted() && vault.key() == market.quote_vault,
+            MatchingEngineError::QuoteVaultMismatch
+        );
+        token::check_program(token_program)?;
+        Ok(Self {
+            seed: Market::QUOTE_VAULT_SEED,
+            market: market_key,
+            mint: market.quote_mint,
+            bump: market.quote_vault_bump,
+            vault,
+            token_program,
+            user: accounts.user,
+            missing,
+        })
+    }
+
+    /// The base vault of a base-escrowed `market`; the vault and the token
+    /// program are required.
+    fn base(market_key: Pubkey, market: &Market, accounts: &VaultAccounts<'a, 'info>) -> Result<Self> {
+        let missing = MatchingEngineError::BaseAccountsRequired;
+        let (Some(vault), Some(token_program)) = (accounts.vault, accounts.token_program) else {
+            return Err(missing.into());
+        };
+        require!(
+            market.is_base_escrowed() && vault.key() == market.base_vault,
+            MatchingEngineError::BaseVaultMismatch
+        );
+        token::check_program(token_program)?;
+        Ok(Self {
+            seed: Market::BASE_VAULT_SEED,
+            market: market_key,
+            mint: market.base_mint,
+            bump: market.base_vault_bump,
+            vault,
+            token_program,
+            user: accounts.user,
+            missing,
+        })
+    }
+
+    /// The user's token account passed alongside the vault.
+    fn user(&self) -> Result<&'a AccountInfo<'info>> {
+        self.user.ok_or_else(|| self.missing.into())
+    }
+
+    /// Pull `amount` into the vault from `from`, a token account `authority`
+    /// (a transaction signer) controls.
+    fn deposit(&self, from: &AccountInfo<'info>, authority: &AccountInfo<'info>, amount: u64) -> Result<()> {
+        token::transfer(self.token_program, from, self.vault, authority, amount, &[])
+    }
+
+    /// Pay `amount` out of the vault to `to`, a token account of the vault's
+    /// mint — held by `recipient`, when given.
+    fn pay(&self, to: &AccountInfo<'info>, recipient: Option<Pubkey>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let account = token::read_account(to)?;
+        require!(
+            account.mint == self.mint && recipient.is_none_or(|owner| account.owner == owner),
+            MatchingEngineError::QuoteAccountMismatch
+        );
+        let bump = [self.bump];
+        let seeds: &[&[u8]] = &[self.seed, self.market.as_ref(), &bump];
+        token::transfer(self.token_program, self.vault, to, self.vault, amount, &[seeds])
+    }
+}
+
+/// Accounts an instruction is passed for an order's escrow vault: the
+/// vault and the System program that moves lamports out of it.
+#[derive(Clone, Copy)]
+struct EscrowAccounts<'a, 'info> {
+    vault: &'a AccountInfo<'info>,
+    system_program: &'a AccountInfo<'info>,
+}
+
+/// A BUY's escrow vault — the system account at Order::ESCROW_SEED —
+/// checked against the order. Debits are System transfers signed with the
+/// vault's seeds.
+struct EscrowVault<'a, 'info> {
+    market: Pubkey,
+    order_id: u64,
+    bump: u8,
+    vault: &'a AccountInfo<'info>,
+    system_program: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> EscrowVault<'a, 'info> {
+    /// The vault of `market`'s order `order_id`, which Anchor's seeds
+    /// constraint already derived with `bump`.
+    fn new(market: Pubkey, order_id: u64, bump: u8, accounts: &EscrowAccounts<'a, 'info>) -> Self {
+        Self {
+            market,
+            order_id,
+            bump,
+            vault: accounts.vault,
+            system_program: accounts.system_program,
+        }
+    }
+
+    /// `order`'s escrow vault, which `accounts.vault` must be.
+    fn of(order: &Order, accounts: &EscrowAccounts<'a, 'info>) -> Result<Self> {
+        require!(
+            order.is_escrow_vault(accounts.vault.key),
+            MatchingEngineError::EscrowVaultMismatch
+        );
+        require_keys_eq!(
+            accounts.system_program.key(),
+            System::id(),
+            ErrorCode::InvalidProgramId
+        );
+        Ok(Self::new(order.market, order.order_id, order.escrow_bump, accounts))
+    }
+
+    /// Check the vault holds `amount` of escrow on top of its rent.
+    fn require_escrow(&self, amount: u64) -> Result<()> {
+        let rent = Rent::get()?.minimum_balance(0);
+        require!(
+            self.vault
+                .lamports()
+                .checked_sub(amount)
+                .is_some_and(|left| left >= rent),
+            MatchingEngineError::EscrowBelowRent
+        );
+        Ok(())
+    }
+
+    /// Pay `amount` out of the vault to `to`.
+    fn pay(&self, to: &AccountInfo<'info>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let order_id = self.order_id.to_le_bytes();
+        let bump = [self.bump];
+        let seeds: &[&[u8]] = &[Order::ESCROW_SEED, self.market.as_ref(), &order_id, &bump];
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                self.system_program.clone(),
+                system_program::Transfer {
+                    from: self.vault.clone(),
+                    to: to.clone(),
+                },
+                &[seeds],
+            ),
+            amount,
+        )
+    }
+
+    /// Empty the vault — its rent and anything left in it — into `to`.
+    fn close(&self, to: &AccountInfo<'info>) -> Result<()> {
+        self.pay(to, self.vault.lamports())
+    }
+}
+
+/// Top `vault` up to the rent-exempt minimum of an empty system account
+/// from `payer`, so it can hold escrow.
+fn fund_vault_rent<'info>(
+    vault: &AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let top_up = Rent::get()?
+        .minimum_balance(0)
+        .saturating_sub(vault.lamports());
+    if top_up == 0 {
+        return Ok(());
+    }
+    system_program::transfer(
+        CpiContext::new(
+            system_program.clone(),
+            system_program::Transfer {
+                from: payer,
+                to: vault.clone(),
+            },
+        ),
+        top_up,
+    )
+}
+
+/// Shared body of start_auction and end_auction, after their checks.
+fn set_auction_mode(
+    ctx: Context<AuthorityAction>,
+    auction_mode: bool,
+    auction_end_ts: i64,
+    now: i64,
+) -> Result<()> {
+    let market = &mut ctx.accounts.market;
+    market.auction_mode = auction_mode;
+    market.auction_end_ts = auction_end_ts;
+    let event = AuctionModeSetEvent {
+        market: market.key(),
+        authority: ctx.accounts.authority.key(),
+        auction_mode,
+        auction_end_ts,
+        timestamp: now,
+        event_seq: 0,
+        state_hash: [0; 32], // stamped by record_event
+    };
+    record_event(market, event)?;
+    msg!(
+        "Market '{}' auction_mode = {} (ends at {})",
+        market.market_name,
+        auction_mode,
+        auction_end_ts
+    );
+    Ok(())
+}
+
+fn set_side_paused(ctx: Context<AuthorityAction>, side: Side, paused: bool) -> Result<()> {
+    require_admin(
+        &ctx.accounts.authority,
+        &ctx.accounts.market,
+        &ctx.accounts.roles,
+        Role::Pauser,
+    )?;
+    let market = &mut ctx.accounts.market;
+    if paused {
+        require!(!market.side_paused(&side), MatchingEngineError::SidePaused);
+    }
+    match side {
+        Side::Buy => market.buys_paused = paused,
+        Side::Sell => market.sells_paused = paused,
+    }
+    let event = MarketSidePausedEvent {
+        market: market.key(),
+        authority: ctx.accounts.authority.key(),
+        side: side.clone(),
+        is_paused: paused,
+        timestamp: Clock::get()?.unix_timestamp,
+        event_seq: 0,
+        state_hash: [0; 32], // stamped by record_event
+    };
+    record_event(market, event)?;
+    msg!(
+        "Market '{}' {:?} side {}",
+        market.market_name,
+        side,
+        if paused { "PAUSED" } else { "RESUMED" }
+    );
+    Ok(())
+}
+
+/// Market fees held in the fee vault: its balance less rent and the
+/// protocol's share.
+fn market_fees_available(vault: &Account<FeeVault>) -> Result<u64> {
+    let vault_info = vault.to_account_info();
+    let reserved = Rent::get()?
+        .minimum_balance(vault_info.data_len())
+        .checked_add(vault.protocol_fees)
+        .ok_or(MatchingEngineError::MathOverflow)?;
+    Ok(vault_info.lamports().saturating_sub(reserved))
+}
+
+/// Move a subsidized order's remaining lamports (its rent) back into the
+/// rent subsidy vault ahead of the account closing. Returns the vault key.
+fn return_subsidized_rent<'info>(
+    order: &Account<'info, Order>,
+    vault: Option<&mut Account<'info, RentSubsidyVault>>,
+) -> Result<Pubkey> {
+    let vault = vault.ok_or(MatchingEngineError::RentSubsidyVaultRequired)?;
+    let rent = order.to_account_info().lamports();
+    move_lamports(&order.to_account_info(), &vault.to_account_info(), rent)?;
+    vault.lamports = vault
+        .lamports
+        .checked_add(rent)
+        .ok_or(MatchingEngineError::MathOverflow)?;
+    Ok(vault.key())
+}
+
+/// Whether `market` is a live, decodable Market account of this program.
+fn market_is_healthy(market: &AccountInfo) -> bool {
+    if market.owner != &crate::ID {
+        return false;
+    }
+    let Ok(data) = market.try_borrow_data() else {
+        return false;
+    };
+    Market::try_deserialize(&mut &data[..]).is_ok()
+}
+
+/// Whether fees can be credited straight to `recipient`: a plain system
+/// account that stays rent-exempt, or this program's fee vault.
+fn can_receive_fees(recipient: &AccountInfo, amount: u64) -> Result<bool> {
+    if recipient.owner == &crate::ID {
+        let data = recipient.try_borrow_data()?;
+        return Ok(data.starts_with(FeeVault::DISCRIMINATOR));
+    }
+    if recipient.owner != &system_program::ID || recipient.executable {
+        return Ok(false);
+    }
+    let balance = recipient
+        .lamports()
+        .checked_add(amount)
+        .ok_or(MatchingEngineError::MathOverflow)?;
+    Ok(Rent::get()?.is_exempt(balance, recipient.data_len()))
+}
+
+/// Admin check: `authority` must be the market authority, or the holder of
+/// `role` in the market's roles account when one is passed. Nobody passes
+/// once the authority is renounced.
+fn require_admin(
+    authority: &Signer,
+    market: &Market,
+    roles: &Option<Account<MarketRoles>>,
+    role: Role,
+) -> Result<()> {
+    require!(!market.is_renounced(), MatchingEngineError::AuthorityRenounced);
+    if authority.key() == market.authority {
+        return Ok(());
+    }
+    let holder = roles.as_ref().map(|roles| roles.holder(role));
+    require!(
+        holder == Some(authority.key()),
+        MatchingEngineError::Unauthorized
+    );
+    Ok(())
+}
+
+/// On matcher_restricted markets the signing matcher must pass its seat;
+/// the account constraint has already tied a passed seat to the signer.
+fn require_matcher_seat(market: &Market, matcher_seat: &Option<Account<MatcherSeat>>) -> Result<()> {
+    require!(
+        !market.matcher_restricted || matcher_seat.is_some(),
+        MatchingEngineError::MatcherNotAuthorized
+    );
+    Ok(())
+}
+
+/// (maker, taker) fee exemption for a pair, from whichever seats were
+/// passed. The maker is the resting (earlier) order; the taker crossed it.
+fn fee_exemptions(
+    bid_order: &Order,
+    ask_order: &Order,
+    bid_seat: &Option<Account<TraderSeat>>,
+    ask_seat: &Option<Account<TraderSeat>>,
+) -> (bool, bool) {
+    let exempt = |seat: &Option<Account<TraderSeat>>| seat.as_ref().is_some_and(|seat| seat.fee_exempt);
+    pair_exemptions(bid_order, ask_order, exempt(bid_seat), exempt(ask_seat))
+}
+
+/// (maker, taker) fee exemption for a pair whose (bid, ask) owners are
+/// exempt as given.
+fn pair_exemptions(bid_order: &Order, ask_order: &Order, bid_exempt: bool, ask_exempt: bool) -> (bool, bool) {
+    if bid_order.order_id < ask_order.order_id {
+        (bid_exempt, ask_exempt)
+    } else {
+        (ask_exempt, bid_exempt)
+    }
+}
+
+/// Whether the seat slot of a maker group waives the maker's fees. The
+/// program id marks "no seat"; a seat passed must be the maker owner's.
+fn maker_fee_exempt<'info>(info: &'info AccountInfo<'info>, maker: &Order) -> Result<bool> {
+    let Some(seat) = optional_account::<TraderSeat>(info)? else {
+        return Ok(false);
+    };
+    require!(
+        seat.market == maker.market && seat.trader == maker.owner,
+        MatchingEngineError::InvalidMakerAccounts
+    );
+    Ok(seat.fee_exempt)
+}
+
+/// A new order's terms, as passed to place_order or revealed from a commitment.
+struct OrderRequest {
+    side: Side,
+    /// Whole lamports per unit (the integer part, from place_order_q64).
+    price: u64,
+    /// Exact Q64.64 price from place_order_q64; None = integer `price`.
+    price_q64: Option<u128>,
+    quantity: u64,
+    order_id: u64,
+    expires_at: i64,
+    /// Receives sell proceeds; None = the owner.
+    beneficiary: Option<Pubkey>,
+    /// Earns the referral share of the fees the order pays; None = nobody.
+    referrer: Option<Pubkey>,
+    /// Gtc rests; Ioc / Fok never do.
+    time_in_force: TimeInForce,
+    /// Rejected rather than placed if it would cross the book.
+    post_only: bool,
+    /// Stop trigger (price, direction): the order opens PendingTrigger.
+    trigger: Option<(u64, TriggerDirection)>,
+    /// Iceberg tranche shown to matching (place_iceberg_order); 0 = all.
+    display_quantity: u64,
+}
+
+impl OrderRequest {
+    /// The price as Q64.64; see Order::price_key.
+    fn price_key(&self) -> u128 {
+        self.price_q64
+            .unwrap_or_else(|| solamatch_core::to_q64(self.price))
+    }
+
+    /// Lamports a BUY escrows: price * quantity, rounded up for Q64.64 prices.
+    fn escrow(&self) -> Result<u64> {
+        match self.price_q64 {
+            Some(price_q64) => solamatch_core::escrow_q64(price_q64, self.quantity)
+                .map_err(|err| MatchingEngineError::from(err).into()),
+            None => self
+                .price
+                .checked_mul(self.quantity)
+                .ok_or_else(|| MatchingEngineError::MathOverflow.into()),
+        }
+    }
+}
+
+/// How a new order is funded and tracked.
+#[derive(Default)]
+struct Placement {
+    escrow_lamports: u64,
+    funded_from_balance: bool,
+    counted_in_stats: bool,
+    tracked_in_open_orders: bool,
+    /// Paid the escrow instead of the owner; None = the owner.
+    funder: Option<Pubkey>,
+    /// The order's rent was reimbursed from the RentSubsidyVault.
+    rent_subsidized: bool,
+    /// The escrow is quote tokens in the market's quote vault.
+    escrow_in_vault: bool,
+    /// Base tokens an ask locked in the market's base vault.
+    base_escrow: u64,
+    /// PDA nonce of a place_order_v2 order; None = seeded by order_id.
+    client_nonce: Option<u64>,
+    /// Bump of the order's escrow vault.
+    escrow_bump: u8,
+    /// match_orders settles the order into the owner's UserBalance.
+    settles_to_balance: bool,
+}
+
+/// Move a BUY's `amount` of escrow into its escrow `vault`, which `owner`
+/// first tops up to its rent: from `balance` when it covers the amount (no
+/// System CPI), else from `payer`'s wallet. Returns whether the balance
+/// paid.
+fn fund_escrow<'info>(
+    vault: &AccountInfo<'info>,
+    amount: u64,
+    balance: Option<&mut Account<'info, TradingBalance>>,
+    payer: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<bool> {
+    fund_vault_rent(vault, owner, &system_program.to_account_info())?;
+    match balance {
+        Some(balance) if balance.lamports >= amount => {
+            move_lamports(&balance.to_account_info(), vault, amount)?;
+            balance.lamports -= amount;
+            Ok(true)
+        }
+        _ => {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: payer,
+                        to: vault.clone(),
+                    },
+                ),
+                amount,
+            )?;
+            Ok(false)
+        }
+    }
+}
+
+/// Shared body of place_order and place_order_q64: check, escrow, subsidize
+/// and open the order.
+fn place(ctx: &mut Context<PlaceOrder>, request: OrderRequest) -> Result<()> {
+    let clock = Clock::get()?;
+    let mut placement = check_placement(
+        &ctx.accounts.config,
+        &ctx.accounts.market,
+        ctx.accounts.trader_seat.is_some(),
+        ctx.accounts.user_stats.as_mut(),
+        ctx.accounts.open_orders.as_mut(),
+        &request,
+        clock.unix_timestamp,
+    )?;
+
+    // ── Escrow ───────────────────────────────────────────────────────────
+    // Token-quoted markets pull the escrow from the owner's quote token
+    // account into the quote vault. Otherwise a co-signing funder pays from
+    // its wallet, a supplied TradingBalance that covers the escrow is
+    // debited directly (no System CPI), and failing that the owner's
+    // wallet pays as usual.
+    if let Some(funder) = &ctx.accounts.funder {
+        require!(
+            request.side == Side::Buy,
+            MatchingEngineError::FunderOnlyForBuys
+        );
+        require!(
+            !ctx.accounts.market.is_token_quoted(),
+            MatchingEngineError::TokenQuoteUnsupported
+        );
+        placement.funder = Some(funder.key());
+    }
+    // Deferred settlement credits the owner's UserBalance in lamports, so
+    // it can't pay a beneficiary, refund a funder or settle in tokens
+    if ctx.accounts.user_balance.is_some() {
+        require!(
+            placement.funder.is_none() && request.beneficiary.is_none(),
+            MatchingEngineError::DeferredSettlementUnsupported
+        );
+        require!(
+            !ctx.accounts.market.is_token_quoted(),
+            MatchingEngineError::TokenQuoteUnsupported
+        );
+        placement.settles_to_balance = true;
+    }
+    if request.side == Side::Buy && ctx.accounts.market.is_token_quoted() {
+        placement.escrow_lamports = request.escrow()?;
+        let quote = VaultAccounts {
+            vault: ctx.accounts.quote_vault.as_deref(),
+            token_program: ctx.accounts.token_program.as_deref(),
+            user: ctx.accounts.owner_quote_account.as_deref(),
+        };
+        let vault = TokenVault::quote(ctx.accounts.market.key(), &ctx.accounts.market, &quote)?;
+        vault.deposit(
+            vault.user()?,
+            &ctx.accounts.owner.to_account_info(),
+            placement.escrow_lamports,
+        )?;
+        placement.escrow_in_vault = true;
+    } else if request.side == Side::Buy {
+        placement.escrow_lamports = request.escrow()?;
+        let payer = match &ctx.accounts.funder {
+            Some(funder) => funder.to_account_info(),
+            None => ctx.accounts.owner.to_account_info(),
+        };
+        placement.funded_from_balance = fund_escrow(
+            &ctx.accounts.escrow_vault.to_account_info(),
+            placement.escrow_lamports,
+            ctx.accounts
+                .trading_balance
+                .as_mut()
+                .filter(|_| placement.funder.is_none()),
+            payer,
+            ctx.accounts.owner.to_account_info(),
+            &ctx.accounts.system_program,
+        )?;
+    }
+    placement.escrow_bump = ctx.bumps.escrow_vault;
+
+    // On base-escrowed markets an ask locks its base tokens up front
+    if request.side == Side::Sell && ctx.accounts.market.is_base_escrowed() {
+        let base = VaultAccounts {
+            vault: ctx.accounts.base_vault.as_deref(),
+            token_program: ctx.accounts.token_program.as_deref(),
+            user: ctx.accounts.owner_base_account.as_deref(),
+        };
+        let vault = TokenVault::base(ctx.accounts.market.key(), &ctx.accounts.market, &base)?;
+        vault.deposit(
+            vault.user()?,
+            &ctx.accounts.owner.to_account_info(),
+            request.quantity,
+        )?;
+        placement.base_escrow = request.quantity;
+    }
+
+    // ── Rent subsidy ─────────────────────────────────────────────────────
+    // The owner paid the rent in `init`; a vault that can cover it pays
+    // it back. An underfunded vault is ignored and the owner keeps paying.
+    if let Some(vault) = ctx.accounts.rent_subsidy_vault.as_mut() {
+        let rent = Rent::get()?.minimum_balance(Order::LEN);
+        if vault.lamports >= rent {
+            move_lamports(
+                &vault.to_account_info(),
+                &ctx.accounts.owner.to_account_info(),
+                rent,
+            )?;
+            vault.lamports -= rent;
+            vault.orders_subsidized = vault.orders_subsidized.saturating_add(1);
+            placement.rent_subsidized = true;
+        }
+    }
+
+    open_order(
+        &mut ctx.accounts.market,
+        &mut ctx.accounts.order,
+        ctx.accounts.owner.key(),
+        ctx.bumps.order,
+        &request,
+        &placement,
+        ctx.accounts.book_side.as_ref(),
+        clock.unix_timestamp,
+        Some(EventCpi::new(&ctx.accounts.event_authority, ctx.bumps.event_authority)),
+    )
+}
+
+/// Every placement guard (pauses, inputs, maker gating, expiry, taker-only
+/// window, TTL, per-owner limits). Counts the order in `user_stats` and
+/// lists it in `open_orders` when supplied; the returned Placement has no
+/// escrow yet.
+fn check_placement(
+    config: &GlobalConfig,
+    market: &Market,
+    has_trader_seat: bool,
+    user_stats: Option<&mut Account<UserStats>>,
+    open_orders: Option<&mut Account<OpenOrders>>,
+    request: &OrderRequest,
+    now: i64,
+) -> Result<Placement> {
+    let OrderRequest {
+        ref side,
+        price,
+        price_q64,
+        quantity,
+        order_id,
+        expires_at,
+        beneficiary,
+        referrer: _,
+        time_in_force,
+        post_only,
+        trigger,
+        display_quantity,
+    } = *request;
+
+    // ── Pause guard ─────────────────────────────────────────────────────
+    require!(!config.paused, MatchingEngineError::ProtocolPaused);
+    require!(!market.is_paused, MatchingEngineError::MarketPaused);
+    require!(!market.is_archiving, MatchingEngineError::MarketArchiving);
+    require!(!market.side_paused(side), MatchingEngineError::SidePaused);
+    // ── Input validation ────────────────────────────────────────────────
+    require!(
+        price_q64.is_some() == market.fixed_point_prices,
+        MatchingEngineError::PriceFormatMismatch
+    );
+    require!(
+        price_q64.map_or(price > 0, |p| p > 0),
+        MatchingEngineError::InvalidPrice
+    );
+    require!(quantity > 0, MatchingEngineError::InvalidQuantity);
+    require!(
+        trigger.is_none_or(|(trigger_price, _)| trigger_price > 0),
+        MatchingEngineError::InvalidTriggerPrice
+    );
+    market.check_tick(price)?;
+    market.check_quantity(quantity)?;
+    if display_quantity > 0 {
+        require!(
+            display_quantity <= quantity,
+            MatchingEngineError::InvalidDisplayQuantity
+        );
+        market.check_quantity(display_quantity)?;
+    }
+    require!(market.price_in_band(price), MatchingEngineError::PriceOutOfBand);
+    require!(
+        order_id == market.next_order_id,
+        MatchingEngineError::InvalidOrderId
+    );
+    require!(
+        beneficiary.is_none() || *side == Side::Sell,
+        MatchingEngineError::BeneficiaryOnlyForSells
+    );
+    // ── Post-only guard ──────────────────────────────────────────────────
+    require!(
+        !post_only || !market.crosses_book(side, request.price_key()),
+        MatchingEngineError::PostOnlyWouldCross
+    );
+    // ── Allow-list ───────────────────────────────────────────────────────
+    require!(
+        !market.permissioned || has_trader_seat,
+        MatchingEngineError::TraderNotWhitelisted
+    );
+    // ── Maker gating ─────────────────────────────────────────────────────
+    // A GTC order can rest, so restricted markets need the owner's seat.
+    // IOC / FOK orders only ever take.
+    let rests = time_in_force == TimeInForce::Gtc;
+    if market.makers_restricted && rests {
+        require!(has_trader_seat, MatchingEngineError::MakerSeatRequired);
+    }
+
+    // ── Expiry guard ─────────────────────────────────────────────────────
+    require!(!market.is_expired(now), MatchingEngineError::MarketExpired);
+
+    // ── Taker-only window ────────────────────────────────────────────────
+    // No order that can rest is accepted until the window ends.
+    require!(
+        !rests || now >= market.taker_only_until_ts,
+        MatchingEngineError::TakerOnlyWindow
+    );
+
+    // Validate TTL if set
+    if expires_at > 0 {
+        require!(expires_at > now, MatchingEngineError::OrderExpired);
+    }
+
+    // ── Per-owner limits (open orders, maker concentration, probation) ───
+    // Count the order in the owner's stats when supplied; the limits need them.
+    let counted_in_stats = match user_stats {
+        Some(stats) => {
+            market.check_open_order_cap(stats)?;
+            let (owner_after, side_after) = match side {
+                Side::Buy => (
+                    stats.open_bid_volume.checked_add(quantity),
+                    market.total_bid_volume.checked_add(quantity),
+                ),
+                Side::Sell => (
+                    stats.open_ask_volume.checked_add(quantity),
+                    market.total_ask_volume.checked_add(quantity),
+                ),
+            };
+            let owner_after = owner_after.ok_or(MatchingEngineError::MathOverflow)?;
+            let side_after = side_after.ok_or(MatchingEngineError::MathOverflow)?;
+            require!(
+                !market.maker_share_exceeded(owner_after, side_after),
+                MatchingEngineError::MakerConcentrationExceeded
+            );
+            if market.on_probation(stats) {
+                let notional = request.escrow()?;
+                require!(
+                    market.probation_max_order_notional == 0
+                        || notional <= market.probation_max_order_notional,
+                    MatchingEngineError::ProbationOrderTooLarge
+                );
+                require!(
+                    market.probation_max_open_orders == 0
+                        || stats.open_orders < market.probation_max_open_orders,
+                    MatchingEngineError::ProbationOpenOrderLimit
+                );
+            }
+            match side {
+                Side::Buy => stats.open_bid_volume = owner_after,
+                Side::Sell => stats.open_ask_volume = owner_after,
+            }
+            stats.open_orders = stats
+                .open_orders
+                .checked_add(1)
+                .ok_or(MatchingEngineError::MathOverflow)?;
+            true
+        }
+        None => {
+            require!(
+                !market.requires_user_stats(),
+                MatchingEngineError::UserStatsRequired
+            );
+            false
+        }
+    };
+    let tracked_in_open_orders = match open_orders {
+        Some(open_orders) => {
+            open_orders.insert(order_id)?;
+            true
+        }
+        None => false,
+    };
+    Ok(Placement {
+        counted_in_stats,
+        tracked_in_open_orders,
+        ..Placement::default()
+    })
+}
+
+/// Write a checked, escrowed order and add it to the market's book totals,
+/// indexing a GTC order in `book` when passed. OrderPlacedEvent also goes
+/// through `events` when passed.
+#[allow(clippy::too_many_arguments)]
+fn open_order(
+    market: &mut Account<Market>,
+    order: &mut Account<Order>,
+    owner: Pubkey,
+    order_bump: u8,
+    request: &OrderRequest,
+    placement: &Placement,
+    book: Option<&AccountLoader<BookSide>>,
+    now: i64,
+    events: Option<EventCpi>,
+) -> Result<()> {
+    let OrderRequest {
+        ref side,
+        price,
+        price_q64,
+        quantity,
+        order_id,
+        expires_at,
+        beneficiary,
+        referrer,
+        time_in_force,
+        post_only,
+        trigger,
+        display_quantity,
+    } = *request;
+
+    // ── Populate Order account fields ────────────────────────────────────
+    order.owner = owner;
+    order.market = market.key();
+    order.order_id = order_id;
+    order.side = side.clone();
+    order.price = price;
+    order.quantity = quantity;
+    order.filled_quantity = 0;
+    order.status = OrderStatus::Open;
+    order.timestamp = now;
+    order.bump = order_bump;
+    order.version = Order::VERSION;
+    order.is_locked = false;
+    order.expires_at = expires_at;
+    order.escrow_lamports = placement.escrow_lamports;
+    order.funded_from_balance = placement.funded_from_balance;
+    order.update_count = 1;
+    order.counted_in_stats = placement.counted_in_stats;
+    order.tracked_in_open_orders = placement.tracked_in_open_orders;
+    order.fee_bps = market.fee_bps;
+    order.taker_fee_bps = market.taker_fee_bps;
+    order.beneficiary = beneficiary.unwrap_or(owner);
+    order.referrer = referrer.unwrap_or_default();
+    order.funder = placement.funder.unwrap_or(owner);
+    order.subsidized = placement.rent_subsidized;
+    order.placed_slot = Clock::get()?.slot;
+    order.price_q64 = price_q64.unwrap_or(0);
+    order.escrow_in_vault = placement.escrow_in_vault;
+    order.base_escrow = placement.base_escrow;
+    order.escrow_bump = placement.escrow_bump;
+    order.time_in_force = time_in_force;
+    order.post_only = post_only;
+    let (trigger_price, trigger_direction) = trigger.unwrap_or_default();
+    order.trigger_price = trigger_price;
+    order.trigger_direction = trigger_direction;
+    order.owner_seeded = placement.client_nonce.is_some();
+    order.client_nonce = placement.client_nonce.unwrap_or(0);
+    order.settles_to_balance = placement.settles_to_balance;
+    order.display_quantity = display_quantity;
+    order.displayed_remaining = display_quantity;
+
+    // ── Update market volumes ────────────────────────────────────────────
+    // A stop joins the book volumes only once trigger_order opens it.
+    if trigger.is_some() {
+        order.status = OrderStatus::PendingTrigger;
+    } else {
+        add_to_book(market, order)?;
+    }
+
+    order.in_book = book.is_some() && time_in_force == TimeInForce::Gtc;
+    sync_book(market, book, order)?;
+
+    market.next_order_id = market
+        .next_order_id
+        .checked_add(1)
+        .ok_or(MatchingEngineError::MathOverflow)?;
+    market.open_order_count = market
+        .open_order_count
+        .checked_add(1)
+        .ok_or(MatchingEngineError::MathOverflow)?;
+
+    let event = OrderPlacedEvent {
+        order_id,
+        owner,
+        market: order.market,
+        side: side.clone(),
+        price,
+        price_q64: order.price_q64,
+        quantity: order.visible_quantity(),
+        fee_bps: order.fee_bps,
+        time_in_force,
+        post_only,
+        trigger_price,
+        trigger_direction,
+        client_nonce: placement.client_nonce,
+        timestamp: now,
+        event_seq: 0,
+        state_hash: [0; 32], // stamped by record_event
+    };
+    record_event_cpi(market, event, events)?;
+
+    msg!(
+        "Order #{} placed | side={:?} price={} qty={} expires_at={}",
+        order_id,
+        side,
+        price,
+        quantity,
+        expires_at,
+    );
+    Ok(())
+}
+
+/// Quantity an order still shows on the book: none once it's Filled, which
+/// also covers a dust remainder closed with its last fill, and only the
+/// current tranche of an iceberg.
+fn book_remaining(order: &Order) -> u64 {
+    if order.status == OrderStatus::Filled {
+        0
+    } else {
+        order.visible_quantity()
+    }
+}
+
+/// Add an order's remaining quantity to the market's side volume and top
+/// of book.
+fn add_to_book(market: &mut Market, order: &Order) -> Result<()> {
+    let quantity = order.remaining_quantity();
+    market.add_to_top_of_book(order, quantity);
+    if order.side == Side::Buy {
+        market.total_bid_volume = market
+            .total_bid_volume
+            .checked_add(quantity)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+    } else {
+        market.total_ask_volume = market
+            .total_ask_volume
+            .checked_add(quantity)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+    }
+    Ok(())
+}
+
+/// Bring an indexed order's entry in its BookSide up to date: at its price
+/// with its displayed remainder while it rests, gone once it doesn't.
+/// Orders that aren't indexed are left alone; indexed ones need the
+/// market's book of their side (BookSideRequired, BookSideMismatch). A
+/// best level that has emptied on the market is refilled from the book.
+fn sync_book(market: &mut Market, book: Option<&AccountLoader<BookSide>>, order: &Order) -> Result<()> {
+    if !order.in_book {
+        return Ok(());
+    }
+    let mut book = order_book(market, book, order)?.load_mut()?;
+    book.remove(order.order_id);
+    let remaining = book_remaining(order);
+    if order.is_active() && remaining > 0 {
+        book.insert(BookEntry::new(order, remaining))?;
+    }
+    market.refill_top_of_book(&order.side, &book);
+    Ok(())
+}
+
+/// The passed `book`, checked to be the market's BookSide of `order`'s
+/// side (BookSideRequired, BookSideMismatch).
+fn order_book<'a, 'info>(
+    market: &Market,
+    book: Option<&'a AccountLoader<'info, BookSide>>,
+    order: &Order,
+) -> Result<&'a AccountLoader<'info, BookSide>> {
+    let book = book.ok_or(MatchingEngineError::BookSideRequired)?;
+    require_keys_eq!(
+        book.key(),
+        market.book_side(&order.side),
+        MatchingEngineError::BookSideMismatch
+    );
+    Ok(book)
+}
+
+// ─── Fills Outside match_orders ───────────────────────────────────────────────
+//
+// place_order_tif fills a new order against several makers in one
+// instruction, take_order does the same for a taker without an Order, and
+// match_orders_multi sweeps several asks with one bid.
+// Each fill is settled by settle_fill, the lamport path of match_orders
+// without crank reward or token vaults; seats come as maker group slots.
+
+/// The MatchContext settle_fill validates and prices a fill with.
+fn fill_context(
+    market: &Market,
+    bid: &Order,
+    ask: &Order,
+    fees: &FillFees,
+    exempt: (bool, bool),
+    clock: &Clock,
+    clearing_price: Option<u64>,
+) -> Result<MatchContext> {
+    // Only match_orders can credit a UserBalance
+    require!(
+        !bid.settles_to_balance && !ask.settles_to_balance,
+        MatchingEngineError::DeferredSettlementUnsupported
+    );
+    let (maker_fee_exempt, taker_fee_exempt) = pair_exemptions(bid, ask, exempt.0, exempt.1);
+    Ok(MatchContext {
+        is_paused: market.is_paused,
+        is_expired: market.is_expired(clock.unix_timestamp),
+        is_archiving: market.is_archiving,
+        fee_bps: if fees.fee_config.is_some() && !maker_fee_exempt && !taker_fee_exempt {
+            ask_fee_bps(bid, ask)
+        } else {
+            0
+        },
+        matcher_fee_bps: if fees.matcher.is_some() {
+            market.matcher_fee_bps
+        } else {
+            0
+        },
+        now: clock.unix_timestamp,
+        last_trade_price: market.last_trade_price,
+        price_band_bps: market.price_band_bps,
+        is_auction: market.auction_mode,
+        clearing_price,
+        ..MatchContext::default()
+    })
+}
+
+/// Program error code of `err`, as reported in return data; u32::MAX for
+/// a runtime error.
+fn error_code(err: &Error) -> u32 {
+    match err {
+        Error::AnchorError(err) => err.error_code_number,
+        Error::ProgramError(err) => match err.program_error {
+            ProgramError::Custom(code) => code,
+            _ => u32::MAX,
+        },
+    }
+}
+
+/// Where a fill's price-improvement refund goes.
+enum RefundTo<'a, 'info> {
+    Wallet(&'a AccountInfo<'info>),
+    Balance(&'a mut Account<'info, TradingBalance>),
+}
+
+/// Where a fill's bid lamports come from.
+enum BidFunds<'a, 'info> {
+    /// The bid order's escrow vault, which keeps its rent; the price
+    /// improvement is refunded to `refund`.
+    Escrow {
+        vault: EscrowVault<'a, 'info>,
+        refund: RefundTo<'a, 'info>,
+    },
+    /// A take_order taker's wallet, debited only what the fill costs. The
+    /// bid is then the taker's stand-in order.
+    Taker {
+        wallet: &'a AccountInfo<'info>,
+        system_program: &'a AccountInfo<'info>,
+    },
+}
+
+impl<'info> BidFunds<'_, 'info> {
+    /// Pay `amount` of the fill out of the bid's funds.
+    fn pay(&self, to: &AccountInfo<'info>, amount: u64) -> Result<()> {
+        match self {
+            BidFunds::Escrow { vault, .. } => vault.pay(to, amount),
+            BidFunds::Taker { .. } if amount == 0 => Ok(()),
+            BidFunds::Taker { wallet, system_program: program } => system_program::transfer(
+                CpiContext::new(
+                    (*program).clone(),
+                    system_program::Transfer {
+                        from: (*wallet).clone(),
+                        to: to.clone(),
+                    },
+                ),
+                amount,
+            ),
+        }
+    }
+}
+
+/// Fee accounts a fill pays into.
+struct FillFees<'a, 'info> {
+    config: &'a GlobalConfig,
+    fee_config: Option<&'a mut Account<'info, FeeConfig>>,
+    /// Must be market.fee_recipient when passed.
+    treasury: Option<&'a AccountInfo<'info>>,
+    fee_vault: Option<&'a mut Account<'info, FeeVault>>,
+    /// Paid the market's matcher fee; None for fills no matcher cranked.
+    matcher: Option<&'a AccountInfo<'info>>,
+}
+
+/// Post-trade invariants on the settled accounts themselves: neither order
+/// filled past its quantity, and the bid still holding at least its rent.
+/// compute_settlement has already checked the amounts balance.
+fn check_filled_pair(bid: &Account<Order>, ask: &Account<Order>) -> Result<()> {
+    require!(
+        bid.filled_quantity <= bid.quantity && ask.filled_quantity <= ask.quantity,
+        MatchingEngineError::FillExceedsQuantity
+    );
+    let bid_info = bid.to_account_info();
+    require!(
+        bid_info.lamports() >= Rent::get()?.minimum_balance(bid_info.data_len()),
+        MatchingEngineError::EscrowBelowRent
+    );
+    Ok(())
+}
+
+/// Settle one fill of `bid` against `ask` out of `bid_funds`: the seller
+/// side is paid to `seller_payee`, an escrowed bid's price improvement is
+/// refunded, and fee and dust go where match_orders sends them. The fill
+/// is at the ask's price, or at `clearing_price` (settle_auction, and
+/// take_order selling into a bid), with no fee when `exempt` says the
+/// (bid, ask) owner's seat waives it, as in match_orders. Updates fills,
+/// volumes — except for a taker's stand-in, which never rested — `stats`
+/// (for whichever counted order it belongs to) and the trade sequence,
+/// and records a TradeExecutedEvent unless the market batches trade
+/// events.
+#[allow(clippy::too_many_arguments)]
+fn settle_fill<'info>(
+    market: &mut Account<'info, Market>,
+    bid: &mut Order,
+    ask: &mut Order,
+    seller_payee: &AccountInfo<'info>,
+    mut bid_funds: BidFunds<'_, 'info>,
+    mut stats: Option<&mut Account<'info, UserStats>>,
+    fees: &mut FillFees<'_, 'info>,
+    exempt: (bool, bool),
+    clock: &Clock,
+    clearing_price: Option<u64>,
+) -> Result<MatchSettlement> {
+    let (maker_fee_exempt, taker_fee_exempt) = pair_exemptions(bid, ask, exempt.0, exempt.1);
+    let match_ctx = fill_context(market, bid, ask, fees, exempt, clock, clearing_price)?;
+    let settlement = matching::compute_settlement(bid, ask, &match_ctx)?;
+    let MatchSettlement {
+        fill_quantity: fill_qty,
+        fill_price,
+        fill_price_q64,
+        fee_amount,
+        dust_amount,
+        matcher_fee_amount,
+        net_seller_payment,
+        buyer_refund: refund,
+        total_debit,
+        ..
+    } = settlement.clone();
+
+    require!(
+        seller_payee.key() == ask.proceeds_recipient(),
+        MatchingEngineError::BeneficiaryMismatch
+    );
+
+    // ── Move the lamports out of the bid's funds ──────────────────────────
+    if let BidFunds::Escrow { vault, refund: refund_to } = &mut bid_funds {
+        vault.require_escrow(total_debit)?;
+        match refund_to {
+            RefundTo::Wallet(wallet) => {
+                require!(
+                    wallet.key() == bid.refund_recipient(),
+                    MatchingEngineError::FunderMismatch
+                );
+                vault.pay(wallet, refund)?;
+            }
+            RefundTo::Balance(balance) => {
+                vault.pay(&balance.to_account_info(), refund)?;
+                balance.lamports = balance
+                    .lamports
+                    .checked_add(refund)
+                    .ok_or(MatchingEngineError::MathOverflow)?;
+            }
+        }
+    }
+    bid_funds.pay(seller_payee, net_seller_payment)?;
+    if let Some(matcher) = fees.matcher {
+        bid_funds.pay(matcher, matcher_fee_amount)?;
+    }
+
+    let protocol_fee_amount = fees.config.protocol_share(fee_amount);
+    let treasury_amount = (fee_amount - protocol_fee_amount)
+        .checked_add(dust_amount)
+        .ok_or(MatchingEngineError::MathOverflow)?;
+    if protocol_fee_amount > 0 {
+        let vault = fees
+            .fee_vault
+            .as_mut()
+            .ok_or(MatchingEngineError::FeeVaultRequired)?;
+        bid_funds.pay(&vault.to_account_info(), protocol_fee_amount)?;
+        vault.protocol_fees = vault
+            .protocol_fees
+            .checked_add(protocol_fee_amount)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+    }
+    let mut fee_paid_to = market.fee_recipient;
+    if treasury_amount > 0 {
+        let direct = match fees.treasury {
+            Some(treasury) => {
+                require!(
+                    treasury.key() == market.fee_recipient,
+                    MatchingEngineError::TreasuryMismatch
+                );
+                can_receive_fees(treasury, treasury_amount)?
+            }
+            None => false,
+        };
+        let recipient = match fees.treasury {
+            Some(treasury) if direct => treasury.clone(),
+            _ => fees
+                .fee_vault
+                .as_ref()
+                .ok_or(MatchingEngineError::FeeVaultRequired)?
+                .to_account_info(),
+        };
+        fee_paid_to = recipient.key();
+        bid_funds.pay(&recipient, treasury_amount)?;
+    }
+    if let Some(fee_config) = fees.fee_config.as_mut() {
+        fee_config.accumulated_fees = fee_config.accumulated_fees.saturating_add(fee_amount);
+        fee_config.protocol_accumulated_fees = fee_config
+            .protocol_accumulated_fees
+            .saturating_add(protocol_fee_amount);
+    }
+    market.dust_lamports = market
+        .dust_lamports
+        .checked_add(dust_amount)
+        .ok_or(MatchingEngineError::MathOverflow)?;
+    if let BidFunds::Escrow { .. } = bid_funds {
+        bid.escrow_lamports = bid
+            .escrow_lamports
+            .checked_sub(total_debit)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+    }
+
+    // ── Fill state, volumes, stats ────────────────────────────────────────
+    bid.filled_quantity = settlement.bid_filled_after;
+    ask.filled_quantity = settlement.ask_filled_after;
+    bid.set_status(settlement.bid_status_after.clone(), clock.unix_timestamp);
+    ask.set_status(settlement.ask_status_after.clone(), clock.unix_timestamp);
+    bid.consume_display(fill_qty);
+    ask.consume_display(fill_qty);
+    bid.bump_update_count();
+    ask.bump_update_count();
+    require!(
+        bid.filled_quantity <= bid.quantity && ask.filled_quantity <= ask.quantity,
+        MatchingEngineError::FillExceedsQuantity
+    );
+
+    for order in [&*bid, &*ask] {
+        if order.is_taker_stand_in() {
+            continue;
+        }
+        match order.side {
+            Side::Buy => market.total_bid_volume = market.total_bid_volume.saturating_sub(fill_qty),
+            Side::Sell => market.total_ask_volume = market.total_ask_volume.saturating_sub(fill_qty),
+        }
+        market.remove_from_top_of_book(order, fill_qty);
+    }
+    market.record_trade(
+        fill_price,
+        fill_qty,
+        settlement.gross_seller_payment,
+        clock.unix_timestamp,
+    )?;
+    for order in [&*bid, &*ask] {
+        if order.status == OrderStatus::Filled && !order.is_taker_stand_in() {
+            market.open_order_count = market.open_order_count.saturating_sub(1);
+        }
+    }
+    for (order, side) in [(&*bid, Side::Buy), (&*ask, Side::Sell)] {
+        if !order.counted_in_stats {
+            continue;
+        }
+        match stats.as_mut() {
+            Some(stats) if stats.owner == order.owner => stats.record_fill(
+                &side,
+                fill_qty,
+                settlement.gross_seller_payment,
+                order.status == OrderStatus::Filled,
+            ),
+            _ => return err!(MatchingEngineError::UserStatsRequired),
+        }
+    }
+
+    if !market.batch_trade_events {
+        let event = TradeExecutedEvent {
+            bid_order_id: bid.order_id,
+            ask_order_id: ask.order_id,
+            market: market.key(),
+            buyer: bid.owner,
+            seller: ask.owner,
+            proceeds_to: seller_payee.key(),
+            fill_price,
+            fill_price_q64,
+            fill_quantity: fill_qty,
+            fee_bps: match_ctx.fee_bps,
+            ask_is_taker: ask.order_id > bid.order_id,
+            fee_amount,
+            dust_amount,
+            fee_paid_to,
+            protocol_fee_amount,
+            referrer: ask.referrer,
+            referral_amount: 0,
+            trade_seq: market.trade_seq,
+            maker_fee_exempt,
+            taker_fee_exempt,
+            crank_reward: 0,
+            matcher_fee: matcher_fee_amount,
+            bid_residual: 0,
+            ask_residual: 0,
+            residual_refund: 0,
+            bid_remaining: book_remaining(bid),
+            ask_remaining: book_remaining(ask),
+            bid_status: bid.status.clone(),
+            ask_status: ask.status.clone(),
+            // Fills at placement have no crank: the taker's owner
+            matcher: match fees.matcher {
+                Some(matcher) => matcher.key(),
+                None if ask.order_id > bid.order_id => ask.owner,
+                None => bid.owner,
+            },
+            timestamp: clock.unix_timestamp,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)?;
+    }
+    Ok(settlement)
+}
+
+/// One taker's fills against several makers, recorded as a TradeBatchEvent.
+#[derive(Default)]
+struct TakerFills {
+    fills: [TradeBatchFill; TRADE_BATCH_CAPACITY],
+    count: usize,
+    first_trade_seq: u64,
+    total_quantity: u64,
+    total_notional: u64,
+    total_fee: u64,
+}
+
+impl TakerFills {
+    fn push(&mut self, maker_order_id: u64, settlement: &MatchSettlement, trade_seq: u64) {
+        if self.count == 0 {
+            self.first_trade_seq = trade_seq;
+        }
+        self.fills[self.count] = TradeBatchFill {
+            maker_order_id,
+            quantity: settlement.fill_quantity,
+            price: settlement.fill_price,
+        };
+        self.count += 1;
+        self.total_quantity = self.total_quantity.saturating_add(settlement.fill_quantity);
+        self.total_notional = self
+            .total_notional
+            .saturating_add(settlement.gross_seller_payment);
+        self.total_fee = self.total_fee.saturating_add(settlement.fee_amount);
+    }
+
+    /// Record the batch, if anything filled.
+    fn record(self, market: &mut Market, taker: &Order, now: i64) -> Result<()> {
+        if self.count == 0 {
+            return Ok(());
+        }
+        let event = TradeBatchEvent {
+            market: taker.market,
+            taker_order_id: taker.order_id,
+            taker_side: taker.side.clone(),
+            taker: taker.owner,
+            first_trade_seq: self.first_trade_seq,
+            last_trade_seq: market.trade_seq,
+            total_quantity: self.total_quantity,
+            total_notional: self.total_notional,
+            total_fee: self.total_fee,
+            fill_count: self.count as u8,
+            fills: self.fills,
+            timestamp: now,
+            event_seq: 0,
+            state_hash: [0; 32], // stamped by record_event
+        };
+        record_event(market, event)
+    }
+}
+
+/// Load a maker order from `remaining_accounts`: a genuine order PDA of
+/// `market` whose fill needs no accounts beyond its group and the market's
+/// book of its side.
+fn load_maker<'info>(info: &'info AccountInfo<'info>, market: Pubkey) -> Result<Account<'info, Order>> {
+    let maker: Account<'info, Order> = Account::try_from(info)?;
+    require!(maker.market == market, MatchingEngineError::MarketMismatch);
+    require!(maker.is_pda(info.key), MatchingEngineError::MarketMismatch);
+    require!(
+        !maker.funded_from_balance
+            && !maker.counted_in_stats
+            && !maker.tracked_in_open_orders
+            && !maker.escrow_in_vault
+            && maker.base_escrow == 0,
+        MatchingEngineError::MakerNotFillable
+    );
+    Ok(maker)
+}
+
+/// Fill the order just placed against the (maker order, maker wallet,
+/// maker seat, maker escrow vault) groups in `remaining_accounts`, in the
+/// order given, until it is filled. Passed seats' fee exemptions apply,
+/// the owner's from `trader_seat`.
+fn fill_at_placement<'info>(ctx: &mut Context<'_, '_, 'info, 'info, PlaceOrder<'info>>) -> Result<()> {
+    let makers = ctx.remaining_accounts;
+    require!(
+        makers.len().is_multiple_of(Market::MAKER_ACCOUNTS)
+            && makers.len() / Market::MAKER_ACCOUNTS <= TRADE_BATCH_CAPACITY,
+        MatchingEngineError::InvalidMakerAccounts
+    );
+    let clock = Clock::get()?;
+    let accounts = &mut *ctx.accounts;
+    let market_key = accounts.market.key();
+    let owner = accounts.owner.to_account_info();
+    let owner_exempt = accounts.trader_seat.as_ref().is_some_and(|seat| seat.fee_exempt);
+    let funder = accounts.funder.as_ref().map(|f| f.to_account_info());
+    let beneficiary = accounts.beneficiary.as_ref().map(|b| b.to_account_info());
+    let treasury = accounts.treasury.as_ref().map(|t| t.to_account_info());
+    let escrow_vault = accounts.escrow_vault.to_account_info();
+    let system_program = accounts.system_program.to_account_info();
+    let mut fees = FillFees {
+        config: &accounts.config,
+        fee_config: accounts.fee_config.as_mut(),
+        treasury: treasury.as_ref(),
+        fee_vault: accounts.fee_vault.as_mut(),
+        matcher: None,
+    };
+    let mut batch = TakerFills::default();
+
+    for group in makers.chunks(Market::MAKER_ACCOUNTS) {
+        if !accounts.order.is_active() {
+            break;
+        }
+        let mut maker = load_maker(&group[0], market_key)?;
+        let wallet = &group[1];
+        let maker_exempt = maker_fee_exempt(&group[2], &maker)?;
+        let settlement = match accounts.order.side {
+            Side::Buy => {
+                let refund = if accounts.order.funded_from_balance {
+                    RefundTo::Balance(
+                        accounts
+                            .trading_balance
+                            .as_mut()
+                            .ok_or(MatchingEngineError::TradingBalanceRequired)?,
+                    )
+                } else {
+                    RefundTo::Wallet(refund_wallet(&accounts.order, &owner, funder.as_ref())?)
+                };
+                let escrow = EscrowAccounts {
+                    vault: &escrow_vault,
+                    system_program: &system_program,
+                };
+                let bid_funds = BidFunds::Escrow {
+                    vault: EscrowVault::of(&accounts.order, &escrow)?,
+                    refund,
+                };
+                settle_fill(
+                    &mut accounts.market,
+                    &mut accounts.order,
+                    &mut maker,
+                    wallet,
+                    bid_funds,
+                    accounts.user_stats.as_mut(),
+                    &mut fees,
+                    (owner_exempt, maker_exempt),
+                    &clock,
+                    None,
+                )?
+            }
+            Side::Sell => {
+                let escrow = EscrowAccounts {
+                    vault: &group[3],
+                    system_program: &system_program,
+                };
+                let bid_funds = BidFunds::Escrow {
+                    vault: EscrowVault::of(&maker, &escrow)?,
+                    refund: RefundTo::Wallet(wallet),
+                };
+                settle_fill(
+                    &mut accounts.market,
+                    &mut maker,
+                    &mut accounts.order,
+                    beneficiary.as_ref().unwrap_or(&owner),
+                    bid_funds,
+                    accounts.user_stats.as_mut(),
+                    &mut fees,
+                    (maker_exempt, owner_exempt),
+                    &clock,
+                    None,
+                )?
+            }
+        };
+        sync_book(&mut accounts.market, accounts.maker_book_side.as_ref(), &maker)?;
+        maker.exit(&crate::ID)?;
+        batch.push(maker.order_id, &settlement, accounts.market.trade_seq);
+    }
+    if accounts.order.status == OrderStatus::Filled {
+        untrack_order(&accounts.order, accounts.open_orders.as_mut())?;
+    }
+    batch.record(&mut accounts.market, &accounts.order, clock.unix_timestamp)
+}
+
+/// Fee rate for a pair: the ask's placement-time snapshot (the seller pays
+/// the fee) — its maker rate when it was resting, its taker rate when it
+/// was the incoming order — waived for an exempt party. Exemptions are
+/// read live, so a revocation applies from the next trade. No fee without
+/// the fee config.
+fn trade_fee_bps(
+    fee_config: &Option<Account<FeeConfig>>,
+    bid_order: &Order,
+    ask_order: &Order,
+    fee_exempt: bool,
+) -> u16 {
+    if fee_config.is_none() || fee_exempt {
+        return 0;
+    }
+    ask_fee_bps(bid_order, ask_order)
+}
+
+/// The ask's taker rate when it is the newer order of the pair, else its
+/// maker rate.
+fn ask_fee_bps(bid_order: &Order, ask_order: &Order) -> u16 {
+    if ask_order.order_id > bid_order.order_id {
+        ask_order.taker_fee_bps
+    } else {
+        ask_order.fee_bps
+    }
+}
+
+/// Deserialize an optional remaining account; the program id marks "none".
+fn optional_account<'info, T>(info: &'info AccountInfo<'info>) -> Result<Option<Account<'info, T>>>
+where
+    T: AccountSerialize + AccountDeserialize + Owner + Clone,
+{
+    if info.key() == crate::ID {
+        return Ok(None);
+    }
+    Ok(Some(Account::try_from(info)?))
+}
+
+fn validate_market_params(params: &MarketParams) -> Result<()> {
+    require!(
+        params.fee_bps <= FeeConfig::MAX_FEE_BPS,
+        MatchingEngineError::FeeBpsTooHigh
+    );
+    require!(
+        params.params_timelock_secs >= 0,
+        MatchingEngineError::InvalidTimelock
+    );
+    Ok(())
+}
+
+fn write_market_params(market: &mut Market, fee_config: &mut FeeConfig, params: &MarketParams) {
+    fee_config.fee_bps = params.fee_bps;
+    fee_config.taker_fee_bps = params.fee_bps;
+    market.fee_bps = params.fee_bps;
+    market.taker_fee_bps = params.fee_bps;
+    market.fee_recipient = params.treasury;
+    market.params_timelock_secs = params.params_timelock_secs;
+    market.price_band_bps = params.price_band_bps;
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Account Validation Contexts
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Accounts)]
+#[instruction(market_name: String, taker_only_window_secs: i64)]
+pub struct InitializeMarket<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Market::LEN,
+        seeds = [b"market", authority.key().as_ref(), market_name.as_bytes()],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = FeeVault::LEN,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump,
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    /// CHECK: Optional SPL quote mint, verified in the instruction body.
+    pub quote_mint: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Quote vault PDA ["quote_vault", market], created in the
+    /// instruction body — required with a non-native quote mint.
+    #[account(mut)]
+    pub quote_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Optional SPL base mint, verified in the instruction body.
+    pub base_mint: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Base vault PDA ["base_vault", market], created in the
+    /// instruction body — required with a base mint.
+    #[account(mut)]
+    pub base_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SPL Token program, verified in the instruction body.
+    pub token_program: Option<UncheckedAccount<'info>>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    /// Every market is listed, so the registry must exist before the first
+    /// one is created.
+    #[account(mut, seeds = [b"registry"], bump = registry.bump)]
+    pub registry: Account<'info, MarketRegistry>,
+
+    /// The market's listing.
+    /// Seeds: ["listing", registry.count]
+    #[account(
+        init,
+        payer = authority,
+        space = MarketListing::LEN,
+        seeds = [b"listing".as_ref(), &registry.count.to_le_bytes()],
+        bump,
+    )]
+    pub listing: Account<'info, MarketListing>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpgradeMarket<'info> {
+    /// The market authority; pays the rent of any bytes the market gains.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: A Market of this program at any layout version, possibly too
+    /// short to deserialize; its discriminator and authority are checked in
+    /// the instruction body.
+    #[account(mut, owner = crate::ID)]
+    pub market: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpgradeOrder<'info> {
+    /// The order owner; pays the rent of any bytes the order gains.
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: An Order of this program at any layout version, possibly too
+    /// short to deserialize; its discriminator, owner and market are checked
+    /// in the instruction body.
+    #[account(mut, owner = crate::ID)]
+    pub order: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Context for admin market state changes; the signer is checked against the
+/// authority or the instruction's role in the instruction body.
+#[derive(Accounts)]
+pub struct AuthorityAction<'info> {
+    /// Market authority, or the holder of the instruction's role.
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    /// Optional delegated roles; lets a role holder sign instead of the authority.
+    #[account(
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, MarketRoles>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRoles<'info> {
+    #[account(
+        mut,
+        constraint = !market.is_renounced() @ MatchingEngineError::AuthorityRenounced,
+        constraint = authority.key() == market.authority @ MatchingEngineError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = MarketRoles::LEN,
+        seeds = [b"roles", market.key().as_ref()],
+        bump,
+    )]
+    pub roles: Account<'info, MarketRoles>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Role changes — always the market authority itself, never a role holder.
+#[derive(Accounts)]
+pub struct ManageRoles<'info> {
+    #[account(
+        constraint = !market.is_renounced() @ MatchingEngineError::AuthorityRenounced,
+        constraint = authority.key() == market.authority @ MatchingEngineError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Account<'info, MarketRoles>,
+}
+
+#[derive(Accounts)]
+pub struct RenounceAuthority<'info> {
+    #[account(
+        constraint = !market.is_renounced() @ MatchingEngineError::AuthorityRenounced,
+        constraint = authority.key() == market.authority @ MatchingEngineError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: Must be empty — pending params could otherwise still change the market.
+    #[account(
+        seeds = [b"staged_params", market.key().as_ref()],
+        bump,
+    )]
+    pub staged_params: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeFeeConfig<'info> {
+    /// Market authority, or the holder of the instruction's role.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = FeeConfig::LEN,
+        seeds = [b"fee_config", market.key().as_ref()],
+        bump,
+    )]
+    pub fee_config: Account<'info, FeeConfig>,
+
+    /// Optional delegated roles; lets a role holder sign instead of the authority.
+    #[account(
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, MarketRoles>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFeeConfig<'info> {
+    /// Market authority, or the holder of the instruction's role.
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_config", market.key().as_ref()],
+        bump = fee_config.bump,
+        constraint = fee_config.market == market.key() @ MatchingEngineError::TreasuryMismatch,
+    )]
+    pub fee_config: Account<'info, FeeConfig>,
+
+    /// Optional delegated roles; lets a role holder sign instead of the authority.
+    #[account(
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, MarketRoles>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = GlobalConfig::LEN,
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRegistry<'info> {
+    #[account(mut, constraint = admin.key() == config.admin @ MatchingEngineError::Unauthorized)]
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = MarketRegistry::LEN,
+        seeds = [b"registry"],
+        bump,
+    )]
+    pub registry: Account<'info, MarketRegistry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(registry_index: u64)]
+pub struct DelistMarket<'info> {
+    /// Market authority or protocol admin, checked in the instruction body.
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"listing".as_ref(), &registry_index.to_le_bytes()],
+        bump = listing.bump,
+    )]
+    pub listing: Account<'info, MarketListing>,
+
+    /// CHECK: The listed market; may already be closed. Its authority is
+    /// read, and the event chained into it, when it's still a live Market.
+    #[account(mut, address = listing.market)]
+    pub market: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetProtocolConfig<'info> {
+    #[account(constraint = admin.key() == config.admin @ MatchingEngineError::Unauthorized)]
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    /// Market authority, or the holder of the instruction's role.
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump = fee_vault.bump,
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    /// CHECK: Any account chosen by the authority receives the lamports —
+    /// a quote token account on token-quoted markets.
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+
+    /// CHECK: The market's quote vault — required on token-quoted markets;
+    /// verified in the instruction body.
+    #[account(mut)]
+    pub quote_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SPL Token program, verified in the instruction body.
+    pub token_program: Option<UncheckedAccount<'info>>,
+
+    /// Optional delegated roles; lets a role holder sign instead of the authority.
+    #[account(
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, MarketRoles>>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawProtocolFees<'info> {
+    #[account(constraint = admin.key() == config.admin @ MatchingEngineError::Unauthorized)]
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump = fee_vault.bump,
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    /// CHECK: The protocol treasury — or, on token-quoted markets, a quote
+    /// token account it owns (verified when paid).
+    #[account(
+        mut,
+        constraint = market.is_token_quoted()
+            || destination.key() == config.treasury @ MatchingEngineError::ProtocolTreasuryMismatch,
+    )]
+    pub destination: UncheckedAccount<'info>,
+
+    /// CHECK: The market's quote vault — required on token-quoted markets;
+    /// verified in the instruction body.
+    #[account(mut)]
+    pub quote_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SPL Token program, verified in the instruction body.
+    pub token_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMarketParams<'info> {
+    /// Market authority, or the holder of the instruction's role.
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_config", market.key().as_ref()],
+        bump = fee_config.bump,
+    )]
+    pub fee_config: Account<'info, FeeConfig>,
+
+    /// Optional delegated roles; lets a role holder sign instead of the authority.
+    #[account(
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, MarketRoles>>,
+}
+
+#[derive(Accounts)]
+pub struct StageMarketParams<'info> {
+    /// Market authority, or the holder of the instruction's role.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    /// Staged params write into the fee config, so it must already exist.
+    #[account(
+        seeds = [b"fee_config", market.key().as_ref()],
+        bump = fee_config.bump,
+    )]
+    pub fee_config: Account<'info, FeeConfig>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = StagedParams::LEN,
+        seeds = [b"staged_params", market.key().as_ref()],
+        bump,
+    )]
+    pub staged_params: Account<'info, StagedParams>,
+
+    /// Optional delegated roles; lets a role holder sign instead of the authority.
+    #[account(
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, MarketRoles>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyStagedParams<'info> {
+    /// CHECK: Receives the staging rent. Pinned to market.authority.
+    #[account(
+        mut,
+        constraint = !market.is_renounced() @ MatchingEngineError::AuthorityRenounced,
+        address = market.authority @ MatchingEngineError::Unauthorized,
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_config", market.key().as_ref()],
+        bump = fee_config.bump,
+    )]
+    pub fee_config: Account<'info, FeeConfig>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"staged_params", market.key().as_ref()],
+        bump = staged_params.bump,
+    )]
+    pub staged_params: Account<'info, StagedParams>,
+}
+
+#[derive(Accounts)]
+pub struct DiscardStagedParams<'info> {
+    /// Market authority, or the holder of the instruction's role.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"staged_params", market.key().as_ref()],
+        bump = staged_params.bump,
+    )]
+    pub staged_params: Account<'info, StagedParams>,
+
+    /// Optional delegated roles; lets a role holder sign instead of the authority.
+    #[account(
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, MarketRoles>>,
+}
+
+#[derive(Accounts)]
+#[instruction(trader: Pubkey)]
+pub struct AddTrader<'info> {
+    /// Market authority, or the holder of the instruction's role.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = TraderSeat::LEN,
+        seeds = [b"seat", market.key().as_ref(), trader.as_ref()],
+        bump,
+    )]
+    pub trader_seat: Account<'info, TraderSeat>,
+
+    /// Optional delegated roles; lets a role holder sign instead of the authority.
+    #[account(
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, MarketRoles>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(trader: Pubkey)]
+pub struct RemoveTrader<'info> {
+    /// Market authority, or the holder of the instruction's role.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"seat", market.key().as_ref(), trader.as_ref()],
+        bump = trader_seat.bump,
+    )]
+    pub trader_seat: Account<'info, TraderSeat>,
+
+    /// Optional delegated roles; lets a role holder sign instead of the authority.
+    #[account(
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, MarketRoles>>,
+}
+
+#[derive(Accounts)]
+pub struct SetMatcherRestricted<'info> {
+    #[account(
+        constraint = !market.is_renounced() @ MatchingEngineError::AuthorityRenounced,
+        constraint = authority.key() == market.authority @ MatchingEngineError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+#[instruction(matcher: Pubkey)]
+pub struct RegisterMatcher<'info> {
+    #[account(
+        mut,
+        constraint = !market.is_renounced() @ MatchingEngineError::AuthorityRenounced,
+        constraint = authority.key() == market.authority @ MatchingEngineError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = MatcherSeat::LEN,
+        seeds = [b"matcher_seat", market.key().as_ref(), matcher.as_ref()],
+        bump,
+    )]
+    pub matcher_seat: Account<'info, MatcherSeat>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(matcher: Pubkey)]
+pub struct RevokeMatcher<'info> {
+    #[account(
+        mut,
+        constraint = !market.is_renounced() @ MatchingEngineError::AuthorityRenounced,
+        constraint = authority.key() == market.authority @ MatchingEngineError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"matcher_seat", market.key().as_ref(), matcher.as_ref()],
+        bump = matcher_seat.bump,
+    )]
+    pub matcher_seat: Account<'info, MatcherSeat>,
+}
+
+#[derive(Accounts)]
+#[instruction(trader: Pubkey)]
+pub struct SetFeeExempt<'info> {
+    /// Market authority, or the holder of the instruction's role.
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"seat", market.key().as_ref(), trader.as_ref()],
+        bump = trader_seat.bump,
+    )]
+    pub trader_seat: Account<'info, TraderSeat>,
+
+    /// Optional delegated roles; lets a role holder sign instead of the authority.
+    #[account(
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, MarketRoles>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(side: Side, price: u64, quantity: u64, order_id: u64, expires_at: i64)]
+pub struct PlaceOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+        constraint = !market.has_book_sides() || book_side.is_some() @ MatchingEngineError::BookSideRequired,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Order::LEN,
+        seeds = [b"order", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump,
+    )]
+    pub order: Account<'info, Order>,
+
+    /// The order's escrow vault — holds a BUY's lamport escrow.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump,
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// Optional pre-funded balance. Used for BUY escrow when it covers the amount.
+    #[account(
+        mut,
+        seeds = [b"balance", market.key().as_ref(), owner.key().as_ref()],
+        bump = trading_balance.bump,
+    )]
+    pub trading_balance: Option<Account<'info, TradingBalance>>,
+
+    /// Owner's seat — required when the market is permissioned or makers_restricted.
+    #[account(
+        seeds = [b"seat", market.key().as_ref(), owner.key().as_ref()],
+        bump = trader_seat.bump,
+    )]
+    pub trader_seat: Option<Account<'info, TraderSeat>>,
+
+    /// Owner's open-volume stats — required when a maker share cap is set.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), owner.key().as_ref()],
+        bump = user_stats.bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+
+    /// Owner's OpenOrders — lists the new order when passed.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), owner.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// The market's BookSide of the order's side — required once it has book
+    /// sides; indexes a GTC order.
+    #[account(mut)]
+    pub book_side: Option<AccountLoader<'info, BookSide>>,
+
+    /// The market's BookSide of the other side — required when a maker
+    /// filled at placement is indexed.
+    #[account(mut)]
+    pub maker_book_side: Option<AccountLoader<'info, BookSide>>,
+
+    /// Protocol config — placement stops while the protocol is paused.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    /// CHECK: Receives the sell's proceeds instead of the owner; paid
+    /// directly by fills at placement (place_order_tif).
+    #[account(mut)]
+    pub beneficiary: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Only its key is recorded, as the order's referrer.
+    pub referrer: Option<UncheckedAccount<'info>>,
+
+    /// Co-signer paying a BUY's escrow; refunds return to it.
+    #[account(mut)]
+    pub funder: Option<Signer<'info>>,
+
+    /// Market rent sponsor — reimburses the order's rent when funded.
+    #[account(
+        mut,
+        seeds = [b"rent_subsidy", market.key().as_ref()],
+        bump = rent_subsidy_vault.bump,
+    )]
+    pub rent_subsidy_vault: Option<Account<'info, RentSubsidyVault>>,
+
+    /// CHECK: The market's quote vault — required for a BUY on a
+    /// token-quoted market; verified in the instruction body.
+    #[account(mut)]
+    pub quote_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The owner's quote token account the BUY escrow is pulled from;
+    /// the token program checks it.
+    #[account(mut)]
+    pub owner_quote_account: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The market's base vault — required for a SELL on a
+    /// base-escrowed market; verified in the instruction body.
+    #[account(mut)]
+    pub base_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The owner's base token account a SELL's quantity is pulled
+    /// from; the token program checks it.
+    #[account(mut)]
+    pub owner_base_account: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SPL Token program, verified in the instruction body.
+    pub token_program: Option<UncheckedAccount<'info>>,
+
+    /// Fee config — fees on fills at placement (place_order_tif), as in match_orders.
+    #[account(
+        mut,
+        seeds = [b"fee_config", market.key().as_ref()],
+        bump = fee_config.bump,
+    )]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+
+    /// CHECK: The market's fee recipient, for fees on fills at placement;
+    /// verified in the instruction body.
+    #[account(mut)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+
+    /// Market fee vault — the protocol's share of fees on fills at
+    /// placement, and the fallback when the fee recipient can't be paid.
+    #[account(
+        mut,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump = fee_vault.bump,
+    )]
+    pub fee_vault: Option<Account<'info, FeeVault>>,
+
+    /// Owner's UserBalance — match_orders settles the order into it
+    /// rather than the owner's wallet when passed.
+    #[account(
+        seeds = [b"user_balance", market.key().as_ref(), owner.key().as_ref()],
+        bump = user_balance.bump,
+    )]
+    pub user_balance: Option<Account<'info, UserBalance>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(side: Side, price: u64, quantity: u64, client_nonce: u64)]
+pub struct PlaceOrderV2<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+        constraint = !market.has_book_sides() || book_side.is_some() @ MatchingEngineError::BookSideRequired,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Order::LEN,
+        seeds = [b"order", market.key().as_ref(), owner.key().as_ref(), &client_nonce.to_le_bytes()],
+        bump,
+    )]
+    pub order: Account<'info, Order>,
+
+    /// The order's escrow vault — holds a BUY's lamport escrow.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &market.next_order_id.to_le_bytes()],
+        bump,
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// Optional pre-funded balance. Used for BUY escrow when it covers the amount.
+    #[account(
+        mut,
+        seeds = [b"balance", market.key().as_ref(), owner.key().as_ref()],
+        bump = trading_balance.bump,
+    )]
+    pub trading_balance: Option<Account<'info, TradingBalance>>,
+
+    /// Owner's seat — required when the market is permissioned or makers_restricted.
+    #[account(
+        seeds = [b"seat", market.key().as_ref(), owner.key().as_ref()],
+        bump = trader_seat.bump,
+    )]
+    pub trader_seat: Option<Account<'info, TraderSeat>>,
+
+    /// Owner's open-volume stats — required when a maker share cap is set.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), owner.key().as_ref()],
+        bump = user_stats.bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+
+    /// Owner's OpenOrders — lists the new order when passed.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), owner.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// The market's BookSide of the order's side — required once it has book
+    /// sides; indexes a GTC order.
+    #[account(mut)]
+    pub book_side: Option<AccountLoader<'info, BookSide>>,
+
+    /// Protocol config — placement stops while the protocol is paused.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    /// CHECK: Only its key is recorded, as the order's referrer.
+    pub referrer: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(
+    bid_price: u64,
+    bid_quantity: u64,
+    ask_price: u64,
+    ask_quantity: u64,
+    bid_order_id: u64,
+    ask_order_id: u64,
+)]
+pub struct PlaceDualOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+        constraint = !market.has_book_sides() || (bids.is_some() && asks.is_some()) @ MatchingEngineError::BookSideRequired,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Order::LEN,
+        seeds = [b"order", market.key().as_ref(), &bid_order_id.to_le_bytes()],
+        bump,
+    )]
+    pub bid_order: Account<'info, Order>,
+
+    /// The bid's escrow vault — holds its lamport escrow.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &bid_order_id.to_le_bytes()],
+        bump,
+    )]
+    pub bid_escrow: SystemAccount<'info>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Order::LEN,
+        seeds = [b"order", market.key().as_ref(), &ask_order_id.to_le_bytes()],
+        bump,
+    )]
+    pub ask_order: Account<'info, Order>,
+
+    /// The ask's escrow vault, left empty.
+    #[account(
+        seeds = [b"escrow", market.key().as_ref(), &ask_order_id.to_le_bytes()],
+        bump,
+    )]
+    pub ask_escrow: SystemAccount<'info>,
+
+    /// Optional pre-funded balance. Used for the bid's escrow when it covers the amount.
+    #[account(
+        mut,
+        seeds = [b"balance", market.key().as_ref(), owner.key().as_ref()],
+        bump = trading_balance.bump,
+    )]
+    pub trading_balance: Option<Account<'info, TradingBalance>>,
+
+    /// Owner's seat — required when the market is permissioned or makers_restricted.
+    #[account(
+        seeds = [b"seat", market.key().as_ref(), owner.key().as_ref()],
+        bump = trader_seat.bump,
+    )]
+    pub trader_seat: Option<Account<'info, TraderSeat>>,
+
+    /// Owner's open-volume stats — required when a maker share cap is set.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), owner.key().as_ref()],
+        bump = user_stats.bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+
+    /// Owner's OpenOrders — lists both new orders when passed.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), owner.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// The market's bid-side BookSide — required once it has book sides.
+    #[account(mut)]
+    pub bids: Option<AccountLoader<'info, BookSide>>,
+
+    /// The market's ask-side BookSide — required once it has book sides.
+    #[account(mut)]
+    pub asks: Option<AccountLoader<'info, BookSide>>,
+
+    /// Protocol config — placement stops while the protocol is paused.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MatchOrders<'info> {
+    /// Matcher / crank — anyone, or only seat holders on matcher_restricted
+    /// markets. Receives the crank reward.
+    #[account(mut)]
+    pub matcher: Signer<'info>,
+
+    /// The market account — must not be paused.
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    /// Must be a genuine order PDA, and of this market.
+    #[account(
+        mut,
+        constraint = bid_order.is_pda(&bid_order.key()) @ ErrorCode::ConstraintSeeds,
+        constraint = bid_order.market == market.key() @ MatchingEngineError::MarketMismatch,
+    )]
+    pub bid_order: Account<'info, Order>,
+
+    /// Must be a genuine order PDA, and of this market.
+    #[account(
+        mut,
+        constraint = ask_order.is_pda(&ask_order.key()) @ ErrorCode::ConstraintSeeds,
+        constraint = ask_order.market == market.key() @ MatchingEngineError::MarketMismatch,
+    )]
+    pub ask_order: Account<'info, Order>,
+
+    /// The bid's escrow vault — pays the fill.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &bid_order.order_id.to_le_bytes()],
+        bump = bid_order.escrow_bump,
+    )]
+    pub bid_escrow: SystemAccount<'info>,
+
+    /// CHECK: Verified in instruction body against bid_order.owner
+    #[account(mut)]
+    pub bid_owner: UncheckedAccount<'info>,
+
+    /// CHECK: Verified in instruction body against ask_order.owner
+    #[account(mut)]
+    pub ask_owner: UncheckedAccount<'info>,
+
+    /// Optional fee config PDA. If present, fee is deducted.
+    /// Seeds: ["fee_config", market]
+    #[account(
+        mut,
+        seeds = [b"fee_config", market.key().as_ref()],
+        bump = fee_config.bump,
+    )]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+
+    /// CHECK: Must be market.fee_recipient when a fee config is present.
+    /// Verified in instruction body.
+    #[account(mut)]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// Market fee vault — required when the fee recipient can't be paid directly.
+    #[account(
+        mut,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump = fee_vault.bump,
+    )]
+    pub fee_vault: Option<Account<'info, FeeVault>>,
+
+    /// Buyer's trading balance — required when the bid was funded from it.
+    #[account(
+        mut,
+        seeds = [b"balance", market.key().as_ref(), bid_order.owner.as_ref()],
+        bump = bid_trading_balance.bump,
+    )]
+    pub bid_trading_balance: Option<Account<'info, TradingBalance>>,
+
+    /// Buyer's stats — required when the bid is counted in them.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), bid_order.owner.as_ref()],
+        bump = bid_user_stats.bump,
+    )]
+    pub bid_user_stats: Option<Account<'info, UserStats>>,
+
+    /// Seller's stats — required when the ask is counted in them.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), ask_order.owner.as_ref()],
+        bump = ask_user_stats.bump,
+    )]
+    pub ask_user_stats: Option<Account<'info, UserStats>>,
+
+    /// Buyer's OpenOrders — required when the bid is listed in it.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), bid_order.owner.as_ref()],
+        bump = bid_open_orders.bump,
+    )]
+    pub bid_open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// Seller's OpenOrders — required when the ask is listed in it.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), ask_order.owner.as_ref()],
+        bump = ask_open_orders.bump,
+    )]
+    pub ask_open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// Bid-side BookSide — required when that order is indexed.
+    #[account(mut)]
+    pub bids: Option<AccountLoader<'info, BookSide>>,
+
+    /// Ask-side BookSide — required when that order is indexed.
+    #[account(mut)]
+    pub asks: Option<AccountLoader<'info, BookSide>>,
+
+    /// Protocol config — global pause and protocol fee share.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    /// Matcher's activity stats — updated when supplied.
+    #[account(
+        mut,
+        seeds = [b"matcher", market.key().as_ref(), matcher.key().as_ref()],
+        bump = matcher_stats.bump,
+    )]
+    pub matcher_stats: Option<Account<'info, MatcherStats>>,
+
+    /// Matcher's seat — required on matcher_restricted markets.
+    #[account(
+        constraint = matcher_seat.market == market.key() @ MatchingEngineError::MatcherNotAuthorized,
+        constraint = matcher_seat.matcher == matcher.key() @ MatchingEngineError::MatcherNotAuthorized,
+    )]
+    pub matcher_seat: Option<Account<'info, MatcherSeat>>,
+
+    /// Buyer's seat — a fee-exempt seat waives the trade's fee.
+    #[account(
+        seeds = [b"seat", market.key().as_ref(), bid_order.owner.as_ref()],
+        bump = bid_seat.bump,
+    )]
+    pub bid_seat: Option<Account<'info, TraderSeat>>,
+
+    /// Seller's seat — a fee-exempt seat waives the trade's fee.
+    #[account(
+        seeds = [b"seat", market.key().as_ref(), ask_order.owner.as_ref()],
+        bump = ask_seat.bump,
+    )]
+    pub ask_seat: Option<Account<'info, TraderSeat>>,
+
+    /// CHECK: Verified in instruction body against ask_order.beneficiary —
+    /// required when the ask names a beneficiary other than its owner.
+    #[account(mut)]
+    pub ask_beneficiary: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Verified in instruction body against bid_order.funder —
+    /// required when a third party funded the bid.
+    #[account(mut)]
+    pub bid_funder: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Verified in instruction body against ask_order.referrer —
+    /// receives the referral share of the fee when passed.
+    #[account(mut)]
+    pub ask_referrer: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The market's quote vault — required when the bid's escrow is
+    /// in it; verified in the instruction body.
+    #[account(mut)]
+    pub quote_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SPL Token program, verified in the instruction body.
+    pub token_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Quote token account of the ask's proceeds recipient; verified
+    /// in the instruction body.
+    #[account(mut)]
+    pub seller_quote_account: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Quote token account of the bid's owner, for price-improvement
+    /// refunds; verified in the instruction body.
+    #[account(mut)]
+    pub buyer_quote_account: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The market's base vault — required when the ask's base tokens
+    /// are locked in it; verified in the instruction body.
+    #[account(mut)]
+    pub base_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Base token account of the bid's owner, receiving the filled
+    /// quantity; verified in the instruction body.
+    #[account(mut)]
+    pub buyer_base_account: Option<UncheckedAccount<'info>>,
+
+    /// Receipt for this fill, created when passed; the matcher pays its rent.
+    /// Seeds: ["fill", market, trade_seq of this fill]
+    #[account(
+        init,
+        payer = matcher,
+        space = FillReceipt::LEN,
+        seeds = [b"fill", market.key().as_ref(), &(market.trade_seq + 1).to_le_bytes()],
+        bump,
+    )]
+    pub fill_receipt: Option<Account<'info, FillReceipt>>,
+
+    /// The market's TradeHistory — the fill is appended to it when passed.
+    #[account(
+        mut,
+        constraint = trade_history.load()?.market == market.key() @ MatchingEngineError::TradeHistoryMismatch,
+    )]
+    pub trade_history: Option<AccountLoader<'info, TradeHistory>>,
+
+    /// Buyer's UserBalance — required when the bid settles to it.
+    #[account(
+        mut,
+        seeds = [b"user_balance", market.key().as_ref(), bid_order.owner.as_ref()],
+        bump = bid_user_balance.bump,
+    )]
+    pub bid_user_balance: Option<Account<'info, UserBalance>>,
+
+    /// Seller's UserBalance — required when the ask settles to it.
+    #[account(
+        mut,
+        seeds = [b"user_balance", market.key().as_ref(), ask_order.owner.as_ref()],
+        bump = ask_user_balance.bump,
+    )]
+    pub ask_user_balance: Option<Account<'info, UserBalance>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseFillReceipt<'info> {
+    /// The matcher that paid the receipt's rent receives it back.
+    #[account(mut)]
+    pub matcher: Signer<'info>,
+
+    #[account(
+        mut,
+        close = matcher,
+        constraint = fill_receipt.matcher == matcher.key() @ MatchingEngineError::Unauthorized,
+        seeds = [b"fill", fill_receipt.market.as_ref(), &fill_receipt.fill_seq.to_le_bytes()],
+        bump = fill_receipt.bump,
+    )]
+    pub fill_receipt: Account<'info, FillReceipt>,
+}
+
+#[derive(Accounts)]
+pub struct TakeOrder<'info> {
+    /// Pays a buy's fills from its wallet; receives a sell's proceeds.
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    /// Taker's seat — required when the market is permissioned.
+    #[account(
+        seeds = [b"seat", market.key().as_ref(), taker.key().as_ref()],
+        bump = trader_seat.bump,
+    )]
+    pub trader_seat: Option<Account<'info, TraderSeat>>,
+
+    /// Optional fee config PDA. If present, fees are deducted.
+    #[account(
+        mut,
+        seeds = [b"fee_config", market.key().as_ref()],
+        bump = fee_config.bump,
+    )]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+
+    /// CHECK: Must be market.fee_recipient; verified in the instruction body.
+    /// Without it the market's share goes to the fee vault.
+    #[account(mut)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+
+    /// Market fee vault — required when the fee recipient can't be paid directly.
+    #[account(
+        mut,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump = fee_vault.bump,
+    )]
+    pub fee_vault: Option<Account<'info, FeeVault>>,
+
+    /// The makers' BookSide — required when any maker is indexed.
+    #[account(mut)]
+    pub book_side: Option<AccountLoader<'info, BookSide>>,
+
+    /// Protocol config — global pause and protocol fee share.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MatchOrdersMulti<'info> {
+    /// Matcher / crank — anyone, or only seat holders on matcher_restricted
+    /// markets. Receives the matcher fee.
+    #[account(mut)]
+    pub matcher: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    /// Must be a genuine order PDA, and of this market.
+    #[account(
+        mut,
+        constraint = bid_order.is_pda(&bid_order.key()) @ ErrorCode::ConstraintSeeds,
+        constraint = bid_order.market == market.key() @ MatchingEngineError::MarketMismatch,
+    )]
+    pub bid_order: Account<'info, Order>,
+
+    /// The bid's escrow vault — pays the fills.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &bid_order.order_id.to_le_bytes()],
+        bump = bid_order.escrow_bump,
+    )]
+    pub bid_escrow: SystemAccount<'info>,
+
+    /// CHECK: Verified in instruction body against bid_order.owner
+    #[account(mut)]
+    pub bid_owner: UncheckedAccount<'info>,
+
+    /// CHECK: Verified in instruction body against bid_order.funder —
+    /// required when a third party funded the bid.
+    #[account(mut)]
+    pub bid_funder: Option<UncheckedAccount<'info>>,
+
+    /// Buyer's trading balance — required when the bid was funded from it.
+    #[account(
+        mut,
+        seeds = [b"balance", market.key().as_ref(), bid_order.owner.as_ref()],
+        bump = bid_trading_balance.bump,
+    )]
+    pub bid_trading_balance: Option<Account<'info, TradingBalance>>,
+
+    /// Buyer's stats — required when the bid is counted in them.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), bid_order.owner.as_ref()],
+        bump = bid_user_stats.bump,
+    )]
+    pub bid_user_stats: Option<Account<'info, UserStats>>,
+
+    /// Buyer's OpenOrders — required when the bid is listed in it.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), bid_order.owner.as_ref()],
+        bump = bid_open_orders.bump,
+    )]
+    pub bid_open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// Bid-side BookSide — required when the bid is indexed.
+    #[account(mut)]
+    pub bids: Option<AccountLoader<'info, BookSide>>,
+
+    /// Ask-side BookSide — required when any ask is indexed.
+    #[account(mut)]
+    pub asks: Option<AccountLoader<'info, BookSide>>,
+
+    /// Buyer's seat — a fee-exempt seat waives its fills' fees.
+    #[account(
+        seeds = [b"seat", market.key().as_ref(), bid_order.owner.as_ref()],
+        bump = bid_seat.bump,
+    )]
+    pub bid_seat: Option<Account<'info, TraderSeat>>,
+
+    /// Optional fee config PDA. If present, fees are deducted.
+    #[account(
+        mut,
+        seeds = [b"fee_config", market.key().as_ref()],
+        bump = fee_config.bump,
+    )]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+
+    /// CHECK: Must be market.fee_recipient; verified in the instruction body.
+    /// Without it the market's share goes to the fee vault.
+    #[account(mut)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+
+    /// Market fee vault — required when the fee recipient can't be paid directly.
+    #[account(
+        mut,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump = fee_vault.bump,
+    )]
+    pub fee_vault: Option<Account<'info, FeeVault>>,
+
+    /// Protocol config — global pause and protocol fee share.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    /// Matcher's seat — required on matcher_restricted markets.
+    #[account(
+        constraint = matcher_seat.market == market.key() @ MatchingEngineError::MatcherNotAuthorized,
+        constraint = matcher_seat.matcher == matcher.key() @ MatchingEngineError::MatcherNotAuthorized,
+    )]
+    pub matcher_seat: Option<Account<'info, MatcherSeat>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleAuction<'info> {
+    /// Anyone — receives the matcher fee of each fill.
+    #[account(mut)]
+    pub settler: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    /// Optional fee config PDA. If present, fees are deducted.
+    #[account(
+        mut,
+        seeds = [b"fee_config", market.key().as_ref()],
+        bump = fee_config.bump,
+    )]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+
+    /// CHECK: Must be market.fee_recipient; verified in the instruction body.
+    /// Without it the market's share goes to the fee vault.
+    #[account(mut)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+
+    /// Market fee vault — required when the fee recipient can't be paid directly.
+    #[account(
+        mut,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump = fee_vault.bump,
+    )]
+    pub fee_vault: Option<Account<'info, FeeVault>>,
+
+    /// Bid-side BookSide — required when any bid is indexed.
+    #[account(mut)]
+    pub bids: Option<AccountLoader<'info, BookSide>>,
+
+    /// Ask-side BookSide — required when any ask is indexed.
+    #[account(mut)]
+    pub asks: Option<AccountLoader<'info, BookSide>>,
+
+    /// Protocol config — global pause and protocol fee share.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SimulateMatch<'info> {
+    #[account(
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(constraint = bid_order.market == market.key() @ MatchingEngineError::MarketMismatch)]
+    pub bid_order: Account<'info, Order>,
+
+    #[account(constraint = ask_order.market == market.key() @ MatchingEngineError::MarketMismatch)]
+    pub ask_order: Account<'info, Order>,
+
+    #[account(
+        seeds = [b"fee_config", market.key().as_ref()],
+        bump = fee_config.bump,
+    )]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+
+    #[account(
+        seeds = [b"seat", market.key().as_ref(), bid_order.owner.as_ref()],
+        bump = bid_seat.bump,
+    )]
+    pub bid_seat: Option<Account<'info, TraderSeat>>,
+
+    #[account(
+        seeds = [b"seat", market.key().as_ref(), ask_order.owner.as_ref()],
+        bump = ask_seat.bump,
+    )]
+    pub ask_seat: Option<Account<'info, TraderSeat>>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct PreviewCancel<'info> {
+    #[account(
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [b"order", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump = order.bump,
+    )]
+    pub order: Account<'info, Order>,
+
+    /// The order's escrow vault.
+    #[account(
+        seeds = [b"escrow", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump = order.escrow_bump,
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetTwap<'info> {
+    #[account(
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(old_order_id: u64, new_price: u64, new_quantity: u64, new_order_id: u64)]
+pub struct CancelAndReplace<'info> {
+    /// The order owner pays the new order's rent.
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+        constraint = !market.has_book_sides() || book_side.is_some() @ MatchingEngineError::BookSideRequired,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        constraint = old_order.owner == owner.key() @ MatchingEngineError::Unauthorized,
+        seeds = [b"order", market.key().as_ref(), &old_order_id.to_le_bytes()],
+        bump = old_order.bump,
+    )]
+    pub old_order: Account<'info, Order>,
+
+    /// The old order's escrow vault.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &old_order_id.to_le_bytes()],
+        bump = old_order.escrow_bump,
+    )]
+    pub old_escrow: SystemAccount<'info>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Order::LEN,
+        seeds = [b"order", market.key().as_ref(), &new_order_id.to_le_bytes()],
+        bump,
+    )]
+    pub new_order: Account<'info, Order>,
+
+    /// The new order's escrow vault.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &new_order_id.to_le_bytes()],
+        bump,
+    )]
+    pub new_escrow: SystemAccount<'info>,
+
+    /// Owner's trading balance — required when the old order was funded from
+    /// it; otherwise funds the new BUY's escrow when it covers the amount.
+    #[account(
+        mut,
+        seeds = [b"balance", market.key().as_ref(), owner.key().as_ref()],
+        bump = trading_balance.bump,
+    )]
+    pub trading_balance: Option<Account<'info, TradingBalance>>,
+
+    /// Owner's seat — required when the market is permissioned or makers_restricted.
+    #[account(
+        seeds = [b"seat", market.key().as_ref(), owner.key().as_ref()],
+        bump = trader_seat.bump,
+    )]
+    pub trader_seat: Option<Account<'info, TraderSeat>>,
+
+    /// Owner's stats — required when the old order is counted in them or
+    /// the market's limits need them.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), owner.key().as_ref()],
+        bump = user_stats.bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+
+    /// Owner's OpenOrders — required when the old order is listed in it;
+    /// lists the new order when passed.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), owner.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// The market's BookSide of the orders' side — required once it has
+    /// book sides; indexes the new order.
+    #[account(mut)]
+    pub book_side: Option<AccountLoader<'info, BookSide>>,
+
+    /// CHECK: Refund recipient when a third party funded the old order;
+    /// verified against old_order.funder.
+    #[account(mut)]
+    pub funder: Option<UncheckedAccount<'info>>,
+
+    /// Protocol config — placement stops while the protocol is paused.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct CancelOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        constraint = order.owner == owner.key() @ MatchingEngineError::Unauthorized,
+        seeds = [b"order", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump = order.bump,
+    )]
+    pub order: Account<'info, Order>,
+
+    /// The order's escrow vault — holds a BUY's lamport escrow.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump = order.escrow_bump,
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// Owner's trading balance — required when the order was funded from it.
+    #[account(
+        mut,
+        seeds = [b"balance", market.key().as_ref(), owner.key().as_ref()],
+        bump = trading_balance.bump,
+    )]
+    pub trading_balance: Option<Account<'info, TradingBalance>>,
+
+    /// Owner's stats — required when the order is counted in them.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), owner.key().as_ref()],
+        bump = user_stats.bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+
+    /// Owner's OpenOrders — required when the order is listed in it.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), owner.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// BookSide of the order's side — required when the order is indexed.
+    #[account(mut)]
+    pub book_side: Option<AccountLoader<'info, BookSide>>,
+
+    /// CHECK: Refund recipient when a third party funded the order; verified
+    /// against order.funder.
+    #[account(mut)]
+    pub funder: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The market's quote vault — required when the order's escrow
+    /// is in it; verified in the instruction body.
+    #[account(mut)]
+    pub quote_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SPL Token program, verified in the instruction body.
+    pub token_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The owner's quote token account receiving the refund; verified
+    /// in the instruction body.
+    #[account(mut)]
+    pub owner_quote_account: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The market's base vault — required when an ask's base tokens
+    /// are locked in it; verified in the instruction body.
+    #[account(mut)]
+    pub base_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The owner's base token account receiving an ask's locked base
+    /// tokens; verified in the instruction body.
+    #[account(mut)]
+    pub owner_base_account: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelAllOrders<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    /// Owner's trading balance — orders funded from it are skipped without it.
+    #[account(
+        mut,
+        seeds = [b"balance", market.key().as_ref(), owner.key().as_ref()],
+        bump = trading_balance.bump,
+    )]
+    pub trading_balance: Option<Account<'info, TradingBalance>>,
+
+    /// Owner's stats — orders counted in them are skipped without it.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), owner.key().as_ref()],
+        bump = user_stats.bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+
+    /// Owner's OpenOrders — orders listed in it are skipped without it.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), owner.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// Bid-side BookSide — required when any order of that side is indexed.
+    #[account(mut)]
+    pub bids: Option<AccountLoader<'info, BookSide>>,
+
+    /// Ask-side BookSide — required when any order of that side is indexed.
+    #[account(mut)]
+    pub asks: Option<AccountLoader<'info, BookSide>>,
+
+    /// CHECK: The market's quote vault — buys escrowed in it are skipped
+    /// without it; verified when paid from.
+    #[account(mut)]
+    pub quote_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SPL Token program, verified when used.
+    pub token_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The owner's quote token account; verified when paid.
+    #[account(mut)]
+    pub owner_quote_account: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The market's base vault — asks with base tokens locked in it
+    /// are skipped without it; verified when paid from.
+    #[account(mut)]
+    pub base_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The owner's base token account; verified when paid.
+    #[account(mut)]
+    pub owner_base_account: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CancelAllOrders<'info> {
+    /// Whether `order` (at `key`) is one of the owner's active orders on
+    /// this market that these accounts can cancel and refund.
+    fn can_cancel(&self, order: &Order, key: &Pubkey, escrow_vault: &Pubkey) -> bool {
+        let token_accounts = self.token_program.is_some()
+            && match order.side {
+                Side::Buy => self.quote_vault.is_some() && self.owner_quote_account.is_some(),
+                Side::Sell => self.base_vault.is_some() && self.owner_base_account.is_some(),
+            };
+        order.market == self.market.key()
+            && order.owner == self.owner.key()
+            && order.is_pda(key)
+            && order.is_cancellable()
+            && !order.is_locked
+            && order.refund_recipient() == order.owner
+            && (!order.funded_from_balance || self.trading_balance.is_some())
+            && (!order.counted_in_stats || self.user_stats.is_some())
+            && (!order.tracked_in_open_orders || self.open_orders.is_some())
+            && (!(order.escrow_in_vault || order.base_escrow > 0) || token_accounts)
+            && (order.escrow_in_vault || order.escrow_lamports == 0 || order.is_escrow_vault(escrow_vault))
+    }
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(client_nonce: u64)]
+pub struct CancelOrderV2<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"order", market.key().as_ref(), owner.key().as_ref(), &client_nonce.to_le_bytes()],
+        bump = order.bump,
+    )]
+    pub order: Account<'info, Order>,
+
+    /// The order's escrow vault — holds a BUY's lamport escrow.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &order.order_id.to_le_bytes()],
+        bump = order.escrow_bump,
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// Owner's trading balance — required when the order was funded from it.
+    #[account(
+        mut,
+        seeds = [b"balance", market.key().as_ref(), owner.key().as_ref()],
+        bump = trading_balance.bump,
+    )]
+    pub trading_balance: Option<Account<'info, TradingBalance>>,
+
+    /// Owner's stats — required when the order is counted in them.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), owner.key().as_ref()],
+        bump = user_stats.bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+
+    /// Owner's OpenOrders — required when the order is listed in it.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), owner.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// BookSide of the order's side — required when the order is indexed.
+    #[account(mut)]
+    pub book_side: Option<AccountLoader<'info, BookSide>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct EmergencyCancel<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: The order's market — possibly closed or undecodable. Written
+    /// only to chain the event while it's still a live Market.
+    #[account(mut, address = order.market @ MatchingEngineError::MarketMismatch)]
+    pub market: UncheckedAccount<'info>,
+
+    /// Validated from its own fields: the PDA must derive from them.
+    #[account(
+        mut,
+        constraint = order.owner == owner.key() @ MatchingEngineError::Unauthorized,
+        seeds = [b"order", order.market.as_ref(), &order_id.to_le_bytes()],
+        bump = order.bump,
+    )]
+    pub order: Account<'info, Order>,
+
+    /// The order's escrow vault, checked against the order in the
+    /// instruction body.
+    #[account(mut)]
+    pub escrow_vault: SystemAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    /// Protocol admin — approval required while the market is healthy.
+    #[account(constraint = admin.key() == config.admin @ MatchingEngineError::Unauthorized)]
+    pub admin: Option<Signer<'info>>,
+
+    /// CHECK: Refund recipient when a third party funded the order; verified
+    /// against order.funder.
+    #[account(mut)]
+    pub funder: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct CloseOrder<'info> {
+    /// The order owner receives the reclaimed rent.
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        close = owner,
+        constraint = order.owner == owner.key() @ MatchingEngineError::Unauthorized,
+        seeds = [b"order", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump = order.bump,
+    )]
+    pub order: Account<'info, Order>,
+
+    /// The order's escrow vault — closes with it.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump = order.escrow_bump,
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// Market rent sponsor — required when the order's rent was subsidized.
+    #[account(
+        mut,
+        seeds = [b"rent_subsidy", market.key().as_ref()],
+        bump = rent_subsidy_vault.bump,
+    )]
+    pub rent_subsidy_vault: Option<Account<'info, RentSubsidyVault>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(client_nonce: u64)]
+pub struct CloseOrderV2<'info> {
+    /// The order owner receives the reclaimed rent.
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"order", market.key().as_ref(), owner.key().as_ref(), &client_nonce.to_le_bytes()],
+        bump = order.bump,
+    )]
+    pub order: Account<'info, Order>,
+
+    /// The order's escrow vault — closes with it.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &order.order_id.to_le_bytes()],
+        bump = order.escrow_bump,
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct SweepOrder<'info> {
+    /// Anyone; keeps the tip.
+    #[account(mut)]
+    pub sweeper: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    /// Either kind of order PDA (place_order or place_order_v2), validated
+    /// from its own fields.
+    #[account(
+        mut,
+        close = owner,
+        constraint = order.is_pda(&order.key()) @ ErrorCode::ConstraintSeeds,
+        constraint = order.market == market.key() @ MatchingEngineError::MarketMismatch,
+        constraint = order.order_id == order_id @ MatchingEngineError::InvalidOrderId,
+    )]
+    pub order: Account<'info, Order>,
+
+    /// The order's escrow vault — closes with it.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump = order.escrow_bump,
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// CHECK: Rent recipient. Pinned to order.owner.
+    #[account(mut, address = order.owner @ MatchingEngineError::OrderOwnerMismatch)]
+    pub owner: UncheckedAccount<'info>,
+
+    /// Market rent sponsor — required when the order's rent was subsidized.
+    #[account(
+        mut,
+        seeds = [b"rent_subsidy", market.key().as_ref()],
+        bump = rent_subsidy_vault.bump,
+    )]
+    pub rent_subsidy_vault: Option<Account<'info, RentSubsidyVault>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64, new_order_id: u64)]
+pub struct SplitOrder<'info> {
+    /// The order owner pays the new order's rent.
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        constraint = order.owner == owner.key() @ MatchingEngineError::Unauthorized,
+        seeds = [b"order", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump = order.bump,
+    )]
+    pub order: Account<'info, Order>,
+
+    /// The order's escrow vault — holds a BUY's lamport escrow.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump = order.escrow_bump,
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Order::LEN,
+        seeds = [b"order", market.key().as_ref(), &new_order_id.to_le_bytes()],
+        bump,
+    )]
+    pub new_order: Account<'info, Order>,
+
+    /// The slice's escrow vault.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &new_order_id.to_le_bytes()],
+        bump,
+    )]
+    pub new_escrow_vault: SystemAccount<'info>,
+
+    /// Owner's stats — required when the order is counted in them.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), owner.key().as_ref()],
+        bump = user_stats.bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+
+    /// Owner's OpenOrders — required when the order is listed in it.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), owner.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// BookSide of the order's side — indexes the new half too when the order is indexed.
+    #[account(mut)]
+    pub book_side: Option<AccountLoader<'info, BookSide>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(survivor_order_id: u64, absorbed_order_id: u64)]
+pub struct MergeOrders<'info> {
+    /// The order owner receives the absorbed order's rent.
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        constraint = survivor.owner == owner.key() @ MatchingEngineError::Unauthorized,
+        seeds = [b"order", market.key().as_ref(), &survivor_order_id.to_le_bytes()],
+        bump = survivor.bump,
+    )]
+    pub survivor: Account<'info, Order>,
+
+    /// The survivor's escrow vault.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &survivor_order_id.to_le_bytes()],
+        bump = survivor.escrow_bump,
+    )]
+    pub survivor_escrow: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        close = owner,
+        constraint = absorbed.owner == owner.key() @ MatchingEngineError::Unauthorized,
+        seeds = [b"order", market.key().as_ref(), &absorbed_order_id.to_le_bytes()],
+        bump = absorbed.bump,
+    )]
+    pub absorbed: Account<'info, Order>,
+
+    /// The absorbed order's escrow vault — closes to the owner.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &absorbed_order_id.to_le_bytes()],
+        bump = absorbed.escrow_bump,
+    )]
+    pub absorbed_escrow: SystemAccount<'info>,
+
+    /// Owner's stats — required when the orders are counted in them.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), owner.key().as_ref()],
+        bump = user_stats.bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+
+    /// Owner's OpenOrders — required when the orders are listed in it.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), owner.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// BookSide of the order's side — required when the orders are indexed.
+    #[account(mut)]
+    pub book_side: Option<AccountLoader<'info, BookSide>>,
+
+    /// Market rent sponsor — required when the absorbed order's rent was
+    /// subsidized.
+    #[account(
+        mut,
+        seeds = [b"rent_subsidy", market.key().as_ref()],
+        bump = rent_subsidy_vault.bump,
+    )]
+    pub rent_subsidy_vault: Option<Account<'info, RentSubsidyVault>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Owner-signed change to one of their orders (set_beneficiary,
+/// update_order_expiry).
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct OwnerOrderAction<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        constraint = order.owner == owner.key() @ MatchingEngineError::Unauthorized,
+        seeds = [b"order", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump = order.bump,
+    )]
+    pub order: Account<'info, Order>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct ModifyOrder<'info> {
+    /// The order owner pays any escrow increase.
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        constraint = order.owner == owner.key() @ MatchingEngineError::Unauthorized,
+        seeds = [b"order", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump = order.bump,
+    )]
+    pub order: Account<'info, Order>,
+
+    /// The order's escrow vault — holds a BUY's lamport escrow.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump = order.escrow_bump,
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// Owner's trading balance — required when the order was funded from it.
+    #[account(
+        mut,
+        seeds = [b"balance", market.key().as_ref(), owner.key().as_ref()],
+        bump = trading_balance.bump,
+    )]
+    pub trading_balance: Option<Account<'info, TradingBalance>>,
+
+    /// Owner's stats — required when the order is counted in them.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), owner.key().as_ref()],
+        bump = user_stats.bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+
+    /// CHECK: Refund recipient when a third party funded the order; verified
+    /// against order.funder.
+    #[account(mut)]
+    pub funder: Option<UncheckedAccount<'info>>,
+
+    /// Owner's OpenOrders — required when reduce_order cancels a listed order.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), owner.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// BookSide of the order's side — required when the order is indexed.
+    #[account(mut)]
+    pub book_side: Option<AccountLoader<'info, BookSide>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(commitment_id: u64)]
+pub struct CommitOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Commitment::LEN,
+        seeds = [b"commitment", market.key().as_ref(), owner.key().as_ref(), &commitment_id.to_le_bytes()],
+        bump,
+    )]
+    pub commitment: Account<'info, Commitment>,
+
+    /// Protocol config — commitments stop while the protocol is paused.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(commitment_id: u64, side: Side, price: u64, quantity: u64, salt: [u8; 32], order_id: u64)]
+pub struct RevealOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+        constraint = !market.has_book_sides() || book_side.is_some() @ MatchingEngineError::BookSideRequired,
+    )]
+    pub market: Account<'info, Market>,
+
+    /// Closed to the owner, returning unused escrow with the rent.
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"commitment", market.key().as_ref(), owner.key().as_ref(), &commitment_id.to_le_bytes()],
+        bump = commitment.bump,
+    )]
+    pub commitment: Account<'info, Commitment>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Order::LEN,
+        seeds = [b"order", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump,
+    )]
+    pub order: Account<'info, Order>,
+
+    /// The order's escrow vault — holds a BUY's lamport escrow.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump,
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// Owner's seat — required when the market is permissioned or makers_restricted.
+    #[account(
+        seeds = [b"seat", market.key().as_ref(), owner.key().as_ref()],
+        bump = trader_seat.bump,
+    )]
+    pub trader_seat: Option<Account<'info, TraderSeat>>,
+
+    /// Owner's open-volume stats — required when a maker share cap is set.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), owner.key().as_ref()],
+        bump = user_stats.bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+
+    /// Owner's OpenOrders — lists the new order when passed.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), owner.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// The market's BookSide of the order's side — required once it has book
+    /// sides; indexes a GTC order.
+    #[account(mut)]
+    pub book_side: Option<AccountLoader<'info, BookSide>>,
+
+    /// Protocol config — placement stops while the protocol is paused.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(commitment_id: u64)]
+pub struct RefundCommitment<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: A seed of the commitment; the market may since have been
+    /// closed. Written only while it's still a live Market.
+    #[account(mut)]
+    pub market: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"commitment", market.key().as_ref(), owner.key().as_ref(), &commitment_id.to_le_bytes()],
+        bump = commitment.bump,
+    )]
+    pub commitment: Account<'info, Commitment>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeUserStats<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = UserStats::LEN,
+        seeds = [b"user_stats", market.key().as_ref(), owner.key().as_ref()],
+        bump,
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateOpenOrders<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = OpenOrders::LEN,
+        seeds = [b"open_orders", market.key().as_ref(), owner.key().as_ref()],
+        bump,
+    )]
+    pub open_orders: Account<'info, OpenOrders>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateBookSides<'info> {
+    /// Market authority, or the holder of the instruction's role.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+        constraint = !market.has_book_sides() @ MatchingEngineError::BookSidesExist,
+    )]
+    pub market: Account<'info, Market>,
+
+    /// Allocated by the client (BookSide::LEN bytes, owned by this program).
+    #[account(zero)]
+    pub bids: AccountLoader<'info, BookSide>,
+
+    /// Allocated by the client, as `bids`.
+    #[account(zero)]
+    pub asks: AccountLoader<'info, BookSide>,
+
+    /// Optional delegated roles; lets a role holder sign instead of the authority.
+    #[account(
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, MarketRoles>>,
+}
 
 #[derive(Accounts)]
-#[instruction(market_name: String)]
-pub struct InitializeMarket<'info> {
+pub struct CreateTradeHistory<'info> {
+    /// Market authority, or the holder of the instruction's role.
     #[account(mut)]
     pub authority: Signer<'info>,
 
+    #[account(
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
     #[account(
         init,
         payer = authority,
-        space = Market::LEN,
-        seeds = [b"market", authority.key().as_ref(), market_name.as_bytes()],
+        space = TradeHistory::LEN,
+        seeds = [b"trades", market.key().as_ref()],
         bump,
     )]
+    pub trade_history: AccountLoader<'info, TradeHistory>,
+
+    /// Optional delegated roles; lets a role holder sign instead of the authority.
+    #[account(
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, MarketRoles>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateMatcherStats<'info> {
+    #[account(mut)]
+    pub matcher: Signer<'info>,
+
+    #[account(
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
     pub market: Account<'info, Market>,
 
+    #[account(
+        init,
+        payer = matcher,
+        space = MatcherStats::LEN,
+        seeds = [b"matcher", market.key().as_ref(), matcher.key().as_ref()],
+        bump,
+    )]
+    pub matcher_stats: Account<'info, MatcherStats>,
+
     pub system_program: Program<'info, System>,
 }
 
-/// Context for authority-only market state changes (pause / resume).
 #[derive(Accounts)]
-pub struct AuthorityAction<'info> {
+pub struct InitializeTradingBalance<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
     #[account(
-        constraint = authority.key() == market.authority @ MatchingEngineError::Unauthorized
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
     )]
-    pub authority: Signer<'info>,
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = TradingBalance::LEN,
+        seeds = [b"balance", market.key().as_ref(), owner.key().as_ref()],
+        bump,
+    )]
+    pub trading_balance: Account<'info, TradingBalance>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositBalance<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"market", market.authority.as_ref(), market.market_name.as_bytes()],
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
         bump = market.bump,
     )]
     pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"balance", market.key().as_ref(), owner.key().as_ref()],
+        bump = trading_balance.bump,
+    )]
+    pub trading_balance: Account<'info, TradingBalance>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct InitializeFeeConfig<'info> {
+pub struct WithdrawBalance<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
     #[account(
         mut,
-        constraint = authority.key() == market.authority @ MatchingEngineError::Unauthorized
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
     )]
-    pub authority: Signer<'info>,
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"balance", market.key().as_ref(), owner.key().as_ref()],
+        bump = trading_balance.bump,
+    )]
+    pub trading_balance: Account<'info, TradingBalance>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeUserBalance<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
 
     #[account(
-        seeds = [b"market", market.authority.as_ref(), market.market_name.as_bytes()],
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
         bump = market.bump,
     )]
     pub market: Account<'info, Market>,
 
     #[account(
         init,
-        payer = authority,
-        space = FeeConfig::LEN,
-        seeds = [b"fee_config", market.key().as_ref()],
+        payer = owner,
+        space = UserBalance::LEN,
+        seeds = [b"user_balance", market.key().as_ref(), owner.key().as_ref()],
         bump,
     )]
-    pub fee_config: Account<'info, FeeConfig>,
+    pub user_balance: Account<'info, UserBalance>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateFeeConfig<'info> {
-    #[account(
-        constraint = authority.key() == market.authority @ MatchingEngineError::Unauthorized
-    )]
-    pub authority: Signer<'info>,
+pub struct ClaimFunds<'info> {
+    pub owner: Signer<'info>,
 
     #[account(
-        seeds = [b"market", market.authority.as_ref(), market.market_name.as_bytes()],
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
         bump = market.bump,
     )]
     pub market: Account<'info, Market>,
 
     #[account(
         mut,
-        seeds = [b"fee_config", market.key().as_ref()],
-        bump = fee_config.bump,
-        constraint = fee_config.market == market.key() @ MatchingEngineError::TreasuryMismatch,
+        seeds = [b"user_balance", market.key().as_ref(), owner.key().as_ref()],
+        bump = user_balance.bump,
     )]
-    pub fee_config: Account<'info, FeeConfig>,
+    pub user_balance: Account<'info, UserBalance>,
+
+    /// CHECK: Any account the owner chooses; only receives lamports.
+    #[account(
+        mut,
+        constraint = destination.key() != user_balance.key() @ MatchingEngineError::InvalidClaimDestination,
+    )]
+    pub destination: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(side: Side, price: u64, quantity: u64, order_id: u64, expires_at: i64)]
-pub struct PlaceOrder<'info> {
+pub struct InitializeRentSubsidyVault<'info> {
+    /// Market authority, or the holder of the instruction's role.
     #[account(mut)]
-    pub owner: Signer<'info>,
+    pub authority: Signer<'info>,
 
     #[account(
-        mut,
-        seeds = [b"market", market.authority.as_ref(), market.market_name.as_bytes()],
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
         bump = market.bump,
     )]
     pub market: Account<'info, Market>,
 
     #[account(
         init,
-        payer = owner,
-        space = Order::LEN,
-        seeds = [b"order", market.key().as_ref(), &order_id.to_le_bytes()],
+        payer = authority,
+        space = RentSubsidyVault::LEN,
+        seeds = [b"rent_subsidy", market.key().as_ref()],
         bump,
     )]
-    pub order: Account<'info, Order>,
+    pub rent_subsidy_vault: Account<'info, RentSubsidyVault>,
+
+    /// Optional delegated roles; lets a role holder sign instead of the authority.
+    #[account(
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, MarketRoles>>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct MatchOrders<'info> {
-    /// Matcher / crank — can be anyone (no authority restriction)
-    pub matcher: Signer<'info>,
+pub struct FundRentSubsidy<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
 
-    /// The market account — must not be paused.
     #[account(
-        seeds = [b"market", market.authority.as_ref(), market.market_name.as_bytes()],
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
         bump = market.bump,
     )]
     pub market: Account<'info, Market>,
 
-    #[account(mut)]
-    pub bid_order: Account<'info, Order>,
+    #[account(
+        mut,
+        seeds = [b"rent_subsidy", market.key().as_ref()],
+        bump = rent_subsidy_vault.bump,
+    )]
+    pub rent_subsidy_vault: Account<'info, RentSubsidyVault>,
 
-    #[account(mut)]
-    pub ask_order: Account<'info, Order>,
+    pub system_program: Program<'info, System>,
+}
 
-    /// CHECK: Verified in instruction body against bid_order.owner
+#[derive(Accounts)]
+pub struct InitializeOracleFeed<'info> {
     #[account(mut)]
-    pub bid_owner: UncheckedAccount<'info>,
+    pub publisher: Signer<'info>,
 
-    /// CHECK: Verified in instruction body against ask_order.owner
-    #[account(mut)]
-    pub ask_owner: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = publisher,
+        space = OracleFeed::LEN,
+        seeds = [b"oracle_feed", publisher.key().as_ref()],
+        bump,
+    )]
+    pub oracle_feed: Account<'info, OracleFeed>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PublishOraclePrice<'info> {
+    pub publisher: Signer<'info>,
 
-    /// Optional fee config PDA. If present, fee is deducted.
-    /// Seeds: ["fee_config", market]
     #[account(
         mut,
-        seeds = [b"fee_config", market.key().as_ref()],
-        bump = fee_config.bump,
+        seeds = [b"oracle_feed", publisher.key().as_ref()],
+        bump = oracle_feed.bump,
     )]
-    pub fee_config: Option<Account<'info, FeeConfig>>,
+    pub oracle_feed: Account<'info, OracleFeed>,
+}
 
-    /// CHECK: Treasury account from fee_config. Verified in instruction body.
-    #[account(mut)]
-    pub treasury: UncheckedAccount<'info>,
+#[derive(Accounts)]
+pub struct SettleAtExpiry<'info> {
+    /// Market authority, or anyone when settlement is permissionless.
+    pub settler: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(address = market.settlement_oracle @ MatchingEngineError::OracleMismatch)]
+    pub oracle_feed: Account<'info, OracleFeed>,
 }
 
 #[derive(Accounts)]
 #[instruction(order_id: u64)]
-pub struct CancelOrder<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
+pub struct TriggerOrder<'info> {
+    /// Anyone may open a stop whose trigger has been reached.
+    pub caller: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"market", market.authority.as_ref(), market.market_name.as_bytes()],
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
         bump = market.bump,
     )]
     pub market: Account<'info, Market>,
 
     #[account(
         mut,
-        constraint = order.owner == owner.key() @ MatchingEngineError::Unauthorized,
         seeds = [b"order", market.key().as_ref(), &order_id.to_le_bytes()],
         bump = order.bump,
     )]
     pub order: Account<'info, Order>,
 
-    pub system_program: Program<'info, System>,
+    /// BookSide of the order's side — required when the order is indexed.
+    #[account(mut)]
+    pub book_side: Option<AccountLoader<'info, BookSide>>,
 }
 
 #[derive(Accounts)]
 #[instruction(order_id: u64)]
-pub struct CloseOrder<'info> {
-    /// The order owner receives the reclaimed rent.
-    #[account(mut)]
-    pub owner: Signer<'info>,
+pub struct ForceCancelOrder<'info> {
+    /// Anyone may clean up a settled market or an expired order; on a live
+    /// market force_cancel_order needs the authority or RiskManager.
+    pub caller: Signer<'info>,
 
     #[account(
-        seeds = [b"market", market.authority.as_ref(), market.market_name.as_bytes()],
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
         bump = market.bump,
     )]
     pub market: Account<'info, Market>,
 
+    /// Either kind of order PDA (place_order or place_order_v2), validated
+    /// from its own fields.
     #[account(
         mut,
-        close = owner,
-        constraint = order.owner == owner.key() @ MatchingEngineError::Unauthorized,
-        seeds = [b"order", market.key().as_ref(), &order_id.to_le_bytes()],
-        bump = order.bump,
+        constraint = order.is_pda(&order.key()) @ ErrorCode::ConstraintSeeds,
+        constraint = order.market == market.key() @ MatchingEngineError::MarketMismatch,
+        constraint = order.order_id == order_id @ MatchingEngineError::InvalidOrderId,
     )]
     pub order: Account<'info, Order>,
 
+    /// The order's escrow vault — holds a BUY's lamport escrow.
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), &order_id.to_le_bytes()],
+        bump = order.escrow_bump,
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// CHECK: Refund recipient. Pinned to order.owner.
+    #[account(mut, address = order.owner @ MatchingEngineError::OrderOwnerMismatch)]
+    pub owner: UncheckedAccount<'info>,
+
+    /// Owner's trading balance — required when the order was funded from it.
+    #[account(
+        mut,
+        seeds = [b"balance", market.key().as_ref(), order.owner.as_ref()],
+        bump = trading_balance.bump,
+    )]
+    pub trading_balance: Option<Account<'info, TradingBalance>>,
+
+    /// Owner's stats — required when the order is counted in them.
+    #[account(
+        mut,
+        seeds = [b"user_stats", market.key().as_ref(), order.owner.as_ref()],
+        bump = user_stats.bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+
+    /// Owner's OpenOrders — required when the order is listed in it.
+    #[account(
+        mut,
+        seeds = [b"open_orders", market.key().as_ref(), order.owner.as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Account<'info, OpenOrders>>,
+
+    /// BookSide of the order's side — required when the order is indexed.
+    #[account(mut)]
+    pub book_side: Option<AccountLoader<'info, BookSide>>,
+
+    /// CHECK: Refund recipient when a third party funded the order; verified
+    /// against order.funder.
+    #[account(mut)]
+    pub funder: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The market's quote vault — required when the order's escrow
+    /// is in it; verified in the instruction body.
+    #[account(mut)]
+    pub quote_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SPL Token program, verified in the instruction body.
+    pub token_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The owner's quote token account receiving the refund; verified
+    /// in the instruction body.
+    #[account(mut)]
+    pub owner_quote_account: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The market's base vault — required when an ask's base tokens
+    /// are locked in it; verified in the instruction body.
+    #[account(mut)]
+    pub base_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The owner's base token account receiving an ask's locked base
+    /// tokens; verified in the instruction body.
+    #[account(mut)]
+    pub owner_base_account: Option<UncheckedAccount<'info>>,
+
+    /// Optional delegated roles; lets a RiskManager force-cancel.
+    #[account(
+        seeds = [b"roles", market.key().as_ref()],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, MarketRoles>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ArchiveStep<'info> {
+    /// Anyone may drive the wind-down.
+    pub caller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: The market's quote vault — required to refund escrow held in
+    /// it; verified in the instruction body.
+    #[account(mut)]
+    pub quote_vault: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The market's base vault — required to return asks' locked
+    /// base tokens; verified in the instruction body.
+    #[account(mut)]
+    pub base_vault: Option<UncheckedAccount<'info>>,
+
+    /// Bid-side BookSide — required when any order of that side is indexed.
+    #[account(mut)]
+    pub bids: Option<AccountLoader<'info, BookSide>>,
+
+    /// Ask-side BookSide — required when any order of that side is indexed.
+    #[account(mut)]
+    pub asks: Option<AccountLoader<'info, BookSide>>,
+
+    /// CHECK: SPL Token program, verified in the instruction body.
+    pub token_program: Option<UncheckedAccount<'info>>,
+
     pub system_program: Program<'info, System>,
 }
+
+#[derive(Accounts)]
+pub struct CloseMarket<'info> {
+    #[account(
+        mut,
+        constraint = !market.is_renounced() @ MatchingEngineError::AuthorityRenounced,
+        constraint = authority.key() == market.authority @ MatchingEngineError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump = fee_vault.bump,
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+}
+
+#[derive(Accounts)]
+pub struct PokeMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.creator.as_ref(), market.market_name.as_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    /// Optional fee config; its fee_bps goes into the snapshot (0 without it).
+    #[account(
+        seeds = [b"fee_config", market.key().as_ref()],
+        bump = fee_config.bump,
+    )]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+}
@@ -0,0 +1,383 @@
+use anchor_lang::prelude::*;
+use crate::errors::MatchingEngineError;
+use crate::state::{Order, OrderStatus, Side};
+use solamatch_core::{
+    check_cross, check_cross_q64, compute_fill_q64_up_to, compute_fill_up_to, to_q64, within_band,
+    CrossCheck, FillOutcome, OrderTerms, OrderTermsQ64,
+};
+
+// ─── Pure Match Settlement ────────────────────────────────────────────────────
+//
+// Every check and every lamport amount of a bid/ask match is computed here,
+// without touching accounts. match_orders applies the result; simulate_match
+// returns it. Sharing one code path means a preview can never diverge from
+// the real settlement. preview_cancel does the same for cancel_order and
+// close_order.
+//
+// The math itself (crossing, fill size, fee/dust, refunds) lives in
+// solamatch-core, shared with off-chain tooling; see its rounding / dust
+// policy. This module adds the order-state checks around it.
+
+/// The matcher's guards for one match_orders_v2 fill.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct MatchOrdersParams {
+    pub max_slippage_bps: u16,           // 0 = no limit
+    pub min_expected_bid_remaining: u64, // staleness guards (0 = off)
+    pub min_expected_ask_remaining: u64,
+    pub max_fill_quantity: Option<u64>,  // cap on the fill (None = as much as crosses)
+    pub min_fill_quantity: u64,          // floor on the fill (0 = any non-empty fill)
+}
+
+/// Match inputs that don't live on the two orders.
+#[derive(Clone, Debug, Default)]
+pub struct MatchContext {
+    pub is_paused: bool,
+    pub is_expired: bool,
+    pub is_archiving: bool,
+    pub fee_bps: u16,
+    pub matcher_fee_bps: u16, // 0 when no matcher is paid for the fill
+    pub max_slippage_bps: u16,
+    pub now: i64,
+    pub min_bid_remaining: u64, // matcher staleness guards (0 = disabled)
+    pub min_ask_remaining: u64,
+    pub last_trade_price: u64, // price band reference (0 = no trade yet)
+    pub price_band_bps: u64,   // 0 = no band
+    pub min_residual_quantity: u64, // remainders below this close with the fill (0 = off)
+    pub max_fill_quantity: Option<u64>, // matcher's cap on the fill (None = as much as crosses)
+    pub min_fill_quantity: u64, // matcher's floor on the fill (0 = any non-empty fill)
+    pub is_auction: bool,       // market is collecting a call auction
+    pub clearing_price: Option<u64>, // settle_auction's uniform fill price (None = the ask's price)
+}
+
+/// Full settlement breakdown of one bid/ask fill.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct MatchSettlement {
+    pub fill_quantity: u64,
+    pub fill_price: u64,         // maker (ask) price, whole lamports
+    pub fill_price_q64: u128,    // maker (ask) price, exact Q64.64
+    pub gross_seller_payment: u64,
+    pub fee_amount: u64,         // deducted from the seller payment
+    pub dust_amount: u64,        // rounding remainder kept back from the seller
+    pub matcher_fee_amount: u64, // paid to the matcher out of the seller payment
+    pub net_seller_payment: u64,
+    pub buyer_refund: u64,       // price improvement (plus residual_refund) returned to the buyer
+    pub total_debit: u64,        // lamports leaving the bid escrow
+    pub bid_residual: u64,       // bid remainder closed out as dust
+    pub ask_residual: u64,       // ask remainder closed out as dust
+    pub residual_refund: u64,    // bid escrow left behind its closed remainder
+    pub bid_filled_after: u64,
+    pub ask_filled_after: u64,
+    pub bid_status_after: OrderStatus,
+    pub ask_status_after: OrderStatus,
+}
+
+/// Result of `simulate_match`, returned via return data.
+/// `error_code` is the program error the real match would fail with (0 = none).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct SimulatedMatch {
+    pub would_match: bool,
+    pub error_code: u32,
+    pub settlement: MatchSettlement,
+}
+
+fn status_after(complete: bool) -> OrderStatus {
+    if complete {
+        OrderStatus::Filled
+    } else {
+        OrderStatus::PartiallyFilled
+    }
+}
+
+/// Remainder a fill leaves behind `order` when it is dust: unfilled, but
+/// below the market's minimum. 0 otherwise.
+fn dust_residual(order: &Order, filled_after: u64, min_residual: u64) -> u64 {
+    let remaining = order.quantity.saturating_sub(filled_after);
+    if remaining < min_residual {
+        remaining
+    } else {
+        0
+    }
+}
+
+fn terms(order: &Order) -> OrderTerms {
+    OrderTerms {
+        price: order.price,
+        quantity: order.quantity,
+        filled_quantity: order.filled_quantity,
+    }
+}
+
+fn terms_q64(order: &Order) -> OrderTermsQ64 {
+    OrderTermsQ64 {
+        price_q64: if order.is_fixed_point() {
+            order.price_q64
+        } else {
+            to_q64(order.price)
+        },
+        quantity: order.quantity,
+        filled_quantity: order.filled_quantity,
+    }
+}
+
+/// Validate a bid/ask pair and compute its settlement.
+pub fn compute_settlement(
+    bid: &Order,
+    ask: &Order,
+    ctx: &MatchContext,
+) -> std::result::Result<MatchSettlement, MatchingEngineError> {
+    use MatchingEngineError::*;
+
+    // ── Pause guard ─────────────────────────────────────────────────────
+    if ctx.is_paused {
+        return Err(MarketPaused);
+    }
+
+    // ── Expiry guard ─────────────────────────────────────────────────────
+    if ctx.is_expired {
+        return Err(MarketExpired);
+    }
+
+    // ── Archival guard ───────────────────────────────────────────────────
+    if ctx.is_archiving {
+        return Err(MarketArchiving);
+    }
+
+    // ── Call auction ─────────────────────────────────────────────────────
+    // While orders collect, only settle_auction fills, at its clearing price.
+    if ctx.is_auction && ctx.clearing_price.is_none() {
+        return Err(AuctionInProgress);
+    }
+
+    // ── Validate sides ───────────────────────────────────────────────────
+    if bid.side != Side::Buy || ask.side != Side::Sell {
+        return Err(InvalidOrderSide);
+    }
+
+    // ── Validate both orders are active ──────────────────────────────────
+    if bid.status == OrderStatus::PendingTrigger || ask.status == OrderStatus::PendingTrigger {
+        return Err(StopNotTriggered);
+    }
+    if !bid.is_active() || !ask.is_active() {
+        return Err(OrderNotActive);
+    }
+
+    // ── Matcher staleness guards ─────────────────────────────────────────
+    if bid.visible_quantity() < ctx.min_bid_remaining
+        || ask.visible_quantity() < ctx.min_ask_remaining
+    {
+        return Err(StaleMakerState);
+    }
+
+    // ── Re-entrancy locks ────────────────────────────────────────────────
+    if bid.is_locked || ask.is_locked {
+        return Err(OrderLocked);
+    }
+
+    // ── TTL / Expiry check ────────────────────────────────────────────────
+    if bid.is_expired(ctx.now) || ask.is_expired(ctx.now) {
+        return Err(OrderExpired);
+    }
+
+    // ── Same market ───────────────────────────────────────────────────────
+    if bid.market != ask.market {
+        return Err(MarketMismatch);
+    }
+
+    // Fixed-point markets price in Q64.64 and draw fills from the bid's
+    // tracked escrow; integer markets keep the plain integer math.
+    let fixed_point = bid.is_fixed_point() || ask.is_fixed_point();
+
+    // ── Price crossing + optional slippage guard ──────────────────────────
+    // Slippage = (bid_price - ask_price) / bid_price
+    let cross = if fixed_point {
+        check_cross_q64(
+            terms_q64(bid).price_q64,
+            terms_q64(ask).price_q64,
+            ctx.max_slippage_bps,
+        )
+    } else {
+        check_cross(bid.price, ask.price, ctx.max_slippage_bps)
+    };
+    match cross {
+        CrossCheck::Crossed { .. } => {}
+        CrossCheck::NotCrossed => return Err(PriceMismatch),
+        CrossCheck::SlippageExceeded { .. } => return Err(SlippageExceeded),
+    }
+    if let Some(clearing_price) = ctx.clearing_price {
+        if fixed_point || clearing_price > bid.price || clearing_price < ask.price {
+            return Err(InvalidClearingPrice);
+        }
+    }
+
+    // ── Fill amounts, fee and refund ──────────────────────────────────────
+    // The matcher may cap the fill; the orders' remainders always do, and
+    // an iceberg's current tranche stands in for its remainder.
+    if ctx.max_fill_quantity == Some(0) {
+        return Err(InvalidQuantity);
+    }
+    let visible = bid.visible_quantity().min(ask.visible_quantity());
+    let max_fill = ctx.max_fill_quantity.unwrap_or(u64::MAX).min(visible);
+    let fill = if fixed_point {
+        compute_fill_q64_up_to(
+            &terms_q64(bid),
+            bid.escrow_lamports,
+            &terms_q64(ask),
+            ctx.fee_bps,
+            max_fill,
+        )?
+    } else {
+        // An auction fill pays the clearing price; the bid's improvement
+        // over it is refunded as usual.
+        let ask_terms = OrderTerms {
+            price: ctx.clearing_price.unwrap_or(ask.price),
+            ..terms(ask)
+        };
+        compute_fill_up_to(&terms(bid), &ask_terms, ctx.fee_bps, max_fill)?
+    };
+    let capped = fill.fill_quantity < visible;
+    // A racing crank that finds the pair (nearly) used up fails here rather
+    // than settling a token fill — and nobody settles an empty one.
+    if fill.fill_quantity == 0 || fill.fill_quantity < ctx.min_fill_quantity {
+        return Err(FillBelowMinimum);
+    }
+
+    // ── Price band ───────────────────────────────────────────────────────
+    if !within_band(fill.fill_price, ctx.last_trade_price, ctx.price_band_bps) {
+        return Err(PriceOutOfBand);
+    }
+    let fee = fill.fee.pay_matcher(ctx.matcher_fee_bps)?;
+    FillOutcome { fee, ..fill }.check(bid.quantity, ask.quantity)?;
+
+    // ── Dust remainders ──────────────────────────────────────────────────
+    // A remainder too small to ever rest as an order closes with the fill.
+    // The bid's leftover escrow joins the buyer refund, so the debit is
+    // never more than the bid escrowed. An ask with base tokens locked
+    // keeps its remainder: returning them needs the seller's token account.
+    // Nor does a capped fill close anything — the matcher chose to stop
+    // short, and must not be able to cut an order down that way.
+    let min_residual = if capped { 0 } else { ctx.min_residual_quantity };
+    let bid_residual = dust_residual(bid, fill.bid_filled_after, min_residual);
+    let ask_residual = if ask.base_escrow == 0 {
+        dust_residual(ask, fill.ask_filled_after, min_residual)
+    } else {
+        0
+    };
+    let residual_refund = if bid_residual > 0 {
+        bid.escrow_lamports
+            .checked_sub(fill.total_debit)
+            .ok_or(MathOverflow)?
+    } else {
+        0
+    };
+
+    Ok(MatchSettlement {
+        fill_quantity: fill.fill_quantity,
+        fill_price: fill.fill_price,
+        fill_price_q64: fill.fill_price_q64,
+        gross_seller_payment: fee.gross,
+        fee_amount: fee.fee,
+        dust_amount: fee.dust,
+        matcher_fee_amount: fee.matcher_fee,
+        net_seller_payment: fee.net,
+        buyer_refund: fill
+            .buyer_refund
+            .checked_add(residual_refund)
+            .ok_or(MathOverflow)?,
+        total_debit: fill
+            .total_debit
+            .checked_add(residual_refund)
+            .ok_or(MathOverflow)?,
+        bid_residual,
+        ask_residual,
+        residual_refund,
+        bid_filled_after: fill.bid_filled_after,
+        ask_filled_after: fill.ask_filled_after,
+        bid_status_after: status_after(fill.bid_complete || bid_residual > 0),
+        ask_status_after: status_after(fill.ask_complete || ask_residual > 0),
+    })
+}
+
+/// Wrap `compute_settlement` for `simulate_match`, turning a rejection into
+/// its program error code instead of failing the transaction.
+pub fn simulate(bid: &Order, ask: &Order, ctx: &MatchContext) -> SimulatedMatch {
+    match compute_settlement(bid, ask, ctx) {
+        Ok(settlement) => SimulatedMatch {
+            would_match: true,
+            error_code: 0,
+            settlement,
+        },
+        Err(err) => SimulatedMatch {
+            would_match: false,
+            error_code: err.into(),
+            settlement: MatchSettlement::default(),
+        },
+    }
+}
+
+/// One maker of a match_orders_multi sweep, returned via return data in
+/// the order passed. `skip_code` is the program error a lenient sweep
+/// skipped the maker for (0 = filled; u32::MAX for a runtime error).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct MakerOutcome {
+    pub maker: Pubkey,
+    pub filled_quantity: u64,
+    pub skip_code: u32,
+}
+
+// ─── Cancel Preview ───────────────────────────────────────────────────────────
+
+/// Result of `preview_cancel`, returned via return data.
+/// `error_code` is the program error cancel_order would fail with (0 = none).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct CancelPreview {
+    pub can_cancel: bool,
+    pub error_code: u32,
+    pub remaining_quantity: u64,
+    pub escrow_refund: u64,        // released by cancel_order (BUY escrow still held)
+    pub refund_to_balance: bool,   // into the owner's TradingBalance, not a wallet
+    pub refund_wallet: Pubkey,     // funder or owner, when not to a balance
+    pub rent_refund: u64,          // the order's rent, reclaimed by close_order afterwards
+    pub rent_to_subsidy_vault: bool, // subsidized rent returns to the market's vault
+    pub refund_in_quote_tokens: bool, // escrow_refund is quote tokens from the quote vault
+    pub base_refund: u64,          // an ask's locked base tokens returned from the base vault
+    pub vault_rent_refund: u64,    // the escrow vault's rent, reclaimed to the owner alongside
+}
+
+/// What cancel_order would refund now and close_order after it, for an
+/// order whose account holds `account_lamports` and whose escrow vault
+/// holds `vault_lamports`. Cancels never round: the refund is exactly the
+/// escrow still tracked on the order, which already reflects fills and
+/// price-improvement refunds.
+pub fn preview_cancel(order: &Order, account_lamports: u64, vault_lamports: u64) -> CancelPreview {
+    let error = if !order.is_cancellable() {
+        Some(MatchingEngineError::OrderNotActive)
+    } else if order.is_locked {
+        Some(MatchingEngineError::OrderLocked)
+    } else {
+        None
+    };
+    // Only BUY orders hold escrow, in the escrow vault (or the quote vault);
+    // the order account and what's left in the escrow vault are rent.
+    let escrow = if order.side == Side::Buy {
+        order.escrow_lamports
+    } else {
+        0
+    };
+    CancelPreview {
+        can_cancel: error.is_none(),
+        error_code: error.map_or(0, Into::into),
+        remaining_quantity: order.remaining_quantity(),
+        escrow_refund: if error.is_none() { escrow } else { 0 },
+        refund_to_balance: order.funded_from_balance,
+        refund_wallet: order.refund_recipient(),
+        rent_refund: account_lamports,
+        rent_to_subsidy_vault: order.subsidized,
+        refund_in_quote_tokens: order.escrow_in_vault,
+        base_refund: if error.is_none() { order.base_escrow } else { 0 },
+        vault_rent_refund: if order.escrow_in_vault {
+            vault_lamports
+        } else {
+            vault_lamports.saturating_sub(escrow)
+        },
+    }
+}
@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use crate::errors::MatchingEngineError;
 
 // ─── Account Structs ──────────────────────────────────────────────────────────
 
@@ -11,15 +12,443 @@ pub struct Market {
     pub total_ask_volume: u64,  // 8
     pub bump: u8,               // 1
     pub is_paused: bool,        // 1  ← Emergency Pause kill switch
+    pub params_timelock_secs: i64, // 8  ← Min delay for staged param changes (0 = immediate)
+    pub event_seq: u64,         // 8  ← Number of state changes recorded in the hash chain
+    pub state_hash: [u8; 32],   // 32 ← Head of the state hash chain (see events.rs)
+    pub makers_restricted: bool, // 1 ← Resting orders require a TraderSeat
+    pub taker_only_window_secs: i64, // 8 ← Taker-only window length after open / resume (0 = none)
+    pub taker_only_until_ts: i64, // 8 ← No new resting orders before this time
+    pub dust_lamports: u64,     // 8  ← Lifetime settlement rounding dust sent to the treasury
+    pub creator: Pubkey,        // 32 ← PDA seed; fixed at creation, survives renounce_authority
+    pub expiry_ts: i64,         // 8  ← Dated markets stop trading here (0 = perpetual)
+    pub settlement_oracle: Pubkey, // 32 ← OracleFeed read by settle_at_expiry
+    pub oracle_max_staleness_secs: i64, // 8 ← Oldest acceptable oracle publish age
+    pub permissionless_settlement: bool, // 1 ← Anyone may call settle_at_expiry
+    pub settlement_price: u64,  // 8  ← Recorded once at expiry, then immutable (0 = unsettled)
+    pub settled_at: i64,        // 8
+    pub fee_recipient: Pubkey,  // 32 ← Fee destination; defaults to the market's FeeVault
+    pub max_maker_share_bps: u16, // 2 ← Cap on one owner's share of a side's open volume (0 = off)
+    pub maker_share_min_side_volume: u64, // 8 ← Cap is skipped while the side is below this
+    pub open_order_count: u64,  // 8  ← Orders still Open / PartiallyFilled
+    pub is_archiving: bool,     // 1  ← Wind-down: no placement or matching, archive_step drains
+    pub probation_fills: u64,   // 8  ← New users graduate after this many fills (0 = no fill bar)
+    pub probation_volume: u64,  // 8  ← ...or this much filled notional, lamports (0 = no volume bar)
+    pub probation_max_order_notional: u64, // 8 ← Per-order notional cap on probation (0 = none)
+    pub probation_max_open_orders: u64,    // 8 ← Open-order cap on probation (0 = none)
+    pub last_trade_price: u64,  // 8  ← Fill price of the most recent match (0 = none yet)
+    pub last_trade_ts: i64,     // 8
+    pub last_poke_slot: u64,    // 8  ← Slot of the last poke_market snapshot (0 = never)
+    pub trade_seq: u64,         // 8  ← Number of fills executed; numbers each trade
+    pub batch_trade_events: bool, // 1 ← Multi-maker matches emit only a TradeBatchEvent
+    pub commit_reveal: bool,    // 1  ← commit_order / reveal_order accepted (place_order still works)
+    pub reveal_window_secs: i64, // 8 ← How long a commitment stays revealable
+    pub fee_bps: u16,           // 2  ← Mirror of FeeConfig.fee_bps (maker rate); snapshotted onto new orders
+    pub buys_paused: bool,      // 1  ← No new BUY orders; resting ones still match and cancel
+    pub sells_paused: bool,     // 1  ← No new SELL orders; resting ones still match and cancel
+    pub crank_reward_base: u64, // 8  ← Matcher reward per match, paid from the fee vault (0 = none)
+    pub crank_reward_per_slot: u64, // 8 ← Added per slot the taker order has rested (0 = flat)
+    pub crank_reward_max: u64,  // 8  ← Cap on the aged reward (0 = uncapped)
+    pub fixed_point_prices: bool, // 1 ← Orders are priced in Q64.64 (place_order_q64); set before the first order
+    pub quote_mint: Pubkey,     // 32 ← SPL token prices are quoted in (default = lamports)
+    pub quote_vault: Pubkey,    // 32 ← Token account holding BUY escrow and fees on token-quoted markets
+    pub quote_vault_bump: u8,   // 1
+    pub quote_fees: u64,        // 8  ← Market fees (and dust) held in the quote vault, in quote tokens
+    pub quote_protocol_fees: u64, // 8 ← The protocol's share held in the quote vault
+    pub base_mint: Pubkey,      // 32 ← SPL token asks escrow in the base vault (default = none)
+    pub base_vault: Pubkey,     // 32 ← Token account holding ASK escrow on base-escrowed markets
+    pub base_vault_bump: u8,    // 1
+    pub taker_fee_bps: u16,     // 2  ← Mirror of FeeConfig.taker_fee_bps; snapshotted onto new orders
+    pub best_bid_q64: u128,     // 16 ← Highest resting bid, Q64.64 (0 = none known)
+    pub best_bid_quantity: u64, // 8  ← Open quantity resting at best_bid_q64
+    pub best_ask_q64: u128,     // 16 ← Lowest resting ask, Q64.64 (0 = none known)
+    pub best_ask_quantity: u64, // 8  ← Open quantity resting at best_ask_q64
+    pub tick_size: u64,         // 8  ← Integer prices are multiples of this (1 = any)
+    pub lot_size: u64,          // 8  ← Quantities are multiples of this (1 = any)
+    pub min_order_quantity: u64, // 8 ← Smallest order quantity (0 / 1 = any)
+    pub matcher_fee_bps: u16,   // 2  ← Matcher's cut of each fill's seller payment; fixed at initialization
+    pub cumulative_base_volume: u64,   // 8  ← Lifetime units filled
+    pub cumulative_quote_volume: u128, // 16 ← Lifetime gross notional filled, quote units
+    pub session_high: u64,      // 8  ← Highest fill price this session (0 = no fill yet)
+    pub session_low: u64,       // 8  ← Lowest fill price this session (0 = no fill yet)
+    pub session_start_ts: i64,  // 8  ← When the current session began
+    pub permissioned: bool,     // 1  ← Every placement requires a TraderSeat; fixed at initialization
+    pub price_band_bps: u64,    // 8  ← Max deviation of order and fill prices from last_trade_price (0 = off)
+    pub auction_mode: bool,     // 1  ← Call auction: orders collect unmatched until settle_auction
+    pub auction_end_ts: i64,    // 8  ← settle_auction may run from this time (auction mode only)
+    pub version: u8,            // 1  ← Layout version (Market::VERSION); 0 = created before versioning
+    pub matcher_restricted: bool, // 1 ← match_orders requires the signing matcher's MatcherSeat (version 2)
+    pub max_orders_per_user: u16, // 2 ← Cap on one owner's counted open orders (0 = unlimited; version 3)
+    pub referral_share_bps: u16, // 2 ← Share of the market's fee paid to an ask's referrer (0 = none; version 4)
+    pub sweep_delay_secs: i64,  // 8  ← How long a closed order stays before anyone may sweep_order it (0 = never; version 5)
+    pub price_cumulative: u128, // 16 ← Σ last_trade_price × seconds it stood, advanced on every fill (version 6)
+    pub last_twap_update_ts: i64, // 8 ← When price_cumulative was last advanced (0 = no fill yet; version 6)
+    pub bid_book: Pubkey,       // 32 ← Bid-side BookSide from create_book_sides (default = none; version 7)
+    pub ask_book: Pubkey,       // 32 ← Ask-side BookSide (default = none; version 7)
 }
 
 impl Market {
     // 8 discriminator + fields
-    pub const LEN: usize = 8 + 32 + (4 + 32) + 8 + 8 + 8 + 1 + 1;
+    pub const LEN: usize = 8 + 32 + (4 + 32) + 8 + 8 + 8 + 1 + 1 + 8 + 8 + 32 + 1 + 8 + 8 + 8 + 32 + 8 + 32 + 8 + 1 + 8 + 8 + 32 + 2 + 8 + 8 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 8 + 2 + 1 + 1 + 8 + 8 + 8 + 1 + 32 + 32 + 1 + 8 + 8 + 32 + 32 + 1 + 2 + 16 + 8 + 16 + 8 + 8 + 8 + 8 + 2 + 8 + 16 + 8 + 8 + 8 + 1 + 8 + 1 + 8 + 1 + 1 + 2 + 2 + 8 + 16 + 8 + 32 + 32;
     pub const MAX_NAME_LEN: usize = 32;
+    /// Layout version initialize_market writes and upgrade_market brings
+    /// older markets up to. Bump it, with a step in `upgrade`, when a new
+    /// field's zero value would mean something other than its default.
+    pub const VERSION: u8 = 7;
+    /// sweep_delay_secs of new and upgraded markets: a week for owners to
+    /// close their own orders before a sweeper takes its tip.
+    pub const DEFAULT_SWEEP_DELAY_SECS: i64 = 7 * 24 * 60 * 60;
+    /// Share of a swept order's rent paid to whoever swept it.
+    pub const SWEEP_TIP_BPS: u64 = 500;
+    /// remaining_accounts per order in archive_step:
+    /// [order, owner, trading_balance, user_stats, open_orders, escrow vault]
+    pub const ARCHIVE_ACCOUNTS_PER_ORDER: usize = 6;
+    /// remaining_accounts per order in cancel_all_orders: [order, escrow vault]
+    pub const CANCEL_ACCOUNTS_PER_ORDER: usize = 2;
+    /// Seed of the quote vault PDA: ["quote_vault", market].
+    pub const QUOTE_VAULT_SEED: &'static [u8] = b"quote_vault";
+    /// Seed of the base vault PDA: ["base_vault", market].
+    pub const BASE_VAULT_SEED: &'static [u8] = b"base_vault";
+    /// Session high/low roll over on the first fill after this long.
+    pub const SESSION_SECS: i64 = 24 * 60 * 60;
+    /// Minimum slots between two poke_market snapshots (~10s).
+    pub const POKE_INTERVAL_SLOTS: u64 = 25;
+
+    /// remaining_accounts per pair in settle_auction:
+    /// [bid order, bid refund wallet, bid seat,
+    ///  ask order, ask proceeds wallet, ask seat, bid escrow vault]
+    pub const AUCTION_ACCOUNTS_PER_PAIR: usize = 7;
+    /// remaining_accounts per maker in place_order_tif, take_order and
+    /// match_orders_multi: [maker order, maker wallet, maker seat, maker
+    /// escrow vault] — the vault is only read for a bid
+    pub const MAKER_ACCOUNTS: usize = 4;
+
+    /// Authority after renounce_authority — nobody can sign for it.
+    pub const RENOUNCED_AUTHORITY: Pubkey = Pubkey::new_from_array([0; 32]);
+
+    /// True once create_book_sides has run: every placement must then pass
+    /// its side's book.
+    pub fn has_book_sides(&self) -> bool {
+        self.bid_book != Pubkey::default()
+    }
+
+    /// The BookSide account of `side` (default = none).
+    pub fn book_side(&self, side: &Side) -> Pubkey {
+        match side {
+            Side::Buy => self.bid_book,
+            Side::Sell => self.ask_book,
+        }
+    }
+
+    /// True once renounce_authority has made the market immutable.
+    pub fn is_renounced(&self) -> bool {
+        self.authority == Self::RENOUNCED_AUTHORITY
+    }
+
+    /// True if an owner holding `owner_after` of a side totalling `side_after`
+    /// (both after the placement) would breach the maker concentration cap.
+    pub fn maker_share_exceeded(&self, owner_after: u64, side_after: u64) -> bool {
+        if self.max_maker_share_bps == 0 || side_after < self.maker_share_min_side_volume {
+            return false;
+        }
+        (owner_after as u128) * 10_000 > (self.max_maker_share_bps as u128) * (side_after as u128)
+    }
+
+    /// Probation applies while either graduation bar is set.
+    pub fn probation_enabled(&self) -> bool {
+        self.probation_fills > 0 || self.probation_volume > 0
+    }
+
+    /// True until the owner meets any configured graduation bar.
+    pub fn on_probation(&self, stats: &UserStats) -> bool {
+        self.probation_enabled()
+            && !(self.probation_fills > 0 && stats.fill_count >= self.probation_fills)
+            && !(self.probation_volume > 0 && stats.filled_notional >= self.probation_volume)
+    }
+
+    /// Orders must be placed with the owner's UserStats.
+    pub fn requires_user_stats(&self) -> bool {
+        self.max_maker_share_bps > 0 || self.probation_enabled() || self.max_orders_per_user > 0
+    }
+
+    /// The referrer's cut of `market_fee` (the fee left after the protocol's
+    /// share), rounded down — never more than the fee itself.
+    pub fn referral_share(&self, market_fee: u64) -> u64 {
+        (market_fee as u128 * self.referral_share_bps as u128 / 10_000) as u64
+    }
+
+    /// Check that sweep_order may close an order that reached its terminal
+    /// status at `closed_at`, as of `now`.
+    pub fn check_sweepable(&self, closed_at: i64, now: i64) -> Result<()> {
+        require!(self.sweep_delay_secs > 0, MatchingEngineError::SweepDisabled);
+        require!(
+            now >= closed_at.saturating_add(self.sweep_delay_secs),
+            MatchingEngineError::SweepTooEarly
+        );
+        Ok(())
+    }
+
+    /// Check that an owner with these stats may open one more order.
+    pub fn check_open_order_cap(&self, stats: &UserStats) -> Result<()> {
+        require!(
+            self.max_orders_per_user == 0 || stats.open_orders < self.max_orders_per_user as u64,
+            MatchingEngineError::TooManyOpenOrders
+        );
+        Ok(())
+    }
+
+    /// True once a dated market has reached its expiry.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expiry_ts > 0 && now >= self.expiry_ts
+    }
+
+    /// True when `price` is within the price band around the last trade.
+    /// Always true without a band or before the first trade.
+    pub fn price_in_band(&self, price: u64) -> bool {
+        solamatch_core::within_band(price, self.last_trade_price, self.price_band_bps)
+    }
+
+    /// True while pause_side blocks new orders on `side`.
+    pub fn side_paused(&self, side: &Side) -> bool {
+        match side {
+            Side::Buy => self.buys_paused,
+            Side::Sell => self.sells_paused,
+        }
+    }
+
+    /// Check an integer price against the tick size. Fixed-point prices
+    /// sit below a lamport, so ticks don't apply to them.
+    pub fn check_tick(&self, price: u64) -> Result<()> {
+        require!(
+            self.fixed_point_prices || price.is_multiple_of(self.tick_size()),
+            MatchingEngineError::InvalidTickSize
+        );
+        Ok(())
+    }
+
+    /// `tick_size`, read as 1 on a market from before versioning that has
+    /// none set.
+    pub fn tick_size(&self) -> u64 {
+        if self.version == 0 {
+            self.tick_size.max(1)
+        } else {
+            self.tick_size
+        }
+    }
+
+    /// `lot_size`, read as 1 on a market from before versioning that has
+    /// none set.
+    pub fn lot_size(&self) -> u64 {
+        if self.version == 0 {
+            self.lot_size.max(1)
+        } else {
+            self.lot_size
+        }
+    }
+
+    /// Bring a market written at an older `version` up to VERSION, giving
+    /// the fields it predates their defaults where zero would misread.
+    pub fn upgrade(&mut self, key: &Pubkey) {
+        if self.version < 1 {
+            self.tick_size = self.tick_size();
+            self.lot_size = self.lot_size();
+            // The first markets predate `creator` and `fee_recipient`: their
+            // authority seeded the PDA and fees went to the FeeVault
+            if self.creator == Pubkey::default() {
+                self.creator = self.authority;
+            }
+            if self.fee_recipient == Pubkey::default() {
+                self.fee_recipient =
+                    Pubkey::find_program_address(&[b"fee_vault", key.as_ref()], &crate::ID).0;
+            }
+        }
+        // Versions 2-4 appended matcher_restricted, max_orders_per_user and
+        // referral_share_bps; zero (off / unlimited / none) is the default
+        // of each
+        if self.version < 5 {
+            self.sweep_delay_secs = Self::DEFAULT_SWEEP_DELAY_SECS;
+        }
+        // The last fill's price has stood since its trade; the TWAP
+        // accumulator starts counting it from there
+        if self.version < 6 {
+            self.last_twap_update_ts = self.last_trade_ts;
+        }
+        // Version 7 appended bid_book / ask_book; the default key is "no
+        // book sides"
+        self.version = Self::VERSION;
+    }
+
+    /// Check an order quantity against the lot size and the minimum.
+    pub fn check_quantity(&self, quantity: u64) -> Result<()> {
+        require!(
+            quantity.is_multiple_of(self.lot_size()),
+            MatchingEngineError::InvalidLotSize
+        );
+        require!(
+            quantity >= self.min_order_quantity,
+            MatchingEngineError::OrderTooSmall
+        );
+        Ok(())
+    }
+
+    /// True if an order on `side` at `price_q64` would take liquidity: a buy
+    /// at or above the best ask, a sell at or below the best bid.
+    pub fn crosses_book(&self, side: &Side, price_q64: u128) -> bool {
+        match side {
+            Side::Buy => self.best_ask_q64 > 0 && price_q64 >= self.best_ask_q64,
+            Side::Sell => self.best_bid_q64 > 0 && price_q64 <= self.best_bid_q64,
+        }
+    }
+
+    /// Count `quantity` of a resting order into the top of book. IOC / FOK
+    /// orders never rest and aren't tracked.
+    pub fn add_to_top_of_book(&mut self, order: &Order, quantity: u64) {
+        if order.time_in_force != TimeInForce::Gtc {
+            return;
+        }
+        let price = order.price_key();
+        let (best, best_quantity) = self.top_of_book_mut(&order.side);
+        let improves = match order.side {
+            Side::Buy => price > *best,
+            Side::Sell => price < *best,
+        };
+        if *best == 0 || improves {
+            *best = price;
+            *best_quantity = quantity;
+        } else if price == *best {
+            *best_quantity = best_quantity.saturating_add(quantity);
+        }
+    }
+
+    /// Take `quantity` of a resting order off the top of book. The book's
+    /// deeper levels aren't indexed on chain, so a best level that empties
+    /// is cleared until the next placement on that side sets it again.
+    pub fn remove_from_top_of_book(&mut self, order: &Order, quantity: u64) {
+        if order.time_in_force != TimeInForce::Gtc {
+            return;
+        }
+        let price = order.price_key();
+        let (best, best_quantity) = self.top_of_book_mut(&order.side);
+        if price == *best {
+            *best_quantity = best_quantity.saturating_sub(quantity);
+            if *best_quantity == 0 {
+                *best = 0;
+            }
+        }
+    }
+
+    /// Refill a best level that has emptied (read as "none known") from
+    /// the side's BookSide. Orders placed without the book aren't in it, so
+    /// its best may trail the true best but never beats it: a post-only
+    /// order rejected against it would have crossed.
+    pub fn refill_top_of_book(&mut self, side: &Side, book: &BookSide) {
+        let (best, best_quantity) = self.top_of_book_mut(side);
+        if *best == 0 {
+            if let Some((price, quantity)) = book.best_level() {
+                *best = price;
+                *best_quantity = quantity;
+            }
+        }
+    }
+
+    fn top_of_book_mut(&mut self, side: &Side) -> (&mut u128, &mut u64) {
+        match side {
+            Side::Buy => (&mut self.best_bid_q64, &mut self.best_bid_quantity),
+            Side::Sell => (&mut self.best_ask_q64, &mut self.best_ask_quantity),
+        }
+    }
+
+    /// Start a new session: high/low clear until the next fill.
+    pub fn reset_session(&mut self, now: i64) {
+        self.session_high = 0;
+        self.session_low = 0;
+        self.session_start_ts = now;
+    }
+
+    /// Fold one fill into the ticker: last price, lifetime volumes and the
+    /// session range, rolling the session over first once it is stale.
+    pub fn record_trade(&mut self, price: u64, quantity: u64, notional: u64, now: i64) -> Result<()> {
+        if now.saturating_sub(self.session_start_ts) > Self::SESSION_SECS {
+            self.reset_session(now);
+        }
+        // Weigh the outgoing price before the fill replaces it
+        self.price_cumulative = self.twap_snapshot(now)?.price_cumulative;
+        self.last_twap_update_ts = now;
+        self.last_trade_price = price;
+        self.last_trade_ts = now;
+        self.cumulative_base_volume = self
+            .cumulative_base_volume
+            .checked_add(quantity)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        self.cumulative_quote_volume = self
+            .cumulative_quote_volume
+            .checked_add(notional as u128)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        self.session_high = self.session_high.max(price);
+        self.session_low = if self.session_low == 0 {
+            price
+        } else {
+            self.session_low.min(price)
+        };
+        self.trade_seq = self
+            .trade_seq
+            .checked_add(1)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// The TWAP accumulator as of `now`: price_cumulative advanced by the
+    /// last fill's price for the seconds since it was last updated. Two
+    /// snapshots give the TWAP between them (see get_twap).
+    pub fn twap_snapshot(&self, now: i64) -> Result<TwapSnapshot> {
+        let price_cumulative = solamatch_core::accumulate_price(
+            self.price_cumulative,
+            self.last_trade_price,
+            self.last_twap_update_ts,
+            now,
+        )
+        .map_err(MatchingEngineError::from)?;
+        Ok(TwapSnapshot {
+            price_cumulative,
+            timestamp: now,
+        })
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.settlement_price > 0
+    }
+
+    /// Prices, escrow and fees are in SPL quote tokens held in the quote
+    /// vault rather than in lamports.
+    pub fn is_token_quoted(&self) -> bool {
+        self.quote_mint != Pubkey::default()
+    }
+
+    /// Asks lock their base tokens in the base vault, and fills deliver
+    /// them to the buyer.
+    pub fn is_base_escrowed(&self) -> bool {
+        self.base_mint != Pubkey::default()
+    }
+}
+
+/// A reading of a market's TWAP accumulator. Keep one as the start of a
+/// window; the TWAP to a later reading is the price_cumulative delta over
+/// the seconds between them.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TwapSnapshot {
+    pub price_cumulative: u128,
+    pub timestamp: i64,
+}
+
+/// Result of `get_twap`, returned via return data.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TwapQuote {
+    pub twap: u64,               // average fill price over the window, rounded down
+    pub window_secs: i64,        // seconds from the window start to now
+    pub current: TwapSnapshot,   // the accumulator now — the next window's start
 }
 
 #[account]
+#[derive(Default)]
 pub struct Order {
     pub owner: Pubkey,           // 32
     pub market: Pubkey,          // 32
@@ -33,24 +462,219 @@ pub struct Order {
     pub bump: u8,                // 1
     pub is_locked: bool,         // 1  ← Double-match re-entrancy guard
     pub expires_at: i64,         // 8  ← TTL (0 = no expiry)
+    pub escrow_lamports: u64,    // 8  ← Escrow still held for this order (buys only)
+    pub funded_from_balance: bool, // 1 ← Escrow came from (and returns to) a TradingBalance
+    pub update_count: u64,       // 8  ← Version: starts at 1, bumped on every fill / cancel
+    pub counted_in_stats: bool,  // 1  ← Open volume is tracked in the owner's UserStats
+    pub fee_bps: u16,            // 2  ← Maker fee at placement; charged when this order sells as the resting side
+    pub beneficiary: Pubkey,     // 32 ← Receives sell proceeds (default = owner)
+    pub funder: Pubkey,          // 32 ← Paid the BUY escrow from its wallet; refunds return to it (default = owner)
+    pub subsidized: bool,        // 1  ← Rent came from the RentSubsidyVault and returns there on close
+    pub placed_slot: u64,        // 8  ← Slot the order opened in; ages crank rewards (0 = legacy)
+    pub price_q64: u128,         // 16 ← Exact Q64.64 price on fixed-point markets (0 = integer `price`)
+    pub escrow_in_vault: bool,   // 1  ← escrow_lamports counts quote tokens in the market's quote vault
+    pub base_escrow: u64,        // 8  ← Base tokens still locked in the base vault (asks only)
+    pub escrow_bump: u8,         // 1  ← Bump of the escrow vault ["escrow", market, order_id] holding a BUY's lamports
+    pub taker_fee_bps: u16,      // 2  ← Taker fee at placement; charged when this order sells as the incoming side
+    pub time_in_force: TimeInForce, // 1 ← Gtc rests; Ioc / Fok only fill at placement (place_order_tif)
+    pub post_only: bool,         // 1  ← Placed with place_order_post_only; never took liquidity
+    pub tracked_in_open_orders: bool, // 1 ← Listed in the owner's OpenOrders until filled or cancelled
+    pub trigger_price: u64,      // 8  ← Stop orders: last trade price that opens the order (0 = plain limit order)
+    pub trigger_direction: TriggerDirection, // 1 ← Which side of trigger_price the last trade must reach
+    pub owner_seeded: bool,      // 1  ← PDA seeded by owner + client_nonce (place_order_v2) rather than order_id
+    pub client_nonce: u64,       // 8  ← Owner-chosen PDA nonce of an owner_seeded order
+    pub display_quantity: u64,   // 8  ← Iceberg tranche size; quantity stays the true total (0 = all shown)
+    pub displayed_remaining: u64, // 8 ← Unfilled part of the current iceberg tranche
+    pub in_book: bool,           // 1  ← Indexed in its side's BookSide while it rests
+    pub version: u8,             // 1  ← Layout version (Order::VERSION); 0 = placed before versioning
+    pub referrer: Pubkey,        // 32 ← Earns the referral share of fees this order pays (default = none; version 2)
+    pub closed_at: i64,          // 8  ← When the order was filled or cancelled (0 = still open; version 3)
+    pub settles_to_balance: bool, // 1 ← match_orders credits its proceeds / refunds to the owner's UserBalance (version 4)
 }
 
 impl Order {
     // 8 discriminator + fields
-    pub const LEN: usize = 8 + 32 + 32 + 8 + 1 + 8 + 8 + 8 + 1 + 8 + 1 + 1 + 8;
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 1 + 8 + 8 + 8 + 1 + 8 + 1 + 1 + 8 + 8 + 1 + 8 + 1 + 2 + 32 + 32 + 1 + 8 + 16 + 1 + 8 + 1 + 2 + 1 + 1 + 1 + 8 + 1 + 1 + 8 + 8 + 8 + 1 + 1 + 32 + 8 + 1;
+    /// Layout version new orders are written at.
+    pub const VERSION: u8 = 4;
+    /// Seed of the escrow vault PDA: ["escrow", market, order_id_le]. A
+    /// system account holding a BUY's lamport escrow on top of its own
+    /// rent, so the Order holds only rent.
+    pub const ESCROW_SEED: &'static [u8] = b"escrow";
+    /// order_id of the stand-in order take_order fills a taker with. Newer
+    /// than any resting order, so the taker is always the incoming side.
+    pub const TAKER_ORDER_ID: u64 = u64::MAX;
 
     pub fn remaining_quantity(&self) -> u64 {
         self.quantity.saturating_sub(self.filled_quantity)
     }
 
+    /// Bring an order written at an older `version` up to VERSION, giving
+    /// the fields it predates their defaults where zero would misread.
+    pub fn upgrade(&mut self, now: i64) {
+        // Version 2 appended referrer, where the default key is "none". A
+        // closed order from before closed_at would be sweepable at once; its
+        // sweep delay runs from the upgrade instead
+        if self.version < 3 && self.is_closed() {
+            self.closed_at = now;
+        }
+        // Version 4 appended settles_to_balance; false pays out to wallets
+        // as before
+        self.version = Self::VERSION;
+    }
+
+    /// Priced in Q64.64 rather than whole lamports; `price` then holds the
+    /// whole-lamport part only.
+    pub fn is_fixed_point(&self) -> bool {
+        self.price_q64 > 0
+    }
+
+    /// Escrow a BUY needs for `quantity` units at this order's price.
+    pub fn escrow_for(&self, quantity: u64) -> Result<u64> {
+        if self.is_fixed_point() {
+            solamatch_core::escrow_q64(self.price_q64, quantity)
+                .map_err(|err| MatchingEngineError::from(err).into())
+        } else {
+            self.price
+                .checked_mul(quantity)
+                .ok_or_else(|| MatchingEngineError::MathOverflow.into())
+        }
+    }
+
+    /// Whether `key` is this order's PDA, derived from its own fields with
+    /// the seeds it was created under.
+    pub fn is_pda(&self, key: &Pubkey) -> bool {
+        let bump = [self.bump];
+        let order_id = self.order_id.to_le_bytes();
+        let client_nonce = self.client_nonce.to_le_bytes();
+        let seeds: &[&[u8]] = if self.owner_seeded {
+            &[b"order", self.market.as_ref(), self.owner.as_ref(), &client_nonce, &bump]
+        } else {
+            &[b"order", self.market.as_ref(), &order_id, &bump]
+        };
+        Pubkey::create_program_address(seeds, &crate::ID).is_ok_and(|pda| pda == *key)
+    }
+
+    /// Whether `key` is this order's escrow vault.
+    pub fn is_escrow_vault(&self, key: &Pubkey) -> bool {
+        let bump = [self.escrow_bump];
+        let order_id = self.order_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[Self::ESCROW_SEED, self.market.as_ref(), &order_id, &bump];
+        Pubkey::create_program_address(seeds, &crate::ID).is_ok_and(|pda| pda == *key)
+    }
+
     pub fn is_active(&self) -> bool {
         self.status == OrderStatus::Open || self.status == OrderStatus::PartiallyFilled
     }
 
+    /// Filled or cancelled: nothing left to do but close the account.
+    pub fn is_closed(&self) -> bool {
+        self.status == OrderStatus::Filled || self.status == OrderStatus::Cancelled
+    }
+
+    /// Move to `status`, stamping `closed_at` when it is terminal.
+    pub fn set_status(&mut self, status: OrderStatus, now: i64) {
+        self.status = status;
+        if self.is_closed() {
+            self.closed_at = now;
+        }
+    }
+
+    /// An iceberg shows only `display_quantity` units at a time.
+    pub fn is_iceberg(&self) -> bool {
+        self.display_quantity > 0
+    }
+
+    /// Quantity matching may fill now: the current tranche of an iceberg,
+    /// else the whole remainder.
+    pub fn visible_quantity(&self) -> u64 {
+        if self.is_iceberg() {
+            self.displayed_remaining.min(self.remaining_quantity())
+        } else {
+            self.remaining_quantity()
+        }
+    }
+
+    /// Take a fill of `quantity` out of an iceberg's tranche, revealing the
+    /// next `display_quantity` slice of the hidden remainder once it's used
+    /// up. Call after `filled_quantity` is updated.
+    pub fn consume_display(&mut self, quantity: u64) {
+        if !self.is_iceberg() {
+            return;
+        }
+        self.displayed_remaining = self.displayed_remaining.saturating_sub(quantity);
+        if self.displayed_remaining == 0 {
+            self.displayed_remaining = self.display_quantity.min(self.remaining_quantity());
+        }
+    }
+
+    /// Still holds its escrow: active, or a stop waiting for its trigger.
+    pub fn is_cancellable(&self) -> bool {
+        self.is_active() || self.status == OrderStatus::PendingTrigger
+    }
+
+    /// True once `last_trade_price` has reached this stop's trigger.
+    pub fn trigger_reached(&self, last_trade_price: u64) -> bool {
+        last_trade_price > 0
+            && match self.trigger_direction {
+                TriggerDirection::AtOrBelow => last_trade_price <= self.trigger_price,
+                TriggerDirection::AtOrAbove => last_trade_price >= self.trigger_price,
+            }
+    }
+
+    /// The price as Q64.64, whole-lamport prices included, so integer and
+    /// fixed-point orders compare alike.
+    pub fn price_key(&self) -> u128 {
+        if self.is_fixed_point() {
+            self.price_q64
+        } else {
+            solamatch_core::to_q64(self.price)
+        }
+    }
+
+    /// Optimistic-concurrency guard: `expected` must equal the current
+    /// update_count (0 = skip the check).
+    pub fn check_update_count(&self, expected: u64) -> Result<()> {
+        require!(
+            expected == 0 || expected == self.update_count,
+            MatchingEngineError::StaleOrderState
+        );
+        Ok(())
+    }
+
+    pub fn bump_update_count(&mut self) {
+        self.update_count = self.update_count.saturating_add(1);
+    }
+
     /// Returns true if the order has a TTL and it has expired.
     pub fn is_expired(&self, now: i64) -> bool {
         self.expires_at > 0 && now > self.expires_at
     }
+
+    /// Where sell proceeds go. Orders written before beneficiaries existed
+    /// carry a zeroed field and pay the owner.
+    pub fn proceeds_recipient(&self) -> Pubkey {
+        if self.beneficiary == Pubkey::default() {
+            self.owner
+        } else {
+            self.beneficiary
+        }
+    }
+
+    /// A take_order taker's in-memory stand-in, not an account: it never
+    /// rested, so it's in no book volume.
+    pub fn is_taker_stand_in(&self) -> bool {
+        self.order_id == Self::TAKER_ORDER_ID
+    }
+
+    /// Where wallet escrow refunds go. Zero (older orders) = the owner.
+    pub fn refund_recipient(&self) -> Pubkey {
+        if self.funder == Pubkey::default() {
+            self.owner
+        } else {
+            self.funder
+        }
+    }
 }
 
 /// Fee configuration PDA — one per market.
@@ -58,50 +682,649 @@ impl Order {
 #[account]
 pub struct FeeConfig {
     pub market: Pubkey,          // 32 — parent market
-    pub treasury: Pubkey,        // 32 — SOL recipient for fees
-    pub fee_bps: u16,            // 2  — basis points (100 = 1%). Max 500 (5%)
+    pub fee_bps: u16,            // 2  — maker rate, basis points (100 = 1%). Max 500 (5%)
     pub accumulated_fees: u64,   // 8  — lifetime total for auditing
     pub bump: u8,                // 1
+    pub protocol_accumulated_fees: u64, // 8 — lifetime protocol share of accumulated_fees
+    pub taker_fee_bps: u16,      // 2  — taker rate; equals fee_bps until set_taker_fee splits them
 }
 
 impl FeeConfig {
-    pub const LEN: usize = 8 + 32 + 32 + 2 + 8 + 1;
+    pub const LEN: usize = 8 + 32 + 2 + 8 + 1 + 8 + 2;
     pub const MAX_FEE_BPS: u16 = 500; // 5% hard cap
 
     /// Calculate the fee amount for a given payment.
     pub fn calc_fee(&self, payment: u64) -> u64 {
-        (payment as u128)
-            .checked_mul(self.fee_bps as u128)
-            .unwrap_or(0)
-            .checked_div(10_000)
-            .unwrap_or(0) as u64
+        solamatch_core::calc_fee(payment, self.fee_bps)
     }
 }
 
-// ─── Enums ────────────────────────────────────────────────────────────────────
+/// Program-owned fee vault — one per market, created with it.
+/// Seeds: [b"fee_vault", market_pubkey]
+/// Default fee recipient, and the fallback when the configured recipient
+/// can't be paid directly. Also holds the protocol's share of the market's
+/// fees: the authority may withdraw lamports above rent + protocol_fees,
+/// the protocol admin only protocol_fees.
+#[account]
+pub struct FeeVault {
+    pub market: Pubkey,          // 32
+    pub bump: u8,                // 1
+    pub protocol_fees: u64,      // 8  — protocol share awaiting withdraw_protocol_fees
+}
+
+impl FeeVault {
+    pub const LEN: usize = 8 + 32 + 1 + 8;
+}
+
+/// Protocol-wide configuration — a single PDA.
+/// Seeds: [b"config"]
+/// Once it exists, every collected fee is split: protocol_fee_share_bps of
+/// it to the protocol (held in the market's FeeVault), the rest to the market.
+#[account]
+pub struct GlobalConfig {
+    pub admin: Pubkey,               // 32 — protocol admin
+    pub protocol_fee_share_bps: u16, // 2  — share of each fee (10000 = all)
+    pub bump: u8,                    // 1
+    pub paused: bool,                // 1  — halts placement and matching on every market
+    pub treasury: Pubkey,            // 32 — only destination of withdrawn protocol fees
+}
+
+impl GlobalConfig {
+    pub const LEN: usize = 8 + 32 + 2 + 1 + 1 + 32;
+    pub const MAX_SHARE_BPS: u16 = 10_000;
 
+    /// Protocol share of `fee` (rounded down; the market keeps the remainder).
+    pub fn protocol_share(&self, fee: u64) -> u64 {
+        ((fee as u128) * (self.protocol_fee_share_bps as u128) / 10_000) as u64
+    }
+}
+
+/// Protocol-wide index of listed markets — a single PDA.
+/// Seeds: [b"registry"]
+/// Listings are numbered 0..count in creation order, so a client finds
+/// every listed market by deriving and fetching each MarketListing PDA.
+#[account]
+pub struct MarketRegistry {
+    pub count: u64,              // 8  — listings created; the next one's index
+    pub bump: u8,                // 1
+}
+
+impl MarketRegistry {
+    pub const LEN: usize = 8 + 8 + 1;
+}
+
+/// One market's entry in the MarketRegistry, written by initialize_market
+/// when the registry is passed.
+/// Seeds: [b"listing", registry_index_le_bytes]
+/// delist_market clears `active` but leaves the account, so the indices
+/// stay contiguous.
+#[account]
+pub struct MarketListing {
+    pub registry_index: u64,     // 8
+    pub market: Pubkey,          // 32
+    pub creator: Pubkey,         // 32 — the market's creator (its PDA seed)
+    pub market_name: String,     // 4 + 32
+    pub active: bool,            // 1  — false once delisted
+    pub listed_at: i64,          // 8
+    pub bump: u8,                // 1
+}
+
+impl MarketListing {
+    pub const LEN: usize = 8 + 8 + 32 + 32 + (4 + Market::MAX_NAME_LEN) + 1 + 8 + 1;
+}
+
+/// Pre-funded trading balance — one per (market, owner).
+/// Seeds: [b"balance", market_pubkey, owner_pubkey]
+/// Buys placed with this account draw escrow from it instead of the wallet,
+/// and their refunds flow back into it.
+#[account]
+pub struct TradingBalance {
+    pub owner: Pubkey,           // 32
+    pub market: Pubkey,          // 32
+    pub lamports: u64,           // 8  — spendable balance (excludes rent)
+    pub bump: u8,                // 1
+}
+
+impl TradingBalance {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 1;
+}
+
+/// Deferred settlement proceeds — one per (market, owner).
+/// Seeds: [b"user_balance", market_pubkey, owner_pubkey]
+/// match_orders credits fills of orders placed with it here instead of
+/// paying the owner's wallet; claim_funds sweeps the pending lamports out.
+#[account]
+pub struct UserBalance {
+    pub owner: Pubkey,           // 32
+    pub market: Pubkey,          // 32
+    pub pending_lamports: u64,   // 8  — credited by fills, not yet claimed (excludes rent)
+    pub bump: u8,                // 1
+}
+
+impl UserBalance {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 1;
+
+    /// Record `amount` lamports, already moved into the account, as pending.
+    pub fn credit(&mut self, amount: u64) -> Result<()> {
+        self.pending_lamports = self
+            .pending_lamports
+            .checked_add(amount)
+            .ok_or(MatchingEngineError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Market-sponsored order rent — one per market.
+/// Seeds: [b"rent_subsidy", market_pubkey]
+/// When passed to place_order with enough in it, the vault reimburses the
+/// new Order's rent to the owner; closing that order returns the rent here.
+#[account]
+pub struct RentSubsidyVault {
+    pub market: Pubkey,          // 32
+    pub lamports: u64,           // 8  — available for subsidies (excludes rent)
+    pub orders_subsidized: u64,  // 8  — lifetime count
+    pub bump: u8,                // 1
+}
+
+impl RentSubsidyVault {
+    pub const LEN: usize = 8 + 32 + 8 + 8 + 1;
+}
+
+/// Per-owner list of open order ids — one per (market, owner), so a wallet
+/// finds its orders by fetching one PDA and then the listed Order PDAs.
+/// Seeds: [b"open_orders", market_pubkey, owner_pubkey]
+/// Orders placed with it are listed until filled or cancelled, and every
+/// later fill or cancel of those orders must pass it back in.
+#[account]
+pub struct OpenOrders {
+    pub market: Pubkey,          // 32
+    pub owner: Pubkey,           // 32
+    pub bump: u8,                // 1
+    pub count: u8,               // 1  — ids in use, at the front of order_ids
+    pub order_ids: [u64; OpenOrders::CAPACITY], // 8 × 32
+}
+
+impl OpenOrders {
+    pub const CAPACITY: usize = 32;
+    pub const LEN: usize = 8 + 32 + 32 + 1 + 1 + 8 * Self::CAPACITY;
+
+    /// The listed order ids.
+    pub fn ids(&self) -> &[u64] {
+        &self.order_ids[..self.count as usize]
+    }
+
+    /// List `order_id`; fails with TooManyOpenOrders when full.
+    pub fn insert(&mut self, order_id: u64) -> Result<()> {
+        let count = self.count as usize;
+        require!(count < Self::CAPACITY, MatchingEngineError::TooManyOpenOrders);
+        self.order_ids[count] = order_id;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Unlist `order_id` (swap-remove, so ids are in no particular order).
+    pub fn remove(&mut self, order_id: u64) {
+        if let Some(i) = self.ids().iter().position(|&id| id == order_id) {
+            let last = self.count as usize - 1;
+            self.order_ids[i] = self.order_ids[last];
+            self.order_ids[last] = 0;
+            self.count -= 1;
+        }
+    }
+}
+
+/// One resting order in a BookSide.
+#[zero_copy]
+#[derive(Default, Debug)]
+pub struct BookEntry {
+    pub price: u64,              // 8  — whole lamports per unit
+    pub price_frac: u64,         // 8  — fractional part on fixed-point markets (price_q64's low 64 bits)
+    pub order_id: u64,           // 8
+    pub remaining: u64,          // 8  — displayed remainder (an iceberg's current tranche)
+    pub owner: Pubkey,           // 32
+}
+
+impl BookEntry {
+    pub fn new(order: &Order, remaining: u64) -> Self {
+        let price_q64 = order.price_key();
+        Self {
+            price: (price_q64 >> 64) as u64,
+            price_frac: price_q64 as u64,
+            order_id: order.order_id,
+            remaining,
+            owner: order.owner,
+        }
+    }
+
+    /// The price as Q64.64, as Order::price_key.
+    pub fn price_key(&self) -> u128 {
+        ((self.price as u128) << 64) | self.price_frac as u128
+    }
+}
+
+/// Price ladder of one side of a market's resting orders — the bids best
+/// (highest) first, or the asks best (lowest) first, ties in order_id
+/// (time) order — so cranks find crossing pairs by reading two accounts.
+/// Too large for a PDA: the client allocates BookSide::LEN bytes owned by
+/// the program, and create_book_sides records the account in the market
+/// (bid_book / ask_book). From then on every GTC order placed is indexed
+/// until filled or cancelled, and every change to it must pass the book.
+#[account(zero_copy)]
+pub struct BookSide {
+    pub market: Pubkey,          // 32
+    pub count: u64,              // 8  — entries in use, at the front of `entries`
+    pub side: u8,                // 1  — BookSide::BIDS or BookSide::ASKS
+    pub _padding: [u8; 7],       // 7
+    pub entries: [BookEntry; BookSide::CAPACITY], // 64 × 512
+}
+
+impl BookSide {
+    pub const CAPACITY: usize = 512;
+    pub const LEN: usize = 8 + 32 + 8 + 1 + 7 + 64 * Self::CAPACITY;
+    pub const BIDS: u8 = 0;
+    pub const ASKS: u8 = 1;
+
+    /// The indexed orders, best first.
+    pub fn entries(&self) -> &[BookEntry] {
+        &self.entries[..self.count as usize]
+    }
+
+    /// Best price on this side and the quantity indexed at it.
+    pub fn best_level(&self) -> Option<(u128, u64)> {
+        let best = self.entries().first()?.price_key();
+        let quantity = self
+            .entries()
+            .iter()
+            .take_while(|entry| entry.price_key() == best)
+            .fold(0u64, |total, entry| total.saturating_add(entry.remaining));
+        Some((best, quantity))
+    }
+
+    /// Whether `a` sorts ahead of `b`: better price, then older order.
+    fn ahead(&self, a: &BookEntry, b: &BookEntry) -> bool {
+        let (pa, pb) = (a.price_key(), b.price_key());
+        if pa == pb {
+            a.order_id < b.order_id
+        } else if self.side == Self::BIDS {
+            pa > pb
+        } else {
+            pa < pb
+        }
+    }
+
+    /// Index `entry` in price-time order; fails with BookFull when full.
+    pub fn insert(&mut self, entry: BookEntry) -> Result<()> {
+        let count = self.count as usize;
+        require!(count < Self::CAPACITY, MatchingEngineError::BookFull);
+        let at = self
+            .entries()
+            .iter()
+            .position(|resting| self.ahead(&entry, resting))
+            .unwrap_or(count);
+        self.entries.copy_within(at..count, at + 1);
+        self.entries[at] = entry;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Drop `order_id`'s entry, keeping the rest in order.
+    pub fn remove(&mut self, order_id: u64) {
+        let count = self.count as usize;
+        if let Some(at) = self.entries().iter().position(|entry| entry.order_id == order_id) {
+            self.entries.copy_within(at + 1..count, at);
+            self.entries[count - 1] = BookEntry::default();
+            self.count -= 1;
+        }
+    }
+}
+
+/// Per-owner open-volume tracking — one per (market, owner).
+/// Seeds: [b"user_stats", market_pubkey, owner_pubkey]
+/// Orders placed with it are counted in it until filled or cancelled, and
+/// every later fill or cancel of those orders must pass it back in.
+#[account]
+pub struct UserStats {
+    pub market: Pubkey,          // 32
+    pub owner: Pubkey,           // 32
+    pub open_bid_volume: u64,    // 8  — unfilled units on counted bids
+    pub open_ask_volume: u64,    // 8  — unfilled units on counted asks
+    pub bump: u8,                // 1
+    pub open_orders: u64,        // 8  — counted orders still open
+    pub fill_count: u64,         // 8  — fills of counted orders (probation graduation)
+    pub filled_notional: u64,    // 8  — lamports filled on counted orders
+}
+
+impl UserStats {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 1 + 8 + 8 + 8;
+
+    /// Open volume of `side`.
+    pub fn open_volume(&self, side: &Side) -> u64 {
+        match side {
+            Side::Buy => self.open_bid_volume,
+            Side::Sell => self.open_ask_volume,
+        }
+    }
+
+    /// Record one fill of a counted order; `closed` when it left it Filled.
+    pub fn record_fill(&mut self, side: &Side, quantity: u64, notional: u64, closed: bool) {
+        self.release(side, quantity);
+        self.fill_count = self.fill_count.saturating_add(1);
+        self.filled_notional = self.filled_notional.saturating_add(notional);
+        if closed {
+            self.open_orders = self.open_orders.saturating_sub(1);
+        }
+    }
+
+    /// Release `amount` units of `side` (fill or cancel).
+    pub fn release(&mut self, side: &Side, amount: u64) {
+        match side {
+            Side::Buy => self.open_bid_volume = self.open_bid_volume.saturating_sub(amount),
+            Side::Sell => self.open_ask_volume = self.open_ask_volume.saturating_sub(amount),
+        }
+    }
+}
+
+/// One fill in a TradeHistory.
+#[zero_copy]
+#[derive(Default, Debug)]
+pub struct TradeRecord {
+    pub bid_order_id: u64,       // 8
+    pub ask_order_id: u64,       // 8
+    pub price: u64,              // 8  — fill price, whole lamports per unit
+    pub quantity: u64,           // 8
+    pub timestamp: i64,          // 8
+}
+
+/// The market's most recent match_orders fills, for a UI to read with one
+/// fetch. A ring buffer: once full, each fill overwrites the oldest.
+/// `total_trades` counts every fill ever appended, so a reader that saw
+/// total N last time missed `total_trades - N - CAPACITY` fills when
+/// that is positive.
+/// Seeds: [b"trades", market_pubkey]
+/// Created by create_trade_history; match_orders appends when it's passed.
+#[account(zero_copy)]
+pub struct TradeHistory {
+    pub market: Pubkey,          // 32
+    pub head: u64,               // 8  — slot the next fill is written to
+    pub total_trades: u64,       // 8  — fills appended since creation
+    pub bump: u8,                // 1
+    pub _padding: [u8; 7],       // 7
+    pub trades: [TradeRecord; TradeHistory::CAPACITY], // 40 × 128
+}
+
+impl TradeHistory {
+    pub const CAPACITY: usize = 128;
+    pub const LEN: usize = 8 + 32 + 8 + 8 + 1 + 7 + 40 * Self::CAPACITY;
+
+    /// Write `record` over the oldest slot.
+    pub fn push(&mut self, record: TradeRecord) {
+        self.trades[self.head as usize] = record;
+        self.head = (self.head + 1) % Self::CAPACITY as u64;
+        self.total_trades = self.total_trades.saturating_add(1);
+    }
+
+    /// The records held, oldest first.
+    pub fn recent(&self) -> impl Iterator<Item = &TradeRecord> {
+        let held = self.total_trades.min(Self::CAPACITY as u64) as usize;
+        let start = (self.head as usize + Self::CAPACITY - held) % Self::CAPACITY;
+        (0..held).map(move |i| &self.trades[(start + i) % Self::CAPACITY])
+    }
+}
+
+/// Persistent record of one match_orders fill, written when the matcher
+/// passes it in and paid for by the matcher, who can close it again once
+/// archived off-chain.
+/// Seeds: [b"fill", market_pubkey, fill_seq_le_bytes] — fill_seq is the
+/// fill's Market.trade_seq.
+#[account]
+pub struct FillReceipt {
+    pub market: Pubkey,          // 32
+    pub fill_seq: u64,           // 8
+    pub bid_order_id: u64,       // 8
+    pub ask_order_id: u64,       // 8
+    pub buyer: Pubkey,           // 32
+    pub seller: Pubkey,          // 32
+    pub fill_price: u64,         // 8
+    pub fill_quantity: u64,      // 8
+    pub matcher: Pubkey,         // 32 — paid the rent; reclaims it on close
+    pub timestamp: i64,          // 8
+    pub bump: u8,                // 1
+}
+
+impl FillReceipt {
+    pub const LEN: usize = 8 + 32 + 8 + 8 + 8 + 32 + 32 + 8 + 8 + 32 + 8 + 1;
+}
+
+/// Per-matcher activity — one per (market, matcher), opened by the matcher.
+/// Seeds: [b"matcher", market_pubkey, matcher_pubkey]
+/// match_orders updates it whenever the signing matcher passes it in.
+#[account]
+pub struct MatcherStats {
+    pub market: Pubkey,          // 32
+    pub matcher: Pubkey,         // 32
+    pub matches_executed: u64,   // 8
+    pub volume_matched: u64,     // 8  — units filled
+    pub notional_matched: u64,   // 8  — lamports filled (gross)
+    pub fees_generated: u64,     // 8  — trading fees charged on its matches
+    pub rewards_earned: u64,     // 8  — matcher incentives paid to it
+    pub last_active_slot: u64,   // 8
+    pub bump: u8,                // 1
+}
+
+impl MatcherStats {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1;
+
+    /// Record one executed match.
+    pub fn record_match(&mut self, quantity: u64, notional: u64, fee: u64, slot: u64) {
+        self.matches_executed = self.matches_executed.saturating_add(1);
+        self.volume_matched = self.volume_matched.saturating_add(quantity);
+        self.notional_matched = self.notional_matched.saturating_add(notional);
+        self.fees_generated = self.fees_generated.saturating_add(fee);
+        self.last_active_slot = slot;
+    }
+
+    pub fn record_reward(&mut self, reward: u64) {
+        self.rewards_earned = self.rewards_earned.saturating_add(reward);
+    }
+}
+
+/// Sealed order — one per (market, owner, commitment_id), opened by commit_order.
+/// Seeds: [b"commitment", market_pubkey, owner_pubkey, commitment_id_le]
+/// Holds `escrow_lamports` (the owner's max notional, escrowed whatever the
+/// side so the commitment reveals nothing) until reveal_order turns it into
+/// an Order, or refund_commitment returns it after `reveal_deadline`.
+#[account]
+pub struct Commitment {
+    pub market: Pubkey,          // 32
+    pub owner: Pubkey,           // 32
+    pub commitment_id: u64,      // 8
+    pub hash: [u8; 32],          // 32 — Commitment::hash of the sealed order
+    pub escrow_lamports: u64,    // 8
+    pub committed_at: i64,       // 8
+    pub reveal_deadline: i64,    // 8
+    pub bump: u8,                // 1
+}
+
+impl Commitment {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 32 + 8 + 8 + 8 + 1;
+
+    /// sha256(side_u8 || price_le || quantity_le || salt || owner),
+    /// side 0 = Buy, 1 = Sell. Mirrored by client/commitment.ts.
+    pub fn hash(side: &Side, price: u64, quantity: u64, salt: &[u8; 32], owner: &Pubkey) -> [u8; 32] {
+        let side_byte = [match side {
+            Side::Buy => 0u8,
+            Side::Sell => 1u8,
+        }];
+        solana_sha256_hasher::hashv(&[
+            &side_byte,
+            &price.to_le_bytes(),
+            &quantity.to_le_bytes(),
+            salt,
+            owner.as_ref(),
+        ])
+        .to_bytes()
+    }
+}
+
+/// Trader seat — one per (market, trader), created by the market authority.
+/// Seeds: [b"seat", market_pubkey, trader_pubkey]
+/// On makers_restricted markets only seat holders may place resting orders.
+/// Closing the seat revokes it; orders already resting are unaffected.
+#[account]
+pub struct TraderSeat {
+    pub market: Pubkey,          // 32
+    pub trader: Pubkey,          // 32
+    pub granted_at: i64,         // 8
+    pub bump: u8,                // 1
+    pub fee_exempt: bool,        // 1 — trades involving this trader pay no fee (MM agreement)
+}
+
+impl TraderSeat {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 1 + 1;
+}
+
+/// Matcher seat — one per (market, matcher), created by the market authority.
+/// Seeds: [b"matcher_seat", market_pubkey, matcher_pubkey]
+/// On matcher_restricted markets only seat holders may run match_orders /
+/// match_orders_multi. Closing the seat revokes it.
+#[account]
+pub struct MatcherSeat {
+    pub market: Pubkey,          // 32
+    pub matcher: Pubkey,         // 32
+    pub granted_at: i64,         // 8
+    pub bump: u8,                // 1
+}
+
+impl MatcherSeat {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 1;
+}
+
+/// Delegated admin roles — one per market, created by the authority.
+/// Seeds: [b"roles", market_pubkey]
+/// Each admin instruction accepts the authority or its role's holder.
+/// An unset (or revoked) role holds the default pubkey, which nobody signs for.
+#[account]
+pub struct MarketRoles {
+    pub market: Pubkey,          // 32
+    pub pauser: Pubkey,          // 32 — pause / resume
+    pub fee_manager: Pubkey,     // 32 — fee config, fee recipient, fee withdrawals
+    pub param_manager: Pubkey,   // 32 — market params, limits, expiry
+    pub risk_manager: Pubkey,    // 32 — maker gating, trader seats, archival
+    pub bump: u8,                // 1
+}
+
+impl MarketRoles {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + 32 + 1;
+
+    pub fn holder(&self, role: Role) -> Pubkey {
+        match role {
+            Role::Pauser => self.pauser,
+            Role::FeeManager => self.fee_manager,
+            Role::ParamManager => self.param_manager,
+            Role::RiskManager => self.risk_manager,
+        }
+    }
+
+    pub fn set_holder(&mut self, role: Role, holder: Pubkey) {
+        match role {
+            Role::Pauser => self.pauser = holder,
+            Role::FeeManager => self.fee_manager = holder,
+            Role::ParamManager => self.param_manager = holder,
+            Role::RiskManager => self.risk_manager = holder,
+        }
+    }
+}
+
+/// Price feed written by a designated publisher.
+/// Seeds: [b"oracle_feed", publisher_pubkey]
+/// Dated markets name one of these as their settlement oracle.
+#[account]
+pub struct OracleFeed {
+    pub publisher: Pubkey,       // 32
+    pub price: u64,              // 8  — lamports per unit
+    pub publish_ts: i64,         // 8
+    pub bump: u8,                // 1
+}
+
+impl OracleFeed {
+    pub const LEN: usize = 8 + 32 + 8 + 8 + 1;
+}
+
+/// Market parameter set — applied immediately via `update_market_params`
+/// when the market has no timelock, otherwise staged and applied later.
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct MarketParams {
+    pub fee_bps: u16,              // 2  — maker and taker rate alike
+    pub treasury: Pubkey,          // 32 — becomes Market.fee_recipient
+    pub params_timelock_secs: i64, // 8
+    pub price_band_bps: u64,       // 8  — becomes Market.price_band_bps
+}
+
+impl MarketParams {
+    pub const LEN: usize = 2 + 32 + 8 + 8;
+}
+
+/// Pending parameter change waiting out the market timelock — one per market.
+/// Seeds: [b"staged_params", market_pubkey]
+#[account]
+pub struct StagedParams {
+    pub market: Pubkey,          // 32
+    pub params: MarketParams,    // 42
+    pub effective_ts: i64,       // 8  — earliest time apply_staged_params may run
+    pub staged_at: i64,          // 8
+    pub bump: u8,                // 1
+}
+
+impl StagedParams {
+    pub const LEN: usize = 8 + 32 + MarketParams::LEN + 8 + 8 + 1;
+}
+
+// ─── Enums ────────────────────────────────────────────────────────────────────
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, Default)]
 pub enum Side {
+    #[default]
     Buy,
     Sell,
 }
 
-impl Default for Side {
-    fn default() -> Self {
-        Side::Buy
-    }
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    Pauser,
+    FeeManager,
+    ParamManager,
+    RiskManager,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, Default)]
 pub enum OrderStatus {
+    #[default]
     Open,
     PartiallyFilled,
     Filled,
     Cancelled,
+    /// Stop order waiting for its trigger: holds its escrow, can't match.
+    PendingTrigger,
 }
 
-impl Default for OrderStatus {
-    fn default() -> Self {
-        OrderStatus::Open
-    }
+/// How long an order stays on the book.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TimeInForce {
+    /// Good-till-cancelled: the unfilled rest stays on the book.
+    #[default]
+    Gtc,
+    /// Immediate-or-cancel: fills what it can at placement, the rest is cancelled.
+    Ioc,
+    /// Fill-or-kill: fills its whole quantity at placement or not at all.
+    Fok,
+}
+
+/// When a stop order opens, relative to its trigger price.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TriggerDirection {
+    /// The last trade is at or below the trigger (stop-loss sell).
+    #[default]
+    AtOrBelow,
+    /// The last trade is at or above the trigger (stop-buy).
+    AtOrAbove,
 }
@@ -0,0 +1,150 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::{invoke, invoke_signed};
+use anchor_lang::system_program;
+use crate::errors::MatchingEngineError;
+
+// ─── Minimal SPL Token CPI ────────────────────────────────────────────────────
+//
+// Token-quoted markets escrow SPL tokens in a vault instead of lamports in
+// the order PDA. Only three token instructions are needed (create a vault,
+// transfer in, transfer out), so they're built by hand here from the
+// token program's fixed wire format rather than pulling in a token crate.
+//
+// A vault is a token account at a PDA of this program ([seed, market]) and
+// is its own token authority, so only this program can move its tokens.
+
+/// The SPL Token program.
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+/// Wrapped SOL. A market quoted in it keeps the plain lamport path.
+pub const NATIVE_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+
+/// spl_token::state::Account::LEN
+pub const ACCOUNT_LEN: usize = 165;
+/// spl_token::state::Mint::LEN
+pub const MINT_LEN: usize = 82;
+
+// Instruction tags (spl_token::instruction::TokenInstruction)
+const TRANSFER: u8 = 3;
+const INITIALIZE_ACCOUNT3: u8 = 18;
+
+/// The fields of a token account this program reads.
+pub struct TokenAccount {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+/// Decode an initialized token account owned by the token program.
+pub fn read_account(info: &AccountInfo) -> Result<TokenAccount> {
+    require!(
+        info.owner == &TOKEN_PROGRAM_ID,
+        MatchingEngineError::InvalidTokenAccount
+    );
+    let data = info.try_borrow_data()?;
+    // Layout: mint (32) | owner (32) | amount (8) | delegate (36) | state (1) …
+    require!(
+        data.len() == ACCOUNT_LEN && data[108] != 0,
+        MatchingEngineError::InvalidTokenAccount
+    );
+    let key = |range: std::ops::Range<usize>| {
+        Pubkey::try_from(&data[range]).map_err(|_| MatchingEngineError::InvalidTokenAccount)
+    };
+    Ok(TokenAccount {
+        mint: key(0..32)?,
+        owner: key(32..64)?,
+        amount: u64::from_le_bytes(data[64..72].try_into().unwrap()),
+    })
+}
+
+/// True for an initialized mint owned by the token program.
+pub fn is_mint(info: &AccountInfo) -> Result<bool> {
+    if info.owner != &TOKEN_PROGRAM_ID {
+        return Ok(false);
+    }
+    let data = info.try_borrow_data()?;
+    // Layout: mint_authority (36) | supply (8) | decimals (1) | is_initialized (1) …
+    Ok(data.len() == MINT_LEN && data[45] != 0)
+}
+
+/// Require `info` to be the token program.
+pub fn check_program(info: &AccountInfo) -> Result<()> {
+    require!(
+        info.key() == TOKEN_PROGRAM_ID,
+        MatchingEngineError::InvalidTokenProgram
+    );
+    Ok(())
+}
+
+/// Create the vault token account for `mint` at the PDA `vault` signs for
+/// with `vault_seeds`, owned by itself. `payer` funds the rent.
+pub fn create_vault<'info>(
+    payer: &AccountInfo<'info>,
+    vault: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    vault_seeds: &[&[u8]],
+) -> Result<()> {
+    system_program::create_account(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            system_program::CreateAccount {
+                from: payer.clone(),
+                to: vault.clone(),
+            },
+            &[vault_seeds],
+        ),
+        Rent::get()?.minimum_balance(ACCOUNT_LEN),
+        ACCOUNT_LEN as u64,
+        &TOKEN_PROGRAM_ID,
+    )?;
+
+    let mut data = Vec::with_capacity(33);
+    data.push(INITIALIZE_ACCOUNT3);
+    data.extend_from_slice(vault.key.as_ref());
+    let ix = Instruction {
+        program_id: TOKEN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(vault.key(), false),
+            AccountMeta::new_readonly(mint.key(), false),
+        ],
+        data,
+    };
+    invoke(&ix, &[vault.clone(), mint.clone(), token_program.clone()])?;
+    Ok(())
+}
+
+/// Transfer `amount` tokens from `from` to `to`. `authority` signs either
+/// as a transaction signer (empty `signer_seeds`) or as a PDA of this
+/// program. A zero amount is a no-op.
+pub fn transfer<'info>(
+    token_program: &AccountInfo<'info>,
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let mut data = Vec::with_capacity(9);
+    data.push(TRANSFER);
+    data.extend_from_slice(&amount.to_le_bytes());
+    let ix = Instruction {
+        program_id: TOKEN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(from.key(), false),
+            AccountMeta::new(to.key(), false),
+            AccountMeta::new_readonly(authority.key(), true),
+        ],
+        data,
+    };
+    invoke_signed(
+        &ix,
+        &[from.clone(), to.clone(), authority.clone(), token_program.clone()],
+        signer_seeds,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,162 @@
+import * as anchor from "@coral-xyz/anchor";
+import { AccountMeta, Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, escrowVaultPda, expectError, feeVaultPda, marketPda, orderPda, program, provider, tradingBalancePda } from "./helpers";
+
+describe("Market archival", () => {
+    const MARKET_NAME = "ARCHIVE/MOCK";
+    const ORDER_COUNT = 25;
+    const STEP = 10;
+    const PRICE = 1_000;
+    const authority = provider.wallet;
+    const walletBuyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const balanceBuyer = Keypair.generate();
+    const cranker = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [vaultPda] = feeVaultPda(mktPda);
+    const [balancePda] = tradingBalancePda(mktPda, balanceBuyer.publicKey);
+    const ownerOf = (i: number) => [walletBuyer, seller, balanceBuyer][i % 3];
+    const none = program.programId;
+
+    // [order, owner, trading_balance, user_stats, open_orders, escrow vault] per order
+    function archiveSlots(ids: number[]): AccountMeta[] {
+        return ids.flatMap((i) => {
+            const owner = ownerOf(i);
+            return [
+                { pubkey: orderPda(mktPda, i)[0], isSigner: false, isWritable: true },
+                { pubkey: owner.publicKey, isSigner: false, isWritable: true },
+                { pubkey: owner === balanceBuyer ? balancePda : none, isSigner: false, isWritable: owner === balanceBuyer },
+                { pubkey: none, isSigner: false, isWritable: false },
+                { pubkey: none, isSigner: false, isWritable: false },
+                { pubkey: escrowVaultPda(mktPda, i)[0], isSigner: false, isWritable: true },
+            ];
+        });
+    }
+
+    const archiveStep = (ids: number[]) =>
+        program.methods
+            .archiveStep(ids.length)
+            .accounts({ caller: cranker.publicKey, market: mktPda })
+            .remainingAccounts(archiveSlots(ids))
+            .signers([cranker])
+            .rpc();
+
+    const closeMarket = () =>
+        program.methods
+            .closeMarket()
+            .accounts({ authority: authority.publicKey, market: mktPda, feeVault: vaultPda })
+            .rpc();
+
+    before(async () => {
+        for (const kp of [walletBuyer, seller, balanceBuyer, cranker]) await airdrop(kp.publicKey, 2);
+
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeTradingBalance()
+            .accounts({ owner: balanceBuyer.publicKey, market: mktPda, tradingBalance: balancePda, systemProgram: SystemProgram.programId })
+            .signers([balanceBuyer])
+            .rpc();
+        await program.methods
+            .depositBalance(new anchor.BN(PRICE * 100))
+            .accounts({ owner: balanceBuyer.publicKey, market: mktPda, tradingBalance: balancePda, systemProgram: SystemProgram.programId })
+            .signers([balanceBuyer])
+            .rpc();
+
+        // Sells are priced above the buys so nothing crosses
+        for (let i = 0; i < ORDER_COUNT; i++) {
+            const owner = ownerOf(i);
+            const isSell = owner === seller;
+            await program.methods
+                .placeOrder(
+                    isSell ? { sell: {} } : { buy: {} },
+                    new anchor.BN(isSell ? PRICE * 2 : PRICE),
+                    new anchor.BN(1 + (i % 4)),
+                    new anchor.BN(i),
+                    new anchor.BN(0)
+                )
+                .accounts({
+                    owner: owner.publicKey,
+                    market: mktPda,
+                    order: orderPda(mktPda, i)[0],
+                    tradingBalance: owner === balanceBuyer ? balancePda : null,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([owner])
+                .rpc();
+        }
+
+        // One order is already gone before the wind-down
+        await program.methods
+            .cancelOrder(new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                owner: walletBuyer.publicKey,
+                market: mktPda,
+                order: orderPda(mktPda, 0)[0],
+                tradingBalance: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([walletBuyer])
+            .rpc();
+    });
+
+    it("Only archives from the authority, and stops placement", async () => {
+        await expectError(archiveStep([1]), "MarketNotArchiving");
+        await program.methods.beginArchive().accounts({ authority: authority.publicKey, market: mktPda }).rpc();
+
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.isTrue(mkt.isArchiving);
+        assert.equal(mkt.openOrderCount.toNumber(), ORDER_COUNT - 1);
+
+        await expectError(
+            program.methods
+                .placeOrder({ buy: {} }, new anchor.BN(PRICE), new anchor.BN(1), new anchor.BN(ORDER_COUNT), new anchor.BN(0))
+                .accounts({ owner: walletBuyer.publicKey, market: mktPda, order: orderPda(mktPda, ORDER_COUNT)[0], systemProgram: SystemProgram.programId })
+                .signers([walletBuyer])
+                .rpc(),
+            "MarketArchiving"
+        );
+        await expectError(closeMarket(), "OpenOrdersRemain");
+    });
+
+    it("Drains the book over several permissionless steps with full refunds", async () => {
+        const ids = Array.from({ length: ORDER_COUNT }, (_, i) => i);
+        const walletBefore = await provider.connection.getBalance(walletBuyer.publicKey);
+
+        let steps = 0;
+        for (let start = 0; start < ids.length; start += STEP) {
+            await archiveStep(ids.slice(start, start + STEP));
+            steps += 1;
+            const mkt = await program.account.market.fetch(mktPda);
+            const left = ids.slice(start + STEP).length;
+            assert.equal(mkt.openOrderCount.toNumber(), left, `after step ${steps}`);
+        }
+        assert.equal(steps, 3);
+
+        for (const i of ids) {
+            const o = await program.account.order.fetch(orderPda(mktPda, i)[0]);
+            assert.deepEqual(o.status, { cancelled: {} });
+            assert.equal(o.escrowLamports.toNumber(), 0);
+        }
+
+        // Wallet-funded buys refund to the wallet (order 0 was refunded earlier)
+        const walletRefund = ids
+            .filter((i) => i !== 0 && ownerOf(i) === walletBuyer)
+            .reduce((sum, i) => sum + PRICE * (1 + (i % 4)), 0);
+        assert.equal((await provider.connection.getBalance(walletBuyer.publicKey)) - walletBefore, walletRefund);
+
+        // Balance-funded buys refund into the trading balance
+        const bal = await program.account.tradingBalance.fetch(balancePda);
+        assert.equal(bal.lamports.toNumber(), PRICE * 100);
+    });
+
+    it("Closes the empty market", async () => {
+        await closeMarket();
+        assert.isNull(await provider.connection.getAccountInfo(mktPda));
+        assert.isNull(await provider.connection.getAccountInfo(vaultPda));
+    });
+});
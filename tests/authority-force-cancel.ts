@@ -0,0 +1,65 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, program, provider, sleep } from "./helpers";
+
+describe("Authority force-cancel", () => {
+    const MARKET_NAME = "FORCE/MOCK";
+    const authority = provider.wallet;
+    const trader = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [order] = orderPda(mktPda, 0);
+    const balance = (key: PublicKey) => provider.connection.getBalance(key);
+
+    const forceCancel = (caller: Keypair | null, owner: PublicKey) => {
+        const call = program.methods
+            .forceCancelOrder(new anchor.BN(0))
+            .accounts({ caller: caller ? caller.publicKey : authority.publicKey, market: mktPda, order, owner, tradingBalance: null, roles: null });
+        return caller ? call.signers([caller]).rpc() : call.rpc();
+    };
+
+    before(async () => {
+        for (const kp of [trader, stranger]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .placeOrder({ buy: {} }, new anchor.BN(1_000), new anchor.BN(25), new anchor.BN(0), new anchor.BN(0))
+            .accounts({ owner: trader.publicKey, market: mktPda, order, systemProgram: SystemProgram.programId })
+            .signers([trader])
+            .rpc();
+    });
+
+    it("Refuses anyone but the authority on a live market", async () => {
+        await expectError(forceCancel(stranger, trader.publicKey), "Unauthorized");
+    });
+
+    it("Refuses to refund the authority's own wallet", async () => {
+        await expectError(forceCancel(null, authority.publicKey), "OrderOwnerMismatch");
+        assert.deepEqual((await program.account.order.fetch(order)).status, { open: {} });
+    });
+
+    it("Cancels the order with the refund going to its owner", async () => {
+        const before = await balance(trader.publicKey);
+        let event: any = null;
+        const listener = program.addEventListener("orderForceCancelledEvent", (e) => (event = e));
+        await forceCancel(null, trader.publicKey);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        assert.equal((await balance(trader.publicKey)) - before, 25_000);
+        assert.deepEqual((await program.account.order.fetch(order)).status, { cancelled: {} });
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.totalBidVolume.toNumber(), 0);
+        assert.equal(mkt.openOrderCount.toNumber(), 0);
+
+        assert.equal(event.orderId.toNumber(), 0);
+        assert.isTrue(event.owner.equals(trader.publicKey));
+        assert.isTrue(event.cancelledBy.equals(authority.publicKey));
+        assert.equal(event.refundLamports.toNumber(), 25_000);
+        assert.isFalse(event.marketSettled);
+    });
+});
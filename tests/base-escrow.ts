@@ -0,0 +1,160 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { TOKEN_PROGRAM_ID, createAccount, createMint, getAccount, mintTo } from "@solana/spl-token";
+import { assert } from "chai";
+import { previewCancel } from "../client/cancelPreview";
+import { airdrop, baseVaultPda, expectError, marketPda, matchPair, orderPda, program, provider } from "./helpers";
+
+describe("Base-asset escrow for asks", () => {
+    const MARKET_NAME = "BASE/MOCK";
+    const authority = provider.wallet;
+    const payer = (authority as anchor.Wallet).payer;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [vaultPda] = baseVaultPda(mktPda);
+
+    let mint: PublicKey;
+    let buyerBase: PublicKey;
+    let sellerBase: PublicKey;
+    let nextId = 0;
+
+    const tokens = async (account: PublicKey) => Number((await getAccount(provider.connection, account)).amount);
+
+    function placeSell(price: number, qty: number, baseAccounts = true) {
+        const id = nextId;
+        const [order] = orderPda(mktPda, id);
+        return program.methods
+            .placeOrder({ sell: {} }, new anchor.BN(price), new anchor.BN(qty), new anchor.BN(id), new anchor.BN(0))
+            .accounts({
+                owner: seller.publicKey,
+                market: mktPda,
+                order,
+                baseVault: baseAccounts ? vaultPda : null,
+                ownerBaseAccount: baseAccounts ? sellerBase : null,
+                tokenProgram: baseAccounts ? TOKEN_PROGRAM_ID : null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([seller])
+            .rpc()
+            .then(() => {
+                nextId++;
+                return order;
+            });
+    }
+
+    async function placeBuy(price: number, qty: number): Promise<PublicKey> {
+        const id = nextId++;
+        const [order] = orderPda(mktPda, id);
+        await program.methods
+            .placeOrder({ buy: {} }, new anchor.BN(price), new anchor.BN(qty), new anchor.BN(id), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        return order;
+    }
+
+    const delivery = (buyerBaseAccount: PublicKey | null) => ({
+        bidTradingBalance: null,
+        baseVault: vaultPda,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        buyerBaseAccount,
+    });
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 5);
+        mint = await createMint(provider.connection, payer, authority.publicKey, null, 0);
+        buyerBase = await createAccount(provider.connection, payer, mint, buyer.publicKey);
+        sellerBase = await createAccount(provider.connection, payer, mint, seller.publicKey);
+        await mintTo(provider.connection, payer, mint, sellerBase, payer, 100);
+
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({
+                authority: authority.publicKey,
+                market: mktPda,
+                baseMint: mint,
+                baseVault: vaultPda,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                systemProgram: SystemProgram.programId,
+            })
+            .rpc();
+    });
+
+    it("Locks an ask's quantity in the base vault at placement", async () => {
+        const market = await program.account.market.fetch(mktPda);
+        assert.isTrue(market.baseMint.equals(mint));
+        assert.isTrue(market.baseVault.equals(vaultPda));
+
+        await expectError(placeSell(1_000, 10, false), "BaseAccountsRequired");
+        const ask = await placeSell(1_000, 10);
+        assert.equal(await tokens(sellerBase), 90);
+        assert.equal(await tokens(vaultPda), 10);
+        assert.equal((await program.account.order.fetch(ask)).baseEscrow.toNumber(), 10);
+    });
+
+    it("Delivers the filled base to the buyer as the seller is paid", async () => {
+        const ask = orderPda(mktPda, 0)[0];
+        const bid = await placeBuy(1_000, 4);
+        await expectError(matchPair(bid, ask, delivery(null)), "BaseAccountsRequired");
+
+        const sellerLamports = await provider.connection.getBalance(seller.publicKey);
+        await matchPair(bid, ask, delivery(buyerBase));
+
+        assert.equal(await tokens(buyerBase), 4);
+        assert.equal(await tokens(vaultPda), 6);
+        assert.equal((await provider.connection.getBalance(seller.publicKey)) - sellerLamports, 4_000);
+        const after = await program.account.order.fetch(ask);
+        assert.equal(after.baseEscrow.toNumber(), 6);
+        assert.deepEqual(after.status, { partiallyFilled: {} });
+    });
+
+    it("Moves locked base with a split", async () => {
+        const [ask] = orderPda(mktPda, 0);
+        const newId = nextId++;
+        const [slice] = orderPda(mktPda, newId);
+        await program.methods
+            .splitOrder(new anchor.BN(0), new anchor.BN(newId), new anchor.BN(2), new anchor.BN(0))
+            .accounts({ owner: seller.publicKey, market: mktPda, order: ask, newOrder: slice, systemProgram: SystemProgram.programId })
+            .signers([seller])
+            .rpc();
+        assert.equal((await program.account.order.fetch(ask)).baseEscrow.toNumber(), 4);
+        assert.equal((await program.account.order.fetch(slice)).baseEscrow.toNumber(), 2);
+    });
+
+    it("Returns only the unfilled remainder on cancel", async () => {
+        for (const [id, remainder] of [[0, 4], [2, 2]]) {
+            const [order] = orderPda(mktPda, id);
+            const preview = await previewCancel(program as any, mktPda, id);
+            assert.equal(preview.baseRefund, remainder);
+            assert.equal(preview.escrowRefund, 0);
+
+            const before = await tokens(sellerBase);
+            await program.methods
+                .cancelOrder(new anchor.BN(id), new anchor.BN(0))
+                .accounts({
+                    owner: seller.publicKey,
+                    market: mktPda,
+                    order,
+                    tradingBalance: null,
+                    baseVault: vaultPda,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    ownerBaseAccount: sellerBase,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([seller])
+                .rpc();
+            assert.equal((await tokens(sellerBase)) - before, remainder);
+            assert.equal((await program.account.order.fetch(order)).baseEscrow.toNumber(), 0);
+
+            await program.methods
+                .closeOrder(new anchor.BN(id))
+                .accounts({ owner: seller.publicKey, market: mktPda, order, rentSubsidyVault: null, systemProgram: SystemProgram.programId })
+                .signers([seller])
+                .rpc();
+        }
+        assert.equal(await tokens(vaultPda), 0);
+        assert.equal(await tokens(sellerBase), 96);
+    });
+});
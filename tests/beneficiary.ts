@@ -0,0 +1,106 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, matchPair, place, program, provider } from "./helpers";
+
+describe("Sell order beneficiary", () => {
+    const MARKET_NAME = "BENEF/MOCK";
+    const PRICE = 10_000;
+    const QTY = 5;
+    const GROSS = PRICE * QTY;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const payout = Keypair.generate();
+    const attacker = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+
+    const setBeneficiary = (owner: Keypair, order: PublicKey, orderId: number, beneficiary: PublicKey) =>
+        program.methods
+            .setBeneficiary(new anchor.BN(orderId), beneficiary)
+            .accounts({ owner: owner.publicKey, market: mktPda, order })
+            .signers([owner])
+            .rpc();
+
+    const balance = (key: PublicKey) => provider.connection.getBalance(key);
+
+    before(async () => {
+        for (const kp of [buyer, seller, payout, attacker]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Pays the owner when no beneficiary is named", async () => {
+        const [ask] = await place(mktPda, seller, { sell: {} }, PRICE, QTY);
+        assert.ok((await program.account.order.fetch(ask)).beneficiary.equals(seller.publicKey));
+
+        const [bid] = await place(mktPda, buyer, { buy: {} }, PRICE, QTY);
+        const sellerBefore = await balance(seller.publicKey);
+        await matchPair(bid, ask);
+        assert.equal((await balance(seller.publicKey)) - sellerBefore, GROSS);
+    });
+
+    it("Routes proceeds to the named beneficiary", async () => {
+        const [ask] = await place(mktPda, seller, { sell: {} }, PRICE, QTY, { accounts: { beneficiary: payout.publicKey } });
+        assert.ok((await program.account.order.fetch(ask)).beneficiary.equals(payout.publicKey));
+
+        const [bid] = await place(mktPda, buyer, { buy: {} }, PRICE, QTY);
+        const sellerBefore = await balance(seller.publicKey);
+        const payoutBefore = await balance(payout.publicKey);
+        await matchPair(bid, ask, { askBeneficiary: payout.publicKey });
+        assert.equal((await balance(payout.publicKey)) - payoutBefore, GROSS);
+        assert.equal(await balance(seller.publicKey), sellerBefore);
+    });
+
+    it("Rejects a missing or substituted beneficiary account", async () => {
+        const [ask] = await place(mktPda, seller, { sell: {} }, PRICE, QTY, { accounts: { beneficiary: payout.publicKey } });
+        const [bid] = await place(mktPda, buyer, { buy: {} }, PRICE, QTY);
+        const attackerBefore = await balance(attacker.publicKey);
+
+        await expectError(matchPair(bid, ask), "BeneficiaryMismatch");
+        await expectError(matchPair(bid, ask, { askBeneficiary: attacker.publicKey }), "BeneficiaryMismatch");
+        assert.equal(await balance(attacker.publicKey), attackerBefore);
+
+        await matchPair(bid, ask, { askBeneficiary: payout.publicKey });
+        assert.deepEqual((await program.account.order.fetch(ask)).status, { filled: {} });
+    });
+
+    it("Only sells name a beneficiary", async () => {
+        const { nextOrderId } = await program.account.market.fetch(mktPda);
+        await expectError(place(mktPda, buyer, { buy: {} }, PRICE, QTY, { accounts: { beneficiary: payout.publicKey } }), "BeneficiaryOnlyForSells");
+        const [bid] = await place(mktPda, buyer, { buy: {} }, PRICE, QTY);
+        await expectError(
+            setBeneficiary(buyer, bid, nextOrderId.toNumber(), payout.publicKey),
+            "BeneficiaryOnlyForSells"
+        );
+    });
+
+    it("Only the owner changes it, and the rent still returns to the owner", async () => {
+        const { nextOrderId } = await program.account.market.fetch(mktPda);
+        const orderId = nextOrderId.toNumber();
+        const [ask] = await place(mktPda, seller, { sell: {} }, PRICE, QTY);
+
+        await expectError(setBeneficiary(attacker, ask, orderId, attacker.publicKey), "Unauthorized");
+        await setBeneficiary(seller, ask, orderId, payout.publicKey);
+        assert.ok((await program.account.order.fetch(ask)).beneficiary.equals(payout.publicKey));
+
+        const rent = await balance(ask);
+        const sellerBefore = await balance(seller.publicKey);
+        const payoutBefore = await balance(payout.publicKey);
+        await program.methods
+            .cancelOrder(new anchor.BN(orderId), new anchor.BN(0))
+            .accounts({ owner: seller.publicKey, market: mktPda, order: ask, tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([seller])
+            .rpc();
+        await program.methods
+            .closeOrder(new anchor.BN(orderId))
+            .accounts({ owner: seller.publicKey, market: mktPda, order: ask, systemProgram: SystemProgram.programId })
+            .signers([seller])
+            .rpc();
+        assert.equal((await balance(seller.publicKey)) - sellerBefore, rent);
+        assert.equal(await balance(payout.publicKey), payoutBefore);
+    });
+});
@@ -0,0 +1,244 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, escrowVaultPda, expectError, marketPda, orderPda, place, program, provider, sleep } from "./helpers";
+
+describe("Book sides", () => {
+    const MARKET_NAME = "BOOK/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const bidBook = Keypair.generate();
+    const askBook = Keypair.generate();
+    const bids = bidBook.publicKey;
+    const asks = askBook.publicKey;
+    const fetchMarket = () => program.account.market.fetch(mktPda);
+    const created: any[] = [];
+
+    // (price, order id, remaining) of each indexed order, best first
+    const ladder = async (book: PublicKey) => {
+        const { count, entries } = await program.account.bookSide.fetch(book);
+        return entries.slice(0, count.toNumber()).map((e: any) => [e.price.toNumber(), e.orderId.toNumber(), e.remaining.toNumber()]);
+    };
+
+    const rest = async (owner: Keypair, side: any, price: number, quantity: number, bookSide: PublicKey | null) =>
+        (await place(mktPda, owner, side, price, quantity, { accounts: { bookSide } }))[1];
+
+    const cancel = (owner: Keypair, orderId: number, book: PublicKey | null) =>
+        program.methods
+            .cancelOrder(new anchor.BN(orderId), new anchor.BN(0))
+            .accounts({ owner: owner.publicKey, market: mktPda, order: orderPda(mktPda, orderId)[0], tradingBalance: null, bookSide: book, systemProgram: SystemProgram.programId })
+            .signers([owner])
+            .rpc();
+
+    const match = (bidId: number, askId: number, books: { bids?: PublicKey; asks?: PublicKey }) =>
+        program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, bidId)[0],
+                askOrder: orderPda(mktPda, askId)[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+                bids: books.bids ?? null,
+                asks: books.asks ?? null,
+            })
+            .rpc();
+
+    // (maker order, wallet, no seat, escrow vault) groups for fills outside match_orders
+    const makers = (groups: [number, Keypair][]) =>
+        groups.flatMap(([id, wallet]) => [
+            { pubkey: orderPda(mktPda, id)[0], isSigner: false, isWritable: true },
+            { pubkey: wallet.publicKey, isSigner: false, isWritable: true },
+            { pubkey: program.programId, isSigner: false, isWritable: false },
+            { pubkey: escrowVaultPda(mktPda, id)[0], isSigner: false, isWritable: true },
+        ]);
+
+    // The books are too large for PDAs: the client allocates them, owned by the program
+    const createBookSides = async (books: Keypair[]) => {
+        const space = program.account.bookSide.size;
+        const lamports = await provider.connection.getMinimumBalanceForRentExemption(space);
+        return program.methods
+            .createBookSides()
+            .accounts({ authority: authority.publicKey, market: mktPda, bids: books[0].publicKey, asks: books[1].publicKey, roles: null })
+            .preInstructions(
+                books.map((book) =>
+                    SystemProgram.createAccount({
+                        fromPubkey: authority.publicKey,
+                        newAccountPubkey: book.publicKey,
+                        space,
+                        lamports,
+                        programId: program.programId,
+                    })
+                )
+            )
+            .signers(books)
+            .rpc();
+    };
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        const listener = program.addEventListener("bookSidesCreatedEvent", (e) => created.push(e));
+        await createBookSides([bidBook, askBook]);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+    });
+
+    it("Creates one empty ladder per side, recorded in the market", async () => {
+        const bidLadder = await program.account.bookSide.fetch(bids);
+        const askLadder = await program.account.bookSide.fetch(asks);
+        assert.ok(bidLadder.market.equals(mktPda));
+        assert.equal(bidLadder.side, 0);
+        assert.equal(askLadder.side, 1);
+        assert.equal(bidLadder.count.toNumber(), 0);
+        assert.lengthOf(bidLadder.entries, 512);
+
+        const market = await fetchMarket();
+        assert.ok(market.bidBook.equals(bids));
+        assert.ok(market.askBook.equals(asks));
+
+        // Chained into the market's state hash
+        assert.lengthOf(created, 1);
+        assert.ok(created[0].bids.equals(bids));
+        assert.ok(created[0].asks.equals(asks));
+        assert.equal(created[0].eventSeq.toNumber(), market.eventSeq.toNumber());
+        assert.deepEqual(Buffer.from(created[0].stateHash), Buffer.from(market.stateHash));
+    });
+
+    it("Creates them only once", async () => {
+        await expectError(createBookSides([Keypair.generate(), Keypair.generate()]), "BookSidesExist");
+    });
+
+    it("Requires the book for every placement once it exists", async () => {
+        await expectError(rest(buyer, { buy: {} }, 970, 1, null), "BookSideRequired");
+    });
+
+    it("Keeps bids best price first, then oldest first", async () => {
+        const a = await rest(buyer, { buy: {} }, 900, 5, bids);
+        const b = await rest(buyer, { buy: {} }, 950, 3, bids);
+        const c = await rest(buyer, { buy: {} }, 900, 2, bids);
+        assert.deepEqual(await ladder(bids), [[950, b, 3], [900, a, 5], [900, c, 2]]);
+        assert.isTrue((await program.account.order.fetch(orderPda(mktPda, a)[0])).inBook);
+    });
+
+    it("Keeps asks lowest price first", async () => {
+        const a = await rest(seller, { sell: {} }, 1_100, 4, asks);
+        const b = await rest(seller, { sell: {} }, 1_050, 2, asks);
+        assert.deepEqual(await ladder(asks), [[1_050, b, 2], [1_100, a, 4]]);
+    });
+
+    it("Rejects the other side's book", async () => {
+        await expectError(rest(buyer, { buy: {} }, 900, 1, asks), "BookSideMismatch");
+    });
+
+    it("Shrinks a partial fill in place and drops a filled order", async () => {
+        // Bid 1 (3 @ 950) crosses a fresh indexed ask of 2 @ 950
+        const ask = await rest(seller, { sell: {} }, 950, 2, asks);
+        await expectError(match(1, ask, { asks }), "BookSideRequired");
+        await match(1, ask, { bids, asks });
+        assert.deepEqual((await ladder(bids))[0], [950, 1, 1]);
+        assert.notInclude((await ladder(asks)).map((e: number[]) => e[1]), ask);
+
+        const ask2 = await rest(seller, { sell: {} }, 950, 1, asks);
+        await match(1, ask2, { bids, asks });
+        assert.deepEqual((await ladder(bids)).map((e: number[]) => e[1]), [0, 2]);
+        // The emptied best bid is refilled from the ladder
+        const market = await fetchMarket();
+        assert.equal(market.bestBidQ64.shrn(64).toNumber(), 900);
+        assert.equal(market.bestBidQuantity.toNumber(), 7);
+    });
+
+    it("Drops a cancelled order, which needs the book", async () => {
+        await expectError(cancel(buyer, 0, null), "BookSideRequired");
+        await cancel(buyer, 0, bids);
+        assert.deepEqual(await ladder(bids), [[900, 2, 2]]);
+    });
+
+    it("Fills indexed makers by take_order, given their book", async () => {
+        const take = (book: PublicKey | null) =>
+            program.methods
+                .takeOrder({ buy: {} }, new anchor.BN(3), new anchor.BN(1_100))
+                .accounts({
+                    taker: buyer.publicKey,
+                    market: mktPda,
+                    traderSeat: null,
+                    feeConfig: null,
+                    treasury: null,
+                    feeVault: null,
+                    bookSide: book,
+                    systemProgram: SystemProgram.programId,
+                })
+                .remainingAccounts(makers([[4, seller], [3, seller]]))
+                .signers([buyer])
+                .rpc();
+        await expectError(take(null), "BookSideRequired");
+        await take(asks);
+        // Ask 4 (2 @ 1_050) filled and dropped, ask 3 shrunk to 3
+        assert.deepEqual(await ladder(asks), [[1_100, 3, 3]]);
+        const market = await fetchMarket();
+        assert.equal(market.bestAskQ64.shrn(64).toNumber(), 1_100);
+        assert.equal(market.bestAskQuantity.toNumber(), 3);
+    });
+
+    it("Fills an indexed bid by an IOC sell at placement, given the maker's book", async () => {
+        const { nextOrderId } = await fetchMarket();
+        const ioc = (makerBook: PublicKey | null) =>
+            program.methods
+                .placeOrderTif({ sell: {} }, new anchor.BN(900), new anchor.BN(1), nextOrderId, new anchor.BN(0), { ioc: {} })
+                .accounts({
+                    owner: seller.publicKey,
+                    market: mktPda,
+                    order: orderPda(mktPda, nextOrderId.toNumber())[0],
+                    bookSide: asks,
+                    makerBookSide: makerBook,
+                    systemProgram: SystemProgram.programId,
+                })
+                .remainingAccounts(makers([[2, buyer]]))
+                .signers([seller])
+                .rpc();
+        await expectError(ioc(null), "BookSideRequired");
+        await ioc(bids);
+        assert.deepEqual(await ladder(bids), [[900, 2, 1]]);
+        // The IOC order never rests
+        assert.deepEqual(await ladder(asks), [[1_100, 3, 3]]);
+    });
+
+    it("Sweeps indexed asks by match_orders_multi, given both books", async () => {
+        const bid = await rest(buyer, { buy: {} }, 1_100, 3, bids);
+        const sweep = (books: { bids?: PublicKey; asks?: PublicKey }) =>
+            program.methods
+                .matchOrdersMulti(false)
+                .accounts({
+                    matcher: authority.publicKey,
+                    market: mktPda,
+                    bidOrder: orderPda(mktPda, bid)[0],
+                    bidOwner: buyer.publicKey,
+                    bidFunder: null,
+                    bidTradingBalance: null,
+                    bidUserStats: null,
+                    bidOpenOrders: null,
+                    bids: books.bids ?? null,
+                    asks: books.asks ?? null,
+                    bidSeat: null,
+                    feeConfig: null,
+                    treasury: null,
+                    feeVault: null,
+                })
+                .remainingAccounts(makers([[3, seller]]))
+                .rpc();
+        await expectError(sweep({ bids }), "BookSideRequired");
+        await sweep({ bids, asks });
+        assert.deepEqual(await ladder(asks), []);
+        assert.deepEqual(await ladder(bids), [[900, 2, 1]]);
+        assert.deepEqual((await program.account.order.fetch(orderPda(mktPda, bid)[0])).status, { filled: {} });
+    });
+});
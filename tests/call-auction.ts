@@ -0,0 +1,137 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, chainTime, escrowVaultPda, expectError, marketPda, orderPda, place, program, provider, sleep } from "./helpers";
+
+describe("Call auctions", () => {
+    const MARKET_NAME = "AUCTION/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const balance = (key: PublicKey) => provider.connection.getBalance(key);
+    const fetchMarket = () => program.account.market.fetch(mktPda);
+    const fetchOrder = (orderId: number) => program.account.order.fetch(orderPda(mktPda, orderId)[0]);
+
+    let auctionEnd = 0;
+
+    const startAuction = (endTs: number) =>
+        program.methods
+            .startAuction(new anchor.BN(endTs))
+            .accounts({ authority: authority.publicKey, market: mktPda, roles: null })
+            .rpc();
+
+    const match = (bidId: number, askId: number) =>
+        program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, bidId)[0],
+                askOrder: orderPda(mktPda, askId)[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+            })
+            .rpc();
+
+    const settle = (clearingPrice: number, pairs: [number, number][]) =>
+        program.methods
+            .settleAuction(new anchor.BN(clearingPrice))
+            .accounts({ settler: authority.publicKey, market: mktPda, feeConfig: null, treasury: null, feeVault: null })
+            .remainingAccounts(
+                pairs.flatMap(([bidId, askId]) => [
+                    { pubkey: orderPda(mktPda, bidId)[0], isSigner: false, isWritable: true },
+                    { pubkey: buyer.publicKey, isSigner: false, isWritable: true },
+                    { pubkey: program.programId, isSigner: false, isWritable: false },
+                    { pubkey: orderPda(mktPda, askId)[0], isSigner: false, isWritable: true },
+                    { pubkey: seller.publicKey, isSigner: false, isWritable: true },
+                    { pubkey: program.programId, isSigner: false, isWritable: false },
+                    { pubkey: escrowVaultPda(mktPda, bidId)[0], isSigner: false, isWritable: true },
+                ])
+            )
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Rejects an auction end in the past", async () => {
+        await expectError(startAuction((await chainTime()) - 10), "InvalidAuctionEnd");
+    });
+
+    it("Collects orders without matching them", async () => {
+        auctionEnd = (await chainTime()) + 4;
+        await startAuction(auctionEnd);
+        const market = await fetchMarket();
+        assert.isTrue(market.auctionMode);
+        assert.equal(market.auctionEndTs.toNumber(), auctionEnd);
+
+        // Bids 10 @ 1_200 and 5 @ 1_100; asks 10 @ 1_000 and 5 @ 1_050
+        await place(mktPda, buyer, { buy: {} }, 1_200, 10);
+        await place(mktPda, buyer, { buy: {} }, 1_100, 5);
+        await place(mktPda, seller, { sell: {} }, 1_000, 10);
+        await place(mktPda, seller, { sell: {} }, 1_050, 5);
+
+        await expectError(match(0, 2), "AuctionInProgress");
+        await expectError(settle(1_100, [[0, 2]]), "AuctionNotEnded");
+    });
+
+    it("Rejects a clearing price outside any pair's limits", async () => {
+        while ((await chainTime()) < auctionEnd) await sleep(500);
+        // Above the second bid's limit
+        await expectError(settle(1_150, [[0, 2], [1, 3]]), "InvalidClearingPrice");
+        // Below the second ask's limit
+        await expectError(settle(1_000, [[0, 2], [1, 3]]), "InvalidClearingPrice");
+    });
+
+    it("Fills every pair at the clearing price, refunding bids above it", async () => {
+        const before = await Promise.all([balance(buyer.publicKey), balance(seller.publicKey)]);
+
+        let settled: any = null;
+        const trades: any[] = [];
+        const settledListener = program.addEventListener("auctionSettledEvent", (e) => (settled = e));
+        const tradeListener = program.addEventListener("tradeExecutedEvent", (e) => trades.push(e));
+        await settle(1_100, [[0, 2], [1, 3]]);
+        await sleep(1000);
+        await program.removeEventListener(settledListener);
+        await program.removeEventListener(tradeListener);
+
+        const after = await Promise.all([balance(buyer.publicKey), balance(seller.publicKey)]);
+        // 10 × (1_200 − 1_100) refunded to the buyer; 15 × 1_100 to the seller
+        assert.equal(after[0] - before[0], 1_000);
+        assert.equal(after[1] - before[1], 16_500);
+        for (const id of [0, 1, 2, 3]) {
+            const order = await fetchOrder(id);
+            assert.deepEqual(order.status, { filled: {} });
+            assert.equal(order.escrowLamports.toNumber(), 0);
+        }
+
+        assert.equal(settled.clearingPrice.toNumber(), 1_100);
+        assert.equal(settled.fillCount, 2);
+        assert.equal(settled.totalQuantity.toNumber(), 15);
+        assert.equal(trades.length, 2);
+        for (const trade of trades) assert.equal(trade.fillPrice.toNumber(), 1_100);
+        assert.equal((await fetchMarket()).lastTradePrice.toNumber(), 1_100);
+    });
+
+    it("Returns to continuous matching", async () => {
+        await program.methods
+            .endAuction()
+            .accounts({ authority: authority.publicKey, market: mktPda, roles: null })
+            .rpc();
+        assert.isFalse((await fetchMarket()).auctionMode);
+        await expectError(settle(1_100, [[0, 2]]), "AuctionNotActive");
+
+        const [, bid] = await place(mktPda, buyer, { buy: {} }, 1_000, 1);
+        const [, ask] = await place(mktPda, seller, { sell: {} }, 1_000, 1);
+        await match(bid, ask);
+        assert.deepEqual((await fetchOrder(bid)).status, { filled: {} });
+    });
+});
@@ -0,0 +1,112 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, escrowVaultPda, marketPda, orderPda, place, program, provider } from "./helpers";
+
+describe("cancel_all_orders", () => {
+    const MARKET_NAME = "CANCELALL/MOCK";
+    const authority = provider.wallet;
+    const trader = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const fetchMarket = () => program.account.market.fetch(mktPda);
+    const balance = (key: PublicKey) => provider.connection.getBalance(key);
+    // Each placed order's escrow vault, by order address
+    const vaults = new Map<string, PublicKey>();
+    const vaultOf = (order: PublicKey) => vaults.get(order.toBase58()) ?? program.programId;
+
+    async function rest(owner: Keypair, side: any, price: number, qty: number): Promise<PublicKey> {
+        const [order, id] = await place(mktPda, owner, side, price, qty);
+        vaults.set(order.toBase58(), escrowVaultPda(mktPda, id)[0]);
+        return order;
+    }
+
+    // (order, escrow vault) pairs; a vault passed explicitly overrides the order's
+    const cancelAll = (owner: Keypair, orders: (PublicKey | [PublicKey, PublicKey])[]) =>
+        program.methods
+            .cancelAllOrders()
+            .accounts({
+                owner: owner.publicKey,
+                market: mktPda,
+                tradingBalance: null,
+                userStats: null,
+                openOrders: null,
+                quoteVault: null,
+                tokenProgram: null,
+                ownerQuoteAccount: null,
+                baseVault: null,
+                ownerBaseAccount: null,
+            })
+            .remainingAccounts(
+                orders.flatMap((entry) => {
+                    const [order, vault] = Array.isArray(entry) ? entry : [entry, vaultOf(entry)];
+                    return [
+                        { pubkey: order, isSigner: false, isWritable: true },
+                        { pubkey: vault, isSigner: false, isWritable: true },
+                    ];
+                })
+            )
+            .signers([owner])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [trader, stranger]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Cancels 5 buys and 3 sells in one transaction", async () => {
+        const buys: PublicKey[] = [];
+        for (let i = 1; i <= 5; i++) buys.push(await rest(trader, { buy: {} }, 1_000 * i, 10));
+        const sells: PublicKey[] = [];
+        for (let i = 1; i <= 3; i++) sells.push(await rest(trader, { sell: {} }, 20_000, 5));
+        // 10 × (1_000 + 2_000 + … + 5_000)
+        const escrowed = 150_000;
+
+        const before = await balance(trader.publicKey);
+        await cancelAll(trader, [...buys, ...sells]);
+        assert.equal((await balance(trader.publicKey)) - before, escrowed);
+
+        for (const order of [...buys, ...sells]) {
+            const o = await program.account.order.fetch(order);
+            assert.deepEqual(o.status, { cancelled: {} });
+            assert.equal(o.escrowLamports.toNumber(), 0);
+        }
+        const mkt = await fetchMarket();
+        assert.equal(mkt.totalBidVolume.toNumber(), 0);
+        assert.equal(mkt.totalAskVolume.toNumber(), 0);
+        assert.equal(mkt.openOrderCount.toNumber(), 0);
+    });
+
+    it("Skips accounts it can't cancel instead of failing the batch", async () => {
+        const mine = await rest(trader, { buy: {} }, 1_000, 3);
+        const theirs = await rest(stranger, { buy: {} }, 1_000, 3);
+        const cancelled = orderPda(mktPda, 0)[0];
+
+        // A stranger's order, an already-cancelled one, a duplicate and a
+        // non-order account alongside one live order
+        await cancelAll(trader, [theirs, cancelled, mine, mine, mktPda, Keypair.generate().publicKey]);
+
+        assert.deepEqual((await program.account.order.fetch(mine)).status, { cancelled: {} });
+        assert.deepEqual((await program.account.order.fetch(theirs)).status, { open: {} });
+        const mkt = await fetchMarket();
+        assert.equal(mkt.totalBidVolume.toNumber(), 3);
+        assert.equal(mkt.openOrderCount.toNumber(), 1);
+    });
+
+    it("Skips a buy passed with another order's escrow vault", async () => {
+        const first = await rest(trader, { buy: {} }, 1_000, 2);
+        const second = await rest(trader, { buy: {} }, 1_000, 2);
+
+        await cancelAll(trader, [[first, vaultOf(second)]]);
+        assert.deepEqual((await program.account.order.fetch(first)).status, { open: {} });
+
+        await cancelAll(trader, [first, second]);
+        for (const order of [first, second]) {
+            assert.deepEqual((await program.account.order.fetch(order)).status, { cancelled: {} });
+        }
+    });
+});
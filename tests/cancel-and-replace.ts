@@ -0,0 +1,107 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, matchPair, orderPda, place, program, provider, sleep, tradingBalancePda } from "./helpers";
+
+describe("Cancel and replace", () => {
+    const MARKET_NAME = "REPLACE/MOCK";
+    const authority = provider.wallet;
+    const maker = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [balancePda] = tradingBalancePda(mktPda, maker.publicKey);
+    const balanceOf = async () => (await program.account.tradingBalance.fetch(balancePda)).lamports.toNumber();
+
+    async function replace(oldId: number, price: number, quantity: number) {
+        const newId = (await program.account.market.fetch(mktPda)).nextOrderId.toNumber();
+        await program.methods
+            .cancelAndReplace(new anchor.BN(oldId), new anchor.BN(price), new anchor.BN(quantity), new anchor.BN(newId))
+            .accounts({
+                owner: maker.publicKey,
+                market: mktPda,
+                oldOrder: orderPda(mktPda, oldId)[0],
+                newOrder: orderPda(mktPda, newId)[0],
+                tradingBalance: balancePda,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([maker])
+            .rpc();
+        return newId;
+    }
+
+    before(async () => {
+        for (const kp of [maker, seller]) await airdrop(kp.publicKey, 5);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeTradingBalance()
+            .accounts({ owner: maker.publicKey, market: mktPda, tradingBalance: balancePda, systemProgram: SystemProgram.programId })
+            .signers([maker])
+            .rpc();
+        await program.methods
+            .depositBalance(new anchor.BN(1_000_000))
+            .accounts({ owner: maker.publicKey, market: mktPda, tradingBalance: balancePda, systemProgram: SystemProgram.programId })
+            .signers([maker])
+            .rpc();
+    });
+
+    it("Replaces a partially filled bid, moving only the escrow difference", async () => {
+        // 10 @ 10_000, 4 of them filled ⇒ 60_000 of escrow left for 6 units
+        const [bid] = await place(mktPda, maker, { buy: {} }, 10_000, 10, { accounts: { tradingBalance: balancePda } });
+        const [ask] = await place(mktPda, seller, { sell: {} }, 10_000, 4);
+        await matchPair(bid, ask, { bidTradingBalance: balancePda });
+        assert.deepEqual((await program.account.order.fetch(bid)).status, { partiallyFilled: {} });
+
+        const events: string[] = [];
+        const listeners = [
+            program.addEventListener("orderCancelledEvent", () => events.push("cancelled")),
+            program.addEventListener("orderPlacedEvent", () => events.push("placed")),
+        ];
+
+        // 5 @ 11_000 ⇒ 55_000: 5_000 returns to the balance
+        let before = await balanceOf();
+        const newId = await replace(0, 11_000, 5);
+        assert.equal((await balanceOf()) - before, 60_000 - 55_000);
+
+        await sleep(1000);
+        for (const listener of listeners) await program.removeEventListener(listener);
+        assert.sameMembers(events, ["cancelled", "placed"]);
+
+        const old = await program.account.order.fetch(bid);
+        assert.deepEqual(old.status, { cancelled: {} });
+        assert.equal(old.escrowLamports.toNumber(), 0);
+        const replaced = await program.account.order.fetch(orderPda(mktPda, newId)[0]);
+        assert.deepEqual(replaced.side, { buy: {} });
+        assert.deepEqual(replaced.status, { open: {} });
+        assert.equal(replaced.price.toNumber(), 11_000);
+        assert.equal(replaced.escrowLamports.toNumber(), 55_000);
+        assert.isTrue(replaced.fundedFromBalance);
+
+        // 10 @ 12_000 ⇒ 120_000: 65_000 more comes from the balance
+        before = await balanceOf();
+        await replace(newId, 12_000, 10);
+        assert.equal(before - (await balanceOf()), 120_000 - 55_000);
+    });
+
+    it("Keeps the side of a replaced ask", async () => {
+        const [, askId] = await place(mktPda, maker, { sell: {} }, 20_000, 3);
+        const newId = await replace(askId, 19_000, 2);
+        const replaced = await program.account.order.fetch(orderPda(mktPda, newId)[0]);
+        assert.deepEqual(replaced.side, { sell: {} });
+        assert.equal(replaced.escrowLamports.toNumber(), 0);
+    });
+
+    it("Reverts both halves when the new order is rejected", async () => {
+        const [bid, bidId] = await place(mktPda, maker, { buy: {} }, 10_000, 2, { accounts: { tradingBalance: balancePda } });
+        const before = await balanceOf();
+        await expectError(replace(bidId, 0, 2), "InvalidPrice");
+
+        const order = await program.account.order.fetch(bid);
+        assert.deepEqual(order.status, { open: {} });
+        assert.equal(order.escrowLamports.toNumber(), 20_000);
+        assert.equal(await balanceOf(), before);
+    });
+});
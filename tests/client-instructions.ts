@@ -0,0 +1,217 @@
+import * as anchor from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram, TransactionInstruction } from "@solana/web3.js";
+import { assert } from "chai";
+import { execFileSync } from "child_process";
+import {
+    configPda,
+    escrowVaultPda,
+    feeConfigPda,
+    feeVaultPda,
+    listingPda,
+    marketPda,
+    matcherSeatPda,
+    openOrdersPda,
+    orderPda,
+    program,
+    registryPda,
+    tradeHistoryPda,
+    tradingBalancePda,
+    traderSeatPda,
+    userStatsPda,
+} from "./helpers";
+
+// The Rust `client` module (programs/order-matching-engine/src/client.rs)
+// must build the same instructions as the TypeScript client. The example
+// prints its builders' output for fixed keys; each is rebuilt here.
+describe("Rust client instructions", () => {
+    const MARKET_NAME = "CLIENT/MOCK";
+    const REGISTRY_INDEX = 9;
+    const key = (byte: number) => new PublicKey(new Uint8Array(32).fill(byte));
+    const [authority, buyer, seller, matcher, treasury, funder, beneficiary, referrer, bidBook] = [1, 2, 3, 4, 5, 6, 7, 8, 9].map(key);
+    const [mktPda] = marketPda(authority, MARKET_NAME);
+    const [bidPda] = orderPda(mktPda, 7);
+    const [askPda] = orderPda(mktPda, 8);
+    const [rentSubsidyPda] = PublicKey.findProgramAddressSync([Buffer.from("rent_subsidy"), mktPda.toBuffer()], program.programId);
+
+    type Built = {
+        programId: string;
+        data: string;
+        accounts: { pubkey: string; isSigner: boolean; isWritable: boolean }[];
+    };
+    let built: Record<string, Built>;
+
+    before(() => {
+        const out = execFileSync(
+            "cargo",
+            ["run", "-q", "-p", "order-matching-engine", "--features", "client", "--example", "client_instructions"],
+            { encoding: "utf8" }
+        );
+        built = Object.fromEntries(JSON.parse(out).map((ix: Built & { name: string }) => [ix.name, ix]));
+    });
+
+    function assertSame(name: string, expected: TransactionInstruction) {
+        const ix = built[name];
+        assert.equal(ix.programId, expected.programId.toBase58());
+        assert.equal(ix.data, expected.data.toString("hex"), `${name} data`);
+        assert.deepEqual(
+            ix.accounts,
+            expected.keys.map((meta) => ({ pubkey: meta.pubkey.toBase58(), isSigner: meta.isSigner, isWritable: meta.isWritable })),
+            `${name} accounts`
+        );
+    }
+
+    it("initialize_market", async () => {
+        const expected = await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(30), new anchor.BN(10), new anchor.BN(2), new anchor.BN(4), 25, false)
+            .accountsPartial({
+                authority,
+                market: mktPda,
+                feeVault: feeVaultPda(mktPda)[0],
+                quoteMint: null,
+                quoteVault: null,
+                baseMint: null,
+                baseVault: null,
+                tokenProgram: null,
+                config: configPda()[0],
+                registry: registryPda()[0],
+                listing: listingPda(REGISTRY_INDEX)[0],
+                systemProgram: SystemProgram.programId,
+            })
+            .instruction();
+        assertSame("initializeMarket", expected);
+    });
+
+    it("place_order", async () => {
+        const expected = await program.methods
+            .placeOrder({ buy: {} }, new anchor.BN(1_000), new anchor.BN(10), new anchor.BN(7), new anchor.BN(0))
+            .accountsPartial({
+                owner: buyer,
+                market: mktPda,
+                order: bidPda,
+                escrowVault: escrowVaultPda(mktPda, 7)[0],
+                tradingBalance: null,
+                traderSeat: null,
+                userStats: null,
+                openOrders: null,
+                bookSide: bidBook,
+                config: configPda()[0],
+                beneficiary: null,
+                referrer: null,
+                funder: null,
+                rentSubsidyVault: null,
+                quoteVault: null,
+                ownerQuoteAccount: null,
+                baseVault: null,
+                ownerBaseAccount: null,
+                tokenProgram: null,
+                feeConfig: null,
+                treasury: null,
+                feeVault: null,
+                userBalance: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .instruction();
+        assertSame("placeOrder", expected);
+    });
+
+    // Accounts of the bid and ask the example matches, shared by both match instructions
+    const matchAccounts = () => ({
+        matcher,
+        market: mktPda,
+        bidOrder: bidPda,
+        askOrder: askPda,
+        bidEscrow: escrowVaultPda(mktPda, 7)[0],
+        bidOwner: buyer,
+        askOwner: seller,
+        feeConfig: feeConfigPda(mktPda)[0],
+        treasury,
+        feeVault: feeVaultPda(mktPda)[0],
+        bidTradingBalance: tradingBalancePda(mktPda, buyer)[0],
+        bidUserStats: userStatsPda(mktPda, buyer)[0],
+        askUserStats: null,
+        bidOpenOrders: null,
+        askOpenOrders: openOrdersPda(mktPda, seller)[0],
+        bids: bidBook,
+        asks: null,
+        config: configPda()[0],
+        matcherStats: null,
+        matcherSeat: matcherSeatPda(mktPda, matcher)[0],
+        bidSeat: traderSeatPda(mktPda, buyer)[0],
+        askSeat: null,
+        askBeneficiary: beneficiary,
+        bidFunder: funder,
+        askReferrer: referrer,
+        quoteVault: null,
+        tokenProgram: null,
+        sellerQuoteAccount: null,
+        buyerQuoteAccount: null,
+        baseVault: null,
+        buyerBaseAccount: null,
+        fillReceipt: null,
+        tradeHistory: tradeHistoryPda(mktPda)[0],
+        bidUserBalance: null,
+        askUserBalance: null,
+        systemProgram: SystemProgram.programId,
+    });
+
+    it("match_orders, with the optional accounts the orders call for", async () => {
+        const expected = await program.methods
+            .matchOrders(50, new anchor.BN(10), new anchor.BN(10))
+            .accountsPartial(matchAccounts())
+            .instruction();
+        assertSame("matchOrders", expected);
+    });
+
+    it("match_orders_v2, with its fill size guards", async () => {
+        const expected = await program.methods
+            .matchOrdersV2({
+                maxSlippageBps: 50,
+                minExpectedBidRemaining: new anchor.BN(10),
+                minExpectedAskRemaining: new anchor.BN(10),
+                maxFillQuantity: new anchor.BN(6),
+                minFillQuantity: new anchor.BN(1),
+            })
+            .accountsPartial(matchAccounts())
+            .instruction();
+        assertSame("matchOrdersV2", expected);
+    });
+
+    it("cancel_order", async () => {
+        const expected = await program.methods
+            .cancelOrder(new anchor.BN(7), new anchor.BN(3))
+            .accountsPartial({
+                owner: buyer,
+                market: mktPda,
+                order: bidPda,
+                escrowVault: escrowVaultPda(mktPda, 7)[0],
+                tradingBalance: tradingBalancePda(mktPda, buyer)[0],
+                userStats: userStatsPda(mktPda, buyer)[0],
+                openOrders: null,
+                bookSide: bidBook,
+                funder,
+                quoteVault: null,
+                tokenProgram: null,
+                ownerQuoteAccount: null,
+                baseVault: null,
+                ownerBaseAccount: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .instruction();
+        assertSame("cancelOrder", expected);
+    });
+
+    it("close_order, returning subsidized rent to the vault", async () => {
+        const expected = await program.methods
+            .closeOrder(new anchor.BN(8))
+            .accountsPartial({
+                owner: seller,
+                market: mktPda,
+                order: askPda,
+                escrowVault: escrowVaultPda(mktPda, 8)[0],
+                rentSubsidyVault: rentSubsidyPda,
+                systemProgram: SystemProgram.programId,
+            })
+            .instruction();
+        assertSame("closeOrder", expected);
+    });
+});
@@ -0,0 +1,80 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, feeVaultPda, marketPda, matchPair, orderPda, place, program, provider } from "./helpers";
+
+describe("Close market", () => {
+    const MARKET_NAME = "CLOSE/MOCK";
+    const PRICE = 10_000;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [vaultPda] = feeVaultPda(mktPda);
+
+    const openOrders = async () => (await program.account.market.fetch(mktPda)).openOrderCount.toNumber();
+
+    const closeMarket = (signer?: Keypair) => {
+        const call = program.methods
+            .closeMarket()
+            .accounts({ authority: (signer ?? authority).publicKey, market: mktPda, feeVault: vaultPda });
+        return signer ? call.signers([signer]).rpc() : call.rpc();
+    };
+
+    before(async () => {
+        for (const kp of [buyer, seller, stranger]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Counts a partially filled bid until a second fill completes it", async () => {
+        await place(mktPda, buyer, { buy: {} }, PRICE, 3, { orderId: 0 });
+        await place(mktPda, seller, { sell: {} }, PRICE, 1, { orderId: 1 });
+        assert.equal(await openOrders(), 2);
+
+        await matchPair(orderPda(mktPda, 0)[0], orderPda(mktPda, 1)[0]);
+        assert.deepEqual((await program.account.order.fetch(orderPda(mktPda, 0)[0])).status, { partiallyFilled: {} });
+        assert.equal(await openOrders(), 1);
+
+        await place(mktPda, seller, { sell: {} }, PRICE, 2, { orderId: 2 });
+        await matchPair(orderPda(mktPda, 0)[0], orderPda(mktPda, 2)[0]);
+        assert.deepEqual((await program.account.order.fetch(orderPda(mktPda, 0)[0])).status, { filled: {} });
+        assert.equal(await openOrders(), 0);
+    });
+
+    it("Refuses to close while an order is open, or for anyone but the authority", async () => {
+        await place(mktPda, buyer, { buy: {} }, PRICE, 2, { orderId: 3 });
+        await place(mktPda, seller, { sell: {} }, PRICE, 1, { orderId: 4 });
+        await matchPair(orderPda(mktPda, 3)[0], orderPda(mktPda, 4)[0]);
+        assert.equal(await openOrders(), 1);
+
+        await expectError(closeMarket(), "OpenOrdersRemain");
+        await expectError(closeMarket(stranger), "Unauthorized");
+    });
+
+    it("Releases a partially filled bid on cancel, then closes the market", async () => {
+        const [bidPda] = orderPda(mktPda, 3);
+        const before = await provider.connection.getBalance(buyer.publicKey);
+        await program.methods
+            .cancelOrder(new anchor.BN(3), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: bidPda, tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        // Only the unfilled unit's escrow comes back
+        assert.equal((await provider.connection.getBalance(buyer.publicKey)) - before, PRICE);
+        assert.equal(await openOrders(), 0);
+
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.isFalse(mkt.isArchiving);
+        assert.equal(mkt.totalBidVolume.toNumber(), 0);
+        assert.equal(mkt.totalAskVolume.toNumber(), 0);
+
+        await closeMarket();
+        assert.isNull(await provider.connection.getAccountInfo(mktPda));
+        assert.isNull(await provider.connection.getAccountInfo(vaultPda));
+    });
+});
@@ -0,0 +1,172 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { CommitSide, commitmentHash, newSalt } from "../client/commitment";
+import { airdrop, chainTime, commitmentPda, configPda, escrowVaultPda, expectError, marketPda, orderPda, program, provider, sleep } from "./helpers";
+
+describe("Commit–reveal placement", () => {
+    const MARKET_NAME = "SEALED/MOCK";
+    const REVEAL_WINDOW_SECS = 4;
+    const PRICE = 10_000;
+    const QTY = 5;
+    const MAX_NOTIONAL = 80_000;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [cfgPda] = configPda();
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const commitmentOf = (id: number) => commitmentPda(mktPda, buyer.publicKey, id)[0];
+
+    const commit = (id: number, hash: Buffer) =>
+        program.methods
+            .commitOrder(new anchor.BN(id), Array.from(hash), new anchor.BN(MAX_NOTIONAL))
+            .accounts({ owner: buyer.publicKey, market: mktPda, commitment: commitmentOf(id), config: cfgPda, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+
+    const reveal = async (id: number, side: CommitSide, price: number, qty: number, salt: Buffer) => {
+        const { nextOrderId } = await program.account.market.fetch(mktPda);
+        return program.methods
+            .revealOrder(
+                new anchor.BN(id),
+                side === "buy" ? { buy: {} } : { sell: {} },
+                new anchor.BN(price),
+                new anchor.BN(qty),
+                Array.from(salt),
+                nextOrderId,
+                new anchor.BN(0)
+            )
+            .accounts({
+                owner: buyer.publicKey,
+                market: mktPda,
+                commitment: commitmentOf(id),
+                order: orderPda(mktPda, nextOrderId.toNumber())[0],
+                traderSeat: null,
+                userStats: null,
+                config: cfgPda,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([buyer])
+            .rpc();
+    };
+
+    const refund = (id: number) =>
+        program.methods
+            .refundCommitment(new anchor.BN(id))
+            .accounts({ owner: buyer.publicKey, market: mktPda, commitment: commitmentOf(id) })
+            .signers([buyer])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Is off by default", async () => {
+        const salt = newSalt();
+        await expectError(commit(0, commitmentHash("buy", PRICE, QTY, salt, buyer.publicKey)), "CommitRevealDisabled");
+
+        await expectError(
+            program.methods
+                .setCommitReveal(true, new anchor.BN(0))
+                .accounts({ authority: authority.publicKey, market: mktPda, roles: null })
+                .rpc(),
+            "InvalidRevealWindow"
+        );
+        await program.methods
+            .setCommitReveal(true, new anchor.BN(REVEAL_WINDOW_SECS))
+            .accounts({ authority: authority.publicKey, market: mktPda, roles: null })
+            .rpc();
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.isTrue(mkt.commitReveal);
+        assert.equal(mkt.revealWindowSecs.toNumber(), REVEAL_WINDOW_SECS);
+    });
+
+    it("Rejects a reveal that doesn't match the hash", async () => {
+        const salt = newSalt();
+        await commit(0, commitmentHash("buy", PRICE, QTY, salt, buyer.publicKey));
+        await expectError(reveal(0, "buy", PRICE, QTY + 1, salt), "CommitmentMismatch");
+        await expectError(reveal(0, "sell", PRICE, QTY, salt), "CommitmentMismatch");
+        await expectError(reveal(0, "buy", PRICE, QTY, newSalt()), "CommitmentMismatch");
+
+        // The commitment survives a failed reveal
+        const commitment = await program.account.commitment.fetch(commitmentOf(0));
+        assert.equal(commitment.escrowLamports.toNumber(), MAX_NOTIONAL);
+    });
+
+    it("Opens the order from the escrow and it trades normally", async () => {
+        const salt = newSalt();
+        await commit(1, commitmentHash("buy", PRICE, QTY, salt, buyer.publicKey));
+        const walletBefore = await provider.connection.getBalance(buyer.publicKey);
+        const commitmentBalance = await provider.connection.getBalance(commitmentOf(1));
+        const { nextOrderId } = await program.account.market.fetch(mktPda);
+        const [bidPda] = orderPda(mktPda, nextOrderId.toNumber());
+
+        await reveal(1, "buy", PRICE, QTY, salt);
+
+        assert.isNull(await provider.connection.getAccountInfo(commitmentOf(1)));
+        const bid = await program.account.order.fetch(bidPda);
+        assert.ok(bid.owner.equals(buyer.publicKey));
+        assert.equal(bid.escrowLamports.toNumber(), PRICE * QTY);
+        // The order's escrow came from the commitment; the rest of it and its
+        // rent returned to the wallet, which paid the rent of the order and
+        // its escrow vault.
+        const orderBalance = await provider.connection.getBalance(bidPda);
+        const vaultBalance = await provider.connection.getBalance(escrowVaultPda(mktPda, nextOrderId.toNumber())[0]);
+        assert.equal(
+            await provider.connection.getBalance(buyer.publicKey),
+            walletBefore + commitmentBalance - orderBalance - vaultBalance
+        );
+
+        const [askPda] = orderPda(mktPda, nextOrderId.toNumber() + 1);
+        await program.methods
+            .placeOrder({ sell: {} }, new anchor.BN(PRICE), new anchor.BN(QTY), nextOrderId.addn(1), new anchor.BN(0))
+            .accounts({ owner: seller.publicKey, market: mktPda, order: askPda, systemProgram: SystemProgram.programId })
+            .signers([seller])
+            .rpc();
+        const mkt = await program.account.market.fetch(mktPda);
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bidPda,
+                askOrder: askPda,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: mkt.feeRecipient,
+                bidTradingBalance: null,
+            })
+            .rpc();
+        const filled = await program.account.order.fetch(bidPda);
+        assert.deepEqual(filled.status, { filled: {} });
+    });
+
+    it("Rejects a reveal that exceeds the escrowed max notional", async () => {
+        const salt = newSalt();
+        await commit(2, commitmentHash("buy", PRICE, 10, salt, buyer.publicKey));
+        await expectError(reveal(2, "buy", PRICE, 10, salt), "EscrowBoundExceeded");
+    });
+
+    it("Refuses late reveals and refunds expired commitments", async () => {
+        await expectError(refund(2), "CommitmentNotExpired");
+
+        const { revealDeadline } = await program.account.commitment.fetch(commitmentOf(2));
+        while ((await chainTime()) <= revealDeadline.toNumber()) await sleep(500);
+
+        // The window is checked before the preimage
+        await expectError(reveal(2, "buy", PRICE, 10, newSalt()), "RevealWindowClosed");
+
+        const before = await provider.connection.getBalance(buyer.publicKey);
+        const rent = await provider.connection.getBalance(commitmentOf(2));
+        await refund(2);
+        assert.isNull(await provider.connection.getAccountInfo(commitmentOf(2)));
+        assert.isAbove(await provider.connection.getBalance(buyer.publicKey), before + rent - 10_000);
+        assert.isAbove(rent, MAX_NOTIONAL);
+    });
+});
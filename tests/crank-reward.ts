@@ -0,0 +1,164 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram, Transaction } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, feeConfigPda, feeVaultPda, marketPda, matcherStatsPda, orderPda, program, provider, sleep } from "./helpers";
+
+describe("Crank reward", () => {
+    const MARKET_NAME = "CRANK/MOCK";
+    const PRICE = 10_000;
+    const QTY = 10; // fee 1% of 100_000 ⇒ 1_000 into the vault per match
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const matcher = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+    const [vaultPda] = feeVaultPda(mktPda);
+    const [statsPda] = matcherStatsPda(mktPda, matcher.publicKey);
+    const balance = (pk: PublicKey) => provider.connection.getBalance(pk);
+
+    let nextId = 0;
+
+    const setReward = (base: number, perSlot: number, max: number, signer: any = authority) => {
+        const call = program.methods
+            .setCrankReward(new anchor.BN(base), new anchor.BN(perSlot), new anchor.BN(max))
+            .accounts({ authority: signer.publicKey, market: mktPda, roles: null });
+        return signer === authority ? call.rpc() : call.signers([signer]).rpc();
+    };
+
+    const placeIx = (owner: Keypair, side: any, orderId: number, order: PublicKey) =>
+        program.methods
+            .placeOrder(side, new anchor.BN(PRICE), new anchor.BN(QTY), new anchor.BN(orderId), new anchor.BN(0))
+            .accounts({ owner: owner.publicKey, market: mktPda, order, systemProgram: SystemProgram.programId })
+            .instruction();
+
+    const matchIx = (bid: PublicKey, ask: PublicKey) =>
+        program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: matcher.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: feePda,
+                treasury: vaultPda,
+                feeVault: vaultPda,
+                bidTradingBalance: null,
+                matcherStats: statsPda,
+            })
+            .instruction();
+
+    // Place a crossing pair, optionally wait, and match it. The ask is placed
+    // second, so it is the taker whose age sets the reward. Returns the
+    // reward paid, the taker's age and the match's fee-vault delta.
+    async function crossAndMatch(waitMs: number | null) {
+        const [bid] = orderPda(mktPda, nextId);
+        const [ask] = orderPda(mktPda, nextId + 1);
+        const placeBid = await placeIx(buyer, { buy: {} }, nextId, bid);
+        const placeAsk = await placeIx(seller, { sell: {} }, nextId + 1, ask);
+        nextId += 2;
+
+        const matcherBefore = await balance(matcher.publicKey);
+        let sig: string;
+        if (waitMs === null) {
+            // All three in one transaction ⇒ same slot ⇒ age zero
+            const tx = new Transaction().add(placeBid, placeAsk, await matchIx(bid, ask));
+            sig = await provider.sendAndConfirm(tx, [buyer, seller, matcher]);
+        } else {
+            await provider.sendAndConfirm(new Transaction().add(placeBid, placeAsk), [buyer, seller]);
+            await sleep(waitMs);
+            sig = await provider.sendAndConfirm(new Transaction().add(await matchIx(bid, ask)), [matcher]);
+        }
+        const { slot } = await provider.connection.getTransaction(sig, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+        const { placedSlot } = await program.account.order.fetch(ask);
+        return {
+            reward: (await balance(matcher.publicKey)) - matcherBefore,
+            age: slot - placedSlot.toNumber(),
+        };
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller, matcher, stranger]) await airdrop(kp.publicKey, 5);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeFeeConfig(100, vaultPda)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .createMatcherStats()
+            .accounts({ matcher: matcher.publicKey, market: mktPda, matcherStats: statsPda, systemProgram: SystemProgram.programId })
+            .signers([matcher])
+            .rpc();
+    });
+
+    it("Defaults to no reward, and validates who sets it and how", async () => {
+        const { reward } = await crossAndMatch(0);
+        assert.equal(reward, 0);
+
+        await expectError(setReward(100, 0, 0, stranger), "Unauthorized");
+        await expectError(setReward(100, 1, 0), "InvalidCrankReward"); // per-slot rate without a cap
+        await expectError(setReward(100, 1, 99), "InvalidCrankReward"); // cap below the base
+
+        let event: any = null;
+        const listener = program.addEventListener("crankRewardSetEvent", (e) => (event = e));
+        await setReward(100, 0, 0);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+        assert.equal(event.base.toNumber(), 100);
+        assert.equal(event.perSlot.toNumber(), 0);
+
+        // Flat: the base regardless of age
+        assert.equal((await crossAndMatch(800)).reward, 100);
+    });
+
+    it("Pays just the base at age zero", async () => {
+        await setReward(100, 10, 5_000);
+        const { reward, age } = await crossAndMatch(null);
+        assert.equal(age, 0);
+        assert.equal(reward, 100);
+    });
+
+    it("Adds the per-slot rate for every slot the taker rested", async () => {
+        await setReward(100, 10, 5_000);
+        const { reward, age } = await crossAndMatch(1_000);
+        assert.isAbove(age, 0);
+        assert.isBelow(age, 490); // well short of the cap
+        assert.equal(reward, 100 + 10 * age);
+    });
+
+    it("Stops at the cap", async () => {
+        await setReward(100, 1_000, 300);
+        const { reward, age } = await crossAndMatch(1_000);
+        assert.isAbove(age, 0);
+        assert.equal(reward, 300);
+    });
+
+    it("Pays only what market fees the vault holds", async () => {
+        // Far more than the vault has ever collected
+        await setReward(10_000_000, 0, 0);
+        const vaultBefore = await balance(vaultPda);
+        const statsBefore = await program.account.matcherStats.fetch(statsPda);
+
+        const { reward } = await crossAndMatch(0);
+        const vault = await program.account.feeVault.fetch(vaultPda);
+        const rent = await provider.connection.getMinimumBalanceForRentExemption(
+            (await provider.connection.getAccountInfo(vaultPda)).data.length
+        );
+        // Drained down to rent plus the protocol's share, never below
+        assert.equal(await balance(vaultPda), rent + vault.protocolFees.toNumber());
+        assert.equal(reward, vaultBefore + 1_000 - rent - vault.protocolFees.toNumber());
+
+        const stats = await program.account.matcherStats.fetch(statsPda);
+        assert.equal(stats.rewardsEarned.toNumber() - statsBefore.rewardsEarned.toNumber(), reward);
+
+        // An empty vault pays out just the fee the match itself brought in
+        assert.equal((await crossAndMatch(0)).reward, 1_000);
+    });
+});
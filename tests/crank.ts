@@ -0,0 +1,82 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { ChildProcess, execFileSync, spawn } from "child_process";
+import { airdrop, marketPda, orderPda, place, program, provider, sleep } from "./helpers";
+
+describe("Rust crank", () => {
+    const MARKET_NAME = "CRANK/MOCK";
+    const FILL_WITHIN_MS = 20_000;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const fetchOrder = (orderId: number) => program.account.order.fetch(orderPda(mktPda, orderId)[0]);
+
+    let crank: ChildProcess | null = null;
+
+    // Polls until `orderId` is filled or `ms` have passed
+    async function filledWithin(orderId: number, ms: number): Promise<boolean> {
+        const deadline = Date.now() + ms;
+        while (Date.now() < deadline) {
+            if ("filled" in (await fetchOrder(orderId)).status) return true;
+            await sleep(500);
+        }
+        return false;
+    }
+
+    before(async () => {
+        execFileSync("cargo", ["build", "-p", "solamatch-crank"], { stdio: "inherit" });
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+
+        crank = spawn(
+            "target/debug/solamatch-crank",
+            [
+                "--rpc-url", provider.connection.rpcEndpoint,
+                "--keypair", process.env.ANCHOR_WALLET!,
+                "--market", mktPda.toBase58(),
+                "--poll-interval-ms", "1000",
+            ],
+            { stdio: "inherit" }
+        );
+        // Let it finish its first scan and subscribe
+        await sleep(3000);
+    });
+
+    after(() => {
+        crank?.kill();
+    });
+
+    it("Fills a crossing pair", async () => {
+        const [, bid] = await place(mktPda, buyer, { buy: {} }, 1_000, 10);
+        const [, ask] = await place(mktPda, seller, { sell: {} }, 990, 10);
+
+        assert.isTrue(await filledWithin(bid, FILL_WITHIN_MS), "crank didn't fill the bid in time");
+        assert.deepEqual((await fetchOrder(ask)).status, { filled: {} });
+        const market = await program.account.market.fetch(mktPda);
+        assert.equal(market.lastTradePrice.toNumber(), 990);
+        assert.equal(market.openOrderCount.toNumber(), 0);
+    });
+
+    it("Sweeps one bid across several asks", async () => {
+        const [, ask1] = await place(mktPda, seller, { sell: {} }, 1_000, 4);
+        const [, ask2] = await place(mktPda, seller, { sell: {} }, 1_010, 4);
+        const [, bid] = await place(mktPda, buyer, { buy: {} }, 1_010, 8);
+
+        assert.isTrue(await filledWithin(bid, FILL_WITHIN_MS), "crank didn't fill the bid in time");
+        for (const ask of [ask1, ask2]) assert.deepEqual((await fetchOrder(ask)).status, { filled: {} });
+    });
+
+    it("Leaves orders that don't cross on the book", async () => {
+        const [, bid] = await place(mktPda, buyer, { buy: {} }, 900, 5);
+        const [, ask] = await place(mktPda, seller, { sell: {} }, 1_100, 5);
+        await sleep(3000);
+        assert.deepEqual((await fetchOrder(bid)).status, { open: {} });
+        assert.deepEqual((await fetchOrder(ask)).status, { open: {} });
+    });
+});
@@ -0,0 +1,195 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, place, program, provider, userBalancePda } from "./helpers";
+
+describe("Deferred settlement (UserBalance / claim_funds)", () => {
+    const MARKET_NAME = "DEFER/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const destination = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [buyerBalance] = userBalancePda(mktPda, buyer.publicKey);
+    const [sellerBalance] = userBalancePda(mktPda, seller.publicKey);
+    const balance = (pk: PublicKey) => provider.connection.getBalance(pk);
+    const pending = async (pk: PublicKey) =>
+        (await program.account.userBalance.fetch(pk)).pendingLamports.toNumber();
+
+    const match = (
+        bid: PublicKey,
+        ask: PublicKey,
+        bidUserBalance: PublicKey | null,
+        askUserBalance: PublicKey | null
+    ) =>
+        program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+                bidTradingBalance: null,
+                bidUserBalance,
+                askUserBalance,
+            })
+            .rpc();
+
+    const claim = (owner: Keypair, userBalance: PublicKey, to: PublicKey) =>
+        program.methods
+            .claimFunds()
+            .accounts({ owner: owner.publicKey, market: mktPda, userBalance, destination: to })
+            .signers([owner])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        for (const [owner, userBalance] of [[buyer, buyerBalance], [seller, sellerBalance]] as const) {
+            await program.methods
+                .initializeUserBalance()
+                .accounts({ owner: owner.publicKey, market: mktPda, userBalance, systemProgram: SystemProgram.programId })
+                .signers([owner])
+                .rpc();
+        }
+    });
+
+    it("Pays wallets directly by default", async () => {
+        const [bid] = await place(mktPda, buyer, { buy: {} }, 10_000, 2);
+        const [ask] = await place(mktPda, seller, { sell: {} }, 10_000, 2);
+        assert.isFalse((await program.account.order.fetch(ask)).settlesToBalance);
+        const sellerBefore = await balance(seller.publicKey);
+
+        await match(bid, ask, null, null);
+        assert.equal((await balance(seller.publicKey)) - sellerBefore, 20_000);
+        assert.equal(await pending(sellerBalance), 0);
+    });
+
+    it("Credits a deferred order's proceeds and refund to the owners' UserBalances", async () => {
+        const [bid] = await place(mktPda, buyer, { buy: {} }, 12_000, 3, { accounts: { userBalance: buyerBalance } });
+        const [ask] = await place(mktPda, seller, { sell: {} }, 10_000, 3, { accounts: { userBalance: sellerBalance } });
+        assert.isTrue((await program.account.order.fetch(bid)).settlesToBalance);
+        await expectError(match(bid, ask, null, sellerBalance), "UserBalanceRequired");
+        await expectError(match(bid, ask, buyerBalance, null), "UserBalanceRequired");
+
+        const sellerBefore = await balance(seller.publicKey);
+        const buyerBefore = await balance(buyer.publicKey);
+        const accountBefore = await balance(sellerBalance);
+        await match(bid, ask, buyerBalance, sellerBalance);
+
+        // Nothing reaches the wallets; the lamports sit in the PDAs
+        assert.equal(await balance(seller.publicKey), sellerBefore);
+        assert.equal(await balance(buyer.publicKey), buyerBefore);
+        assert.equal(await pending(sellerBalance), 30_000);
+        assert.equal(await pending(buyerBalance), 3 * 2_000);
+        assert.equal((await balance(sellerBalance)) - accountBefore, 30_000);
+    });
+
+    it("Claims everything pending to any destination, once", async () => {
+        await claim(seller, sellerBalance, destination.publicKey);
+        assert.equal(await balance(destination.publicKey), 30_000);
+        assert.equal(await pending(sellerBalance), 0);
+
+        await expectError(claim(seller, sellerBalance, destination.publicKey), "NothingToClaim");
+        await expectError(claim(seller, sellerBalance, sellerBalance), "InvalidClaimDestination");
+    });
+
+    it("Only lets the owner claim", async () => {
+        // The PDA is derived from the signer, so a stranger's claim targets
+        // their own (non-existent) balance
+        const stranger = Keypair.generate();
+        await airdrop(stranger.publicKey, 1);
+        await expectError(claim(stranger, buyerBalance, stranger.publicKey), "ConstraintSeeds");
+        assert.equal(await pending(buyerBalance), 6_000);
+    });
+
+    it("Rejects deferred orders with a beneficiary", async () => {
+        const id = (await program.account.market.fetch(mktPda)).nextOrderId.toNumber();
+        await expectError(
+            program.methods
+                .placeOrder({ sell: {} }, new anchor.BN(10_000), new anchor.BN(1), new anchor.BN(id), new anchor.BN(0))
+                .accounts({
+                    owner: seller.publicKey,
+                    market: mktPda,
+                    order: orderPda(mktPda, id)[0],
+                    beneficiary: destination.publicKey,
+                    userBalance: sellerBalance,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([seller])
+                .rpc(),
+            "DeferredSettlementUnsupported"
+        );
+    });
+
+    it("Can't be given a beneficiary later either", async () => {
+        const [ask] = await place(mktPda, seller, { sell: {} }, 10_000, 1, { accounts: { userBalance: sellerBalance } });
+        const { orderId } = await program.account.order.fetch(ask);
+        await expectError(
+            program.methods
+                .setBeneficiary(orderId, destination.publicKey)
+                .accounts({ owner: seller.publicKey, market: mktPda, order: ask })
+                .signers([seller])
+                .rpc(),
+            "DeferredSettlementUnsupported"
+        );
+    });
+
+    it("Carries the flag through split_order and cancel_and_replace", async () => {
+        const [ask] = await place(mktPda, seller, { sell: {} }, 10_000, 4, { accounts: { userBalance: sellerBalance } });
+        const { orderId } = await program.account.order.fetch(ask);
+        const sliceId = (await program.account.market.fetch(mktPda)).nextOrderId;
+        await program.methods
+            .splitOrder(orderId, sliceId, new anchor.BN(2), new anchor.BN(0))
+            .accounts({
+                owner: seller.publicKey,
+                market: mktPda,
+                order: ask,
+                newOrder: orderPda(mktPda, sliceId.toNumber())[0],
+                userStats: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([seller])
+            .rpc();
+        assert.isTrue((await program.account.order.fetch(orderPda(mktPda, sliceId.toNumber())[0])).settlesToBalance);
+
+        const replacementId = sliceId.toNumber() + 1;
+        await program.methods
+            .cancelAndReplace(orderId, new anchor.BN(11_000), new anchor.BN(2), new anchor.BN(replacementId))
+            .accounts({
+                owner: seller.publicKey,
+                market: mktPda,
+                oldOrder: ask,
+                newOrder: orderPda(mktPda, replacementId)[0],
+                tradingBalance: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([seller])
+            .rpc();
+        assert.isTrue((await program.account.order.fetch(orderPda(mktPda, replacementId)[0])).settlesToBalance);
+    });
+
+    it("Only merges orders that settle the same way", async () => {
+        const [deferred] = await place(mktPda, seller, { sell: {} }, 15_000, 1, { accounts: { userBalance: sellerBalance } });
+        const [direct] = await place(mktPda, seller, { sell: {} }, 15_000, 1);
+        await expectError(
+            program.methods
+                .mergeOrders(
+                    (await program.account.order.fetch(deferred)).orderId,
+                    (await program.account.order.fetch(direct)).orderId
+                )
+                .accounts({ owner: seller.publicKey, market: mktPda, survivor: deferred, absorbed: direct, userStats: null })
+                .signers([seller])
+                .rpc(),
+            "OrdersNotMergeable"
+        );
+    });
+});
@@ -0,0 +1,95 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, program, provider, sleep } from "./helpers";
+
+describe("place_dual_order", () => {
+    const MARKET_NAME = "DUAL/MOCK";
+    const authority = provider.wallet;
+    const maker = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const fetchMarket = () => program.account.market.fetch(mktPda);
+
+    const placeDual = (bidPrice: number, bidQty: number, askPrice: number, askQty: number, bidId: number, askId: number) =>
+        program.methods
+            .placeDualOrder(
+                new anchor.BN(bidPrice),
+                new anchor.BN(bidQty),
+                new anchor.BN(askPrice),
+                new anchor.BN(askQty),
+                new anchor.BN(bidId),
+                new anchor.BN(askId)
+            )
+            .accounts({
+                owner: maker.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, bidId)[0],
+                askOrder: orderPda(mktPda, askId)[0],
+                tradingBalance: null,
+            })
+            .signers([maker])
+            .rpc();
+
+    before(async () => {
+        await airdrop(maker.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Places both sides in one instruction", async () => {
+        const { nextOrderId } = await fetchMarket();
+        const id = nextOrderId.toNumber();
+
+        const placed: any[] = [];
+        const listener = program.addEventListener("orderPlacedEvent", (e) => placed.push(e));
+        await placeDual(990, 10, 1_010, 7, id, id + 1);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        const bid = await program.account.order.fetch(orderPda(mktPda, id)[0]);
+        const ask = await program.account.order.fetch(orderPda(mktPda, id + 1)[0]);
+        assert.deepEqual(bid.side, { buy: {} });
+        assert.deepEqual(ask.side, { sell: {} });
+        assert.equal(bid.escrowLamports.toNumber(), 9_900);
+        assert.equal(ask.escrowLamports.toNumber(), 0);
+        assert.ok(bid.owner.equals(maker.publicKey) && ask.owner.equals(maker.publicKey));
+
+        const market = await fetchMarket();
+        assert.equal(market.nextOrderId.toNumber(), id + 2);
+        assert.equal(market.totalBidVolume.toNumber(), 10);
+        assert.equal(market.totalAskVolume.toNumber(), 7);
+        assert.equal(market.openOrderCount.toNumber(), 2);
+
+        assert.deepEqual(placed.map((e) => e.orderId.toNumber()), [id, id + 1]);
+    });
+
+    it("Rejects a self-crossing quote", async () => {
+        const id = (await fetchMarket()).nextOrderId.toNumber();
+        await expectError(placeDual(1_000, 1, 1_000, 1, id, id + 1), "CrossedQuote");
+        await expectError(placeDual(1_001, 1, 1_000, 1, id, id + 1), "CrossedQuote");
+    });
+
+    it("Reverts the bid when the ask can't be placed", async () => {
+        const before = await fetchMarket();
+        const id = before.nextOrderId.toNumber();
+
+        // The ask's PDA initializes, but its id skips one
+        await expectError(placeDual(990, 5, 1_010, 5, id, id + 2), "InvalidOrderId");
+        // Both PDAs the same: the second init fails
+        try {
+            await placeDual(990, 5, 1_010, 5, id, id);
+            assert.fail("Expected the second init to fail");
+        } catch (err: any) {
+            assert.notInclude(err.message, "Expected the second init to fail");
+        }
+
+        assert.isNull(await provider.connection.getAccountInfo(orderPda(mktPda, id)[0]));
+        const after = await fetchMarket();
+        assert.equal(after.nextOrderId.toNumber(), id);
+        assert.equal(after.totalBidVolume.toNumber(), before.totalBidVolume.toNumber());
+        assert.equal(after.openOrderCount.toNumber(), before.openOrderCount.toNumber());
+    });
+});
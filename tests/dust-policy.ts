@@ -0,0 +1,120 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, escrowVaultPda, feeConfigPda, marketPda, place, program, provider } from "./helpers";
+
+// Deterministic PRNG so a failing combination can be replayed.
+function mulberry32(seed: number) {
+    return () => {
+        seed = (seed + 0x6d2b79f5) | 0;
+        let t = Math.imul(seed ^ (seed >>> 15), 1 | seed);
+        t = (t + Math.imul(t ^ (t >>> 7), 61 | t)) ^ t;
+        return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+    };
+}
+
+describe("Rounding and dust policy", () => {
+    const MARKET_NAME = "DUST/MOCK";
+    const ROUNDS = 12;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const treasury = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+    const rand = mulberry32(0x5eed);
+    const between = (lo: number, hi: number) => lo + Math.floor(rand() * (hi - lo + 1));
+
+    const balance = (pk: PublicKey) => provider.connection.getBalance(pk);
+
+    before(async () => {
+        await airdrop(buyer.publicKey, 50);
+        await airdrop(seller.publicKey, 5);
+        await airdrop(treasury.publicKey, 1);
+
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeFeeConfig(0, treasury.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Cumulative dust equals escrow in minus payouts out, exactly", async () => {
+        let escrowIn = 0;
+        let payoutsOut = 0;
+        let expectedDust = 0;
+        let nextId = 0;
+
+        for (let round = 0; round < ROUNDS; round++) {
+            const feeBps = between(1, 500);
+            const askPrice = between(1, 1_000_003);
+            const bidPrice = askPrice + between(0, 997);
+            const bidQty = between(1, 9);
+            const askQty = between(1, 9);
+
+            await program.methods
+                .updateFeeConfig(feeBps, treasury.publicKey)
+                .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda })
+                .rpc();
+
+            const [bid] = await place(mktPda, buyer, { buy: {} }, bidPrice, bidQty, { orderId: nextId++ });
+            const [ask] = await place(mktPda, seller, { sell: {} }, askPrice, askQty, { orderId: nextId++ });
+            escrowIn += (await program.account.order.fetch(bid)).escrowLamports.toNumber();
+
+            const feesBefore = (await program.account.feeConfig.fetch(feePda)).accumulatedFees.toNumber();
+            const sellerBefore = await balance(seller.publicKey);
+            const buyerBefore = await balance(buyer.publicKey);
+            const treasuryBefore = await balance(treasury.publicKey);
+
+            await program.methods
+                .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+                .accounts({
+                    matcher: authority.publicKey,
+                    market: mktPda,
+                    bidOrder: bid,
+                    askOrder: ask,
+                    bidOwner: buyer.publicKey,
+                    askOwner: seller.publicKey,
+                    feeConfig: feePda,
+                    treasury: treasury.publicKey,
+                    bidTradingBalance: null,
+                })
+                .rpc();
+
+            const fees = (await program.account.feeConfig.fetch(feePda)).accumulatedFees.toNumber() - feesBefore;
+            payoutsOut += (await balance(seller.publicKey)) - sellerBefore;
+            payoutsOut += (await balance(buyer.publicKey)) - buyerBefore;
+            payoutsOut += fees;
+
+            // Independent model of the policy: exact fee fractional ⇒ 1 lamport of dust
+            const fill = Math.min(bidQty, askQty);
+            const gross = BigInt(askPrice) * BigInt(fill);
+            const dust = (gross * BigInt(feeBps)) % 10_000n === 0n ? 0 : 1;
+            expectedDust += dust;
+            assert.equal(fees, Number((gross * BigInt(feeBps)) / 10_000n), `round ${round}: fee`);
+            assert.equal((await balance(treasury.publicKey)) - treasuryBefore, fees + dust, `round ${round}: treasury`);
+
+            // Release the residual bid escrow, if any
+            const bidAfter = await program.account.order.fetch(bid);
+            if ("partiallyFilled" in bidAfter.status) {
+                const [bidVault] = escrowVaultPda(mktPda, bidAfter.orderId.toNumber());
+                const vaultBefore = await balance(bidVault);
+                await program.methods
+                    .cancelOrder(bidAfter.orderId, new anchor.BN(0))
+                    .accounts({ owner: buyer.publicKey, market: mktPda, order: bid, tradingBalance: null, systemProgram: SystemProgram.programId })
+                    .signers([buyer])
+                    .rpc();
+                payoutsOut += vaultBefore - (await balance(bidVault));
+            }
+            assert.equal((await program.account.order.fetch(bid)).escrowLamports.toNumber(), 0, `round ${round}: escrow left`);
+        }
+
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.dustLamports.toNumber(), expectedDust);
+        assert.equal(escrowIn - payoutsOut, mkt.dustLamports.toNumber());
+    });
+});
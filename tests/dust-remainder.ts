@@ -0,0 +1,101 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, marketPda, orderPda, place, program, provider, sleep } from "./helpers";
+
+describe("Dust remainders", () => {
+    const MARKET_NAME = "DUST/MOCK";
+    const MIN_QTY = 5;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const fetchMarket = () => program.account.market.fetch(mktPda);
+    const fetchOrder = (orderId: number) => program.account.order.fetch(orderPda(mktPda, orderId)[0]);
+
+    const match = (bidId: number, askId: number) =>
+        program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, bidId)[0],
+                askOrder: orderPda(mktPda, askId)[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+            })
+            .rpc();
+
+    async function matchWithEvent(bidId: number, askId: number): Promise<any> {
+        let event: any = null;
+        const listener = program.addEventListener("tradeExecutedEvent", (e) => (event = e));
+        await match(bidId, askId);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+        return event;
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(MIN_QTY), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Closes a bid's dust remainder and refunds its escrow with the improvement", async () => {
+        // Bid 10 @ 1_000 vs ask 7 @ 900 leaves 3 < MIN_QTY on the bid
+        await place(mktPda, buyer, { buy: {} }, 1_000, 10, { orderId: 0 });
+        await place(mktPda, seller, { sell: {} }, 900, 7, { orderId: 1 });
+        const before = await provider.connection.getBalance(buyer.publicKey);
+
+        const event = await matchWithEvent(0, 1);
+
+        const bid = await fetchOrder(0);
+        assert.deepEqual(bid.status, { filled: {} });
+        assert.equal(bid.filledQuantity.toNumber(), 7);
+        assert.equal(bid.escrowLamports.toNumber(), 0);
+        // 7 × 100 improvement + 3 × 1_000 residual escrow
+        assert.equal((await provider.connection.getBalance(buyer.publicKey)) - before, 3_700);
+
+        const mkt = await fetchMarket();
+        assert.equal(mkt.totalBidVolume.toNumber(), 0);
+        assert.equal(mkt.totalAskVolume.toNumber(), 0);
+        assert.equal(mkt.openOrderCount.toNumber(), 0);
+        // Only the traded units count as volume
+        assert.equal(mkt.cumulativeBaseVolume.toNumber(), 7);
+
+        assert.equal(event.fillQuantity.toNumber(), 7);
+        assert.equal(event.bidResidual.toNumber(), 3);
+        assert.equal(event.askResidual.toNumber(), 0);
+        assert.equal(event.residualRefund.toNumber(), 3_000);
+    });
+
+    it("Closes an ask's dust remainder", async () => {
+        await place(mktPda, buyer, { buy: {} }, 1_000, 6, { orderId: 2 });
+        await place(mktPda, seller, { sell: {} }, 1_000, 8, { orderId: 3 });
+
+        const event = await matchWithEvent(2, 3);
+
+        assert.deepEqual((await fetchOrder(2)).status, { filled: {} });
+        assert.deepEqual((await fetchOrder(3)).status, { filled: {} });
+        assert.equal((await fetchMarket()).totalAskVolume.toNumber(), 0);
+        assert.equal(event.bidResidual.toNumber(), 0);
+        assert.equal(event.askResidual.toNumber(), 2);
+        assert.equal(event.residualRefund.toNumber(), 0);
+    });
+
+    it("Leaves a remainder at the minimum on the book", async () => {
+        await place(mktPda, buyer, { buy: {} }, 1_000, 10, { orderId: 4 });
+        await place(mktPda, seller, { sell: {} }, 1_000, MIN_QTY, { orderId: 5 });
+        await match(4, 5);
+
+        const bid = await fetchOrder(4);
+        assert.deepEqual(bid.status, { partiallyFilled: {} });
+        assert.equal(bid.escrowLamports.toNumber(), MIN_QTY * 1_000);
+        assert.equal((await fetchMarket()).totalBidVolume.toNumber(), MIN_QTY);
+    });
+});
@@ -0,0 +1,117 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { createHash } from "crypto";
+import { airdrop, configPda, escrowVaultPda, expectError, marketPda, orderPda, program, provider, sleep } from "./helpers";
+
+describe("Emergency cancel", () => {
+    const MARKET_NAME = "EMERG/MOCK";
+    const PRICE = 10_000;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [cfgPda] = configPda();
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [bidPda] = orderPda(mktPda, 0);
+
+    // tests/fixtures/orphan-order.json (loaded by the validator, see Anchor.toml):
+    // order #0, BUY 5 @ 1_000 with 5_000 escrowed in its vault
+    // (tests/fixtures/orphan-escrow.json), owned by the seed-[7; 32] keypair,
+    // on a market account that never existed.
+    const orphanOwner = Keypair.fromSeed(new Uint8Array(32).fill(7));
+    const missingMarket = new PublicKey(createHash("sha256").update("solamatch:missing-market").digest());
+    const [orphanPda] = orderPda(missingMarket, 0);
+    const [orphanVault] = escrowVaultPda(missingMarket, 0);
+    const ORPHAN_ESCROW = 5_000;
+
+    const emergencyCancel = (owner: Keypair, market: PublicKey, order: PublicKey, admin: Keypair | null) => {
+        return program.methods
+            .emergencyCancel(new anchor.BN(0))
+            .accounts({
+                owner: owner.publicKey,
+                market,
+                order,
+                escrowVault: escrowVaultPda(market, 0)[0],
+                config: cfgPda,
+                admin: admin ? admin.publicKey : null,
+            })
+            .signers(admin ? [owner, admin] : [owner])
+            .rpc();
+    };
+
+    before(async () => {
+        for (const kp of [buyer, stranger, orphanOwner]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .placeOrder({ buy: {} }, new anchor.BN(PRICE), new anchor.BN(3), new anchor.BN(0), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: bidPda, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+    });
+
+    it("Refunds an order whose market account is missing", async () => {
+        assert.isNull(await provider.connection.getAccountInfo(missingMarket));
+        const before = await provider.connection.getBalance(orphanVault);
+
+        await emergencyCancel(orphanOwner, missingMarket, orphanPda, null);
+
+        assert.equal(before - (await provider.connection.getBalance(orphanVault)), ORPHAN_ESCROW);
+        const order = await program.account.order.fetch(orphanPda);
+        assert.deepEqual(order.status, { cancelled: {} });
+        assert.equal(order.escrowLamports.toNumber(), 0);
+        await expectError(emergencyCancel(orphanOwner, missingMarket, orphanPda, null), "OrderNotActive");
+    });
+
+    it("Is refused on a healthy market without protocol-admin approval", async () => {
+        await expectError(emergencyCancel(buyer, mktPda, bidPda, null), "EmergencyCancelNotAllowed");
+        await expectError(emergencyCancel(buyer, mktPda, bidPda, stranger), "Unauthorized");
+    });
+
+    it("Only the order owner can use it", async () => {
+        await expectError(
+            program.methods
+                .emergencyCancel(new anchor.BN(0))
+                .accounts({
+                    owner: stranger.publicKey,
+                    market: mktPda,
+                    order: bidPda,
+                    escrowVault: escrowVaultPda(mktPda, 0)[0],
+                    config: cfgPda,
+                    admin: null,
+                })
+                .signers([stranger])
+                .rpc(),
+            "Unauthorized"
+        );
+    });
+
+    it("Refunds with protocol-admin approval, skipping market accounting", async () => {
+        const admin = (provider.wallet as anchor.Wallet).payer;
+        const mktBefore = await program.account.market.fetch(mktPda);
+        const [bidVault] = escrowVaultPda(mktPda, 0);
+        const escrowBefore = await provider.connection.getBalance(bidVault);
+
+        let event: any = null;
+        const listener = program.addEventListener("emergencyCancelEvent", (e) => (event = e));
+        await emergencyCancel(buyer, mktPda, bidPda, admin);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        assert.equal(escrowBefore - (await provider.connection.getBalance(bidVault)), PRICE * 3);
+        const order = await program.account.order.fetch(bidPda);
+        assert.deepEqual(order.status, { cancelled: {} });
+
+        // Volumes and counts are left for reconciliation; the event still
+        // takes the market's next sequence number
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.totalBidVolume.toNumber(), mktBefore.totalBidVolume.toNumber());
+        assert.equal(mkt.openOrderCount.toNumber(), mktBefore.openOrderCount.toNumber());
+        assert.equal(mkt.eventSeq.toNumber(), mktBefore.eventSeq.toNumber() + 1);
+        assert.equal(event.eventSeq.toNumber(), mkt.eventSeq.toNumber());
+        assert.isFalse(event.marketMissing);
+    });
+});
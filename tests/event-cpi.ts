@@ -0,0 +1,90 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { CpiEvent, parseCpiEvents } from "../client/cpiEvents";
+import { airdrop, marketPda, orderPda, program, provider } from "./helpers";
+
+describe("Events via self-CPI", () => {
+    const MARKET_NAME = "CPIEV/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const coder = program.coder as anchor.BorshCoder;
+    const parser = new anchor.EventParser(program.programId, coder);
+
+    // The CPI events of a confirmed transaction, checked against its log events
+    async function eventsOf(sig: string): Promise<CpiEvent[]> {
+        const tx = await provider.connection.getTransaction(sig, {
+            commitment: "confirmed",
+            maxSupportedTransactionVersion: 0,
+        });
+        const viaCpi = parseCpiEvents(coder, program.programId, tx);
+        const logged = Array.from(parser.parseLogs(tx.meta.logMessages));
+        assert.deepEqual(viaCpi.map((e) => e.name), logged.map((e) => e.name));
+        viaCpi.forEach((event, i) => assert.equal(JSON.stringify(event.data), JSON.stringify(logged[i].data)));
+        return viaCpi;
+    }
+
+    const place = (owner: Keypair, side: any, orderId: number) =>
+        program.methods
+            .placeOrder(side, new anchor.BN(1_000), new anchor.BN(5), new anchor.BN(orderId), new anchor.BN(0))
+            .accounts({ owner: owner.publicKey, market: mktPda, order: orderPda(mktPda, orderId)[0], systemProgram: SystemProgram.programId })
+            .signers([owner])
+            .rpc({ commitment: "confirmed" });
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Emits OrderPlacedEvent through the event authority", async () => {
+        const [placed] = await eventsOf(await place(buyer, { buy: {} }, 0));
+        assert.equal(placed.name, "OrderPlacedEvent");
+        assert.equal(placed.data.orderId.toNumber(), 0);
+        await place(seller, { sell: {} }, 1);
+    });
+
+    it("Emits TradeExecutedEvent through the event authority", async () => {
+        const sig = await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, 0)[0],
+                askOrder: orderPda(mktPda, 1)[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+            })
+            .rpc({ commitment: "confirmed" });
+        const [trade] = await eventsOf(sig);
+        assert.equal(trade.name, "TradeExecutedEvent");
+        assert.equal(trade.data.fillQuantity.toNumber(), 5);
+    });
+
+    it("Emits OrderCancelledEvent and OrderClosedEvent through the event authority", async () => {
+        await place(buyer, { buy: {} }, 2);
+        const cancelSig = await program.methods
+            .cancelOrder(new anchor.BN(2), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: orderPda(mktPda, 2)[0], tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc({ commitment: "confirmed" });
+        const [cancelled] = await eventsOf(cancelSig);
+        assert.equal(cancelled.name, "OrderCancelledEvent");
+        assert.equal(cancelled.data.remainingQuantity.toNumber(), 5);
+
+        const closeSig = await program.methods
+            .closeOrder(new anchor.BN(2))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: orderPda(mktPda, 2)[0], systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc({ commitment: "confirmed" });
+        const [closed] = await eventsOf(closeSig);
+        assert.equal(closed.name, "OrderClosedEvent");
+    });
+});
@@ -0,0 +1,110 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { ChainEvent, CHAINED_EVENTS, verifyStateHashChain } from "../client/stateHash";
+import { airdrop, marketPda, orderPda, program, provider, traderSeatPda } from "./helpers";
+
+describe("Event sequence numbers", () => {
+    const MARKET_NAME = "SEQ/MOCK";
+    const authority = provider.wallet;
+    const seller = Keypair.generate();
+    const trader = Keypair.generate();
+    const payout = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [ask] = orderPda(mktPda, 0);
+    const coder = program.coder as anchor.BorshCoder;
+    const parser = new anchor.EventParser(program.programId, coder);
+
+    // Every event the program logged, chained or not
+    const events: ChainEvent[] = [];
+
+    async function record(sig: string) {
+        const tx = await provider.connection.getTransaction(sig, {
+            commitment: "confirmed",
+            maxSupportedTransactionVersion: 0,
+        });
+        events.push(...parser.parseLogs(tx.meta.logMessages));
+    }
+
+    before(async () => {
+        await airdrop(seller.publicKey, 2);
+
+        // Events that used to be emitted outside the chain, between chained ones
+        await record(
+            await program.methods
+                .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+                .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+                .rpc({ commitment: "confirmed" })
+        );
+        await record(
+            await program.methods
+                .addTrader(trader.publicKey)
+                .accounts({
+                    authority: authority.publicKey,
+                    market: mktPda,
+                    traderSeat: traderSeatPda(mktPda, trader.publicKey)[0],
+                    systemProgram: SystemProgram.programId,
+                })
+                .rpc({ commitment: "confirmed" })
+        );
+        await record(
+            await program.methods
+                .placeOrder({ sell: {} }, new anchor.BN(1_000), new anchor.BN(5), new anchor.BN(0), new anchor.BN(0))
+                .accounts({ owner: seller.publicKey, market: mktPda, order: ask, systemProgram: SystemProgram.programId })
+                .signers([seller])
+                .rpc({ commitment: "confirmed" })
+        );
+        await record(
+            await program.methods
+                .setBeneficiary(new anchor.BN(0), payout.publicKey)
+                .accounts({ owner: seller.publicKey, market: mktPda, order: ask })
+                .signers([seller])
+                .rpc({ commitment: "confirmed" })
+        );
+        await record(
+            await program.methods
+                .cancelOrder(new anchor.BN(0), new anchor.BN(0))
+                .accounts({ owner: seller.publicKey, market: mktPda, order: ask, tradingBalance: null, systemProgram: SystemProgram.programId })
+                .signers([seller])
+                .rpc({ commitment: "confirmed" })
+        );
+        await record(
+            await program.methods
+                .closeOrder(new anchor.BN(0))
+                .accounts({ owner: seller.publicKey, market: mktPda, order: ask, rentSubsidyVault: null, systemProgram: SystemProgram.programId })
+                .signers([seller])
+                .rpc({ commitment: "confirmed" })
+        );
+    });
+
+    it("Numbers every event of the market without gaps", async () => {
+        assert.deepEqual(events.map((e) => e.name), [
+            "MarketInitializedEvent",
+            "TraderSeatGrantedEvent",
+            "OrderPlacedEvent",
+            "BeneficiarySetEvent",
+            "OrderCancelledEvent",
+            "OrderClosedEvent",
+        ]);
+        for (const event of events) {
+            assert.isTrue(CHAINED_EVENTS.has(event.name), event.name);
+            assert.ok(event.data.market.equals(mktPda));
+        }
+        events.forEach((e, i) => assert.equal(e.data.eventSeq.toNumber(), i + 1));
+        assert.equal((await program.account.market.fetch(mktPda)).eventSeq.toNumber(), events.length);
+    });
+
+    it("Chains them into the market's state hash", async () => {
+        const result = verifyStateHashChain(coder, events, await program.account.market.fetch(mktPda));
+        assert.isTrue(result.ok, result.reason);
+    });
+
+    it("Shows a dropped event as a gap", async () => {
+        const withGap = events.filter((e) => e.name !== "BeneficiarySetEvent");
+        const result = verifyStateHashChain(coder, withGap, await program.account.market.fetch(mktPda));
+        assert.isFalse(result.ok);
+        assert.equal(result.reason, "sequence gap");
+        assert.equal(withGap[result.failedAt].data.eventSeq.toNumber() - result.eventSeq, 1);
+    });
+});
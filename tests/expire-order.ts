@@ -0,0 +1,71 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, chainTime, expectError, marketPda, matchPair, orderPda, place, program, provider, sleep } from "./helpers";
+
+describe("expire_order", () => {
+    const MARKET_NAME = "EXPIRE/MOCK";
+    const PRICE = 10_000;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const crank = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+
+    const expire = (orderId: number, owner: PublicKey) =>
+        program.methods
+            .expireOrder(new anchor.BN(orderId))
+            .accounts({ caller: crank.publicKey, market: mktPda, order: orderPda(mktPda, orderId)[0], owner, roles: null })
+            .signers([crank])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller, crank]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Matches up to expiry and rejects the match after it", async () => {
+        const expiresAt = (await chainTime()) + 4;
+        const [bid, bidId] = await place(mktPda, buyer, { buy: {} }, PRICE, 4, { expiresAt });
+        const [ask1] = await place(mktPda, seller, { sell: {} }, PRICE, 1);
+        const [ask2] = await place(mktPda, seller, { sell: {} }, PRICE, 1);
+
+        // Before expiry: fills
+        assert.isBelow(await chainTime(), expiresAt);
+        await matchPair(bid, ask1);
+        assert.equal((await program.account.order.fetch(bid)).filledQuantity.toNumber(), 1);
+
+        // Not expired yet, so nobody but the owner can cancel it
+        await expectError(expire(bidId, buyer.publicKey), "OrderNotExpired");
+
+        // After expiry: the same pair no longer matches
+        while ((await chainTime()) <= expiresAt) await sleep(1000);
+        await expectError(matchPair(bid, ask2), "OrderExpired");
+    });
+
+    it("Lets anyone cancel an expired order with cancel_order's refund", async () => {
+        const bidId = 0; // the expired bid above, 3 units unfilled
+        const [bid] = orderPda(mktPda, bidId);
+        const escrow = (await program.account.order.fetch(bid)).escrowLamports.toNumber();
+        assert.equal(escrow, 3 * PRICE);
+        const volumeBefore = (await program.account.market.fetch(mktPda)).totalBidVolume.toNumber();
+        const buyerBefore = await provider.connection.getBalance(buyer.publicKey);
+
+        await expire(bidId, buyer.publicKey);
+
+        const order = await program.account.order.fetch(bid);
+        assert.deepEqual(order.status, { cancelled: {} });
+        assert.equal(order.escrowLamports.toNumber(), 0);
+        assert.equal((await provider.connection.getBalance(buyer.publicKey)) - buyerBefore, escrow);
+        assert.equal((await program.account.market.fetch(mktPda)).totalBidVolume.toNumber(), volumeBefore - 3);
+    });
+
+    it("Never expires a good-till-cancelled order", async () => {
+        const [, orderId] = await place(mktPda, seller, { sell: {} }, PRICE, 1);
+        await expectError(expire(orderId, seller.publicKey), "OrderNotExpired");
+    });
+});
@@ -0,0 +1,149 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, chainTime, expectError, marketPda, oracleFeedPda, orderPda, program, provider, sleep } from "./helpers";
+
+describe("Expiry settlement", () => {
+    const MARKET_NAME = "DATED/MOCK";
+    const EXPIRY_IN_SECS = 4;
+    const MAX_STALENESS_SECS = 2;
+    const authority = provider.wallet;
+    const publisher = Keypair.generate();
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feedPda] = oracleFeedPda(publisher.publicKey);
+    const [bid] = orderPda(mktPda, 0);
+    const [ask] = orderPda(mktPda, 1);
+
+    const publish = (price: number) =>
+        program.methods
+            .publishOraclePrice(new anchor.BN(price))
+            .accounts({ publisher: publisher.publicKey, oracleFeed: feedPda })
+            .signers([publisher])
+            .rpc();
+
+    const settle = (settler: Keypair | null) => {
+        const call = program.methods
+            .settleAtExpiry()
+            .accounts({ settler: settler ? settler.publicKey : authority.publicKey, market: mktPda, oracleFeed: feedPda });
+        return settler ? call.signers([settler]).rpc() : call.rpc();
+    };
+
+    const forceCancel = (orderId: number, order: anchor.web3.PublicKey, owner: anchor.web3.PublicKey) =>
+        program.methods
+            .forceCancelOrder(new anchor.BN(orderId))
+            .accounts({ caller: stranger.publicKey, market: mktPda, order, owner, tradingBalance: null, roles: null })
+            .signers([stranger])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [publisher, buyer, seller, stranger]) await airdrop(kp.publicKey, 2);
+
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeOracleFeed()
+            .accounts({ publisher: publisher.publicKey, oracleFeed: feedPda, systemProgram: SystemProgram.programId })
+            .signers([publisher])
+            .rpc();
+        await publish(10_000);
+
+        const now = await chainTime();
+        await program.methods
+            .configureExpiry(new anchor.BN(now + EXPIRY_IN_SECS), feedPda, new anchor.BN(MAX_STALENESS_SECS), false)
+            .accounts({ authority: authority.publicKey, market: mktPda })
+            .rpc();
+
+        // A crossed pair nobody matched before expiry
+        await program.methods
+            .placeOrder({ buy: {} }, new anchor.BN(12_000), new anchor.BN(3), new anchor.BN(0), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: bid, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        await program.methods
+            .placeOrder({ sell: {} }, new anchor.BN(11_000), new anchor.BN(3), new anchor.BN(1), new anchor.BN(0))
+            .accounts({ owner: seller.publicKey, market: mktPda, order: ask, systemProgram: SystemProgram.programId })
+            .signers([seller])
+            .rpc();
+    });
+
+    it("Cannot settle before expiry", async () => {
+        await expectError(settle(null), "MarketNotExpired");
+    });
+
+    it("Stops trading at expiry", async () => {
+        const mkt = await program.account.market.fetch(mktPda);
+        while ((await chainTime()) < mkt.expiryTs.toNumber()) await sleep(500);
+
+        const [next] = orderPda(mktPda, 2);
+        await expectError(
+            program.methods
+                .placeOrder({ buy: {} }, new anchor.BN(1), new anchor.BN(1), new anchor.BN(2), new anchor.BN(0))
+                .accounts({ owner: buyer.publicKey, market: mktPda, order: next, systemProgram: SystemProgram.programId })
+                .signers([buyer])
+                .rpc(),
+            "MarketExpired"
+        );
+        await expectError(
+            program.methods
+                .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+                .accounts({
+                    matcher: authority.publicKey,
+                    market: mktPda,
+                    bidOrder: bid,
+                    askOrder: ask,
+                    bidOwner: buyer.publicKey,
+                    askOwner: seller.publicKey,
+                    feeConfig: null,
+                    treasury: authority.publicKey,
+                    bidTradingBalance: null,
+                })
+                .rpc(),
+            "MarketExpired"
+        );
+    });
+
+    it("Rejects force-cancel before settlement", async () => {
+        await expectError(forceCancel(0, bid, buyer.publicKey), "MarketNotSettled");
+    });
+
+    it("Rejects a stale oracle price", async () => {
+        await sleep((MAX_STALENESS_SECS + 1) * 1000);
+        await expectError(settle(null), "OracleStale");
+    });
+
+    it("Only the authority may settle unless configured otherwise", async () => {
+        await publish(10_500);
+        await expectError(settle(stranger), "Unauthorized");
+    });
+
+    it("Records the settlement price, which is then immutable", async () => {
+        await settle(null);
+        let mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.settlementPrice.toNumber(), 10_500);
+        assert.isAbove(mkt.settledAt.toNumber(), 0);
+
+        await publish(99_999);
+        await expectError(settle(null), "SettlementRecorded");
+        mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.settlementPrice.toNumber(), 10_500);
+    });
+
+    it("Anyone can force-cancel remaining orders with full refunds", async () => {
+        const buyerBefore = await provider.connection.getBalance(buyer.publicKey);
+        await forceCancel(0, bid, buyer.publicKey);
+        await forceCancel(1, ask, seller.publicKey);
+
+        const b = await program.account.order.fetch(bid);
+        const a = await program.account.order.fetch(ask);
+        assert.deepEqual(b.status, { cancelled: {} });
+        assert.deepEqual(a.status, { cancelled: {} });
+        assert.equal(b.escrowLamports.toNumber(), 0);
+        assert.equal((await provider.connection.getBalance(buyer.publicKey)) - buyerBefore, 12_000 * 3);
+    });
+});
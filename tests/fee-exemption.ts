@@ -0,0 +1,210 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, escrowVaultPda, expectError, feeConfigPda, marketPda, orderPda, place, program, provider, sleep, traderSeatPda } from "./helpers";
+
+describe("Fee-exempt market makers", () => {
+    const MARKET_NAME = "FEEX/MOCK";
+    const FEE_BPS = 100;
+    const PRICE = 10_000;
+    const QTY = 10;
+    const GROSS = PRICE * QTY;
+    const FEE = (GROSS * FEE_BPS) / 10_000;
+    const authority = provider.wallet;
+    const mm = Keypair.generate();
+    const trader = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+    const [mmSeat] = traderSeatPda(mktPda, mm.publicKey);
+
+    const setFeeExempt = (feeExempt: boolean, signer: Keypair | null = null) => {
+        const builder = program.methods
+            .setFeeExempt(mm.publicKey, feeExempt)
+            .accounts({ authority: signer ? signer.publicKey : authority.publicKey, market: mktPda, traderSeat: mmSeat, roles: null });
+        return signer ? builder.signers([signer]).rpc() : builder.rpc();
+    };
+
+    // The first owner places the resting (maker) order, the second crosses it.
+    // Returns the seller's proceeds and the trade event.
+    async function trade(maker: Keypair, taker: Keypair, passSeats = true): Promise<[number, any]> {
+        const makerSide = maker === mm ? { buy: {} } : { sell: {} };
+        const takerSide = maker === mm ? { sell: {} } : { buy: {} };
+        const [makerOrder] = await place(mktPda, maker, makerSide, PRICE, QTY);
+        const [takerOrder] = await place(mktPda, taker, takerSide, PRICE, QTY);
+        const [buyer, seller] = maker === mm ? [maker, taker] : [taker, maker];
+        const [bid, ask] = maker === mm ? [makerOrder, takerOrder] : [takerOrder, makerOrder];
+        const seatOf = (kp: Keypair) => (passSeats && kp === mm ? mmSeat : null);
+
+        const sellerBefore = await provider.connection.getBalance(seller.publicKey);
+        let event: any = null;
+        const listener = program.addEventListener("tradeExecutedEvent", (e) => (event = e));
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: feePda,
+                treasury: authority.publicKey,
+                bidTradingBalance: null,
+                bidSeat: seatOf(buyer),
+                askSeat: seatOf(seller),
+            })
+            .rpc();
+        await sleep(1000);
+        await program.removeEventListener(listener);
+        return [(await provider.connection.getBalance(seller.publicKey)) - sellerBefore, event];
+    }
+
+    before(async () => {
+        for (const kp of [mm, trader]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeFeeConfig(FEE_BPS, authority.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .setFeeRecipient(authority.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda })
+            .rpc();
+        await program.methods
+            .addTrader(mm.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, traderSeat: mmSeat, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Only the authority or FeeManager sets the flag", async () => {
+        try {
+            await setFeeExempt(true, trader);
+            assert.fail("Expected Unauthorized error");
+        } catch (err: any) {
+            assert.include(err.message, "Unauthorized");
+        }
+        await setFeeExempt(true);
+        const seat = await program.account.traderSeat.fetch(mmSeat);
+        assert.isTrue(seat.feeExempt);
+    });
+
+    it("Waives the fee when the maker is exempt", async () => {
+        const [proceeds, event] = await trade(mm, trader);
+        assert.equal(proceeds, GROSS);
+        assert.equal(event.feeAmount.toNumber(), 0);
+        assert.isTrue(event.makerFeeExempt);
+        assert.isFalse(event.takerFeeExempt);
+    });
+
+    it("Waives the fee when the taker is exempt", async () => {
+        const [proceeds, event] = await trade(trader, mm);
+        assert.equal(proceeds, GROSS);
+        assert.equal(event.feeAmount.toNumber(), 0);
+        assert.isFalse(event.makerFeeExempt);
+        assert.isTrue(event.takerFeeExempt);
+    });
+
+    it("Charges the fee when the exempt seat isn't supplied", async () => {
+        const [proceeds, event] = await trade(trader, mm, false);
+        assert.equal(proceeds, GROSS - FEE);
+        assert.isFalse(event.takerFeeExempt);
+    });
+
+    // Runs `call` and returns the TradeExecutedEvent it logged
+    async function tradeEvent(call: () => Promise<string>): Promise<any> {
+        let event: any = null;
+        const listener = program.addEventListener("tradeExecutedEvent", (e) => (event = e));
+        await call();
+        await sleep(1000);
+        await program.removeEventListener(listener);
+        return event;
+    }
+
+    it("Waives the fee for an exempt maker filled at placement", async () => {
+        const [bid, bidId] = await place(mktPda, mm, { buy: {} }, PRICE, QTY);
+        const [bidVault] = escrowVaultPda(mktPda, bidId);
+        const [order] = orderPda(mktPda, bidId + 1);
+        const event = await tradeEvent(() =>
+            program.methods
+                .placeOrderTif({ sell: {} }, new anchor.BN(PRICE), new anchor.BN(QTY), new anchor.BN(bidId + 1), new anchor.BN(0), { ioc: {} })
+                .accounts({ owner: trader.publicKey, market: mktPda, order, feeConfig: feePda, treasury: authority.publicKey, systemProgram: SystemProgram.programId })
+                .remainingAccounts([
+                    { pubkey: bid, isSigner: false, isWritable: true },
+                    { pubkey: mm.publicKey, isSigner: false, isWritable: true },
+                    { pubkey: mmSeat, isSigner: false, isWritable: false },
+                    { pubkey: bidVault, isSigner: false, isWritable: true },
+                ])
+                .signers([trader])
+                .rpc()
+        );
+        assert.equal(event.feeAmount.toNumber(), 0);
+        assert.isTrue(event.makerFeeExempt);
+        assert.isFalse(event.takerFeeExempt);
+    });
+
+    it("Waives the fee for an exempt taker of take_order", async () => {
+        const [ask] = await place(mktPda, trader, { sell: {} }, PRICE, QTY);
+        const event = await tradeEvent(() =>
+            program.methods
+                .takeOrder({ buy: {} }, new anchor.BN(QTY), new anchor.BN(PRICE))
+                .accounts({
+                    taker: mm.publicKey,
+                    market: mktPda,
+                    traderSeat: mmSeat,
+                    feeConfig: feePda,
+                    treasury: authority.publicKey,
+                    feeVault: null,
+                    systemProgram: SystemProgram.programId,
+                })
+                .remainingAccounts([
+                    { pubkey: ask, isSigner: false, isWritable: true },
+                    { pubkey: trader.publicKey, isSigner: false, isWritable: true },
+                    { pubkey: program.programId, isSigner: false, isWritable: false },
+                    { pubkey: program.programId, isSigner: false, isWritable: false },
+                ])
+                .signers([mm])
+                .rpc()
+        );
+        assert.equal(event.feeAmount.toNumber(), 0);
+        assert.isFalse(event.makerFeeExempt);
+        assert.isTrue(event.takerFeeExempt);
+    });
+
+    it("Rejects a maker seat that isn't the maker's", async () => {
+        const [ask] = await place(mktPda, trader, { sell: {} }, PRICE, QTY);
+        await expectError(
+            program.methods
+                .takeOrder({ buy: {} }, new anchor.BN(QTY), new anchor.BN(PRICE))
+                .accounts({
+                    taker: mm.publicKey,
+                    market: mktPda,
+                    traderSeat: null,
+                    feeConfig: feePda,
+                    treasury: authority.publicKey,
+                    feeVault: null,
+                    systemProgram: SystemProgram.programId,
+                })
+                .remainingAccounts([
+                    { pubkey: ask, isSigner: false, isWritable: true },
+                    { pubkey: trader.publicKey, isSigner: false, isWritable: true },
+                    { pubkey: mmSeat, isSigner: false, isWritable: false },
+                    { pubkey: program.programId, isSigner: false, isWritable: false },
+                ])
+                .signers([mm])
+                .rpc(),
+            "InvalidMakerAccounts"
+        );
+    });
+
+    it("Revocation applies from the next trade", async () => {
+        await setFeeExempt(false);
+        const [proceeds, event] = await trade(mm, trader);
+        assert.equal(proceeds, GROSS - FEE);
+        assert.equal(event.feeAmount.toNumber(), FEE);
+        assert.isFalse(event.makerFeeExempt);
+    });
+});
@@ -0,0 +1,106 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, feeConfigPda, feeVaultPda, marketPda, matchPair, place, program, provider } from "./helpers";
+
+describe("Fee recipient", () => {
+    const MARKET_NAME = "FEES/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const multisig = Keypair.generate();   // funded system account
+    const unfunded = Keypair.generate();   // can't stay rent-exempt on a small fee
+    const rotated = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+    const [vaultPda] = feeVaultPda(mktPda);
+    const balance = (pk: PublicKey) => provider.connection.getBalance(pk);
+
+    // 10 units @ 10_000 at 100 bps ⇒ 1_000 lamports of fee per match
+    const FEE = 1_000;
+    async function trade(treasury: PublicKey) {
+        const [bid] = await place(mktPda, buyer, { buy: {} }, 10_000, 10);
+        const [ask] = await place(mktPda, seller, { sell: {} }, 10_000, 10);
+        await matchPair(bid, ask, { feeConfig: feePda, treasury, feeVault: vaultPda });
+    }
+
+    async function setRecipient(recipient: PublicKey) {
+        await program.methods
+            .setFeeRecipient(recipient)
+            .accounts({ authority: authority.publicKey, market: mktPda })
+            .rpc();
+    }
+
+    before(async () => {
+        await airdrop(buyer.publicKey, 5);
+        await airdrop(seller.publicKey, 5);
+        await airdrop(multisig.publicKey, 1);
+        await airdrop(rotated.publicKey, 1);
+
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeVault: vaultPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Defaults the fee recipient to the market vault", async () => {
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.ok(mkt.feeRecipient.equals(vaultPda));
+        const vault = await program.account.feeVault.fetch(vaultPda);
+        assert.ok(vault.market.equals(mktPda));
+    });
+
+    it("Pays a plain system-account recipient directly", async () => {
+        await program.methods
+            .initializeFeeConfig(100, multisig.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .rpc();
+
+        const before = await balance(multisig.publicKey);
+        const vaultBefore = await balance(vaultPda);
+        await trade(multisig.publicKey);
+        assert.equal((await balance(multisig.publicKey)) - before, FEE);
+        assert.equal(await balance(vaultPda), vaultBefore);
+    });
+
+    it("Falls back to the vault when the recipient can't be paid directly", async () => {
+        await setRecipient(unfunded.publicKey);
+
+        const vaultBefore = await balance(vaultPda);
+        await trade(unfunded.publicKey);
+        assert.equal(await balance(unfunded.publicKey), 0, "a sub-rent credit would have failed the tx");
+        assert.equal((await balance(vaultPda)) - vaultBefore, FEE);
+    });
+
+    it("Rotates the recipient; the old one is no longer accepted", async () => {
+        await setRecipient(rotated.publicKey);
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.ok(mkt.feeRecipient.equals(rotated.publicKey));
+
+        try {
+            await trade(multisig.publicKey);
+            assert.fail("Expected TreasuryMismatch error");
+        } catch (err: any) {
+            assert.include(err.message, "TreasuryMismatch");
+        }
+
+        // The pair placed above is still open; a fresh pair pays the new recipient
+        const before = await balance(rotated.publicKey);
+        await trade(rotated.publicKey);
+        assert.equal((await balance(rotated.publicKey)) - before, FEE);
+    });
+
+    it("Rejects recipient changes from a non-authority", async () => {
+        try {
+            await program.methods
+                .setFeeRecipient(buyer.publicKey)
+                .accounts({ authority: buyer.publicKey, market: mktPda })
+                .signers([buyer])
+                .rpc();
+            assert.fail("Expected Unauthorized error");
+        } catch (err: any) {
+            assert.include(err.message, "Unauthorized");
+        }
+    });
+});
@@ -0,0 +1,80 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, feeConfigPda, marketPda, matchPair, orderPda, place, program, provider, sleep } from "./helpers";
+
+describe("Order fee snapshots", () => {
+    const MARKET_NAME = "FEESNAP/MOCK";
+    const PRICE = 10_000;
+    const QTY = 10;
+    const GROSS = PRICE * QTY;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const treasury = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+
+    const setFee = (feeBps: number) =>
+        program.methods
+            .updateFeeConfig(feeBps, treasury.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda })
+            .rpc();
+
+    async function matchEvent(bid: PublicKey, ask: PublicKey): Promise<any> {
+        let event: any = null;
+        const listener = program.addEventListener("tradeExecutedEvent", (e) => (event = e));
+        await matchPair(bid, ask, { feeConfig: feePda, treasury: treasury.publicKey });
+        await sleep(1000);
+        await program.removeEventListener(listener);
+        return event;
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller, treasury]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeFeeConfig(100, treasury.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Records the fee in force on each order", async () => {
+        const [ask] = await place(mktPda, seller, { sell: {} }, PRICE, QTY);
+        assert.equal((await program.account.order.fetch(ask)).feeBps, 100);
+        assert.equal((await program.account.market.fetch(mktPda)).feeBps, 100);
+    });
+
+    it("A fee raise after placement doesn't reach resting orders", async () => {
+        const [oldAsk] = orderPda(mktPda, 0);
+        await setFee(300);
+        const [bid] = await place(mktPda, buyer, { buy: {} }, PRICE, QTY);
+        const [newAsk] = await place(mktPda, seller, { sell: {} }, PRICE, QTY);
+        assert.equal((await program.account.order.fetch(newAsk)).feeBps, 300);
+
+        const first = await matchEvent(bid, oldAsk);
+        assert.equal(first.feeBps, 100);
+        assert.equal(first.feeAmount.toNumber(), GROSS / 100);
+
+        const [bid2] = await place(mktPda, buyer, { buy: {} }, PRICE, QTY);
+        const second = await matchEvent(bid2, newAsk);
+        assert.equal(second.feeBps, 300);
+        assert.equal(second.feeAmount.toNumber(), (GROSS * 3) / 100);
+    });
+
+    it("A fee cut after placement doesn't reach resting orders either", async () => {
+        const [ask] = await place(mktPda, seller, { sell: {} }, PRICE, QTY);
+        await setFee(0);
+        const [bid] = await place(mktPda, buyer, { buy: {} }, PRICE, QTY);
+        assert.equal((await program.account.order.fetch(bid)).feeBps, 0);
+
+        // The seller pays the fee, so the ask's snapshot is the one applied
+        const event = await matchEvent(bid, ask);
+        assert.equal(event.feeBps, 300);
+        assert.equal(event.feeAmount.toNumber(), (GROSS * 3) / 100);
+    });
+});
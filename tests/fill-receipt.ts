@@ -0,0 +1,92 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, fillReceiptPda, marketPda, orderPda, place, program, provider } from "./helpers";
+
+describe("Fill receipts", () => {
+    const MARKET_NAME = "RECEIPT/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const matcher = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const match = (bidId: number, askId: number, fillReceipt: PublicKey | null) =>
+        program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: matcher.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, bidId)[0],
+                askOrder: orderPda(mktPda, askId)[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+                fillReceipt,
+            })
+            .signers([matcher])
+            .rpc();
+
+    const closeReceipt = (receipt: PublicKey, signer: Keypair) =>
+        program.methods
+            .closeFillReceipt()
+            .accounts({ matcher: signer.publicKey, fillReceipt: receipt })
+            .signers([signer])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller, matcher, stranger]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Records every fill of a market in its own receipt", async () => {
+        const [, bid] = await place(mktPda, buyer, { buy: {} }, 1_200, 5);
+        const [, ask1] = await place(mktPda, seller, { sell: {} }, 1_000, 2);
+        const [, ask2] = await place(mktPda, seller, { sell: {} }, 1_100, 3);
+
+        await match(bid, ask1, fillReceiptPda(mktPda, 1)[0]);
+        await match(bid, ask2, fillReceiptPda(mktPda, 2)[0]);
+
+        const history = await Promise.all([1, 2].map((seq) => program.account.fillReceipt.fetch(fillReceiptPda(mktPda, seq)[0])));
+        assert.deepEqual(
+            history.map((r) => [r.fillSeq.toNumber(), r.bidOrderId.toNumber(), r.askOrderId.toNumber(), r.fillPrice.toNumber(), r.fillQuantity.toNumber()]),
+            [
+                [1, bid, ask1, 1_000, 2],
+                [2, bid, ask2, 1_100, 3],
+            ]
+        );
+        for (const r of history) {
+            assert.ok(r.market.equals(mktPda));
+            assert.ok(r.buyer.equals(buyer.publicKey));
+            assert.ok(r.seller.equals(seller.publicKey));
+            assert.ok(r.matcher.equals(matcher.publicKey));
+            assert.isAbove(r.timestamp.toNumber(), 0);
+        }
+        assert.equal((await program.account.market.fetch(mktPda)).tradeSeq.toNumber(), 2);
+    });
+
+    it("Only takes the receipt for the market's next fill", async () => {
+        const [, bid] = await place(mktPda, buyer, { buy: {} }, 1_000, 1);
+        const [, ask] = await place(mktPda, seller, { sell: {} }, 1_000, 1);
+        await expectError(match(bid, ask, fillReceiptPda(mktPda, 2)[0]), "ConstraintSeeds");
+        // Without a receipt the match still goes through
+        await match(bid, ask, null);
+        assert.isNull(await provider.connection.getAccountInfo(fillReceiptPda(mktPda, 3)[0]));
+    });
+
+    it("Returns the rent to the matcher that paid it, and only to it", async () => {
+        const [receipt] = fillReceiptPda(mktPda, 1);
+        await expectError(closeReceipt(receipt, stranger), "Unauthorized");
+
+        const rent = await provider.connection.getBalance(receipt);
+        const before = await provider.connection.getBalance(matcher.publicKey);
+        await closeReceipt(receipt, matcher);
+        assert.isNull(await provider.connection.getAccountInfo(receipt));
+        assert.equal((await provider.connection.getBalance(matcher.publicKey)) - before, rent);
+    });
+});
@@ -0,0 +1,175 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, matchPair, orderPda, program, provider } from "./helpers";
+
+const Q64_ONE = 1n << 64n;
+
+// price_q64 * qty in whole lamports, as the program rounds it
+function notional(priceQ64: bigint, qty: bigint, roundUp: boolean): number {
+    const exact = priceQ64 * qty;
+    const whole = exact / Q64_ONE;
+    return Number(roundUp && exact % Q64_ONE !== 0n ? whole + 1n : whole);
+}
+
+describe("Fixed-point (Q64.64) prices", () => {
+    const MARKET_NAME = "Q64/MOCK";
+    const INTEGER_NAME = "Q64-INT/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [intPda] = marketPda(authority.publicKey, INTEGER_NAME);
+    const balance = (pk: PublicKey) => provider.connection.getBalance(pk);
+
+    // 0.3 and 0.25 lamports per unit
+    const BID_PRICE = (Q64_ONE * 3n) / 10n;
+    const ASK_PRICE = Q64_ONE / 4n;
+
+    let nextId = 0;
+
+    function placeQ64(market: PublicKey, owner: Keypair, side: any, priceQ64: bigint, qty: number, id: number) {
+        const [order] = orderPda(market, id);
+        return program.methods
+            .placeOrderQ64(
+                side,
+                new anchor.BN((priceQ64 >> 64n).toString()),
+                new anchor.BN(qty),
+                new anchor.BN(id),
+                new anchor.BN(0),
+                new anchor.BN((priceQ64 & (Q64_ONE - 1n)).toString())
+            )
+            .accounts({ owner: owner.publicKey, market, order, systemProgram: SystemProgram.programId })
+            .signers([owner])
+            .rpc();
+    }
+
+    async function place(owner: Keypair, side: any, priceQ64: bigint, qty: number): Promise<PublicKey> {
+        const id = nextId++;
+        await placeQ64(mktPda, owner, side, priceQ64, qty, id);
+        return orderPda(mktPda, id)[0];
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller, stranger]) await airdrop(kp.publicKey, 5);
+        for (const [name, market] of [[MARKET_NAME, mktPda], [INTEGER_NAME, intPda]] as const) {
+            await program.methods
+                .initializeMarket(name, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+                .accounts({ authority: authority.publicKey, market, systemProgram: SystemProgram.programId })
+                .rpc();
+        }
+    });
+
+    it("Is authority-gated and picks which placement the market takes", async () => {
+        const setFormat = (signer: any, enabled: boolean) => {
+            const call = program.methods
+                .setFixedPointPrices(enabled)
+                .accounts({ authority: signer.publicKey, market: mktPda, roles: null });
+            return signer === authority ? call.rpc() : call.signers([signer]).rpc();
+        };
+        await expectError(setFormat(stranger, true), "Unauthorized");
+        await setFormat(authority, true);
+        assert.isTrue((await program.account.market.fetch(mktPda)).fixedPointPrices);
+
+        // Integer placement on a fixed-point market, and the reverse
+        const [order] = orderPda(mktPda, 0);
+        await expectError(
+            program.methods
+                .placeOrder({ buy: {} }, new anchor.BN(1), new anchor.BN(1), new anchor.BN(0), new anchor.BN(0))
+                .accounts({ owner: buyer.publicKey, market: mktPda, order, systemProgram: SystemProgram.programId })
+                .signers([buyer])
+                .rpc(),
+            "PriceFormatMismatch"
+        );
+        await expectError(placeQ64(intPda, buyer, { buy: {} }, BID_PRICE, 1, 0), "PriceFormatMismatch");
+        await expectError(placeQ64(mktPda, buyer, { buy: {} }, 0n, 1, 0), "InvalidPrice");
+    });
+
+    it("Escrows a sub-lamport BUY rounded up and locks the format", async () => {
+        const before = await balance(buyer.publicKey);
+        const bid = await place(buyer, { buy: {} }, BID_PRICE, 1_000_000);
+        const order = await program.account.order.fetch(bid);
+        assert.equal(order.price.toNumber(), 0); // whole-lamport part
+        assert.equal(BigInt(order.priceQ64.toString()), BID_PRICE);
+        assert.equal(order.escrowLamports.toNumber(), notional(BID_PRICE, 1_000_000n, true));
+        const rent = await provider.connection.getMinimumBalanceForRentExemption(program.account.order.size);
+        const vaultRent = await provider.connection.getMinimumBalanceForRentExemption(0);
+        assert.equal(before - (await balance(buyer.publicKey)), rent + vaultRent + order.escrowLamports.toNumber());
+
+        await expectError(
+            program.methods
+                .setFixedPointPrices(false)
+                .accounts({ authority: authority.publicKey, market: mktPda, roles: null })
+                .rpc(),
+            "PriceFormatLocked"
+        );
+    });
+
+    it("Pays sellers rounded down and conserves the escrow over partial fills", async () => {
+        const bid = orderPda(mktPda, 0)[0];
+        const escrowBefore = (await program.account.order.fetch(bid)).escrowLamports.toNumber();
+        let released = 0;
+        let sellerPaid = 0;
+        let buyerRefunded = 0;
+
+        for (const [priceQ64, qty] of [
+            [ASK_PRICE, 400_001],
+            [ASK_PRICE + 12_345n, 3],
+            [BID_PRICE, 599_996],
+        ] as [bigint, number][]) {
+            const ask = await place(seller, { sell: {} }, priceQ64, qty);
+            const sellerBefore = await balance(seller.publicKey);
+            const buyerBefore = await balance(buyer.publicKey);
+            const escrow = (await program.account.order.fetch(bid)).escrowLamports.toNumber();
+
+            await matchPair(bid, ask);
+
+            const after = await program.account.order.fetch(bid);
+            const debit = escrow - after.escrowLamports.toNumber();
+            const paid = (await balance(seller.publicKey)) - sellerBefore;
+            const refunded = (await balance(buyer.publicKey)) - buyerBefore;
+            assert.equal(paid, notional(priceQ64, BigInt(qty), false));
+            assert.equal(debit, paid + refunded);
+            // The remainder always keeps the escrow it needs
+            const remaining = BigInt(after.quantity.sub(after.filledQuantity).toString());
+            assert.isAtLeast(after.escrowLamports.toNumber(), notional(BID_PRICE, remaining, true));
+
+            released += debit;
+            sellerPaid += paid;
+            buyerRefunded += refunded;
+        }
+
+        const done = await program.account.order.fetch(bid);
+        assert.deepEqual(done.status, { filled: {} });
+        assert.equal(done.escrowLamports.toNumber(), 0);
+        assert.equal(released, escrowBefore);
+        assert.equal(sellerPaid + buyerRefunded, escrowBefore);
+    });
+
+    it("Tops up the lamport a split's rounding needs", async () => {
+        const fits = (Q64_ONE * 7n) / 10n; // 0.7 × 3 ⇒ 3; split 1 + 2 ⇒ 1 + 2 = 3
+        const short = (Q64_ONE * 6n) / 10n; // 0.6 × 3 ⇒ 2; split 1 + 2 ⇒ 1 + 2 = 3
+        for (const [priceQ64, topUp] of [[fits, 0], [short, 1]] as [bigint, number][]) {
+            const order = await place(buyer, { buy: {} }, priceQ64, 3);
+            const id = nextId - 1;
+            const newId = nextId++;
+            const [newOrder] = orderPda(mktPda, newId);
+            await program.methods
+                .splitOrder(new anchor.BN(id), new anchor.BN(newId), new anchor.BN(1), new anchor.BN(0))
+                .accounts({ owner: buyer.publicKey, market: mktPda, order, newOrder, systemProgram: SystemProgram.programId })
+                .signers([buyer])
+                .rpc();
+            const kept = await program.account.order.fetch(order);
+            const slice = await program.account.order.fetch(newOrder);
+            assert.equal(slice.escrowLamports.toNumber(), notional(priceQ64, 1n, true));
+            assert.equal(kept.escrowLamports.toNumber(), notional(priceQ64, 2n, true));
+            assert.equal(
+                kept.escrowLamports.toNumber() + slice.escrowLamports.toNumber(),
+                notional(priceQ64, 3n, true) + topUp
+            );
+            assert.equal(BigInt(slice.priceQ64.toString()), priceQ64);
+        }
+    });
+});
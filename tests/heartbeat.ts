@@ -0,0 +1,137 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram, Transaction } from "@solana/web3.js";
+import { assert } from "chai";
+import { HeartbeatAlert, HeartbeatService } from "../client/heartbeat";
+import { airdrop, marketPda, place, program, provider, sleep } from "./helpers";
+
+describe("Cancel-on-disconnect heartbeat (client)", () => {
+    const MARKET_NAME = "HEARTBEAT/MOCK";
+    const OTHER_NAME = "HEARTBEAT-B/MOCK";
+    const authority = provider.wallet;
+    const maker = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [otherPda] = marketPda(authority.publicKey, OTHER_NAME);
+
+    const quote = async (market: PublicKey, side: any, price: number) => (await place(market, maker, side, price, 1))[0];
+
+    const status = async (order: PublicKey) => Object.keys((await program.account.order.fetch(order)).status)[0];
+
+    // Stands in for a remote signing service holding the maker's key
+    const remoteSigner = (calls: Transaction[], failFirst = 0) => async (tx: Transaction) => {
+        calls.push(tx);
+        if (calls.length <= failFirst) throw new Error("signer unreachable");
+        tx.partialSign(maker);
+        return tx;
+    };
+
+    before(async () => {
+        await airdrop(maker.publicKey, 5);
+        for (const [name, market] of [[MARKET_NAME, mktPda], [OTHER_NAME, otherPda]] as const) {
+            await program.methods
+                .initializeMarket(name, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+                .accounts({ authority: authority.publicKey, market, systemProgram: SystemProgram.programId })
+                .rpc();
+        }
+    });
+
+    it("Keeps quotes while pinged and cancels them all once pings stop", async () => {
+        const quotes: PublicKey[] = [];
+        for (let i = 0; i < 4; i++) {
+            quotes.push(await quote(mktPda, { buy: {} }, 9_000 - i));
+            quotes.push(await quote(mktPda, { sell: {} }, 11_000 + i));
+        }
+        const elsewhere = await quote(otherPda, { buy: {} }, 9_000);
+
+        const alerts: HeartbeatAlert[] = [];
+        const signed: Transaction[] = [];
+        const service = new HeartbeatService(program as any, {
+            owner: maker.publicKey,
+            markets: [mktPda],
+            timeoutMs: 1_500,
+            checkIntervalMs: 100,
+            ordersPerTx: 3,
+            signTransaction: remoteSigner(signed),
+            onAlert: (a) => alerts.push(a),
+        });
+        const found = await service.discover();
+        assert.equal(found.length, quotes.length); // the other market isn't configured
+        await service.start();
+
+        // A live strategy keeps the switch armed
+        for (let i = 0; i < 6; i++) {
+            await sleep(500);
+            service.ping();
+        }
+        assert.isEmpty(alerts);
+        for (const q of quotes) assert.equal(await status(q), "open");
+
+        // The strategy dies
+        await sleep(4_000);
+        service.stop();
+
+        assert.equal(alerts[0].kind, "missed");
+        const submitted = alerts.filter((a) => a.kind === "submitted");
+        assert.lengthOf(submitted, 3); // 8 orders, 3 per transaction
+        assert.lengthOf(signed, 3);
+        for (const q of quotes) assert.equal(await status(q), "cancelled");
+        assert.equal(await status(elsewhere), "open");
+        assert.isTrue(service.tripped);
+        assert.isEmpty(service.trackedOrders());
+    });
+
+    it("Retries through a failing signer and skips orders already gone", async () => {
+        const a = await quote(mktPda, { buy: {} }, 8_000);
+        const b = await quote(mktPda, { buy: {} }, 8_001);
+
+        const alerts: HeartbeatAlert[] = [];
+        const signed: Transaction[] = [];
+        const service = new HeartbeatService(program as any, {
+            owner: maker.publicKey,
+            markets: [mktPda],
+            timeoutMs: 60_000,
+            maxRetries: 2,
+            retryDelayMs: 100,
+            signTransaction: remoteSigner(signed, 1),
+            onAlert: (x) => alerts.push(x),
+        });
+        service.track(a);
+        service.track(b);
+        await service.refresh();
+
+        // The maker cancels one by hand after the cancels were prepared
+        await program.methods
+            .cancelOrder((await program.account.order.fetch(b)).orderId, new anchor.BN(0))
+            .accounts({ owner: maker.publicKey, market: mktPda, order: b, tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([maker])
+            .rpc();
+
+        const sigs = await service.cancelAll();
+        assert.lengthOf(sigs, 1);
+        assert.deepEqual(alerts.map((x) => x.kind), ["retry", "submitted"]);
+        const done = alerts[1] as Extract<HeartbeatAlert, { kind: "submitted" }>;
+        assert.deepEqual(done.orders.map((o) => o.toBase58()), [a.toBase58()]);
+        assert.equal(await status(a), "cancelled");
+    });
+
+    it("Alerts when every attempt fails", async () => {
+        const order = await quote(mktPda, { sell: {} }, 12_000);
+        const alerts: HeartbeatAlert[] = [];
+        const service = new HeartbeatService(program as any, {
+            owner: maker.publicKey,
+            markets: [mktPda],
+            timeoutMs: 60_000,
+            maxRetries: 1,
+            retryDelayMs: 50,
+            signTransaction: remoteSigner([], 10),
+            onAlert: (x) => alerts.push(x),
+        });
+        service.track(order);
+        await service.refresh();
+
+        assert.isEmpty(await service.cancelAll());
+        assert.deepEqual(alerts.map((x) => x.kind), ["retry", "failed"]);
+        assert.equal(await status(order), "open");
+        assert.lengthOf(service.trackedOrders(), 1); // still tracked for the next attempt
+    });
+});
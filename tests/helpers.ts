@@ -0,0 +1,274 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { OrderMatchingEngine } from "../target/types/order_matching_engine";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+
+// ─── Shared test helpers ──────────────────────────────────────────────────────
+
+export const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+export const program = anchor.workspace.OrderMatchingEngine as Program<OrderMatchingEngine>;
+
+export async function airdrop(pk: PublicKey, sol = 2) {
+    const sig = await provider.connection.requestAirdrop(pk, sol * LAMPORTS_PER_SOL);
+    await provider.connection.confirmTransaction(sig, "confirmed");
+}
+
+export function sleep(ms: number) {
+    return new Promise((resolve) => setTimeout(resolve, ms));
+}
+
+/** Await `call` and assert it fails with the error `name`. */
+export async function expectError(call: Promise<unknown>, name: string) {
+    try {
+        await call;
+        assert.fail(`Expected ${name} error`);
+    } catch (err: any) {
+        assert.include(err.message, name);
+    }
+}
+
+export type Accounts = Record<string, PublicKey | null>;
+
+/**
+ * place_order for `owner`, escrowed from its wallet, at `orderId` (default
+ * the market's next order id). `accounts` adds or overrides accounts.
+ * Returns the order's PDA and id.
+ */
+export async function place(
+    market: PublicKey,
+    owner: Keypair,
+    side: any,
+    price: number,
+    quantity: number,
+    { orderId, expiresAt = 0, accounts = {} }: { orderId?: number; expiresAt?: number; accounts?: Accounts } = {}
+): Promise<[PublicKey, number]> {
+    const id = orderId ?? (await program.account.market.fetch(market)).nextOrderId.toNumber();
+    const [order] = orderPda(market, id);
+    await program.methods
+        .placeOrder(side, new anchor.BN(price), new anchor.BN(quantity), new anchor.BN(id), new anchor.BN(expiresAt))
+        .accountsPartial({ owner: owner.publicKey, market, order, systemProgram: SystemProgram.programId, ...accounts })
+        .signers([owner])
+        .rpc();
+    return [order, id];
+}
+
+/**
+ * match_orders `bid` against `ask` without guards, signed by the provider
+ * wallet. The owners and the fee recipient are read from chain; `accounts`
+ * adds or overrides accounts.
+ */
+export async function matchPair(bid: PublicKey, ask: PublicKey, accounts: Accounts = {}): Promise<string> {
+    const [bidOrder, askOrder] = await Promise.all([bid, ask].map((order) => program.account.order.fetch(order)));
+    const { feeRecipient } = await program.account.market.fetch(bidOrder.market);
+    return program.methods
+        .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+        .accountsPartial({
+            matcher: provider.wallet.publicKey,
+            market: bidOrder.market,
+            bidOrder: bid,
+            askOrder: ask,
+            bidOwner: bidOrder.owner,
+            askOwner: askOrder.owner,
+            feeConfig: null,
+            treasury: feeRecipient,
+            ...accounts,
+        })
+        .rpc();
+}
+
+export async function chainTime(): Promise<number> {
+    const slot = await provider.connection.getSlot("confirmed");
+    const ts = await provider.connection.getBlockTime(slot);
+    return ts ?? Math.floor(Date.now() / 1000);
+}
+
+function u64Le(n: number): Buffer {
+    const buf = Buffer.alloc(8);
+    buf.writeBigUInt64LE(BigInt(n));
+    return buf;
+}
+
+export function marketPda(authority: PublicKey, name: string): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("market"), authority.toBuffer(), Buffer.from(name)],
+        program.programId
+    );
+}
+
+export function orderPda(market: PublicKey, orderId: number): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), market.toBuffer(), u64Le(orderId)],
+        program.programId
+    );
+}
+
+/** The escrow vault holding order `orderId`'s lamport escrow. */
+export function escrowVaultPda(market: PublicKey, orderId: number): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), market.toBuffer(), u64Le(orderId)],
+        program.programId
+    );
+}
+
+export function orderV2Pda(market: PublicKey, owner: PublicKey, clientNonce: number): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), market.toBuffer(), owner.toBuffer(), u64Le(clientNonce)],
+        program.programId
+    );
+}
+
+export function feeConfigPda(market: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("fee_config"), market.toBuffer()],
+        program.programId
+    );
+}
+
+export function stagedParamsPda(market: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("staged_params"), market.toBuffer()],
+        program.programId
+    );
+}
+
+export function tradingBalancePda(market: PublicKey, owner: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("balance"), market.toBuffer(), owner.toBuffer()],
+        program.programId
+    );
+}
+
+export function commitmentPda(market: PublicKey, owner: PublicKey, commitmentId: number): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("commitment"), market.toBuffer(), owner.toBuffer(), u64Le(commitmentId)],
+        program.programId
+    );
+}
+
+export function traderSeatPda(market: PublicKey, trader: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("seat"), market.toBuffer(), trader.toBuffer()],
+        program.programId
+    );
+}
+
+export function oracleFeedPda(publisher: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("oracle_feed"), publisher.toBuffer()],
+        program.programId
+    );
+}
+
+export function feeVaultPda(market: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("fee_vault"), market.toBuffer()],
+        program.programId
+    );
+}
+
+export function quoteVaultPda(market: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("quote_vault"), market.toBuffer()],
+        program.programId
+    );
+}
+
+export function baseVaultPda(market: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("base_vault"), market.toBuffer()],
+        program.programId
+    );
+}
+
+export function userStatsPda(market: PublicKey, owner: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("user_stats"), market.toBuffer(), owner.toBuffer()],
+        program.programId
+    );
+}
+
+export function userBalancePda(market: PublicKey, owner: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("user_balance"), market.toBuffer(), owner.toBuffer()],
+        program.programId
+    );
+}
+
+export function openOrdersPda(market: PublicKey, owner: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("open_orders"), market.toBuffer(), owner.toBuffer()],
+        program.programId
+    );
+}
+
+export function fillReceiptPda(market: PublicKey, fillSeq: number): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("fill"), market.toBuffer(), u64Le(fillSeq)],
+        program.programId
+    );
+}
+
+export function matcherStatsPda(market: PublicKey, matcher: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("matcher"), market.toBuffer(), matcher.toBuffer()],
+        program.programId
+    );
+}
+
+export function matcherSeatPda(market: PublicKey, matcher: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("matcher_seat"), market.toBuffer(), matcher.toBuffer()],
+        program.programId
+    );
+}
+
+export function tradeHistoryPda(market: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync([Buffer.from("trades"), market.toBuffer()], program.programId);
+}
+
+export function configPda(): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync([Buffer.from("config")], program.programId);
+}
+
+export function registryPda(): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync([Buffer.from("registry")], program.programId);
+}
+
+export function listingPda(registryIndex: number): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync([Buffer.from("listing"), u64Le(registryIndex)], program.programId);
+}
+
+/** Listings created so far — the index initialize_market lists at next. */
+export async function registryCount(): Promise<number> {
+    return (await program.account.marketRegistry.fetch(registryPda()[0])).count.toNumber();
+}
+
+export function rolesPda(market: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from("roles"), market.toBuffer()],
+        program.programId
+    );
+}
+
+// place_order / match_orders require the protocol config, and
+// initialize_market lists every market in the registry, so every spec
+// needs both to exist. Root hook: runs once before any spec.
+before(async () => {
+    const [config] = configPda();
+    if (!(await provider.connection.getAccountInfo(config))) {
+        await program.methods
+            .initializeConfig(0)
+            .accounts({ admin: provider.wallet.publicKey, config, systemProgram: anchor.web3.SystemProgram.programId })
+            .rpc();
+    }
+    const [registry] = registryPda();
+    if (!(await provider.connection.getAccountInfo(registry))) {
+        await program.methods
+            .initializeRegistry()
+            .accounts({ admin: provider.wallet.publicKey, config, registry, systemProgram: anchor.web3.SystemProgram.programId })
+            .rpc();
+    }
+});
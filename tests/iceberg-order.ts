@@ -0,0 +1,203 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, place, program, provider, sleep } from "./helpers";
+
+describe("Iceberg orders", () => {
+    const MARKET_NAME = "ICEBERG/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const balance = (key: PublicKey) => provider.connection.getBalance(key);
+    const fetchOrder = (orderId: number) => program.account.order.fetch(orderPda(mktPda, orderId)[0]);
+
+    const accounts = (owner: Keypair, orderId: number) => ({
+        owner: owner.publicKey,
+        market: mktPda,
+        order: orderPda(mktPda, orderId)[0],
+        systemProgram: SystemProgram.programId,
+    });
+
+    const placeIceberg = (owner: Keypair, side: any, price: number, quantity: number, orderId: number, display: number) =>
+        program.methods
+            .placeIcebergOrder(side, new anchor.BN(price), new anchor.BN(quantity), new anchor.BN(orderId), new anchor.BN(0), new anchor.BN(display))
+            .accounts(accounts(owner, orderId))
+            .signers([owner])
+            .rpc();
+
+    const match = (bidId: number, askId: number) =>
+        program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, bidId)[0],
+                askOrder: orderPda(mktPda, askId)[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+            })
+            .rpc();
+
+    async function withEvent(name: string, call: () => Promise<unknown>): Promise<any> {
+        let event: any = null;
+        const listener = program.addEventListener(name as any, (e) => (event = e));
+        await call();
+        await sleep(1000);
+        await program.removeEventListener(listener);
+        return event;
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Rejects a display size of zero or above the quantity", async () => {
+        await expectError(placeIceberg(buyer, { buy: {} }, 1_000, 100, 0, 0), "InvalidDisplayQuantity");
+        await expectError(placeIceberg(buyer, { buy: {} }, 1_000, 100, 0, 101), "InvalidDisplayQuantity");
+    });
+
+    it("Escrows the full total but shows only the first tranche", async () => {
+        const placed = await withEvent("orderPlacedEvent", () => placeIceberg(buyer, { buy: {} }, 1_000, 100, 0, 20));
+        const bid = await fetchOrder(0);
+        assert.equal(bid.quantity.toNumber(), 100);
+        assert.equal(bid.escrowLamports.toNumber(), 100_000);
+        assert.equal(bid.displayQuantity.toNumber(), 20);
+        assert.equal(bid.displayedRemaining.toNumber(), 20);
+        assert.equal(placed.quantity.toNumber(), 20);
+    });
+
+    it("Fills a 100 / 20 iceberg in five tranches", async () => {
+        await place(mktPda, seller, { sell: {} }, 1_000, 100, { orderId: 1 });
+
+        for (let tranche = 1; tranche <= 5; tranche++) {
+            const trade = await withEvent("tradeExecutedEvent", () => match(0, 1));
+            assert.equal(trade.fillQuantity.toNumber(), 20);
+            const bid = await fetchOrder(0);
+            assert.equal(bid.filledQuantity.toNumber(), tranche * 20);
+            if (tranche < 5) {
+                // The next slice is revealed; the hidden total never shows
+                assert.deepEqual(bid.status, { partiallyFilled: {} });
+                assert.equal(bid.displayedRemaining.toNumber(), 20);
+                assert.equal(trade.bidRemaining.toNumber(), 20);
+            } else {
+                assert.deepEqual(bid.status, { filled: {} });
+                assert.equal(bid.displayedRemaining.toNumber(), 0);
+                assert.equal(trade.bidRemaining.toNumber(), 0);
+                assert.equal(bid.escrowLamports.toNumber(), 0);
+            }
+        }
+        assert.deepEqual((await fetchOrder(1)).status, { filled: {} });
+    });
+
+    it("Fills part of a tranche without revealing the next", async () => {
+        await placeIceberg(seller, { sell: {} }, 1_000, 50, 2, 20);
+        await place(mktPda, buyer, { buy: {} }, 1_000, 15, { orderId: 3 });
+        const trade = await withEvent("tradeExecutedEvent", () => match(3, 2));
+
+        const ask = await fetchOrder(2);
+        assert.equal(ask.filledQuantity.toNumber(), 15);
+        assert.equal(ask.displayedRemaining.toNumber(), 5);
+        assert.equal(trade.askRemaining.toNumber(), 5);
+    });
+
+    it("Refunds the true remainder on cancel", async () => {
+        await placeIceberg(buyer, { buy: {} }, 1_000, 60, 4, 10);
+        await place(mktPda, seller, { sell: {} }, 1_000, 10, { orderId: 5 });
+        await match(4, 5);
+        const bid = await fetchOrder(4);
+        assert.equal(bid.displayedRemaining.toNumber(), 10);
+        assert.equal(bid.escrowLamports.toNumber(), 50_000);
+
+        const before = await balance(buyer.publicKey);
+        await program.methods
+            .cancelOrder(new anchor.BN(4), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: orderPda(mktPda, 4)[0], tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        assert.equal((await balance(buyer.publicKey)) - before, 50_000);
+        assert.deepEqual((await fetchOrder(4)).status, { cancelled: {} });
+    });
+
+    it("Splits into a slice that shows only a tranche of its own", async () => {
+        await placeIceberg(seller, { sell: {} }, 1_000, 50, 6, 10);
+        await program.methods
+            .splitOrder(new anchor.BN(6), new anchor.BN(7), new anchor.BN(30), new anchor.BN(0))
+            .accounts({
+                owner: seller.publicKey,
+                market: mktPda,
+                order: orderPda(mktPda, 6)[0],
+                newOrder: orderPda(mktPda, 7)[0],
+                userStats: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([seller])
+            .rpc();
+
+        const [original, slice] = [await fetchOrder(6), await fetchOrder(7)];
+        assert.equal(slice.displayQuantity.toNumber(), 10);
+        assert.equal(slice.displayedRemaining.toNumber(), 10);
+        assert.equal(original.displayQuantity.toNumber(), 10);
+        assert.equal(original.displayedRemaining.toNumber(), 10);
+
+        // Matching fills one tranche of the slice, not all 30
+        await place(mktPda, buyer, { buy: {} }, 1_000, 30, { orderId: 8 });
+        const trade = await withEvent("tradeExecutedEvent", () => match(8, 7));
+        assert.equal(trade.fillQuantity.toNumber(), 10);
+        assert.equal(trade.askRemaining.toNumber(), 10);
+    });
+
+    it("Merges only orders with the same tranche size", async () => {
+        const merge = (survivorId: number, absorbedId: number) =>
+            program.methods
+                .mergeOrders(new anchor.BN(survivorId), new anchor.BN(absorbedId))
+                .accounts({
+                    owner: seller.publicKey,
+                    market: mktPda,
+                    survivor: orderPda(mktPda, survivorId)[0],
+                    absorbed: orderPda(mktPda, absorbedId)[0],
+                    userStats: null,
+                })
+                .signers([seller])
+                .rpc();
+        await placeIceberg(seller, { sell: {} }, 2_000, 20, 9, 5);
+        await place(mktPda, seller, { sell: {} }, 2_000, 20, { orderId: 10 });
+        await placeIceberg(seller, { sell: {} }, 2_000, 20, 11, 4);
+        await expectError(merge(9, 10), "OrdersNotMergeable");
+        await expectError(merge(9, 11), "OrdersNotMergeable");
+
+        await placeIceberg(seller, { sell: {} }, 2_000, 20, 12, 5);
+        await merge(9, 12);
+        const merged = await fetchOrder(9);
+        assert.equal(merged.quantity.toNumber(), 40);
+        assert.equal(merged.displayQuantity.toNumber(), 5);
+        assert.equal(merged.displayedRemaining.toNumber(), 5);
+    });
+
+    it("Keeps the tranche size through cancel_and_replace", async () => {
+        await placeIceberg(buyer, { buy: {} }, 1_000, 40, 13, 10);
+        await program.methods
+            .cancelAndReplace(new anchor.BN(13), new anchor.BN(900), new anchor.BN(30), new anchor.BN(14))
+            .accounts({
+                owner: buyer.publicKey,
+                market: mktPda,
+                oldOrder: orderPda(mktPda, 13)[0],
+                newOrder: orderPda(mktPda, 14)[0],
+                tradingBalance: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([buyer])
+            .rpc();
+        const replaced = await fetchOrder(14);
+        assert.equal(replaced.quantity.toNumber(), 30);
+        assert.equal(replaced.displayQuantity.toNumber(), 10);
+        assert.equal(replaced.displayedRemaining.toNumber(), 10);
+    });
+});
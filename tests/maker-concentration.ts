@@ -0,0 +1,142 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, program, provider, userStatsPda } from "./helpers";
+
+describe("Maker concentration limit", () => {
+    const MARKET_NAME = "SHARE/MOCK";
+    const MAX_SHARE_BPS = 4_000; // 40%
+    const MIN_SIDE_VOLUME = 100;
+    const authority = provider.wallet;
+    const alice = Keypair.generate();
+    const bob = Keypair.generate();
+    const carol = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const statsOf = (kp: Keypair) => userStatsPda(mktPda, kp.publicKey)[0];
+
+    let nextId = 0;
+
+    async function sell(owner: Keypair, qty: number, withStats = true) {
+        const orderId = nextId;
+        const [order] = orderPda(mktPda, orderId);
+        await program.methods
+            .placeOrder({ sell: {} }, new anchor.BN(10_000), new anchor.BN(qty), new anchor.BN(orderId), new anchor.BN(0))
+            .accounts({
+                owner: owner.publicKey,
+                market: mktPda,
+                order,
+                userStats: withStats ? statsOf(owner) : null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([owner])
+            .rpc();
+        nextId += 1;
+        return { orderId, order };
+    }
+
+    before(async () => {
+        for (const kp of [alice, bob, carol]) {
+            await airdrop(kp.publicKey, 2);
+        }
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        for (const kp of [alice, bob, carol]) {
+            await program.methods
+                .initializeUserStats()
+                .accounts({ owner: kp.publicKey, market: mktPda, userStats: statsOf(kp), systemProgram: SystemProgram.programId })
+                .signers([kp])
+                .rpc();
+        }
+        await program.methods
+            .setMakerShareLimit(MAX_SHARE_BPS, new anchor.BN(MIN_SIDE_VOLUME))
+            .accounts({ authority: authority.publicKey, market: mktPda })
+            .rpc();
+    });
+
+    it("Requires the owner's stats while the cap is on", async () => {
+        await expectError(sell(alice, 1, false), "UserStatsRequired");
+    });
+
+    it("Exempts sides below the minimum volume", async () => {
+        await sell(alice, 20); // 100% of a 20-unit side
+        await sell(bob, 40);   // 67% of a 60-unit side
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.totalAskVolume.toNumber(), 60);
+    });
+
+    it("Allows a share exactly at the cap", async () => {
+        await sell(carol, 40); // 40 / 100 = 40%
+        const stats = await program.account.userStats.fetch(statsOf(carol));
+        assert.equal(stats.openAskVolume.toNumber(), 40);
+    });
+
+    it("Rejects one unit above the cap", async () => {
+        // Alice at 20 of 100: 33 more ⇒ 53/133 = 39.8%, 34 more ⇒ 54/134 = 40.3%
+        await expectError(sell(alice, 34), "MakerConcentrationExceeded");
+        const { orderId, order } = await sell(alice, 33);
+        let stats = await program.account.userStats.fetch(statsOf(alice));
+        assert.equal(stats.openAskVolume.toNumber(), 53);
+
+        // Cancelling releases the order's open volume from the stats
+        await program.methods
+            .cancelOrder(new anchor.BN(orderId), new anchor.BN(0))
+            .accounts({
+                owner: alice.publicKey,
+                market: mktPda,
+                order,
+                tradingBalance: null,
+                userStats: statsOf(alice),
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([alice])
+            .rpc();
+        stats = await program.account.userStats.fetch(statsOf(alice));
+        assert.equal(stats.openAskVolume.toNumber(), 20);
+    });
+
+    it("Fills release open volume from the owner's stats", async () => {
+        const [bid] = orderPda(mktPda, nextId);
+        await program.methods
+            .placeOrder({ buy: {} }, new anchor.BN(10_000), new anchor.BN(15), new anchor.BN(nextId), new anchor.BN(0))
+            .accounts({ owner: carol.publicKey, market: mktPda, order: bid, userStats: statsOf(carol), systemProgram: SystemProgram.programId })
+            .signers([carol])
+            .rpc();
+        nextId += 1;
+
+        const [bobAsk] = orderPda(mktPda, 1);
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: bobAsk,
+                bidOwner: carol.publicKey,
+                askOwner: bob.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+                bidTradingBalance: null,
+                bidUserStats: statsOf(carol),
+                askUserStats: statsOf(bob),
+            })
+            .rpc();
+
+        const bobStats = await program.account.userStats.fetch(statsOf(bob));
+        const carolStats = await program.account.userStats.fetch(statsOf(carol));
+        assert.equal(bobStats.openAskVolume.toNumber(), 25);
+        assert.equal(carolStats.openBidVolume.toNumber(), 0);
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.totalAskVolume.toNumber(), 85);
+    });
+
+    it("Disabling the cap drops the stats requirement", async () => {
+        await program.methods
+            .setMakerShareLimit(0, new anchor.BN(0))
+            .accounts({ authority: authority.publicKey, market: mktPda })
+            .rpc();
+        await sell(alice, 500, false);
+    });
+});
@@ -0,0 +1,134 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, marketPda, orderPda, program, provider, traderSeatPda } from "./helpers";
+
+describe("Maker-restricted markets", () => {
+    const MARKET_NAME = "MAKERS/MOCK";
+    const authority = provider.wallet;
+    const maker = Keypair.generate();
+    const outsider = Keypair.generate();
+    const crank = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [makerSeat] = traderSeatPda(mktPda, maker.publicKey);
+
+    let nextId = 0;
+
+    async function placeOrder(owner: Keypair, side: any, price: number, qty: number, seat: PublicKey | null) {
+        const orderId = nextId;
+        const [order] = orderPda(mktPda, orderId);
+        await program.methods
+            .placeOrder(side, new anchor.BN(price), new anchor.BN(qty), new anchor.BN(orderId), new anchor.BN(0))
+            .accounts({
+                owner: owner.publicKey,
+                market: mktPda,
+                order,
+                tradingBalance: null,
+                traderSeat: seat,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([owner])
+            .rpc();
+        nextId += 1;
+        return order;
+    }
+
+    before(async () => {
+        await airdrop(maker.publicKey, 5);
+        await airdrop(outsider.publicKey, 5);
+        await airdrop(crank.publicKey, 1);
+
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .setMakersRestricted(true)
+            .accounts({ authority: authority.publicKey, market: mktPda })
+            .rpc();
+        await program.methods
+            .addTrader(maker.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, traderSeat: makerSeat, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Rejects resting orders from accounts without a seat", async () => {
+        try {
+            await placeOrder(outsider, { buy: {} }, 10_000, 5, null);
+            assert.fail("Expected MakerSeatRequired error");
+        } catch (err: any) {
+            assert.include(err.message, "MakerSeatRequired");
+        }
+    });
+
+    it("Seat holders can rest orders", async () => {
+        const order = await placeOrder(maker, { sell: {} }, 10_000, 5, makerSeat);
+        const o = await program.account.order.fetch(order);
+        assert.ok(o.owner.equals(maker.publicKey));
+    });
+
+    it("Taking stays open: any crank can match against resting quotes", async () => {
+        // A bid from the seat holder crossed by an unseated crank
+        const ask = orderPda(mktPda, 0)[0];
+        const bid = await placeOrder(maker, { buy: {} }, 10_000, 5, makerSeat);
+
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: crank.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: maker.publicKey,
+                askOwner: maker.publicKey,
+                feeConfig: null,
+                treasury: crank.publicKey,
+                bidTradingBalance: null,
+            })
+            .signers([crank])
+            .rpc();
+
+        const a = await program.account.order.fetch(ask);
+        assert.deepEqual(a.status, { filled: {} });
+    });
+
+    it("Revoking a seat blocks new resting orders but keeps existing ones live", async () => {
+        const resting = await placeOrder(maker, { buy: {} }, 9_000, 3, makerSeat);
+
+        await program.methods
+            .removeTrader(maker.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, traderSeat: makerSeat })
+            .rpc();
+        assert.isNull(await provider.connection.getAccountInfo(makerSeat), "seat should be closed");
+
+        try {
+            await placeOrder(maker, { buy: {} }, 9_000, 3, null);
+            assert.fail("Expected MakerSeatRequired error");
+        } catch (err: any) {
+            assert.include(err.message, "MakerSeatRequired");
+        }
+
+        const o = await program.account.order.fetch(resting);
+        assert.deepEqual(o.status, { open: {} });
+
+        // The revoked maker can still cancel and reclaim escrow
+        await program.methods
+            .cancelOrder(new anchor.BN(2), new anchor.BN(0))
+            .accounts({ owner: maker.publicKey, market: mktPda, order: resting, tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([maker])
+            .rpc();
+        const cancelled = await program.account.order.fetch(resting);
+        assert.deepEqual(cancelled.status, { cancelled: {} });
+    });
+
+    it("Lifting the restriction opens placement to everyone", async () => {
+        await program.methods
+            .setMakersRestricted(false)
+            .accounts({ authority: authority.publicKey, market: mktPda })
+            .rpc();
+        const order = await placeOrder(outsider, { sell: {} }, 11_000, 1, null);
+        const o = await program.account.order.fetch(order);
+        assert.ok(o.owner.equals(outsider.publicKey));
+    });
+});
@@ -0,0 +1,98 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, feeConfigPda, marketPda, matchPair, place, program, provider, sleep } from "./helpers";
+
+describe("Maker / taker fee rates", () => {
+    const MARKET_NAME = "MAKERTAKER/MOCK";
+    const PRICE = 10_000;
+    const QTY = 10;
+    const GROSS = PRICE * QTY;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const treasury = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+
+    const setTakerFee = (takerFeeBps: number) =>
+        program.methods
+            .setTakerFee(takerFeeBps)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, roles: null })
+            .rpc();
+
+    async function matchEvent(bid: PublicKey, ask: PublicKey): Promise<any> {
+        let event: any = null;
+        const listener = program.addEventListener("tradeExecutedEvent", (e) => (event = e));
+        await matchPair(bid, ask, { feeConfig: feePda, treasury: treasury.publicKey });
+        await sleep(1000);
+        await program.removeEventListener(listener);
+        return event;
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller, treasury]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeFeeConfig(20, treasury.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Starts with one rate for makers and takers", async () => {
+        const fee = await program.account.feeConfig.fetch(feePda);
+        assert.equal(fee.feeBps, 20);
+        assert.equal(fee.takerFeeBps, 20);
+    });
+
+    it("Rejects a taker rate above the cap", async () => {
+        try {
+            await setTakerFee(501);
+            assert.fail("Expected FeeBpsTooHigh error");
+        } catch (err: any) {
+            assert.include(err.message, "FeeBpsTooHigh");
+        }
+    });
+
+    it("Charges a resting ask the maker rate", async () => {
+        await setTakerFee(60);
+        const [ask] = await place(mktPda, seller, { sell: {} }, PRICE, QTY);
+        const placed = await program.account.order.fetch(ask);
+        assert.equal(placed.feeBps, 20);
+        assert.equal(placed.takerFeeBps, 60);
+
+        const [bid] = await place(mktPda, buyer, { buy: {} }, PRICE, QTY);
+        const event = await matchEvent(bid, ask);
+        assert.isFalse(event.askIsTaker);
+        assert.equal(event.feeBps, 20);
+        assert.equal(event.feeAmount.toNumber(), (GROSS * 20) / 10_000);
+    });
+
+    it("Charges an incoming ask the taker rate, never more than the escrow", async () => {
+        const [bid] = await place(mktPda, buyer, { buy: {} }, PRICE, QTY);
+        const [ask] = await place(mktPda, seller, { sell: {} }, PRICE, QTY);
+        const sellerBefore = await provider.connection.getBalance(seller.publicKey);
+
+        const event = await matchEvent(bid, ask);
+        assert.isTrue(event.askIsTaker);
+        assert.equal(event.feeBps, 60);
+        const fee = (GROSS * 60) / 10_000;
+        assert.equal(event.feeAmount.toNumber(), fee);
+        assert.equal((await provider.connection.getBalance(seller.publicKey)) - sellerBefore, GROSS - fee);
+        assert.equal((await program.account.order.fetch(bid)).escrowLamports.toNumber(), 0);
+    });
+
+    it("update_fee_config sets both rates back to one", async () => {
+        await program.methods
+            .updateFeeConfig(30, treasury.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda })
+            .rpc();
+        const market = await program.account.market.fetch(mktPda);
+        assert.equal(market.feeBps, 30);
+        assert.equal(market.takerFeeBps, 30);
+    });
+});
@@ -0,0 +1,113 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { aggregateMarkets, listMarkets, loadAllMarkets, MarketEntry } from "../client/markets";
+import { airdrop, feeVaultPda, listingPda, marketPda, orderPda, program, provider, registryCount, registryPda } from "./helpers";
+
+describe("Market discovery (client)", () => {
+    const NAMES = ["DISC-A/MOCK", "DISC-B/MOCK", "DISC-C/MOCK"];
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const markets = NAMES.map((name) => marketPda(authority.publicKey, name)[0]);
+    const [busy, quiet, closed] = markets;
+    let firstListing: number;
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        firstListing = await registryCount();
+        for (const [i, name] of NAMES.entries()) {
+            await program.methods
+                .initializeMarket(name, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+                .accounts({
+                    authority: authority.publicKey,
+                    market: marketPda(authority.publicKey, name)[0],
+                    registry: registryPda()[0],
+                    listing: listingPda(firstListing + i)[0],
+                    systemProgram: SystemProgram.programId,
+                })
+                .rpc();
+        }
+
+        // `busy`: two fills and 4 bid units left resting
+        for (let i = 0; i < 2; i++) {
+            const [bid] = orderPda(busy, 2 * i);
+            const [ask] = orderPda(busy, 2 * i + 1);
+            await program.methods
+                .placeOrder({ buy: {} }, new anchor.BN(10_000), new anchor.BN(3), new anchor.BN(2 * i), new anchor.BN(0))
+                .accounts({ owner: buyer.publicKey, market: busy, order: bid, systemProgram: SystemProgram.programId })
+                .signers([buyer])
+                .rpc();
+            await program.methods
+                .placeOrder({ sell: {} }, new anchor.BN(10_000), new anchor.BN(1), new anchor.BN(2 * i + 1), new anchor.BN(0))
+                .accounts({ owner: seller.publicKey, market: busy, order: ask, systemProgram: SystemProgram.programId })
+                .signers([seller])
+                .rpc();
+            const mkt = await program.account.market.fetch(busy);
+            await program.methods
+                .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+                .accounts({
+                    matcher: authority.publicKey,
+                    market: busy,
+                    bidOrder: bid,
+                    askOrder: ask,
+                    bidOwner: buyer.publicKey,
+                    askOwner: seller.publicKey,
+                    feeConfig: null,
+                    treasury: mkt.feeRecipient,
+                    bidTradingBalance: null,
+                })
+                .rpc();
+        }
+
+        // `closed`: delisted, then archived and closed
+        await program.methods
+            .delistMarket(new anchor.BN(firstListing + 2))
+            .accounts({ authority: authority.publicKey, market: closed })
+            .rpc();
+        await program.methods.beginArchive().accounts({ authority: authority.publicKey, market: closed }).rpc();
+        await program.methods
+            .closeMarket()
+            .accounts({ authority: authority.publicKey, market: closed, feeVault: feeVaultPda(closed)[0] })
+            .rpc();
+    });
+
+    it("Pages through every market", async () => {
+        const seen: MarketEntry[] = [];
+        let cursor: number | null = 0;
+        let pages = 0;
+        while (cursor !== null) {
+            const page = await listMarkets(program as any, cursor, 2);
+            assert.isAtMost(page.entries.length, 2);
+            seen.push(...page.entries);
+            cursor = page.nextCursor;
+            pages += 1;
+        }
+        assert.isAbove(pages, 1);
+        const keys = seen.map((e) => e.address.toBase58());
+        assert.equal(new Set(keys).size, keys.length, "pages must not overlap");
+        assert.include(keys, busy.toBase58());
+        assert.include(keys, quiet.toBase58());
+        assert.notInclude(keys, closed.toBase58());
+    });
+
+    it("Hydrates markets and flags closed ones", async () => {
+        const loaded = await loadAllMarkets(program as any, markets);
+        assert.deepEqual(loaded.map((m) => m.address.toBase58()), markets.map((m) => m.toBase58()));
+        assert.equal(loaded[0].account.marketName, NAMES[0]);
+        assert.equal(loaded[1].account.marketName, NAMES[1]);
+        assert.isNull(loaded[2].account);
+    });
+
+    it("Aggregates open interest and ranks by activity", async () => {
+        const loaded = await loadAllMarkets(program as any, markets);
+        const agg = aggregateMarkets(loaded);
+        assert.equal(agg.totalMarkets, 2);
+        assert.equal(agg.closedMarkets, 1);
+        assert.equal(agg.totalOpenInterest, 4);
+        assert.ok(agg.topMarkets[0].address.equals(busy));
+        assert.equal(agg.topMarkets[0].fills, 2);
+        assert.equal(agg.topMarkets[0].lastTradePrice, 10_000);
+    });
+});
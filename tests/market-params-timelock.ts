@@ -0,0 +1,190 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import {
+    airdrop,
+    chainTime,
+    feeConfigPda,
+    marketPda,
+    matchPair,
+    place,
+    program,
+    provider,
+    sleep,
+    stagedParamsPda,
+} from "./helpers";
+
+describe("Market parameter timelock", () => {
+    const MARKET_NAME = "TIMELOCK/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const treasury = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+    const [stagedPda] = stagedParamsPda(mktPda);
+
+    const TIMELOCK_SECS = 3;
+
+    async function placePair(bidId: number, askId: number) {
+        const [bid] = await place(mktPda, buyer, { buy: {} }, 10_000, 10, { orderId: bidId });
+        const [ask] = await place(mktPda, seller, { sell: {} }, 10_000, 10, { orderId: askId });
+        return [bid, ask];
+    }
+
+    before(async () => {
+        await airdrop(buyer.publicKey, 5);
+        await airdrop(seller.publicKey, 5);
+        await airdrop(treasury.publicKey, 1);
+        await airdrop(stranger.publicKey, 1);
+
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeFeeConfig(0, treasury.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Applies params immediately while no timelock is set", async () => {
+        await program.methods
+            .updateMarketParams({ feeBps: 0, treasury: treasury.publicKey, paramsTimelockSecs: new anchor.BN(TIMELOCK_SECS), priceBandBps: new anchor.BN(0) })
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda })
+            .rpc();
+
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.paramsTimelockSecs.toNumber(), TIMELOCK_SECS);
+    });
+
+    it("Rejects immediate updates once the timelock is active", async () => {
+        try {
+            await program.methods
+                .updateFeeConfig(500, treasury.publicKey)
+                .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda })
+                .rpc();
+            assert.fail("Expected ParamsTimelocked error");
+        } catch (err: any) {
+            assert.include(err.message, "ParamsTimelocked");
+        }
+    });
+
+    it("Rejects staging with an effective time inside the timelock", async () => {
+        const now = await chainTime();
+        try {
+            await program.methods
+                .stageMarketParams(
+                    { feeBps: 500, treasury: treasury.publicKey, paramsTimelockSecs: new anchor.BN(TIMELOCK_SECS), priceBandBps: new anchor.BN(0) },
+                    new anchor.BN(now)
+                )
+                .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, stagedParams: stagedPda, systemProgram: SystemProgram.programId })
+                .rpc();
+            assert.fail("Expected TimelockTooShort error");
+        } catch (err: any) {
+            assert.include(err.message, "TimelockTooShort");
+        }
+    });
+
+    it("Discards a staged set before it becomes effective", async () => {
+        const now = await chainTime();
+        await program.methods
+            .stageMarketParams(
+                { feeBps: 500, treasury: treasury.publicKey, paramsTimelockSecs: new anchor.BN(TIMELOCK_SECS), priceBandBps: new anchor.BN(0) },
+                new anchor.BN(now + 60)
+            )
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, stagedParams: stagedPda, systemProgram: SystemProgram.programId })
+            .rpc();
+
+        await program.methods
+            .discardStagedParams()
+            .accounts({ authority: authority.publicKey, market: mktPda, stagedParams: stagedPda })
+            .rpc();
+
+        assert.isNull(await provider.connection.getAccountInfo(stagedPda), "staged params should be closed");
+        const fee = await program.account.feeConfig.fetch(feePda);
+        assert.equal(fee.feeBps, 0);
+    });
+
+    it("Rejects discard by a non-authority", async () => {
+        const now = await chainTime();
+        await program.methods
+            .stageMarketParams(
+                { feeBps: 500, treasury: treasury.publicKey, paramsTimelockSecs: new anchor.BN(TIMELOCK_SECS), priceBandBps: new anchor.BN(0) },
+                new anchor.BN(now + TIMELOCK_SECS + 1)
+            )
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, stagedParams: stagedPda, systemProgram: SystemProgram.programId })
+            .rpc();
+
+        try {
+            await program.methods
+                .discardStagedParams()
+                .accounts({ authority: stranger.publicKey, market: mktPda, stagedParams: stagedPda })
+                .signers([stranger])
+                .rpc();
+            assert.fail("Expected Unauthorized error");
+        } catch (err: any) {
+            assert.include(err.message, "Unauthorized");
+        }
+    });
+
+    it("Staged params do not affect matching before the effective time", async () => {
+        const [bid, ask] = await placePair(0, 1);
+        const treasuryBefore = await provider.connection.getBalance(treasury.publicKey);
+
+        await matchPair(bid, ask, { feeConfig: feePda, treasury: treasury.publicKey });
+
+        const treasuryAfter = await provider.connection.getBalance(treasury.publicKey);
+        assert.equal(treasuryAfter, treasuryBefore, "no fee should be charged before the staged fee applies");
+
+        try {
+            await program.methods
+                .applyStagedParams()
+                .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, stagedParams: stagedPda })
+                .signers([stranger])
+                .rpc();
+            assert.fail("Expected TimelockNotElapsed error");
+        } catch (err: any) {
+            assert.include(err.message, "TimelockNotElapsed");
+        }
+    });
+
+    it("Anyone can apply staged params after the effective time", async () => {
+        await sleep((TIMELOCK_SECS + 2) * 1000);
+
+        await program.methods
+            .applyStagedParams()
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, stagedParams: stagedPda })
+            .signers([stranger])
+            .rpc();
+
+        const fee = await program.account.feeConfig.fetch(feePda);
+        assert.equal(fee.feeBps, 500);
+        assert.isNull(await provider.connection.getAccountInfo(stagedPda), "staged params should be closed");
+
+        // The applied fee now affects fills: 5% of 10 * 10_000 lamports.
+        const [bid, ask] = await placePair(2, 3);
+        const treasuryBefore = await provider.connection.getBalance(treasury.publicKey);
+        await matchPair(bid, ask, { feeConfig: feePda, treasury: treasury.publicKey });
+        const treasuryAfter = await provider.connection.getBalance(treasury.publicKey);
+        assert.equal(treasuryAfter - treasuryBefore, 5_000);
+    });
+
+    it("Pausing stays immediate under a timelock", async () => {
+        await program.methods
+            .pauseMarket()
+            .accounts({ authority: authority.publicKey, market: mktPda })
+            .rpc();
+        let mkt = await program.account.market.fetch(mktPda);
+        assert.isTrue(mkt.isPaused);
+
+        await program.methods
+            .resumeMarket()
+            .accounts({ authority: authority.publicKey, market: mktPda })
+            .rpc();
+        mkt = await program.account.market.fetch(mktPda);
+        assert.isFalse(mkt.isPaused);
+    });
+});
@@ -0,0 +1,111 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, place, program, provider, sleep } from "./helpers";
+
+describe("Market pause", () => {
+    const MARKET_NAME = "MPAUSE/MOCK";
+    const PRICE = 10_000;
+    const QTY = 3;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [bidPda] = orderPda(mktPda, 0);
+    const [askPda] = orderPda(mktPda, 1);
+
+    const pauseMarket = (signer?: Keypair) => {
+        const call = program.methods
+            .pauseMarket()
+            .accounts({ authority: (signer ?? authority).publicKey, market: mktPda, roles: null });
+        return signer ? call.signers([signer]).rpc() : call.rpc();
+    };
+
+    const resumeMarket = () =>
+        program.methods.resumeMarket().accounts({ authority: authority.publicKey, market: mktPda, roles: null }).rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller, stranger]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        // A crossing pair left resting when the pause lands
+        await place(mktPda, buyer, { buy: {} }, PRICE, QTY, { orderId: 0 });
+        await place(mktPda, seller, { sell: {} }, PRICE, QTY, { orderId: 1 });
+    });
+
+    it("Only the authority may pause, with an event cranks can watch", async () => {
+        await expectError(pauseMarket(stranger), "Unauthorized");
+
+        let event: any = null;
+        const listener = program.addEventListener("marketPausedEvent", (e) => (event = e));
+        await pauseMarket();
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        assert.isTrue((await program.account.market.fetch(mktPda)).isPaused);
+        assert.isNotNull(event);
+        assert.isTrue(event.isPaused);
+        assert.isAbove(event.timestamp.toNumber(), 0);
+        await expectError(pauseMarket(), "MarketPaused");
+    });
+
+    it("Rejects place_order and match_orders while paused", async () => {
+        await expectError(place(mktPda, buyer, { buy: {} }, PRICE, QTY, { orderId: 2 }), "MarketPaused");
+        await expectError(
+            program.methods
+                .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+                .accounts({
+                    matcher: authority.publicKey,
+                    market: mktPda,
+                    bidOrder: bidPda,
+                    askOrder: askPda,
+                    bidOwner: buyer.publicKey,
+                    askOwner: seller.publicKey,
+                    feeConfig: null,
+                    treasury: authority.publicKey,
+                })
+                .rpc(),
+            "MarketPaused"
+        );
+    });
+
+    it("Still cancels a buy, returning its escrow, and closes it", async () => {
+        const escrow = (await program.account.order.fetch(bidPda)).escrowLamports.toNumber();
+        assert.equal(escrow, PRICE * QTY);
+        const before = await provider.connection.getBalance(buyer.publicKey);
+
+        await program.methods
+            .cancelOrder(new anchor.BN(0), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: bidPda, tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        const bid = await program.account.order.fetch(bidPda);
+        assert.deepEqual(bid.status, { cancelled: {} });
+        assert.equal(bid.escrowLamports.toNumber(), 0);
+        assert.equal((await provider.connection.getBalance(buyer.publicKey)) - before, escrow);
+
+        await program.methods
+            .closeOrder(new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: bidPda, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        assert.isNull(await provider.connection.getAccountInfo(bidPda));
+    });
+
+    it("Resuming reopens placement, and only a paused market resumes", async () => {
+        let event: any = null;
+        const listener = program.addEventListener("marketPausedEvent", (e) => (event = e));
+        await resumeMarket();
+        await sleep(1000);
+        await program.removeEventListener(listener);
+        assert.isFalse(event.isPaused);
+
+        await expectError(resumeMarket(), "MarketNotPaused");
+        await place(mktPda, buyer, { buy: {} }, PRICE, QTY, { orderId: 2 });
+        assert.equal((await program.account.market.fetch(mktPda)).nextOrderId.toNumber(), 3);
+    });
+});
@@ -0,0 +1,95 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { listMarkets } from "../client/markets";
+import { airdrop, expectError, listingPda, marketPda, program, provider, registryCount, registryPda } from "./helpers";
+
+describe("Market registry", () => {
+    const authority = provider.wallet;
+    const creator = Keypair.generate();
+    const stranger = Keypair.generate();
+    const [registry] = registryPda();
+
+    function initialize(name: string, signer: Keypair | null, listing: PublicKey) {
+        const owner = signer?.publicKey ?? authority.publicKey;
+        return program.methods
+            .initializeMarket(name, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({
+                authority: owner,
+                market: marketPda(owner, name)[0],
+                registry,
+                listing,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers(signer ? [signer] : [])
+            .rpc();
+    }
+
+    const delist = (signer: Keypair | null, index: number, market: PublicKey) =>
+        program.methods
+            .delistMarket(new anchor.BN(index))
+            .accounts({ authority: signer?.publicKey ?? authority.publicKey, market })
+            .signers(signer ? [signer] : [])
+            .rpc();
+
+    async function listed(): Promise<string[]> {
+        const keys: string[] = [];
+        let cursor: number | null = 0;
+        while (cursor !== null) {
+            const page = await listMarkets(program as any, cursor);
+            keys.push(...page.entries.map((e) => e.address.toBase58()));
+            cursor = page.nextCursor;
+        }
+        return keys;
+    }
+
+    let ownIndex: number;
+    let creatorIndex: number;
+    const ownMarket = marketPda(authority.publicKey, "REGISTRY-A/MOCK")[0];
+    const creatorMarket = marketPda(creator.publicKey, "REGISTRY-B/MOCK")[0];
+
+    before(async () => {
+        for (const kp of [creator, stranger]) await airdrop(kp.publicKey, 2);
+    });
+
+    it("Lists a market created with the registry at the next index", async () => {
+        ownIndex = await registryCount();
+        await initialize("REGISTRY-A/MOCK", null, listingPda(ownIndex)[0]);
+        assert.equal(await registryCount(), ownIndex + 1);
+
+        const listing = await program.account.marketListing.fetch(listingPda(ownIndex)[0]);
+        assert.equal(listing.registryIndex.toNumber(), ownIndex);
+        assert.ok(listing.market.equals(ownMarket));
+        assert.ok(listing.creator.equals(authority.publicKey));
+        assert.equal(listing.marketName, "REGISTRY-A/MOCK");
+        assert.isTrue(listing.active);
+        assert.include(await listed(), ownMarket.toBase58());
+    });
+
+    it("Lists every new market, at the registry's count only", async () => {
+        const count = await registryCount();
+        await expectError(initialize("REGISTRY-X/MOCK", null, listingPda(count + 1)[0]), "ConstraintSeeds");
+        assert.isNull(await provider.connection.getAccountInfo(marketPda(authority.publicKey, "REGISTRY-X/MOCK")[0]));
+    });
+
+    it("Is delisted by the market authority only, keeping the index", async () => {
+        await expectError(delist(stranger, ownIndex, ownMarket), "Unauthorized");
+        const count = await registryCount();
+        await delist(null, ownIndex, ownMarket);
+
+        assert.isFalse((await program.account.marketListing.fetch(listingPda(ownIndex)[0])).active);
+        assert.equal(await registryCount(), count);
+        assert.notInclude(await listed(), ownMarket.toBase58());
+        await expectError(delist(null, ownIndex, ownMarket), "MarketNotListed");
+    });
+
+    it("Is delisted by the protocol admin for any market", async () => {
+        creatorIndex = await registryCount();
+        await initialize("REGISTRY-B/MOCK", creator, listingPda(creatorIndex)[0]);
+        assert.ok((await program.account.marketListing.fetch(listingPda(creatorIndex)[0])).creator.equals(creator.publicKey));
+
+        // The provider wallet is the protocol admin, not this market's authority
+        await delist(null, creatorIndex, creatorMarket);
+        assert.isFalse((await program.account.marketListing.fetch(listingPda(creatorIndex)[0])).active);
+    });
+});
@@ -0,0 +1,108 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, feeConfigPda, marketPda, orderPda, program, provider, sleep } from "./helpers";
+
+describe("Market snapshots (poke_market)", () => {
+    const MARKET_NAME = "POKE/MOCK";
+    const POKE_INTERVAL_SLOTS = 25;
+    const PRICE = 10_000;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+
+    const poke = () => program.methods.pokeMarket().accounts({ market: mktPda, feeConfig: feePda }).rpc();
+
+    async function pokeAndCapture(): Promise<any> {
+        let snapshot: any = null;
+        const listener = program.addEventListener("marketSnapshotEvent", (e) => (snapshot = e));
+        await poke();
+        await sleep(1000);
+        await program.removeEventListener(listener);
+        return snapshot;
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeFeeConfig(30, authority.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .rpc();
+
+        // One 2-unit trade, leaving 3 bid units resting
+        const [bid] = orderPda(mktPda, 0);
+        const [ask] = orderPda(mktPda, 1);
+        await program.methods
+            .placeOrder({ buy: {} }, new anchor.BN(PRICE), new anchor.BN(5), new anchor.BN(0), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: bid, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        await program.methods
+            .placeOrder({ sell: {} }, new anchor.BN(PRICE), new anchor.BN(2), new anchor.BN(1), new anchor.BN(0))
+            .accounts({ owner: seller.publicKey, market: mktPda, order: ask, systemProgram: SystemProgram.programId })
+            .signers([seller])
+            .rpc();
+        const mkt = await program.account.market.fetch(mktPda);
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: feePda,
+                treasury: mkt.feeRecipient,
+                bidTradingBalance: null,
+            })
+            .rpc();
+    });
+
+    it("Emits a snapshot matching the market account", async () => {
+        const { eventSeq } = await program.account.market.fetch(mktPda);
+        const snapshot = await pokeAndCapture();
+        assert.isNotNull(snapshot);
+
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.lastPokeSlot.toNumber(), snapshot.slot.toNumber());
+        assert.equal(snapshot.nextOrderId.toNumber(), mkt.nextOrderId.toNumber());
+        assert.equal(snapshot.openOrderCount.toNumber(), 1);
+        assert.equal(snapshot.totalBidVolume.toNumber(), 3);
+        assert.equal(snapshot.totalAskVolume.toNumber(), 0);
+        assert.equal(snapshot.lastTradePrice.toNumber(), PRICE);
+        assert.equal(snapshot.lastTradeTs.toNumber(), mkt.lastTradeTs.toNumber());
+        assert.equal(snapshot.feeBps, 30);
+        assert.ok(snapshot.feeRecipient.equals(mkt.feeRecipient));
+        assert.equal(snapshot.dustLamports.toNumber(), mkt.dustLamports.toNumber());
+        assert.isFalse(snapshot.isPaused);
+        assert.isFalse(snapshot.isArchiving);
+        // The snapshot takes the next event_seq, chained like any other event
+        assert.equal(snapshot.eventSeq.toNumber(), eventSeq.toNumber() + 1);
+        assert.equal(snapshot.eventSeq.toNumber(), mkt.eventSeq.toNumber());
+        assert.deepEqual(Buffer.from(snapshot.stateHash), Buffer.from(mkt.stateHash));
+    });
+
+    it("Rate-limits pokes per market", async () => {
+        try {
+            await poke();
+            assert.fail("Expected PokeTooSoon error");
+        } catch (err: any) {
+            assert.include(err.message, "PokeTooSoon");
+        }
+
+        const { lastPokeSlot } = await program.account.market.fetch(mktPda);
+        while ((await provider.connection.getSlot("confirmed")) < lastPokeSlot.toNumber() + POKE_INTERVAL_SLOTS) {
+            await sleep(400);
+        }
+        const snapshot = await pokeAndCapture();
+        assert.isAtLeast(snapshot.slot.toNumber(), lastPokeSlot.toNumber() + POKE_INTERVAL_SLOTS);
+    });
+});
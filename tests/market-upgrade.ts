@@ -0,0 +1,181 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, feeVaultPda, marketPda, orderPda, program, provider, sleep } from "./helpers";
+
+describe("Market upgrades", () => {
+    const MARKET_NAME = "UPGRADE/MOCK";
+    const authority = provider.wallet;
+    const stranger = Keypair.generate();
+
+    // tests/fixtures/legacy-market.json (loaded by the validator, see Anchor.toml):
+    // a market in the original 102-byte layout — no creator, fee recipient,
+    // tick or lot size — created by the seed-[9; 32] keypair, next order #8.
+    const legacyAuthority = Keypair.fromSeed(new Uint8Array(32).fill(9));
+    const LEGACY_NAME = "LEGACY/MOCK-BEFORE-VERSIONING-V0";
+    const [legacyPda] = marketPda(legacyAuthority.publicKey, LEGACY_NAME);
+    const LEGACY_LEN = 102;
+    // Market::LEN and Market::VERSION today
+    const MARKET_LEN = 816;
+    const MARKET_VERSION = 7;
+
+    // tests/fixtures/legacy-order.json: its order #7, a cancelled SELL written
+    // at Order version 2 (no closed_at or settles_to_balance), owned by the
+    // seed-[11; 32] keypair.
+    const legacyOwner = Keypair.fromSeed(new Uint8Array(32).fill(11));
+    const [legacyOrder] = orderPda(legacyPda, 7);
+    const LEGACY_ORDER_LEN = 316;
+    // Order::LEN and Order::VERSION today
+    const ORDER_LEN = 325;
+    const ORDER_VERSION = 4;
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const accountLen = async (key: PublicKey) => (await provider.connection.getAccountInfo(key))!.data.length;
+
+    const upgrade = (signer: Keypair, market: PublicKey) =>
+        program.methods
+            .upgradeMarket()
+            .accounts({ authority: signer.publicKey, market, systemProgram: SystemProgram.programId })
+            .signers([signer])
+            .rpc();
+
+    const upgradeOrder = (signer: Keypair, order: PublicKey) =>
+        program.methods
+            .upgradeOrder()
+            .accounts({ owner: signer.publicKey, market: legacyPda, order, systemProgram: SystemProgram.programId })
+            .signers([signer])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [stranger, legacyAuthority, legacyOwner]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Creates new markets at the current version", async () => {
+        assert.equal((await program.account.market.fetch(mktPda)).version, MARKET_VERSION);
+        await expectError(
+            program.methods
+                .upgradeMarket()
+                .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+                .rpc(),
+            "MarketUpToDate"
+        );
+    });
+
+    it("Can't use a legacy market until it is upgraded", async () => {
+        assert.equal(await accountLen(legacyPda), LEGACY_LEN);
+        await expectError(
+            program.methods
+                .startAuction(new anchor.BN(0))
+                .accounts({ authority: legacyAuthority.publicKey, market: legacyPda, roles: null })
+                .signers([legacyAuthority])
+                .rpc(),
+            "AccountDidNotDeserialize"
+        );
+    });
+
+    it("Rejects an upgrade by anyone but the market authority", async () => {
+        await expectError(upgrade(stranger, legacyPda), "Unauthorized");
+        assert.equal(await accountLen(legacyPda), LEGACY_LEN);
+    });
+
+    it("Grows the account, tops up its rent and fills in the new defaults", async () => {
+        let event: any = null;
+        const listener = program.addEventListener("marketUpgradedEvent", (e) => (event = e));
+        await upgrade(legacyAuthority, legacyPda);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        const info = (await provider.connection.getAccountInfo(legacyPda))!;
+        assert.equal(info.data.length, MARKET_LEN);
+        assert.equal(info.lamports, await provider.connection.getMinimumBalanceForRentExemption(MARKET_LEN));
+
+        const market = await program.account.market.fetch(legacyPda);
+        assert.equal(market.version, MARKET_VERSION);
+        assert.equal(market.marketName, LEGACY_NAME);
+        assert.ok(market.authority.equals(legacyAuthority.publicKey));
+        // The original layout had neither: the authority seeded the PDA and
+        // fees went to the fee vault
+        assert.ok(market.creator.equals(legacyAuthority.publicKey));
+        assert.ok(market.feeRecipient.equals(feeVaultPda(legacyPda)[0]));
+        assert.equal(market.nextOrderId.toNumber(), 8);
+        assert.equal(market.tickSize.toNumber(), 1);
+        assert.equal(market.lotSize.toNumber(), 1);
+        assert.equal(market.sweepDelaySecs.toNumber(), 7 * 24 * 60 * 60);
+        // The TWAP accumulator starts from the legacy market's last trade
+        assert.equal(market.lastTwapUpdateTs.toNumber(), market.lastTradeTs.toNumber());
+        assert.equal(market.priceCumulative.toString(), "0");
+        assert.isTrue(market.bidBook.equals(PublicKey.default));
+
+        assert.equal(event.fromVersion, 0);
+        assert.equal(event.toVersion, MARKET_VERSION);
+        assert.equal(event.fromLen.toNumber(), LEGACY_LEN);
+        assert.equal(event.toLen.toNumber(), MARKET_LEN);
+
+        await expectError(upgrade(legacyAuthority, legacyPda), "MarketUpToDate");
+    });
+
+    it("Trades on the upgraded market", async () => {
+        const { nextOrderId } = await program.account.market.fetch(legacyPda);
+        const [orderKey] = orderPda(legacyPda, nextOrderId.toNumber());
+        await program.methods
+            .placeOrder({ buy: {} }, new anchor.BN(1_001), new anchor.BN(3), nextOrderId, new anchor.BN(0))
+            .accounts({ owner: stranger.publicKey, market: legacyPda, order: orderKey, systemProgram: SystemProgram.programId })
+            .signers([stranger])
+            .rpc();
+
+        const order = await program.account.order.fetch(orderKey);
+        assert.equal(order.version, ORDER_VERSION);
+        assert.equal(order.escrowLamports.toNumber(), 3_003);
+        assert.equal((await program.account.market.fetch(legacyPda)).totalBidVolume.toNumber(), 3);
+    });
+
+    it("Rejects an order upgrade by anyone but the order owner", async () => {
+        await expectError(upgradeOrder(stranger, legacyOrder), "Unauthorized");
+        assert.equal(await accountLen(legacyOrder), LEGACY_ORDER_LEN);
+    });
+
+    it("Grows a legacy order and starts its sweep delay from the upgrade", async () => {
+        let event: any = null;
+        const listener = program.addEventListener("orderUpgradedEvent", (e) => (event = e));
+        await upgradeOrder(legacyOwner, legacyOrder);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        const info = (await provider.connection.getAccountInfo(legacyOrder))!;
+        assert.equal(info.data.length, ORDER_LEN);
+        assert.equal(info.lamports, await provider.connection.getMinimumBalanceForRentExemption(ORDER_LEN));
+
+        const order = await program.account.order.fetch(legacyOrder);
+        assert.equal(order.version, ORDER_VERSION);
+        assert.equal(order.orderId.toNumber(), 7);
+        assert.deepEqual(order.status, { cancelled: {} });
+        assert.equal(order.closedAt.toNumber(), event.timestamp.toNumber());
+        assert.isFalse(order.settlesToBalance);
+
+        assert.equal(event.fromVersion, 2);
+        assert.equal(event.toVersion, ORDER_VERSION);
+        assert.equal(event.fromLen.toNumber(), LEGACY_ORDER_LEN);
+        assert.equal(event.toLen.toNumber(), ORDER_LEN);
+
+        await expectError(upgradeOrder(legacyOwner, legacyOrder), "OrderUpToDate");
+    });
+
+    it("Closes the upgraded order", async () => {
+        await program.methods
+            .closeOrder(new anchor.BN(7))
+            .accounts({
+                owner: legacyOwner.publicKey,
+                market: legacyPda,
+                order: legacyOrder,
+                rentSubsidyVault: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([legacyOwner])
+            .rpc();
+        assert.isNull(await provider.connection.getAccountInfo(legacyOrder));
+    });
+});
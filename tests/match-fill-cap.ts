@@ -0,0 +1,89 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, escrowVaultPda, expectError, marketPda, orderPda, place, program, provider } from "./helpers";
+
+describe("Matcher fill cap", () => {
+    const MARKET_NAME = "CAP/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const balance = (key: PublicKey) => provider.connection.getBalance(key);
+    const fetchOrder = (orderId: number) => program.account.order.fetch(orderPda(mktPda, orderId)[0]);
+
+    const match = (bidId: number, askId: number, maxFill: number | null) =>
+        program.methods
+            .matchOrdersV2({
+                maxSlippageBps: 0,
+                minExpectedBidRemaining: new anchor.BN(0),
+                minExpectedAskRemaining: new anchor.BN(0),
+                maxFillQuantity: maxFill === null ? null : new anchor.BN(maxFill),
+                minFillQuantity: new anchor.BN(0),
+            })
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, bidId)[0],
+                askOrder: orderPda(mktPda, askId)[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+            })
+            .rpc();
+
+    // Matches bid/ask with each cap in turn; returns what buyer, seller
+    // and the bid's escrow vault gained
+    async function fillInSteps(bidId: number, askId: number, caps: (number | null)[]) {
+        const [bidVault] = escrowVaultPda(mktPda, bidId);
+        const before = await Promise.all([balance(buyer.publicKey), balance(seller.publicKey), balance(bidVault)]);
+        for (const cap of caps) await match(bidId, askId, cap);
+        const after = await Promise.all([balance(buyer.publicKey), balance(seller.publicKey), balance(bidVault)]);
+        return after.map((value, i) => value - before[i]);
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        // Two identical 100-unit crosses: bid 100 @ 1_200 vs ask 100 @ 1_000
+        for (const [bidId, askId] of [[0, 1], [2, 3]]) {
+            await place(mktPda, buyer, { buy: {} }, 1_200, 100, { orderId: bidId });
+            await place(mktPda, seller, { sell: {} }, 1_000, 100, { orderId: askId });
+        }
+    });
+
+    it("Rejects a zero cap", async () => {
+        await expectError(match(0, 1, 0), "InvalidQuantity");
+    });
+
+    it("Leaves both orders PartiallyFilled below the cap", async () => {
+        await match(0, 1, 60);
+        const [bid, ask] = await Promise.all([fetchOrder(0), fetchOrder(1)]);
+        assert.deepEqual(bid.status, { partiallyFilled: {} });
+        assert.deepEqual(ask.status, { partiallyFilled: {} });
+        assert.equal(bid.filledQuantity.toNumber(), 60);
+        assert.equal(bid.escrowLamports.toNumber(), 40 * 1_200);
+        assert.equal((await program.account.market.fetch(mktPda)).totalBidVolume.toNumber(), 40 + 100);
+    });
+
+    it("Settles 60 then 40 exactly like a single 100-unit fill", async () => {
+        const split = await fillInSteps(0, 1, [40]);
+        const whole = await fillInSteps(2, 3, [null]);
+        // The 60-unit leg already paid out before fillInSteps started
+        const firstLeg = [60 * 200, 60 * 1_000, -(60 * 1_200)];
+        assert.deepEqual(split.map((value, i) => value + firstLeg[i]), whole);
+
+        for (const id of [0, 1, 2, 3]) {
+            assert.deepEqual((await fetchOrder(id)).status, { filled: {} });
+        }
+        assert.equal((await fetchOrder(0)).escrowLamports.toNumber(), 0);
+        assert.equal((await fetchOrder(2)).escrowLamports.toNumber(), 0);
+        assert.equal(await balance(orderPda(mktPda, 0)[0]), await balance(orderPda(mktPda, 2)[0]));
+        assert.equal(await balance(escrowVaultPda(mktPda, 0)[0]), await balance(escrowVaultPda(mktPda, 2)[0]));
+    });
+});
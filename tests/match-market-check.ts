@@ -0,0 +1,57 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, marketPda, place, program, provider } from "./helpers";
+
+describe("match_orders market binding", () => {
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [marketA] = marketPda(authority.publicKey, "BIND-A/MOCK");
+    const [marketB] = marketPda(authority.publicKey, "BIND-B/MOCK");
+
+    function matchIn(market: PublicKey, bid: PublicKey, ask: PublicKey) {
+        return program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+            })
+            .rpc();
+    }
+
+    before(async () => {
+        await airdrop(buyer.publicKey, 5);
+        await airdrop(seller.publicKey, 5);
+        for (const [market, name] of [[marketA, "BIND-A/MOCK"], [marketB, "BIND-B/MOCK"]] as const) {
+            await program.methods
+                .initializeMarket(name, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+                .accounts({ authority: authority.publicKey, market, systemProgram: SystemProgram.programId })
+                .rpc();
+        }
+    });
+
+    it("Rejects two orders of market B matched against market A", async () => {
+        const [bid] = await place(marketB, buyer, { buy: {} }, 100_000, 2, { orderId: 0 });
+        const [ask] = await place(marketB, seller, { sell: {} }, 100_000, 2, { orderId: 1 });
+
+        try {
+            await matchIn(marketA, bid, ask);
+            assert.fail("Expected MarketMismatch error");
+        } catch (err: any) {
+            assert.include(err.message, "MarketMismatch");
+        }
+
+        // Untouched, and still matchable in their own market
+        assert.equal((await program.account.order.fetch(bid)).filledQuantity.toNumber(), 0);
+        await matchIn(marketB, bid, ask);
+        assert.deepEqual((await program.account.order.fetch(ask)).status, { filled: {} });
+    });
+});
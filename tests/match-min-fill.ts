@@ -0,0 +1,64 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, place, program, provider } from "./helpers";
+
+describe("Matcher minimum fill", () => {
+    const MARKET_NAME = "MINFILL/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const fetchOrder = (orderId: number) => program.account.order.fetch(orderPda(mktPda, orderId)[0]);
+
+    const match = (bidId: number, askId: number, minFill: number) =>
+        program.methods
+            .matchOrdersV2({
+                maxSlippageBps: 0,
+                minExpectedBidRemaining: new anchor.BN(0),
+                minExpectedAskRemaining: new anchor.BN(0),
+                maxFillQuantity: null,
+                minFillQuantity: new anchor.BN(minFill),
+            })
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, bidId)[0],
+                askOrder: orderPda(mktPda, askId)[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+            })
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        // Bid 10 @ 1_000 vs ask 4 @ 1_000: at most 4 units cross
+        await place(mktPda, buyer, { buy: {} }, 1_000, 10, { orderId: 0 });
+        await place(mktPda, seller, { sell: {} }, 1_000, 4, { orderId: 1 });
+    });
+
+    it("Rejects a fill below the matcher's minimum and leaves both orders untouched", async () => {
+        await expectError(match(0, 1, 5), "FillBelowMinimum");
+        assert.equal((await fetchOrder(0)).filledQuantity.toNumber(), 0);
+        assert.equal((await fetchOrder(1)).filledQuantity.toNumber(), 0);
+    });
+
+    it("Fills when the minimum is exactly met", async () => {
+        await match(0, 1, 4);
+        assert.equal((await fetchOrder(0)).filledQuantity.toNumber(), 4);
+        assert.deepEqual((await fetchOrder(1)).status, { filled: {} });
+    });
+
+    it("Fails a racing crank cleanly once the pair is used up", async () => {
+        // The second crank built its transaction from the same snapshot
+        await expectError(match(0, 1, 4), "OrderNotActive");
+        assert.equal((await fetchOrder(0)).filledQuantity.toNumber(), 4);
+    });
+});
@@ -0,0 +1,186 @@
+import * as anchor from "@coral-xyz/anchor";
+import { ComputeBudgetProgram, Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, place, program, provider, sleep } from "./helpers";
+
+function errorCode(name: string): number {
+    const err = program.idl.errors.find((e) => e.name.toLowerCase() === name.toLowerCase());
+    if (!err) throw new Error(`unknown error ${name}`);
+    return err.code;
+}
+
+describe("match_orders_multi", () => {
+    const MARKET_NAME = "SWEEP/MOCK";
+    const OTHER_NAME = "SWEEP-B/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [otherPda] = marketPda(authority.publicKey, OTHER_NAME);
+
+    const balance = (key: PublicKey) => provider.connection.getBalance(key);
+
+    const rest = async (market: PublicKey, owner: Keypair, side: any, price: number, qty: number) =>
+        (await place(market, owner, side, price, qty))[0];
+
+    const sweeper = (bid: PublicKey, asks: PublicKey[], lenient = false) =>
+        program.methods
+            .matchOrdersMulti(lenient)
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                bidOwner: buyer.publicKey,
+                bidFunder: null,
+                bidTradingBalance: null,
+                bidUserStats: null,
+                bidSeat: null,
+                feeConfig: null,
+                treasury: null,
+                feeVault: null,
+            })
+            .remainingAccounts(
+                asks.flatMap((ask) => [
+                    { pubkey: ask, isSigner: false, isWritable: true },
+                    { pubkey: seller.publicKey, isSigner: false, isWritable: true },
+                    { pubkey: program.programId, isSigner: false, isWritable: false },
+                    { pubkey: program.programId, isSigner: false, isWritable: false },
+                ])
+            );
+
+    const sweep = (bid: PublicKey, asks: PublicKey[], computeUnits = 0) =>
+        sweeper(bid, asks)
+            .preInstructions(computeUnits > 0 ? [ComputeBudgetProgram.setComputeUnitLimit({ units: computeUnits })] : [])
+            .rpc();
+
+    // MakerOutcomes from a sweep's return data
+    async function outcomes(sig: string): Promise<any[]> {
+        const tx = await provider.connection.getTransaction(sig, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+        const data = Buffer.from(tx.meta.returnData.data[0], "base64");
+        const size = 32 + 8 + 4;
+        return Array.from({ length: data.readUInt32LE(0) }, (_, i) =>
+            program.coder.types.decode("MakerOutcome", data.subarray(4 + i * size, 4 + (i + 1) * size))
+        );
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 5);
+        for (const [market, name] of [[mktPda, MARKET_NAME], [otherPda, OTHER_NAME]] as const) {
+            await program.methods
+                .initializeMarket(name, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+                .accounts({ authority: authority.publicKey, market, systemProgram: SystemProgram.programId })
+                .rpc();
+        }
+    });
+
+    it("Fills one bid across asks at their own prices, refunding each improvement", async () => {
+        const bid = await rest(mktPda, buyer, { buy: {} }, 12_000, 10);
+        const asks = [
+            await rest(mktPda, seller, { sell: {} }, 10_000, 2),
+            await rest(mktPda, seller, { sell: {} }, 11_000, 3),
+            await rest(mktPda, seller, { sell: {} }, 12_000, 4),
+            await rest(mktPda, seller, { sell: {} }, 9_000, 5),
+            await rest(mktPda, seller, { sell: {} }, 9_500, 1),
+        ];
+        const buyerBefore = await balance(buyer.publicKey);
+        const sellerBefore = await balance(seller.publicKey);
+        const volumeBefore = await program.account.market.fetch(mktPda);
+
+        const trades: any[] = [];
+        const listener = program.addEventListener("tradeExecutedEvent", (e) => trades.push(e));
+        await sweep(bid, asks);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        // 2 @ 10_000, 3 @ 11_000, 4 @ 12_000, then 1 of the 5 @ 9_000; the last ask is never reached
+        const notional = 2 * 10_000 + 3 * 11_000 + 4 * 12_000 + 1 * 9_000;
+        const refunds = 2 * 2_000 + 3 * 1_000 + 4 * 0 + 1 * 3_000;
+        assert.equal(notional + refunds, 12_000 * 10);
+        assert.equal((await balance(buyer.publicKey)) - buyerBefore, refunds);
+        assert.equal((await balance(seller.publicKey)) - sellerBefore, notional);
+
+        assert.lengthOf(trades, 4);
+        assert.deepEqual(trades.map((t) => t.fillPrice.toNumber()), [10_000, 11_000, 12_000, 9_000]);
+
+        const order = await program.account.order.fetch(bid);
+        assert.deepEqual(order.status, { filled: {} });
+        assert.equal(order.escrowLamports.toNumber(), 0);
+        assert.equal((await program.account.order.fetch(asks[3])).filledQuantity.toNumber(), 1);
+        assert.equal((await program.account.order.fetch(asks[4])).filledQuantity.toNumber(), 0);
+
+        const market = await program.account.market.fetch(mktPda);
+        assert.equal(market.totalBidVolume.toNumber(), volumeBefore.totalBidVolume.toNumber() - 10);
+        assert.equal(market.totalAskVolume.toNumber(), volumeBefore.totalAskVolume.toNumber() - 10);
+        assert.equal(market.tradeSeq.toNumber(), volumeBefore.tradeSeq.toNumber() + 4);
+    });
+
+    it("Fails the whole sweep on an ask that doesn't cross", async () => {
+        const bid = await rest(mktPda, buyer, { buy: {} }, 10_000, 3);
+        const good = await rest(mktPda, seller, { sell: {} }, 10_000, 1);
+        const tooHigh = await rest(mktPda, seller, { sell: {} }, 10_001, 1);
+
+        await expectError(sweep(bid, [good, tooHigh]), "PriceMismatch");
+        assert.equal((await program.account.order.fetch(good)).filledQuantity.toNumber(), 0);
+        assert.equal((await program.account.order.fetch(bid)).filledQuantity.toNumber(), 0);
+    });
+
+    it("Fails the whole sweep on an ask of another market", async () => {
+        const bid = await rest(mktPda, buyer, { buy: {} }, 10_000, 2);
+        const foreign = await rest(otherPda, seller, { sell: {} }, 10_000, 1);
+        await expectError(sweep(bid, [foreign]), "MarketMismatch");
+    });
+
+    it("Skips asks cancelled or filled since, without touching them, when lenient", async () => {
+        const bid = await rest(mktPda, buyer, { buy: {} }, 10_000, 3);
+        const asks: PublicKey[] = [];
+        for (let i = 0; i < 4; i++) asks.push(await rest(mktPda, seller, { sell: {} }, 10_000, 1));
+        const { orderId } = await program.account.order.fetch(asks[1]);
+        await program.methods
+            .cancelOrder(orderId, new anchor.BN(0))
+            .accounts({ owner: seller.publicKey, market: mktPda, order: asks[1], tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([seller])
+            .rpc();
+        await sweep(await rest(mktPda, buyer, { buy: {} }, 10_000, 1), [asks[2]]);
+
+        await expectError(sweep(bid, asks), "OrderNotActive");
+        const skipped = [asks[1], asks[2]];
+        const before = await Promise.all(skipped.map((ask) => provider.connection.getAccountInfo(ask)));
+        const sig = await sweeper(bid, asks, true).rpc({ commitment: "confirmed" });
+
+        for (const [i, ask] of skipped.entries()) {
+            const after = await provider.connection.getAccountInfo(ask);
+            assert.equal(after.lamports, before[i].lamports);
+            assert.isTrue(after.data.equals(before[i].data));
+        }
+        for (const ask of [asks[0], asks[3]]) {
+            assert.deepEqual((await program.account.order.fetch(ask)).status, { filled: {} });
+        }
+        assert.equal((await program.account.order.fetch(bid)).filledQuantity.toNumber(), 2);
+
+        const notActive = errorCode("OrderNotActive");
+        const result = await outcomes(sig);
+        assert.deepEqual(result.map((o) => o.maker.toBase58()), asks.map((a) => a.toBase58()));
+        assert.deepEqual(result.map((o) => o.filledQuantity.toNumber()), [1, 0, 0, 1]);
+        assert.deepEqual(result.map((o) => o.skipCode), [0, notActive, notActive, 0]);
+    });
+
+    it("Sweeps eight asks in one instruction", async () => {
+        const bid = await rest(mktPda, buyer, { buy: {} }, 10_000, 8);
+        const asks: PublicKey[] = [];
+        for (let i = 0; i < 8; i++) asks.push(await rest(mktPda, seller, { sell: {} }, 9_000 + i * 100, 1));
+
+        await sweep(bid, asks, 600_000);
+        assert.deepEqual((await program.account.order.fetch(bid)).status, { filled: {} });
+        for (const ask of asks) {
+            assert.deepEqual((await program.account.order.fetch(ask)).status, { filled: {} });
+        }
+    });
+
+    it("Rejects more than eight asks", async () => {
+        const bid = await rest(mktPda, buyer, { buy: {} }, 10_000, 9);
+        const asks: PublicKey[] = [];
+        for (let i = 0; i < 9; i++) asks.push(await rest(mktPda, seller, { sell: {} }, 10_000, 1));
+        await expectError(sweep(bid, asks, 600_000), "InvalidMakerAccounts");
+    });
+});
@@ -0,0 +1,85 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, place, program, provider, sleep } from "./helpers";
+
+describe("Matcher fee", () => {
+    const MARKET_NAME = "MFEE/MOCK";
+    const MATCHER_FEE_BPS = 25;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const matcher = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const balance = (pk: PublicKey) => provider.connection.getBalance(pk);
+
+    const initMarket = (name: string, matcherFeeBps: number) =>
+        program.methods
+            .initializeMarket(name, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), matcherFeeBps, false)
+            .accounts({ authority: authority.publicKey, market: marketPda(authority.publicKey, name)[0], systemProgram: SystemProgram.programId })
+            .rpc();
+
+    const match = (bidId: number, askId: number) =>
+        program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: matcher.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, bidId)[0],
+                askOrder: orderPda(mktPda, askId)[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+            })
+            .signers([matcher])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller, matcher]) await airdrop(kp.publicKey, 2);
+    });
+
+    it("Caps the matcher fee like the trading fee", async () => {
+        await expectError(initMarket("MFEE/HIGH", 501), "FeeBpsTooHigh");
+        await initMarket(MARKET_NAME, MATCHER_FEE_BPS);
+        assert.equal((await program.account.market.fetch(mktPda)).matcherFeeBps, MATCHER_FEE_BPS);
+    });
+
+    it("Pays the matcher out of the seller payment, leaving the buyer's debit unchanged", async () => {
+        // 4 units @ 10_000 against a bid of 10 @ 12_000: gross 40_000, matcher 100
+        await place(mktPda, buyer, { buy: {} }, 12_000, 10, { orderId: 0 });
+        await place(mktPda, seller, { sell: {} }, 10_000, 4, { orderId: 1 });
+
+        const [bidPda] = orderPda(mktPda, 0);
+        const escrowBefore = (await program.account.order.fetch(bidPda)).escrowLamports.toNumber();
+        const [buyerBefore, sellerBefore, matcherBefore] = await Promise.all(
+            [buyer, seller, matcher].map((kp) => balance(kp.publicKey))
+        );
+
+        let event: any = null;
+        const listener = program.addEventListener("tradeExecutedEvent", (e) => (event = e));
+        await match(0, 1);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        assert.equal((await balance(matcher.publicKey)) - matcherBefore, 100);
+        assert.equal((await balance(seller.publicKey)) - sellerBefore, 39_900);
+        assert.equal((await balance(buyer.publicKey)) - buyerBefore, 8_000);
+        // The bid still holds exactly what its remaining 6 units locked
+        const escrowAfter = (await program.account.order.fetch(bidPda)).escrowLamports.toNumber();
+        assert.equal(escrowBefore - escrowAfter, 48_000);
+        assert.equal(escrowAfter, 6 * 12_000);
+        assert.equal(event.matcherFee.toNumber(), 100);
+    });
+
+    it("Rounds the matcher fee down in the seller's favour", async () => {
+        // 3 units @ 11_999: gross 35_997, exact matcher fee 89.9925
+        await place(mktPda, seller, { sell: {} }, 11_999, 3, { orderId: 2 });
+        const sellerBefore = await balance(seller.publicKey);
+        const matcherBefore = await balance(matcher.publicKey);
+        await match(0, 2);
+        assert.equal((await balance(matcher.publicKey)) - matcherBefore, 89);
+        assert.equal((await balance(seller.publicKey)) - sellerBefore, 35_997 - 89);
+    });
+});
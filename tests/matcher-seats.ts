@@ -0,0 +1,128 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, matcherSeatPda, place, program, provider, sleep } from "./helpers";
+
+describe("Matcher seats", () => {
+    const MARKET_NAME = "MSEATS/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const registered = Keypair.generate();
+    const outsider = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [registeredSeat] = matcherSeatPda(mktPda, registered.publicKey);
+
+    async function crossingPair(): Promise<[PublicKey, PublicKey]> {
+        const [bid] = await place(mktPda, buyer, { buy: {} }, 10_000, 2);
+        const [ask] = await place(mktPda, seller, { sell: {} }, 10_000, 2);
+        return [bid, ask];
+    }
+
+    const matchAs = (matcher: Keypair, [bid, ask]: [PublicKey, PublicKey], matcherSeat: PublicKey | null) =>
+        program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: matcher.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: null,
+                bidTradingBalance: null,
+                matcherSeat,
+            })
+            .signers([matcher])
+            .rpc();
+
+    const setRestricted = (signer: Keypair | null, restricted: boolean) =>
+        program.methods
+            .setMatcherRestricted(restricted)
+            .accounts({ authority: signer?.publicKey ?? authority.publicKey, market: mktPda })
+            .signers(signer ? [signer] : [])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller, registered, outsider]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Starts unrestricted: anyone matches without a seat", async () => {
+        assert.isFalse((await program.account.market.fetch(mktPda)).matcherRestricted);
+        const pair = await crossingPair();
+        await matchAs(outsider, pair, null);
+        assert.deepEqual((await program.account.order.fetch(pair[0])).status, { filled: {} });
+    });
+
+    it("Only the authority can restrict matching or register matchers", async () => {
+        await expectError(setRestricted(outsider, true), "Unauthorized");
+        await expectError(
+            program.methods
+                .registerMatcher(outsider.publicKey)
+                .accounts({
+                    authority: outsider.publicKey,
+                    market: mktPda,
+                    matcherSeat: matcherSeatPda(mktPda, outsider.publicKey)[0],
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([outsider])
+                .rpc(),
+            "Unauthorized"
+        );
+
+        await setRestricted(null, true);
+        await program.methods
+            .registerMatcher(registered.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, matcherSeat: registeredSeat, systemProgram: SystemProgram.programId })
+            .rpc();
+        assert.isTrue((await program.account.market.fetch(mktPda)).matcherRestricted);
+        const seat = await program.account.matcherSeat.fetch(registeredSeat);
+        assert.ok(seat.matcher.equals(registered.publicKey));
+        assert.ok(seat.market.equals(mktPda));
+    });
+
+    it("Rejects matchers without a seat, or with someone else's", async () => {
+        const pair = await crossingPair();
+        await expectError(matchAs(outsider, pair, null), "MatcherNotAuthorized");
+        await expectError(matchAs(outsider, pair, registeredSeat), "MatcherNotAuthorized");
+        await expectError(matchAs(registered, pair, null), "MatcherNotAuthorized");
+        assert.deepEqual((await program.account.order.fetch(pair[0])).status, { open: {} });
+    });
+
+    it("Lets a registered matcher match, and names it in the trade event", async () => {
+        let event: any = null;
+        const listener = program.addEventListener("tradeExecutedEvent", (e) => (event = e));
+        const pair = await crossingPair();
+        await matchAs(registered, pair, registeredSeat);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        assert.deepEqual((await program.account.order.fetch(pair[0])).status, { filled: {} });
+        assert.ok(event.matcher.equals(registered.publicKey));
+    });
+
+    it("Fails a revoked matcher's transactions, with or without the closed seat", async () => {
+        await program.methods
+            .revokeMatcher(registered.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, matcherSeat: registeredSeat })
+            .rpc();
+        assert.isNull(await provider.connection.getAccountInfo(registeredSeat));
+
+        const pair = await crossingPair();
+        await expectError(matchAs(registered, pair, registeredSeat), "AccountNotInitialized");
+        await expectError(matchAs(registered, pair, null), "MatcherNotAuthorized");
+    });
+
+    it("Opens matching back up when the restriction is lifted", async () => {
+        await setRestricted(null, false);
+        const pair = await crossingPair();
+        await matchAs(outsider, pair, null);
+        assert.deepEqual((await program.account.order.fetch(pair[0])).status, { filled: {} });
+    });
+});
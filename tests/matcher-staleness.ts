@@ -0,0 +1,106 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, marketPda, orderPda, place, program, provider } from "./helpers";
+
+describe("Matcher staleness guards", () => {
+    const MARKET_NAME = "STALE/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [bid] = orderPda(mktPda, 0);
+    const [askA] = orderPda(mktPda, 1);
+    const [askB] = orderPda(mktPda, 2);
+
+    const match = (ask: PublicKey, minBid: number, minAsk: number) =>
+        program.methods
+            .matchOrders(0, new anchor.BN(minBid), new anchor.BN(minAsk))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+                bidTradingBalance: null,
+            })
+            .rpc();
+
+    before(async () => {
+        await airdrop(buyer.publicKey, 5);
+        await airdrop(seller.publicKey, 5);
+
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await place(mktPda, buyer, { buy: {} }, 10_000, 10, { orderId: 0 });
+        await place(mktPda, seller, { sell: {} }, 10_000, 5, { orderId: 1 });
+        await place(mktPda, seller, { sell: {} }, 10_000, 8, { orderId: 2 });
+    });
+
+    it("Fails with StaleMakerState when a concurrent fill shrank the maker", async () => {
+        // Crank A snapshots the book: bid has 10 left, askA has 5
+        const seenBid = (await program.account.order.fetch(bid)).quantity.toNumber();
+
+        // Crank B lands first and takes 8 of the bid
+        await match(askB, 0, 0);
+
+        try {
+            await match(askA, seenBid, 5);
+            assert.fail("Expected StaleMakerState error");
+        } catch (err: any) {
+            assert.include(err.message, "StaleMakerState");
+        }
+
+        // Nothing was settled by the rejected match
+        const a = await program.account.order.fetch(askA);
+        assert.equal(a.filledQuantity.toNumber(), 0);
+        assert.deepEqual(a.status, { open: {} });
+    });
+
+    it("Executes once the crank rebuilds with the current sizes", async () => {
+        const b = await program.account.order.fetch(bid);
+        const remaining = b.quantity.sub(b.filledQuantity).toNumber();
+        assert.equal(remaining, 2);
+
+        await match(askA, remaining, 5);
+        const a = await program.account.order.fetch(askA);
+        assert.equal(a.filledQuantity.toNumber(), 2);
+    });
+
+    it("Guards the ask side independently; zero disables", async () => {
+        const [bid2] = orderPda(mktPda, 3);
+        await place(mktPda, buyer, { buy: {} }, 10_000, 1, { orderId: 3 });
+
+        const matchBid2 = (minAsk: number) =>
+            program.methods
+                .matchOrders(0, new anchor.BN(0), new anchor.BN(minAsk))
+                .accounts({
+                    matcher: authority.publicKey,
+                    market: mktPda,
+                    bidOrder: bid2,
+                    askOrder: askA,
+                    bidOwner: buyer.publicKey,
+                    askOwner: seller.publicKey,
+                    feeConfig: null,
+                    treasury: authority.publicKey,
+                    bidTradingBalance: null,
+                })
+                .rpc();
+
+        try {
+            await matchBid2(5); // askA only has 3 left
+            assert.fail("Expected StaleMakerState error");
+        } catch (err: any) {
+            assert.include(err.message, "StaleMakerState");
+        }
+        await matchBid2(0);
+        const a = await program.account.order.fetch(askA);
+        assert.equal(a.filledQuantity.toNumber(), 3);
+    });
+});
@@ -0,0 +1,103 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, feeConfigPda, marketPda, matcherStatsPda, place, program, provider } from "./helpers";
+
+describe("Matcher statistics", () => {
+    const MARKET_NAME = "MSTATS/MOCK";
+    const FEE_BPS = 100;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const matcherA = Keypair.generate();
+    const matcherB = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+    const statsOf = (kp: Keypair) => matcherStatsPda(mktPda, kp.publicKey)[0];
+
+    async function matchAs(matcher: Keypair, price: number, qty: number, stats: PublicKey | null) {
+        const [bid] = await place(mktPda, buyer, { buy: {} }, price, qty);
+        const [ask] = await place(mktPda, seller, { sell: {} }, price, qty);
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: matcher.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: feePda,
+                treasury: authority.publicKey,
+                bidTradingBalance: null,
+                matcherStats: stats,
+            })
+            .signers([matcher])
+            .rpc();
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller, matcherA, matcherB]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeFeeConfig(FEE_BPS, authority.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .setFeeRecipient(authority.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda })
+            .rpc();
+        for (const kp of [matcherA, matcherB]) {
+            await program.methods
+                .createMatcherStats()
+                .accounts({ matcher: kp.publicKey, market: mktPda, matcherStats: statsOf(kp), systemProgram: SystemProgram.programId })
+                .signers([kp])
+                .rpc();
+        }
+    });
+
+    it("Starts empty", async () => {
+        const stats = await program.account.matcherStats.fetch(statsOf(matcherA));
+        assert.ok(stats.matcher.equals(matcherA.publicKey));
+        assert.equal(stats.matchesExecuted.toNumber(), 0);
+        assert.equal(stats.lastActiveSlot.toNumber(), 0);
+    });
+
+    it("Accumulates per matcher across several matches", async () => {
+        // A: 10 @ 10_000 and 5 @ 20_000; B: 3 @ 10_000
+        await matchAs(matcherA, 10_000, 10, statsOf(matcherA));
+        await matchAs(matcherB, 10_000, 3, statsOf(matcherB));
+        await matchAs(matcherA, 20_000, 5, statsOf(matcherA));
+
+        const a = await program.account.matcherStats.fetch(statsOf(matcherA));
+        assert.equal(a.matchesExecuted.toNumber(), 2);
+        assert.equal(a.volumeMatched.toNumber(), 15);
+        assert.equal(a.notionalMatched.toNumber(), 200_000);
+        assert.equal(a.feesGenerated.toNumber(), 2_000);
+        assert.equal(a.rewardsEarned.toNumber(), 0);
+
+        const b = await program.account.matcherStats.fetch(statsOf(matcherB));
+        assert.equal(b.matchesExecuted.toNumber(), 1);
+        assert.equal(b.volumeMatched.toNumber(), 3);
+        assert.equal(b.notionalMatched.toNumber(), 30_000);
+        assert.equal(b.feesGenerated.toNumber(), 300);
+        assert.isAbove(a.lastActiveSlot.toNumber(), b.lastActiveSlot.toNumber());
+    });
+
+    it("Is optional, and only the matcher's own stats are accepted", async () => {
+        await matchAs(matcherB, 10_000, 1, null);
+        const b = await program.account.matcherStats.fetch(statsOf(matcherB));
+        assert.equal(b.matchesExecuted.toNumber(), 1);
+
+        try {
+            await matchAs(matcherB, 10_000, 1, statsOf(matcherA));
+            assert.fail("Expected a seeds constraint error");
+        } catch (err: any) {
+            assert.include(err.message, "ConstraintSeeds");
+        }
+    });
+});
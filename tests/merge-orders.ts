@@ -0,0 +1,123 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, escrowVaultPda, expectError, marketPda, orderPda, place, program, provider, sleep } from "./helpers";
+
+describe("merge_orders", () => {
+    const MARKET_NAME = "MERGE/MOCK";
+    const PRICE = 10_000;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+
+    const merge = (owner: Keypair, survivorId: number, absorbedId: number) =>
+        program.methods
+            .mergeOrders(new anchor.BN(survivorId), new anchor.BN(absorbedId))
+            .accounts({
+                owner: owner.publicKey,
+                market: mktPda,
+                survivor: orderPda(mktPda, survivorId)[0],
+                absorbed: orderPda(mktPda, absorbedId)[0],
+                userStats: null,
+            })
+            .signers([owner])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Only merges same-side, same-price orders of one owner", async () => {
+        const [, a] = await place(mktPda, buyer, { buy: {} }, PRICE, 2);
+        const [, otherPrice] = await place(mktPda, buyer, { buy: {} }, PRICE + 1, 2);
+        const [, otherOwner] = await place(mktPda, seller, { sell: {} }, PRICE * 2, 2);
+        await expectError(merge(buyer, a, otherPrice), "OrdersNotMergeable");
+        await expectError(merge(buyer, a, a), "OrdersNotMergeable");
+        await expectError(merge(buyer, a, otherOwner), "Unauthorized");
+    });
+
+    it("Combines quantities and escrow and returns the absorbed rent", async () => {
+        const [, survivorId] = await place(mktPda, buyer, { buy: {} }, PRICE, 3);
+        await sleep(1500); // a later placement timestamp for the absorbed order
+        const [, absorbedId] = await place(mktPda, buyer, { buy: {} }, PRICE, 5);
+        const [survivorPda] = orderPda(mktPda, survivorId);
+        const [absorbedPda] = orderPda(mktPda, absorbedId);
+        const [survivorVault] = escrowVaultPda(mktPda, survivorId);
+        const [absorbedVault] = escrowVaultPda(mktPda, absorbedId);
+
+        const mktBefore = await program.account.market.fetch(mktPda);
+        const absorbedBefore = await program.account.order.fetch(absorbedPda);
+        const survivorLamports = await provider.connection.getBalance(survivorPda);
+        const absorbedLamports = await provider.connection.getBalance(absorbedPda);
+        const survivorVaultLamports = await provider.connection.getBalance(survivorVault);
+        const absorbedVaultLamports = await provider.connection.getBalance(absorbedVault);
+        const walletBefore = await provider.connection.getBalance(buyer.publicKey);
+
+        await merge(buyer, survivorId, absorbedId);
+
+        assert.isNull(await provider.connection.getAccountInfo(absorbedPda));
+        assert.isNull(await provider.connection.getAccountInfo(absorbedVault));
+        const survivor = await program.account.order.fetch(survivorPda);
+        assert.equal(survivor.quantity.toNumber(), 8);
+        assert.equal(survivor.escrowLamports.toNumber(), 8 * PRICE);
+        assert.equal(await provider.connection.getBalance(survivorPda), survivorLamports);
+        assert.equal(await provider.connection.getBalance(survivorVault), survivorVaultLamports + 5 * PRICE);
+        // Only the rent, the order's and its vault's, comes back to the wallet
+        assert.equal(
+            (await provider.connection.getBalance(buyer.publicKey)) - walletBefore,
+            absorbedLamports + absorbedVaultLamports - 5 * PRICE
+        );
+
+        // Priority rule: the survivor takes the worse (later) timestamp
+        assert.equal(survivor.timestamp.toNumber(), absorbedBefore.timestamp.toNumber());
+
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.totalBidVolume.toNumber(), mktBefore.totalBidVolume.toNumber());
+        assert.equal(mkt.openOrderCount.toNumber(), mktBefore.openOrderCount.toNumber() - 1);
+    });
+
+    it("Carries fills over and the survivor trades out in full", async () => {
+        const [, survivorId] = await place(mktPda, buyer, { buy: {} }, PRICE, 4);
+        const [, absorbedId] = await place(mktPda, buyer, { buy: {} }, PRICE, 4);
+        const [survivorPda] = orderPda(mktPda, survivorId);
+        const [absorbedPda] = orderPda(mktPda, absorbedId);
+
+        const match = async (bid: PublicKey, qty: number) => {
+            const [, askId] = await place(mktPda, seller, { sell: {} }, PRICE, qty);
+            const mkt = await program.account.market.fetch(mktPda);
+            await program.methods
+                .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+                .accounts({
+                    matcher: authority.publicKey,
+                    market: mktPda,
+                    bidOrder: bid,
+                    askOrder: orderPda(mktPda, askId)[0],
+                    bidOwner: buyer.publicKey,
+                    askOwner: seller.publicKey,
+                    feeConfig: null,
+                    treasury: mkt.feeRecipient,
+                    bidTradingBalance: null,
+                })
+                .rpc();
+        };
+
+        await match(absorbedPda, 1);
+        await merge(buyer, survivorId, absorbedId);
+        const merged = await program.account.order.fetch(survivorPda);
+        assert.equal(merged.quantity.toNumber(), 8);
+        assert.equal(merged.filledQuantity.toNumber(), 1);
+        assert.deepEqual(merged.status, { partiallyFilled: {} });
+        assert.equal(merged.escrowLamports.toNumber(), 7 * PRICE);
+
+        await match(survivorPda, 7);
+        const filled = await program.account.order.fetch(survivorPda);
+        assert.deepEqual(filled.status, { filled: {} });
+        assert.equal(filled.escrowLamports.toNumber(), 0);
+    });
+});
@@ -0,0 +1,109 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, place, program, provider } from "./helpers";
+
+describe("modify_order", () => {
+    const MARKET_NAME = "MODIFY/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+
+    const bn = (n: number | null) => (n === null ? null : new anchor.BN(n));
+    const balance = (key: PublicKey) => provider.connection.getBalance(key);
+
+    const modify = (owner: Keypair, orderId: number, price: number | null, qty: number | null) =>
+        program.methods
+            .modifyOrder(new anchor.BN(orderId), bn(price), bn(qty))
+            .accounts({
+                owner: owner.publicKey,
+                market: mktPda,
+                order: orderPda(mktPda, orderId)[0],
+                tradingBalance: null,
+                userStats: null,
+                funder: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([owner])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Raises a bid's price, escrowing the difference from the owner", async () => {
+        const [bid, bidId] = await place(mktPda, buyer, { buy: {} }, 100, 5);
+        const { updateCount } = await program.account.order.fetch(bid);
+        const before = await balance(buyer.publicKey);
+
+        await modify(buyer, bidId, 120, null);
+
+        const order = await program.account.order.fetch(bid);
+        assert.equal(order.orderId.toNumber(), bidId);
+        assert.equal(order.price.toNumber(), 120);
+        assert.equal(order.escrowLamports.toNumber(), 5 * 120);
+        assert.equal(order.updateCount.toNumber(), updateCount.toNumber() + 1);
+        assert.equal(before - (await balance(buyer.publicKey)), 5 * 20);
+    });
+
+    it("Shrinks a bid, refunding the escrow and releasing the volume", async () => {
+        const bidId = 0;
+        const [bid] = orderPda(mktPda, bidId);
+        const volumeBefore = (await program.account.market.fetch(mktPda)).totalBidVolume.toNumber();
+        const before = await balance(buyer.publicKey);
+
+        await modify(buyer, bidId, null, 3);
+
+        const order = await program.account.order.fetch(bid);
+        assert.equal(order.quantity.toNumber(), 3);
+        assert.equal(order.escrowLamports.toNumber(), 3 * 120);
+        assert.equal((await balance(buyer.publicKey)) - before, 2 * 120);
+        assert.equal((await program.account.market.fetch(mktPda)).totalBidVolume.toNumber(), volumeBefore - 2);
+    });
+
+    it("Grows an ask's open volume without escrow", async () => {
+        const [ask, askId] = await place(mktPda, seller, { sell: {} }, 150, 2);
+        const volumeBefore = (await program.account.market.fetch(mktPda)).totalAskVolume.toNumber();
+
+        await modify(seller, askId, 120, 4);
+
+        const order = await program.account.order.fetch(ask);
+        assert.equal(order.price.toNumber(), 120);
+        assert.equal(order.quantity.toNumber(), 4);
+        assert.equal(order.escrowLamports.toNumber(), 0);
+        assert.equal((await program.account.market.fetch(mktPda)).totalAskVolume.toNumber(), volumeBefore + 2);
+    });
+
+    it("Rejects a quantity below what has already filled", async () => {
+        const [bid] = orderPda(mktPda, 0);
+        const [ask] = orderPda(mktPda, 1);
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+            })
+            .rpc();
+        assert.equal((await program.account.order.fetch(bid)).filledQuantity.toNumber(), 3);
+        assert.equal((await program.account.order.fetch(ask)).filledQuantity.toNumber(), 3);
+
+        await expectError(modify(seller, 1, null, 2), "InvalidQuantity");
+    });
+
+    it("Rejects modifying a filled order and someone else's order", async () => {
+        await expectError(modify(buyer, 0, 130, null), "OrderNotActive");
+        await expectError(modify(buyer, 1, 110, null), "Unauthorized");
+    });
+});
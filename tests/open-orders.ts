@@ -0,0 +1,116 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, openOrdersPda, orderPda, place, program, provider } from "./helpers";
+
+describe("Open orders", () => {
+    const MARKET_NAME = "OPENORD/MOCK";
+    const PRICE = 1_000;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [buyerList] = openOrdersPda(mktPda, buyer.publicKey);
+    const [sellerList] = openOrdersPda(mktPda, seller.publicKey);
+    const listed = async (list: anchor.web3.PublicKey) => {
+        const { count, orderIds } = await program.account.openOrders.fetch(list);
+        return orderIds.slice(0, count).map((id: anchor.BN) => id.toNumber()).sort((a: number, b: number) => a - b);
+    };
+    const onList = (openOrders: anchor.web3.PublicKey) => ({ accounts: { openOrders } });
+
+    const cancel = (owner: Keypair, orderId: number, list: anchor.web3.PublicKey | null) =>
+        program.methods
+            .cancelOrder(new anchor.BN(orderId), new anchor.BN(0))
+            .accounts({ owner: owner.publicKey, market: mktPda, order: orderPda(mktPda, orderId)[0], tradingBalance: null, openOrders: list, systemProgram: SystemProgram.programId })
+            .signers([owner])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        for (const [owner, list] of [[buyer, buyerList], [seller, sellerList]] as const) {
+            await program.methods
+                .createOpenOrders()
+                .accounts({ owner: owner.publicKey, market: mktPda, openOrders: list, systemProgram: SystemProgram.programId })
+                .signers([owner])
+                .rpc();
+        }
+    });
+
+    it("Lists orders placed with it, and only those", async () => {
+        const [, a] = await place(mktPda, buyer, { buy: {} }, PRICE, 2, onList(buyerList));
+        const [, b] = await place(mktPda, buyer, { buy: {} }, PRICE, 1, onList(buyerList));
+        await place(mktPda, buyer, { buy: {} }, PRICE, 1);
+        assert.deepEqual(await listed(buyerList), [a, b]);
+        assert.isTrue((await program.account.order.fetch(orderPda(mktPda, a)[0])).trackedInOpenOrders);
+    });
+
+    it("Keeps a partially filled order listed and drops it once filled", async () => {
+        const bid = 0;
+        const match = (askId: number) =>
+            program.methods
+                .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+                .accounts({
+                    matcher: authority.publicKey,
+                    market: mktPda,
+                    bidOrder: orderPda(mktPda, bid)[0],
+                    askOrder: orderPda(mktPda, askId)[0],
+                    bidOwner: buyer.publicKey,
+                    askOwner: seller.publicKey,
+                    feeConfig: null,
+                    treasury: authority.publicKey,
+                    bidOpenOrders: buyerList,
+                    askOpenOrders: sellerList,
+                })
+                .rpc();
+
+        const [, ask1] = await place(mktPda, seller, { sell: {} }, PRICE, 1, onList(sellerList));
+        await match(ask1);
+        assert.deepEqual(await listed(buyerList), [0, 1]);
+        assert.deepEqual(await listed(sellerList), []);
+
+        const [, ask2] = await place(mktPda, seller, { sell: {} }, PRICE, 1, onList(sellerList));
+        // A listed order's fill needs its owner's list
+        await expectError(
+            program.methods
+                .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+                .accounts({
+                    matcher: authority.publicKey,
+                    market: mktPda,
+                    bidOrder: orderPda(mktPda, bid)[0],
+                    askOrder: orderPda(mktPda, ask2)[0],
+                    bidOwner: buyer.publicKey,
+                    askOwner: seller.publicKey,
+                    feeConfig: null,
+                    treasury: authority.publicKey,
+                    bidOpenOrders: buyerList,
+                })
+                .rpc(),
+            "OpenOrdersRequired"
+        );
+        await match(ask2);
+        assert.deepEqual(await listed(buyerList), [1]);
+    });
+
+    it("Unlists on cancel, which needs the list", async () => {
+        await expectError(cancel(buyer, 1, null), "OpenOrdersRequired");
+        await cancel(buyer, 1, buyerList);
+        assert.deepEqual(await listed(buyerList), []);
+    });
+
+    it("Refuses placement once the list is full", async () => {
+        for (let i = 0; i < 32; i++) await place(mktPda, seller, { sell: {} }, PRICE, 1, onList(sellerList));
+        assert.equal((await listed(sellerList)).length, 32);
+        await expectError(place(mktPda, seller, { sell: {} }, PRICE, 1, onList(sellerList)), "TooManyOpenOrders");
+
+        // Cancelling one frees a slot
+        const [first] = await listed(sellerList);
+        await cancel(seller, first, sellerList);
+        await place(mktPda, seller, { sell: {} }, PRICE, 1, onList(sellerList));
+        assert.equal((await listed(sellerList)).length, 32);
+    });
+});
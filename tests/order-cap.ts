@@ -0,0 +1,116 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, place, program, provider, userStatsPda } from "./helpers";
+
+describe("Open-order cap per owner", () => {
+    const MARKET_NAME = "ORDERCAP/MOCK";
+    const PRICE = 10_000;
+    const CAP = 2;
+    const authority = provider.wallet;
+    const trader = Keypair.generate();
+    const seller = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const statsOf = (kp: Keypair) => userStatsPda(mktPda, kp.publicKey)[0];
+    const openOrdersOf = async (kp: Keypair) =>
+        (await program.account.userStats.fetch(statsOf(kp))).openOrders.toNumber();
+
+    const rest = (owner: Keypair, side: any, withStats = true) =>
+        place(mktPda, owner, side, PRICE, 1, withStats ? { accounts: { userStats: statsOf(owner) } } : {});
+
+    const setCap = (signer: Keypair | null, cap: number) =>
+        program.methods
+            .setMaxOrdersPerUser(cap)
+            .accounts({ authority: signer?.publicKey ?? authority.publicKey, market: mktPda, roles: null })
+            .signers(signer ? [signer] : [])
+            .rpc();
+
+    const resting: [PublicKey, number][] = [];
+
+    before(async () => {
+        for (const kp of [trader, seller, stranger]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        for (const kp of [trader, seller]) {
+            await program.methods
+                .initializeUserStats()
+                .accounts({ owner: kp.publicKey, market: mktPda, userStats: statsOf(kp), systemProgram: SystemProgram.programId })
+                .signers([kp])
+                .rpc();
+        }
+    });
+
+    it("Is set by the authority only", async () => {
+        assert.equal((await program.account.market.fetch(mktPda)).maxOrdersPerUser, 0);
+        await expectError(setCap(stranger, CAP), "Unauthorized");
+        await setCap(null, CAP);
+        assert.equal((await program.account.market.fetch(mktPda)).maxOrdersPerUser, CAP);
+    });
+
+    it("Requires user stats while a cap is set", async () => {
+        await expectError(rest(trader, { buy: {} }, false), "UserStatsRequired");
+    });
+
+    it("Rejects a placement past the cap", async () => {
+        resting.push(await rest(trader, { buy: {} }));
+        resting.push(await rest(trader, { buy: {} }));
+        await expectError(rest(trader, { buy: {} }), "TooManyOpenOrders");
+        assert.equal(await openOrdersOf(trader), CAP);
+    });
+
+    it("Frees a slot when an order is cancelled", async () => {
+        const [order, orderId] = resting.shift()!;
+        const { updateCount } = await program.account.order.fetch(order);
+        await program.methods
+            .cancelOrder(new anchor.BN(orderId), updateCount)
+            .accounts({ owner: trader.publicKey, market: mktPda, order, tradingBalance: null, userStats: statsOf(trader) })
+            .signers([trader])
+            .rpc();
+        assert.equal(await openOrdersOf(trader), CAP - 1);
+        resting.push(await rest(trader, { buy: {} }));
+    });
+
+    it("Frees a slot when match_orders fills an order, and closing it doesn't free another", async () => {
+        const [bid, bidId] = resting.shift()!;
+        const [ask] = await rest(seller, { sell: {} });
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: trader.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: null,
+                bidTradingBalance: null,
+                bidUserStats: statsOf(trader),
+                askUserStats: statsOf(seller),
+            })
+            .rpc();
+        assert.deepEqual((await program.account.order.fetch(bid)).status, { filled: {} });
+        assert.equal(await openOrdersOf(trader), CAP - 1);
+        assert.equal(await openOrdersOf(seller), 0);
+
+        await program.methods
+            .closeOrder(new anchor.BN(bidId))
+            .accounts({ owner: trader.publicKey, market: mktPda, order: bid })
+            .signers([trader])
+            .rpc();
+        assert.equal(await openOrdersOf(trader), CAP - 1);
+
+        resting.push(await rest(trader, { buy: {} }));
+        await expectError(rest(trader, { buy: {} }), "TooManyOpenOrders");
+    });
+
+    it("Lifts the limit at 0", async () => {
+        await setCap(null, 0);
+        await rest(trader, { buy: {} });
+        await rest(trader, { buy: {} }, false);
+    });
+});
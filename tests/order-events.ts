@@ -0,0 +1,92 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, escrowVaultPda, marketPda, orderPda, place, program, provider, sleep } from "./helpers";
+
+describe("Order state from events", () => {
+    const MARKET_NAME = "EVENTS/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+
+    // Runs `call` and returns the last `name` event it emitted
+    async function withEvent(name: string, call: () => Promise<unknown>): Promise<any> {
+        let event: any = null;
+        const listener = program.addEventListener(name as any, (e) => (event = e));
+        await call();
+        await sleep(1000);
+        await program.removeEventListener(listener);
+        return event;
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await place(mktPda, buyer, { buy: {} }, 1_000, 10, { orderId: 0 });
+        await place(mktPda, seller, { sell: {} }, 1_000, 4, { orderId: 1 });
+    });
+
+    it("Reports both orders' status and remaining quantity with the trade", async () => {
+        const trade = await withEvent("tradeExecutedEvent", () =>
+            program.methods
+                .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+                .accounts({
+                    matcher: authority.publicKey,
+                    market: mktPda,
+                    bidOrder: orderPda(mktPda, 0)[0],
+                    askOrder: orderPda(mktPda, 1)[0],
+                    bidOwner: buyer.publicKey,
+                    askOwner: seller.publicKey,
+                    feeConfig: null,
+                    treasury: authority.publicKey,
+                })
+                .rpc()
+        );
+        assert.deepEqual(trade.bidStatus, { partiallyFilled: {} });
+        assert.deepEqual(trade.askStatus, { filled: {} });
+        assert.equal(trade.bidRemaining.toNumber(), 6);
+        assert.equal(trade.askRemaining.toNumber(), 0);
+        assert.isTrue(trade.matcher.equals(authority.publicKey));
+    });
+
+    it("Reports the quantity a cancel takes off the book", async () => {
+        const { updateCount } = await program.account.order.fetch(orderPda(mktPda, 0)[0]);
+        const cancelled = await withEvent("orderCancelledEvent", () =>
+            program.methods
+                .cancelOrder(new anchor.BN(0), updateCount)
+                .accounts({ owner: buyer.publicKey, market: mktPda, order: orderPda(mktPda, 0)[0], tradingBalance: null, systemProgram: SystemProgram.programId })
+                .signers([buyer])
+                .rpc()
+        );
+        assert.equal(cancelled.orderId.toNumber(), 0);
+        assert.equal(cancelled.remainingQuantity.toNumber(), 6);
+        assert.isAbove(cancelled.timestamp.toNumber(), 0);
+    });
+
+    it("Emits OrderClosedEvent with the rent reclaimed", async () => {
+        for (const [owner, orderId] of [[buyer, 0], [seller, 1]] as [Keypair, number][]) {
+            const [order] = orderPda(mktPda, orderId);
+            const rent = await provider.connection.getBalance(order);
+            // Only the buy's escrow vault holds rent
+            const vaultRent = await provider.connection.getBalance(escrowVaultPda(mktPda, orderId)[0]);
+            const closed = await withEvent("orderClosedEvent", () =>
+                program.methods
+                    .closeOrder(new anchor.BN(orderId))
+                    .accounts({ owner: owner.publicKey, market: mktPda, order, systemProgram: SystemProgram.programId })
+                    .signers([owner])
+                    .rpc()
+            );
+            assert.equal(closed.orderId.toNumber(), orderId);
+            assert.isTrue(closed.owner.equals(owner.publicKey));
+            assert.isTrue(closed.market.equals(mktPda));
+            assert.equal(closed.rentReclaimed.toNumber(), rent);
+            assert.isTrue(closed.reclaimedTo.equals(owner.publicKey));
+            assert.equal(closed.vaultRentReclaimed.toNumber(), vaultRent);
+        }
+    });
+});
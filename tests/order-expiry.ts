@@ -0,0 +1,76 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, place, program, provider, sleep } from "./helpers";
+
+describe("update_order_expiry", () => {
+    const MARKET_NAME = "EXPIRY/MOCK";
+    const PRICE = 10_000;
+    const authority = provider.wallet;
+    const seller = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const now = () => Math.floor(Date.now() / 1000);
+
+    const sell = (expiresAt: number) => place(mktPda, seller, { sell: {} }, PRICE, 1, { expiresAt });
+
+    const update = (owner: Keypair, orderId: number, expiresAt: number) =>
+        program.methods
+            .updateOrderExpiry(new anchor.BN(orderId), new anchor.BN(expiresAt))
+            .accounts({ owner: owner.publicKey, market: mktPda, order: orderPda(mktPda, orderId)[0] })
+            .signers([owner])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [seller, stranger]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Extends and shortens in place, with old/new values evented", async () => {
+        const [order, orderId] = await sell(now() + 60);
+        const before = await program.account.order.fetch(order);
+
+        let event: any = null;
+        const listener = program.addEventListener("orderExpiryUpdatedEvent", (e) => (event = e));
+        const extended = now() + 3600;
+        await update(seller, orderId, extended);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        const after = await program.account.order.fetch(order);
+        assert.equal(after.expiresAt.toNumber(), extended);
+        assert.equal(after.timestamp.toNumber(), before.timestamp.toNumber());
+        assert.equal(after.updateCount.toNumber(), before.updateCount.toNumber() + 1);
+        assert.equal(event.oldExpiresAt.toNumber(), before.expiresAt.toNumber());
+        assert.equal(event.newExpiresAt.toNumber(), extended);
+
+        const shortened = now() + 30;
+        await update(seller, orderId, shortened);
+        assert.equal((await program.account.order.fetch(order)).expiresAt.toNumber(), shortened);
+
+        // 0 makes the order good-til-cancelled
+        await update(seller, orderId, 0);
+        assert.equal((await program.account.order.fetch(order)).expiresAt.toNumber(), 0);
+    });
+
+    it("Rejects a deadline already in the past", async () => {
+        const [order, orderId] = await sell(now() + 60);
+        await expectError(update(seller, orderId, now() - 10), "OrderExpired");
+        assert.deepEqual((await program.account.order.fetch(order)).status, { open: {} });
+    });
+
+    it("Can't revive an order that has already expired", async () => {
+        const [, orderId] = await sell(now() + 2);
+        await sleep(4000);
+        await expectError(update(seller, orderId, now() + 3600), "OrderExpired");
+    });
+
+    it("Only the owner can update", async () => {
+        const [, orderId] = await sell(now() + 60);
+        await expectError(update(stranger, orderId, now() + 3600), "Unauthorized");
+    });
+});
@@ -1,40 +1,11 @@
 import * as anchor from "@coral-xyz/anchor";
-import { Program, AnchorError } from "@coral-xyz/anchor";
-import { OrderMatchingEngine } from "../target/types/order_matching_engine";
 import {
     Keypair,
-    LAMPORTS_PER_SOL,
     PublicKey,
     SystemProgram,
 } from "@solana/web3.js";
 import { assert } from "chai";
-
-// ─── Helpers ──────────────────────────────────────────────────────────────────
-
-const program = anchor.workspace.OrderMatchingEngine as Program<OrderMatchingEngine>;
-const provider = anchor.AnchorProvider.env();
-anchor.setProvider(provider);
-
-async function airdrop(pk: PublicKey, sol = 2) {
-    const sig = await provider.connection.requestAirdrop(pk, sol * LAMPORTS_PER_SOL);
-    await provider.connection.confirmTransaction(sig, "confirmed");
-}
-
-function marketPda(authority: PublicKey, name: string): [PublicKey, number] {
-    return PublicKey.findProgramAddressSync(
-        [Buffer.from("market"), authority.toBuffer(), Buffer.from(name)],
-        program.programId
-    );
-}
-
-function orderPda(market: PublicKey, orderId: number): [PublicKey, number] {
-    const buf = Buffer.alloc(8);
-    buf.writeBigUInt64LE(BigInt(orderId));
-    return PublicKey.findProgramAddressSync(
-        [Buffer.from("order"), market.toBuffer(), buf],
-        program.programId
-    );
-}
+import { airdrop, escrowVaultPda, marketPda, orderPda, program, provider } from "./helpers";
 
 // ─────────────────────────────────────────────────────────────────────────────
 
@@ -57,7 +28,7 @@ describe("Order Matching Engine", () => {
     // ── 1. Initialize Market ─────────────────────────────────────────────────────
     it("Initializes a market", async () => {
         await program.methods
-            .initializeMarket(MARKET_NAME)
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
             .accounts({
                 authority: authority.publicKey,
                 market: mktPda,
@@ -70,6 +41,12 @@ describe("Order Matching Engine", () => {
         assert.equal(mkt.nextOrderId.toNumber(), 0);
         assert.equal(mkt.totalBidVolume.toNumber(), 0);
         assert.equal(mkt.totalAskVolume.toNumber(), 0);
+        // 1 / 1 / 1: any price and quantity, as before order-size limits
+        assert.equal(mkt.tickSize.toNumber(), 1);
+        assert.equal(mkt.lotSize.toNumber(), 1);
+        assert.equal(mkt.minOrderQuantity.toNumber(), 1);
+        // Market::LEN, with the tick, lot and minimum fields
+        assert.equal((await provider.connection.getAccountInfo(mktPda)).data.length, 816);
     });
 
     // ── 2. Place BUY order ───────────────────────────────────────────────────────
@@ -97,10 +74,14 @@ describe("Order Matching Engine", () => {
         assert.equal(order.filledQuantity.toNumber(), 0);
         assert.ok(order.status.open !== undefined, "status should be open");
 
-        // Verify escrow: Order PDA should hold price * qty lamports
-        const pdaBal = await provider.connection.getBalance(oPda);
+        // Verify escrow: the escrow vault holds price * qty lamports on top
+        // of its rent, the Order PDA only its own rent
         const escrow = 100_000 * 5; // 500_000 lamports
-        assert.isAtLeast(pdaBal, escrow, "Order PDA should hold escrowed lamports");
+        const vaultBal = await provider.connection.getBalance(escrowVaultPda(mktPda, 0)[0]);
+        const vaultRent = await provider.connection.getMinimumBalanceForRentExemption(0);
+        assert.equal(vaultBal, vaultRent + escrow, "Escrow vault should hold escrowed lamports");
+        const rent = await provider.connection.getMinimumBalanceForRentExemption(program.account.order.size);
+        assert.equal(await provider.connection.getBalance(oPda), rent, "Order PDA should hold only its rent");
 
         // Buyer balance should have decreased
         const afterBal = await provider.connection.getBalance(buyer.publicKey);
@@ -167,6 +148,11 @@ describe("Order Matching Engine", () => {
         assert.ok(bid.status.filled !== undefined, "bid should be Filled");
         assert.ok(ask.status.filled !== undefined, "ask should be Filled");
 
+        // The filled quantity leaves the book on both sides
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.totalBidVolume.toNumber(), 0);
+        assert.equal(mkt.totalAskVolume.toNumber(), 0);
+
         // Seller receives fill_qty * ask_price = 5 * 99_000 = 495_000 lamports
         const sellerAfter = await provider.connection.getBalance(seller.publicKey);
         assert.isAbove(sellerAfter, sellerBefore, "Seller balance should increase");
@@ -208,7 +194,7 @@ describe("Order Matching Engine", () => {
         const buyerBefore = await provider.connection.getBalance(buyer.publicKey);
 
         await program.methods
-            .cancelOrder(new anchor.BN(2))
+            .cancelOrder(new anchor.BN(2), new anchor.BN(0))
             .accounts({ owner: buyer.publicKey, market: mktPda, order: bid2, systemProgram: SystemProgram.programId })
             .signers([buyer]).rpc();
 
@@ -237,7 +223,7 @@ describe("Order Matching Engine", () => {
         // Create a second market
         const market2Name = "ETH/MOCK";
         const [mkt2] = marketPda(authority.publicKey, market2Name);
-        await program.methods.initializeMarket(market2Name)
+        await program.methods.initializeMarket(market2Name, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
             .accounts({ authority: authority.publicKey, market: mkt2, systemProgram: SystemProgram.programId })
             .rpc();
 
@@ -269,7 +255,7 @@ describe("Order Matching Engine", () => {
         const [ask3] = orderPda(mktPda, 3);
         try {
             await program.methods
-                .cancelOrder(new anchor.BN(3))
+                .cancelOrder(new anchor.BN(3), new anchor.BN(0))
                 .accounts({ owner: stranger.publicKey, market: mktPda, order: ask3, systemProgram: SystemProgram.programId })
                 .signers([stranger]).rpc();
             assert.fail("Expected Unauthorized error");
@@ -284,7 +270,15 @@ describe("Order Matching Engine", () => {
 
     // ── 10. Close filled order, reclaim rent ─────────────────────────────────────
     it("Closes a Filled order PDA and reclaims rent to owner", async () => {
-        // bidPda (order #0) is Filled from test #4
+        // bidPda (order #0) is Filled from test #4 — the fill drained its
+        // escrow vault down to the vault's own rent and never touched the
+        // order, which holds exactly the rent-exempt minimum
+        const [bidVault] = escrowVaultPda(mktPda, 0);
+        const rent = await provider.connection.getMinimumBalanceForRentExemption(program.account.order.size);
+        const vaultRent = await provider.connection.getMinimumBalanceForRentExemption(0);
+        assert.equal(await provider.connection.getBalance(bidPda), rent);
+        assert.equal(await provider.connection.getBalance(bidVault), vaultRent);
+        assert.equal((await program.account.order.fetch(bidPda)).escrowLamports.toNumber(), 0);
         const ownerBefore = await provider.connection.getBalance(buyer.publicKey);
 
         await program.methods
@@ -301,9 +295,10 @@ describe("Order Matching Engine", () => {
         // Account should be gone (null info)
         const info = await provider.connection.getAccountInfo(bidPda);
         assert.isNull(info, "Order PDA should be closed (null account info)");
+        assert.isNull(await provider.connection.getAccountInfo(bidVault), "Escrow vault should be closed");
 
-        // Owner should have received rent back
+        // Owner should have received both rents back
         const ownerAfter = await provider.connection.getBalance(buyer.publicKey);
-        assert.isAbove(ownerAfter, ownerBefore, "Rent should be returned to owner");
+        assert.equal(ownerAfter - ownerBefore, rent + vaultRent, "Rent should be returned to owner");
     });
 });
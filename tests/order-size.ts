@@ -0,0 +1,83 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, place, program, provider } from "./helpers";
+
+describe("Tick size, lot size and minimum order quantity", () => {
+    const MARKET_NAME = "SIZED/MOCK";
+    const TICK = 100;
+    const LOT = 5;
+    const MIN_QTY = 10;
+    const authority = provider.wallet;
+    const trader = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+
+    const init = (name: string, tick: number, lot: number, minQty: number) =>
+        program.methods
+            .initializeMarket(name, new anchor.BN(0), new anchor.BN(tick), new anchor.BN(lot), new anchor.BN(minQty), 0, false)
+            .accounts({
+                authority: authority.publicKey,
+                market: marketPda(authority.publicKey, name)[0],
+                systemProgram: SystemProgram.programId,
+            })
+            .rpc();
+
+    before(async () => {
+        await airdrop(trader.publicKey, 5);
+        await init(MARKET_NAME, TICK, LOT, MIN_QTY);
+    });
+
+    it("Stores the limits on the market", async () => {
+        const market = await program.account.market.fetch(mktPda);
+        assert.equal(market.tickSize.toNumber(), TICK);
+        assert.equal(market.lotSize.toNumber(), LOT);
+        assert.equal(market.minOrderQuantity.toNumber(), MIN_QTY);
+    });
+
+    it("Rejects a zero tick or lot size", async () => {
+        await expectError(init("ZERO-TICK/MOCK", 0, 1, 1), "InvalidTickSize");
+        await expectError(init("ZERO-LOT/MOCK", 1, 0, 1), "InvalidLotSize");
+    });
+
+    it("Rejects off-tick prices, off-lot quantities and small orders", async () => {
+        await expectError(place(mktPda, trader, { buy: {} }, 1_050, MIN_QTY), "InvalidTickSize");
+        await expectError(place(mktPda, trader, { sell: {} }, 1_000, MIN_QTY + 1), "InvalidLotSize");
+        await expectError(place(mktPda, trader, { sell: {} }, 1_000, LOT), "OrderTooSmall");
+    });
+
+    it("Accepts an order on the grid", async () => {
+        const [order] = await place(mktPda, trader, { buy: {} }, 1_000, MIN_QTY + LOT);
+        assert.deepEqual((await program.account.order.fetch(order)).status, { open: {} });
+    });
+
+    it("Applies the same limits to modify_order", async () => {
+        const [order, orderId] = await place(mktPda, trader, { sell: {} }, 2_000, MIN_QTY);
+        const modify = (price: number | null, qty: number | null) =>
+            program.methods
+                .modifyOrder(
+                    new anchor.BN(orderId),
+                    price === null ? null : new anchor.BN(price),
+                    qty === null ? null : new anchor.BN(qty)
+                )
+                .accounts({
+                    owner: trader.publicKey,
+                    market: mktPda,
+                    order,
+                    tradingBalance: null,
+                    userStats: null,
+                    funder: null,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([trader])
+                .rpc();
+
+        await expectError(modify(2_001, null), "InvalidTickSize");
+        await expectError(modify(null, MIN_QTY + 2), "InvalidLotSize");
+        await expectError(modify(null, LOT), "OrderTooSmall");
+        await modify(2_100, MIN_QTY + LOT);
+        const modified = await program.account.order.fetch(order);
+        assert.equal(modified.price.toNumber(), 2_100);
+        assert.equal(modified.quantity.toNumber(), MIN_QTY + LOT);
+    });
+});
@@ -0,0 +1,71 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, place, program, provider, traderSeatPda } from "./helpers";
+
+describe("Permissioned markets", () => {
+    const MARKET_NAME = "KYC/MOCK";
+    const OPEN_NAME = "OPEN/MOCK";
+    const authority = provider.wallet;
+    const trader = Keypair.generate();
+    const outsider = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [openPda] = marketPda(authority.publicKey, OPEN_NAME);
+    const [traderSeat] = traderSeatPda(mktPda, trader.publicKey);
+
+    const initMarket = (name: string, permissioned: boolean) =>
+        program.methods
+            .initializeMarket(name, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, permissioned)
+            .accounts({ authority: authority.publicKey, market: marketPda(authority.publicKey, name)[0], systemProgram: SystemProgram.programId })
+            .rpc();
+
+    before(async () => {
+        for (const kp of [trader, outsider]) await airdrop(kp.publicKey, 2);
+        await initMarket(MARKET_NAME, true);
+        await initMarket(OPEN_NAME, false);
+        await program.methods
+            .addTrader(trader.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, traderSeat, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Records the flag at initialization", async () => {
+        assert.isTrue((await program.account.market.fetch(mktPda)).permissioned);
+        assert.isFalse((await program.account.market.fetch(openPda)).permissioned);
+    });
+
+    it("Rejects orders from owners without a seat", async () => {
+        await expectError(place(mktPda, outsider, { buy: {} }, 10_000, 2, { orderId: 0 }), "TraderNotWhitelisted");
+        // Another trader's seat doesn't pass the seeds check
+        await expectError(place(mktPda, outsider, { buy: {} }, 10_000, 2, { orderId: 0, accounts: { traderSeat } }), "ConstraintSeeds");
+    });
+
+    it("Accepts orders from seat holders", async () => {
+        await place(mktPda, trader, { buy: {} }, 10_000, 2, { orderId: 0, accounts: { traderSeat } });
+        const order = await program.account.order.fetch(orderPda(mktPda, 0)[0]);
+        assert.ok(order.owner.equals(trader.publicKey));
+    });
+
+    it("After revocation blocks new orders but still lets the trader cancel", async () => {
+        await program.methods
+            .removeTrader(trader.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, traderSeat })
+            .rpc();
+        await expectError(place(mktPda, trader, { sell: {} }, 10_000, 2, { orderId: 1 }), "TraderNotWhitelisted");
+
+        await program.methods
+            .cancelOrder(new anchor.BN(0), new anchor.BN(0))
+            .accounts({ owner: trader.publicKey, market: mktPda, order: orderPda(mktPda, 0)[0], tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([trader])
+            .rpc();
+        const order = await program.account.order.fetch(orderPda(mktPda, 0)[0]);
+        assert.deepEqual(order.status, { cancelled: {} });
+    });
+
+    it("Never asks for a seat on a permissionless market", async () => {
+        await place(openPda, outsider, { sell: {} }, 10_000, 2, { orderId: 0 });
+        const order = await program.account.order.fetch(orderPda(openPda, 0)[0]);
+        assert.ok(order.owner.equals(outsider.publicKey));
+    });
+});
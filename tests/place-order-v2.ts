@@ -0,0 +1,122 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, orderV2Pda, program, provider } from "./helpers";
+
+describe("place_order_v2", () => {
+    const MARKET_NAME = "V2/MOCK";
+    const authority = provider.wallet;
+    const alice = Keypair.generate();
+    const bob = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const fetchMarket = () => program.account.market.fetch(mktPda);
+
+    const placeV2 = (owner: Keypair, side: any, price: number, quantity: number, nonce: number) =>
+        program.methods
+            .placeOrderV2(side, new anchor.BN(price), new anchor.BN(quantity), new anchor.BN(nonce), new anchor.BN(0))
+            .accounts({ owner: owner.publicKey, market: mktPda, order: orderV2Pda(mktPda, owner.publicKey, nonce)[0], tradingBalance: null })
+            .signers([owner])
+            .rpc();
+
+    const placeV1 = (owner: Keypair, orderId: number) =>
+        program.methods
+            .placeOrder({ sell: {} }, new anchor.BN(1_000), new anchor.BN(1), new anchor.BN(orderId), new anchor.BN(0))
+            .accounts({ owner: owner.publicKey, market: mktPda, order: orderPda(mktPda, orderId)[0], systemProgram: SystemProgram.programId })
+            .signers([owner])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [alice, bob]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Lets two owners place against the same next_order_id", async () => {
+        const { nextOrderId } = await fetchMarket();
+        // Both built from the same market state and sent together
+        await Promise.all([
+            placeV2(alice, { buy: {} }, 1_000, 5, 7),
+            placeV2(bob, { sell: {} }, 900, 5, 7),
+        ]);
+
+        const bid = await program.account.order.fetch(orderV2Pda(mktPda, alice.publicKey, 7)[0]);
+        const ask = await program.account.order.fetch(orderV2Pda(mktPda, bob.publicKey, 7)[0]);
+        // The program hands out the sequential ids itself
+        assert.sameMembers(
+            [bid.orderId.toNumber(), ask.orderId.toNumber()],
+            [nextOrderId.toNumber(), nextOrderId.toNumber() + 1]
+        );
+        assert.isTrue(bid.ownerSeeded);
+        assert.equal(bid.clientNonce.toNumber(), 7);
+        assert.equal(bid.escrowLamports.toNumber(), 5_000);
+        assert.equal((await fetchMarket()).nextOrderId.toNumber(), nextOrderId.toNumber() + 2);
+    });
+
+    it("Still races with place_order", async () => {
+        const { nextOrderId } = await fetchMarket();
+        const results = await Promise.allSettled([placeV1(alice, nextOrderId.toNumber()), placeV1(bob, nextOrderId.toNumber())]);
+        assert.equal(results.filter((r) => r.status === "fulfilled").length, 1);
+    });
+
+    it("Rejects a nonce the owner already used", async () => {
+        try {
+            await placeV2(alice, { buy: {} }, 1_000, 1, 7);
+            assert.fail("Expected the reused nonce to fail");
+        } catch (err: any) {
+            assert.include(err.message, "already in use");
+        }
+    });
+
+    it("Matches v2 orders with match_orders", async () => {
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: orderV2Pda(mktPda, alice.publicKey, 7)[0],
+                askOrder: orderV2Pda(mktPda, bob.publicKey, 7)[0],
+                bidOwner: alice.publicKey,
+                askOwner: bob.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+            })
+            .rpc();
+        const bid = await program.account.order.fetch(orderV2Pda(mktPda, alice.publicKey, 7)[0]);
+        assert.deepEqual(bid.status, { filled: {} });
+    });
+
+    it("Cancels and closes by nonce", async () => {
+        const [order] = orderV2Pda(mktPda, alice.publicKey, 8);
+        await placeV2(alice, { buy: {} }, 1_000, 3, 8);
+        const { orderId, updateCount } = await program.account.order.fetch(order);
+
+        // The id-seeded instructions can't reach it
+        await expectError(
+            program.methods
+                .cancelOrder(orderId, new anchor.BN(0))
+                .accounts({ owner: alice.publicKey, market: mktPda, order, tradingBalance: null, systemProgram: SystemProgram.programId })
+                .signers([alice])
+                .rpc(),
+            "ConstraintSeeds"
+        );
+
+        const before = await provider.connection.getBalance(alice.publicKey);
+        await program.methods
+            .cancelOrderV2(new anchor.BN(8), updateCount)
+            .accounts({ owner: alice.publicKey, market: mktPda, order, tradingBalance: null, userStats: null, openOrders: null })
+            .signers([alice])
+            .rpc();
+        assert.equal((await provider.connection.getBalance(alice.publicKey)) - before, 3_000);
+        assert.deepEqual((await program.account.order.fetch(order)).status, { cancelled: {} });
+
+        await program.methods
+            .closeOrderV2(new anchor.BN(8))
+            .accounts({ owner: alice.publicKey, market: mktPda, order })
+            .signers([alice])
+            .rpc();
+        assert.isNull(await provider.connection.getAccountInfo(order));
+    });
+});
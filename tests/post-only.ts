@@ -0,0 +1,75 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, program, provider } from "./helpers";
+
+describe("Post-only orders", () => {
+    const MARKET_NAME = "POSTONLY/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+
+    const q64 = (price: number) => new anchor.BN(price).shln(64).toString();
+
+    async function place(owner: Keypair, side: any, price: number, qty: number, postOnly = false): Promise<[number, PublicKey]> {
+        const { nextOrderId } = await program.account.market.fetch(mktPda);
+        const [order] = orderPda(mktPda, nextOrderId.toNumber());
+        const method = postOnly ? program.methods.placeOrderPostOnly : program.methods.placeOrder;
+        await method(side, new anchor.BN(price), new anchor.BN(qty), nextOrderId, new anchor.BN(0))
+            .accounts({ owner: owner.publicKey, market: mktPda, order, systemProgram: SystemProgram.programId })
+            .signers([owner])
+            .rpc();
+        return [nextOrderId.toNumber(), order];
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Rejects a post-only bid at the best ask and rests one below it", async () => {
+        await place(seller, { sell: {} }, 100, 2);
+        let market = await program.account.market.fetch(mktPda);
+        assert.equal(market.bestAskQ64.toString(), q64(100));
+        assert.equal(market.bestAskQuantity.toNumber(), 2);
+
+        await expectError(place(buyer, { buy: {} }, 100, 1, true), "PostOnlyWouldCross");
+
+        const [, bid] = await place(buyer, { buy: {} }, 99, 1, true);
+        const order = await program.account.order.fetch(bid);
+        assert.isTrue(order.postOnly);
+        assert.deepEqual(order.status, { open: {} });
+        market = await program.account.market.fetch(mktPda);
+        assert.equal(market.bestBidQ64.toString(), q64(99));
+    });
+
+    it("Rejects a post-only ask at or below the best bid", async () => {
+        await expectError(place(seller, { sell: {} }, 99, 1, true), "PostOnlyWouldCross");
+        await place(seller, { sell: {} }, 101, 1, true);
+        // Worse than the best ask: the top of book is unchanged
+        const market = await program.account.market.fetch(mktPda);
+        assert.equal(market.bestAskQ64.toString(), q64(100));
+        assert.equal(market.bestAskQuantity.toNumber(), 2);
+    });
+
+    it("Clears the best level once it is cancelled", async () => {
+        const [, ask] = await place(seller, { sell: {} }, 98, 1);
+        assert.equal((await program.account.market.fetch(mktPda)).bestAskQ64.toString(), q64(98));
+
+        const { orderId } = await program.account.order.fetch(ask);
+        await program.methods
+            .cancelOrder(orderId, new anchor.BN(0))
+            .accounts({ owner: seller.publicKey, market: mktPda, order: ask, tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([seller])
+            .rpc();
+
+        const market = await program.account.market.fetch(mktPda);
+        assert.equal(market.bestAskQ64.toString(), "0");
+        assert.equal(market.bestAskQuantity.toNumber(), 0);
+    });
+});
@@ -0,0 +1,127 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram, Transaction } from "@solana/web3.js";
+import { assert } from "chai";
+import { decodeCancelPreview, previewCancel } from "../client/cancelPreview";
+import { airdrop, escrowVaultPda, marketPda, place, program, provider } from "./helpers";
+
+function errorCode(name: string): number {
+    const err = program.idl.errors.find((e) => e.name.toLowerCase() === name.toLowerCase());
+    if (!err) throw new Error(`unknown error ${name}`);
+    return err.code;
+}
+
+describe("preview_cancel", () => {
+    const MARKET_NAME = "PREVIEW/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const balance = (pk: PublicKey) => provider.connection.getBalance(pk);
+
+    // Cancel, then close, checking each payout against the preview taken first
+    async function cancelAndCloseMatchesPreview(owner: Keypair, id: number, order: PublicKey) {
+        const preview = await previewCancel(program as any, mktPda, id);
+        assert.isTrue(preview.canCancel);
+        assert.equal(preview.errorCode, 0);
+        assert.isTrue(preview.refundWallet.equals(owner.publicKey));
+
+        let before = await balance(owner.publicKey);
+        await program.methods
+            .cancelOrder(new anchor.BN(id), new anchor.BN(0))
+            .accounts({ owner: owner.publicKey, market: mktPda, order, tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([owner])
+            .rpc();
+        assert.equal((await balance(owner.publicKey)) - before, preview.escrowRefund);
+
+        before = await balance(owner.publicKey);
+        await program.methods
+            .closeOrder(new anchor.BN(id))
+            .accounts({ owner: owner.publicKey, market: mktPda, order, rentSubsidyVault: null, systemProgram: SystemProgram.programId })
+            .signers([owner])
+            .rpc();
+        assert.equal((await balance(owner.publicKey)) - before, preview.rentRefund + preview.vaultRentRefund);
+        return preview;
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 5);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Matches the refund of an untouched buy", async () => {
+        const [order, id] = await place(mktPda, buyer, { buy: {} }, 10_000, 3);
+        const preview = await cancelAndCloseMatchesPreview(buyer, id, order);
+        assert.equal(preview.escrowRefund, 30_000);
+        assert.equal(preview.remainingQuantity, 3);
+        assert.isAbove(preview.rentRefund, 0);
+    });
+
+    it("Matches the refund of a partially filled, price-improved buy", async () => {
+        const [bid, bidId] = await place(mktPda, buyer, { buy: {} }, 12_345, 5);
+        const [ask] = await place(mktPda, seller, { sell: {} }, 10_000, 2);
+        const mkt = await program.account.market.fetch(mktPda);
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: mkt.feeRecipient,
+                bidTradingBalance: null,
+            })
+            .rpc();
+
+        // Improvement on the filled 2 was already refunded; 3 left at the bid price
+        const preview = await cancelAndCloseMatchesPreview(buyer, bidId, bid);
+        assert.equal(preview.remainingQuantity, 3);
+        assert.equal(preview.escrowRefund, 3 * 12_345);
+    });
+
+    it("Refunds no escrow for a sell, only rent", async () => {
+        const [order, id] = await place(mktPda, seller, { sell: {} }, 20_000, 4);
+        const preview = await cancelAndCloseMatchesPreview(seller, id, order);
+        assert.equal(preview.escrowRefund, 0);
+        assert.equal(preview.rentRefund, (await provider.connection.getMinimumBalanceForRentExemption(program.account.order.size)));
+    });
+
+    it("Reports why an inactive order can't be cancelled", async () => {
+        const [order, id] = await place(mktPda, buyer, { buy: {} }, 10_000, 1);
+        await program.methods
+            .cancelOrder(new anchor.BN(id), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order, tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+
+        const preview = await previewCancel(program as any, mktPda, id);
+        assert.isFalse(preview.canCancel);
+        assert.equal(preview.errorCode, errorCode("OrderNotActive"));
+        assert.equal(preview.escrowRefund, 0);
+        // The escrow went back on cancel; both rents remain
+        assert.equal(preview.rentRefund, await balance(order));
+        assert.equal(preview.vaultRentRefund, await balance(escrowVaultPda(mktPda, id)[0]));
+    });
+
+    it("Decodes raw return data from a simulated transaction", async () => {
+        const [order, id] = await place(mktPda, buyer, { buy: {} }, 7_000, 2);
+        const ix = await program.methods
+            .previewCancel(new anchor.BN(id))
+            .accounts({ market: mktPda, order })
+            .instruction();
+        const tx = new Transaction().add(ix);
+        tx.feePayer = authority.publicKey;
+        tx.recentBlockhash = (await provider.connection.getLatestBlockhash()).blockhash;
+        const sim = await provider.connection.simulateTransaction(tx);
+        const preview = decodeCancelPreview(program as any, sim.value.returnData!.data[0]);
+        assert.isTrue(preview.canCancel);
+        assert.equal(preview.escrowRefund, 14_000);
+        assert.deepEqual(preview, await previewCancel(program as any, mktPda, id));
+    });
+});
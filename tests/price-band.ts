@@ -0,0 +1,84 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, feeConfigPda, marketPda, orderPda, place, program, provider } from "./helpers";
+
+describe("Price band", () => {
+    const MARKET_NAME = "BAND/MOCK";
+    const BAND_BPS = 500; // 5%
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const treasury = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+    const fetchMarket = () => program.account.market.fetch(mktPda);
+
+    const setBand = (priceBandBps: number) =>
+        program.methods
+            .updateMarketParams({ feeBps: 0, treasury: treasury.publicKey, paramsTimelockSecs: new anchor.BN(0), priceBandBps: new anchor.BN(priceBandBps) })
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda })
+            .rpc();
+
+    const match = (bidId: number, askId: number) =>
+        program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, bidId)[0],
+                askOrder: orderPda(mktPda, askId)[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: treasury.publicKey,
+            })
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller, treasury]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeFeeConfig(0, treasury.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await setBand(BAND_BPS);
+        assert.equal((await fetchMarket()).priceBandBps.toNumber(), BAND_BPS);
+    });
+
+    it("Exempts the market's first trade", async () => {
+        await place(mktPda, buyer, { buy: {} }, 20_000, 10, { orderId: 0 });
+        await place(mktPda, seller, { sell: {} }, 10_000, 1, { orderId: 1 });
+        await match(0, 1);
+        assert.equal((await fetchMarket()).lastTradePrice.toNumber(), 10_000);
+    });
+
+    it("Accepts orders exactly at the band edge and rejects one lamport beyond", async () => {
+        await place(mktPda, seller, { sell: {} }, 10_500, 1, { orderId: 2 });
+        await place(mktPda, seller, { sell: {} }, 9_500, 1, { orderId: 3 });
+        await expectError(place(mktPda, seller, { sell: {} }, 10_501, 1, { orderId: 4 }), "PriceOutOfBand");
+        await expectError(place(mktPda, buyer, { buy: {} }, 9_499, 1, { orderId: 4 }), "PriceOutOfBand");
+        // The fat-finger case: a bid at 10x the going price
+        await expectError(place(mktPda, buyer, { buy: {} }, 100_000, 1, { orderId: 4 }), "PriceOutOfBand");
+    });
+
+    it("Rejects a fill that has drifted outside the band since placement", async () => {
+        // Fills at the edge move the last price to 10_500...
+        await match(0, 2);
+        assert.equal((await fetchMarket()).lastTradePrice.toNumber(), 10_500);
+        // ...leaving the ask at 9_500 (placed in band) ~9.5% away
+        await expectError(match(0, 3), "PriceOutOfBand");
+    });
+
+    it("Treats a zero band as disabled", async () => {
+        await setBand(0);
+        await match(0, 3);
+        assert.equal((await fetchMarket()).lastTradePrice.toNumber(), 9_500);
+        await place(mktPda, seller, { sell: {} }, 100, 1, { orderId: 4 });
+        assert.equal((await program.account.order.fetch(orderPda(mktPda, 4)[0])).price.toNumber(), 100);
+    });
+});
@@ -0,0 +1,103 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, place, program, provider, userStatsPda } from "./helpers";
+
+describe("New-account probation limits", () => {
+    const MARKET_NAME = "PROBATION/MOCK";
+    const PRICE = 10_000;
+    const PROBATION_FILLS = 2;
+    const MAX_NOTIONAL = 2 * PRICE;
+    const MAX_OPEN = 2;
+    const authority = provider.wallet;
+    const newbie = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const statsOf = (kp: Keypair) => userStatsPda(mktPda, kp.publicKey)[0];
+
+    const rest = async (owner: Keypair, side: any, qty: number, withStats = true) =>
+        (await place(mktPda, owner, side, PRICE, qty, withStats ? { accounts: { userStats: statsOf(owner) } } : {}))[0];
+
+    const setLimits = (fills: number, volume: number, notional: number, open: number) =>
+        program.methods
+            .setProbationLimits(new anchor.BN(fills), new anchor.BN(volume), new anchor.BN(notional), new anchor.BN(open))
+            .accounts({ authority: authority.publicKey, market: mktPda })
+            .rpc();
+
+    const bids: PublicKey[] = [];
+
+    before(async () => {
+        await airdrop(newbie.publicKey, 5);
+        await airdrop(seller.publicKey, 5);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        for (const kp of [newbie, seller]) {
+            await program.methods
+                .initializeUserStats()
+                .accounts({ owner: kp.publicKey, market: mktPda, userStats: statsOf(kp), systemProgram: SystemProgram.programId })
+                .signers([kp])
+                .rpc();
+        }
+        // Graduate after 2 fills; the volume bar is set out of reach
+        await setLimits(PROBATION_FILLS, 1_000_000_000, MAX_NOTIONAL, MAX_OPEN);
+    });
+
+    it("Requires user stats while probation is on", async () => {
+        await expectError(rest(newbie, { buy: {} }, 1, false), "UserStatsRequired");
+    });
+
+    it("Caps per-order notional for new accounts", async () => {
+        await expectError(rest(newbie, { buy: {} }, 3), "ProbationOrderTooLarge");
+    });
+
+    it("Caps open orders for new accounts", async () => {
+        bids.push(await rest(newbie, { buy: {} }, 1));
+        bids.push(await rest(newbie, { buy: {} }, 1));
+        await expectError(rest(newbie, { buy: {} }, 1), "ProbationOpenOrderLimit");
+
+        const stats = await program.account.userStats.fetch(statsOf(newbie));
+        assert.equal(stats.openOrders.toNumber(), 2);
+    });
+
+    it("Graduates via fills, after which the caps no longer apply", async () => {
+        for (const bid of bids) {
+            const ask = await rest(seller, { sell: {} }, 1);
+            await program.methods
+                .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+                .accounts({
+                    matcher: authority.publicKey,
+                    market: mktPda,
+                    bidOrder: bid,
+                    askOrder: ask,
+                    bidOwner: newbie.publicKey,
+                    askOwner: seller.publicKey,
+                    feeConfig: null,
+                    treasury: authority.publicKey,
+                    bidTradingBalance: null,
+                    bidUserStats: statsOf(newbie),
+                    askUserStats: statsOf(seller),
+                })
+                .rpc();
+        }
+
+        const stats = await program.account.userStats.fetch(statsOf(newbie));
+        assert.equal(stats.fillCount.toNumber(), PROBATION_FILLS);
+        assert.equal(stats.filledNotional.toNumber(), 2 * PRICE);
+        assert.equal(stats.openOrders.toNumber(), 0);
+
+        // Bigger than the probation notional cap, and more than MAX_OPEN orders
+        await rest(newbie, { buy: {} }, 5);
+        await rest(newbie, { buy: {} }, 1);
+        await rest(newbie, { buy: {} }, 1);
+    });
+
+    it("Imposes nothing when the feature is disabled", async () => {
+        await setLimits(0, 0, MAX_NOTIONAL, MAX_OPEN);
+        const fresh = Keypair.generate();
+        await airdrop(fresh.publicKey, 2);
+        await rest(fresh, { buy: {} }, 10, false);
+    });
+});
@@ -0,0 +1,165 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, configPda, expectError, feeConfigPda, feeVaultPda, marketPda, matchPair, place, program, provider } from "./helpers";
+
+describe("Protocol fee split", () => {
+    const MARKET_NAME = "SPLIT/MOCK";
+    const SHARE_BPS = 2_000; // 20% to the protocol
+    const admin = provider.wallet;
+    const operator = Keypair.generate();
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const payout = Keypair.generate();
+
+    const [cfgPda] = configPda();
+    const [mktPda] = marketPda(operator.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+    const [vaultPda] = feeVaultPda(mktPda);
+    const balance = (pk: PublicKey) => provider.connection.getBalance(pk);
+
+    async function trade(price: number, qty: number) {
+        const [bid] = await place(mktPda, buyer, { buy: {} }, price, qty);
+        const [ask] = await place(mktPda, seller, { sell: {} }, price, qty);
+        await matchPair(bid, ask, { feeConfig: feePda, treasury: vaultPda, feeVault: vaultPda, config: cfgPda });
+    }
+
+    const withdrawFees = (signer: Keypair | null, amount: number) => {
+        const call = program.methods
+            .withdrawFees(new anchor.BN(amount))
+            .accounts({
+                authority: signer ? signer.publicKey : admin.publicKey,
+                market: mktPda,
+                feeVault: vaultPda,
+                destination: payout.publicKey,
+            });
+        return signer ? call.signers([signer]).rpc() : call.rpc();
+    };
+
+    const withdrawProtocolFees = (signer: Keypair | null, amount: number) => {
+        const call = program.methods
+            .withdrawProtocolFees(new anchor.BN(amount))
+            .accounts({
+                admin: signer ? signer.publicKey : admin.publicKey,
+                config: cfgPda,
+                market: mktPda,
+                feeVault: vaultPda,
+                destination: payout.publicKey,
+            });
+        return signer ? call.signers([signer]).rpc() : call.rpc();
+    };
+
+    before(async () => {
+        for (const kp of [operator, buyer, seller, payout]) await airdrop(kp.publicKey, 2);
+
+        // The config itself is created by the root hook in helpers.ts
+        await program.methods
+            .updateConfig(admin.publicKey, SHARE_BPS, payout.publicKey)
+            .accounts({ admin: admin.publicKey, config: cfgPda })
+            .rpc();
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: operator.publicKey, market: mktPda, feeVault: vaultPda, systemProgram: SystemProgram.programId })
+            .signers([operator])
+            .rpc();
+        await program.methods
+            .initializeFeeConfig(100, vaultPda)
+            .accounts({ authority: operator.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .signers([operator])
+            .rpc();
+    });
+
+    after(async () => {
+        // The config is protocol-wide; don't leak the split into other specs.
+        await program.methods
+            .updateConfig(admin.publicKey, 0, admin.publicKey)
+            .accounts({ admin: admin.publicKey, config: cfgPda })
+            .rpc();
+    });
+
+    it("Splits every fee exactly between market and protocol", async () => {
+        const vaultBefore = await balance(vaultPda);
+
+        // gross 100_000 ⇒ fee 1_000 ⇒ protocol 200, market 800
+        await trade(10_000, 10);
+        // gross 9_999 ⇒ fee 99 + 1 dust ⇒ protocol 19, market 80 + 1
+        await trade(9_999, 1);
+
+        const vault = await program.account.feeVault.fetch(vaultPda);
+        const cfg = await program.account.feeConfig.fetch(feePda);
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(vault.protocolFees.toNumber(), 219);
+        assert.equal(cfg.accumulatedFees.toNumber(), 1_099);
+        assert.equal(cfg.protocolAccumulatedFees.toNumber(), 219);
+        assert.equal(
+            (await balance(vaultPda)) - vaultBefore,
+            cfg.accumulatedFees.toNumber() + mkt.dustLamports.toNumber()
+        );
+    });
+
+    it("Neither party can withdraw the other's share", async () => {
+        // Market share is 880 + 1 dust; the protocol's 219 stays reserved
+        await expectError(withdrawFees(operator, 882), "InsufficientFees");
+        await expectError(withdrawProtocolFees(operator, 1), "Unauthorized");
+        await expectError(withdrawFees(null, 1), "Unauthorized");
+        await expectError(withdrawProtocolFees(null, 220), "InsufficientFees");
+    });
+
+    it("Pays protocol fees only to the treasury", async () => {
+        await expectError(
+            program.methods
+                .withdrawProtocolFees(new anchor.BN(1))
+                .accounts({ admin: admin.publicKey, config: cfgPda, market: mktPda, feeVault: vaultPda, destination: admin.publicKey })
+                .rpc(),
+            "ProtocolTreasuryMismatch"
+        );
+    });
+
+    it("Each party withdraws exactly its own share", async () => {
+        const before = await balance(payout.publicKey);
+        await withdrawFees(operator, 881);
+        await withdrawProtocolFees(null, 219);
+        assert.equal((await balance(payout.publicKey)) - before, 1_100);
+
+        const vault = await program.account.feeVault.fetch(vaultPda);
+        assert.equal(vault.protocolFees.toNumber(), 0);
+        await expectError(withdrawFees(operator, 1), "InsufficientFees");
+    });
+
+    it("Only the protocol admin may change the share", async () => {
+        await expectError(
+            program.methods
+                .setProtocolFeeShare(10_000)
+                .accounts({ admin: operator.publicKey, config: cfgPda })
+                .signers([operator])
+                .rpc(),
+            "Unauthorized"
+        );
+        await expectError(
+            program.methods.setProtocolFeeShare(10_001).accounts({ admin: admin.publicKey, config: cfgPda }).rpc(),
+            "InvalidProtocolFeeShare"
+        );
+    });
+
+    it("Only the protocol admin may update the config", async () => {
+        await expectError(
+            program.methods
+                .updateConfig(operator.publicKey, SHARE_BPS, operator.publicKey)
+                .accounts({ admin: operator.publicKey, config: cfgPda })
+                .signers([operator])
+                .rpc(),
+            "Unauthorized"
+        );
+        await expectError(
+            program.methods
+                .updateConfig(admin.publicKey, SHARE_BPS, PublicKey.default)
+                .accounts({ admin: admin.publicKey, config: cfgPda })
+                .rpc(),
+            "InvalidProtocolConfig"
+        );
+        const cfg = await program.account.globalConfig.fetch(cfgPda);
+        assert.isTrue(cfg.admin.equals(admin.publicKey));
+        assert.isTrue(cfg.treasury.equals(payout.publicKey));
+        assert.equal(cfg.protocolFeeShareBps, SHARE_BPS);
+    });
+});
@@ -0,0 +1,149 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, configPda, expectError, marketPda, orderPda, place, program, provider, tradingBalancePda } from "./helpers";
+
+describe("Global protocol pause", () => {
+    const MARKET_NAME = "GPAUSE/MOCK";
+    const PRICE = 10_000;
+    const DEPOSIT = 50_000;
+    const admin = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [cfgPda] = configPda();
+    const [mktPda] = marketPda(admin.publicKey, MARKET_NAME);
+    const [balancePda] = tradingBalancePda(mktPda, buyer.publicKey);
+    const [bidPda] = orderPda(mktPda, 0);
+    const [askPda] = orderPda(mktPda, 1);
+
+    const cancel = (owner: Keypair, orderId: number) =>
+        program.methods
+            .cancelOrder(new anchor.BN(orderId), new anchor.BN(0))
+            .accounts({
+                owner: owner.publicKey,
+                market: mktPda,
+                order: orderPda(mktPda, orderId)[0],
+                tradingBalance: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([owner])
+            .rpc();
+
+    const pauseProtocol = () => program.methods.pauseProtocol().accounts({ admin: admin.publicKey, config: cfgPda }).rpc();
+    const resumeProtocol = () => program.methods.resumeProtocol().accounts({ admin: admin.publicKey, config: cfgPda }).rpc();
+
+    before(async () => {
+        await airdrop(buyer.publicKey, 2);
+        await airdrop(seller.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: admin.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeTradingBalance()
+            .accounts({ owner: buyer.publicKey, market: mktPda, tradingBalance: balancePda, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        await program.methods
+            .depositBalance(new anchor.BN(DEPOSIT))
+            .accounts({ owner: buyer.publicKey, market: mktPda, tradingBalance: balancePda, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        // A crossing pair left resting when the pause lands
+        await place(mktPda, buyer, { buy: {} }, PRICE, 1, { orderId: 0 });
+        await place(mktPda, seller, { sell: {} }, PRICE, 1, { orderId: 1 });
+    });
+
+    after(async () => {
+        // The config is protocol-wide; never leave other specs paused.
+        const cfg = await program.account.globalConfig.fetch(cfgPda);
+        if (cfg.paused) await resumeProtocol();
+    });
+
+    it("Only the protocol admin may pause", async () => {
+        await expectError(
+            program.methods.pauseProtocol().accounts({ admin: buyer.publicKey, config: cfgPda }).signers([buyer]).rpc(),
+            "Unauthorized"
+        );
+
+        let seen: any = null;
+        const listener = program.addEventListener("protocolPausedEvent", (e) => (seen = e));
+        await pauseProtocol();
+        await new Promise((r) => setTimeout(r, 1000));
+        await program.removeEventListener(listener);
+        assert.isNotNull(seen);
+        assert.ok(seen.admin.equals(admin.publicKey));
+
+        const cfg = await program.account.globalConfig.fetch(cfgPda);
+        assert.isTrue(cfg.paused);
+        await expectError(pauseProtocol(), "ProtocolPaused");
+    });
+
+    it("Rejects initialize_market", async () => {
+        await expectError(
+            program.methods
+                .initializeMarket("GPAUSE/NEW", new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+                .accounts({ authority: admin.publicKey, market: marketPda(admin.publicKey, "GPAUSE/NEW")[0], systemProgram: SystemProgram.programId })
+                .rpc(),
+            "ProtocolPaused"
+        );
+    });
+
+    it("Rejects place_order on every market", async () => {
+        await expectError(place(mktPda, buyer, { buy: {} }, PRICE, 1, { orderId: 2 }), "ProtocolPaused");
+    });
+
+    it("Rejects match_orders on every market", async () => {
+        await expectError(
+            program.methods
+                .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+                .accounts({
+                    matcher: admin.publicKey,
+                    market: mktPda,
+                    bidOrder: bidPda,
+                    askOrder: askPda,
+                    bidOwner: buyer.publicKey,
+                    askOwner: seller.publicKey,
+                    feeConfig: null,
+                    treasury: admin.publicKey,
+                    bidTradingBalance: null,
+                    config: cfgPda,
+                })
+                .rpc(),
+            "ProtocolPaused"
+        );
+    });
+
+    it("Keeps cancel, close and withdrawal open", async () => {
+        const walletBefore = await provider.connection.getBalance(buyer.publicKey);
+        await cancel(buyer, 0);
+        await cancel(seller, 1);
+        const bid = await program.account.order.fetch(bidPda);
+        assert.deepEqual(bid.status, { cancelled: {} });
+        assert.isAbove(await provider.connection.getBalance(buyer.publicKey), walletBefore + PRICE - 10_000);
+
+        await program.methods
+            .closeOrder(new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: bidPda, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        assert.isNull(await provider.connection.getAccountInfo(bidPda));
+
+        await program.methods
+            .withdrawBalance(new anchor.BN(DEPOSIT))
+            .accounts({ owner: buyer.publicKey, market: mktPda, tradingBalance: balancePda })
+            .signers([buyer])
+            .rpc();
+        const bal = await program.account.tradingBalance.fetch(balancePda);
+        assert.equal(bal.lamports.toNumber(), 0);
+    });
+
+    it("Resuming reopens placement", async () => {
+        await resumeProtocol();
+        await expectError(resumeProtocol(), "ProtocolNotPaused");
+        await place(mktPda, buyer, { buy: {} }, PRICE, 1, { orderId: 2 });
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.nextOrderId.toNumber(), 3);
+    });
+});
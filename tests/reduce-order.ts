@@ -0,0 +1,126 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, place, program, provider, sleep } from "./helpers";
+
+describe("reduce_order", () => {
+    const MARKET_NAME = "REDUCE/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const balance = (key: PublicKey) => provider.connection.getBalance(key);
+    const fetchOrder = (orderId: number) => program.account.order.fetch(orderPda(mktPda, orderId)[0]);
+    const bidVolume = async () => (await program.account.market.fetch(mktPda)).totalBidVolume.toNumber();
+
+    const reduce = (owner: Keypair, orderId: number, reduceBy: number) =>
+        program.methods
+            .reduceOrder(new anchor.BN(orderId), new anchor.BN(reduceBy))
+            .accounts({
+                owner: owner.publicKey,
+                market: mktPda,
+                order: orderPda(mktPda, orderId)[0],
+                tradingBalance: null,
+                userStats: null,
+                funder: null,
+                openOrders: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([owner])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Shrinks a resting bid in place and refunds the removed units", async () => {
+        const [, id] = await place(mktPda, buyer, { buy: {} }, 100, 1_000);
+        const placed = await fetchOrder(id);
+        const before = await balance(buyer.publicKey);
+
+        let event: any = null;
+        const listener = program.addEventListener("orderReducedEvent", (e) => (event = e));
+        await reduce(buyer, id, 600);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        const order = await fetchOrder(id);
+        assert.equal(order.quantity.toNumber(), 400);
+        assert.equal(order.escrowLamports.toNumber(), 40_000);
+        assert.deepEqual(order.status, { open: {} });
+        // Queue position kept
+        assert.equal(order.timestamp.toNumber(), placed.timestamp.toNumber());
+        assert.equal((await balance(buyer.publicKey)) - before, 60_000);
+        assert.equal(await bidVolume(), 400);
+
+        assert.equal(event.oldQuantity.toNumber(), 1_000);
+        assert.equal(event.newQuantity.toNumber(), 400);
+        assert.equal(event.escrowRefunded.toNumber(), 60_000);
+        assert.isFalse(event.closed);
+    });
+
+    it("Rejects a stranger and a reduction past the unfilled remainder", async () => {
+        await expectError(reduce(seller, 0, 1), "Unauthorized");
+        await expectError(reduce(buyer, 0, 401), "InvalidQuantity");
+        await expectError(reduce(buyer, 0, 0), "InvalidQuantity");
+    });
+
+    it("Cancels an unfilled order reduced to nothing", async () => {
+        const before = await balance(buyer.publicKey);
+        await reduce(buyer, 0, 400);
+        const order = await fetchOrder(0);
+        assert.deepEqual(order.status, { cancelled: {} });
+        assert.equal(order.quantity.toNumber(), 0);
+        assert.equal(order.escrowLamports.toNumber(), 0);
+        assert.equal((await balance(buyer.publicKey)) - before, 40_000);
+        assert.equal(await bidVolume(), 0);
+    });
+
+    it("After a partial fill refunds from the remainder, then closes the order as Filled", async () => {
+        // Bid 10 @ 12_000 fills 4 @ 10_000; its 6 remaining units hold 72_000
+        const [, bid] = await place(mktPda, buyer, { buy: {} }, 12_000, 10);
+        const [, ask] = await place(mktPda, seller, { sell: {} }, 10_000, 4);
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, bid)[0],
+                askOrder: orderPda(mktPda, ask)[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+            })
+            .rpc();
+        assert.equal((await fetchOrder(bid)).escrowLamports.toNumber(), 72_000);
+
+        let before = await balance(buyer.publicKey);
+        await reduce(buyer, bid, 3);
+        let order = await fetchOrder(bid);
+        assert.equal(order.quantity.toNumber(), 7);
+        assert.equal(order.escrowLamports.toNumber(), 36_000);
+        assert.equal((await balance(buyer.publicKey)) - before, 36_000);
+        assert.equal(await bidVolume(), 3);
+
+        before = await balance(buyer.publicKey);
+        await reduce(buyer, bid, 3);
+        order = await fetchOrder(bid);
+        assert.deepEqual(order.status, { filled: {} });
+        assert.equal(order.quantity.toNumber(), 4);
+        assert.equal((await balance(buyer.publicKey)) - before, 36_000);
+        assert.equal(await bidVolume(), 0);
+
+        await program.methods
+            .closeOrder(new anchor.BN(bid))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: orderPda(mktPda, bid)[0], systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        assert.isNull(await provider.connection.getAccountInfo(orderPda(mktPda, bid)[0]));
+    });
+});
@@ -0,0 +1,120 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, configPda, expectError, feeConfigPda, feeVaultPda, marketPda, place, program, provider, sleep } from "./helpers";
+
+describe("Referral rebates", () => {
+    const MARKET_NAME = "REFERRAL/MOCK";
+    const SHARE_BPS = 2_000;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const treasury = Keypair.generate();
+    const referrer = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+    const [vaultPda] = feeVaultPda(mktPda);
+    const balance = (pk: PublicKey) => provider.connection.getBalance(pk);
+    const referred = { accounts: { referrer: referrer.publicKey } };
+
+    // 10 units @ 10_000 at 100 bps ⇒ 1_000 lamports of fee per match, of
+    // which the market keeps what the protocol doesn't take
+    const FEE = 1_000;
+    let marketFee: number;
+    const match = (bid: PublicKey, ask: PublicKey, askReferrer: PublicKey | null) =>
+        program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: feePda,
+                treasury: treasury.publicKey,
+                feeVault: vaultPda,
+                bidTradingBalance: null,
+                askReferrer,
+            })
+            .rpc();
+
+    const setShare = (signer: Keypair | null, bps: number) =>
+        program.methods
+            .setReferralShare(bps)
+            .accounts({ authority: signer?.publicKey ?? authority.publicKey, market: mktPda, roles: null })
+            .signers(signer ? [signer] : [])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller, stranger]) await airdrop(kp.publicKey, 5);
+        for (const kp of [treasury, referrer]) await airdrop(kp.publicKey, 1);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeVault: vaultPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeFeeConfig(100, treasury.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .rpc();
+        const { protocolFeeShareBps } = await program.account.globalConfig.fetch(configPda()[0]);
+        marketFee = FEE - Math.floor((FEE * protocolFeeShareBps) / 10_000);
+    });
+
+    it("Records the referrer passed at placement", async () => {
+        const [ask] = await place(mktPda, seller, { sell: {} }, 10_000, 10, referred);
+        const [bid] = await place(mktPda, buyer, { buy: {} }, 10_000, 10);
+        assert.ok((await program.account.order.fetch(ask)).referrer.equals(referrer.publicKey));
+        assert.ok((await program.account.order.fetch(bid)).referrer.equals(PublicKey.default));
+        await match(bid, ask, null);
+    });
+
+    it("Is set by the authority only, up to 100%", async () => {
+        assert.equal((await program.account.market.fetch(mktPda)).referralShareBps, 0);
+        await expectError(setShare(stranger, SHARE_BPS), "Unauthorized");
+        await expectError(setShare(null, 10_001), "InvalidReferralShare");
+        await setShare(null, SHARE_BPS);
+        assert.equal((await program.account.market.fetch(mktPda)).referralShareBps, SHARE_BPS);
+    });
+
+    it("Pays the ask's referrer its share of the market's fee", async () => {
+        const [ask] = await place(mktPda, seller, { sell: {} }, 10_000, 10, referred);
+        const [bid] = await place(mktPda, buyer, { buy: {} }, 10_000, 10);
+        const referrerBefore = await balance(referrer.publicKey);
+        const treasuryBefore = await balance(treasury.publicKey);
+
+        let event: any = null;
+        const listener = program.addEventListener("tradeExecutedEvent", (e) => (event = e));
+        await match(bid, ask, referrer.publicKey);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        const referral = Math.floor((marketFee * SHARE_BPS) / 10_000);
+        assert.equal((await balance(referrer.publicKey)) - referrerBefore, referral);
+        assert.equal((await balance(treasury.publicKey)) - treasuryBefore, marketFee - referral);
+        assert.ok(event.referrer.equals(referrer.publicKey));
+        assert.equal(event.referralAmount.toNumber(), referral);
+        assert.equal(event.feeAmount.toNumber(), FEE);
+    });
+
+    it("Rejects a referrer other than the ask's", async () => {
+        const [ask] = await place(mktPda, seller, { sell: {} }, 10_000, 10, referred);
+        const [bid] = await place(mktPda, buyer, { buy: {} }, 10_000, 10);
+        await expectError(match(bid, ask, stranger.publicKey), "ReferrerMismatch");
+
+        const [plainAsk] = await place(mktPda, seller, { sell: {} }, 10_000, 10);
+        await expectError(match(bid, plainAsk, referrer.publicKey), "ReferrerMismatch");
+    });
+
+    it("Keeps the share with the market when the referrer isn't passed", async () => {
+        const [ask] = await place(mktPda, seller, { sell: {} }, 10_000, 10, referred);
+        const [bid] = await place(mktPda, buyer, { buy: {} }, 10_000, 10);
+        const referrerBefore = await balance(referrer.publicKey);
+        const treasuryBefore = await balance(treasury.publicKey);
+        await match(bid, ask, null);
+        assert.equal(await balance(referrer.publicKey), referrerBefore);
+        assert.equal((await balance(treasury.publicKey)) - treasuryBefore, marketFee);
+    });
+});
@@ -0,0 +1,156 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import {
+    airdrop,
+    chainTime,
+    feeConfigPda,
+    marketPda,
+    orderPda,
+    program,
+    provider,
+    stagedParamsPda,
+    traderSeatPda,
+} from "./helpers";
+
+describe("Renounced market authority", () => {
+    const MARKET_NAME = "IMMUTABLE/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const treasury = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+    const [stagedPda] = stagedParamsPda(mktPda);
+    const [seatPda] = traderSeatPda(mktPda, buyer.publicKey);
+    const params = () => ({ feeBps: 0, treasury: treasury.publicKey, paramsTimelockSecs: new anchor.BN(0), priceBandBps: new anchor.BN(0) });
+
+    async function expectRenounced(call: Promise<unknown>) {
+        try {
+            await call;
+            assert.fail("Expected AuthorityRenounced error");
+        } catch (err: any) {
+            assert.include(err.message, "AuthorityRenounced");
+        }
+    }
+
+    async function renounce() {
+        return program.methods
+            .renounceAuthority()
+            .accounts({ authority: authority.publicKey, market: mktPda, stagedParams: stagedPda })
+            .rpc();
+    }
+
+    before(async () => {
+        await airdrop(buyer.publicKey, 5);
+        await airdrop(seller.publicKey, 5);
+        await airdrop(treasury.publicKey, 1);
+
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeFeeConfig(100, treasury.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Refuses to renounce while staged params are pending", async () => {
+        const now = await chainTime();
+        await program.methods
+            .stageMarketParams(params(), new anchor.BN(now + 60))
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, stagedParams: stagedPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        try {
+            await renounce();
+            assert.fail("Expected StagedParamsPending error");
+        } catch (err: any) {
+            assert.include(err.message, "StagedParamsPending");
+        }
+        await program.methods
+            .discardStagedParams()
+            .accounts({ authority: authority.publicKey, market: mktPda, stagedParams: stagedPda })
+            .rpc();
+    });
+
+    it("Renounces irreversibly and emits an event", async () => {
+        let seen: any = null;
+        const listener = program.addEventListener("authorityRenouncedEvent", (e) => (seen = e));
+        await renounce();
+        await new Promise((r) => setTimeout(r, 1000));
+        await program.removeEventListener(listener);
+
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.ok(mkt.authority.equals(PublicKey.default));
+        assert.ok(mkt.creator.equals(authority.publicKey), "PDA seed must survive the renounce");
+        assert.isNotNull(seen);
+        assert.ok(seen.previousAuthority.equals(authority.publicKey));
+
+        await expectRenounced(renounce());
+    });
+
+    it("Every admin instruction is dead", async () => {
+        const auth = { authority: authority.publicKey, market: mktPda };
+        await expectRenounced(program.methods.pauseMarket().accounts(auth).rpc());
+        await expectRenounced(program.methods.resumeMarket().accounts(auth).rpc());
+        await expectRenounced(program.methods.setMakersRestricted(true).accounts(auth).rpc());
+        await expectRenounced(
+            program.methods.updateFeeConfig(0, treasury.publicKey).accounts({ ...auth, feeConfig: feePda }).rpc()
+        );
+        await expectRenounced(
+            program.methods.updateMarketParams(params()).accounts({ ...auth, feeConfig: feePda }).rpc()
+        );
+        await expectRenounced(
+            program.methods
+                .stageMarketParams(params(), new anchor.BN((await chainTime()) + 60))
+                .accounts({ ...auth, feeConfig: feePda, stagedParams: stagedPda, systemProgram: SystemProgram.programId })
+                .rpc()
+        );
+        await expectRenounced(
+            program.methods
+                .addTrader(buyer.publicKey)
+                .accounts({ ...auth, traderSeat: seatPda, systemProgram: SystemProgram.programId })
+                .rpc()
+        );
+    });
+
+    it("Trading keeps working", async () => {
+        const [bid] = orderPda(mktPda, 0);
+        const [ask] = orderPda(mktPda, 1);
+        await program.methods
+            .placeOrder({ buy: {} }, new anchor.BN(10_000), new anchor.BN(2), new anchor.BN(0), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: bid, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        await program.methods
+            .placeOrder({ sell: {} }, new anchor.BN(10_000), new anchor.BN(1), new anchor.BN(1), new anchor.BN(0))
+            .accounts({ owner: seller.publicKey, market: mktPda, order: ask, systemProgram: SystemProgram.programId })
+            .signers([seller])
+            .rpc();
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: buyer.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: feePda,
+                treasury: treasury.publicKey,
+                bidTradingBalance: null,
+            })
+            .signers([buyer])
+            .rpc();
+        await program.methods
+            .cancelOrder(new anchor.BN(0), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: bid, tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+
+        const b = await program.account.order.fetch(bid);
+        assert.deepEqual(b.status, { cancelled: {} });
+    });
+});
@@ -0,0 +1,105 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, program, provider } from "./helpers";
+
+describe("Rent subsidy vault", () => {
+    const MARKET_NAME = "RENTSUB/MOCK";
+    const PRICE = 10_000;
+    const authority = provider.wallet;
+    const trader = Keypair.generate();
+    const sponsor = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [vaultPda] = PublicKey.findProgramAddressSync([Buffer.from("rent_subsidy"), mktPda.toBuffer()], program.programId);
+    const balance = (key: PublicKey) => provider.connection.getBalance(key);
+
+    let rent: number;
+    let subsidizedId: number;
+
+    async function placeSell(): Promise<[number, PublicKey]> {
+        const { nextOrderId } = await program.account.market.fetch(mktPda);
+        const [order] = orderPda(mktPda, nextOrderId.toNumber());
+        await program.methods
+            .placeOrder({ sell: {} }, new anchor.BN(PRICE), new anchor.BN(1), nextOrderId, new anchor.BN(0))
+            .accounts({ owner: trader.publicKey, market: mktPda, order, rentSubsidyVault: vaultPda, systemProgram: SystemProgram.programId })
+            .signers([trader])
+            .rpc();
+        return [nextOrderId.toNumber(), order];
+    }
+
+    async function cancelAndClose(orderId: number, vault: PublicKey | null) {
+        const [order] = orderPda(mktPda, orderId);
+        const status = (await program.account.order.fetch(order)).status;
+        if (!("cancelled" in status)) {
+            await program.methods
+                .cancelOrder(new anchor.BN(orderId), new anchor.BN(0))
+                .accounts({ owner: trader.publicKey, market: mktPda, order, tradingBalance: null, systemProgram: SystemProgram.programId })
+                .signers([trader])
+                .rpc();
+        }
+        await program.methods
+            .closeOrder(new anchor.BN(orderId))
+            .accounts({ owner: trader.publicKey, market: mktPda, order, rentSubsidyVault: vault, systemProgram: SystemProgram.programId })
+            .signers([trader])
+            .rpc();
+    }
+
+    before(async () => {
+        for (const kp of [trader, sponsor]) await airdrop(kp.publicKey, 2);
+        rent = await provider.connection.getMinimumBalanceForRentExemption(program.account.order.size);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeRentSubsidyVault()
+            .accounts({ authority: authority.publicKey, market: mktPda, rentSubsidyVault: vaultPda, roles: null, systemProgram: SystemProgram.programId })
+            .rpc();
+        // Anyone may fund: enough for one order and a half
+        await program.methods
+            .fundRentSubsidy(new anchor.BN(rent + rent / 2))
+            .accounts({ funder: sponsor.publicKey, market: mktPda, rentSubsidyVault: vaultPda, systemProgram: SystemProgram.programId })
+            .signers([sponsor])
+            .rpc();
+    });
+
+    it("Sponsors the rent of a new order", async () => {
+        const traderBefore = await balance(trader.publicKey);
+        const [orderId, order] = await placeSell();
+        subsidizedId = orderId;
+
+        assert.equal(await balance(trader.publicKey), traderBefore);
+        assert.isTrue((await program.account.order.fetch(order)).subsidized);
+        const vault = await program.account.rentSubsidyVault.fetch(vaultPda);
+        assert.equal(vault.lamports.toNumber(), rent / 2);
+        assert.equal(vault.ordersSubsidized.toNumber(), 1);
+    });
+
+    it("Falls back to owner-paid rent when the vault can't cover it", async () => {
+        const traderBefore = await balance(trader.publicKey);
+        const [orderId, order] = await placeSell();
+
+        assert.equal(traderBefore - (await balance(trader.publicKey)), rent);
+        assert.isFalse((await program.account.order.fetch(order)).subsidized);
+        assert.equal((await program.account.rentSubsidyVault.fetch(vaultPda)).lamports.toNumber(), rent / 2);
+
+        // Unsubsidized rent still closes back to the owner
+        const before = await balance(trader.publicKey);
+        await cancelAndClose(orderId, null);
+        assert.equal((await balance(trader.publicKey)) - before, rent);
+    });
+
+    it("Returns subsidized rent to the vault on close", async () => {
+        await expectError(cancelAndClose(subsidizedId, null), "RentSubsidyVaultRequired");
+
+        const traderBefore = await balance(trader.publicKey);
+        const vaultBefore = await balance(vaultPda);
+        await cancelAndClose(subsidizedId, vaultPda);
+
+        assert.isNull(await provider.connection.getAccountInfo(orderPda(mktPda, subsidizedId)[0]));
+        assert.equal(await balance(trader.publicKey), traderBefore);
+        assert.equal((await balance(vaultPda)) - vaultBefore, rent);
+        assert.equal((await program.account.rentSubsidyVault.fetch(vaultPda)).lamports.toNumber(), rent + rent / 2);
+    });
+});
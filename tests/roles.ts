@@ -0,0 +1,181 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, feeConfigPda, marketPda, program, provider, rolesPda, traderSeatPda } from "./helpers";
+
+describe("Role-based admin permissions", () => {
+    const MARKET_NAME = "ROLES/MOCK";
+    const authority = provider.wallet;
+    const pauser = Keypair.generate();
+    const feeManager = Keypair.generate();
+    const paramManager = Keypair.generate();
+    const riskManager = Keypair.generate();
+    const treasury = Keypair.generate();
+    const trader = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+    const [rolesAcc] = rolesPda(mktPda);
+    const [seatPda] = traderSeatPda(mktPda, trader.publicKey);
+
+    // Run `build` as `signer` (null = the market authority, without the roles account)
+    async function as(signer: Keypair | null, build: (admin: PublicKey, roles: PublicKey | null) => any) {
+        const admin = signer ? signer.publicKey : authority.publicKey;
+        const call = build(admin, signer ? rolesAcc : null);
+        return signer ? call.signers([signer]).rpc() : call.rpc();
+    }
+
+    const cases: { name: string; holder: Keypair; wrong: Keypair; run: (s: Keypair | null) => Promise<unknown> }[] = [
+        {
+            name: "pause_market / resume_market (Pauser)",
+            holder: pauser,
+            wrong: feeManager,
+            run: async (s) => {
+                await as(s, (a, roles) => program.methods.pauseMarket().accounts({ authority: a, market: mktPda, roles }));
+                await as(s, (a, roles) => program.methods.resumeMarket().accounts({ authority: a, market: mktPda, roles }));
+            },
+        },
+        {
+            name: "update_fee_config (FeeManager)",
+            holder: feeManager,
+            wrong: paramManager,
+            run: (s) =>
+                as(s, (a, roles) =>
+                    program.methods.updateFeeConfig(50, treasury.publicKey).accounts({ authority: a, market: mktPda, feeConfig: feePda, roles })
+                ),
+        },
+        {
+            name: "set_fee_recipient (FeeManager)",
+            holder: feeManager,
+            wrong: riskManager,
+            run: (s) =>
+                as(s, (a, roles) => program.methods.setFeeRecipient(treasury.publicKey).accounts({ authority: a, market: mktPda, roles })),
+        },
+        {
+            name: "update_market_params (ParamManager)",
+            holder: paramManager,
+            wrong: pauser,
+            run: (s) =>
+                as(s, (a, roles) =>
+                    program.methods
+                        .updateMarketParams({ feeBps: 25, treasury: treasury.publicKey, paramsTimelockSecs: new anchor.BN(0), priceBandBps: new anchor.BN(0) })
+                        .accounts({ authority: a, market: mktPda, feeConfig: feePda, roles })
+                ),
+        },
+        {
+            name: "set_maker_share_limit (ParamManager)",
+            holder: paramManager,
+            wrong: feeManager,
+            run: (s) =>
+                as(s, (a, roles) =>
+                    program.methods.setMakerShareLimit(0, new anchor.BN(0)).accounts({ authority: a, market: mktPda, roles })
+                ),
+        },
+        {
+            name: "set_makers_restricted (RiskManager)",
+            holder: riskManager,
+            wrong: paramManager,
+            run: (s) =>
+                as(s, (a, roles) => program.methods.setMakersRestricted(false).accounts({ authority: a, market: mktPda, roles })),
+        },
+        {
+            name: "add_trader / remove_trader (RiskManager)",
+            holder: riskManager,
+            wrong: pauser,
+            run: async (s) => {
+                await as(s, (a, roles) =>
+                    program.methods
+                        .addTrader(trader.publicKey)
+                        .accounts({ authority: a, market: mktPda, traderSeat: seatPda, roles, systemProgram: SystemProgram.programId })
+                );
+                await as(s, (a, roles) =>
+                    program.methods.removeTrader(trader.publicKey).accounts({ authority: a, market: mktPda, traderSeat: seatPda, roles })
+                );
+            },
+        },
+    ];
+
+    before(async () => {
+        for (const kp of [pauser, feeManager, paramManager, riskManager, treasury]) await airdrop(kp.publicKey, 2);
+
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeFeeConfig(100, treasury.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeRoles()
+            .accounts({ authority: authority.publicKey, market: mktPda, roles: rolesAcc, systemProgram: SystemProgram.programId })
+            .rpc();
+
+        const grants: [any, Keypair][] = [
+            [{ pauser: {} }, pauser],
+            [{ feeManager: {} }, feeManager],
+            [{ paramManager: {} }, paramManager],
+            [{ riskManager: {} }, riskManager],
+        ];
+        for (const [role, kp] of grants) {
+            await program.methods
+                .grantRole(role, kp.publicKey)
+                .accounts({ authority: authority.publicKey, market: mktPda, roles: rolesAcc })
+                .rpc();
+        }
+    });
+
+    for (const c of cases) {
+        describe(c.name, () => {
+            it("rejects the holder of a different role", async () => {
+                await expectError(c.run(c.wrong), "Unauthorized");
+            });
+            it("accepts the role holder", async () => {
+                await c.run(c.holder);
+            });
+            it("accepts the authority without any role", async () => {
+                await c.run(null);
+            });
+        });
+    }
+
+    it("Only the authority manages roles", async () => {
+        await expectError(
+            program.methods
+                .grantRole({ pauser: {} }, paramManager.publicKey)
+                .accounts({ authority: pauser.publicKey, market: mktPda, roles: rolesAcc })
+                .signers([pauser])
+                .rpc(),
+            "Unauthorized"
+        );
+    });
+
+    it("A revoked role can no longer sign", async () => {
+        let seen: any = null;
+        const listener = program.addEventListener("roleRevokedEvent", (e) => (seen = e));
+        await program.methods
+            .revokeRole({ pauser: {} })
+            .accounts({ authority: authority.publicKey, market: mktPda, roles: rolesAcc })
+            .rpc();
+        await new Promise((r) => setTimeout(r, 1000));
+        await program.removeEventListener(listener);
+        assert.isNotNull(seen);
+        assert.ok(seen.previousHolder.equals(pauser.publicKey));
+
+        const roles = await program.account.marketRoles.fetch(rolesAcc);
+        assert.ok(roles.pauser.equals(PublicKey.default));
+        await expectError(cases[0].run(pauser), "Unauthorized");
+    });
+
+    it("Rejects an unknown role", async () => {
+        try {
+            await program.methods
+                .grantRole({ superUser: {} } as any, pauser.publicKey)
+                .accounts({ authority: authority.publicKey, market: mktPda, roles: rolesAcc })
+                .rpc();
+            assert.fail("Expected an unknown role to be rejected");
+        } catch (err: any) {
+            assert.notInclude(err.message, "Expected an unknown role");
+        }
+    });
+});
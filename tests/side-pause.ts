@@ -0,0 +1,96 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, matchPair, place, program, provider, sleep } from "./helpers";
+
+describe("One-sided pause", () => {
+    const MARKET_NAME = "SIDEPAUSE/MOCK";
+    const PRICE = 10_000;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+
+    const cancel = (owner: Keypair, orderId: number, order: PublicKey) =>
+        program.methods
+            .cancelOrder(new anchor.BN(orderId), new anchor.BN(0))
+            .accounts({ owner: owner.publicKey, market: mktPda, order, tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([owner])
+            .rpc();
+
+    const setSide = (side: any, paused: boolean, signer: any = authority) => {
+        const method = paused ? program.methods.pauseSide(side) : program.methods.resumeSide(side);
+        const call = method.accounts({ authority: signer.publicKey, market: mktPda, roles: null });
+        return signer === authority ? call.rpc() : call.signers([signer]).rpc();
+    };
+
+    before(async () => {
+        for (const kp of [buyer, seller, stranger]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Is authority-gated, evented, and rejects a double pause", async () => {
+        await expectError(setSide({ buy: {} }, true, stranger), "Unauthorized");
+
+        let event: any = null;
+        const listener = program.addEventListener("marketSidePausedEvent", (e) => (event = e));
+        await setSide({ sell: {} }, true);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+        assert.deepEqual(event.side, { sell: {} });
+        assert.isTrue(event.isPaused);
+
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.isTrue(mkt.sellsPaused);
+        assert.isFalse(mkt.buysPaused);
+        assert.isFalse(mkt.isPaused);
+
+        await expectError(setSide({ sell: {} }, true), "SidePaused");
+        await setSide({ sell: {} }, false);
+        assert.isFalse((await program.account.market.fetch(mktPda)).sellsPaused);
+    });
+
+    for (const [buysPaused, sellsPaused] of [
+        [false, false],
+        [true, false],
+        [false, true],
+        [true, true],
+    ]) {
+        it(`buys ${buysPaused ? "paused" : "open"} / sells ${sellsPaused ? "paused" : "open"}: blocks placement only`, async () => {
+            // Resting orders on both sides, placed before the pause
+            const [bid, bidId] = await place(mktPda, buyer, { buy: {} }, PRICE, 2);
+            const [ask] = await place(mktPda, seller, { sell: {} }, PRICE, 1);
+            const [restingAsk, restingAskId] = await place(mktPda, seller, { sell: {} }, PRICE * 3, 1);
+
+            await setSide({ buy: {} }, buysPaused);
+            await setSide({ sell: {} }, sellsPaused);
+
+            // Placement: blocked exactly on the paused sides
+            const newBuy = place(mktPda, buyer, { buy: {} }, PRICE / 2, 1);
+            const newSell = place(mktPda, seller, { sell: {} }, PRICE * 4, 1);
+            if (buysPaused) await expectError(newBuy, "SidePaused");
+            else await newBuy;
+            if (sellsPaused) await expectError(newSell, "SidePaused");
+            else await newSell;
+
+            // Matching: resting orders on either side still fill
+            await matchPair(bid, ask);
+            const filled = await program.account.order.fetch(bid);
+            assert.equal(filled.filledQuantity.toNumber(), 1);
+
+            // Cancelling: always allowed
+            await cancel(buyer, bidId, bid);
+            await cancel(seller, restingAskId, restingAsk);
+            assert.deepEqual((await program.account.order.fetch(bid)).status, { cancelled: {} });
+            assert.deepEqual((await program.account.order.fetch(restingAsk)).status, { cancelled: {} });
+
+            await setSide({ buy: {} }, false);
+            await setSide({ sell: {} }, false);
+        });
+    }
+});
@@ -0,0 +1,119 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import {
+    airdrop,
+    feeConfigPda,
+    marketPda,
+    place,
+    program,
+    provider,
+} from "./helpers";
+
+function errorCode(name: string): number {
+    const err = program.idl.errors.find((e) => e.name.toLowerCase() === name.toLowerCase());
+    if (!err) throw new Error(`unknown error ${name}`);
+    return err.code;
+}
+
+describe("simulate_match", () => {
+    const MARKET_NAME = "SIM/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const treasury = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+
+    function simulate(bid: PublicKey, ask: PublicKey, maxSlippageBps = 0) {
+        return program.methods
+            .simulateMatch(maxSlippageBps)
+            .accounts({ market: mktPda, bidOrder: bid, askOrder: ask, feeConfig: feePda })
+            .view();
+    }
+
+    before(async () => {
+        await airdrop(buyer.publicKey, 5);
+        await airdrop(seller.publicKey, 5);
+        await airdrop(treasury.publicKey, 1);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeFeeConfig(100, treasury.publicKey)
+            .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Simulated settlement equals the real match", async () => {
+        const [bid] = await place(mktPda, buyer, { buy: {} }, 120_000, 7, { orderId: 0 });
+        const [ask] = await place(mktPda, seller, { sell: {} }, 100_000, 4, { orderId: 1 });
+
+        const sim = await simulate(bid, ask);
+        assert.isTrue(sim.wouldMatch);
+        assert.equal(sim.errorCode, 0);
+
+        // Simulation must not mutate anything
+        const bidBefore = await program.account.order.fetch(bid);
+        assert.equal(bidBefore.filledQuantity.toNumber(), 0);
+
+        const sellerBefore = await provider.connection.getBalance(seller.publicKey);
+        const buyerBefore = await provider.connection.getBalance(buyer.publicKey);
+        const treasuryBefore = await provider.connection.getBalance(treasury.publicKey);
+
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: feePda,
+                treasury: treasury.publicKey,
+            })
+            .rpc();
+
+        const s = sim.settlement;
+        const bidAfter = await program.account.order.fetch(bid);
+        const askAfter = await program.account.order.fetch(ask);
+        assert.equal(bidAfter.filledQuantity.toNumber(), s.bidFilledAfter.toNumber());
+        assert.equal(askAfter.filledQuantity.toNumber(), s.askFilledAfter.toNumber());
+        assert.deepEqual(bidAfter.status, s.bidStatusAfter);
+        assert.deepEqual(askAfter.status, s.askStatusAfter);
+
+        assert.equal(s.fillQuantity.toNumber(), 4);
+        assert.equal(s.fillPrice.toNumber(), 100_000);
+        assert.equal(s.feeAmount.toNumber(), 4_000);
+        assert.equal(s.buyerRefund.toNumber(), 80_000);
+
+        assert.equal((await provider.connection.getBalance(seller.publicKey)) - sellerBefore, s.netSellerPayment.toNumber());
+        assert.equal((await provider.connection.getBalance(buyer.publicKey)) - buyerBefore, s.buyerRefund.toNumber());
+        assert.equal(s.dustAmount.toNumber(), 0);
+        assert.equal(
+            (await provider.connection.getBalance(treasury.publicKey)) - treasuryBefore,
+            s.feeAmount.toNumber() + s.dustAmount.toNumber()
+        );
+    });
+
+    it("Reports the rejection reason for a non-crossing pair", async () => {
+        const [bid] = await place(mktPda, buyer, { buy: {} }, 90_000, 1, { orderId: 2 });
+        const [ask] = await place(mktPda, seller, { sell: {} }, 95_000, 1, { orderId: 3 });
+
+        const sim = await simulate(bid, ask);
+        assert.isFalse(sim.wouldMatch);
+        assert.equal(sim.errorCode, errorCode("PriceMismatch"));
+    });
+
+    it("Reports slippage rejections using the same guard as match_orders", async () => {
+        const [bid] = await place(mktPda, buyer, { buy: {} }, 100_000, 1, { orderId: 4 });
+        const [ask] = await place(mktPda, seller, { sell: {} }, 90_000, 1, { orderId: 5 });
+
+        const sim = await simulate(bid, ask, 50);
+        assert.isFalse(sim.wouldMatch);
+        assert.equal(sim.errorCode, errorCode("SlippageExceeded"));
+    });
+});
@@ -0,0 +1,170 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, escrowVaultPda, expectError, marketPda, matchPair, orderPda, place, program, provider } from "./helpers";
+
+describe("split_order", () => {
+    const MARKET_NAME = "SPLIT/MOCK";
+    const PRICE = 10_000;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [bidPda] = orderPda(mktPda, 0);
+    const [bidVault] = escrowVaultPda(mktPda, 0);
+
+    async function split(owner: Keypair, orderId: number, qty: number, expiresAt = 0) {
+        const { nextOrderId } = await program.account.market.fetch(mktPda);
+        await program.methods
+            .splitOrder(new anchor.BN(orderId), nextOrderId, new anchor.BN(qty), new anchor.BN(expiresAt))
+            .accounts({
+                owner: owner.publicKey,
+                market: mktPda,
+                order: orderPda(mktPda, orderId)[0],
+                newOrder: orderPda(mktPda, nextOrderId.toNumber())[0],
+                userStats: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([owner])
+            .rpc();
+        return orderPda(mktPda, nextOrderId.toNumber())[0];
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller, stranger]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        // BUY 10, then 3 filled: 7 unfilled with 70_000 escrowed
+        await place(mktPda, buyer, { buy: {} }, PRICE, 10);
+        const [ask] = await place(mktPda, seller, { sell: {} }, PRICE, 3);
+        await matchPair(bidPda, ask);
+    });
+
+    it("Can't exceed the unfilled remainder", async () => {
+        await expectError(split(buyer, 0, 7), "InvalidSplitQuantity");
+        await expectError(split(buyer, 0, 8), "InvalidSplitQuantity");
+        await expectError(split(buyer, 0, 0), "InvalidSplitQuantity");
+    });
+
+    it("Only the owner can split", async () => {
+        await expectError(split(stranger, 0, 2), "Unauthorized");
+    });
+
+    it("Moves proportional escrow and conserves it", async () => {
+        const mktBefore = await program.account.market.fetch(mktPda);
+        const original = await program.account.order.fetch(bidPda);
+        const pdaBefore = await provider.connection.getBalance(bidPda);
+        const vaultBefore = await provider.connection.getBalance(bidVault);
+
+        const slicePda = await split(buyer, 0, 4);
+
+        const after = await program.account.order.fetch(bidPda);
+        const slice = await program.account.order.fetch(slicePda);
+        assert.equal(after.quantity.toNumber(), 6);
+        assert.equal(after.filledQuantity.toNumber(), 3);
+        assert.equal(after.escrowLamports.toNumber(), 3 * PRICE);
+        assert.equal(slice.quantity.toNumber(), 4);
+        assert.equal(slice.filledQuantity.toNumber(), 0);
+        assert.equal(slice.escrowLamports.toNumber(), 4 * PRICE);
+        assert.equal(
+            after.escrowLamports.toNumber() + slice.escrowLamports.toNumber(),
+            original.escrowLamports.toNumber()
+        );
+        // The escrow moves vault to vault; both orders hold only their rent
+        assert.equal(await provider.connection.getBalance(bidPda), pdaBefore);
+        assert.equal(vaultBefore - (await provider.connection.getBalance(bidVault)), 4 * PRICE);
+        const rent = await provider.connection.getMinimumBalanceForRentExemption(program.account.order.size);
+        const vaultRent = await provider.connection.getMinimumBalanceForRentExemption(0);
+        assert.equal(await provider.connection.getBalance(slicePda), rent);
+        const [sliceVault] = escrowVaultPda(mktPda, slice.orderId.toNumber());
+        assert.equal(await provider.connection.getBalance(sliceVault), vaultRent + 4 * PRICE);
+
+        // Same terms, inherited time priority
+        assert.deepEqual(slice.side, original.side);
+        assert.equal(slice.price.toNumber(), original.price.toNumber());
+        assert.equal(slice.timestamp.toNumber(), original.timestamp.toNumber());
+        assert.equal(slice.feeBps, original.feeBps);
+
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.totalBidVolume.toNumber(), mktBefore.totalBidVolume.toNumber());
+        assert.equal(mkt.openOrderCount.toNumber(), mktBefore.openOrderCount.toNumber() + 1);
+    });
+
+    it("Both halves trade and cancel independently", async () => {
+        const [slicePda] = orderPda(mktPda, 2);
+        const [ask] = await place(mktPda, seller, { sell: {} }, PRICE, 4);
+        await matchPair(slicePda, ask);
+        assert.deepEqual((await program.account.order.fetch(slicePda)).status, { filled: {} });
+
+        const walletBefore = await provider.connection.getBalance(buyer.publicKey);
+        await program.methods
+            .cancelOrder(new anchor.BN(0), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: bidPda, tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        assert.equal((await provider.connection.getBalance(buyer.publicKey)) - walletBefore, 3 * PRICE);
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.totalBidVolume.toNumber(), 0);
+    });
+
+    it("Splits a SELL without moving lamports", async () => {
+        const [ask] = await place(mktPda, seller, { sell: {} }, PRICE, 5);
+        const { orderId } = await program.account.order.fetch(ask);
+        const slicePda = await split(seller, orderId.toNumber(), 2);
+        const slice = await program.account.order.fetch(slicePda);
+        assert.equal(slice.escrowLamports.toNumber(), 0);
+        assert.equal((await program.account.order.fetch(ask)).quantity.toNumber(), 3);
+    });
+
+    it("Checks the unfilled part that stays, not the total less the slice", async () => {
+        // Minimum order of 3: BUY 10 with 5 filled leaves 5 unfilled, so a
+        // slice of 3 would strand 2 (though 10 - 3 = 7 passes)
+        const [minMkt] = marketPda(authority.publicKey, "SPLIT-MIN/MOCK");
+        await program.methods
+            .initializeMarket("SPLIT-MIN/MOCK", new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(3), 0, false)
+            .accounts({ authority: authority.publicKey, market: minMkt, systemProgram: SystemProgram.programId })
+            .rpc();
+        for (const [owner, side, id, qty] of [[buyer, { buy: {} }, 0, 10], [seller, { sell: {} }, 1, 5]] as const) {
+            await program.methods
+                .placeOrder(side, new anchor.BN(PRICE), new anchor.BN(qty), new anchor.BN(id), new anchor.BN(0))
+                .accounts({ owner: owner.publicKey, market: minMkt, order: orderPda(minMkt, id)[0], systemProgram: SystemProgram.programId })
+                .signers([owner])
+                .rpc();
+        }
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: minMkt,
+                bidOrder: orderPda(minMkt, 0)[0],
+                askOrder: orderPda(minMkt, 1)[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: (await program.account.market.fetch(minMkt)).feeRecipient,
+                bidTradingBalance: null,
+            })
+            .rpc();
+        assert.equal((await program.account.order.fetch(orderPda(minMkt, 0)[0])).filledQuantity.toNumber(), 5);
+
+        await expectError(
+            program.methods
+                .splitOrder(new anchor.BN(0), new anchor.BN(2), new anchor.BN(3), new anchor.BN(0))
+                .accounts({
+                    owner: buyer.publicKey,
+                    market: minMkt,
+                    order: orderPda(minMkt, 0)[0],
+                    newOrder: orderPda(minMkt, 2)[0],
+                    userStats: null,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([buyer])
+                .rpc(),
+            "OrderTooSmall"
+        );
+    });
+});
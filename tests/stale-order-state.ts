@@ -0,0 +1,91 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, marketPda, orderPda, place, program, provider } from "./helpers";
+
+describe("Optimistic-concurrency guard on cancel", () => {
+    const MARKET_NAME = "VERSION/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [bid] = orderPda(mktPda, 0);
+    const [ask] = orderPda(mktPda, 1);
+
+    const cancel = (expected: anchor.BN) =>
+        program.methods
+            .cancelOrder(new anchor.BN(0), expected)
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: bid, tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+
+    before(async () => {
+        await airdrop(buyer.publicKey, 5);
+        await airdrop(seller.publicKey, 5);
+
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await place(mktPda, buyer, { buy: {} }, 10_000, 5, { orderId: 0 });
+        await place(mktPda, seller, { sell: {} }, 10_000, 2, { orderId: 1 });
+    });
+
+    it("Starts new orders at update_count 1", async () => {
+        const b = await program.account.order.fetch(bid);
+        assert.equal(b.updateCount.toNumber(), 1);
+    });
+
+    it("Rejects a cancel built before a fill landed", async () => {
+        // Client reads the order...
+        const seen = (await program.account.order.fetch(bid)).updateCount;
+
+        // ...a crank fills part of it...
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+                bidTradingBalance: null,
+            })
+            .rpc();
+
+        // ...and the client's cancel lands against the newer state.
+        try {
+            await cancel(seen);
+            assert.fail("Expected StaleOrderState error");
+        } catch (err: any) {
+            assert.include(err.message, "StaleOrderState");
+        }
+        const b = await program.account.order.fetch(bid);
+        assert.deepEqual(b.status, { partiallyFilled: {} });
+        assert.equal(b.updateCount.toNumber(), seen.toNumber() + 1);
+    });
+
+    it("Accepts the cancel after a refetch", async () => {
+        const fresh = (await program.account.order.fetch(bid)).updateCount;
+        await cancel(fresh);
+        const b = await program.account.order.fetch(bid);
+        assert.deepEqual(b.status, { cancelled: {} });
+        assert.equal(b.updateCount.toNumber(), fresh.toNumber() + 1);
+    });
+
+    it("Zero skips the check", async () => {
+        const [other] = orderPda(mktPda, 2);
+        await place(mktPda, buyer, { buy: {} }, 10_000, 1, { orderId: 2 });
+        await program.methods
+            .cancelOrder(new anchor.BN(2), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: other, tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        const o = await program.account.order.fetch(other);
+        assert.deepEqual(o.status, { cancelled: {} });
+    });
+});
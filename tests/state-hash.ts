@@ -0,0 +1,132 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { ChainEvent, CHAINED_EVENTS, verifyStateHashChain } from "../client/stateHash";
+import { airdrop, feeConfigPda, marketPda, orderPda, program, provider } from "./helpers";
+
+describe("Market state hash chain", () => {
+    const MARKET_NAME = "HASH/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const treasury = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [feePda] = feeConfigPda(mktPda);
+    const coder = program.coder as anchor.BorshCoder;
+    const parser = new anchor.EventParser(program.programId, coder);
+
+    const events: ChainEvent[] = [];
+
+    async function record(sig: string) {
+        const tx = await provider.connection.getTransaction(sig, {
+            commitment: "confirmed",
+            maxSupportedTransactionVersion: 0,
+        });
+        for (const event of parser.parseLogs(tx.meta.logMessages)) {
+            if (CHAINED_EVENTS.has(event.name)) events.push(event);
+        }
+    }
+
+    async function placeOrder(owner: Keypair, side: any, price: number, qty: number, orderId: number) {
+        const [order] = orderPda(mktPda, orderId);
+        await record(
+            await program.methods
+                .placeOrder(side, new anchor.BN(price), new anchor.BN(qty), new anchor.BN(orderId), new anchor.BN(0))
+                .accounts({ owner: owner.publicKey, market: mktPda, order, systemProgram: SystemProgram.programId })
+                .signers([owner])
+                .rpc({ commitment: "confirmed" })
+        );
+        return order;
+    }
+
+    before(async () => {
+        await airdrop(buyer.publicKey, 5);
+        await airdrop(seller.publicKey, 5);
+        await airdrop(treasury.publicKey, 1);
+
+        // Replay a lifecycle: init → fee config → orders → trade → cancel → pause/resume
+        await record(
+            await program.methods
+                .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+                .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+                .rpc({ commitment: "confirmed" })
+        );
+        await record(
+            await program.methods
+                .initializeFeeConfig(100, treasury.publicKey)
+                .accounts({ authority: authority.publicKey, market: mktPda, feeConfig: feePda, systemProgram: SystemProgram.programId })
+                .rpc({ commitment: "confirmed" })
+        );
+
+        const bid = await placeOrder(buyer, { buy: {} }, 12_000, 10, 0);
+        const ask = await placeOrder(seller, { sell: {} }, 10_000, 4, 1);
+        await record(
+            await program.methods
+                .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+                .accounts({
+                    matcher: authority.publicKey,
+                    market: mktPda,
+                    bidOrder: bid,
+                    askOrder: ask,
+                    bidOwner: buyer.publicKey,
+                    askOwner: seller.publicKey,
+                    feeConfig: feePda,
+                    treasury: treasury.publicKey,
+                })
+                .rpc({ commitment: "confirmed" })
+        );
+        await record(
+            await program.methods
+                .cancelOrder(new anchor.BN(0), new anchor.BN(0))
+                .accounts({ owner: buyer.publicKey, market: mktPda, order: bid, systemProgram: SystemProgram.programId })
+                .signers([buyer])
+                .rpc({ commitment: "confirmed" })
+        );
+        await record(
+            await program.methods
+                .pauseMarket()
+                .accounts({ authority: authority.publicKey, market: mktPda })
+                .rpc({ commitment: "confirmed" })
+        );
+        await record(
+            await program.methods
+                .resumeMarket()
+                .accounts({ authority: authority.publicKey, market: mktPda })
+                .rpc({ commitment: "confirmed" })
+        );
+    });
+
+    it("Stamps every state change with the next sequence number", async () => {
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(events.length, 7);
+        assert.equal(mkt.eventSeq.toNumber(), 7);
+        events.forEach((e, i) => assert.equal(e.data.eventSeq.toNumber(), i + 1));
+        assert.deepEqual(Buffer.from(events[6].data.stateHash), Buffer.from(mkt.stateHash));
+    });
+
+    it("Replayed event stream reproduces the market's state hash", async () => {
+        const mkt = await program.account.market.fetch(mktPda);
+        const result = verifyStateHashChain(coder, events, mkt);
+        assert.isTrue(result.ok, result.reason);
+        assert.equal(result.stateHash.toString("hex"), Buffer.from(mkt.stateHash).toString("hex"));
+    });
+
+    it("Tampering with one replayed event breaks verification", async () => {
+        const mkt = await program.account.market.fetch(mktPda);
+        const tampered = events.map((e) => ({ name: e.name, data: { ...e.data } }));
+        const trade = tampered.findIndex((e) => e.name === "TradeExecutedEvent");
+        tampered[trade].data.fillQuantity = tampered[trade].data.fillQuantity.addn(1);
+
+        const result = verifyStateHashChain(coder, tampered, mkt);
+        assert.isFalse(result.ok);
+        assert.equal(result.failedAt, trade);
+    });
+
+    it("Dropping an event breaks verification", async () => {
+        const mkt = await program.account.market.fetch(mktPda);
+        const result = verifyStateHashChain(coder, events.slice(0, 3).concat(events.slice(4)), mkt);
+        assert.isFalse(result.ok);
+        assert.equal(result.reason, "sequence gap");
+    });
+});
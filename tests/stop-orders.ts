@@ -0,0 +1,121 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, place, program, provider } from "./helpers";
+
+describe("Stop orders", () => {
+    const MARKET_NAME = "STOP/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const cranker = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const fetchMarket = () => program.account.market.fetch(mktPda);
+    const fetchOrder = (orderId: number) => program.account.order.fetch(orderPda(mktPda, orderId)[0]);
+
+    const placeStop = (owner: Keypair, side: any, price: number, quantity: number, orderId: number, trigger: number, direction: any) =>
+        program.methods
+            .placeStopOrder(side, new anchor.BN(price), new anchor.BN(quantity), new anchor.BN(orderId), new anchor.BN(0), new anchor.BN(trigger), direction)
+            .accounts({ owner: owner.publicKey, market: mktPda, order: orderPda(mktPda, orderId)[0], systemProgram: SystemProgram.programId })
+            .signers([owner])
+            .rpc();
+
+    const trigger = (orderId: number) =>
+        program.methods
+            .triggerOrder(new anchor.BN(orderId))
+            .accounts({ caller: cranker.publicKey, market: mktPda, order: orderPda(mktPda, orderId)[0] })
+            .signers([cranker])
+            .rpc();
+
+    const match = (bidId: number, askId: number) =>
+        program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, bidId)[0],
+                askOrder: orderPda(mktPda, askId)[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+            })
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller, cranker]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Rejects a zero trigger price", async () => {
+        await expectError(placeStop(seller, { sell: {} }, 9_500, 2, 0, 0, { atOrBelow: {} }), "InvalidTriggerPrice");
+    });
+
+    it("Rests a stop-loss sell off the book until it triggers", async () => {
+        // Sell 2 @ 9_500 once the market trades at or below 10_000
+        await placeStop(seller, { sell: {} }, 9_500, 2, 0, 10_000, { atOrBelow: {} });
+        const stop = await fetchOrder(0);
+        assert.deepEqual(stop.status, { pendingTrigger: {} });
+        assert.equal(stop.triggerPrice.toNumber(), 10_000);
+        assert.deepEqual(stop.triggerDirection, { atOrBelow: {} });
+
+        const mkt = await fetchMarket();
+        assert.equal(mkt.totalAskVolume.toNumber(), 0);
+        assert.equal(mkt.openOrderCount.toNumber(), 1);
+
+        await expectError(trigger(0), "TriggerNotReached");
+    });
+
+    it("Refuses to match a stop that is still pending", async () => {
+        await place(mktPda, buyer, { buy: {} }, 12_000, 5, { orderId: 1 });
+        await expectError(match(1, 0), "StopNotTriggered");
+    });
+
+    it("Stays pending while trades print above the trigger", async () => {
+        await place(mktPda, seller, { sell: {} }, 10_001, 1, { orderId: 2 });
+        await match(1, 2);
+        assert.equal((await fetchMarket()).lastTradePrice.toNumber(), 10_001);
+        await expectError(trigger(0), "TriggerNotReached");
+    });
+
+    it("Triggers on a trade exactly at the boundary price, then matches", async () => {
+        await place(mktPda, seller, { sell: {} }, 10_000, 1, { orderId: 3 });
+        await match(1, 3);
+        assert.equal((await fetchMarket()).lastTradePrice.toNumber(), 10_000);
+
+        await trigger(0);
+        assert.deepEqual((await fetchOrder(0)).status, { open: {} });
+        assert.equal((await fetchMarket()).totalAskVolume.toNumber(), 2);
+        await expectError(trigger(0), "OrderNotPendingTrigger");
+
+        await match(1, 0);
+        assert.deepEqual((await fetchOrder(0)).status, { filled: {} });
+        assert.equal((await fetchMarket()).lastTradePrice.toNumber(), 9_500);
+    });
+
+    it("Refunds a pending stop-buy's full escrow on cancel", async () => {
+        const openBefore = (await fetchMarket()).openOrderCount.toNumber();
+        const [stopPda] = orderPda(mktPda, 4);
+        await placeStop(buyer, { buy: {} }, 11_000, 3, 4, 20_000, { atOrAbove: {} });
+        assert.equal((await fetchOrder(4)).escrowLamports.toNumber(), 33_000);
+        const bidVolume = (await fetchMarket()).totalBidVolume.toNumber();
+
+        const before = await provider.connection.getBalance(buyer.publicKey);
+        await program.methods
+            .cancelOrder(new anchor.BN(4), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: stopPda, tradingBalance: null, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        assert.equal((await provider.connection.getBalance(buyer.publicKey)) - before, 33_000);
+
+        const mkt = await fetchMarket();
+        assert.deepEqual((await fetchOrder(4)).status, { cancelled: {} });
+        assert.equal(mkt.openOrderCount.toNumber(), openBefore);
+        // The stop never joined the bid volume, so cancelling leaves it alone
+        assert.equal(mkt.totalBidVolume.toNumber(), bidVolume);
+    });
+});
@@ -0,0 +1,108 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, escrowVaultPda, expectError, marketPda, place, program, provider, sleep } from "./helpers";
+
+describe("Sweeping closed orders", () => {
+    const MARKET_NAME = "SWEEP/MOCK";
+    const DELAY_SECS = 3;
+    const TIP_BPS = 500;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const sweeper = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const balance = (pk: PublicKey) => provider.connection.getBalance(pk);
+
+    const sweep = ([order, orderId]: [PublicKey, number], owner: PublicKey) =>
+        program.methods
+            .sweepOrder(new anchor.BN(orderId))
+            .accounts({ sweeper: sweeper.publicKey, market: mktPda, order, owner, rentSubsidyVault: null })
+            .signers([sweeper])
+            .rpc();
+
+    const setDelay = (secs: number) =>
+        program.methods
+            .setSweepDelay(new anchor.BN(secs))
+            .accounts({ authority: authority.publicKey, market: mktPda, roles: null })
+            .rpc();
+
+    let bid: [PublicKey, number];
+    let ask: [PublicKey, number];
+
+    before(async () => {
+        for (const kp of [buyer, seller, sweeper]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Starts new markets at the default delay", async () => {
+        const market = await program.account.market.fetch(mktPda);
+        assert.equal(market.sweepDelaySecs.toNumber(), 7 * 24 * 60 * 60);
+        await expectError(setDelay(-1), "InvalidSweepDelay");
+        await setDelay(DELAY_SECS);
+    });
+
+    it("Refuses to sweep an active order", async () => {
+        bid = await place(mktPda, buyer, { buy: {} }, 10_000, 2);
+        await expectError(sweep(bid, buyer.publicKey), "OrderNotClosed");
+    });
+
+    it("Refuses to sweep before the delay has passed", async () => {
+        ask = await place(mktPda, seller, { sell: {} }, 10_000, 2);
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid[0],
+                askOrder: ask[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: null,
+                bidTradingBalance: null,
+            })
+            .rpc();
+        const order = await program.account.order.fetch(bid[0]);
+        assert.deepEqual(order.status, { filled: {} });
+        assert.isAbove(order.closedAt.toNumber(), 0);
+        await expectError(sweep(bid, buyer.publicKey), "SweepTooEarly");
+    });
+
+    it("Returns the rent less the tip to the owner, not the caller", async () => {
+        await sleep((DELAY_SECS + 1) * 1000);
+        await expectError(sweep(bid, sweeper.publicKey), "OrderOwnerMismatch");
+
+        const rent = await balance(bid[0]);
+        const tip = Math.floor((rent * TIP_BPS) / 10_000);
+        // The tip comes out of the order's rent; its escrow vault's is returned whole
+        const [bidVault] = escrowVaultPda(mktPda, bid[1]);
+        const vaultRent = await balance(bidVault);
+        const ownerBefore = await balance(buyer.publicKey);
+        const sweeperBefore = await balance(sweeper.publicKey);
+        const events: any[] = [];
+        const listener = program.addEventListener("orderSweptEvent", (e) => events.push(e));
+        await sweep(bid, buyer.publicKey);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        assert.isNull(await provider.connection.getAccountInfo(bid[0]));
+        assert.isNull(await provider.connection.getAccountInfo(bidVault));
+        assert.equal((await balance(buyer.publicKey)) - ownerBefore, rent - tip + vaultRent);
+        assert.lengthOf(events, 1);
+        assert.equal(events[0].tip.toNumber(), tip);
+        assert.equal(events[0].rentReclaimed.toNumber(), rent - tip);
+        assert.equal(events[0].vaultRentReclaimed.toNumber(), vaultRent);
+        // The sweeper also paid the transaction fee
+        assert.isAbove((await balance(sweeper.publicKey)) - sweeperBefore, 0);
+    });
+
+    it("Stops sweeping at a delay of 0", async () => {
+        await setDelay(0);
+        await expectError(sweep(ask, seller.publicKey), "SweepDisabled");
+    });
+});
@@ -0,0 +1,137 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, escrowVaultPda, expectError, marketPda, orderPda, program, provider, sleep } from "./helpers";
+
+describe("Taker market orders (take_order)", () => {
+    const MARKET_NAME = "TAKE/MOCK";
+    const TAKER_ORDER_ID = "18446744073709551615"; // u64::MAX
+    const authority = provider.wallet;
+    const taker = Keypair.generate();
+    const seller1 = Keypair.generate();
+    const seller2 = Keypair.generate();
+    const buyer = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const balance = (pk: PublicKey) => provider.connection.getBalance(pk);
+    // Each resting order's escrow vault, by order address
+    const vaults = new Map<string, PublicKey>();
+
+    async function rest(owner: Keypair, side: any, price: number, qty: number): Promise<PublicKey> {
+        const id = (await program.account.market.fetch(mktPda)).nextOrderId.toNumber();
+        const [order] = orderPda(mktPda, id);
+        vaults.set(order.toBase58(), escrowVaultPda(mktPda, id)[0]);
+        await program.methods
+            .placeOrder(side, new anchor.BN(price), new anchor.BN(qty), new anchor.BN(id), new anchor.BN(0))
+            .accounts({ owner: owner.publicKey, market: mktPda, order, systemProgram: SystemProgram.programId })
+            .signers([owner])
+            .rpc();
+        return order;
+    }
+
+    const take = (side: any, maxQuantity: number, limitPrice: number, makers: [PublicKey, PublicKey][]) =>
+        program.methods
+            .takeOrder(side, new anchor.BN(maxQuantity), new anchor.BN(limitPrice))
+            .accounts({
+                taker: taker.publicKey,
+                market: mktPda,
+                traderSeat: null,
+                feeConfig: null,
+                treasury: null,
+                feeVault: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .remainingAccounts(
+                makers.flatMap(([maker, wallet]) => [
+                    { pubkey: maker, isSigner: false, isWritable: true },
+                    { pubkey: wallet, isSigner: false, isWritable: true },
+                    { pubkey: program.programId, isSigner: false, isWritable: false },
+                    { pubkey: vaults.get(maker.toBase58()) ?? program.programId, isSigner: false, isWritable: true },
+                ])
+            )
+            .signers([taker])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [taker, seller1, seller2, buyer]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Buys across asks at each maker's price, paying sellers from the wallet", async () => {
+        const ask1 = await rest(seller1, { sell: {} }, 10_000, 5);
+        const ask2 = await rest(seller2, { sell: {} }, 11_000, 5);
+        const before = await program.account.market.fetch(mktPda);
+        const seller1Before = await balance(seller1.publicKey);
+        const seller2Before = await balance(seller2.publicKey);
+
+        const events: any[] = [];
+        const listener = program.addEventListener("tradeExecutedEvent", (e) => events.push(e));
+        await take({ buy: {} }, 7, 11_000, [
+            [ask1, seller1.publicKey],
+            [ask2, seller2.publicKey],
+        ]);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        assert.equal((await balance(seller1.publicKey)) - seller1Before, 5 * 10_000);
+        assert.equal((await balance(seller2.publicKey)) - seller2Before, 2 * 11_000);
+        assert.deepEqual((await program.account.order.fetch(ask1)).status, { filled: {} });
+        const partial = await program.account.order.fetch(ask2);
+        assert.deepEqual(partial.status, { partiallyFilled: {} });
+        assert.equal(partial.filledQuantity.toNumber(), 2);
+
+        // No order for the taker; only the makers' volume moves
+        const after = await program.account.market.fetch(mktPda);
+        assert.equal(after.nextOrderId.toNumber(), before.nextOrderId.toNumber());
+        assert.equal(before.totalAskVolume.toNumber() - after.totalAskVolume.toNumber(), 7);
+        assert.equal(after.totalBidVolume.toNumber(), before.totalBidVolume.toNumber());
+        assert.equal(after.tradeSeq.toNumber() - before.tradeSeq.toNumber(), 2);
+        assert.equal(after.lastTradePrice.toNumber(), 11_000);
+
+        assert.lengthOf(events, 2);
+        assert.deepEqual(events.map((e) => e.askOrderId.toNumber()), [
+            (await program.account.order.fetch(ask1)).orderId.toNumber(),
+            partial.orderId.toNumber(),
+        ]);
+        for (const e of events) {
+            assert.equal(e.bidOrderId.toString(), TAKER_ORDER_ID);
+            assert.ok(e.buyer.equals(taker.publicKey));
+            assert.isFalse(e.askIsTaker);
+        }
+        assert.deepEqual(events.map((e) => e.fillPrice.toNumber()), [10_000, 11_000]);
+    });
+
+    it("Stops at the limit price", async () => {
+        const ask = await rest(seller1, { sell: {} }, 12_000, 1);
+        await expectError(take({ buy: {} }, 1, 11_999, [[ask, seller1.publicKey]]), "PriceMismatch");
+    });
+
+    it("Sells into a bid at the bid's price, paid from its escrow", async () => {
+        const bid = await rest(buyer, { buy: {} }, 9_000, 4);
+        const buyerBefore = await balance(buyer.publicKey);
+
+        let event: any = null;
+        const listener = program.addEventListener("tradeExecutedEvent", (e) => (event = e));
+        await take({ sell: {} }, 10, 8_000, [[bid, buyer.publicKey]]);
+        await sleep(1000);
+        await program.removeEventListener(listener);
+
+        const order = await program.account.order.fetch(bid);
+        assert.deepEqual(order.status, { filled: {} });
+        assert.equal(order.escrowLamports.toNumber(), 0);
+        // Filled at the bid's own price: nothing to refund
+        assert.equal(await balance(buyer.publicKey), buyerBefore);
+        assert.equal(event.fillPrice.toNumber(), 9_000);
+        assert.equal(event.fillQuantity.toNumber(), 4);
+        assert.equal(event.askOrderId.toString(), TAKER_ORDER_ID);
+        assert.ok(event.proceedsTo.equals(taker.publicKey));
+        assert.isTrue(event.askIsTaker);
+    });
+
+    it("Fails rather than fill nothing", async () => {
+        await expectError(take({ buy: {} }, 1, 20_000, []), "FillBelowMinimum");
+    });
+});
@@ -0,0 +1,119 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, chainTime, marketPda, orderPda, program, provider, sleep } from "./helpers";
+
+describe("Taker-only window", () => {
+    const MARKET_NAME = "WINDOW/MOCK";
+    const WINDOW_SECS = 3;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+
+    let nextId = 0;
+
+    async function placeOrder(owner: Keypair, side: any, price: number, qty: number) {
+        const orderId = nextId;
+        const [order] = orderPda(mktPda, orderId);
+        await program.methods
+            .placeOrder(side, new anchor.BN(price), new anchor.BN(qty), new anchor.BN(orderId), new anchor.BN(0))
+            .accounts({ owner: owner.publicKey, market: mktPda, order, systemProgram: SystemProgram.programId })
+            .signers([owner])
+            .rpc();
+        nextId += 1;
+        return order;
+    }
+
+    async function waitUntil(ts: number) {
+        while ((await chainTime()) < ts) await sleep(500);
+    }
+
+    before(async () => {
+        await airdrop(buyer.publicKey, 5);
+        await airdrop(seller.publicKey, 5);
+
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(WINDOW_SECS), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Opens the window at initialization", async () => {
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.takerOnlyWindowSecs.toNumber(), WINDOW_SECS);
+        assert.isAbove(mkt.takerOnlyUntilTs.toNumber(), 0);
+    });
+
+    it("Rejects resting orders inside the window", async () => {
+        try {
+            await placeOrder(buyer, { buy: {} }, 10_000, 5);
+            assert.fail("Expected TakerOnlyWindow error");
+        } catch (err: any) {
+            assert.include(err.message, "TakerOnlyWindow");
+        }
+    });
+
+    it("Accepts resting orders from the window end onwards", async () => {
+        const mkt = await program.account.market.fetch(mktPda);
+        await waitUntil(mkt.takerOnlyUntilTs.toNumber());
+
+        await placeOrder(buyer, { buy: {} }, 10_000, 5);
+        await placeOrder(seller, { sell: {} }, 10_000, 5);
+        assert.equal(nextId, 2);
+    });
+
+    it("Resume restarts the window; matching pre-existing orders still works", async () => {
+        await program.methods.pauseMarket().accounts({ authority: authority.publicKey, market: mktPda }).rpc();
+        const before = await chainTime();
+        await program.methods.resumeMarket().accounts({ authority: authority.publicKey, market: mktPda }).rpc();
+
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.isAtLeast(mkt.takerOnlyUntilTs.toNumber(), before + WINDOW_SECS);
+
+        try {
+            await placeOrder(buyer, { buy: {} }, 10_000, 1);
+            assert.fail("Expected TakerOnlyWindow error");
+        } catch (err: any) {
+            assert.include(err.message, "TakerOnlyWindow");
+        }
+
+        const [bid] = orderPda(mktPda, 0);
+        const [ask] = orderPda(mktPda, 1);
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+                bidTradingBalance: null,
+            })
+            .rpc();
+        const a = await program.account.order.fetch(ask);
+        assert.deepEqual(a.status, { filled: {} });
+    });
+
+    it("Markets opened with a zero window accept orders immediately", async () => {
+        const name = "NOWINDOW/MOCK";
+        const [pda] = marketPda(authority.publicKey, name);
+        await program.methods
+            .initializeMarket(name, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: pda, systemProgram: SystemProgram.programId })
+            .rpc();
+
+        const [order] = orderPda(pda, 0);
+        await program.methods
+            .placeOrder({ sell: {} }, new anchor.BN(10_000), new anchor.BN(1), new anchor.BN(0), new anchor.BN(0))
+            .accounts({ owner: seller.publicKey, market: pda, order, systemProgram: SystemProgram.programId })
+            .signers([seller])
+            .rpc();
+        const o = await program.account.order.fetch(order);
+        assert.deepEqual(o.status, { open: {} });
+    });
+});
@@ -0,0 +1,119 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, escrowVaultPda, expectError, marketPda, matchPair, orderPda, program, provider } from "./helpers";
+
+describe("Third-party funded buys", () => {
+    const MARKET_NAME = "FUNDED/MOCK";
+    const PRICE = 10_000;
+    const IMPROVEMENT = 1_000;
+    const authority = provider.wallet;
+    const operator = Keypair.generate();
+    const treasury = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const balance = (key: PublicKey) => provider.connection.getBalance(key);
+
+    let fundedId: number;
+    let fundedBid: PublicKey;
+
+    async function place(owner: Keypair, side: any, price: number, qty: number, funder: Keypair | null = null): Promise<[number, PublicKey]> {
+        const { nextOrderId } = await program.account.market.fetch(mktPda);
+        const [order] = orderPda(mktPda, nextOrderId.toNumber());
+        await program.methods
+            .placeOrder(side, new anchor.BN(price), new anchor.BN(qty), nextOrderId, new anchor.BN(0))
+            .accounts({
+                owner: owner.publicKey,
+                market: mktPda,
+                order,
+                funder: funder ? funder.publicKey : null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers(funder ? [owner, funder] : [owner])
+            .rpc();
+        return [nextOrderId.toNumber(), order];
+    }
+
+    const cancel = (signer: Keypair, order: PublicKey, orderId: number, funder: PublicKey | null) =>
+        program.methods
+            .cancelOrder(new anchor.BN(orderId), new anchor.BN(0))
+            .accounts({ owner: signer.publicKey, market: mktPda, order, tradingBalance: null, funder, systemProgram: SystemProgram.programId })
+            .signers([signer])
+            .rpc();
+
+    before(async () => {
+        for (const kp of [operator, treasury, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Owner-funded buys are unchanged", async () => {
+        const operatorBefore = await balance(operator.publicKey);
+        const [orderId, order] = await place(operator, { buy: {} }, PRICE, 2);
+        // The order's rent and its escrow vault's
+        const rent = (await balance(order)) + (await balance(escrowVaultPda(mktPda, orderId)[0])) - 2 * PRICE;
+        assert.equal(operatorBefore - (await balance(operator.publicKey)), rent + 2 * PRICE);
+        assert.ok((await program.account.order.fetch(order)).funder.equals(operator.publicKey));
+
+        const walletBefore = await balance(operator.publicKey);
+        await cancel(operator, order, orderId, null);
+        assert.equal((await balance(operator.publicKey)) - walletBefore, 2 * PRICE);
+    });
+
+    it("The funder pays the escrow; the owner pays only rent", async () => {
+        const operatorBefore = await balance(operator.publicKey);
+        const treasuryBefore = await balance(treasury.publicKey);
+        const [orderId, order] = await place(operator, { buy: {} }, PRICE + IMPROVEMENT, 5, treasury);
+        [fundedId, fundedBid] = [orderId, order];
+        const escrow = 5 * (PRICE + IMPROVEMENT);
+        const rent = (await balance(order)) + (await balance(escrowVaultPda(mktPda, orderId)[0])) - escrow;
+
+        assert.equal(treasuryBefore - (await balance(treasury.publicKey)), escrow);
+        assert.equal(operatorBefore - (await balance(operator.publicKey)), rent);
+        const stored = await program.account.order.fetch(order);
+        assert.ok(stored.owner.equals(operator.publicKey));
+        assert.ok(stored.funder.equals(treasury.publicKey));
+    });
+
+    it("Price improvement goes back to the funder", async () => {
+        const [, ask] = await place(seller, { sell: {} }, PRICE, 2);
+
+        await expectError(matchPair(fundedBid, ask, { bidFunder: null }), "FunderMismatch");
+        await expectError(matchPair(fundedBid, ask, { bidFunder: operator.publicKey }), "FunderMismatch");
+
+        const treasuryBefore = await balance(treasury.publicKey);
+        const operatorBefore = await balance(operator.publicKey);
+        await matchPair(fundedBid, ask, { bidFunder: treasury.publicKey });
+        assert.equal((await balance(treasury.publicKey)) - treasuryBefore, 2 * IMPROVEMENT);
+        assert.equal(await balance(operator.publicKey), operatorBefore);
+    });
+
+    it("Only the owner cancels, and the refund goes to the funder", async () => {
+        await expectError(cancel(treasury, fundedBid, fundedId, treasury.publicKey), "Unauthorized");
+        await expectError(cancel(operator, fundedBid, fundedId, null), "FunderMismatch");
+
+        const remaining = (await program.account.order.fetch(fundedBid)).escrowLamports.toNumber();
+        assert.equal(remaining, 3 * (PRICE + IMPROVEMENT));
+        const treasuryBefore = await balance(treasury.publicKey);
+        const operatorBefore = await balance(operator.publicKey);
+        await cancel(operator, fundedBid, fundedId, treasury.publicKey);
+        assert.equal((await balance(treasury.publicKey)) - treasuryBefore, remaining);
+        assert.equal(await balance(operator.publicKey), operatorBefore);
+
+        // Close rent still follows the owner
+        const rent = (await balance(fundedBid)) + (await balance(escrowVaultPda(mktPda, fundedId)[0]));
+        await program.methods
+            .closeOrder(new anchor.BN(fundedId))
+            .accounts({ owner: operator.publicKey, market: mktPda, order: fundedBid, systemProgram: SystemProgram.programId })
+            .signers([operator])
+            .rpc();
+        assert.equal((await balance(operator.publicKey)) - operatorBefore, rent);
+    });
+
+    it("Sells can't name a funder", async () => {
+        await expectError(place(operator, { sell: {} }, PRICE, 1, treasury), "FunderOnlyForBuys");
+    });
+});
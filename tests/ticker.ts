@@ -0,0 +1,97 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, expectError, marketPda, orderPda, place, program, provider } from "./helpers";
+
+describe("Ticker stats", () => {
+    const MARKET_NAME = "TICK/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const fetchMarket = () => program.account.market.fetch(mktPda);
+
+    const match = (bidId: number, askId: number) =>
+        program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, bidId)[0],
+                askOrder: orderPda(mktPda, askId)[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+            })
+            .rpc();
+
+    const resetSession = (signer?: Keypair) => {
+        const call = program.methods
+            .resetSessionStats()
+            .accounts({ authority: (signer ?? authority).publicKey, market: mktPda, roles: null });
+        return signer ? call.signers([signer]).rpc() : call.rpc();
+    };
+
+    before(async () => {
+        for (const kp of [buyer, seller, stranger]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Starts with an empty ticker and an open session", async () => {
+        const mkt = await fetchMarket();
+        assert.equal(mkt.lastTradePrice.toNumber(), 0);
+        assert.equal(mkt.cumulativeBaseVolume.toNumber(), 0);
+        assert.equal(mkt.cumulativeQuoteVolume.toString(), "0");
+        assert.equal(mkt.sessionHigh.toNumber(), 0);
+        assert.equal(mkt.sessionLow.toNumber(), 0);
+        assert.isAbove(mkt.sessionStartTs.toNumber(), 0);
+    });
+
+    it("Tracks last price, volumes and the session range over three fills", async () => {
+        // A bid of 10 @ 20_000 takes three asks, each filling at its own price
+        await place(mktPda, buyer, { buy: {} }, 20_000, 10, { orderId: 0 });
+        const fills = [
+            { price: 12_000, quantity: 2 },
+            { price: 15_000, quantity: 3 },
+            { price: 11_000, quantity: 1 },
+        ];
+        for (const [i, { price, quantity }] of fills.entries()) {
+            await place(mktPda, seller, { sell: {} }, price, quantity, { orderId: i + 1 });
+            await match(0, i + 1);
+        }
+
+        const mkt = await fetchMarket();
+        assert.equal(mkt.lastTradePrice.toNumber(), 11_000);
+        assert.isAbove(mkt.lastTradeTs.toNumber(), 0);
+        assert.equal(mkt.tradeSeq.toNumber(), 3);
+        assert.equal(mkt.cumulativeBaseVolume.toNumber(), 6);
+        assert.equal(mkt.cumulativeQuoteVolume.toString(), String(2 * 12_000 + 3 * 15_000 + 11_000));
+        assert.equal(mkt.sessionHigh.toNumber(), 15_000);
+        assert.equal(mkt.sessionLow.toNumber(), 11_000);
+    });
+
+    it("Lets only the authority start a new session, keeping lifetime stats", async () => {
+        await expectError(resetSession(stranger), "Unauthorized");
+
+        await resetSession();
+        let mkt = await fetchMarket();
+        assert.equal(mkt.sessionHigh.toNumber(), 0);
+        assert.equal(mkt.sessionLow.toNumber(), 0);
+        assert.equal(mkt.lastTradePrice.toNumber(), 11_000);
+        assert.equal(mkt.cumulativeBaseVolume.toNumber(), 6);
+
+        // The next fill opens the new range on its own
+        await place(mktPda, seller, { sell: {} }, 13_000, 1, { orderId: 4 });
+        await match(0, 4);
+        mkt = await fetchMarket();
+        assert.equal(mkt.sessionHigh.toNumber(), 13_000);
+        assert.equal(mkt.sessionLow.toNumber(), 13_000);
+        assert.equal(mkt.cumulativeBaseVolume.toNumber(), 7);
+    });
+});
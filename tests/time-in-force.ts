@@ -0,0 +1,138 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, escrowVaultPda, expectError, marketPda, orderPda, program, provider } from "./helpers";
+
+describe("Time in force (place_order_tif)", () => {
+    const MARKET_NAME = "TIF/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+
+    let rent: number;
+    let vaultRent: number;
+    // Each resting order's escrow vault, by order address
+    const vaults = new Map<string, PublicKey>();
+
+    async function nextId(): Promise<number> {
+        return (await program.account.market.fetch(mktPda)).nextOrderId.toNumber();
+    }
+
+    async function rest(owner: Keypair, side: any, price: number, qty: number): Promise<PublicKey> {
+        const id = await nextId();
+        const [order] = orderPda(mktPda, id);
+        vaults.set(order.toBase58(), escrowVaultPda(mktPda, id)[0]);
+        await program.methods
+            .placeOrder(side, new anchor.BN(price), new anchor.BN(qty), new anchor.BN(id), new anchor.BN(0))
+            .accounts({ owner: owner.publicKey, market: mktPda, order, systemProgram: SystemProgram.programId })
+            .signers([owner])
+            .rpc();
+        return order;
+    }
+
+    async function take(
+        owner: Keypair,
+        side: any,
+        price: number,
+        qty: number,
+        timeInForce: any,
+        makers: [PublicKey, PublicKey][]
+    ): Promise<PublicKey> {
+        const id = await nextId();
+        const [order] = orderPda(mktPda, id);
+        await program.methods
+            .placeOrderTif(side, new anchor.BN(price), new anchor.BN(qty), new anchor.BN(id), new anchor.BN(0), timeInForce)
+            .accounts({ owner: owner.publicKey, market: mktPda, order, systemProgram: SystemProgram.programId })
+            .remainingAccounts(
+                makers.flatMap(([maker, wallet]) => [
+                    { pubkey: maker, isSigner: false, isWritable: true },
+                    { pubkey: wallet, isSigner: false, isWritable: true },
+                    { pubkey: program.programId, isSigner: false, isWritable: false },
+                    { pubkey: vaults.get(maker.toBase58()) ?? program.programId, isSigner: false, isWritable: true },
+                ])
+            )
+            .signers([owner])
+            .rpc();
+        return order;
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 5);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        rent = await provider.connection.getMinimumBalanceForRentExemption(program.account.order.size);
+        vaultRent = await provider.connection.getMinimumBalanceForRentExemption(0);
+    });
+
+    it("IOC buy fills across makers and refunds the unfilled escrow at once", async () => {
+        const ask1 = await rest(seller, { sell: {} }, 100, 2);
+        const ask2 = await rest(seller, { sell: {} }, 101, 3);
+        const volumeBefore = await program.account.market.fetch(mktPda);
+        const buyerBefore = await provider.connection.getBalance(buyer.publicKey);
+        const sellerBefore = await provider.connection.getBalance(seller.publicKey);
+
+        const bid = await take(buyer, { buy: {} }, 102, 10, { ioc: {} }, [
+            [ask1, seller.publicKey],
+            [ask2, seller.publicKey],
+        ]);
+
+        const order = await program.account.order.fetch(bid);
+        assert.deepEqual(order.timeInForce, { ioc: {} });
+        assert.deepEqual(order.status, { cancelled: {} });
+        assert.equal(order.filledQuantity.toNumber(), 5);
+        assert.equal(order.escrowLamports.toNumber(), 0);
+        assert.equal(await provider.connection.getBalance(bid), rent);
+        assert.equal(await provider.connection.getBalance(escrowVaultPda(mktPda, order.orderId.toNumber())[0]), vaultRent);
+
+        // Paid each maker's price and nothing for the cancelled rest
+        const notional = 2 * 100 + 3 * 101;
+        assert.equal(buyerBefore - (await provider.connection.getBalance(buyer.publicKey)), rent + vaultRent + notional);
+        assert.equal((await provider.connection.getBalance(seller.publicKey)) - sellerBefore, notional);
+        for (const ask of [ask1, ask2]) {
+            assert.deepEqual((await program.account.order.fetch(ask)).status, { filled: {} });
+        }
+
+        const market = await program.account.market.fetch(mktPda);
+        assert.equal(market.totalAskVolume.toNumber(), volumeBefore.totalAskVolume.toNumber() - 5);
+        assert.equal(market.totalBidVolume.toNumber(), volumeBefore.totalBidVolume.toNumber());
+    });
+
+    it("FOK fails when the makers can't fill it in full, leaving them untouched", async () => {
+        const ask = await rest(seller, { sell: {} }, 100, 3);
+        await expectError(take(buyer, { buy: {} }, 100, 4, { fok: {} }, [[ask, seller.publicKey]]), "FillOrKillNotFilled");
+        const untouched = await program.account.order.fetch(ask);
+        assert.equal(untouched.filledQuantity.toNumber(), 0);
+        assert.deepEqual(untouched.status, { open: {} });
+    });
+
+    it("FOK sell fills in full against a resting bid", async () => {
+        const bid = await rest(buyer, { buy: {} }, 110, 3);
+        const ask = await take(seller, { sell: {} }, 105, 3, { fok: {} }, [[bid, buyer.publicKey]]);
+        assert.deepEqual((await program.account.order.fetch(ask)).status, { filled: {} });
+        const maker = await program.account.order.fetch(bid);
+        assert.deepEqual(maker.status, { filled: {} });
+        assert.equal(maker.escrowLamports.toNumber(), 0);
+    });
+
+    it("GTC rests whatever the makers didn't fill", async () => {
+        const ask = await rest(seller, { sell: {} }, 100, 1);
+        const bid = await take(buyer, { buy: {} }, 100, 4, { gtc: {} }, [[ask, seller.publicKey]]);
+        const order = await program.account.order.fetch(bid);
+        assert.deepEqual(order.status, { partiallyFilled: {} });
+        assert.equal(order.escrowLamports.toNumber(), 3 * 100);
+    });
+
+    it("Rejects a maker that doesn't cross", async () => {
+        const ask = await rest(seller, { sell: {} }, 200, 1);
+        await expectError(take(buyer, { buy: {} }, 150, 1, { ioc: {} }, [[ask, seller.publicKey]]), "PriceMismatch");
+    });
+
+    it("Rejects a wallet that isn't the maker's proceeds recipient", async () => {
+        const ask = await rest(seller, { sell: {} }, 100, 1);
+        await expectError(take(buyer, { buy: {} }, 100, 1, { ioc: {} }, [[ask, buyer.publicKey]]), "BeneficiaryMismatch");
+    });
+});
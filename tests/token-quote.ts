@@ -0,0 +1,225 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+    NATIVE_MINT,
+    TOKEN_PROGRAM_ID,
+    createAccount,
+    createMint,
+    getAccount,
+    mintTo,
+} from "@solana/spl-token";
+import { assert } from "chai";
+import { previewCancel } from "../client/cancelPreview";
+import { airdrop, escrowVaultPda, expectError, marketPda, orderPda, program, provider, quoteVaultPda } from "./helpers";
+
+describe("Token-quoted markets", () => {
+    const MARKET_NAME = "TOKENQ/MOCK";
+    const WSOL_NAME = "TOKENQ-WSOL/MOCK";
+    const authority = provider.wallet;
+    const payer = (authority as anchor.Wallet).payer;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [wsolPda] = marketPda(authority.publicKey, WSOL_NAME);
+    const [vaultPda] = quoteVaultPda(mktPda);
+
+    let mint: PublicKey;
+    let buyerQuote: PublicKey;
+    let sellerQuote: PublicKey;
+    let nextId = 0;
+
+    const tokens = async (account: PublicKey) => Number((await getAccount(provider.connection, account)).amount);
+
+    function placeBuy(price: number, qty: number, quoteAccounts = true) {
+        const id = nextId;
+        const [order] = orderPda(mktPda, id);
+        return program.methods
+            .placeOrder({ buy: {} }, new anchor.BN(price), new anchor.BN(qty), new anchor.BN(id), new anchor.BN(0))
+            .accounts({
+                owner: buyer.publicKey,
+                market: mktPda,
+                order,
+                quoteVault: quoteAccounts ? vaultPda : null,
+                ownerQuoteAccount: quoteAccounts ? buyerQuote : null,
+                tokenProgram: quoteAccounts ? TOKEN_PROGRAM_ID : null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([buyer])
+            .rpc()
+            .then(() => {
+                nextId++;
+                return order;
+            });
+    }
+
+    async function placeSell(price: number, qty: number): Promise<PublicKey> {
+        const id = nextId++;
+        const [order] = orderPda(mktPda, id);
+        await program.methods
+            .placeOrder({ sell: {} }, new anchor.BN(price), new anchor.BN(qty), new anchor.BN(id), new anchor.BN(0))
+            .accounts({ owner: seller.publicKey, market: mktPda, order, systemProgram: SystemProgram.programId })
+            .signers([seller])
+            .rpc();
+        return order;
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 5);
+        mint = await createMint(provider.connection, payer, authority.publicKey, null, 6);
+        buyerQuote = await createAccount(provider.connection, payer, mint, buyer.publicKey);
+        sellerQuote = await createAccount(provider.connection, payer, mint, seller.publicKey);
+        await mintTo(provider.connection, payer, mint, buyerQuote, payer, 1_000_000);
+    });
+
+    it("Creates the quote vault at initialize_market", async () => {
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({
+                authority: authority.publicKey,
+                market: mktPda,
+                quoteMint: mint,
+                quoteVault: vaultPda,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                systemProgram: SystemProgram.programId,
+            })
+            .rpc();
+
+        const market = await program.account.market.fetch(mktPda);
+        assert.isTrue(market.quoteMint.equals(mint));
+        assert.isTrue(market.quoteVault.equals(vaultPda));
+        const vault = await getAccount(provider.connection, vaultPda);
+        assert.isTrue(vault.mint.equals(mint));
+        assert.isTrue(vault.owner.equals(vaultPda)); // its own authority
+        assert.equal(Number(vault.amount), 0);
+    });
+
+    it("Escrows a BUY's notional in quote tokens, not lamports", async () => {
+        await expectError(placeBuy(120, 10, false), "QuoteAccountsRequired");
+
+        const lamportsBefore = await provider.connection.getBalance(buyer.publicKey);
+        const order = await placeBuy(120, 10);
+        const rent = await provider.connection.getMinimumBalanceForRentExemption(program.account.order.size);
+
+        assert.equal(await tokens(buyerQuote), 1_000_000 - 1_200);
+        assert.equal(await tokens(vaultPda), 1_200);
+        assert.equal(lamportsBefore - (await provider.connection.getBalance(buyer.publicKey)), rent);
+        const placed = await program.account.order.fetch(order);
+        assert.isTrue(placed.escrowInVault);
+        assert.equal(placed.escrowLamports.toNumber(), 1_200);
+        assert.equal(await provider.connection.getBalance(order), rent);
+    });
+
+    it("Pays the seller and refunds price improvement from the vault", async () => {
+        const bid = orderPda(mktPda, 0)[0];
+        const ask = await placeSell(100, 4);
+        const mkt = await program.account.market.fetch(mktPda);
+        const sellerLamports = await provider.connection.getBalance(seller.publicKey);
+
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: mkt.feeRecipient,
+                bidTradingBalance: null,
+                quoteVault: vaultPda,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                sellerQuoteAccount: sellerQuote,
+                buyerQuoteAccount: buyerQuote,
+            })
+            .rpc();
+
+        assert.equal(await tokens(sellerQuote), 400);
+        assert.equal(await tokens(buyerQuote), 1_000_000 - 1_200 + 80); // 4 × (120 − 100)
+        assert.equal(await tokens(vaultPda), 6 * 120);
+        assert.equal(await provider.connection.getBalance(seller.publicKey), sellerLamports);
+        const after = await program.account.order.fetch(bid);
+        assert.equal(after.escrowLamports.toNumber(), 6 * 120);
+    });
+
+    it("Refuses to pay a token account of the wrong owner", async () => {
+        const ask = await placeSell(110, 1);
+        const mkt = await program.account.market.fetch(mktPda);
+        await expectError(
+            program.methods
+                .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+                .accounts({
+                    matcher: authority.publicKey,
+                    market: mktPda,
+                    bidOrder: orderPda(mktPda, 0)[0],
+                    askOrder: ask,
+                    bidOwner: buyer.publicKey,
+                    askOwner: seller.publicKey,
+                    feeConfig: null,
+                    treasury: mkt.feeRecipient,
+                    bidTradingBalance: null,
+                    quoteVault: vaultPda,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    sellerQuoteAccount: buyerQuote,
+                    buyerQuoteAccount: buyerQuote,
+                })
+                .rpc(),
+            "QuoteAccountMismatch"
+        );
+    });
+
+    it("Refunds the remaining escrow in quote tokens on cancel", async () => {
+        const [bid] = orderPda(mktPda, 0);
+        const preview = await previewCancel(program as any, mktPda, 0);
+        assert.isTrue(preview.refundInQuoteTokens);
+        assert.equal(preview.escrowRefund, 6 * 120);
+        assert.equal(preview.rentRefund, await provider.connection.getBalance(bid));
+
+        const before = await tokens(buyerQuote);
+        await program.methods
+            .cancelOrder(new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                owner: buyer.publicKey,
+                market: mktPda,
+                order: bid,
+                tradingBalance: null,
+                quoteVault: vaultPda,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                ownerQuoteAccount: buyerQuote,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([buyer])
+            .rpc();
+
+        assert.equal((await tokens(buyerQuote)) - before, 6 * 120);
+        assert.equal(await tokens(vaultPda), 0);
+        assert.deepEqual((await program.account.order.fetch(bid)).status, { cancelled: {} });
+    });
+
+    it("Keeps the lamport path for a market quoted in the native mint", async () => {
+        await program.methods
+            .initializeMarket(WSOL_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({
+                authority: authority.publicKey,
+                market: wsolPda,
+                quoteMint: NATIVE_MINT,
+                systemProgram: SystemProgram.programId,
+            })
+            .rpc();
+        assert.isTrue((await program.account.market.fetch(wsolPda)).quoteMint.equals(PublicKey.default));
+
+        const [order] = orderPda(wsolPda, 0);
+        await program.methods
+            .placeOrder({ buy: {} }, new anchor.BN(5_000), new anchor.BN(3), new anchor.BN(0), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: wsolPda, order, systemProgram: SystemProgram.programId })
+            .signers([buyer])
+            .rpc();
+        const placed = await program.account.order.fetch(order);
+        assert.isFalse(placed.escrowInVault);
+        const rent = await provider.connection.getMinimumBalanceForRentExemption(program.account.order.size);
+        const vaultRent = await provider.connection.getMinimumBalanceForRentExemption(0);
+        assert.equal(await provider.connection.getBalance(order), rent);
+        assert.equal(await provider.connection.getBalance(escrowVaultPda(wsolPda, 0)[0]), vaultRent + 15_000);
+    });
+});
@@ -0,0 +1,88 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, marketPda, place, program, provider, sleep } from "./helpers";
+
+// Multi-maker matching (which emits TradeBatchEvent) arrives with
+// match_orders_multi; this spec covers the mode flag and trade sequencing.
+describe("Trade sequencing and batch event mode", () => {
+    const MARKET_NAME = "TBATCH/MOCK";
+    const PRICE = 10_000;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+
+    async function matchOnce(): Promise<any> {
+        const [bid] = await place(mktPda, buyer, { buy: {} }, PRICE, 1);
+        const [ask] = await place(mktPda, seller, { sell: {} }, PRICE, 1);
+        const mkt = await program.account.market.fetch(mktPda);
+        let trade: any = null;
+        const listener = program.addEventListener("tradeExecutedEvent", (e) => (trade = e));
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: mkt.feeRecipient,
+                bidTradingBalance: null,
+            })
+            .rpc();
+        await sleep(1000);
+        await program.removeEventListener(listener);
+        return trade;
+    }
+
+    before(async () => {
+        for (const kp of [buyer, seller, stranger]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Numbers every fill in trade_seq order", async () => {
+        const first = await matchOnce();
+        const second = await matchOnce();
+        assert.equal(first.tradeSeq.toNumber(), 1);
+        assert.equal(second.tradeSeq.toNumber(), 2);
+
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.equal(mkt.tradeSeq.toNumber(), 2);
+    });
+
+    it("Only the authority or ParamManager sets the mode", async () => {
+        try {
+            await program.methods
+                .setBatchTradeEvents(true)
+                .accounts({ authority: stranger.publicKey, market: mktPda, roles: null })
+                .signers([stranger])
+                .rpc();
+            assert.fail("Expected Unauthorized error");
+        } catch (err: any) {
+            assert.include(err.message, "Unauthorized");
+        }
+
+        const before = await program.account.market.fetch(mktPda);
+        await program.methods
+            .setBatchTradeEvents(true)
+            .accounts({ authority: authority.publicKey, market: mktPda, roles: null })
+            .rpc();
+        const mkt = await program.account.market.fetch(mktPda);
+        assert.isTrue(mkt.batchTradeEvents);
+        assert.equal(mkt.eventSeq.toNumber(), before.eventSeq.toNumber() + 1);
+    });
+
+    it("Single-pair matches still emit TradeExecutedEvent in batch mode", async () => {
+        const trade = await matchOnce();
+        assert.isNotNull(trade);
+        assert.equal(trade.tradeSeq.toNumber(), 3);
+    });
+});
@@ -0,0 +1,119 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, marketPda, orderPda, program, provider, tradeHistoryPda } from "./helpers";
+
+describe("Trade history", () => {
+    const MARKET_NAME = "HISTORY/MOCK";
+    const CAPACITY = 128;
+    const FILLS = 130;
+    const PRICE = 10_000;
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [historyPda] = tradeHistoryPda(mktPda);
+    const [bid] = orderPda(mktPda, 0);
+    const [ask] = orderPda(mktPda, 1);
+    // Fill k (0-based) is for k + 1 units, so every record is distinct
+    const TOTAL_QUANTITY = (FILLS * (FILLS + 1)) / 2;
+
+    const createHistory = (signer: Keypair | null) =>
+        program.methods
+            .createTradeHistory()
+            .accounts({
+                authority: signer?.publicKey ?? authority.publicKey,
+                market: mktPda,
+                tradeHistory: historyPda,
+                roles: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers(signer ? [signer] : [])
+            .rpc();
+
+    const fill = (quantity: number, tradeHistory: PublicKey | null) =>
+        program.methods
+            .matchOrdersV2({
+                maxSlippageBps: 0,
+                minExpectedBidRemaining: new anchor.BN(0),
+                minExpectedAskRemaining: new anchor.BN(0),
+                maxFillQuantity: new anchor.BN(quantity),
+                minFillQuantity: new anchor.BN(0),
+            })
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: null,
+                bidTradingBalance: null,
+                tradeHistory,
+            })
+            .rpc();
+
+    before(async () => {
+        for (const kp of [buyer, seller, stranger]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        // Room for the fill without the history too, and one unit more keeps
+        // both orders open after the last fill
+        for (const [owner, side, id] of [[buyer, { buy: {} }, 0], [seller, { sell: {} }, 1]] as const) {
+            await program.methods
+                .placeOrder(side, new anchor.BN(PRICE), new anchor.BN(TOTAL_QUANTITY + 2), new anchor.BN(id), new anchor.BN(0))
+                .accounts({ owner: owner.publicKey, market: mktPda, order: orderPda(mktPda, id)[0], systemProgram: SystemProgram.programId })
+                .signers([owner])
+                .rpc();
+        }
+    });
+
+    it("Is created by the authority only, empty", async () => {
+        try {
+            await createHistory(stranger);
+            assert.fail("Expected Unauthorized error");
+        } catch (err: any) {
+            assert.include(err.message, "Unauthorized");
+        }
+        await createHistory(null);
+        const history = await program.account.tradeHistory.fetch(historyPda);
+        assert.ok(history.market.equals(mktPda));
+        assert.equal(history.head.toNumber(), 0);
+        assert.equal(history.totalTrades.toNumber(), 0);
+        assert.lengthOf(history.trades, CAPACITY);
+    });
+
+    it("Leaves the history alone when match_orders isn't given it", async () => {
+        await fill(1, null);
+        assert.equal((await program.account.tradeHistory.fetch(historyPda)).totalTrades.toNumber(), 0);
+    });
+
+    it("Wraps after 128 fills, overwriting the oldest", async () => {
+        for (let k = 0; k < FILLS; k++) await fill(k + 1, historyPda);
+
+        const history = await program.account.tradeHistory.fetch(historyPda);
+        assert.equal(history.totalTrades.toNumber(), FILLS);
+        assert.equal(history.head.toNumber(), FILLS % CAPACITY);
+
+        // Fills 0 and 1 were overwritten by fills 128 and 129; oldest first
+        // from head, the buffer holds fills 2..129
+        const quantities = Array.from({ length: CAPACITY }, (_, i) =>
+            history.trades[(history.head.toNumber() + i) % CAPACITY].quantity.toNumber()
+        );
+        assert.deepEqual(quantities, Array.from({ length: CAPACITY }, (_, i) => i + 3));
+        const newest = history.trades[(history.head.toNumber() + CAPACITY - 1) % CAPACITY];
+        assert.equal(newest.bidOrderId.toNumber(), 0);
+        assert.equal(newest.askOrderId.toNumber(), 1);
+        assert.equal(newest.price.toNumber(), PRICE);
+        assert.isAbove(newest.timestamp.toNumber(), 0);
+
+        // A reader that last saw the empty history missed the two overwritten fills
+        const lastSeenTotal = 0;
+        assert.equal(history.totalTrades.toNumber() - lastSeenTotal - CAPACITY, 2);
+    });
+});
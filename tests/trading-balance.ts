@@ -0,0 +1,156 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import {
+    airdrop,
+    escrowVaultPda,
+    marketPda,
+    orderPda,
+    program,
+    provider,
+    tradingBalancePda,
+} from "./helpers";
+
+describe("Trading balance", () => {
+    const MARKET_NAME = "BALANCE/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+    const [balancePda] = tradingBalancePda(mktPda, buyer.publicKey);
+
+    const PRICE = 50_000;
+    const QTY = 4;
+    const ESCROW = PRICE * QTY;
+
+    before(async () => {
+        await airdrop(buyer.publicKey, 5);
+        await airdrop(seller.publicKey, 5);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+        await program.methods
+            .initializeTradingBalance()
+            .accounts({ owner: buyer.publicKey, market: mktPda, tradingBalance: balancePda, systemProgram: SystemProgram.programId })
+            .signers([buyer]).rpc();
+    });
+
+    it("Deposits lamports into the trading balance", async () => {
+        const pdaBefore = await provider.connection.getBalance(balancePda);
+        await program.methods
+            .depositBalance(new anchor.BN(1_000_000))
+            .accounts({ owner: buyer.publicKey, market: mktPda, tradingBalance: balancePda, systemProgram: SystemProgram.programId })
+            .signers([buyer]).rpc();
+
+        const bal = await program.account.tradingBalance.fetch(balancePda);
+        assert.equal(bal.lamports.toNumber(), 1_000_000);
+        const pdaAfter = await provider.connection.getBalance(balancePda);
+        assert.equal(pdaAfter - pdaBefore, 1_000_000);
+    });
+
+    it("Places a BUY quoted from the balance without touching the wallet escrow", async () => {
+        const [bid] = orderPda(mktPda, 0);
+        const walletBefore = await provider.connection.getBalance(buyer.publicKey);
+        const rent = await provider.connection.getMinimumBalanceForRentExemption(program.account.order.size);
+
+        await program.methods
+            .placeOrder({ buy: {} }, new anchor.BN(PRICE), new anchor.BN(QTY), new anchor.BN(0), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: bid, tradingBalance: balancePda, systemProgram: SystemProgram.programId })
+            .signers([buyer]).rpc();
+
+        const order = await program.account.order.fetch(bid);
+        assert.isTrue(order.fundedFromBalance);
+        assert.equal(order.escrowLamports.toNumber(), ESCROW);
+
+        const bal = await program.account.tradingBalance.fetch(balancePda);
+        assert.equal(bal.lamports.toNumber(), 1_000_000 - ESCROW);
+
+        assert.equal(await provider.connection.getBalance(bid), rent);
+        const vaultRent = await provider.connection.getMinimumBalanceForRentExemption(0);
+        assert.equal(await provider.connection.getBalance(escrowVaultPda(mktPda, 0)[0]), vaultRent + ESCROW);
+        const walletAfter = await provider.connection.getBalance(buyer.publicKey);
+        assert.isBelow(walletBefore - walletAfter, ESCROW, "wallet should only pay rent and fees");
+    });
+
+    it("Credits the price-improvement refund back to the balance", async () => {
+        const [bid] = orderPda(mktPda, 0);
+        const [ask] = orderPda(mktPda, 1);
+        await program.methods
+            .placeOrder({ sell: {} }, new anchor.BN(PRICE - 10_000), new anchor.BN(1), new anchor.BN(1), new anchor.BN(0))
+            .accounts({ owner: seller.publicKey, market: mktPda, order: ask, systemProgram: SystemProgram.programId })
+            .signers([seller]).rpc();
+
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: bid,
+                askOrder: ask,
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                treasury: authority.publicKey,
+                bidTradingBalance: balancePda,
+            })
+            .rpc();
+
+        const bal = await program.account.tradingBalance.fetch(balancePda);
+        assert.equal(bal.lamports.toNumber(), 1_000_000 - ESCROW + 10_000);
+        const order = await program.account.order.fetch(bid);
+        assert.equal(order.escrowLamports.toNumber(), PRICE * (QTY - 1));
+    });
+
+    it("Refunds a cancelled balance-funded BUY into the balance", async () => {
+        const [bid] = orderPda(mktPda, 0);
+        await program.methods
+            .cancelOrder(new anchor.BN(0), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: bid, tradingBalance: balancePda, systemProgram: SystemProgram.programId })
+            .signers([buyer]).rpc();
+
+        const bal = await program.account.tradingBalance.fetch(balancePda);
+        assert.equal(bal.lamports.toNumber(), 1_000_000 - PRICE + 10_000);
+        const order = await program.account.order.fetch(bid);
+        assert.equal(order.escrowLamports.toNumber(), 0);
+    });
+
+    it("Falls back to the wallet when the balance is too low", async () => {
+        const [bid] = orderPda(mktPda, 2);
+        await program.methods
+            .placeOrder({ buy: {} }, new anchor.BN(1_000_000), new anchor.BN(2), new anchor.BN(2), new anchor.BN(0))
+            .accounts({ owner: buyer.publicKey, market: mktPda, order: bid, tradingBalance: balancePda, systemProgram: SystemProgram.programId })
+            .signers([buyer]).rpc();
+
+        const order = await program.account.order.fetch(bid);
+        assert.isFalse(order.fundedFromBalance);
+        const bal = await program.account.tradingBalance.fetch(balancePda);
+        assert.equal(bal.lamports.toNumber(), 1_000_000 - PRICE + 10_000);
+    });
+
+    it("Withdraws the balance back to the wallet", async () => {
+        const bal = await program.account.tradingBalance.fetch(balancePda);
+        const amount = bal.lamports.toNumber();
+        const walletBefore = await provider.connection.getBalance(buyer.publicKey);
+
+        await program.methods
+            .withdrawBalance(new anchor.BN(amount))
+            .accounts({ owner: buyer.publicKey, market: mktPda, tradingBalance: balancePda })
+            .signers([buyer]).rpc();
+
+        const after = await program.account.tradingBalance.fetch(balancePda);
+        assert.equal(after.lamports.toNumber(), 0);
+        const walletAfter = await provider.connection.getBalance(buyer.publicKey);
+        assert.isAbove(walletAfter, walletBefore + amount - 10_000);
+
+        try {
+            await program.methods
+                .withdrawBalance(new anchor.BN(1))
+                .accounts({ owner: buyer.publicKey, market: mktPda, tradingBalance: balancePda })
+                .signers([buyer]).rpc();
+            assert.fail("Expected InsufficientBalance error");
+        } catch (err: any) {
+            assert.include(err.message, "InsufficientBalance");
+        }
+    });
+});
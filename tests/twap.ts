@@ -0,0 +1,121 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { airdrop, marketPda, orderPda, program, provider, sleep } from "./helpers";
+
+describe("TWAP accumulator", () => {
+    const MARKET_NAME = "TWAP/MOCK";
+    const authority = provider.wallet;
+    const buyer = Keypair.generate();
+    const seller = Keypair.generate();
+    const [mktPda] = marketPda(authority.publicKey, MARKET_NAME);
+
+    const snapshot = async () => {
+        const market = await program.account.market.fetch(mktPda);
+        return {
+            priceCumulative: BigInt(market.priceCumulative.toString()),
+            timestamp: market.lastTwapUpdateTs.toNumber(),
+            lastTradePrice: market.lastTradePrice.toNumber(),
+        };
+    };
+
+    async function trade(price: number) {
+        const ids: number[] = [];
+        for (const [owner, side] of [[buyer, { buy: {} }], [seller, { sell: {} }]] as const) {
+            const id = (await program.account.market.fetch(mktPda)).nextOrderId.toNumber();
+            await program.methods
+                .placeOrder(side, new anchor.BN(price), new anchor.BN(1), new anchor.BN(id), new anchor.BN(0))
+                .accounts({ owner: owner.publicKey, market: mktPda, order: orderPda(mktPda, id)[0], systemProgram: SystemProgram.programId })
+                .signers([owner])
+                .rpc();
+            ids.push(id);
+        }
+        await program.methods
+            .matchOrders(0, new anchor.BN(0), new anchor.BN(0))
+            .accounts({
+                matcher: authority.publicKey,
+                market: mktPda,
+                bidOrder: orderPda(mktPda, ids[0])[0],
+                askOrder: orderPda(mktPda, ids[1])[0],
+                bidOwner: buyer.publicKey,
+                askOwner: seller.publicKey,
+                feeConfig: null,
+                treasury: authority.publicKey,
+                bidTradingBalance: null,
+            })
+            .rpc();
+    }
+
+    const getTwap = (priceCumulative: bigint, timestamp: number) =>
+        program.methods
+            .getTwap({ priceCumulative: new anchor.BN(priceCumulative.toString()), timestamp: new anchor.BN(timestamp) })
+            .accounts({ market: mktPda })
+            .view();
+
+    before(async () => {
+        for (const kp of [buyer, seller]) await airdrop(kp.publicKey, 2);
+        await program.methods
+            .initializeMarket(MARKET_NAME, new anchor.BN(0), new anchor.BN(1), new anchor.BN(1), new anchor.BN(1), 0, false)
+            .accounts({ authority: authority.publicKey, market: mktPda, systemProgram: SystemProgram.programId })
+            .rpc();
+    });
+
+    it("Starts counting at the first fill", async () => {
+        const before = await snapshot();
+        assert.equal(before.priceCumulative, 0n);
+        assert.equal(before.timestamp, 0);
+
+        await trade(10_000);
+        const after = await snapshot();
+        assert.equal(after.priceCumulative, 0n);
+        assert.isAbove(after.timestamp, 0);
+        assert.equal(after.lastTradePrice, 10_000);
+    });
+
+    it("Weighs each fill's price by how long it stood", async () => {
+        const first = await snapshot();
+        await sleep(2000);
+        await trade(12_000);
+        const second = await snapshot();
+        await sleep(2000);
+        await trade(11_000);
+        const third = await snapshot();
+
+        // The accumulator advances with the outgoing price, not the new one
+        assert.isAbove(second.timestamp, first.timestamp);
+        assert.equal(
+            second.priceCumulative - first.priceCumulative,
+            10_000n * BigInt(second.timestamp - first.timestamp)
+        );
+        assert.equal(
+            third.priceCumulative - second.priceCumulative,
+            12_000n * BigInt(third.timestamp - second.timestamp)
+        );
+    });
+
+    it("get_twap averages from a kept snapshot to now, counting the last price up to it", async () => {
+        const start = await snapshot();
+        await sleep(2000);
+        const quote: any = await getTwap(start.priceCumulative, start.timestamp);
+
+        // No fill since start: the average is the standing price
+        assert.equal(quote.twap.toNumber(), start.lastTradePrice);
+        assert.isAbove(quote.windowSecs.toNumber(), 0);
+        assert.equal(
+            BigInt(quote.current.priceCumulative.toString()) - start.priceCumulative,
+            BigInt(start.lastTradePrice) * BigInt(quote.windowSecs.toNumber())
+        );
+        // The accumulator itself is untouched by the view
+        assert.equal((await snapshot()).priceCumulative, start.priceCumulative);
+    });
+
+    it("Rejects an empty or future window", async () => {
+        const { priceCumulative } = await snapshot();
+        try {
+            await getTwap(priceCumulative, Math.floor(Date.now() / 1000) + 3_600);
+            assert.fail("Expected InvalidTwapWindow error");
+        } catch (err: any) {
+            assert.include(err.message, "InvalidTwapWindow");
+        }
+    });
+});